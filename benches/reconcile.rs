@@ -0,0 +1,65 @@
+//! # Reconcile benchmarks
+//!
+//! This benchmark suite measures the throughput of the reconciliation loop, by
+//! replaying synthetic events of custom resources against the in-memory
+//! kubernetes api server and clever cloud api. It only runs with the `bench`
+//! feature, see `make bench`.
+
+use std::{sync::Arc, time::Duration};
+
+use clever_operator::svc::{
+    bench::{CountingAllocator, Workload},
+    cfg::Configuration,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tokio::runtime::Runtime;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// number of synthetic custom resources
+const RESOURCES: usize = 10;
+
+/// numbers of replayed events, the first event of each custom resource
+/// provisions it, the following ones find it up to date
+const EVENTS: &[usize] = &[10, 100];
+
+fn reconcile(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("tokio runtime to be created");
+    let config = Arc::new(Configuration::try_default().expect("configuration to be loaded"));
+
+    let mut group = c.benchmark_group("reconcile");
+    for events in EVENTS {
+        group.throughput(Throughput::Elements(*events as u64));
+        group.bench_with_input(BenchmarkId::new("addon", events), events, |b, events| {
+            b.to_async(&runtime).iter_custom(|iters| {
+                let config = config.to_owned();
+                async move {
+                    let mut elapsed = Duration::ZERO;
+                    for _ in 0..iters {
+                        let workload =
+                            Workload::try_new(config.to_owned()).expect("workload to be created");
+
+                        workload
+                            .seed(RESOURCES)
+                            .await
+                            .expect("custom resources to be seeded");
+
+                        elapsed += workload
+                            .replay(RESOURCES, *events)
+                            .await
+                            .expect("events to be replayed")
+                            .elapsed;
+                    }
+
+                    elapsed
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, reconcile);
+criterion_main!(benches);
@@ -0,0 +1,85 @@
+//! # Bench module
+//!
+//! This module provides the hidden bench command line interface function
+//! implementation which replays synthetic events of custom resources through
+//! the reconciliation loop, against in-memory apis, and reports its
+//! throughput.
+
+use std::{path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::{
+    cmd::Executor,
+    svc::{
+        bench::{self, Workload},
+        cfg::Configuration,
+    },
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const DEFAULT_RESOURCES: &str = "100";
+pub const DEFAULT_EVENTS: &str = "1000";
+
+// -----------------------------------------------------------------------------
+// BenchError enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum BenchError {
+    #[error("failed to run synthetic workload, {0}")]
+    Workload(bench::Error),
+}
+
+// -----------------------------------------------------------------------------
+// Bench structure
+
+#[derive(Args, Clone, Debug)]
+pub struct Bench {
+    /// Number of synthetic custom resources
+    #[clap(long = "resources", default_value = DEFAULT_RESOURCES)]
+    pub resources: usize,
+    /// Number of events replayed over the custom resources, the first event
+    /// of each custom resource provisions it
+    #[clap(short = 'n', long = "events", default_value = DEFAULT_EVENTS)]
+    pub events: usize,
+}
+
+#[async_trait]
+impl Executor for Bench {
+    type Error = BenchError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+    async fn execute(
+        &self,
+        _kubeconfig: Option<PathBuf>,
+        config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
+        let workload = Workload::try_new(config).map_err(BenchError::Workload)?;
+        workload
+            .seed(self.resources)
+            .await
+            .map_err(BenchError::Workload)?;
+
+        let report = workload
+            .replay(self.resources, self.events)
+            .await
+            .map_err(BenchError::Workload)?;
+
+        println!("resources\t{}", self.resources);
+        println!("events\t{}", report.events);
+        println!("elapsed\t{:?}", report.elapsed);
+        println!("reconciles/sec\t{:.2}", report.reconciles_per_second());
+        match (report.allocations, report.allocations_per_reconcile()) {
+            (Some(allocations), Some(per_reconcile)) => {
+                println!("allocations\t{}", allocations);
+                println!("allocations/reconcile\t{:.2}", per_reconcile);
+            }
+            _ => println!("allocations\tnot counted, build with the 'bench' feature"),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+//! # Completion module
+//!
+//! This module provides the completion command line interface function
+//! implementation which generates the completion script of the given shell
+//! from the definition of the command line interface.
+
+use std::{io, path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+use crate::{
+    cmd::{Args, Executor},
+    svc::cfg::Configuration,
+};
+
+// -----------------------------------------------------------------------------
+// CompletionError enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum CompletionError {}
+
+// -----------------------------------------------------------------------------
+// Completion structure
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct Completion {
+    /// Shell to generate the completion script for
+    #[clap(value_enum)]
+    pub shell: Shell,
+}
+
+#[async_trait]
+impl Executor for Completion {
+    type Error = CompletionError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(_config)))]
+    async fn execute(
+        &self,
+        _kubeconfig: Option<PathBuf>,
+        _config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
+        clap_complete::generate(
+            self.shell,
+            &mut Args::command(),
+            env!("CARGO_PKG_NAME"),
+            &mut io::stdout(),
+        );
+
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@
 //! This module provides custom resource module command line interface function
 //! implementation
 
-use std::{error::Error, str::FromStr, sync::Arc};
+use std::{error::Error, path::PathBuf, str::FromStr, sync::Arc};
 
 use async_trait::async_trait;
 use clap::Subcommand;
@@ -14,9 +14,12 @@ use crate::{
     svc::{
         cfg::Configuration,
         crd::{
-            config_provider::ConfigProvider, elasticsearch::ElasticSearch, mongodb::MongoDb,
-            mysql::MySql, postgresql::PostgreSql, pulsar::Pulsar, redis::Redis,
+            addon::Addon, backup::Backup, cellar_bucket::CellarBucket,
+            config_provider::ConfigProvider, elasticsearch::ElasticSearch, fs_bucket::FSBucket,
+            gravitee::Gravitee, jenkins::Jenkins, mailpace::Mailpace, mongodb::MongoDb,
+            mysql::MySql, postgresql::PostgreSql, pulsar::Pulsar, redis::Redis, runtime::Runtime,
         },
+        k8s::conversion,
     },
 };
 
@@ -32,6 +35,14 @@ pub enum CustomResource {
     Pulsar,
     ConfigProvider,
     ElasticSearch,
+    Runtime,
+    CellarBucket,
+    Backup,
+    Addon,
+    FsBucket,
+    Mailpace,
+    Jenkins,
+    Gravitee,
 }
 
 impl FromStr for CustomResource {
@@ -47,7 +58,15 @@ impl FromStr for CustomResource {
             "pulsar" => Ok(Self::Pulsar),
             "config-provider" => Ok(Self::ConfigProvider),
             "elasticsearch" => Ok(Self::ElasticSearch),
-            _ => Err(format!("failed to parse '{}', available options are 'elasticsearch', 'config-provider', 'pulsar', 'postgresql', 'redis', 'mysql' or 'mongodb", s).into()),
+            "runtime" => Ok(Self::Runtime),
+            "cellar-bucket" => Ok(Self::CellarBucket),
+            "backup" => Ok(Self::Backup),
+            "addon" => Ok(Self::Addon),
+            "fs-bucket" => Ok(Self::FsBucket),
+            "mailpace" => Ok(Self::Mailpace),
+            "jenkins" => Ok(Self::Jenkins),
+            "gravitee" => Ok(Self::Gravitee),
+            _ => Err(format!("failed to parse '{}', available options are 'gravitee', 'jenkins', 'mailpace', 'fs-bucket', 'addon', 'backup', 'cellar-bucket', 'runtime', 'elasticsearch', 'config-provider', 'pulsar', 'postgresql', 'redis', 'mysql' or 'mongodb", s).into()),
         }
     }
 }
@@ -78,7 +97,11 @@ impl Executor for CustomResourceDefinition {
     type Error = CustomResourceDefinitionError;
 
     #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error> {
+    async fn execute(
+        &self,
+        _kubeconfig: Option<PathBuf>,
+        config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
         match self {
             Self::View { custom_resource } => view(config, custom_resource).await,
         }
@@ -88,43 +111,106 @@ impl Executor for CustomResourceDefinition {
 // -----------------------------------------------------------------------------
 // view function
 
-#[cfg_attr(feature = "trace", tracing::instrument(skip(_config)))]
+#[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
 pub async fn view(
-    _config: Arc<Configuration>,
+    config: Arc<Configuration>,
     custom_resource: &Option<CustomResource>,
 ) -> Result<(), CustomResourceDefinitionError> {
+    let webhook = &config.operator.webhook;
     let crds = if let Some(cr) = custom_resource {
         vec![match cr {
-            CustomResource::PostgreSql => serde_yaml::to_string(&PostgreSql::crd())
+            CustomResource::PostgreSql => {
+                serde_yaml::to_string(&conversion::versions(PostgreSql::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::Redis => {
+                serde_yaml::to_string(&conversion::versions(Redis::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::MySql => {
+                serde_yaml::to_string(&conversion::versions(MySql::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::MongoDb => {
+                serde_yaml::to_string(&conversion::versions(MongoDb::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::Pulsar => {
+                serde_yaml::to_string(&conversion::versions(Pulsar::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::ConfigProvider => {
+                serde_yaml::to_string(&conversion::versions(ConfigProvider::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::ElasticSearch => {
+                serde_yaml::to_string(&conversion::versions(ElasticSearch::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::Runtime => {
+                serde_yaml::to_string(&conversion::versions(Runtime::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::CellarBucket => {
+                serde_yaml::to_string(&conversion::versions(CellarBucket::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::Backup => {
+                serde_yaml::to_string(&conversion::versions(Backup::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::Addon => {
+                serde_yaml::to_string(&conversion::versions(Addon::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::FsBucket => {
+                serde_yaml::to_string(&conversion::versions(FSBucket::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::Mailpace => {
+                serde_yaml::to_string(&conversion::versions(Mailpace::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::Jenkins => {
+                serde_yaml::to_string(&conversion::versions(Jenkins::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+            CustomResource::Gravitee => {
+                serde_yaml::to_string(&conversion::versions(Gravitee::crd(), webhook))
+                    .map_err(CustomResourceDefinitionError::Serialize)?
+            }
+        }]
+    } else {
+        vec![
+            serde_yaml::to_string(&conversion::versions(PostgreSql::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-            CustomResource::Redis => serde_yaml::to_string(&Redis::crd())
+            serde_yaml::to_string(&conversion::versions(Redis::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-            CustomResource::MySql => serde_yaml::to_string(&MySql::crd())
+            serde_yaml::to_string(&conversion::versions(MySql::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-            CustomResource::MongoDb => serde_yaml::to_string(&MongoDb::crd())
+            serde_yaml::to_string(&conversion::versions(MongoDb::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-            CustomResource::Pulsar => serde_yaml::to_string(&Pulsar::crd())
+            serde_yaml::to_string(&conversion::versions(Pulsar::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-            CustomResource::ConfigProvider => serde_yaml::to_string(&ConfigProvider::crd())
+            serde_yaml::to_string(&conversion::versions(ConfigProvider::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-            CustomResource::ElasticSearch => serde_yaml::to_string(&ElasticSearch::crd())
+            serde_yaml::to_string(&conversion::versions(ElasticSearch::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-        }]
-    } else {
-        vec![
-            serde_yaml::to_string(&PostgreSql::crd())
+            serde_yaml::to_string(&conversion::versions(Runtime::crd(), webhook))
+                .map_err(CustomResourceDefinitionError::Serialize)?,
+            serde_yaml::to_string(&conversion::versions(CellarBucket::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-            serde_yaml::to_string(&Redis::crd())
+            serde_yaml::to_string(&conversion::versions(Backup::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-            serde_yaml::to_string(&MySql::crd())
+            serde_yaml::to_string(&conversion::versions(Addon::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-            serde_yaml::to_string(&MongoDb::crd())
+            serde_yaml::to_string(&conversion::versions(FSBucket::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-            serde_yaml::to_string(&Pulsar::crd())
+            serde_yaml::to_string(&conversion::versions(Mailpace::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-            serde_yaml::to_string(&ConfigProvider::crd())
+            serde_yaml::to_string(&conversion::versions(Jenkins::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
-            serde_yaml::to_string(&ElasticSearch::crd())
+            serde_yaml::to_string(&conversion::versions(Gravitee::crd(), webhook))
                 .map_err(CustomResourceDefinitionError::Serialize)?,
         ]
     };
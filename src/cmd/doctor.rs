@@ -0,0 +1,259 @@
+//! # Doctor module
+//!
+//! This module provides the doctor command line interface function
+//! implementation which checks the credentials of the Clever Cloud api, the
+//! custom resource definitions installed in the cluster and the permissions of
+//! the operator, then prints a report. It fails if any check fails, so it
+//! could gate a deployment in a continuous integration pipeline.
+
+use std::{path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use clap::Args;
+use clevercloud_sdk::v2::myself;
+use k8s_openapi::{
+    api::{
+        authorization::v1::{
+            ResourceAttributes, SelfSubjectAccessReview, SelfSubjectAccessReviewSpec,
+        },
+        rbac::v1::PolicyRule,
+    },
+    apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
+};
+use kube::api::{Api, PostParams};
+
+use crate::{
+    cmd::Executor,
+    svc::{
+        cfg::Configuration,
+        clevercloud::{self, client::observe},
+        k8s::{client, definition, rbac},
+        reload,
+    },
+};
+
+// -----------------------------------------------------------------------------
+// DoctorError enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum DoctorError {
+    #[error("failed to create kubernetes client, {0}")]
+    Client(client::Error),
+    #[error("{0} of {1} checks failed")]
+    Failed(usize, usize),
+}
+
+// -----------------------------------------------------------------------------
+// Doctor structure
+
+#[derive(Args, Clone, Debug)]
+pub struct Doctor {
+    /// Namespace in which permissions are checked, defaults to the watched
+    /// namespaces of the configuration or to the whole cluster. Permissions
+    /// are the ones of the identity of the kubernetes configuration
+    #[clap(short = 'n', long = "namespace")]
+    pub namespaces: Vec<String>,
+}
+
+#[async_trait]
+impl Executor for Doctor {
+    type Error = DoctorError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+    async fn execute(
+        &self,
+        kubeconfig: Option<PathBuf>,
+        config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
+        let kube = client::try_new(kubeconfig, &config.proxy)
+            .await
+            .map_err(DoctorError::Client)?;
+
+        let mut checks = vec![];
+
+        // ---------------------------------------------------------------------
+        // Step 1: check connectivity to the kubernetes api server
+        let reachable = kube.apiserver_version().await;
+        checks.push((
+            "kubernetes/connectivity".to_string(),
+            match &reachable {
+                Ok(info) => Ok(format!("reachable, version {}", info.git_version)),
+                Err(err) => Err(err.to_string()),
+            },
+        ));
+
+        // ---------------------------------------------------------------------
+        // Step 2: check connectivity and credentials of the clever cloud api
+        checks.push((
+            "clever-cloud/credentials".to_string(),
+            credentials(kube.to_owned(), &config).await,
+        ));
+
+        if reachable.is_ok() {
+            // -----------------------------------------------------------------
+            // Step 3: check custom resource definitions installed in the cluster
+            let api: Api<CustomResourceDefinition> = Api::all(kube.to_owned());
+            for (expected, _) in rbac::reconcilers() {
+                let name = expected.metadata.name.to_owned().unwrap_or_default();
+                let check = match api.get_opt(&name).await {
+                    Ok(Some(installed)) => skew(&expected, &installed),
+                    Ok(None) if config.operator.custom_resource_definitions.manage => {
+                        Ok("not installed, it will be applied by the operator at startup".into())
+                    }
+                    Ok(None) => Err("not installed, apply the custom resource definition".into()),
+                    Err(err) => Err(err.to_string()),
+                };
+
+                checks.push((format!("crd/{}", name), check));
+            }
+
+            // -----------------------------------------------------------------
+            // Step 4: check permissions of the operator
+            let namespaces = if self.namespaces.is_empty() {
+                config.watch.namespaces.to_owned()
+            } else {
+                self.namespaces.to_owned()
+            };
+
+            let namespaces: Vec<Option<String>> = if namespaces.is_empty() {
+                vec![None]
+            } else {
+                namespaces.into_iter().map(Some).collect()
+            };
+
+            let rules = rbac::rules(&rbac::reconcilers());
+            for namespace in &namespaces {
+                checks.extend(permissions(kube.to_owned(), namespace.as_deref(), &rules).await);
+            }
+
+            if config.operator.leader_election.enabled {
+                let namespace = config
+                    .operator
+                    .leader_election
+                    .namespace
+                    .to_owned()
+                    .unwrap_or_else(|| kube.default_namespace().to_string());
+
+                let rules = rbac::to_rules(rbac::LEADER_ELECTION);
+                checks.extend(permissions(kube.to_owned(), Some(&namespace), &rules).await);
+            }
+        }
+
+        // ---------------------------------------------------------------------
+        // Step 5: print the report
+        let failed = checks.iter().filter(|(_, check)| check.is_err()).count();
+        for (name, check) in &checks {
+            match check {
+                Ok(message) => println!("[ OK ] {}: {}", name, message),
+                Err(message) => println!("[FAIL] {}: {}", name, message),
+            }
+        }
+
+        if failed > 0 {
+            return Err(DoctorError::Failed(failed, checks.len()));
+        }
+
+        println!("All {} checks passed", checks.len());
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(kube, config)))]
+/// returns if the credentials of the configuration, or of the credentials
+/// secret, if any, are accepted by the clever cloud api
+pub async fn credentials(kube: kube::Client, config: &Configuration) -> Result<String, String> {
+    let api = reload::credentials(kube, &config.operator.credentials_secret, &config.api)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let client =
+        clevercloud::client::try_new(&api, &config.proxy).map_err(|err| err.to_string())?;
+
+    observe("self", "get", myself::get(&client))
+        .await
+        .map(|_| format!("authenticated on '{}'", api.endpoint))
+        .map_err(|err| err.to_string())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the installed custom resource definition serves the versions and
+/// the fields known by the operator
+pub fn skew(
+    expected: &CustomResourceDefinition,
+    installed: &CustomResourceDefinition,
+) -> Result<String, String> {
+    definition::skew(expected, installed)
+        .map(|served| format!("installed, serves version(s) '{}'", served.join("', '")))
+        .map_err(|err| format!("{}, upgrade the custom resource definition", err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(kube)))]
+/// returns, for each resource of the rules, if the verbs of the rules are
+/// allowed in the given namespace, or in all namespaces
+pub async fn permissions(
+    kube: kube::Client,
+    namespace: Option<&str>,
+    rules: &[PolicyRule],
+) -> Vec<(String, Result<String, String>)> {
+    let api: Api<SelfSubjectAccessReview> = Api::all(kube);
+    let mut checks = vec![];
+
+    for rule in rules {
+        for group in rule.api_groups.iter().flatten() {
+            for resource in rule.resources.iter().flatten() {
+                let (name, subresource) = match resource.split_once('/') {
+                    Some((name, subresource)) => (name, Some(subresource.to_string())),
+                    None => (resource.as_str(), None),
+                };
+
+                let mut denied = vec![];
+                for verb in &rule.verbs {
+                    let review = SelfSubjectAccessReview {
+                        spec: SelfSubjectAccessReviewSpec {
+                            resource_attributes: Some(ResourceAttributes {
+                                group: Some(group.to_owned()),
+                                resource: Some(name.to_string()),
+                                subresource: subresource.to_owned(),
+                                verb: Some(verb.to_owned()),
+                                namespace: namespace.map(String::from),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    };
+
+                    match api.create(&PostParams::default(), &review).await {
+                        Ok(review)
+                            if review.status.as_ref().map(|status| status.allowed)
+                                == Some(true) => {}
+                        Ok(_) => denied.push(verb.to_owned()),
+                        Err(err) => denied.push(format!("{} ({})", verb, err)),
+                    }
+                }
+
+                let resource = if group.is_empty() {
+                    resource.to_owned()
+                } else {
+                    format!("{}.{}", resource, group)
+                };
+
+                let check = if denied.is_empty() {
+                    Ok(format!("allowed to '{}'", rule.verbs.join("', '")))
+                } else {
+                    Err(format!("not allowed to '{}'", denied.join("', '")))
+                };
+
+                checks.push((
+                    format!("rbac/{}/{}", namespace.unwrap_or("<all>"), resource),
+                    check,
+                ));
+            }
+        }
+    }
+
+    checks
+}
@@ -0,0 +1,675 @@
+//! # Force unlock module
+//!
+//! This module provides the force-unlock command line interface function
+//! implementation which removes the operator's finalizers from custom resources
+//! that could not be deleted anymore as their credentials are permanently lost.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Display, Formatter},
+    future::Future,
+    io,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use clap::Args;
+use clevercloud_sdk::v2::addon;
+use k8s_openapi::{api::core::v1::Secret, NamespaceResourceScope};
+use kube::{api::ListParams, Api, CustomResourceExt, Resource, ResourceExt};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::{info, warn};
+
+use crate::{
+    cmd::{crd::CustomResource, Executor},
+    svc::{
+        cfg::Configuration,
+        clevercloud::{self, ext::AddonExt},
+        crd::CredentialsSecretReference,
+        crd::{
+            addon::{self as crd_addon, Addon},
+            backup::Backup,
+            cellar_bucket::CellarBucket,
+            config_provider::{self, ConfigProvider},
+            elasticsearch::{self, ElasticSearch},
+            fs_bucket::{self, FSBucket},
+            gravitee::{self, Gravitee},
+            jenkins::{self, Jenkins},
+            mailpace::{self, Mailpace},
+            mongodb::{self, MongoDb},
+            mysql::{self, MySql},
+            postgresql::{self, PostgreSql},
+            pulsar::{self, Pulsar},
+            redis::{self, Redis},
+            runtime::Runtime,
+        },
+        k8s::{client, finalizer, recorder, resource, secret},
+    },
+};
+
+// -----------------------------------------------------------------------------
+// Action enumeration
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub enum Action {
+    ForceUnlocked,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::ForceUnlocked => write!(f, "ForceUnlocked"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// ForceUnlockError enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum ForceUnlockError {
+    #[error("failed to create kubernetes client, {0}")]
+    Client(client::Error),
+    #[error("failed to execute request on kubernetes api, {0}")]
+    KubeClient(kube::Error),
+    #[error("failed to create clevercloud client, {0}")]
+    CleverClient(clevercloud::client::Error),
+    #[error("failed to validate credentials of organisation '{0}', {1}")]
+    Validate(String, addon::Error),
+    #[error("failed to read confirmation from standard input, {0}")]
+    Confirmation(io::Error),
+    #[error("could not find custom resource '{0}/{1}'")]
+    NotFound(String, String),
+    #[error("could not find credentials secret '{0}/{1}'")]
+    CredentialsSecretNotFound(String, String),
+    #[error("force unlock is not supported for custom resources of kind '{0}'")]
+    Unsupported(String),
+    #[error("refuse to unlock '{0}/{1}', credentials of organisation '{2}' are still valid, delete the custom resource normally")]
+    CredentialsStillValid(String, String, String),
+}
+
+impl From<kube::Error> for ForceUnlockError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: kube::Error) -> Self {
+        Self::KubeClient(err)
+    }
+}
+
+impl From<clevercloud::client::Error> for ForceUnlockError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: clevercloud::client::Error) -> Self {
+        Self::CleverClient(err)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// ForceUnlock structure
+
+#[derive(Args, Clone, Debug)]
+pub struct ForceUnlock {
+    /// Kind of the custom resources to unlock
+    #[clap(long = "kind")]
+    pub kind: CustomResource,
+    /// Namespace of the custom resources, all namespaces are looked up if
+    /// omitted in combination with '--all-matching'
+    #[clap(
+        short = 'n',
+        long = "namespace",
+        required_unless_present = "all_matching"
+    )]
+    pub namespace: Option<String>,
+    /// Name of the custom resource to unlock
+    #[clap(
+        long = "name",
+        required_unless_present = "all_matching",
+        conflicts_with = "all_matching",
+        requires = "namespace"
+    )]
+    pub name: Option<String>,
+    /// Unlock all custom resources of the kind being deleted and belonging to
+    /// the organisation
+    #[clap(long = "all-matching", requires = "organisation")]
+    pub all_matching: bool,
+    /// Organisation of the custom resources to unlock
+    #[clap(long = "organisation")]
+    pub organisation: Option<String>,
+    /// Do not ask for confirmation
+    #[clap(short = 'y', long = "yes")]
+    pub yes: bool,
+}
+
+#[async_trait]
+impl Executor for ForceUnlock {
+    type Error = ForceUnlockError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+    async fn execute(
+        &self,
+        kubeconfig: Option<PathBuf>,
+        config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
+        let kube = client::try_new(kubeconfig, &config.proxy)
+            .await
+            .map_err(ForceUnlockError::Client)?;
+
+        match self.kind {
+            CustomResource::PostgreSql => {
+                unlock::<PostgreSql>(kube, config, self, postgresql::ADDON_FINALIZER).await
+            }
+            CustomResource::Redis => {
+                unlock::<Redis>(kube, config, self, redis::ADDON_FINALIZER).await
+            }
+            CustomResource::MySql => {
+                unlock::<MySql>(kube, config, self, mysql::ADDON_FINALIZER).await
+            }
+            CustomResource::MongoDb => {
+                unlock::<MongoDb>(kube, config, self, mongodb::ADDON_FINALIZER).await
+            }
+            CustomResource::Pulsar => {
+                unlock::<Pulsar>(kube, config, self, pulsar::ADDON_FINALIZER).await
+            }
+            CustomResource::ConfigProvider => {
+                unlock::<ConfigProvider>(kube, config, self, config_provider::ADDON_FINALIZER).await
+            }
+            CustomResource::ElasticSearch => {
+                unlock::<ElasticSearch>(kube, config, self, elasticsearch::ADDON_FINALIZER).await
+            }
+            CustomResource::Runtime => Err(ForceUnlockError::Unsupported(
+                Runtime::kind(&()).to_string(),
+            )),
+            CustomResource::CellarBucket => Err(ForceUnlockError::Unsupported(
+                CellarBucket::kind(&()).to_string(),
+            )),
+            CustomResource::Backup => {
+                Err(ForceUnlockError::Unsupported(Backup::kind(&()).to_string()))
+            }
+            CustomResource::Addon => {
+                unlock::<Addon>(kube, config, self, crd_addon::ADDON_FINALIZER).await
+            }
+            CustomResource::FsBucket => {
+                unlock::<FSBucket>(kube, config, self, fs_bucket::ADDON_FINALIZER).await
+            }
+            CustomResource::Mailpace => {
+                unlock::<Mailpace>(kube, config, self, mailpace::ADDON_FINALIZER).await
+            }
+            CustomResource::Jenkins => {
+                unlock::<Jenkins>(kube, config, self, jenkins::ADDON_FINALIZER).await
+            }
+            CustomResource::Gravitee => {
+                unlock::<Gravitee>(kube, config, self, gravitee::ADDON_FINALIZER).await
+            }
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(kube, config)))]
+/// remove the given finalizer from targeted custom resources, once we have
+/// checked that credentials of their organisation are not valid anymore
+pub async fn unlock<T>(
+    kube: kube::Client,
+    config: Arc<Configuration>,
+    args: &ForceUnlock,
+    finalizer: &str,
+) -> Result<(), ForceUnlockError>
+where
+    T: Resource<Scope = NamespaceResourceScope>
+        + ResourceExt
+        + CustomResourceExt
+        + AddonExt
+        + DeserializeOwned
+        + Serialize
+        + Clone
+        + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    // -------------------------------------------------------------------------
+    // Step 1: retrieve custom resources to unlock
+
+    let objs: Vec<T> = match (&args.namespace, &args.name) {
+        (Some(namespace), Some(name)) => {
+            match resource::get::<T>(kube.to_owned(), namespace, name).await? {
+                Some(obj) => vec![obj],
+                None => {
+                    return Err(ForceUnlockError::NotFound(
+                        namespace.to_owned(),
+                        name.to_owned(),
+                    ));
+                }
+            }
+        }
+        (namespace, _) => {
+            let api: Api<T> = match namespace {
+                Some(namespace) => Api::namespaced(kube.to_owned(), namespace),
+                None => Api::all(kube.to_owned()),
+            };
+
+            api.list(&ListParams::default())
+                .await?
+                .items
+                .into_iter()
+                .filter(|obj| Some(obj.organisation()) == args.organisation)
+                .collect()
+        }
+    };
+
+    let objs: Vec<T> = objs
+        .into_iter()
+        .filter(|obj| {
+            let (namespace, name) = resource::namespaced_name(obj);
+            if !resource::deleted(obj) {
+                warn!(
+                    namespace = &namespace,
+                    name = &name,
+                    "Skip custom resource, it is not marked for deletion",
+                );
+
+                return false;
+            }
+
+            if !finalizer::contains(obj, finalizer) {
+                warn!(
+                    namespace = &namespace,
+                    name = &name,
+                    finalizer = finalizer,
+                    "Skip custom resource, it does not have the operator's finalizer",
+                );
+
+                return false;
+            }
+
+            true
+        })
+        .collect();
+
+    if objs.is_empty() {
+        println!("There is no custom resource to unlock");
+        return Ok(());
+    }
+
+    // -------------------------------------------------------------------------
+    // Step 2: refuse to unlock custom resources whose credentials still work
+
+    refuse(&objs, |namespace, reference, organisation| {
+        let (kube, config) = (kube.to_owned(), config.to_owned());
+
+        async move {
+            let apis = try_client(kube, config, &namespace, reference.as_ref()).await?;
+
+            clevercloud::client::validate(&apis, &organisation)
+                .await
+                .map_err(|err| ForceUnlockError::Validate(organisation, err))
+        }
+    })
+    .await?;
+
+    // -------------------------------------------------------------------------
+    // Step 3: ask for confirmation
+
+    println!("The following custom resources will be unlocked:");
+    for obj in &objs {
+        let (namespace, name) = resource::namespaced_name(obj);
+        println!(
+            "- {}/{} (organisation: {}, addon: {})",
+            namespace,
+            name,
+            obj.organisation(),
+            obj.id().unwrap_or_else(|| "<none>".to_string())
+        );
+    }
+
+    if !args.yes && !confirm().await? {
+        println!("Aborted, no custom resource has been unlocked");
+        return Ok(());
+    }
+
+    // -------------------------------------------------------------------------
+    // Step 4: remove finalizers and report addons that may be orphaned
+
+    let orphans = release(kube, objs, finalizer).await?;
+    if !orphans.is_empty() {
+        println!("The following addons may be orphaned and should be handled manually:");
+        for (organisation, id) in orphans {
+            println!("- {} (organisation: {})", id, organisation);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(objs, validate)))]
+/// returns an error, if credentials of the organisation of one of the custom
+/// resources are still valid. Credentials are validated once per credentials
+/// secret and organisation using the given function
+pub async fn refuse<T, F, Fut>(objs: &[T], mut validate: F) -> Result<(), ForceUnlockError>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + AddonExt + Debug,
+    F: FnMut(String, Option<CredentialsSecretReference>, String) -> Fut,
+    Fut: Future<Output = Result<bool, ForceUnlockError>>,
+{
+    let mut validated: BTreeMap<(String, String, String), bool> = BTreeMap::new();
+    for obj in objs {
+        let (namespace, name) = resource::namespaced_name(obj);
+        let organisation = obj.organisation();
+        let reference = obj.credentials_secret_ref();
+        let (secret_namespace, secret_name) = secret::credentials_reference(&namespace, reference);
+        let key = (secret_namespace, secret_name, organisation.to_owned());
+
+        let valid = match validated.get(&key) {
+            Some(valid) => *valid,
+            None => {
+                let valid = validate(
+                    namespace.to_owned(),
+                    reference.cloned(),
+                    organisation.to_owned(),
+                )
+                .await?;
+
+                validated.insert(key, valid);
+                valid
+            }
+        };
+
+        if valid {
+            return Err(ForceUnlockError::CredentialsStillValid(
+                namespace,
+                name,
+                organisation,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(kube, objs)))]
+/// remove the given finalizer from the custom resources and returns the
+/// organisation and identifier of addons which may be orphaned
+pub async fn release<T>(
+    kube: kube::Client,
+    objs: Vec<T>,
+    finalizer: &str,
+) -> Result<Vec<(String, String)>, ForceUnlockError>
+where
+    T: Resource<Scope = NamespaceResourceScope>
+        + ResourceExt
+        + CustomResourceExt
+        + AddonExt
+        + DeserializeOwned
+        + Serialize
+        + Clone
+        + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let mut orphans = vec![];
+    for obj in objs {
+        let (namespace, name) = resource::namespaced_name(&obj);
+
+        let action = &Action::ForceUnlocked;
+        let message = &format!(
+            "Force unlock custom resource, addon '{}' on organisation '{}' may be orphaned",
+            obj.id().unwrap_or_else(|| "<none>".to_string()),
+            obj.organisation()
+        );
+
+        if let Err(err) = recorder::warning(kube.to_owned(), &obj, action, message).await {
+            warn!(
+                namespace = &namespace,
+                name = &name,
+                error = err.to_string(),
+                "Could not record event on custom resource, record it on the namespace",
+            );
+
+            let kind = T::kind(&Default::default()).to_string();
+            let message = &format!("{} '{}': {}", kind, name, message);
+            let level = &recorder::Level::Warning;
+            if let Err(err) =
+                recorder::namespace(kube.to_owned(), &namespace, level, action, message).await
+            {
+                warn!(
+                    namespace = &namespace,
+                    name = &name,
+                    error = err.to_string(),
+                    "Could not record event on namespace",
+                );
+            }
+        }
+
+        info!(
+            namespace = &namespace,
+            name = &name,
+            finalizer = finalizer,
+            "Remove finalizer on custom resource",
+        );
+
+        let patch = finalizer::remove_patch(&obj, finalizer);
+        resource::patch(kube.to_owned(), &obj, patch).await?;
+
+        if let Some(id) = obj.id() {
+            orphans.push((obj.organisation(), id));
+        }
+    }
+
+    Ok(orphans)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(kube, config)))]
+/// returns the clever cloud client used by the operator to reconcile custom
+/// resources of the given namespace, which reference the given credentials
+/// secret, if any
+pub async fn try_client(
+    kube: kube::Client,
+    config: Arc<Configuration>,
+    namespace: &str,
+    reference: Option<&CredentialsSecretReference>,
+) -> Result<clevercloud::client::Client, ForceUnlockError> {
+    let (secret_namespace, secret_name) = secret::credentials_reference(namespace, reference);
+    let secret: Option<Secret> = resource::get(kube, &secret_namespace, &secret_name).await?;
+    if secret.is_none() && reference.is_some() {
+        return Err(ForceUnlockError::CredentialsSecretNotFound(
+            secret_namespace,
+            secret_name,
+        ));
+    }
+
+    Ok(match secret {
+        Some(secret) => clevercloud::client::try_from(secret).await?,
+        None => clevercloud::client::try_new(&config.api, &config.proxy)?,
+    })
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// ask the user to confirm the operation on standard input
+pub async fn confirm() -> Result<bool, ForceUnlockError> {
+    println!("Do you want to continue? Type 'yes' to confirm:");
+
+    let mut line = String::new();
+    BufReader::new(tokio::io::stdin())
+        .read_line(&mut line)
+        .await
+        .map_err(ForceUnlockError::Confirmation)?;
+
+    Ok(line.trim() == "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::{json, Value};
+    use wiremock::{
+        http::Method,
+        matchers::{body_partial_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::{refuse, release, ForceUnlockError};
+    use crate::svc::crd::config_provider::{
+        tests::provider, ConfigProvider, Status, ADDON_FINALIZER,
+    };
+
+    const FOREIGN_FINALIZER: &str = "example.com/foreign";
+
+    /// returns a config provider being deleted, which is held by the finalizer
+    /// of the operator behind a foreign one
+    fn deleted(name: &str, organisation: &str) -> ConfigProvider {
+        let mut obj = provider(name, organisation);
+        obj.metadata.deletion_timestamp =
+            serde_json::from_value(json!("2023-01-01T00:00:00Z")).expect("deletion timestamp");
+        obj.metadata.finalizers = Some(vec![
+            FOREIGN_FINALIZER.to_string(),
+            ADDON_FINALIZER.to_string(),
+        ]);
+        obj.status = Some(Status {
+            addon: Some(format!("addon_{}", name)),
+            ..Default::default()
+        });
+
+        obj
+    }
+
+    fn objs(names: &[&str], organisation: &str) -> Vec<ConfigProvider> {
+        names
+            .iter()
+            .map(|name| deleted(name, organisation))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn refuse_to_unlock_while_credentials_are_valid() {
+        let result = refuse(&objs(&["app"], "orga_test"), |_, _, _| async { Ok(true) }).await;
+
+        match result {
+            Err(ForceUnlockError::CredentialsStillValid(namespace, name, organisation)) => {
+                assert_eq!("default", namespace);
+                assert_eq!("app", name);
+                assert_eq!("orga_test", organisation);
+            }
+            other => panic!("expected a refusal, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn validate_credentials_once_per_organisation() {
+        let calls = AtomicUsize::new(0);
+        let result = refuse(&objs(&["a", "b"], "orga_test"), |namespace, _, _| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                assert_eq!("default", namespace);
+                Ok(false)
+            }
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn release_removes_only_the_operator_finalizer() {
+        let server = MockServer::start().await;
+        Mock::given(method("PATCH"))
+            .and(path(
+                "/apis/api.clever-cloud.com/v1/namespaces/default/configproviders/app",
+            ))
+            .and(header("content-type", "application/json-patch+json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(deleted("app", "orga_test")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = kube::Config::new(server.uri().parse().expect("uri"));
+        let kube = kube::Client::try_from(config).expect("kubernetes client");
+
+        let orphans = release(kube, objs(&["app"], "orga_test"), ADDON_FINALIZER)
+            .await
+            .expect("finalizer to be removed");
+
+        assert_eq!(
+            vec![("orga_test".to_string(), "addon_app".to_string())],
+            orphans
+        );
+
+        let requests = server.received_requests().await.expect("requests");
+        let patch = requests
+            .iter()
+            .find(|request| request.method == Method::Patch)
+            .expect("patch request");
+
+        assert_eq!(
+            json!([
+                { "op": "test", "path": "/metadata/finalizers/1", "value": ADDON_FINALIZER },
+                { "op": "remove", "path": "/metadata/finalizers/1" },
+            ]),
+            serde_json::from_slice::<Value>(&patch.body).expect("json patch")
+        );
+    }
+
+    #[tokio::test]
+    async fn release_records_event_on_namespace_if_object_refuses_it() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/namespaces/default/events"))
+            .and(body_partial_json(
+                json!({ "involvedObject": { "kind": "ConfigProvider" } }),
+            ))
+            .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+                "kind": "Status",
+                "apiVersion": "v1",
+                "status": "Failure",
+                "message": "events is forbidden",
+                "reason": "Forbidden",
+                "code": 403,
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/v1/namespaces/default/events"))
+            .and(body_partial_json(json!({
+                "type": "Warning",
+                "reason": "ForceUnlocked",
+                "involvedObject": { "apiVersion": "v1", "kind": "Namespace", "name": "default" },
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "metadata": { "name": "default.0000", "namespace": "default" },
+                "involvedObject": { "kind": "Namespace", "name": "default" },
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("PATCH"))
+            .and(path(
+                "/apis/api.clever-cloud.com/v1/namespaces/default/configproviders/app",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(deleted("app", "orga_test")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = kube::Config::new(server.uri().parse().expect("uri"));
+        let kube = kube::Client::try_from(config).expect("kubernetes client");
+
+        release(kube, objs(&["app"], "orga_test"), ADDON_FINALIZER)
+            .await
+            .expect("finalizer to be removed");
+
+        let requests = server.received_requests().await.expect("requests");
+        let event = requests
+            .iter()
+            .filter(|request| request.method == Method::Post)
+            .map(|request| serde_json::from_slice::<Value>(&request.body).expect("event"))
+            .find(|event| event["involvedObject"]["kind"] == "Namespace")
+            .expect("event on namespace");
+
+        let message = event["message"].as_str().unwrap_or_default();
+        assert!(message.starts_with("ConfigProvider 'app': "), "{}", message);
+        assert!(message.contains("addon_app"), "{}", message);
+    }
+}
@@ -0,0 +1,294 @@
+//! # Import module
+//!
+//! This module provides the import command line interface function
+//! implementation which generates custom resources adopting the existing addons
+//! of an organisation, so they could be managed by the operator.
+
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use clap::Args;
+use clevercloud_sdk::{
+    v2::addon::{self, Addon},
+    v4::addon_provider::{config_provider::addon::environment, AddonProviderId},
+};
+use kube::{
+    api::{DynamicObject, Patch, PatchParams},
+    Api, ResourceExt,
+};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::{
+    cmd::Executor,
+    svc::{
+        cfg::Configuration,
+        clevercloud::{self, client::Client, ext::AddonExt},
+        crd::{postgresql::PostgreSql, ADDON_ID_ANNOTATION},
+        k8s::{client, dependency},
+    },
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// field manager used to apply the generated custom resources
+pub const FIELD_MANAGER: &str = "clever-operator";
+
+// -----------------------------------------------------------------------------
+// ImportError enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum ImportError {
+    #[error("failed to create kubernetes client, {0}")]
+    Client(client::Error),
+    #[error("failed to execute request on kubernetes api, {0}")]
+    KubeClient(kube::Error),
+    #[error("failed to create clevercloud client, {0}")]
+    CleverClient(clevercloud::client::Error),
+    #[error("failed to list addons of organisation '{0}', {1}")]
+    List(String, addon::Error),
+    #[error("failed to serialize custom resource, {0}")]
+    Serialize(serde_yaml::Error),
+}
+
+impl From<kube::Error> for ImportError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: kube::Error) -> Self {
+        Self::KubeClient(err)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Import structure
+
+#[derive(Args, Clone, Debug)]
+pub struct Import {
+    /// Organisation whose addons are imported
+    #[clap(long = "organisation")]
+    pub organisation: String,
+    /// Namespace of the generated custom resources
+    #[clap(short = 'n', long = "namespace", default_value = "default")]
+    pub namespace: String,
+    /// Apply the generated custom resources on the cluster instead of printing
+    /// them
+    #[clap(long = "apply")]
+    pub apply: bool,
+}
+
+#[async_trait]
+impl Executor for Import {
+    type Error = ImportError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+    async fn execute(
+        &self,
+        kubeconfig: Option<PathBuf>,
+        config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
+        let apis = clevercloud::client::try_new(&config.api, &config.proxy)
+            .map_err(ImportError::CleverClient)?;
+
+        let addons = addon::list(&apis, &self.organisation)
+            .await
+            .map_err(|err| ImportError::List(self.organisation.to_owned(), err))?;
+
+        let mut objs = vec![];
+        for addon in &addons {
+            // addons created by the operator are already managed by a custom
+            // resource
+            if addon
+                .name
+                .as_ref()
+                .map(|name| name.starts_with(&format!("{}::", PostgreSql::prefix())))
+                .unwrap_or_default()
+            {
+                continue;
+            }
+
+            match manifest(&apis, &self.organisation, &self.namespace, addon).await {
+                Some(obj) => objs.push(obj),
+                None => warn!(
+                    addon = &addon.id,
+                    provider = &addon.provider.id,
+                    "Skip addon, its provider is not supported by the operator",
+                ),
+            }
+        }
+
+        if !self.apply {
+            for obj in &objs {
+                print!(
+                    "---\n{}",
+                    serde_yaml::to_string(obj).map_err(ImportError::Serialize)?
+                );
+            }
+
+            return Ok(());
+        }
+
+        let kube = client::try_new(kubeconfig, &config.proxy)
+            .await
+            .map_err(ImportError::Client)?;
+
+        for obj in &objs {
+            let kind = obj
+                .types
+                .as_ref()
+                .map(|t| t.kind.to_owned())
+                .unwrap_or_default();
+            let resource = match dependency::api_resource(&kind) {
+                Some(resource) => resource,
+                None => continue,
+            };
+
+            info!(
+                kind = &kind,
+                namespace = &self.namespace,
+                name = obj.name_any(),
+                "Apply custom resource adopting addon",
+            );
+
+            let api: Api<DynamicObject> =
+                Api::namespaced_with(kube.to_owned(), &self.namespace, &resource);
+
+            api.patch(
+                &obj.name_any(),
+                &PatchParams::apply(FIELD_MANAGER),
+                &Patch::Apply(obj),
+            )
+            .await?;
+
+            println!(
+                "{} '{}/{}' has been applied",
+                kind,
+                self.namespace,
+                obj.name_any()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns a name of the addon which is a valid kubernetes object name
+pub fn name(addon: &Addon) -> String {
+    let name = addon.name.to_owned().unwrap_or_else(|| addon.id.to_owned());
+    let name: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+
+    let name: String = name.trim_matches('-').chars().take(63).collect();
+    let name = name.trim_end_matches('-');
+    if name.is_empty() {
+        addon.id.to_lowercase().replace('_', "-")
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(apis)))]
+/// returns the custom resource adopting the addon, if its provider is supported.
+/// The creation options are not exposed by the api, the version is read from
+/// the environment of the addon, when it is available. Variables of a config
+/// provider are imported, so they are not overwritten once adopted
+pub async fn manifest(
+    apis: &Client,
+    organisation: &str,
+    namespace: &str,
+    addon: &Addon,
+) -> Option<DynamicObject> {
+    let providers = [
+        (
+            AddonProviderId::PostgreSql,
+            "PostgreSql",
+            Some("POSTGRESQL_ADDON_VERSION"),
+        ),
+        (AddonProviderId::MySql, "MySql", Some("MYSQL_ADDON_VERSION")),
+        (AddonProviderId::Redis, "Redis", Some("REDIS_VERSION")),
+        (
+            AddonProviderId::MongoDb,
+            "MongoDb",
+            Some("MONGODB_ADDON_VERSION"),
+        ),
+        (
+            AddonProviderId::ElasticSearch,
+            "ElasticSearch",
+            Some("ES_ADDON_VERSION"),
+        ),
+        (AddonProviderId::Pulsar, "Pulsar", None),
+        (AddonProviderId::ConfigProvider, "ConfigProvider", None),
+    ];
+
+    let (kind, version) = providers
+        .iter()
+        .find(|(provider, _, _)| provider.to_string() == addon.provider.id)
+        .map(|(_, kind, version)| (*kind, *version))?;
+
+    let resource = dependency::api_resource(kind)?;
+    let mut spec = json!({ "organisation": organisation });
+
+    match kind {
+        "ConfigProvider" => {
+            let variables = match environment::get(apis, &addon.real_id).await {
+                Ok(variables) => variables,
+                Err(err) => {
+                    warn!(
+                        addon = &addon.id,
+                        error = err.to_string(),
+                        "Could not retrieve variables of config provider",
+                    );
+
+                    return None;
+                }
+            };
+
+            spec["variables"] = variables
+                .into_iter()
+                .map(|var| (var.name, Value::String(var.value)))
+                .collect::<serde_json::Map<_, _>>()
+                .into();
+        }
+        "Pulsar" => {
+            spec["instance"] = json!({ "region": addon.region, "plan": addon.plan.id });
+        }
+        _ => {
+            spec["instance"] = json!({ "region": addon.region, "plan": addon.plan.id });
+
+            let environment = match addon::environment(apis, organisation, &addon.id).await {
+                Ok(environment) => environment,
+                Err(err) => {
+                    warn!(
+                        addon = &addon.id,
+                        error = err.to_string(),
+                        "Could not retrieve environment of addon",
+                    );
+
+                    BTreeMap::new()
+                }
+            };
+
+            let version = version
+                .and_then(|key| environment.get(key))
+                .map(|version| Value::String(version.to_owned()))
+                .unwrap_or(Value::Null);
+
+            spec["options"] = json!({ "version": version, "encryption": false });
+        }
+    }
+
+    let mut obj = DynamicObject::new(&name(addon), &resource)
+        .within(namespace)
+        .data(json!({ "spec": spec }));
+
+    obj.annotations_mut()
+        .insert(ADDON_ID_ANNOTATION.to_string(), addon.id.to_owned());
+
+    Some(obj)
+}
@@ -0,0 +1,115 @@
+//! # Man module
+//!
+//! This module provides the man command line interface function implementation
+//! which renders the manual pages of the operator and of its subcommands from
+//! the definition of the command line interface.
+
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use clap::CommandFactory;
+use clap_mangen::Man;
+use tracing::info;
+
+use crate::{
+    cmd::{Args, Executor},
+    svc::cfg::Configuration,
+};
+
+// -----------------------------------------------------------------------------
+// ManError enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum ManError {
+    #[error("failed to create directory '{0}', {1}")]
+    CreateDirectory(String, io::Error),
+    #[error("failed to create manual page '{0}', {1}")]
+    Create(String, io::Error),
+    #[error("failed to render manual page of command '{0}', {1}")]
+    Render(String, io::Error),
+}
+
+// -----------------------------------------------------------------------------
+// Manual structure
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct Manual {
+    /// Directory in which the manual pages of the operator and of its
+    /// subcommands are written, only the one of the operator is printed on the
+    /// standard output if it is not set
+    #[clap(short = 'o', long = "output")]
+    pub output: Option<PathBuf>,
+}
+
+#[async_trait]
+impl Executor for Manual {
+    type Error = ManError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(_config)))]
+    async fn execute(
+        &self,
+        _kubeconfig: Option<PathBuf>,
+        _config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
+        let mut command = Args::command();
+        command.build();
+
+        let output = match &self.output {
+            Some(output) => output,
+            None => return render(&command, &mut io::stdout()),
+        };
+
+        fs::create_dir_all(output)
+            .map_err(|err| ManError::CreateDirectory(output.display().to_string(), err))?;
+
+        write(&command, output)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(command, writer)))]
+/// render the manual page of the command using the given writer
+pub fn render<W>(command: &clap::Command, writer: &mut W) -> Result<(), ManError>
+where
+    W: Write,
+{
+    Man::new(command.to_owned())
+        .render(writer)
+        .map_err(|err| ManError::Render(command.get_name().to_string(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(command)))]
+/// write the manual pages of the command and of its visible subcommands in the
+/// given directory, pages are named after the full name of their command, e.g.
+/// `clever-operator-olm-generate.1`
+pub fn write(command: &clap::Command, output: &Path) -> Result<(), ManError> {
+    let name = command
+        .get_display_name()
+        .unwrap_or_else(|| command.get_name());
+
+    let path = output.join(format!("{}.1", name));
+    let mut file =
+        File::create(&path).map_err(|err| ManError::Create(path.display().to_string(), err))?;
+
+    render(command, &mut file)?;
+    info!(path = path.display().to_string(), "Write manual page");
+
+    for subcommand in command.get_subcommands() {
+        // the help subcommand is generated by clap and documented by the page
+        // of its parent
+        if subcommand.is_hide_set() || subcommand.get_name() == "help" {
+            continue;
+        }
+
+        write(subcommand, output)?;
+    }
+
+    Ok(())
+}
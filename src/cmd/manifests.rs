@@ -0,0 +1,393 @@
+//! # Manifests module
+//!
+//! This module provides the manifests command line interface function
+//! implementation which renders the manifests deploying the operator, its
+//! roles and its custom resource definitions, either as plain kubernetes
+//! manifests or as a helm chart, from the knowledge of the binary, so shipping
+//! artifacts stay in sync with it.
+
+use std::{
+    error::Error,
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use clap::Args;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    cmd::{
+        olm,
+        rbac::{role_ref, CLUSTER_ROLE},
+        Executor,
+    },
+    svc::{
+        cfg::{Configuration, OPERATOR_LISTEN},
+        k8s::{conversion, rbac},
+    },
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const CONFIGURATION: &str = "clever-operator-configuration";
+pub const PORT_NAME: &str = "observability";
+pub const METRICS_PATH: &str = "/metrics";
+pub const RELEASE_NAMESPACE: &str = "{{ .Release.Namespace }}";
+
+/// configuration of the operator read from the values of the chart, the port
+/// on which the operator listens is the one exposed by the deployment
+pub const HELM_CONFIGURATION: &str = r#"---
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  namespace: {{ .Release.Namespace }}
+  name: clever-operator-configuration
+data:
+  config.toml: |
+    [api]
+    token = "{{ .Values.config.token }}"
+    secret = "{{ .Values.config.secret }}"
+    consumerKey = "{{ .Values.config.consumerKey }}"
+    consumerSecret = "{{ .Values.config.consumerSecret }}"
+
+    [operator]
+    listen = "0.0.0.0:{port}"
+"#;
+
+// -----------------------------------------------------------------------------
+// Format enumeration
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Format {
+    Kubernetes,
+    Helm,
+}
+
+impl FromStr for Format {
+    type Err = Box<dyn Error + Send + Sync>;
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "kubernetes" => Ok(Self::Kubernetes),
+            "helm" => Ok(Self::Helm),
+            _ => Err(format!(
+                "failed to parse '{}', available options are 'kubernetes' or 'helm'",
+                s
+            )
+            .into()),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// ManifestsError enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum ManifestsError {
+    #[error("failed to create directory '{0}', {1}")]
+    CreateDir(PathBuf, io::Error),
+    #[error("failed to write file '{0}', {1}")]
+    Write(PathBuf, io::Error),
+    #[error("failed to serialize manifest, {0}")]
+    Serialize(serde_yaml::Error),
+}
+
+// -----------------------------------------------------------------------------
+// Manifests structure
+
+#[derive(Args, Clone, Debug)]
+pub struct Manifests {
+    /// Format of the manifests, one of 'kubernetes', printed on the standard
+    /// output, or 'helm', written as a chart in the output directory
+    #[clap(short = 'f', long = "format", default_value = "kubernetes")]
+    pub format: Format,
+    /// Directory in which the helm chart is written
+    #[clap(short = 'o', long = "output", default_value = "chart")]
+    pub output: PathBuf,
+    /// Image of the operator
+    #[clap(long = "image", default_value = olm::IMAGE)]
+    pub image: String,
+    /// Namespace in which the operator is deployed, the namespace of the
+    /// release is used by the helm chart
+    #[clap(short = 'n', long = "namespace", default_value = olm::NAMESPACE)]
+    pub namespace: String,
+}
+
+#[async_trait]
+impl Executor for Manifests {
+    type Error = ManifestsError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+    async fn execute(
+        &self,
+        _kubeconfig: Option<PathBuf>,
+        config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
+        match self.format {
+            Format::Kubernetes => self.kubernetes(&config),
+            Format::Helm => self.helm(&config),
+        }
+    }
+}
+
+impl Manifests {
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+    /// print the plain kubernetes manifests on the standard output
+    pub fn kubernetes(&self, config: &Configuration) -> Result<(), ManifestsError> {
+        let (namespace, port) = (&self.namespace, port(config));
+
+        let mut manifests = vec![json!({
+            "apiVersion": "v1",
+            "kind": "Namespace",
+            "metadata": { "name": namespace },
+        })];
+
+        manifests.extend(custom_resource_definitions(config));
+        manifests.extend(roles(namespace));
+        manifests.extend([
+            json!({
+                "apiVersion": "v1",
+                "kind": "ConfigMap",
+                "metadata": { "namespace": namespace, "name": CONFIGURATION },
+                "data": { "config.toml": format!("[operator]\nlisten = \"0.0.0.0:{}\"\n", port) },
+            }),
+            deployment(namespace, &self.image, "Always", port),
+            service(namespace, port),
+        ]);
+
+        for manifest in &manifests {
+            print!("---\n{}", to_yaml(manifest)?);
+        }
+
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+    /// write the helm chart in the output directory
+    pub fn helm(&self, config: &Configuration) -> Result<(), ManifestsError> {
+        let (crds, templates) = (self.output.join("crds"), self.output.join("templates"));
+        for dir in [&crds, &templates] {
+            fs::create_dir_all(dir)
+                .map_err(|err| ManifestsError::CreateDir(dir.to_owned(), err))?;
+        }
+
+        let port = port(config);
+        let (repository, tag) = self
+            .image
+            .rsplit_once(':')
+            .unwrap_or((&self.image, "latest"));
+        let version = env!("CARGO_PKG_VERSION");
+
+        write(
+            &self.output.join("Chart.yaml"),
+            &json!({
+                "apiVersion": "v2",
+                "name": olm::PACKAGE,
+                "description": env!("CARGO_PKG_DESCRIPTION"),
+                "type": "application",
+                "version": version,
+                "appVersion": version,
+            }),
+        )?;
+
+        write(
+            &self.output.join("values.yaml"),
+            &json!({
+                "image": {
+                    "repository": repository,
+                    "tag": tag,
+                    "pullPolicy": "Always",
+                },
+                "config": {
+                    "token": "",
+                    "secret": "",
+                    "consumerKey": "",
+                    "consumerSecret": "",
+                },
+            }),
+        )?;
+
+        // helm installs the custom resource definitions of the `crds`
+        // directory before rendering the templates, they are not templated
+        for crd in custom_resource_definitions(config) {
+            let name = crd["metadata"]["name"].as_str().unwrap_or_default();
+            write(&crds.join(format!("{}.yaml", name)), &crd)?;
+        }
+
+        let image = "{{ .Values.image.repository }}:{{ .Values.image.tag }}";
+        let pull_policy = "{{ .Values.image.pullPolicy }}";
+        let manifests = roles(RELEASE_NAMESPACE).into_iter().chain([
+            deployment(RELEASE_NAMESPACE, image, pull_policy, port),
+            service(RELEASE_NAMESPACE, port),
+        ]);
+
+        for manifest in manifests {
+            let kind = manifest["kind"].as_str().unwrap_or_default().to_lowercase();
+            write(&templates.join(format!("{}.yaml", kind)), &manifest)?;
+        }
+
+        let path = templates.join("configmap.yaml");
+        fs::write(
+            &path,
+            HELM_CONFIGURATION.replace("{port}", &port.to_string()),
+        )
+        .map_err(|err| ManifestsError::Write(path.to_owned(), err))?;
+
+        println!("Helm chart has been written in '{}'", self.output.display());
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(value)))]
+/// returns the value serialized in yaml
+pub fn to_yaml<T>(value: &T) -> Result<String, ManifestsError>
+where
+    T: Serialize,
+{
+    serde_yaml::to_string(value).map_err(ManifestsError::Serialize)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(value)))]
+/// serialize the value in yaml and write it in the file at the given path
+pub fn write<T>(path: &Path, value: &T) -> Result<(), ManifestsError>
+where
+    T: Serialize,
+{
+    fs::write(path, format!("---\n{}", to_yaml(value)?))
+        .map_err(|err| ManifestsError::Write(path.to_owned(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+/// returns the port on which the operator serves its probes and metrics
+pub fn port(config: &Configuration) -> u16 {
+    [config.operator.listen.as_str(), OPERATOR_LISTEN]
+        .iter()
+        .find_map(|listen| listen.parse::<SocketAddr>().ok())
+        .map(|addr| addr.port())
+        .unwrap_or_default()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+/// returns the custom resource definitions of the operator, with their
+/// served versions
+pub fn custom_resource_definitions(config: &Configuration) -> Vec<Value> {
+    olm::custom_resource_definitions()
+        .into_iter()
+        .map(|(_, crd)| json!(conversion::versions(crd, &config.operator.webhook)))
+        .collect()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the service account of the operator, its cluster role and the
+/// binding between them
+pub fn roles(namespace: &str) -> Vec<Value> {
+    let rules: Vec<_> = rbac::rules(&rbac::reconcilers())
+        .into_iter()
+        .chain(rbac::to_rules(rbac::LEADER_ELECTION))
+        .collect();
+
+    vec![
+        json!({
+            "apiVersion": "v1",
+            "kind": "ServiceAccount",
+            "metadata": { "namespace": namespace, "name": olm::SERVICE_ACCOUNT },
+        }),
+        json!({
+            "apiVersion": "rbac.authorization.k8s.io/v1",
+            "kind": "ClusterRole",
+            "metadata": { "name": CLUSTER_ROLE },
+            "rules": rules,
+        }),
+        json!({
+            "apiVersion": "rbac.authorization.k8s.io/v1",
+            "kind": "ClusterRoleBinding",
+            "metadata": { "name": CLUSTER_ROLE },
+            "roleRef": role_ref("ClusterRole", CLUSTER_ROLE),
+            "subjects": [{
+                "kind": "ServiceAccount",
+                "name": olm::SERVICE_ACCOUNT,
+                "namespace": namespace,
+            }],
+        }),
+    ]
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the deployment of the operator, reading its configuration from
+/// the `clever-operator-configuration` config map
+pub fn deployment(namespace: &str, image: &str, pull_policy: &str, port: u16) -> Value {
+    let mut deployment = olm::deployment(image);
+    let template = &mut deployment["template"];
+
+    template["metadata"]["annotations"] = json!({
+        "prometheus.io/scrape": "true",
+        "prometheus.io/port": port.to_string(),
+        "prometheus.io/path": METRICS_PATH,
+    });
+
+    template["spec"]["volumes"] = json!([{
+        "name": "config",
+        "configMap": {
+            "name": CONFIGURATION,
+            "items": [{ "key": "config.toml", "path": "config.toml" }],
+        },
+    }]);
+
+    let container = &mut template["spec"]["containers"][0];
+    container["imagePullPolicy"] = json!(pull_policy);
+    container["ports"][0]["containerPort"] = json!(port);
+    container["volumeMounts"] = json!([{
+        "name": "config",
+        "mountPath": "/etc/clever-operator",
+        "readOnly": true,
+    }]);
+
+    json!({
+        "apiVersion": "apps/v1",
+        "kind": "Deployment",
+        "metadata": {
+            "namespace": namespace,
+            "name": olm::PACKAGE,
+            "labels": { "app": olm::PACKAGE },
+        },
+        "spec": deployment,
+    })
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the service exposing the probes and metrics of the operator
+pub fn service(namespace: &str, port: u16) -> Value {
+    json!({
+        "apiVersion": "v1",
+        "kind": "Service",
+        "metadata": {
+            "namespace": namespace,
+            "name": olm::PACKAGE,
+            "labels": { "app": olm::PACKAGE },
+            "annotations": {
+                "prometheus.io/scrape": "true",
+                "prometheus.io/port": port.to_string(),
+                "prometheus.io/path": METRICS_PATH,
+            },
+        },
+        "spec": {
+            "selector": { "app": olm::PACKAGE },
+            "ports": [{
+                "name": PORT_NAME,
+                "port": port,
+                "targetPort": PORT_NAME,
+                "protocol": "TCP",
+            }],
+        },
+    })
+}
@@ -1,26 +1,54 @@
 //! # Command module
 //!
 //! This module provide command line interface structures and helpers
-use std::{io, path::PathBuf, sync::Arc};
+use std::{io, path::PathBuf, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use clap::{ArgAction, Parser, Subcommand};
-use clevercloud_sdk::oauth10a::Credentials;
+use futures::{
+    future::{pending, select_all},
+    FutureExt,
+};
 use paw::ParseArgs;
+use tokio::task::JoinHandle;
 use tracing::{error, info};
 
+#[cfg(feature = "metrics")]
+use crate::svc::k8s::stats;
 use crate::{
-    cmd::crd::CustomResourceDefinitionError,
+    cmd::{
+        bench::BenchError, completion::CompletionError, crd::CustomResourceDefinitionError,
+        doctor::DoctorError, force_unlock::ForceUnlockError, import::ImportError, man::ManError,
+        manifests::ManifestsError, olm::OlmError, rbac::RbacError, rotate::RotateError,
+        status::StatusError,
+    },
     svc::{
         cfg::Configuration,
         clevercloud,
-        crd::{config_provider, elasticsearch, mongodb, mysql, postgresql, pulsar, redis},
-        http,
-        k8s::{client, Context, Watcher},
+        crd::{
+            addon, backup, cellar_bucket, config_provider, elasticsearch, fs_bucket, gravitee,
+            jenkins, mailpace, mongodb, mysql, postgresql, pulsar, redis, runtime,
+        },
+        health, http,
+        k8s::{client, definition, lease, sweeper, Context, Watcher},
+        reload,
+        shutdown::{self, Shutdown},
+        supervisor, update,
     },
 };
 
+pub mod bench;
+pub mod completion;
 pub mod crd;
+pub mod doctor;
+pub mod force_unlock;
+pub mod import;
+pub mod man;
+pub mod manifests;
+pub mod olm;
+pub mod rbac;
+pub mod rotate;
+pub mod status;
 
 // -----------------------------------------------------------------------------
 // Executor trait
@@ -29,7 +57,11 @@ pub mod crd;
 pub trait Executor {
     type Error;
 
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error>;
+    async fn execute(
+        &self,
+        kubeconfig: Option<PathBuf>,
+        config: Arc<Configuration>,
+    ) -> Result<(), Self::Error>;
 }
 
 // -----------------------------------------------------------------------------
@@ -40,13 +72,39 @@ pub enum Error {
     #[error("failed to execute command '{0}', {1}")]
     Execution(String, Arc<Error>),
     #[error("failed to execute command, {0}")]
+    Bench(BenchError),
+    #[error("failed to execute command, {0}")]
+    Completion(CompletionError),
+    #[error("failed to execute command, {0}")]
     CustomResourceDefinition(CustomResourceDefinitionError),
+    #[error("failed to execute command, {0}")]
+    Doctor(DoctorError),
+    #[error("failed to execute command, {0}")]
+    ForceUnlock(ForceUnlockError),
+    #[error("failed to execute command, {0}")]
+    Import(ImportError),
+    #[error("failed to execute command, {0}")]
+    Man(ManError),
+    #[error("failed to execute command, {0}")]
+    Manifests(ManifestsError),
+    #[error("failed to execute command, {0}")]
+    Olm(OlmError),
+    #[error("failed to execute command, {0}")]
+    Rbac(RbacError),
+    #[error("failed to execute command, {0}")]
+    RotateCredentials(RotateError),
+    #[error("failed to execute command, {0}")]
+    Status(StatusError),
     #[error("failed to handle termintion signal, {0}")]
     SigTerm(io::Error),
     #[error("failed to create kubernetes client, {0}")]
     Client(client::Error),
+    #[error("failed to manage custom resource definitions, {0}")]
+    Definition(definition::Error),
     #[error("failed to create clevercloud client, {0}")]
     CleverClient(clevercloud::client::Error),
+    #[error("failed to retrieve clevercloud credentials, {0}")]
+    Credentials(reload::Error),
     #[error("failed to watch PostgreSql resources, {0}")]
     WatchPostgreSql(postgresql::ReconcilerError),
     #[error("failed to watch Redis resources, {0}")]
@@ -61,8 +119,30 @@ pub enum Error {
     WatchConfigProvider(config_provider::ReconcilerError),
     #[error("failed to watch Pulsar resources, {0}")]
     WatchPulsar(pulsar::ReconcilerError),
+    #[error("failed to watch Runtime resources, {0}")]
+    WatchRuntime(runtime::ReconcilerError),
+    #[error("failed to watch CellarBucket resources, {0}")]
+    WatchCellarBucket(cellar_bucket::ReconcilerError),
+    #[error("failed to watch Backup resources, {0}")]
+    WatchBackup(backup::ReconcilerError),
+    #[error("failed to watch Addon resources, {0}")]
+    WatchAddon(addon::ReconcilerError),
+    #[error("failed to watch FSBucket resources, {0}")]
+    WatchFsBucket(fs_bucket::ReconcilerError),
+    #[error("failed to watch Mailpace resources, {0}")]
+    WatchMailpace(mailpace::ReconcilerError),
+    #[error("failed to watch Jenkins resources, {0}")]
+    WatchJenkins(jenkins::ReconcilerError),
+    #[error("failed to watch Gravitee resources, {0}")]
+    WatchGravitee(gravitee::ReconcilerError),
+    #[error("failed to supervise controller, {0}")]
+    Supervise(supervisor::Error),
     #[error("failed to serve http content, {0}")]
     Serve(http::server::Error),
+    #[error("failed to serve admission webhook, {0}")]
+    Webhook(http::webhook::Error),
+    #[error("failed to hold leader lease, {0}")]
+    Lease(lease::Error),
     #[error("failed to spawn task on tokio, {0}")]
     Join(tokio::task::JoinError),
 }
@@ -72,8 +152,65 @@ pub enum Error {
 
 #[derive(Subcommand, Clone, Debug)]
 pub enum Command {
+    #[clap(
+        name = "bench",
+        hide = true,
+        about = "Replay synthetic events of custom resources through the reconciliation against in-memory apis"
+    )]
+    Bench(bench::Bench),
+    #[clap(
+        name = "completion",
+        about = "Generate the completion script of the command line interface for the given shell"
+    )]
+    Completion(completion::Completion),
     #[clap(name = "custom-resource-definition", aliases= &["crd"], subcommand, about = "Interact with custom resource definition")]
     CustomResourceDefinition(crd::CustomResourceDefinition),
+    #[clap(
+        name = "doctor",
+        about = "Check credentials, custom resource definitions and permissions of the operator"
+    )]
+    Doctor(doctor::Doctor),
+    #[clap(
+        name = "force-unlock",
+        about = "Remove finalizers of custom resources stuck in deletion, once their credentials are permanently lost"
+    )]
+    ForceUnlock(force_unlock::ForceUnlock),
+    #[clap(
+        name = "import",
+        about = "Generate custom resources adopting the existing addons of an organisation"
+    )]
+    Import(import::Import),
+    #[clap(
+        name = "man",
+        about = "Render the manual pages of the command line interface"
+    )]
+    Man(man::Manual),
+    #[clap(
+        name = "manifests",
+        about = "Render the manifests deploying the operator, as plain kubernetes manifests or as a helm chart"
+    )]
+    Manifests(manifests::Manifests),
+    #[clap(
+        name = "olm",
+        subcommand,
+        about = "Interact with the operator lifecycle manager bundle"
+    )]
+    Olm(olm::Olm),
+    #[clap(
+        name = "rbac",
+        about = "Generate the minimal roles and bindings required by the operator"
+    )]
+    Rbac(rbac::Rbac),
+    #[clap(
+        name = "rotate-credentials",
+        about = "Request the rotation of credentials of a custom resource and roll out deployments consuming them"
+    )]
+    RotateCredentials(rotate::RotateCredentials),
+    #[clap(
+        name = "status",
+        about = "Display the debug state of a running operator, like recently skipped reconciliations"
+    )]
+    Status(status::Status),
 }
 
 #[async_trait]
@@ -81,15 +218,74 @@ impl Executor for Command {
     type Error = Error;
 
     #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error> {
+    async fn execute(
+        &self,
+        kubeconfig: Option<PathBuf>,
+        config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
         match self {
+            Self::Bench(bench) => bench
+                .execute(kubeconfig, config)
+                .await
+                .map_err(Error::Bench)
+                .map_err(|err| Error::Execution("bench".into(), Arc::new(err))),
+            Self::Completion(completion) => completion
+                .execute(kubeconfig, config)
+                .await
+                .map_err(Error::Completion)
+                .map_err(|err| Error::Execution("completion".into(), Arc::new(err))),
             Self::CustomResourceDefinition(crd) => crd
-                .execute(config)
+                .execute(kubeconfig, config)
                 .await
                 .map_err(Error::CustomResourceDefinition)
                 .map_err(|err| {
                     Error::Execution("custom-resource-definition".into(), Arc::new(err))
                 }),
+            Self::Doctor(doctor) => doctor
+                .execute(kubeconfig, config)
+                .await
+                .map_err(Error::Doctor)
+                .map_err(|err| Error::Execution("doctor".into(), Arc::new(err))),
+            Self::ForceUnlock(force_unlock) => force_unlock
+                .execute(kubeconfig, config)
+                .await
+                .map_err(Error::ForceUnlock)
+                .map_err(|err| Error::Execution("force-unlock".into(), Arc::new(err))),
+            Self::Import(import) => import
+                .execute(kubeconfig, config)
+                .await
+                .map_err(Error::Import)
+                .map_err(|err| Error::Execution("import".into(), Arc::new(err))),
+            Self::Man(man) => man
+                .execute(kubeconfig, config)
+                .await
+                .map_err(Error::Man)
+                .map_err(|err| Error::Execution("man".into(), Arc::new(err))),
+            Self::Manifests(manifests) => manifests
+                .execute(kubeconfig, config)
+                .await
+                .map_err(Error::Manifests)
+                .map_err(|err| Error::Execution("manifests".into(), Arc::new(err))),
+            Self::Olm(olm) => olm
+                .execute(kubeconfig, config)
+                .await
+                .map_err(Error::Olm)
+                .map_err(|err| Error::Execution("olm".into(), Arc::new(err))),
+            Self::Rbac(rbac) => rbac
+                .execute(kubeconfig, config)
+                .await
+                .map_err(Error::Rbac)
+                .map_err(|err| Error::Execution("rbac".into(), Arc::new(err))),
+            Self::RotateCredentials(rotate) => rotate
+                .execute(kubeconfig, config)
+                .await
+                .map_err(Error::RotateCredentials)
+                .map_err(|err| Error::Execution("rotate-credentials".into(), Arc::new(err))),
+            Self::Status(status) => status
+                .execute(kubeconfig, config)
+                .await
+                .map_err(Error::Status)
+                .map_err(|err| Error::Execution("status".into(), Arc::new(err))),
         }
     }
 }
@@ -109,6 +305,12 @@ pub struct Args {
     /// Specify location of configuration
     #[clap(short = 'c', long = "config", global = true)]
     pub config: Option<PathBuf>,
+    /// Specify the secret holding the clever cloud credentials, as `namespace/name`
+    #[clap(long = "credentials-secret")]
+    pub credentials_secret: Option<String>,
+    /// Apply the custom resource definitions at startup and wait for them to be established
+    #[clap(long = "manage-crds")]
+    pub manage_crds: bool,
     /// Check if configuration is healthy
     #[clap(short = 't', long = "check", global = true)]
     pub check: bool,
@@ -127,21 +329,76 @@ impl ParseArgs for Args {
 // -----------------------------------------------------------------------------
 // daemon function
 
-pub async fn daemon(kubeconfig: Option<PathBuf>, config: Arc<Configuration>) -> Result<(), Error> {
+pub async fn daemon(
+    kubeconfig: Option<PathBuf>,
+    path: Option<PathBuf>,
+    config: Arc<Configuration>,
+) -> Result<(), Error> {
     // -------------------------------------------------------------------------
     // Create a new kubernetes client from path if defined, or via the
     // environment or defaults locations
-    let kube_client = client::try_new(kubeconfig).await.map_err(Error::Client)?;
+    let kube_client = client::try_new(kubeconfig, &config.proxy)
+        .await
+        .map_err(Error::Client)?;
+
+    // -------------------------------------------------------------------------
+    // Apply the custom resource definitions, if they are managed by the
+    // operator, before controllers start to watch their custom resources
+    if config.operator.custom_resource_definitions.manage {
+        definition::apply(kube_client.to_owned(), &config)
+            .await
+            .map_err(Error::Definition)?;
+    }
+
+    // -------------------------------------------------------------------------
+    // Compare the custom resource definitions stored in the cluster with the
+    // ones of the operator, as the api server drops the fields it does not know
+    definition::preflight(kube_client.to_owned(), &config)
+        .await
+        .map_err(Error::Definition)?;
+
+    // -------------------------------------------------------------------------
+    // Retrieve the credentials from the secret, if one is configured
+    let config = match &config.operator.credentials_secret {
+        Some(_) => {
+            let api = reload::credentials(
+                kube_client.to_owned(),
+                &config.operator.credentials_secret,
+                &config.api,
+            )
+            .await
+            .map_err(Error::Credentials)?;
+
+            Arc::new(Configuration {
+                api,
+                ..(*config).to_owned()
+            })
+        }
+        None => config,
+    };
 
     // -------------------------------------------------------------------------
     // Create a new clever-cloud client
-    let credentials: Credentials = config.api.to_owned().into();
     let clever_client =
-        clevercloud::client::try_new(credentials, &config.proxy).map_err(Error::CleverClient)?;
+        clevercloud::client::try_new(&config.api, &config.proxy).map_err(Error::CleverClient)?;
+
+    // -------------------------------------------------------------------------
+    // Create the leader elector, if leader election is enabled
+    let elector = if config.operator.leader_election.enabled {
+        Some(
+            lease::Elector::try_new(kube_client.to_owned(), &config.operator.leader_election)
+                .map_err(Error::Lease)?,
+        )
+    } else {
+        None
+    };
+
+    let renew_elector = elector.to_owned();
 
     // -------------------------------------------------------------------------
     // Create context to give to each reconciler
     let context = Arc::new(Context::new(kube_client, clever_client, config.to_owned()));
+    let probe_client = context.apis.to_owned();
 
     let postgresql_ctx = context.to_owned();
     let mysql_ctx = context.to_owned();
@@ -150,63 +407,418 @@ pub async fn daemon(kubeconfig: Option<PathBuf>, config: Arc<Configuration>) ->
     let elasticsearch_ctx = context.to_owned();
     let config_provider_ctx = context.to_owned();
     let pulsar_ctx = context.to_owned();
+    let runtime_ctx = context.to_owned();
+    let cellar_bucket_ctx = context.to_owned();
+    let webhook_ctx = context.to_owned();
+    let server_ctx = context.to_owned();
+    let backup_ctx = context.to_owned();
+    let addon_ctx = context.to_owned();
+    let fs_bucket_ctx = context.to_owned();
+    let mailpace_ctx = context.to_owned();
+    let jenkins_ctx = context.to_owned();
+    let gravitee_ctx = context.to_owned();
+    let cache_ctx = context.to_owned();
+    let reload_ctx = context.to_owned();
+    let sweeper_ctx = context.to_owned();
+    let update_config = config.to_owned();
+    #[cfg(feature = "metrics")]
+    let stats_config = config.to_owned();
 
     // -------------------------------------------------------------------------
     // Start services
 
-    tokio::select! {
-        r = tokio::spawn(async move {
-            info!(kind = "PostgreSql", "Start to listen for events of custom resource");
-            postgresql::Reconciler::default()
-                .watch(postgresql_ctx)
-                .await
-                .map_err(Error::WatchPostgreSql)
-        }) => r,
-        r = tokio::spawn(async move {
-            info!(kind = "Redis", "Start to listen for events of custom resource");
-            redis::Reconciler::default()
-                .watch(redis_ctx)
-                .await
-                .map_err(Error::WatchRedis)
-        }) => r,
-        r = tokio::spawn(async move {
-            info!(kind = "MySql", "Start to listen for events of custom resource");
-            mysql::Reconciler::default()
-                .watch(mysql_ctx)
-                .await
-                .map_err(Error::WatchMySql)
-        }) => r,
-        r = tokio::spawn(async move {
-            info!(kind = "MongoDb", "Start to listen for events of custom resource");
-            mongodb::Reconciler::default()
-                .watch(mongodb_ctx)
-                .await
-                .map_err(Error::WatchMongoDb)
-        }) => r,
-        r = tokio::spawn(async move {
-            info!(kind = "Pulsar", "Start to listen for events of custom resource");
-            pulsar::Reconciler::default()
-                .watch(pulsar_ctx)
-                .await
-                .map_err(Error::WatchPulsar)
-        }) => r,
-        r = tokio::spawn(async move {
-            info!(kind = "ConfigProvider", "Start to listen for events of custom resource");
-            config_provider::Reconciler::default()
-                .watch(config_provider_ctx)
-                .await
-                .map_err(Error::WatchConfigProvider)
-        }) => r,
-        r = tokio::spawn(async move {
-            info!(kind = "ElasticSearch", "Start to listen for events of custom resource");
-            elasticsearch::Reconciler::default()
-                .watch(elasticsearch_ctx)
-                .await
-                .map_err(Error::WatchElasticSearch)
-        }) => r,
-        r = tokio::spawn(async move { tokio::signal::ctrl_c().await.map_err(Error::SigTerm) }) => r,
-        r = tokio::spawn(async move { http::server::serve(config.to_owned()).await.map_err(Error::Serve) }) => r,
-    }.map_err(Error::Join)??;
+    let shutdown = Shutdown::new(Duration::from_secs(config.operator.shutdown.grace_period));
+
+    let postgresql_signal = shutdown.controllers().shared();
+    let redis_signal = shutdown.controllers().shared();
+    let mysql_signal = shutdown.controllers().shared();
+    let mongodb_signal = shutdown.controllers().shared();
+    let pulsar_signal = shutdown.controllers().shared();
+    let config_provider_signal = shutdown.controllers().shared();
+    let elasticsearch_signal = shutdown.controllers().shared();
+    let runtime_signal = shutdown.controllers().shared();
+    let cellar_bucket_signal = shutdown.controllers().shared();
+    let backup_signal = shutdown.controllers().shared();
+    let addon_signal = shutdown.controllers().shared();
+    let fs_bucket_signal = shutdown.controllers().shared();
+    let mailpace_signal = shutdown.controllers().shared();
+    let jenkins_signal = shutdown.controllers().shared();
+    let gravitee_signal = shutdown.controllers().shared();
+    let server_signal = shutdown.server();
+    let webhook_signal = shutdown.server();
+
+    let probe = tokio::spawn(health::probe(
+        probe_client,
+        Duration::from_secs(config.operator.health.probe_interval),
+    ));
+
+    let mut server = tokio::spawn(async move {
+        http::server::serve(server_ctx, server_signal)
+            .await
+            .map_err(Error::Serve)
+    });
+
+    let mut webhook = tokio::spawn(async move {
+        http::webhook::serve(webhook_ctx, webhook_signal)
+            .await
+            .map_err(Error::Webhook)
+    });
+
+    let update = tokio::spawn(async move { update::watch(update_config).await });
+    let cache = tokio::spawn(async move { cache_ctx.secrets.run().await });
+    let reload = tokio::spawn(async move { reload::watch(reload_ctx, path).await });
+    #[cfg(feature = "metrics")]
+    let addon_metrics = tokio::spawn(async move { stats::watch(stats_config).await });
+
+    // -------------------------------------------------------------------------
+    // Replicas which are not the leader stay ready, they serve the http server
+    // and the admission webhook while they wait for the leader lease
+
+    shutdown::set_ready(true);
+
+    if let Some(elector) = &elector {
+        tokio::select! {
+            _ = elector.acquire() => {}
+            r = shutdown::signal() => {
+                info!("Received termination signal, start to shutdown");
+                update.abort();
+                probe.abort();
+                cache.abort();
+                reload.abort();
+                #[cfg(feature = "metrics")]
+                addon_metrics.abort();
+                shutdown.run(Vec::<JoinHandle<Result<(), Error>>>::new(), server, None).await;
+                webhook.abort();
+                return r.map_err(Error::SigTerm);
+            }
+        }
+    }
+
+    // orphaned addons are only swept by the leader, as they could be deleted
+    let sweeper = tokio::spawn(async move { sweeper::watch(sweeper_ctx).await });
+
+    let mut renew = tokio::spawn(async move {
+        match renew_elector {
+            Some(elector) => elector.renew().await.map_err(Error::Lease),
+            None => pending().await,
+        }
+    });
+
+    let mut controllers = vec![
+        tokio::spawn(async move {
+            info!(
+                kind = "PostgreSql",
+                "Start to listen for events of custom resource"
+            );
+            let config = postgresql_ctx.config.to_owned();
+            supervisor::supervise("PostgreSql", config, postgresql_signal, move |signal| {
+                let ctx = postgresql_ctx.to_owned();
+                async move {
+                    postgresql::Reconciler::default()
+                        .watch(ctx, signal)
+                        .await
+                        .map_err(Error::WatchPostgreSql)
+                }
+            })
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "Redis",
+                "Start to listen for events of custom resource"
+            );
+            let config = redis_ctx.config.to_owned();
+            supervisor::supervise("Redis", config, redis_signal, move |signal| {
+                let ctx = redis_ctx.to_owned();
+                async move {
+                    redis::Reconciler::default()
+                        .watch(ctx, signal)
+                        .await
+                        .map_err(Error::WatchRedis)
+                }
+            })
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "MySql",
+                "Start to listen for events of custom resource"
+            );
+            let config = mysql_ctx.config.to_owned();
+            supervisor::supervise("MySql", config, mysql_signal, move |signal| {
+                let ctx = mysql_ctx.to_owned();
+                async move {
+                    mysql::Reconciler::default()
+                        .watch(ctx, signal)
+                        .await
+                        .map_err(Error::WatchMySql)
+                }
+            })
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "MongoDb",
+                "Start to listen for events of custom resource"
+            );
+            let config = mongodb_ctx.config.to_owned();
+            supervisor::supervise("MongoDb", config, mongodb_signal, move |signal| {
+                let ctx = mongodb_ctx.to_owned();
+                async move {
+                    mongodb::Reconciler::default()
+                        .watch(ctx, signal)
+                        .await
+                        .map_err(Error::WatchMongoDb)
+                }
+            })
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "Pulsar",
+                "Start to listen for events of custom resource"
+            );
+            let config = pulsar_ctx.config.to_owned();
+            supervisor::supervise("Pulsar", config, pulsar_signal, move |signal| {
+                let ctx = pulsar_ctx.to_owned();
+                async move {
+                    pulsar::Reconciler::default()
+                        .watch(ctx, signal)
+                        .await
+                        .map_err(Error::WatchPulsar)
+                }
+            })
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "ConfigProvider",
+                "Start to listen for events of custom resource"
+            );
+            let config = config_provider_ctx.config.to_owned();
+            supervisor::supervise(
+                "ConfigProvider",
+                config,
+                config_provider_signal,
+                move |signal| {
+                    let ctx = config_provider_ctx.to_owned();
+                    async move {
+                        config_provider::Reconciler::default()
+                            .watch(ctx, signal)
+                            .await
+                            .map_err(Error::WatchConfigProvider)
+                    }
+                },
+            )
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "ElasticSearch",
+                "Start to listen for events of custom resource"
+            );
+            let config = elasticsearch_ctx.config.to_owned();
+            supervisor::supervise(
+                "ElasticSearch",
+                config,
+                elasticsearch_signal,
+                move |signal| {
+                    let ctx = elasticsearch_ctx.to_owned();
+                    async move {
+                        elasticsearch::Reconciler::default()
+                            .watch(ctx, signal)
+                            .await
+                            .map_err(Error::WatchElasticSearch)
+                    }
+                },
+            )
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "Runtime",
+                "Start to listen for events of custom resource"
+            );
+            let config = runtime_ctx.config.to_owned();
+            supervisor::supervise("Runtime", config, runtime_signal, move |signal| {
+                let ctx = runtime_ctx.to_owned();
+                async move {
+                    runtime::Reconciler::default()
+                        .watch(ctx, signal)
+                        .await
+                        .map_err(Error::WatchRuntime)
+                }
+            })
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "CellarBucket",
+                "Start to listen for events of custom resource"
+            );
+            let config = cellar_bucket_ctx.config.to_owned();
+            supervisor::supervise(
+                "CellarBucket",
+                config,
+                cellar_bucket_signal,
+                move |signal| {
+                    let ctx = cellar_bucket_ctx.to_owned();
+                    async move {
+                        cellar_bucket::Reconciler::default()
+                            .watch(ctx, signal)
+                            .await
+                            .map_err(Error::WatchCellarBucket)
+                    }
+                },
+            )
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "Backup",
+                "Start to listen for events of custom resource"
+            );
+            let config = backup_ctx.config.to_owned();
+            supervisor::supervise("Backup", config, backup_signal, move |signal| {
+                let ctx = backup_ctx.to_owned();
+                async move {
+                    backup::Reconciler::default()
+                        .watch(ctx, signal)
+                        .await
+                        .map_err(Error::WatchBackup)
+                }
+            })
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "Addon",
+                "Start to listen for events of custom resource"
+            );
+            let config = addon_ctx.config.to_owned();
+            supervisor::supervise("Addon", config, addon_signal, move |signal| {
+                let ctx = addon_ctx.to_owned();
+                async move {
+                    addon::Reconciler::default()
+                        .watch(ctx, signal)
+                        .await
+                        .map_err(Error::WatchAddon)
+                }
+            })
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "FSBucket",
+                "Start to listen for events of custom resource"
+            );
+            let config = fs_bucket_ctx.config.to_owned();
+            supervisor::supervise("FSBucket", config, fs_bucket_signal, move |signal| {
+                let ctx = fs_bucket_ctx.to_owned();
+                async move {
+                    fs_bucket::Reconciler::default()
+                        .watch(ctx, signal)
+                        .await
+                        .map_err(Error::WatchFsBucket)
+                }
+            })
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "Mailpace",
+                "Start to listen for events of custom resource"
+            );
+            let config = mailpace_ctx.config.to_owned();
+            supervisor::supervise("Mailpace", config, mailpace_signal, move |signal| {
+                let ctx = mailpace_ctx.to_owned();
+                async move {
+                    mailpace::Reconciler::default()
+                        .watch(ctx, signal)
+                        .await
+                        .map_err(Error::WatchMailpace)
+                }
+            })
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "Jenkins",
+                "Start to listen for events of custom resource"
+            );
+            let config = jenkins_ctx.config.to_owned();
+            supervisor::supervise("Jenkins", config, jenkins_signal, move |signal| {
+                let ctx = jenkins_ctx.to_owned();
+                async move {
+                    jenkins::Reconciler::default()
+                        .watch(ctx, signal)
+                        .await
+                        .map_err(Error::WatchJenkins)
+                }
+            })
+            .await
+            .map_err(Error::Supervise)
+        }),
+        tokio::spawn(async move {
+            info!(
+                kind = "Gravitee",
+                "Start to listen for events of custom resource"
+            );
+            let config = gravitee_ctx.config.to_owned();
+            supervisor::supervise("Gravitee", config, gravitee_signal, move |signal| {
+                let ctx = gravitee_ctx.to_owned();
+                async move {
+                    gravitee::Reconciler::default()
+                        .watch(ctx, signal)
+                        .await
+                        .map_err(Error::WatchGravitee)
+                }
+            })
+            .await
+            .map_err(Error::Supervise)
+        }),
+    ];
+
+    // -------------------------------------------------------------------------
+    // Wait for a termination signal or for a service to halt
+
+    let result = tokio::select! {
+        r = shutdown::signal() => {
+            info!("Received termination signal, start to shutdown");
+            r.map_err(Error::SigTerm)
+        }
+        (r, _, _) = select_all(controllers.iter_mut()) => r.map_err(Error::Join).and_then(|r| r),
+        r = &mut server => r.map_err(Error::Join).and_then(|r| r),
+        r = &mut webhook => r.map_err(Error::Join).and_then(|r| r),
+        r = &mut renew => r.map_err(Error::Join).and_then(|r| r),
+    };
+
+    // -------------------------------------------------------------------------
+    // Stop services in order
+
+    update.abort();
+    probe.abort();
+    cache.abort();
+    reload.abort();
+    #[cfg(feature = "metrics")]
+    addon_metrics.abort();
+    sweeper.abort();
+    renew.abort();
+    shutdown.run(controllers, server, elector.as_ref()).await;
+    webhook.abort();
 
-    Ok(())
+    result
 }
@@ -0,0 +1,539 @@
+//! # Operator lifecycle manager module
+//!
+//! This module provides the operator lifecycle manager command line interface
+//! function implementation which generates the bundle published on
+//! [OperatorHub](https://operatorhub.io) from the custom resource definitions
+//! of the operator, instead of maintaining its manifests by hand.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use clap::{Args, Subcommand};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::CustomResourceExt;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    cmd::Executor,
+    svc::{
+        cfg::Configuration,
+        crd::{
+            addon::Addon, backup::Backup, cellar_bucket::CellarBucket,
+            config_provider::ConfigProvider, elasticsearch::ElasticSearch, fs_bucket::FSBucket,
+            gravitee::Gravitee, jenkins::Jenkins, mailpace::Mailpace, mongodb::MongoDb,
+            mysql::MySql, postgresql::PostgreSql, pulsar::Pulsar, redis::Redis, runtime::Runtime,
+        },
+        k8s::rbac,
+    },
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const PACKAGE: &str = "clever-operator";
+pub const NAMESPACE: &str = "clever-operator-system";
+pub const SERVICE_ACCOUNT: &str = "clever-operator";
+pub const IMAGE: &str = concat!(
+    "docker.io/clevercloud/clever-operator:",
+    env!("CARGO_PKG_VERSION")
+);
+
+// -----------------------------------------------------------------------------
+// OlmError enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum OlmError {
+    #[error("failed to create directory '{0}', {1}")]
+    CreateDir(PathBuf, io::Error),
+    #[error("failed to write file '{0}', {1}")]
+    Write(PathBuf, io::Error),
+    #[error("failed to serialize manifest, {0}")]
+    Serialize(serde_yaml::Error),
+    #[error("failed to serialize examples of custom resources, {0}")]
+    SerializeExamples(serde_json::Error),
+}
+
+// -----------------------------------------------------------------------------
+// Olm enumeration
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum Olm {
+    #[clap(
+        name = "bundle",
+        about = "Generate the operator lifecycle manager bundle to publish on OperatorHub"
+    )]
+    Bundle(Bundle),
+}
+
+#[async_trait]
+impl Executor for Olm {
+    type Error = OlmError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+    async fn execute(
+        &self,
+        kubeconfig: Option<PathBuf>,
+        config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
+        match self {
+            Self::Bundle(bundle) => bundle.execute(kubeconfig, config).await,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Bundle structure
+
+#[derive(Args, Clone, Debug)]
+pub struct Bundle {
+    /// Directory in which the bundle is written
+    #[clap(short = 'o', long = "output", default_value = "bundle")]
+    pub output: PathBuf,
+    /// Image of the operator deployed by the cluster service version
+    #[clap(long = "image", default_value = IMAGE)]
+    pub image: String,
+    /// Channel in which the bundle is published
+    #[clap(long = "channel", default_value = "alpha")]
+    pub channel: String,
+}
+
+#[async_trait]
+impl Executor for Bundle {
+    type Error = OlmError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(_config)))]
+    async fn execute(
+        &self,
+        _kubeconfig: Option<PathBuf>,
+        _config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
+        let manifests = self.output.join("manifests");
+        let metadata = self.output.join("metadata");
+
+        for dir in [&manifests, &metadata] {
+            fs::create_dir_all(dir).map_err(|err| OlmError::CreateDir(dir.to_owned(), err))?;
+        }
+
+        let crds = custom_resource_definitions();
+        for (name, crd) in &crds {
+            write(
+                &manifests.join(format!("{}-{}.crd.yaml", PACKAGE, name)),
+                crd,
+            )?;
+        }
+
+        let crds: Vec<_> = crds.into_iter().map(|(_, crd)| crd).collect();
+        write(
+            &manifests.join(format!("{}.clusterserviceversion.yaml", PACKAGE)),
+            &cluster_service_version(&self.image, &crds)?,
+        )?;
+
+        write(
+            &metadata.join("annotations.yaml"),
+            &json!({ "annotations": annotations(&self.channel) }),
+        )?;
+
+        let path = self.output.join("bundle.Dockerfile");
+        fs::write(&path, dockerfile(&self.channel))
+            .map_err(|err| OlmError::Write(path.to_owned(), err))?;
+
+        println!("Bundle has been written in '{}'", self.output.display());
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(value)))]
+/// serialize the value in yaml and write it in the file at the given path
+pub fn write<T>(path: &Path, value: &T) -> Result<(), OlmError>
+where
+    T: Serialize,
+{
+    let content = serde_yaml::to_string(value).map_err(OlmError::Serialize)?;
+
+    fs::write(path, format!("---\n{}", content))
+        .map_err(|err| OlmError::Write(path.to_owned(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the custom resource definitions owned by the operator alongside the
+/// name used for their manifest
+pub fn custom_resource_definitions() -> Vec<(&'static str, CustomResourceDefinition)> {
+    vec![
+        ("postgresql", PostgreSql::crd()),
+        ("redis", Redis::crd()),
+        ("mysql", MySql::crd()),
+        ("mongodb", MongoDb::crd()),
+        ("pulsar", Pulsar::crd()),
+        ("config-provider", ConfigProvider::crd()),
+        ("elasticsearch", ElasticSearch::crd()),
+        ("runtime", Runtime::crd()),
+        ("cellar-bucket", CellarBucket::crd()),
+        ("backup", Backup::crd()),
+        ("addon", Addon::crd()),
+        ("fs-bucket", FSBucket::crd()),
+        ("mailpace", Mailpace::crd()),
+        ("jenkins", Jenkins::crd()),
+        ("gravitee", Gravitee::crd()),
+    ]
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the description of the custom resource and if the operator writes
+/// secrets for it
+pub fn description(kind: &str) -> (&'static str, bool) {
+    match kind {
+        "PostgreSql" => ("Clever Cloud's managed postgresql databases", true),
+        "Redis" => ("Clever Cloud's managed redis databases", true),
+        "MySql" => ("Clever Cloud's managed mysql databases", true),
+        "MongoDb" => ("Clever Cloud's managed mongodb databases", true),
+        "Pulsar" => ("Clever Cloud's managed pulsar topics", true),
+        "ConfigProvider" => ("Clever Cloud's configuration providers", true),
+        "ElasticSearch" => ("Clever Cloud's managed elasticsearch indexes", true),
+        "Runtime" => ("Clever Cloud's applications", false),
+        "CellarBucket" => ("Buckets of Clever Cloud's Cellar object storage", true),
+        "Backup" => ("Backups of Clever Cloud's managed databases", false),
+        "Addon" => ("Clever Cloud's addons of any provider", true),
+        "FSBucket" => ("Clever Cloud's persistent file system buckets", true),
+        "Mailpace" => ("Clever Cloud's mailpace transactional email addons", true),
+        "Jenkins" => ("Clever Cloud's managed jenkins instances", true),
+        "Gravitee" => ("Clever Cloud's gravitee api gateway addons", true),
+        _ => ("Clever Cloud's resources", false),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the specification of the example of the custom resource
+pub fn example(kind: &str) -> Value {
+    let instance = json!({ "region": "par", "plan": "plan_xxxx" });
+
+    match kind {
+        "PostgreSql" => json!({
+            "organisation": "orga_xxxx",
+            "options": { "version": 15, "encryption": true },
+            "instance": instance,
+        }),
+        "Redis" => json!({
+            "organisation": "orga_xxxx",
+            "options": { "version": 704, "encryption": true },
+            "instance": instance,
+        }),
+        "MySql" => json!({
+            "organisation": "orga_xxxx",
+            "options": { "version": 80, "encryption": true },
+            "instance": instance,
+        }),
+        "MongoDb" => json!({
+            "organisation": "orga_xxxx",
+            "options": { "version": 403, "encryption": true },
+            "instance": instance,
+        }),
+        "Pulsar" => json!({
+            "organisation": "orga_xxxx",
+            "instance": { "region": "par" },
+        }),
+        "ConfigProvider" => json!({
+            "organisation": "orga_xxxx",
+            "variables": { "ENV_VAR": "VALUE" },
+        }),
+        "ElasticSearch" => json!({
+            "organisation": "orga_xxxx",
+            "options": { "version": 8, "encryption": true, "kibana": false, "apm": false },
+            "instance": instance,
+        }),
+        "Runtime" => json!({
+            "organisation": "orga_xxxx",
+            "instance": { "type": "node", "region": "par" },
+            "scalability": {
+                "minInstances": 1,
+                "maxInstances": 2,
+                "minFlavor": "nano",
+                "maxFlavor": "S",
+            },
+        }),
+        "CellarBucket" => json!({
+            "credentialsRef": { "name": "cellar-credentials" },
+            "bucket": "my-bucket",
+        }),
+        "Backup" => json!({
+            "addonRef": { "kind": "PostgreSql", "name": "postgresql" },
+            "refreshInterval": 3600,
+        }),
+        "Addon" => json!({
+            "organisation": "orga_xxxx",
+            "providerId": "addon-matomo",
+            "instance": instance,
+        }),
+        "FSBucket" => json!({
+            "organisation": "orga_xxxx",
+            "instance": { "region": "par", "plan": "s" },
+        }),
+        "Mailpace" => json!({
+            "organisation": "orga_xxxx",
+            "instance": { "region": "par", "plan": "starter" },
+        }),
+        "Jenkins" => json!({
+            "organisation": "orga_xxxx",
+            "instance": { "region": "par", "plan": "m" },
+        }),
+        "Gravitee" => json!({
+            "organisation": "orga_xxxx",
+            "instance": { "region": "par", "plan": "m" },
+        }),
+        _ => json!({}),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the storage version of the custom resource definition
+pub fn version(crd: &CustomResourceDefinition) -> String {
+    crd.spec
+        .versions
+        .iter()
+        .find(|version| version.storage)
+        .or_else(|| crd.spec.versions.first())
+        .map(|version| version.name.to_owned())
+        .unwrap_or_default()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the descriptors of the top-level fields of the given property
+/// (`spec` or `status`) of the custom resource definition
+pub fn descriptors(crd: &CustomResourceDefinition, property: &str) -> Vec<Value> {
+    let version = version(crd);
+    let properties = crd
+        .spec
+        .versions
+        .iter()
+        .find(|v| v.name == version)
+        .and_then(|v| v.schema.as_ref())
+        .and_then(|schema| schema.open_api_v3_schema.as_ref())
+        .and_then(|schema| schema.properties.as_ref())
+        .and_then(|properties| properties.get(property))
+        .and_then(|schema| schema.properties.to_owned())
+        .unwrap_or_default();
+
+    properties
+        .into_iter()
+        .map(|(path, schema)| {
+            json!({
+                "description": schema.description.unwrap_or_else(|| path.to_owned()),
+                "displayName": path,
+                "path": path,
+            })
+        })
+        .collect()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the entry of the custom resource definition in the owned custom
+/// resource definitions of the cluster service version
+pub fn owned(crd: &CustomResourceDefinition) -> Value {
+    let kind = &crd.spec.names.kind;
+    let (description, secrets) = description(kind);
+    let resources = if secrets {
+        json!([{ "kind": "Secret", "version": "v1" }])
+    } else {
+        json!([])
+    };
+
+    json!({
+        "kind": kind,
+        "name": crd.metadata.name,
+        "version": version(crd),
+        "displayName": kind,
+        "description": description,
+        "resources": resources,
+        "specDescriptors": descriptors(crd, "spec"),
+        "statusDescriptors": descriptors(crd, "status"),
+    })
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the examples of custom resources displayed on OperatorHub
+pub fn examples(crds: &[CustomResourceDefinition]) -> Value {
+    crds.iter()
+        .map(|crd| {
+            let kind = &crd.spec.names.kind;
+
+            json!({
+                "apiVersion": format!("{}/{}", crd.spec.group, version(crd)),
+                "kind": kind,
+                "metadata": {
+                    "namespace": "default",
+                    "name": crd.spec.names.singular,
+                },
+                "spec": example(kind),
+            })
+        })
+        .collect()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the specification of the deployment of the operator
+pub fn deployment(image: &str) -> Value {
+    let resources = json!({ "memory": "64M", "cpu": "100m", "ephemeral-storage": "128Mi" });
+
+    json!({
+        "replicas": 1,
+        "selector": { "matchLabels": { "app": PACKAGE } },
+        "template": {
+            "metadata": { "labels": { "app": PACKAGE } },
+            "spec": {
+                "serviceAccountName": SERVICE_ACCOUNT,
+                "containers": [{
+                    "image": image,
+                    "imagePullPolicy": "Always",
+                    "name": PACKAGE,
+                    "command": ["/usr/local/bin/clever-operator"],
+                    "args": ["-vvvvvvv"],
+                    "resources": { "requests": resources, "limits": resources },
+                    "ports": [{
+                        "containerPort": 8000,
+                        "protocol": "TCP",
+                        "name": "observability",
+                    }],
+                    "livenessProbe": {
+                        "failureThreshold": 3,
+                        "httpGet": { "path": "/livez", "port": "observability", "scheme": "HTTP" },
+                        "periodSeconds": 30,
+                        "successThreshold": 1,
+                        "timeoutSeconds": 1,
+                    },
+                    "readinessProbe": {
+                        "failureThreshold": 3,
+                        "httpGet": { "path": "/readyz", "port": "observability", "scheme": "HTTP" },
+                        "periodSeconds": 5,
+                        "successThreshold": 1,
+                        "timeoutSeconds": 1,
+                    },
+                    "securityContext": {
+                        "readOnlyRootFilesystem": true,
+                        "allowPrivilegeEscalation": false,
+                        "runAsNonRoot": true,
+                        "runAsGroup": 25000,
+                        "runAsUser": 20000,
+                    },
+                }],
+                "restartPolicy": "Always",
+                "terminationGracePeriodSeconds": 30,
+            },
+        },
+    })
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the cluster service version of the operator
+pub fn cluster_service_version(
+    image: &str,
+    crds: &[CustomResourceDefinition],
+) -> Result<Value, OlmError> {
+    let version = env!("CARGO_PKG_VERSION");
+    let examples =
+        serde_json::to_string_pretty(&examples(crds)).map_err(OlmError::SerializeExamples)?;
+
+    Ok(json!({
+        "apiVersion": "operators.coreos.com/v1alpha1",
+        "kind": "ClusterServiceVersion",
+        "metadata": {
+            "name": format!("cleveroperator.v{}", version),
+            "namespace": NAMESPACE,
+            "annotations": {
+                "capabilities": "Full Lifecycle",
+                "categories": "Developer Tools, Integration & Delivery, Cloud Provider",
+                "description": env!("CARGO_PKG_DESCRIPTION"),
+                "certified": "false",
+                "containerImage": image,
+                "repository": env!("CARGO_PKG_REPOSITORY"),
+                "alm-examples": examples,
+            },
+        },
+        "spec": {
+            "displayName": PACKAGE,
+            "description": env!("CARGO_PKG_DESCRIPTION"),
+            "minKubeVersion": "v1.24.0",
+            "keywords": [
+                "clever-cloud",
+                "postgresql",
+                "redis",
+                "mysql",
+                "mongodb",
+                "pulsar",
+                "elasticsearch",
+                "cellar",
+            ],
+            "links": [
+                { "name": "GitHub", "url": env!("CARGO_PKG_REPOSITORY") },
+                { "name": "Clever Cloud", "url": "https://www.clever-cloud.com" },
+            ],
+            "maintainers": [
+                { "name": "Florentin Dubois", "email": "florentin.dubois@clever-cloud.com" },
+            ],
+            "maturity": "alpha",
+            "provider": { "name": "Clever Cloud" },
+            "version": version,
+            "relatedImages": [{ "name": PACKAGE, "image": image }],
+            "customresourcedefinitions": {
+                "owned": crds.iter().map(owned).collect::<Vec<_>>(),
+            },
+            "installModes": [
+                { "supported": true, "type": "OwnNamespace" },
+                { "supported": true, "type": "SingleNamespace" },
+                { "supported": true, "type": "MultiNamespace" },
+                { "supported": true, "type": "AllNamespaces" },
+            ],
+            "install": {
+                "strategy": "deployment",
+                "spec": {
+                    "clusterPermissions": [{
+                        "serviceAccountName": SERVICE_ACCOUNT,
+                        "rules": rbac::rules(&rbac::reconcilers())
+                            .into_iter()
+                            .chain(rbac::to_rules(rbac::LEADER_ELECTION))
+                            .collect::<Vec<_>>(),
+                    }],
+                    "deployments": [{ "name": PACKAGE, "spec": deployment(image) }],
+                },
+            },
+        },
+    }))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the annotations of the bundle
+pub fn annotations(channel: &str) -> Value {
+    json!({
+        "operators.operatorframework.io.bundle.mediatype.v1": "registry+v1",
+        "operators.operatorframework.io.bundle.manifests.v1": "manifests/",
+        "operators.operatorframework.io.bundle.metadata.v1": "metadata/",
+        "operators.operatorframework.io.bundle.package.v1": PACKAGE,
+        "operators.operatorframework.io.bundle.channels.v1": channel,
+        "operators.operatorframework.io.bundle.channel.default.v1": channel,
+    })
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the dockerfile building the image of the bundle
+pub fn dockerfile(channel: &str) -> String {
+    let labels: String = annotations(channel)
+        .as_object()
+        .map(|annotations| {
+            annotations
+                .iter()
+                .map(|(key, value)| format!("LABEL {}={}\n", key, value.as_str().unwrap_or("")))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    format!(
+        "FROM scratch\n\n{}\nADD manifests /manifests/\nADD metadata /metadata/\n",
+        labels
+    )
+}
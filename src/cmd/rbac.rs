@@ -0,0 +1,183 @@
+//! # Role-based access control module
+//!
+//! This module provides the rbac command line interface function implementation
+//! which generates the minimal roles and bindings of the operator from the
+//! permissions declared by its reconcilers.
+
+use std::{path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use clap::Args;
+use k8s_openapi::{
+    api::rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding, RoleRef, Subject},
+    apimachinery::pkg::apis::meta::v1::ObjectMeta,
+};
+use serde::Serialize;
+
+use crate::{
+    cmd::Executor,
+    svc::{cfg::Configuration, k8s::rbac},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const CLUSTER_ROLE: &str = "system:clever-operator";
+pub const ROLE: &str = "clever-operator";
+pub const LEADER_ELECTION_ROLE: &str = "clever-operator-leader-election";
+pub const CUSTOM_RESOURCE_DEFINITIONS_ROLE: &str = "system:clever-operator-crds";
+
+// -----------------------------------------------------------------------------
+// RbacError enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum RbacError {
+    #[error("failed to serialize manifest, {0}")]
+    Serialize(serde_yaml::Error),
+}
+
+// -----------------------------------------------------------------------------
+// Rbac structure
+
+#[derive(Args, Clone, Debug)]
+pub struct Rbac {
+    /// Namespace watched by the operator, roles are generated for each of them
+    /// instead of a cluster role. Defaults to the watched namespaces of the
+    /// configuration
+    #[clap(short = 'n', long = "namespace")]
+    pub namespaces: Vec<String>,
+    /// Namespace in which the operator is deployed
+    #[clap(long = "operator-namespace", default_value = "clever-operator-system")]
+    pub operator_namespace: String,
+    /// Service account of the operator
+    #[clap(long = "service-account", default_value = "clever-operator")]
+    pub service_account: String,
+}
+
+#[async_trait]
+impl Executor for Rbac {
+    type Error = RbacError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+    async fn execute(
+        &self,
+        _kubeconfig: Option<PathBuf>,
+        config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
+        let namespaces = if self.namespaces.is_empty() {
+            &config.watch.namespaces
+        } else {
+            &self.namespaces
+        };
+
+        let rules = rbac::rules(&rbac::reconcilers());
+        let subject = Subject {
+            kind: "ServiceAccount".to_string(),
+            name: self.service_account.to_owned(),
+            namespace: Some(self.operator_namespace.to_owned()),
+            ..Default::default()
+        };
+
+        // custom resource definitions are cluster-scoped, applying them requires
+        // a cluster role even if the operator only watches some namespaces
+        let manage = config.operator.custom_resource_definitions.manage;
+
+        if namespaces.is_empty() {
+            let rules = rules
+                .into_iter()
+                .chain(rbac::to_rules(rbac::LEADER_ELECTION))
+                .chain(if manage {
+                    rbac::to_rules(rbac::CUSTOM_RESOURCE_DEFINITIONS)
+                } else {
+                    vec![]
+                })
+                .collect();
+
+            print(&ClusterRole {
+                metadata: metadata(CLUSTER_ROLE, None),
+                rules: Some(rules),
+                ..Default::default()
+            })?;
+
+            return print(&ClusterRoleBinding {
+                metadata: metadata(CLUSTER_ROLE, None),
+                role_ref: role_ref("ClusterRole", CLUSTER_ROLE),
+                subjects: Some(vec![subject]),
+            });
+        }
+
+        if manage {
+            print(&ClusterRole {
+                metadata: metadata(CUSTOM_RESOURCE_DEFINITIONS_ROLE, None),
+                rules: Some(rbac::to_rules(rbac::CUSTOM_RESOURCE_DEFINITIONS)),
+                ..Default::default()
+            })?;
+
+            print(&ClusterRoleBinding {
+                metadata: metadata(CUSTOM_RESOURCE_DEFINITIONS_ROLE, None),
+                role_ref: role_ref("ClusterRole", CUSTOM_RESOURCE_DEFINITIONS_ROLE),
+                subjects: Some(vec![subject.to_owned()]),
+            })?;
+        }
+
+        let roles = namespaces
+            .iter()
+            .map(|namespace| (ROLE, namespace, rules.to_owned()))
+            .chain([(
+                LEADER_ELECTION_ROLE,
+                &self.operator_namespace,
+                rbac::to_rules(rbac::LEADER_ELECTION),
+            )]);
+
+        for (name, namespace, rules) in roles {
+            print(&Role {
+                metadata: metadata(name, Some(namespace.as_str())),
+                rules: Some(rules),
+            })?;
+
+            print(&RoleBinding {
+                metadata: metadata(name, Some(namespace.as_str())),
+                role_ref: role_ref("Role", name),
+                subjects: Some(vec![subject.to_owned()]),
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+pub fn metadata(name: &str, namespace: Option<&str>) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(name.to_string()),
+        namespace: namespace.map(String::from),
+        labels: Some([("app".to_string(), ROLE.to_string())].into()),
+        ..Default::default()
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+pub fn role_ref(kind: &str, name: &str) -> RoleRef {
+    RoleRef {
+        api_group: "rbac.authorization.k8s.io".to_string(),
+        kind: kind.to_string(),
+        name: name.to_string(),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(obj)))]
+/// print the object in yaml, prefixed by a document separator
+pub fn print<T>(obj: &T) -> Result<(), RbacError>
+where
+    T: Serialize,
+{
+    print!(
+        "---\n{}",
+        serde_yaml::to_string(obj).map_err(RbacError::Serialize)?
+    );
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! # Rotate module
+//!
+//! This module provides the rotate-credentials command line interface function
+//! implementation which requests the operator to rotate the credentials of a
+//! custom resource and to propagate them to the deployments consuming them.
+
+use std::{fmt::Debug, path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use clap::Args;
+use k8s_openapi::NamespaceResourceScope;
+use kube::{CustomResourceExt, Resource, ResourceExt};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::info;
+
+use crate::{
+    cmd::{crd::CustomResource, Executor},
+    svc::{
+        cfg::Configuration,
+        crd::{
+            addon::Addon, backup::Backup, cellar_bucket::CellarBucket,
+            config_provider::ConfigProvider, elasticsearch::ElasticSearch, fs_bucket::FSBucket,
+            gravitee::Gravitee, jenkins::Jenkins, mailpace::Mailpace, mongodb::MongoDb,
+            mysql::MySql, postgresql::PostgreSql, pulsar::Pulsar, redis::Redis, runtime::Runtime,
+        },
+        k8s::{client, resource, rotation::ROTATE_ANNOTATION},
+    },
+};
+
+// -----------------------------------------------------------------------------
+// RotateError enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum RotateError {
+    #[error("failed to create kubernetes client, {0}")]
+    Client(client::Error),
+    #[error("failed to execute request on kubernetes api, {0}")]
+    KubeClient(kube::Error),
+    #[error("failed to compute diff between the original and modified object, {0}")]
+    Diff(serde_json::Error),
+    #[error("could not find custom resource '{0}/{1}'")]
+    NotFound(String, String),
+    #[error("credentials rotation is not supported for custom resources of kind '{0}'")]
+    Unsupported(String),
+}
+
+impl From<kube::Error> for RotateError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: kube::Error) -> Self {
+        Self::KubeClient(err)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// RotateCredentials structure
+
+#[derive(Args, Clone, Debug)]
+pub struct RotateCredentials {
+    /// Kind of the custom resource whose credentials are rotated
+    #[clap(long = "kind")]
+    pub kind: CustomResource,
+    /// Namespace of the custom resource
+    #[clap(short = 'n', long = "namespace")]
+    pub namespace: String,
+    /// Name of the custom resource
+    #[clap(long = "name")]
+    pub name: String,
+}
+
+#[async_trait]
+impl Executor for RotateCredentials {
+    type Error = RotateError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+    async fn execute(
+        &self,
+        kubeconfig: Option<PathBuf>,
+        config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
+        let kube = client::try_new(kubeconfig, &config.proxy)
+            .await
+            .map_err(RotateError::Client)?;
+
+        match self.kind {
+            CustomResource::PostgreSql => request::<PostgreSql>(kube, self).await,
+            CustomResource::Redis => request::<Redis>(kube, self).await,
+            CustomResource::MySql => request::<MySql>(kube, self).await,
+            CustomResource::MongoDb => request::<MongoDb>(kube, self).await,
+            CustomResource::Pulsar => request::<Pulsar>(kube, self).await,
+            CustomResource::ConfigProvider => request::<ConfigProvider>(kube, self).await,
+            CustomResource::ElasticSearch => request::<ElasticSearch>(kube, self).await,
+            CustomResource::CellarBucket => request::<CellarBucket>(kube, self).await,
+            CustomResource::Runtime => {
+                Err(RotateError::Unsupported(Runtime::kind(&()).to_string()))
+            }
+            CustomResource::Backup => Err(RotateError::Unsupported(Backup::kind(&()).to_string())),
+            CustomResource::Addon => request::<Addon>(kube, self).await,
+            CustomResource::FsBucket => request::<FSBucket>(kube, self).await,
+            CustomResource::Mailpace => request::<Mailpace>(kube, self).await,
+            CustomResource::Jenkins => request::<Jenkins>(kube, self).await,
+            CustomResource::Gravitee => request::<Gravitee>(kube, self).await,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(kube)))]
+/// annotate the custom resource to request the rotation of its credentials,
+/// the operator removes the annotation once credentials are propagated
+pub async fn request<T>(kube: kube::Client, args: &RotateCredentials) -> Result<(), RotateError>
+where
+    T: Resource<Scope = NamespaceResourceScope>
+        + ResourceExt
+        + CustomResourceExt
+        + DeserializeOwned
+        + Serialize
+        + Clone
+        + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let (namespace, name) = (&args.namespace, &args.name);
+    let obj = resource::get::<T>(kube.to_owned(), namespace, name)
+        .await?
+        .ok_or_else(|| RotateError::NotFound(namespace.to_owned(), name.to_owned()))?;
+
+    let mut modified = obj.to_owned();
+    modified
+        .annotations_mut()
+        .insert(ROTATE_ANNOTATION.to_string(), "true".to_string());
+
+    info!(
+        namespace = namespace,
+        name = name,
+        "Request rotation of credentials of custom resource",
+    );
+
+    let patch = resource::diff(&obj, &modified).map_err(RotateError::Diff)?;
+    resource::patch(kube, &obj, patch).await?;
+
+    println!(
+        "Rotation of credentials of {} '{}/{}' has been requested",
+        T::kind(&Default::default()),
+        namespace,
+        name
+    );
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+//! # Status module
+//!
+//! This module provides the status command line interface function
+//! implementation which retrieves the debug state of a running operator.
+
+use std::{path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use clap::Args;
+use hyper::{body, Client, StatusCode, Uri};
+
+use crate::{
+    cmd::Executor,
+    svc::{cfg::Configuration, telemetry::State},
+};
+
+// -----------------------------------------------------------------------------
+// StatusError enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum StatusError {
+    #[error("failed to parse endpoint '{0}', {1}")]
+    Endpoint(String, hyper::http::uri::InvalidUri),
+    #[error("failed to request debug state of the operator, {0}")]
+    Request(hyper::Error),
+    #[error("failed to request debug state of the operator, got status code '{0}'")]
+    StatusCode(StatusCode),
+    #[error("failed to deserialize debug state of the operator, {0}")]
+    Deserialize(serde_json::Error),
+    #[error("failed to serialize debug state of the operator, {0}")]
+    Serialize(serde_json::Error),
+}
+
+// -----------------------------------------------------------------------------
+// Status structure
+
+#[derive(Args, Clone, Debug)]
+pub struct Status {
+    /// Endpoint of the operator's http server, defaults to the listen address
+    /// of the configuration
+    #[clap(long = "endpoint")]
+    pub endpoint: Option<String>,
+    /// Only display the recently skipped reconciliations and their reasons
+    #[clap(long = "skipped")]
+    pub skipped: bool,
+}
+
+#[async_trait]
+impl Executor for Status {
+    type Error = StatusError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+    async fn execute(
+        &self,
+        _kubeconfig: Option<PathBuf>,
+        config: Arc<Configuration>,
+    ) -> Result<(), Self::Error> {
+        let endpoint = self.endpoint.to_owned().unwrap_or_else(|| {
+            format!(
+                "http://{}",
+                config.operator.listen.replace("0.0.0.0", "localhost")
+            )
+        });
+
+        let uri: Uri = format!("{}/debug/state", endpoint.trim_end_matches('/'))
+            .parse()
+            .map_err(|err| StatusError::Endpoint(endpoint.to_owned(), err))?;
+
+        let res = Client::new().get(uri).await.map_err(StatusError::Request)?;
+
+        if !res.status().is_success() {
+            return Err(StatusError::StatusCode(res.status()));
+        }
+
+        let buf = body::to_bytes(res.into_body())
+            .await
+            .map_err(StatusError::Request)?;
+
+        let state: State = serde_json::from_slice(&buf).map_err(StatusError::Deserialize)?;
+        if !self.skipped {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&state).map_err(StatusError::Serialize)?
+            );
+
+            return Ok(());
+        }
+
+        if state.skipped.is_empty() {
+            println!("No reconciliation has been skipped recently");
+            return Ok(());
+        }
+
+        for skip in &state.skipped {
+            println!(
+                "{}\t{}\t{}/{}\t{}\t{}",
+                skip.timestamp, skip.kind, skip.namespace, skip.name, skip.reason, skip.message
+            );
+        }
+
+        Ok(())
+    }
+}
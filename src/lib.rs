@@ -0,0 +1,11 @@
+//! # Clever operator
+//!
+//! This library expose helpers to interact with resources generated by the
+//! clever operator from sibling services
+
+#[cfg(feature = "consume")]
+pub mod consume;
+
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod svc;
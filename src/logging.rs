@@ -16,6 +16,8 @@ pub enum Error {
     InitializeRegistry(tracing_subscriber::util::TryInitError),
     #[error("failed to create a jaeger tracer, {0}")]
     CreateJaegerTracer(tracer::Error),
+    #[error("failed to create an otlp tracer, {0}")]
+    CreateOtlpTracer(tracer::Error),
 }
 
 // -----------------------------------------------------------------------------
@@ -24,9 +26,15 @@ pub enum Error {
 pub mod tracer {
     #[cfg(feature = "trace")]
     use opentelemetry::{
-        sdk::trace::{self, RandomIdGenerator, Sampler, Tracer},
+        sdk::{
+            trace::{self, RandomIdGenerator, Sampler, Tracer},
+            Resource,
+        },
         trace::TraceError,
+        KeyValue,
     };
+    #[cfg(feature = "trace")]
+    use opentelemetry_otlp::WithExportConfig;
 
     #[cfg(feature = "trace")]
     use crate::svc::cfg::Configuration;
@@ -39,6 +47,9 @@ pub mod tracer {
         #[cfg(feature = "trace")]
         #[error("failed to configure jaeger collector (agent), {0}")]
         ConfigureJaeger(TraceError),
+        #[cfg(feature = "trace")]
+        #[error("failed to configure otlp exporter, {0}")]
+        ConfigureOtlp(TraceError),
     }
 
     // -------------------------------------------------------------------------
@@ -73,11 +84,81 @@ pub mod tracer {
             .install_batch(opentelemetry::runtime::Tokio)
             .map_err(Error::ConfigureJaeger)
     }
+
+    #[cfg(feature = "trace")]
+    pub fn otlp(config: &Configuration) -> Result<Tracer, Error> {
+        let otlp = &config.telemetry.otlp;
+        let exporter = opentelemetry_otlp::new_exporter()
+            .http()
+            .with_endpoint(otlp.endpoint.to_string())
+            .with_timeout(std::time::Duration::from_secs(10))
+            .with_headers(
+                otlp.headers
+                    .iter()
+                    .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                    .collect(),
+            );
+
+        opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(exporter)
+            .with_trace_config(
+                trace::config()
+                    .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                        otlp.sample_ratio,
+                    ))))
+                    .with_id_generator(RandomIdGenerator::default())
+                    .with_max_attributes_per_span(16)
+                    .with_max_events_per_span(16)
+                    .with_resource(Resource::new(vec![
+                        KeyValue::new("service.name", env!("CARGO_PKG_NAME")),
+                        KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+                    ])),
+            )
+            .install_batch(opentelemetry::runtime::Tokio)
+            .map_err(Error::ConfigureOtlp)
+    }
 }
 
 // -----------------------------------------------------------------------------
 // helpers
 
+#[cfg(feature = "trace")]
+/// returns the tracer exporting spans to the configured collector, the otlp
+/// exporter takes precedence over the jaeger one as only one tracer provider
+/// could be installed globally
+pub fn tracer(config: &Configuration) -> Result<Option<opentelemetry::sdk::trace::Tracer>, Error> {
+    if !config.telemetry.otlp.endpoint.is_empty() {
+        tracing::debug!(
+            endpoint = &config.telemetry.otlp.endpoint,
+            "Configure otlp integration for tracing crate"
+        );
+
+        opentelemetry::global::set_text_map_propagator(
+            opentelemetry::sdk::propagation::TraceContextPropagator::new(),
+        );
+
+        return tracer::otlp(config)
+            .map(Some)
+            .map_err(Error::CreateOtlpTracer);
+    }
+
+    if !config.jaeger.endpoint.is_empty() {
+        tracing::debug!(
+            endpoint = &config.jaeger.endpoint,
+            "Configure jaeger integration for tracing crate"
+        );
+
+        opentelemetry::global::set_text_map_propagator(opentelemetry_jaeger::Propagator::new());
+
+        return tracer::jaeger(config)
+            .map(Some)
+            .map_err(Error::CreateJaegerTracer);
+    }
+
+    Ok(None)
+}
+
 pub const fn level(verbosity: usize) -> Level {
     match verbosity {
         0 => Level::ERROR,
@@ -131,16 +212,9 @@ pub fn initialize(config: &Configuration, verbosity: usize) -> Result<(), Error>
             .with_target(true),
     );
 
-    if !config.jaeger.endpoint.is_empty() {
-        tracing::debug!(
-            endpoint = &config.jaeger.endpoint,
-            "Configure jaeger integration for tracing crate"
-        );
-
-        opentelemetry::global::set_text_map_propagator(opentelemetry_jaeger::Propagator::new());
-
+    if let Some(tracer) = tracer(config)? {
         let layer = tracing_opentelemetry::layer()
-            .with_tracer(tracer::jaeger(config).map_err(Error::CreateJaegerTracer)?)
+            .with_tracer(tracer)
             .with_location(true)
             .with_threads(true)
             .with_tracked_inactivity(true)
@@ -168,16 +242,9 @@ pub fn initialize(config: &Configuration, verbosity: usize) -> Result<(), Error>
                 .with_target(true),
         );
 
-    if !config.jaeger.endpoint.is_empty() {
-        tracing::debug!(
-            endpoint = &config.jaeger.endpoint,
-            "Configure jaeger integration for tracing crate"
-        );
-
-        opentelemetry::global::set_text_map_propagator(opentelemetry_jaeger::Propagator::new());
-
+    if let Some(tracer) = tracer(config)? {
         let layer = tracing_opentelemetry::layer()
-            .with_tracer(tracer::jaeger(config).map_err(Error::CreateJaegerTracer)?)
+            .with_tracer(tracer)
             .with_location(true)
             .with_threads(true)
             .with_tracked_inactivity(true)
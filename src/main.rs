@@ -9,13 +9,37 @@ use tracing::{error, info};
 
 use crate::{
     cmd::{daemon, Args, Executor},
-    svc::cfg::Configuration,
+    svc::{
+        audit,
+        cfg::Configuration,
+        clevercloud::client,
+        crypto,
+        k8s::{naming, resource},
+        notification,
+    },
 };
 
 pub mod cmd;
 pub mod logging;
 pub mod svc;
 
+pub use clever_operator::consume;
+
+#[cfg(feature = "bench")]
+#[global_allocator]
+static ALLOCATOR: svc::bench::CountingAllocator = svc::bench::CountingAllocator;
+
+#[cfg(all(feature = "debug", not(feature = "bench")))]
+#[global_allocator]
+static ALLOCATOR: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+/// build jemalloc with heap profiling, which stays inactive until the
+/// profiling endpoints are enabled
+#[cfg(all(feature = "debug", not(feature = "bench")))]
+#[allow(non_upper_case_globals)]
+#[export_name = "_rjem_malloc_conf"]
+pub static malloc_conf: &[u8] = b"prof:true,prof_active:false,lg_prof_sample:19\0";
+
 // -----------------------------------------------------------------------------
 // Error enumeration
 
@@ -27,6 +51,12 @@ pub enum Error {
     Logging(logging::Error),
     #[error("failed to load configuration, {0}")]
     Configuration(svc::cfg::Error),
+    #[error("failed to configure audit, {0}")]
+    Audit(svc::audit::Error),
+    #[error("failed to configure notifications, {0}")]
+    Notification(svc::notification::Error),
+    #[error("failed to configure crypto backend, {0}")]
+    Crypto(svc::crypto::Error),
     #[error("failed to set subscriber, {0}")]
     Subscriber(tracing::subscriber::SetGlobalDefaultError),
     #[cfg(feature = "trace")]
@@ -55,6 +85,24 @@ impl From<svc::cfg::Error> for Error {
     }
 }
 
+impl From<svc::audit::Error> for Error {
+    fn from(err: svc::audit::Error) -> Self {
+        Self::Audit(err)
+    }
+}
+
+impl From<svc::crypto::Error> for Error {
+    fn from(err: svc::crypto::Error) -> Self {
+        Self::Crypto(err)
+    }
+}
+
+impl From<svc::notification::Error> for Error {
+    fn from(err: svc::notification::Error) -> Self {
+        Self::Notification(err)
+    }
+}
+
 impl From<tracing::subscriber::SetGlobalDefaultError> for Error {
     fn from(err: tracing::subscriber::SetGlobalDefaultError) -> Self {
         Self::Subscriber(err)
@@ -74,13 +122,24 @@ impl From<opentelemetry::trace::TraceError> for Error {
 #[paw::main]
 #[tokio::main]
 pub(crate) async fn main(args: Args) -> Result<(), Error> {
-    let config = Arc::new(match &args.config {
+    let mut config = match &args.config {
         Some(path) => Configuration::try_from(path.to_owned())?,
         None => Configuration::try_default()?,
-    });
+    };
+
+    if let Some(secret) = &args.credentials_secret {
+        config.operator.credentials_secret = Some(secret.to_owned());
+    }
+
+    if args.manage_crds {
+        config.operator.custom_resource_definitions.manage = true;
+    }
+
+    let config = Arc::new(config);
 
     config.help();
     logging::initialize(&config, args.verbosity as usize)?;
+    crypto::configure()?;
     if args.check {
         println!("{} configuration is healthy!", env!("CARGO_PKG_NAME"));
         return Ok(());
@@ -98,14 +157,24 @@ pub(crate) async fn main(args: Args) -> Result<(), Error> {
             Some(sentry::init(sentry::ClientOptions {
                 dsn: Some(dsn.parse().map_err(Error::ParseSentryDsn)?),
                 release: sentry::release_name!(),
+                environment: config.sentry.environment.to_owned().map(Into::into),
+                traces_sample_rate: config.sentry.traces_sample_rate,
                 ..Default::default()
             }))
         }
     };
 
+    resource::configure(&config.operator.server_side_apply);
+    naming::configure(&config.operator.naming);
+    client::configure(&config.api.retry);
+    audit::configure(&config.audit)?;
+    notification::configure(&config)?;
+    #[cfg(feature = "debug")]
+    svc::telemetry::profiling::configure(&config.operator.pprof);
+
     let result = match &args.command {
-        Some(cmd) => cmd.execute(config).await,
-        None => daemon(args.kubeconfig, config).await,
+        Some(cmd) => cmd.execute(args.kubeconfig, config).await,
+        None => daemon(args.kubeconfig, args.config.to_owned(), config).await,
     }
     .map_err(Error::Command);
 
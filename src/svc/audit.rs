@@ -0,0 +1,210 @@
+//! # Audit module
+//!
+//! This module records the mutating operations performed by the operator on
+//! Clever Cloud and kubernetes, e.g. the creation of an addon or the
+//! upsertion of a secret, along with the custom resource on behalf of which
+//! they are performed and their outcome, for post-incident forensics. Entries
+//! are appended as json lines to a file or logged using the `audit` target.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::svc::{cfg, k8s::condition};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// target of the log events holding audit entries
+pub const TARGET: &str = "audit";
+
+// -----------------------------------------------------------------------------
+// State
+
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to open audit file '{0}', {1}")]
+    Open(PathBuf, io::Error),
+}
+
+// -----------------------------------------------------------------------------
+// Sink enumeration
+
+#[derive(Debug)]
+enum Sink {
+    Log,
+    File(File),
+}
+
+// -----------------------------------------------------------------------------
+// Outcome enumeration
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Outcome {
+    #[serde(rename = "success")]
+    Success,
+    #[serde(rename = "failure")]
+    Failure,
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Success => write!(f, "success"),
+            Self::Failure => write!(f, "failure"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Actor structure
+
+/// custom resource on behalf of which an operation is performed
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Actor {
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "namespace")]
+    pub namespace: String,
+    #[serde(rename = "name")]
+    pub name: String,
+}
+
+impl Actor {
+    pub fn new(kind: &str, namespace: &str, name: &str) -> Self {
+        Self {
+            kind: kind.to_string(),
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Entry structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Entry {
+    #[serde(rename = "timestamp")]
+    pub timestamp: String,
+    /// operation performed, e.g. `addon::create` or `secret::upsert`
+    #[serde(rename = "operation")]
+    pub operation: String,
+    #[serde(rename = "actor")]
+    pub actor: Actor,
+    #[serde(
+        rename = "organisation",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub organisation: Option<String>,
+    /// identifier or name of the object on which the operation is performed
+    #[serde(rename = "target")]
+    pub target: String,
+    #[serde(rename = "outcome")]
+    pub outcome: Outcome,
+    #[serde(rename = "error", default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// configure where audit entries are written, they are dropped if the audit
+/// is disabled
+pub fn configure(config: &cfg::Audit) -> Result<(), Error> {
+    let sink = match (&config.enabled, &config.path) {
+        (false, _) => None,
+        (true, None) => Some(Sink::Log),
+        (true, Some(path)) => Some(Sink::File(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|err| Error::Open(path.to_owned(), err))?,
+        )),
+    };
+
+    if let Ok(mut state) = SINK.lock() {
+        *state = sink;
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(result)))]
+/// record the outcome of the given operation, performed on the target on
+/// behalf of the actor, if the audit is enabled
+pub fn record<T, E>(
+    operation: &str,
+    actor: &Actor,
+    organisation: Option<&str>,
+    target: &str,
+    result: &Result<T, E>,
+) where
+    E: Display,
+{
+    let mut sink = match SINK.lock() {
+        Ok(sink) => sink,
+        Err(_) => return,
+    };
+
+    let sink = match sink.as_mut() {
+        Some(sink) => sink,
+        None => return,
+    };
+
+    let entry = Entry {
+        timestamp: condition::now(),
+        operation: operation.to_string(),
+        actor: actor.to_owned(),
+        organisation: organisation.map(String::from),
+        target: target.to_string(),
+        outcome: match result {
+            Ok(_) => Outcome::Success,
+            Err(_) => Outcome::Failure,
+        },
+        error: result.as_ref().err().map(ToString::to_string),
+    };
+
+    match sink {
+        Sink::Log => info!(
+            target: TARGET,
+            operation = &entry.operation,
+            kind = &entry.actor.kind,
+            namespace = &entry.actor.namespace,
+            name = &entry.actor.name,
+            organisation = entry.organisation.as_deref().unwrap_or_default(),
+            object = &entry.target,
+            outcome = entry.outcome.to_string(),
+            error = entry.error.as_deref().unwrap_or_default(),
+            "Record mutating operation",
+        ),
+        Sink::File(file) => {
+            let written = serde_json::to_string(&entry)
+                .map_err(io::Error::from)
+                .and_then(|line| writeln!(file, "{}", line));
+
+            if let Err(err) = written {
+                warn!(
+                    operation = &entry.operation,
+                    error = err.to_string(),
+                    "Could not write audit entry",
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! # Bench module
+//!
+//! This module provide a synthetic workload, which replays events of custom
+//! resources through the reconciliation loop against the in-memory kubernetes
+//! api server and the in-memory clever cloud api. It backs the hidden `bench`
+//! command and the criterion benchmarks, which guard the throughput of the
+//! reconciliation against performance regressions.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use serde_json::json;
+
+use crate::svc::{
+    cfg::Configuration,
+    clevercloud::{self, fake::FakeApi},
+    crd::addon::{self, Addon},
+    k8s::{fake::FakeApiServer, resource, Context, Reconciler},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const NAMESPACE: &str = "bench";
+pub const ORGANISATION: &str = "orga_bench";
+pub const PROVIDER: &str = "addon-bench";
+pub const PLAN: &str = "plan_bench";
+pub const REGION: &str = "par";
+
+// -----------------------------------------------------------------------------
+// State
+
+/// number of allocations made by the process, only counted once the
+/// [`CountingAllocator`] is the global allocator
+pub static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+// -----------------------------------------------------------------------------
+// CountingAllocator structure
+
+/// system allocator counting allocations, it is installed as the global
+/// allocator when the `bench` feature is enabled
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to create clever cloud client, {0}")]
+    CleverClient(clevercloud::client::Error),
+    #[error("failed to build synthetic custom resource, {0}")]
+    Serialize(serde_json::Error),
+    #[error("failed to interact with the in-memory api server, {0}")]
+    Kube(kube::Error),
+    #[error("failed to find custom resource '{0}/{1}'")]
+    NotFound(String, String),
+    #[error("failed to reconcile custom resource, {0}")]
+    Reconcile(addon::ReconcilerError),
+}
+
+// -----------------------------------------------------------------------------
+// Report structure
+
+#[derive(Clone, Debug)]
+pub struct Report {
+    /// number of replayed events
+    pub events: usize,
+    /// duration of the replay
+    pub elapsed: Duration,
+    /// number of allocations made during the replay, if they are counted
+    pub allocations: Option<u64>,
+}
+
+impl Report {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn reconciles_per_second(&self) -> f64 {
+        self.events as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn allocations_per_reconcile(&self) -> Option<f64> {
+        self.allocations
+            .map(|allocations| allocations as f64 / self.events.max(1) as f64)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Workload structure
+
+/// reconciliation context backed by the in-memory kubernetes api server and
+/// clever cloud api
+pub struct Workload {
+    pub ctx: Arc<Context>,
+    pub server: FakeApiServer,
+    pub api: Arc<FakeApi>,
+}
+
+impl Workload {
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+    pub fn try_new(config: Arc<Configuration>) -> Result<Self, Error> {
+        let server = FakeApiServer::default();
+        let api = Arc::new(FakeApi::default());
+
+        // the clever cloud client is never used, calls are served by the
+        // in-memory api
+        let client = clevercloud::client::try_new(&config.api, &config.proxy)
+            .map_err(Error::CleverClient)?;
+
+        let ctx = Context::new(server.client(), client, config).with_backend(api.to_owned());
+
+        Ok(Self {
+            ctx: Arc::new(ctx),
+            server,
+            api,
+        })
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    /// create the given number of synthetic custom resources
+    pub async fn seed(&self, resources: usize) -> Result<(), Error> {
+        for index in 0..resources {
+            resource::create(self.ctx.kube.to_owned(), &synthetic(index)?)
+                .await
+                .map_err(Error::Kube)?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    /// replay the given number of events over the given number of seeded
+    /// custom resources. The first event of each custom resource provisions
+    /// it, the following ones find it up to date
+    pub async fn replay(&self, resources: usize, events: usize) -> Result<Report, Error> {
+        let resources = resources.max(1);
+        let counted = cfg!(feature = "bench");
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let instant = Instant::now();
+
+        for event in 0..events {
+            let name = name(event % resources);
+            let obj: Addon = resource::get(self.ctx.kube.to_owned(), NAMESPACE, &name)
+                .await
+                .map_err(Error::Kube)?
+                .ok_or_else(|| Error::NotFound(NAMESPACE.to_string(), name.to_owned()))?;
+
+            <addon::Reconciler as Reconciler<Addon>>::reconcile(Arc::new(obj), self.ctx.to_owned())
+                .await
+                .map_err(Error::Reconcile)?;
+        }
+
+        Ok(Report {
+            events,
+            elapsed: instant.elapsed(),
+            allocations: counted.then(|| {
+                ALLOCATIONS
+                    .load(Ordering::Relaxed)
+                    .saturating_sub(allocations)
+            }),
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+/// returns the name of the synthetic custom resource at the given index
+fn name(index: usize) -> String {
+    format!("bench-{}", index)
+}
+
+/// returns the synthetic custom resource at the given index
+fn synthetic(index: usize) -> Result<Addon, Error> {
+    serde_json::from_value(json!({
+        "apiVersion": "api.clever-cloud.com/v1",
+        "kind": "Addon",
+        "metadata": {
+            "name": name(index),
+            "namespace": NAMESPACE,
+        },
+        "spec": {
+            "organisation": ORGANISATION,
+            "providerId": PROVIDER,
+            "instance": { "region": REGION, "plan": PLAN },
+        },
+    }))
+    .map_err(Error::Serialize)
+}
@@ -2,12 +2,16 @@
 //!
 //! This module provide utilities and helpers to interact with the configuration
 
+#[cfg(feature = "trace")]
+use std::collections::BTreeMap;
 use std::{
     convert::TryFrom,
     env::{self, VarError},
+    fmt::{self, Display, Formatter},
     path::PathBuf,
 };
 
+use base64::{engine::general_purpose::STANDARD as BASE64_ENGINE, Engine};
 use clevercloud_sdk::{oauth10a::Credentials, PUBLIC_ENDPOINT};
 use config::{Config, ConfigError, File};
 use serde::{Deserialize, Serialize};
@@ -17,6 +21,62 @@ use tracing::warn;
 // Constants
 
 pub const OPERATOR_LISTEN: &str = "0.0.0.0:8000";
+pub const UPDATE_CHECK_URL: &str =
+    "https://api.github.com/repos/CleverCloud/clever-operator/releases/latest";
+pub const UPDATE_CHECK_INTERVAL: u64 = 86400;
+pub const UPDATE_CHECK_TIMEOUT: u64 = 5;
+pub const SHUTDOWN_GRACE_PERIOD: u64 = 25;
+pub const HEALTH_PROBE_INTERVAL: u64 = 30;
+pub const HEALTH_STALL_TIMEOUT: u64 = 600;
+pub const SUPERVISION_MAX_RESTARTS: usize = 5;
+pub const SUPERVISION_WINDOW: u64 = 600;
+pub const API_TCP_KEEPALIVE: u64 = 60;
+pub const API_DNS_CACHE_TTL: u64 = 300;
+pub const API_PLAN_CACHE_TTL: u64 = 300;
+pub const API_CLIENT_POOL_TTL: u64 = 600;
+pub const API_RATE_LIMIT_REQUESTS_PER_SECOND: u32 = 10;
+pub const API_RATE_LIMIT_BURST: u32 = 20;
+pub const API_HTTP_CONNECT_TIMEOUT: u64 = 10;
+pub const API_HTTP_REQUEST_TIMEOUT: u64 = 30;
+pub const API_HTTP_CONNECT_RETRIES: u32 = 2;
+pub const API_HTTP_MAX_RESPONSE_SIZE: u64 = 16 * 1024 * 1024;
+pub const API_RETRY_MAX_RETRIES: u32 = 3;
+pub const API_RETRY_INITIAL_DELAY: u64 = 200;
+pub const API_RETRY_MAX_DELAY: u64 = 5_000;
+pub const RETRY_INITIAL_DELAY: u64 = 500;
+pub const RETRY_MAX_DELAY: u64 = 300_000;
+pub const LEADER_ELECTION_LEASE_NAME: &str = "clever-operator";
+pub const LEADER_ELECTION_LEASE_DURATION: u64 = 15;
+pub const LEADER_ELECTION_RENEW_DEADLINE: u64 = 10;
+pub const LEADER_ELECTION_RETRY_PERIOD: u64 = 2;
+pub const SERVER_SIDE_APPLY_FIELD_MANAGER: &str = "clever-operator";
+pub const WEBHOOK_LISTEN: &str = "0.0.0.0:8443";
+pub const WEBHOOK_CERTIFICATE: &str = "/etc/clever-operator/webhook/tls.crt";
+pub const WEBHOOK_KEY: &str = "/etc/clever-operator/webhook/tls.key";
+pub const WEBHOOK_DEFAULT_REGION: &str = "par";
+pub const WEBHOOK_SERVICE: &str = "clever-operator-system/clever-operator-webhook";
+pub const RESTORE_DOWNLOAD_IMAGE: &str = "curlimages/curl:8.2.1";
+pub const RESTORE_POSTGRESQL_IMAGE: &str = "postgres:15";
+pub const RESTORE_MYSQL_IMAGE: &str = "mysql:8.0";
+pub const RESTORE_MONGODB_IMAGE: &str = "mongo:4.0";
+pub const OTLP_SAMPLE_RATIO: f64 = 1.0;
+pub const RECONCILIATION_CONCURRENCY: usize = 16;
+pub const RELOAD_INTERVAL: u64 = 30;
+pub const SECRET_CASCADE_DELETION: bool = true;
+pub const PPROF_MAX_DURATION: u64 = 120;
+pub const BILLING_REFRESH_INTERVAL: u64 = 3_600;
+pub const ADDON_METRICS_INTERVAL: u64 = 60;
+pub const ADDON_METRICS_FIELDS: [&str; 3] = ["cpu", "mem", "connections"];
+pub const GARBAGE_COLLECTION_INTERVAL: u64 = 3_600;
+pub const GARBAGE_COLLECTION_GRACE_PERIOD: u64 = 86_400;
+pub const ADDON_TAGS_PREFIX: &str = "kubernetes:";
+pub const CUSTOM_RESOURCE_DEFINITIONS_TIMEOUT: u64 = 60;
+pub const ADDON_HEALTH_INTERVAL: u64 = 300;
+pub const ADDON_HEALTH_TIMEOUT: u64 = 5;
+pub const NOTIFICATION_FAILURE_THRESHOLD: u32 = 5;
+pub const NOTIFICATION_TIMEOUT: u64 = 10;
+pub const NOTIFICATION_TEMPLATE: &str =
+    "[{event}] {kind} '{namespace}/{name}' (addon '{addon}'), {message}";
 
 // -----------------------------------------------------------------------------
 // Proxy structure
@@ -29,6 +89,1110 @@ pub struct Proxy {
     pub https: Option<String>,
     #[serde(rename = "no", default = "Default::default")]
     pub no: Vec<String>,
+    /// user of the basic authentication on the proxy
+    #[serde(rename = "username", default)]
+    pub username: Option<String>,
+    /// password of the basic authentication on the proxy
+    #[serde(rename = "password", default)]
+    pub password: Option<String>,
+    /// token of the bearer authentication on the proxy, it takes precedence
+    /// over the basic authentication
+    #[serde(rename = "token", default)]
+    pub token: Option<String>,
+}
+
+impl Proxy {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the given proxy, if it has an url, or else the one configured
+    /// using the conventional environment variables, if any
+    pub fn resolve(proxy: &Option<Self>) -> Option<Self> {
+        match proxy {
+            Some(proxy) if proxy.https.is_some() || proxy.http.is_some() => Some(proxy.to_owned()),
+            _ => Self::from_env(),
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the url of the proxy, the https one is preferred
+    pub fn url(&self) -> Option<String> {
+        self.https.to_owned().or_else(|| self.http.to_owned())
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the value of the `Proxy-Authorization` header, if the proxy
+    /// requires an authentication
+    pub fn authorization(&self) -> Option<String> {
+        if let Some(token) = &self.token {
+            return Some(format!("Bearer {}", token));
+        }
+
+        self.username.as_ref().map(|username| {
+            let credentials = format!(
+                "{}:{}",
+                username,
+                self.password.as_deref().unwrap_or_default()
+            );
+
+            format!("Basic {}", BASE64_ENGINE.encode(credentials))
+        })
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the proxy configured using the conventional environment
+    /// variables, if any
+    pub fn from_env() -> Option<Self> {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+        };
+
+        let http = var(&["HTTP_PROXY", "http_proxy"]);
+        let https = var(&["HTTPS_PROXY", "https_proxy"]);
+        if http.is_none() && https.is_none() {
+            return None;
+        }
+
+        let no = var(&["NO_PROXY", "no_proxy"])
+            .map(|no| {
+                no.split(',')
+                    .map(str::trim)
+                    .filter(|host| !host.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(Self {
+            http,
+            https,
+            no,
+            username: None,
+            password: None,
+            token: None,
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Secret structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Secret {
+    /// glob patterns of keys that should never be written in kubernetes
+    /// secrets, combined with the ones of the custom resource
+    #[serde(rename = "excludeKeys", alias = "exclude-keys", default)]
+    pub exclude_keys: Vec<String>,
+    /// let kubernetes garbage collect the generated secrets along their custom
+    /// resource, otherwise they are released and kept whatever the deletion
+    /// policy of the custom resource
+    #[serde(
+        rename = "cascadeDeletion",
+        alias = "cascade-deletion",
+        default = "Secret::default_cascade_deletion"
+    )]
+    pub cascade_deletion: bool,
+}
+
+impl Default for Secret {
+    fn default() -> Self {
+        Self {
+            exclude_keys: vec![],
+            cascade_deletion: Self::default_cascade_deletion(),
+        }
+    }
+}
+
+impl Secret {
+    fn default_cascade_deletion() -> bool {
+        SECRET_CASCADE_DELETION
+    }
+}
+
+// -----------------------------------------------------------------------------
+// UpdateCheck structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct UpdateCheck {
+    /// periodically check if a newer release of the operator is available
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// url of the version manifest of the latest release
+    #[serde(rename = "url", default = "UpdateCheck::default_url")]
+    pub url: String,
+    /// interval between two checks, in seconds
+    #[serde(rename = "interval", default = "UpdateCheck::default_interval")]
+    pub interval: u64,
+    /// timeout of the request retrieving the version manifest, in seconds
+    #[serde(rename = "timeout", default = "UpdateCheck::default_timeout")]
+    pub timeout: u64,
+}
+
+impl Default for UpdateCheck {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: Self::default_url(),
+            interval: Self::default_interval(),
+            timeout: Self::default_timeout(),
+        }
+    }
+}
+
+impl UpdateCheck {
+    fn default_url() -> String {
+        UPDATE_CHECK_URL.to_string()
+    }
+
+    fn default_interval() -> u64 {
+        UPDATE_CHECK_INTERVAL
+    }
+
+    fn default_timeout() -> u64 {
+        UPDATE_CHECK_TIMEOUT
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Shutdown structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Shutdown {
+    /// maximum duration to wait for in-flight reconciliation on shutdown, in
+    /// seconds
+    #[serde(
+        rename = "gracePeriod",
+        alias = "grace-period",
+        default = "Shutdown::default_grace_period"
+    )]
+    pub grace_period: u64,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            grace_period: Self::default_grace_period(),
+        }
+    }
+}
+
+impl Shutdown {
+    fn default_grace_period() -> u64 {
+        SHUTDOWN_GRACE_PERIOD
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Health structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Health {
+    /// interval between two checks of the reachability of the clever cloud api
+    /// and two heartbeats of the watchers, in seconds
+    #[serde(
+        rename = "probeInterval",
+        alias = "probe-interval",
+        default = "Health::default_probe_interval"
+    )]
+    pub probe_interval: u64,
+    /// duration after which a watcher without heartbeat is considered as
+    /// stalled and the liveness probe fails, in seconds
+    #[serde(
+        rename = "stallTimeout",
+        alias = "stall-timeout",
+        default = "Health::default_stall_timeout"
+    )]
+    pub stall_timeout: u64,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            probe_interval: Self::default_probe_interval(),
+            stall_timeout: Self::default_stall_timeout(),
+        }
+    }
+}
+
+impl Health {
+    fn default_probe_interval() -> u64 {
+        HEALTH_PROBE_INTERVAL
+    }
+
+    fn default_stall_timeout() -> u64 {
+        HEALTH_STALL_TIMEOUT
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Supervision structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Supervision {
+    /// maximum number of restarts of a failed controller within the window,
+    /// the operator exits once it is exceeded
+    #[serde(
+        rename = "maxRestarts",
+        alias = "max-restarts",
+        default = "Supervision::default_max_restarts"
+    )]
+    pub max_restarts: usize,
+    /// duration in which restarts of a controller are counted, in seconds
+    #[serde(rename = "window", default = "Supervision::default_window")]
+    pub window: u64,
+}
+
+impl Default for Supervision {
+    fn default() -> Self {
+        Self {
+            max_restarts: Self::default_max_restarts(),
+            window: Self::default_window(),
+        }
+    }
+}
+
+impl Supervision {
+    fn default_max_restarts() -> usize {
+        SUPERVISION_MAX_RESTARTS
+    }
+
+    fn default_window() -> u64 {
+        SUPERVISION_WINDOW
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Retry structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Retry {
+    /// delay before retrying a failed reconciliation for the first time, in
+    /// milliseconds. It doubles on each consecutive failure of the object
+    #[serde(
+        rename = "initialDelay",
+        alias = "initial-delay",
+        default = "Retry::default_initial_delay"
+    )]
+    pub initial_delay: u64,
+    /// maximum delay before retrying a failed reconciliation, in milliseconds
+    #[serde(
+        rename = "maxDelay",
+        alias = "max-delay",
+        default = "Retry::default_max_delay"
+    )]
+    pub max_delay: u64,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            initial_delay: Self::default_initial_delay(),
+            max_delay: Self::default_max_delay(),
+        }
+    }
+}
+
+impl Retry {
+    fn default_initial_delay() -> u64 {
+        RETRY_INITIAL_DELAY
+    }
+
+    fn default_max_delay() -> u64 {
+        RETRY_MAX_DELAY
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Restore structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Restore {
+    /// image of the container downloading the backup, it must provide `curl`
+    #[serde(
+        rename = "downloadImage",
+        alias = "download-image",
+        default = "Restore::default_download_image"
+    )]
+    pub download_image: String,
+    /// image of the container restoring backups of postgresql addons, it must
+    /// provide `pg_restore`
+    #[serde(
+        rename = "postgresqlImage",
+        alias = "postgresql-image",
+        default = "Restore::default_postgresql_image"
+    )]
+    pub postgresql_image: String,
+    /// image of the container restoring backups of mysql addons, it must
+    /// provide `mysql`
+    #[serde(
+        rename = "mysqlImage",
+        alias = "mysql-image",
+        default = "Restore::default_mysql_image"
+    )]
+    pub mysql_image: String,
+    /// image of the container restoring backups of mongodb addons, it must
+    /// provide `mongorestore`
+    #[serde(
+        rename = "mongodbImage",
+        alias = "mongodb-image",
+        default = "Restore::default_mongodb_image"
+    )]
+    pub mongodb_image: String,
+}
+
+impl Default for Restore {
+    fn default() -> Self {
+        Self {
+            download_image: Self::default_download_image(),
+            postgresql_image: Self::default_postgresql_image(),
+            mysql_image: Self::default_mysql_image(),
+            mongodb_image: Self::default_mongodb_image(),
+        }
+    }
+}
+
+impl Restore {
+    fn default_download_image() -> String {
+        RESTORE_DOWNLOAD_IMAGE.to_string()
+    }
+
+    fn default_postgresql_image() -> String {
+        RESTORE_POSTGRESQL_IMAGE.to_string()
+    }
+
+    fn default_mysql_image() -> String {
+        RESTORE_MYSQL_IMAGE.to_string()
+    }
+
+    fn default_mongodb_image() -> String {
+        RESTORE_MONGODB_IMAGE.to_string()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// LeaderElection structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct LeaderElection {
+    /// only start controllers once the leader lease is acquired, so multiple
+    /// replicas of the operator could run without double reconciliation
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// name of the lease
+    #[serde(
+        rename = "leaseName",
+        alias = "lease-name",
+        default = "LeaderElection::default_lease_name"
+    )]
+    pub lease_name: String,
+    /// namespace of the lease, defaults to the namespace of the operator
+    #[serde(rename = "namespace", default)]
+    pub namespace: Option<String>,
+    /// duration during which other replicas wait before taking over a lease
+    /// which is not renewed, in seconds
+    #[serde(
+        rename = "leaseDuration",
+        alias = "lease-duration",
+        default = "LeaderElection::default_lease_duration"
+    )]
+    pub lease_duration: u64,
+    /// duration during which the leader tries to renew the lease before
+    /// giving up the leadership, in seconds
+    #[serde(
+        rename = "renewDeadline",
+        alias = "renew-deadline",
+        default = "LeaderElection::default_renew_deadline"
+    )]
+    pub renew_deadline: u64,
+    /// interval between two attempts to acquire or renew the lease, in seconds
+    #[serde(
+        rename = "retryPeriod",
+        alias = "retry-period",
+        default = "LeaderElection::default_retry_period"
+    )]
+    pub retry_period: u64,
+}
+
+impl Default for LeaderElection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lease_name: Self::default_lease_name(),
+            namespace: None,
+            lease_duration: Self::default_lease_duration(),
+            renew_deadline: Self::default_renew_deadline(),
+            retry_period: Self::default_retry_period(),
+        }
+    }
+}
+
+impl LeaderElection {
+    fn default_lease_name() -> String {
+        LEADER_ELECTION_LEASE_NAME.to_string()
+    }
+
+    fn default_lease_duration() -> u64 {
+        LEADER_ELECTION_LEASE_DURATION
+    }
+
+    fn default_renew_deadline() -> u64 {
+        LEADER_ELECTION_RENEW_DEADLINE
+    }
+
+    fn default_retry_period() -> u64 {
+        LEADER_ELECTION_RETRY_PERIOD
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Reload structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Reload {
+    /// load the configuration again at a regular interval and rebuild the
+    /// clever cloud client, if the api connection or credentials changed
+    #[serde(rename = "enabled", default = "Reload::default_enabled")]
+    pub enabled: bool,
+    /// interval between two loads of the configuration, in seconds
+    #[serde(rename = "interval", default = "Reload::default_interval")]
+    pub interval: u64,
+}
+
+impl Default for Reload {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            interval: Self::default_interval(),
+        }
+    }
+}
+
+impl Reload {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_interval() -> u64 {
+        RELOAD_INTERVAL
+    }
+}
+
+// -----------------------------------------------------------------------------
+// ServerSideApply structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct ServerSideApply {
+    /// upsert kubernetes resources and their status using server-side apply
+    /// instead of json patches computed from a get request
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// name of the field manager owning the fields applied by the operator
+    #[serde(
+        rename = "fieldManager",
+        alias = "field-manager",
+        default = "ServerSideApply::default_field_manager"
+    )]
+    pub field_manager: String,
+}
+
+impl Default for ServerSideApply {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            field_manager: Self::default_field_manager(),
+        }
+    }
+}
+
+impl ServerSideApply {
+    fn default_field_manager() -> String {
+        SERVER_SIDE_APPLY_FIELD_MANAGER.to_string()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Webhook structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Webhook {
+    /// serve the mutating admission webhook, which resolves plans and fills
+    /// default values of custom resources at admission time
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// address on which the webhook listens for https requests
+    #[serde(rename = "listen", default = "Webhook::default_listen")]
+    pub listen: String,
+    /// path to the pem encoded certificate chain presented by the webhook
+    #[serde(rename = "certificate", default = "Webhook::default_certificate")]
+    pub certificate: PathBuf,
+    /// path to the pem encoded private key of the certificate
+    #[serde(rename = "key", default = "Webhook::default_key")]
+    pub key: PathBuf,
+    /// region set on custom resources which do not provide one
+    #[serde(
+        rename = "defaultRegion",
+        alias = "default-region",
+        default = "Webhook::default_region"
+    )]
+    pub default_region: String,
+    /// organisation set on custom resources which do not provide one
+    #[serde(
+        rename = "defaultOrganisation",
+        alias = "default-organisation",
+        default
+    )]
+    pub default_organisation: Option<String>,
+    /// service exposing the webhook, formatted as `namespace/name`, which is
+    /// referenced by the conversion of custom resource definitions. The
+    /// certificate injected by cert-manager has the same namespace and name
+    #[serde(rename = "service", default = "Webhook::default_service")]
+    pub service: String,
+}
+
+impl Default for Webhook {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: Self::default_listen(),
+            certificate: Self::default_certificate(),
+            key: Self::default_key(),
+            default_region: Self::default_region(),
+            default_organisation: None,
+            service: Self::default_service(),
+        }
+    }
+}
+
+impl Webhook {
+    fn default_listen() -> String {
+        WEBHOOK_LISTEN.to_string()
+    }
+
+    fn default_certificate() -> PathBuf {
+        PathBuf::from(WEBHOOK_CERTIFICATE)
+    }
+
+    fn default_key() -> PathBuf {
+        PathBuf::from(WEBHOOK_KEY)
+    }
+
+    fn default_region() -> String {
+        WEBHOOK_DEFAULT_REGION.to_string()
+    }
+
+    fn default_service() -> String {
+        WEBHOOK_SERVICE.to_string()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Watch structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Watch {
+    /// namespaces in which custom resources are watched, all namespaces are
+    /// watched if it is empty. Watching only some namespaces allows to run
+    /// the operator with a role instead of a cluster role
+    #[serde(rename = "namespaces", default)]
+    pub namespaces: Vec<String>,
+    /// label selector that custom resources have to match to be watched,
+    /// e.g. `team=a`
+    #[serde(rename = "labelSelector", alias = "label-selector", default)]
+    pub label_selector: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Reconciliation structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Reconciliation {
+    /// maximum number of custom resources of a kind reconciled in parallel,
+    /// the concurrency is unbounded if it is zero
+    #[serde(
+        rename = "concurrency",
+        default = "Reconciliation::default_concurrency"
+    )]
+    pub concurrency: usize,
+}
+
+impl Default for Reconciliation {
+    fn default() -> Self {
+        Self {
+            concurrency: Self::default_concurrency(),
+        }
+    }
+}
+
+impl Reconciliation {
+    fn default_concurrency() -> usize {
+        RECONCILIATION_CONCURRENCY
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Audit structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Audit {
+    /// record the mutating operations performed by the operator, e.g. the
+    /// creation of an addon or the upsertion of a secret
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// file in which operations are appended as json lines, they are logged
+    /// using the `audit` target if it is not set
+    #[serde(rename = "path", default)]
+    pub path: Option<PathBuf>,
+}
+
+// -----------------------------------------------------------------------------
+// NotificationEvent enumeration
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum NotificationEvent {
+    #[serde(rename = "addon-created", alias = "addonCreated")]
+    AddonCreated,
+    #[serde(rename = "addon-deleted", alias = "addonDeleted")]
+    AddonDeleted,
+    #[serde(rename = "repeated-failures", alias = "repeatedFailures")]
+    RepeatedFailures,
+    #[serde(rename = "plan-override", alias = "planOverride")]
+    PlanOverride,
+}
+
+impl Display for NotificationEvent {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::AddonCreated => write!(f, "addon-created"),
+            Self::AddonDeleted => write!(f, "addon-deleted"),
+            Self::RepeatedFailures => write!(f, "repeated-failures"),
+            Self::PlanOverride => write!(f, "plan-override"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// NotificationKind enumeration
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum NotificationKind {
+    #[serde(rename = "slack")]
+    Slack,
+    #[serde(rename = "mattermost")]
+    Mattermost,
+    #[serde(rename = "webhook")]
+    Webhook,
+}
+
+// -----------------------------------------------------------------------------
+// NotificationTarget structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct NotificationTarget {
+    /// kind of the target, which drives the payload of notifications, slack
+    /// and mattermost incoming webhooks receive the rendered message, other
+    /// webhooks receive the whole notification
+    #[serde(rename = "kind")]
+    pub kind: NotificationKind,
+    /// url on which notifications are posted, it usually holds a token
+    #[serde(rename = "url")]
+    pub url: String,
+    /// events notified to the target, all events are notified if it is empty
+    #[serde(rename = "events", default)]
+    pub events: Vec<NotificationEvent>,
+    /// template of the message, the `{event}`, `{kind}`, `{namespace}`,
+    /// `{name}`, `{addon}` and `{message}` placeholders are replaced
+    #[serde(rename = "template", default = "NotificationTarget::default_template")]
+    pub template: String,
+}
+
+impl NotificationTarget {
+    fn default_template() -> String {
+        NOTIFICATION_TEMPLATE.to_string()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Notification structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Notification {
+    /// targets to which notifications are sent, nothing is sent if it is empty
+    #[serde(rename = "targets", default)]
+    pub targets: Vec<NotificationTarget>,
+    /// number of consecutive failed reconciliations of a custom resource after
+    /// which the `repeated-failures` event is notified, once per streak
+    #[serde(
+        rename = "failureThreshold",
+        alias = "failure-threshold",
+        alias = "failure_threshold",
+        default = "Notification::default_failure_threshold"
+    )]
+    pub failure_threshold: u32,
+    /// timeout of the request sending a notification, in seconds
+    #[serde(rename = "timeout", default = "Notification::default_timeout")]
+    pub timeout: u64,
+}
+
+impl Default for Notification {
+    fn default() -> Self {
+        Self {
+            targets: vec![],
+            failure_threshold: Self::default_failure_threshold(),
+            timeout: Self::default_timeout(),
+        }
+    }
+}
+
+impl Notification {
+    fn default_failure_threshold() -> u32 {
+        NOTIFICATION_FAILURE_THRESHOLD
+    }
+
+    fn default_timeout() -> u64 {
+        NOTIFICATION_TIMEOUT
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Management structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Management {
+    /// serve the management api on the http server, which lists custom
+    /// resources tracked by the operator and the outcome of their last
+    /// reconciliation
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// bearer token expected in the `Authorization` header of requests on the
+    /// management api, the api is not served without one
+    #[serde(rename = "token", default)]
+    pub token: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Pprof structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Pprof {
+    /// serve the profiling endpoints on the http server and activate the
+    /// sampling of the heap, it requires the operator to be built with the
+    /// `debug` feature
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// maximum duration of a cpu profile, in seconds
+    #[serde(
+        rename = "maxDuration",
+        alias = "max-duration",
+        alias = "max_duration",
+        default = "Pprof::default_max_duration"
+    )]
+    pub max_duration: u64,
+}
+
+impl Default for Pprof {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_duration: Self::default_max_duration(),
+        }
+    }
+}
+
+impl Pprof {
+    fn default_max_duration() -> u64 {
+        PPROF_MAX_DURATION
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Billing structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Billing {
+    /// write the price of the plan and the consumption of the current month
+    /// of addons in the status of their custom resource
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// minimum interval between two requests of the consumption of an addon,
+    /// in seconds
+    #[serde(
+        rename = "refreshInterval",
+        alias = "refresh-interval",
+        alias = "refresh_interval",
+        default = "Billing::default_refresh_interval"
+    )]
+    pub refresh_interval: u64,
+}
+
+impl Default for Billing {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_interval: Self::default_refresh_interval(),
+        }
+    }
+}
+
+impl Billing {
+    fn default_refresh_interval() -> u64 {
+        BILLING_REFRESH_INTERVAL
+    }
+}
+
+// -----------------------------------------------------------------------------
+// AddonMetrics structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct AddonMetrics {
+    /// pull the metrics of reconciled addons from the Clever Cloud api and
+    /// export them as gauges, it requires the `metrics` feature
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// interval between two pulls of the metrics of addons, in seconds
+    #[serde(rename = "interval", default = "AddonMetrics::default_interval")]
+    pub interval: u64,
+    /// metrics to pull, e.g. `cpu`, `mem` or `connections`
+    #[serde(rename = "fields", default = "AddonMetrics::default_fields")]
+    pub fields: Vec<String>,
+}
+
+impl Default for AddonMetrics {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: Self::default_interval(),
+            fields: Self::default_fields(),
+        }
+    }
+}
+
+impl AddonMetrics {
+    fn default_interval() -> u64 {
+        ADDON_METRICS_INTERVAL
+    }
+
+    fn default_fields() -> Vec<String> {
+        ADDON_METRICS_FIELDS
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Naming structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Naming {
+    /// template of the name of addons, the `{kind}`, `{namespace}`, `{name}`
+    /// and `{uid}` placeholders are replaced by the ones of the custom
+    /// resource, e.g. `{namespace}-{name}`. Addons are named after the kind
+    /// and the identifier of their custom resource if it is not set
+    #[serde(rename = "template", default)]
+    pub template: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Tags structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Tags {
+    /// tag addons with the kubernetes metadata of their custom resource, at
+    /// creation and on each reconciliation to correct drifts
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// prefix of tags managed by the operator, tags without it are left as is
+    #[serde(rename = "prefix", default = "Tags::default_prefix")]
+    pub prefix: String,
+    /// name of the kubernetes cluster, it is not pushed if it is not set
+    #[serde(rename = "cluster", default)]
+    pub cluster: Option<String>,
+    /// push the labels of the custom resource as tags
+    #[serde(rename = "labels", default = "Tags::default_labels")]
+    pub labels: bool,
+}
+
+impl Default for Tags {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            prefix: Self::default_prefix(),
+            cluster: None,
+            labels: Self::default_labels(),
+        }
+    }
+}
+
+impl Tags {
+    fn default_prefix() -> String {
+        ADDON_TAGS_PREFIX.to_string()
+    }
+
+    fn default_labels() -> bool {
+        true
+    }
+}
+
+// -----------------------------------------------------------------------------
+// NetworkPolicyProvider enumeration
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum NetworkPolicyProvider {
+    /// `networking.k8s.io/v1` network policies, enforced by most network
+    /// plugins
+    #[default]
+    #[serde(rename = "kubernetes")]
+    Kubernetes,
+    /// `cilium.io/v2` cilium network policies
+    #[serde(rename = "cilium")]
+    Cilium,
+    /// `projectcalico.org/v3` calico network policies
+    #[serde(rename = "calico")]
+    Calico,
+}
+
+// -----------------------------------------------------------------------------
+// NetworkPolicy structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct NetworkPolicy {
+    /// generate a network policy allowing egress from the consumers of a
+    /// custom resource, selected by its `spec.consumers` field, to its addon
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// kind of the generated network policy
+    #[serde(rename = "provider", default)]
+    pub provider: NetworkPolicyProvider,
+}
+
+// -----------------------------------------------------------------------------
+// AddonHealth structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct AddonHealth {
+    /// probe the connection to the addons of database custom resources once
+    /// they are provisioned and on each resync, the result is written in
+    /// their `Healthy` condition
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// interval between two resyncs of custom resources whose addon is
+    /// probed, in seconds
+    #[serde(rename = "interval", default = "AddonHealth::default_interval")]
+    pub interval: u64,
+    /// maximum duration of a probe, in seconds
+    #[serde(rename = "timeout", default = "AddonHealth::default_timeout")]
+    pub timeout: u64,
+}
+
+impl Default for AddonHealth {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: Self::default_interval(),
+            timeout: Self::default_timeout(),
+        }
+    }
+}
+
+impl AddonHealth {
+    fn default_interval() -> u64 {
+        ADDON_HEALTH_INTERVAL
+    }
+
+    fn default_timeout() -> u64 {
+        ADDON_HEALTH_TIMEOUT
+    }
+}
+
+// -----------------------------------------------------------------------------
+// CustomResourceDefinitions structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct CustomResourceDefinitions {
+    /// apply the custom resource definitions of the operator at startup,
+    /// before controllers are started, using server-side apply
+    #[serde(rename = "manage", default)]
+    pub manage: bool,
+    /// maximum duration to wait for custom resource definitions to be
+    /// established, in seconds
+    #[serde(
+        rename = "timeout",
+        default = "CustomResourceDefinitions::default_timeout"
+    )]
+    pub timeout: u64,
+    /// refuse to start if the custom resource definitions stored in the
+    /// cluster are older than the ones of the operator, they are only logged
+    /// otherwise
+    #[serde(rename = "strict", default)]
+    pub strict: bool,
+}
+
+impl Default for CustomResourceDefinitions {
+    fn default() -> Self {
+        Self {
+            manage: false,
+            timeout: Self::default_timeout(),
+            strict: false,
+        }
+    }
+}
+
+impl CustomResourceDefinitions {
+    fn default_timeout() -> u64 {
+        CUSTOM_RESOURCE_DEFINITIONS_TIMEOUT
+    }
+}
+
+// -----------------------------------------------------------------------------
+// GarbageCollection structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct GarbageCollection {
+    /// periodically look for addons created by the operator whose custom
+    /// resource does not exist anymore, e.g. after a crash during a deletion
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+    /// organisations in which orphaned addons are looked for
+    #[serde(rename = "organisations", default)]
+    pub organisations: Vec<String>,
+    /// interval between two sweeps of the organisations, in seconds
+    #[serde(rename = "interval", default = "GarbageCollection::default_interval")]
+    pub interval: u64,
+    /// duration during which an addon has to be orphaned before being
+    /// reported or deleted, in seconds
+    #[serde(
+        rename = "gracePeriod",
+        alias = "grace-period",
+        alias = "grace_period",
+        default = "GarbageCollection::default_grace_period"
+    )]
+    pub grace_period: u64,
+    /// delete orphaned addons once their grace period is elapsed, they are
+    /// only reported if it is not set
+    #[serde(rename = "delete", default)]
+    pub delete: bool,
+}
+
+impl Default for GarbageCollection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            organisations: vec![],
+            interval: Self::default_interval(),
+            grace_period: Self::default_grace_period(),
+            delete: false,
+        }
+    }
+}
+
+impl GarbageCollection {
+    fn default_interval() -> u64 {
+        GARBAGE_COLLECTION_INTERVAL
+    }
+
+    fn default_grace_period() -> u64 {
+        GARBAGE_COLLECTION_GRACE_PERIOD
+    }
 }
 
 // -----------------------------------------------------------------------------
@@ -38,6 +1202,82 @@ pub struct Proxy {
 pub struct Operator {
     #[serde(rename = "listen")]
     pub listen: String,
+    #[serde(rename = "secret", default)]
+    pub secret: Secret,
+    #[serde(rename = "updateCheck", alias = "update-check", default)]
+    pub update_check: UpdateCheck,
+    #[serde(rename = "shutdown", default)]
+    pub shutdown: Shutdown,
+    #[serde(rename = "health", default)]
+    pub health: Health,
+    #[serde(rename = "supervision", default)]
+    pub supervision: Supervision,
+    #[serde(rename = "retry", default)]
+    pub retry: Retry,
+    #[serde(rename = "webhook", default)]
+    pub webhook: Webhook,
+    #[serde(rename = "leaderElection", alias = "leader-election", default)]
+    pub leader_election: LeaderElection,
+    #[serde(rename = "serverSideApply", alias = "server-side-apply", default)]
+    pub server_side_apply: ServerSideApply,
+    #[serde(rename = "reload", default)]
+    pub reload: Reload,
+    /// secret holding the credentials of the clever cloud client, formatted
+    /// as `namespace/name`, they take precedence over the `[api]` section
+    #[serde(
+        rename = "credentialsSecret",
+        alias = "credentials-secret",
+        alias = "credentials_secret",
+        default
+    )]
+    pub credentials_secret: Option<String>,
+    #[serde(rename = "restore", default)]
+    pub restore: Restore,
+    #[serde(rename = "management", default)]
+    pub management: Management,
+    #[serde(rename = "pprof", default)]
+    pub pprof: Pprof,
+    #[serde(rename = "billing", default)]
+    pub billing: Billing,
+    #[serde(
+        rename = "addonMetrics",
+        alias = "addon-metrics",
+        alias = "addon_metrics",
+        default
+    )]
+    pub addon_metrics: AddonMetrics,
+    #[serde(
+        rename = "garbageCollection",
+        alias = "garbage-collection",
+        alias = "garbage_collection",
+        default
+    )]
+    pub garbage_collection: GarbageCollection,
+    #[serde(rename = "naming", default)]
+    pub naming: Naming,
+    #[serde(rename = "tags", default)]
+    pub tags: Tags,
+    #[serde(
+        rename = "customResourceDefinitions",
+        alias = "custom-resource-definitions",
+        alias = "custom_resource_definitions",
+        default
+    )]
+    pub custom_resource_definitions: CustomResourceDefinitions,
+    #[serde(
+        rename = "networkPolicy",
+        alias = "network-policy",
+        alias = "network_policy",
+        default
+    )]
+    pub network_policy: NetworkPolicy,
+    #[serde(
+        rename = "addonHealth",
+        alias = "addon-health",
+        alias = "addon_health",
+        default
+    )]
+    pub addon_health: AddonHealth,
 }
 
 // -----------------------------------------------------------------------------
@@ -55,6 +1295,283 @@ pub struct Api {
     pub consumer_key: String,
     #[serde(rename = "consumerSecret")]
     pub consumer_secret: String,
+    /// negotiate http/2 with the api, requests are then multiplexed over a
+    /// single connection
+    #[serde(rename = "http2", default)]
+    pub http2: bool,
+    /// interval of tcp keep-alive probes on connections to the api, in seconds
+    #[serde(
+        rename = "tcpKeepalive",
+        alias = "tcp-keepalive",
+        default = "Api::default_tcp_keepalive"
+    )]
+    pub tcp_keepalive: u64,
+    /// duration during which dns resolutions of the api are cached, in seconds
+    #[serde(
+        rename = "dnsCacheTtl",
+        alias = "dns-cache-ttl",
+        default = "Api::default_dns_cache_ttl"
+    )]
+    pub dns_cache_ttl: u64,
+    /// duration during which plans resolved on the api are cached, in
+    /// seconds. The cache is disabled if it is zero
+    #[serde(
+        rename = "planCacheTtl",
+        alias = "plan-cache-ttl",
+        default = "Api::default_plan_cache_ttl"
+    )]
+    pub plan_cache_ttl: u64,
+    /// duration during which clients built from the configuration of a
+    /// namespace override are reused, in seconds. The pool is disabled if it
+    /// is zero
+    #[serde(
+        rename = "clientPoolTtl",
+        alias = "client-pool-ttl",
+        default = "Api::default_client_pool_ttl"
+    )]
+    pub client_pool_ttl: u64,
+    /// rate limit of calls on the api, per organisation
+    #[serde(
+        rename = "rateLimit",
+        alias = "rate-limit",
+        alias = "rate_limit",
+        default
+    )]
+    pub rate_limit: RateLimit,
+    /// tls settings of connections to the api
+    #[serde(rename = "tls", default)]
+    pub tls: Tls,
+    /// timeouts and limits of connections to the api
+    #[serde(rename = "http", default)]
+    pub http: Http,
+    /// retries of calls on the api answered by a transient failure
+    #[serde(rename = "retry", default)]
+    pub retry: RequestRetry,
+}
+
+impl Api {
+    fn default_tcp_keepalive() -> u64 {
+        API_TCP_KEEPALIVE
+    }
+
+    fn default_dns_cache_ttl() -> u64 {
+        API_DNS_CACHE_TTL
+    }
+
+    fn default_plan_cache_ttl() -> u64 {
+        API_PLAN_CACHE_TTL
+    }
+
+    fn default_client_pool_ttl() -> u64 {
+        API_CLIENT_POOL_TTL
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Http structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Http {
+    /// maximum duration to open a connection to the api, in seconds. It is
+    /// disabled if it is zero
+    #[serde(
+        rename = "connectTimeout",
+        alias = "connect-timeout",
+        alias = "connect_timeout",
+        default = "Http::default_connect_timeout"
+    )]
+    pub connect_timeout: u64,
+    /// maximum duration without progress while sending a request or reading
+    /// its response, in seconds. Idle connections of the pool are closed after
+    /// it too. It is disabled if it is zero
+    #[serde(
+        rename = "requestTimeout",
+        alias = "request-timeout",
+        alias = "request_timeout",
+        default = "Http::default_request_timeout"
+    )]
+    pub request_timeout: u64,
+    /// number of times the opening of a connection is retried, once it has
+    /// failed or timed out
+    #[serde(
+        rename = "connectRetries",
+        alias = "connect-retries",
+        alias = "connect_retries",
+        default = "Http::default_connect_retries"
+    )]
+    pub connect_retries: u32,
+    /// maximum size of a response of the api, in bytes. It is disabled if it
+    /// is zero
+    #[serde(
+        rename = "maxResponseSize",
+        alias = "max-response-size",
+        alias = "max_response_size",
+        default = "Http::default_max_response_size"
+    )]
+    pub max_response_size: u64,
+}
+
+impl Default for Http {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Self::default_connect_timeout(),
+            request_timeout: Self::default_request_timeout(),
+            connect_retries: Self::default_connect_retries(),
+            max_response_size: Self::default_max_response_size(),
+        }
+    }
+}
+
+impl Http {
+    fn default_connect_timeout() -> u64 {
+        API_HTTP_CONNECT_TIMEOUT
+    }
+
+    fn default_request_timeout() -> u64 {
+        API_HTTP_REQUEST_TIMEOUT
+    }
+
+    fn default_connect_retries() -> u32 {
+        API_HTTP_CONNECT_RETRIES
+    }
+
+    fn default_max_response_size() -> u64 {
+        API_HTTP_MAX_RESPONSE_SIZE
+    }
+}
+
+// -----------------------------------------------------------------------------
+// RequestRetry structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct RequestRetry {
+    /// number of times a call answered by a transient failure, i.e. with the
+    /// status code `429`, `502` or `503`, is retried before failing the
+    /// reconciliation. It is disabled if it is zero
+    #[serde(
+        rename = "maxRetries",
+        alias = "max-retries",
+        alias = "max_retries",
+        default = "RequestRetry::default_max_retries"
+    )]
+    pub max_retries: u32,
+    /// delay before retrying a call for the first time, in milliseconds. It
+    /// doubles on each retry of the call
+    #[serde(
+        rename = "initialDelay",
+        alias = "initial-delay",
+        alias = "initial_delay",
+        default = "RequestRetry::default_initial_delay"
+    )]
+    pub initial_delay: u64,
+    /// maximum delay before retrying a call, in milliseconds
+    #[serde(
+        rename = "maxDelay",
+        alias = "max-delay",
+        alias = "max_delay",
+        default = "RequestRetry::default_max_delay"
+    )]
+    pub max_delay: u64,
+}
+
+impl Default for RequestRetry {
+    fn default() -> Self {
+        Self {
+            max_retries: Self::default_max_retries(),
+            initial_delay: Self::default_initial_delay(),
+            max_delay: Self::default_max_delay(),
+        }
+    }
+}
+
+impl RequestRetry {
+    fn default_max_retries() -> u32 {
+        API_RETRY_MAX_RETRIES
+    }
+
+    fn default_initial_delay() -> u64 {
+        API_RETRY_INITIAL_DELAY
+    }
+
+    fn default_max_delay() -> u64 {
+        API_RETRY_MAX_DELAY
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tls structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Tls {
+    /// path to a pem encoded bundle of certificate authorities trusted in
+    /// addition to the webpki roots, e.g. the one of a tls-intercepting proxy
+    #[serde(rename = "caBundle", alias = "ca-bundle", alias = "ca_bundle", default)]
+    pub ca_bundle: Option<PathBuf>,
+    /// skip the verification of the certificate of the api, this is insecure
+    /// and should only be used to debug
+    #[serde(
+        rename = "insecureSkipVerify",
+        alias = "insecure-skip-verify",
+        alias = "insecure_skip_verify",
+        default
+    )]
+    pub insecure_skip_verify: bool,
+    /// minimum version of the tls protocol, either `1.2` or `1.3`, defaults to
+    /// `1.2`
+    #[serde(
+        rename = "minVersion",
+        alias = "min-version",
+        alias = "min_version",
+        default
+    )]
+    pub min_version: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// RateLimit structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct RateLimit {
+    /// limit the rate of calls on the api using a token bucket per
+    /// organisation
+    #[serde(rename = "enabled", default = "RateLimit::default_enabled")]
+    pub enabled: bool,
+    /// number of calls per second allowed on an organisation, once the burst
+    /// is consumed
+    #[serde(
+        rename = "requestsPerSecond",
+        alias = "requests-per-second",
+        alias = "requests_per_second",
+        default = "RateLimit::default_requests_per_second"
+    )]
+    pub requests_per_second: u32,
+    /// number of calls that could be executed at once on an organisation
+    #[serde(rename = "burst", default = "RateLimit::default_burst")]
+    pub burst: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            requests_per_second: Self::default_requests_per_second(),
+            burst: Self::default_burst(),
+        }
+    }
+}
+
+impl RateLimit {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_requests_per_second() -> u32 {
+        API_RATE_LIMIT_REQUESTS_PER_SECOND
+    }
+
+    fn default_burst() -> u32 {
+        API_RATE_LIMIT_BURST
+    }
 }
 
 #[allow(clippy::from_over_into)]
@@ -89,10 +1606,21 @@ pub enum Error {
 // Sentry structure
 
 #[cfg(feature = "tracker")]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
 pub struct Sentry {
     #[serde(rename = "dsn")]
     pub dsn: Option<String>,
+    /// environment attached to reported events, e.g. `production`
+    #[serde(rename = "environment", default)]
+    pub environment: Option<String>,
+    /// ratio of transactions sent to sentry, between `0.0` and `1.0`
+    #[serde(
+        rename = "tracesSampleRate",
+        alias = "traces-sample-rate",
+        alias = "traces_sample_rate",
+        default
+    )]
+    pub traces_sample_rate: f32,
 }
 
 // -----------------------------------------------------------------------------
@@ -106,6 +1634,58 @@ pub struct Jaeger {
     pub password: Option<String>,
 }
 
+// -----------------------------------------------------------------------------
+// Otlp structure
+
+#[cfg(feature = "trace")]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct Otlp {
+    /// endpoint of the opentelemetry collector receiving spans over http, e.g.
+    /// `http://localhost:4318/v1/traces`, spans are not exported if it is empty
+    #[serde(rename = "endpoint", default)]
+    pub endpoint: String,
+    /// headers sent along spans, e.g. to authenticate on the collector
+    #[serde(rename = "headers", default)]
+    pub headers: BTreeMap<String, String>,
+    /// ratio of traces sampled, between `0.0` and `1.0`, spans of a trace are
+    /// sampled following their parent
+    #[serde(
+        rename = "sampleRatio",
+        alias = "sample-ratio",
+        alias = "sample_ratio",
+        default = "Otlp::default_sample_ratio"
+    )]
+    pub sample_ratio: f64,
+}
+
+#[cfg(feature = "trace")]
+impl Default for Otlp {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            headers: BTreeMap::new(),
+            sample_ratio: Self::default_sample_ratio(),
+        }
+    }
+}
+
+#[cfg(feature = "trace")]
+impl Otlp {
+    fn default_sample_ratio() -> f64 {
+        OTLP_SAMPLE_RATIO
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Telemetry structure
+
+#[cfg(feature = "trace")]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+pub struct Telemetry {
+    #[serde(rename = "otlp", default)]
+    pub otlp: Otlp,
+}
+
 // -----------------------------------------------------------------------------
 // NamespaceConfiguration structures
 
@@ -165,7 +1745,7 @@ impl TryFrom<PathBuf> for NamespaceConfiguration {
 // -----------------------------------------------------------------------------
 // Configuration structures
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct Configuration {
     #[serde(rename = "proxy")]
     pub proxy: Option<Proxy>,
@@ -173,12 +1753,23 @@ pub struct Configuration {
     pub api: Api,
     #[serde(rename = "operator")]
     pub operator: Operator,
+    #[serde(rename = "watch", default)]
+    pub watch: Watch,
+    #[serde(rename = "reconciliation", default)]
+    pub reconciliation: Reconciliation,
+    #[serde(rename = "audit", default)]
+    pub audit: Audit,
+    #[serde(rename = "notification", default)]
+    pub notification: Notification,
     #[cfg(feature = "tracker")]
     #[serde(rename = "sentry", default = "Default::default")]
     pub sentry: Sentry,
     #[cfg(feature = "trace")]
     #[serde(rename = "jaeger")]
     pub jaeger: Jaeger,
+    #[cfg(feature = "trace")]
+    #[serde(rename = "telemetry", default)]
+    pub telemetry: Telemetry,
 }
 
 impl TryFrom<PathBuf> for Configuration {
@@ -233,6 +1824,13 @@ impl TryFrom<PathBuf> for Configuration {
                     .unwrap_or_else(|_err| None),
             )
             .map_err(|err| Error::Default("sentry.dsn".into(), err))?
+            .set_default(
+                "sentry.environment",
+                env::var("CLEVER_OPERATOR_SENTRY_ENVIRONMENT")
+                    .map(Some)
+                    .unwrap_or_else(|_err| None),
+            )
+            .map_err(|err| Error::Default("sentry.environment".into(), err))?
             // -----------------------------------------------------------------
             // Jaeger
             .set_default(
@@ -255,6 +1853,14 @@ impl TryFrom<PathBuf> for Configuration {
             )
             .map_err(|err| Error::Default("jaeger.password".into(), err))?
             // -----------------------------------------------------------------
+            // Telemetry
+            .set_default(
+                "telemetry.otlp.endpoint",
+                env::var("CLEVER_OPERATOR_TELEMETRY_OTLP_ENDPOINT")
+                    .unwrap_or_else(|_err| "".to_string()),
+            )
+            .map_err(|err| Error::Default("telemetry.otlp.endpoint".into(), err))?
+            // -----------------------------------------------------------------
             // Files
             .add_source(File::from(path).required(true))
             .build()
@@ -314,6 +1920,13 @@ impl Configuration {
                     .unwrap_or_else(|_err| None),
             )
             .map_err(|err| Error::Default("sentry.dsn".into(), err))?
+            .set_default(
+                "sentry.environment",
+                env::var("CLEVER_OPERATOR_SENTRY_ENVIRONMENT")
+                    .map(Some)
+                    .unwrap_or_else(|_err| None),
+            )
+            .map_err(|err| Error::Default("sentry.environment".into(), err))?
             // -----------------------------------------------------------------
             // Jaeger
             .set_default(
@@ -336,6 +1949,14 @@ impl Configuration {
             )
             .map_err(|err| Error::Default("jaeger.password".into(), err))?
             // -----------------------------------------------------------------
+            // Telemetry
+            .set_default(
+                "telemetry.otlp.endpoint",
+                env::var("CLEVER_OPERATOR_TELEMETRY_OTLP_ENDPOINT")
+                    .unwrap_or_else(|_err| "".to_string()),
+            )
+            .map_err(|err| Error::Default("telemetry.otlp.endpoint".into(), err))?
+            // -----------------------------------------------------------------
             // Files
             .add_source(
                 File::from(PathBuf::from(format!(
@@ -0,0 +1,255 @@
+//! # Api module
+//!
+//! This module provide the [`CleverApi`] trait, which gathers calls on the
+//! Clever Cloud's api made by reconcilers to manage addons. Reconcilers depend
+//! on the trait rather than on the client of the `clevercloud-sdk` crate, so
+//! the api could be replaced by an in-memory implementation, see
+//! [`crate::svc::clevercloud::fake`].
+
+use std::{collections::BTreeMap, fmt::Debug};
+
+use async_trait::async_trait;
+use clevercloud_sdk::{
+    oauth10a::ClientError,
+    v2::addon::{self, Addon, CreateOpts},
+    v4::addon_provider::{
+        plan::{self, Plan},
+        AddonProviderId,
+    },
+};
+use hyper::StatusCode;
+use tracing::{instrument::WithSubscriber, subscriber::NoSubscriber};
+
+use crate::svc::clevercloud::client::{guarded, observe, retry, Client};
+
+// -----------------------------------------------------------------------------
+// CleverApi trait
+
+#[async_trait]
+pub trait CleverApi: Debug + Send + Sync {
+    /// returns the addon of the organisation, if it exists
+    async fn get_addon(&self, organisation: &str, id: &str) -> Result<Option<Addon>, addon::Error>;
+
+    /// returns the addons of the organisation
+    async fn list_addons(&self, organisation: &str) -> Result<Vec<Addon>, addon::Error>;
+
+    /// create the addon in the organisation and returns it
+    async fn create_addon(
+        &self,
+        organisation: &str,
+        opts: &CreateOpts,
+    ) -> Result<Addon, addon::Error>;
+
+    /// delete the addon of the organisation
+    async fn delete_addon(&self, organisation: &str, id: &str) -> Result<(), addon::Error>;
+
+    /// returns the environment variables exposed by the addon
+    async fn addon_environment(
+        &self,
+        organisation: &str,
+        id: &str,
+    ) -> Result<BTreeMap<String, String>, addon::Error>;
+
+    /// returns the plan of the addon provider matching the given pattern
+    async fn find_plan(
+        &self,
+        provider: &AddonProviderId,
+        organisation: &str,
+        pattern: &str,
+    ) -> Result<Option<Plan>, plan::Error>;
+}
+
+#[async_trait]
+impl CleverApi for Client {
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    async fn get_addon(&self, organisation: &str, id: &str) -> Result<Option<Addon>, addon::Error> {
+        match retry("addon", "get", || addon::get(self, organisation, id)).await {
+            Ok(addon) => Ok(Some(addon)),
+            Err(addon::Error::Get(_, _, ClientError::StatusCode(code, _)))
+                if StatusCode::NOT_FOUND.as_u16() == code.as_u16() =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    async fn list_addons(&self, organisation: &str) -> Result<Vec<Addon>, addon::Error> {
+        retry("addon", "list", || addon::list(self, organisation)).await
+    }
+
+    #[cfg_attr(
+        feature = "trace",
+        tracing::instrument(skip(self, opts), fields(name = %opts.name))
+    )]
+    async fn create_addon(
+        &self,
+        organisation: &str,
+        opts: &CreateOpts,
+    ) -> Result<Addon, addon::Error> {
+        // options may have been read from secrets, while the `clevercloud-sdk`
+        // and `oauth10a` crates record their arguments and the request body in
+        // spans, so the creation is not traced at all.
+        //
+        // the api may have created the addon before failing, look for it by
+        // its name rather than creating it twice
+        guarded(
+            "addon",
+            "create",
+            || addon::create(self, organisation, opts).with_subscriber(NoSubscriber::default()),
+            || async {
+                Ok(addon::list(self, organisation)
+                    .await?
+                    .into_iter()
+                    .find(|addon| addon.name.as_deref() == Some(opts.name.as_str())))
+            },
+        )
+        .await
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    async fn delete_addon(&self, organisation: &str, id: &str) -> Result<(), addon::Error> {
+        retry("addon", "delete", || addon::delete(self, organisation, id)).await
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    async fn addon_environment(
+        &self,
+        organisation: &str,
+        id: &str,
+    ) -> Result<BTreeMap<String, String>, addon::Error> {
+        retry("addon", "environment", || {
+            addon::environment(self, organisation, id)
+        })
+        .await
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    async fn find_plan(
+        &self,
+        provider: &AddonProviderId,
+        organisation: &str,
+        pattern: &str,
+    ) -> Result<Option<Plan>, plan::Error> {
+        // errors of plans do not expose the status code of the response, so
+        // they could not be retried
+        observe(
+            "plan",
+            "find",
+            plan::find(self, provider, organisation, pattern),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use clevercloud_sdk::v2::addon::{CreateOpts, Opts};
+    use serde_json::json;
+    use tracing::Level;
+    use tracing_subscriber::fmt::format::FmtSpan;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::CleverApi;
+    use crate::svc::{
+        cfg::Api,
+        clevercloud::{
+            client::{connector, Client},
+            ext::merge,
+        },
+        redact::Redacted,
+    };
+
+    const SECRET: &str = "s3cr3t-from-options";
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().expect("lock to not be poisoned").extend(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn create_addon_does_not_record_options() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v2/organisations/orga_test/addons"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({})))
+            .mount(&server)
+            .await;
+
+        let api: Api = serde_json::from_value(json!({
+            "endpoint": server.uri(),
+            "token": "token",
+            "secret": "secret",
+            "consumerKey": "consumer-key",
+            "consumerSecret": "consumer-secret",
+        }))
+        .expect("api configuration to be deserialized");
+
+        let client = Client::builder()
+            .with_endpoint(server.uri())
+            .with_credentials(api.to_owned().into())
+            .build(connector(&api, &None).expect("connector to be built"));
+
+        let mut opts = CreateOpts {
+            name: "addon".to_string(),
+            region: "par".to_string(),
+            provider_id: "postgresql-addon".to_string(),
+            plan: "plan_test".to_string(),
+            options: Opts {
+                version: None,
+                encryption: None,
+                services: None,
+            },
+        };
+
+        let values = BTreeMap::from([("version".to_string(), SECRET.to_string())]);
+        merge(&mut opts.options, &Redacted(values));
+
+        let buffer = Buffer::default();
+        let writer = buffer.to_owned();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(Level::TRACE)
+            .with_span_events(FmtSpan::FULL)
+            .with_ansi(false)
+            .with_writer(move || writer.to_owned())
+            .finish();
+
+        let result = {
+            let _guard = tracing::subscriber::set_default(subscriber);
+            client.create_addon("orga_test", &opts).await
+        };
+
+        assert!(result.is_err(), "the fake api rejects the creation");
+
+        let requests = server
+            .received_requests()
+            .await
+            .expect("requests to be recorded");
+        assert_eq!(1, requests.len());
+        assert!(String::from_utf8_lossy(&requests[0].body).contains(SECRET));
+
+        let output = String::from_utf8(buffer.0.lock().expect("lock").to_owned())
+            .expect("output to be utf-8");
+        assert!(!output.is_empty(), "spans and events are recorded");
+        assert!(!output.contains(SECRET), "secret option leaked: {}", output);
+    }
+}
@@ -0,0 +1,413 @@
+//! # Application module
+//!
+//! This module provide structures and helpers to interact with the
+//! applications' endpoints of the Clever Cloud's api, which are not exposed by
+//! the `clevercloud-sdk` crate.
+
+use std::{cmp::Ordering, collections::BTreeMap, fmt::Debug};
+
+use clevercloud_sdk::oauth10a::{ClientError, RestClient};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::svc::clevercloud::client::{guarded, retry, Client};
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to list instances of product, {0}")]
+    Instances(ClientError),
+    #[error("failed to find instance '{0}' in version '{1}'")]
+    Instance(String, String),
+    #[error("failed to get application '{0}' of organisation '{1}', {2}")]
+    Get(String, String, ClientError),
+    #[error("failed to list applications of organisation '{0}', {1}")]
+    List(String, ClientError),
+    #[error("failed to create application on organisation '{0}', {1}")]
+    Create(String, ClientError),
+    #[error("failed to update application '{0}' of organisation '{1}', {2}")]
+    Update(String, String, ClientError),
+    #[error("failed to delete application '{0}' of organisation '{1}', {2}")]
+    Delete(String, String, ClientError),
+    #[error("failed to update environment of application '{0}' of organisation '{1}', {2}")]
+    Environment(String, String, ClientError),
+    #[error("failed to list domains of application '{0}' of organisation '{1}', {2}")]
+    Domains(String, String, ClientError),
+    #[error("failed to add domain '{0}' on application '{1}' of organisation '{2}', {3}")]
+    AddDomain(String, String, String, ClientError),
+    #[error("failed to remove domain '{0}' from application '{1}' of organisation '{2}', {3}")]
+    RemoveDomain(String, String, String, ClientError),
+}
+
+// -----------------------------------------------------------------------------
+// Variant structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Variant {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "slug")]
+    pub slug: String,
+}
+
+// -----------------------------------------------------------------------------
+// Instance structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Instance {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(rename = "version")]
+    pub version: String,
+    #[serde(rename = "variant")]
+    pub variant: Variant,
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+}
+
+// -----------------------------------------------------------------------------
+// Deployment structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Deployment {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(rename = "httpUrl", default, skip_serializing_if = "Option::is_none")]
+    pub http_url: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Application structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Application {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "zone")]
+    pub zone: String,
+    #[serde(rename = "deployment")]
+    pub deployment: Deployment,
+    #[serde(rename = "state", default)]
+    pub state: String,
+}
+
+// -----------------------------------------------------------------------------
+// WannaBeApplication structure
+
+/// payload used to create or update an application
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct WannaBeApplication {
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "description")]
+    pub description: String,
+    #[serde(rename = "zone")]
+    pub zone: String,
+    #[serde(rename = "deploy")]
+    pub deploy: String,
+    #[serde(rename = "instanceType")]
+    pub instance_type: String,
+    #[serde(rename = "instanceVersion")]
+    pub instance_version: String,
+    #[serde(rename = "instanceVariant")]
+    pub instance_variant: String,
+    #[serde(rename = "minInstances")]
+    pub min_instances: u32,
+    #[serde(rename = "maxInstances")]
+    pub max_instances: u32,
+    #[serde(rename = "minFlavor")]
+    pub min_flavor: String,
+    #[serde(rename = "maxFlavor")]
+    pub max_flavor: String,
+}
+
+// -----------------------------------------------------------------------------
+// Domain structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Domain {
+    #[serde(rename = "fqdn")]
+    pub fqdn: String,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the instance of the given type, in the given version if any, which
+/// is required to create an application
+pub async fn instance(
+    client: &Client,
+    endpoint: &str,
+    kind: &str,
+    version: Option<&str>,
+) -> Result<Instance, Error> {
+    let path = format!("{}/v2/products/instances", endpoint);
+
+    debug!(endpoint = &path, "execute a request to list instances");
+    let instances: Vec<Instance> = retry("application", "instances", || client.get(&path))
+        .await
+        .map_err(Error::Instances)?;
+
+    instances
+        .into_iter()
+        .filter(|instance| instance.enabled && instance.kind == kind)
+        .filter(|instance| version.map(|v| v == instance.version).unwrap_or(true))
+        .max_by(|a, b| compare(&a.version, &b.version))
+        .ok_or_else(|| Error::Instance(kind.to_string(), version.unwrap_or("<latest>").to_string()))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// compare versions of instances component by component, numeric components
+/// are compared as numbers, e.g. `20231013` is newer than `20230918` and
+/// `1.10` is newer than `1.9`
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let components = |version: &str| -> Vec<(u64, String)> {
+        version
+            .split(['.', '-', '_'])
+            .map(|component| match component.parse() {
+                Ok(number) => (number, String::new()),
+                Err(_) => (0, component.to_string()),
+            })
+            .collect()
+    };
+
+    components(a).cmp(&components(b))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the application of the organisation with the given identifier
+pub async fn get(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+) -> Result<Application, Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/applications/{}",
+        endpoint, organisation, id
+    );
+
+    debug!(endpoint = &path, "execute a request to get application");
+    retry("application", "get", || client.get(&path))
+        .await
+        .map_err(|err| Error::Get(id.to_owned(), organisation.to_owned(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns applications of the organisation
+pub async fn list(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+) -> Result<Vec<Application>, Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/applications",
+        endpoint, organisation
+    );
+
+    debug!(endpoint = &path, "execute a request to list applications");
+    retry("application", "list", || client.get(&path))
+        .await
+        .map_err(|err| Error::List(organisation.to_owned(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// create an application on the organisation
+pub async fn create(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    app: &WannaBeApplication,
+) -> Result<Application, Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/applications",
+        endpoint, organisation
+    );
+
+    debug!(endpoint = &path, "execute a request to create application");
+    // the api may have created the application before failing, look for it
+    // by its name rather than creating it twice
+    guarded(
+        "application",
+        "create",
+        || client.post(&path, app),
+        || async {
+            let applications: Vec<Application> = client.get(&path).await?;
+
+            Ok(applications
+                .into_iter()
+                .find(|application| application.name == app.name))
+        },
+    )
+    .await
+    .map_err(|err| Error::Create(organisation.to_owned(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// update the application of the organisation, this is used to set its
+/// scalability
+pub async fn update(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+    app: &WannaBeApplication,
+) -> Result<Application, Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/applications/{}",
+        endpoint, organisation, id
+    );
+
+    debug!(endpoint = &path, "execute a request to update application");
+    retry("application", "update", || client.put(&path, app))
+        .await
+        .map_err(|err| Error::Update(id.to_owned(), organisation.to_owned(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// delete the application of the organisation
+pub async fn delete(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+) -> Result<(), Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/applications/{}",
+        endpoint, organisation, id
+    );
+
+    debug!(endpoint = &path, "execute a request to delete application");
+    retry("application", "delete", || client.delete(&path))
+        .await
+        .map_err(|err| Error::Delete(id.to_owned(), organisation.to_owned(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client, variables)))]
+/// replace the environment variables of the application of the organisation
+pub async fn environment(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+    variables: &BTreeMap<String, String>,
+) -> Result<(), Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/applications/{}/env",
+        endpoint, organisation, id
+    );
+
+    debug!(
+        endpoint = &path,
+        "execute a request to update environment of application"
+    );
+
+    retry("application", "environment", || {
+        client.put::<_, serde_json::Value>(&path, variables)
+    })
+    .await
+    .map_err(|err| Error::Environment(id.to_owned(), organisation.to_owned(), err))?;
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns domains of the application of the organisation
+pub async fn domains(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+) -> Result<Vec<Domain>, Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/applications/{}/vhosts",
+        endpoint, organisation, id
+    );
+
+    debug!(
+        endpoint = &path,
+        "execute a request to list domains of application"
+    );
+
+    retry("application", "domains", || client.get(&path))
+        .await
+        .map_err(|err| Error::Domains(id.to_owned(), organisation.to_owned(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// add the domain on the application of the organisation
+pub async fn add_domain(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+    fqdn: &str,
+) -> Result<(), Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/applications/{}/vhosts/{}",
+        endpoint, organisation, id, fqdn
+    );
+
+    debug!(
+        endpoint = &path,
+        "execute a request to add domain on application"
+    );
+
+    let payload = serde_json::Value::Null;
+    retry("application", "add_domain", || {
+        client.put::<_, serde_json::Value>(&path, &payload)
+    })
+    .await
+    .map_err(|err| {
+        Error::AddDomain(fqdn.to_owned(), id.to_owned(), organisation.to_owned(), err)
+    })?;
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// remove the domain from the application of the organisation
+pub async fn remove_domain(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+    fqdn: &str,
+) -> Result<(), Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/applications/{}/vhosts/{}",
+        endpoint, organisation, id, fqdn
+    );
+
+    debug!(
+        endpoint = &path,
+        "execute a request to remove domain from application"
+    );
+
+    retry("application", "remove_domain", || client.delete(&path))
+        .await
+        .map_err(|err| {
+            Error::RemoveDomain(fqdn.to_owned(), id.to_owned(), organisation.to_owned(), err)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::compare;
+
+    #[test]
+    fn compare_versions_by_components() {
+        assert_eq!(Ordering::Greater, compare("20231013", "20230918"));
+        assert_eq!(Ordering::Greater, compare("1.10", "1.9"));
+        assert_eq!(Ordering::Greater, compare("21", "8"));
+        assert_eq!(Ordering::Less, compare("17", "17.1"));
+        assert_eq!(Ordering::Equal, compare("3.11", "3.11"));
+    }
+}
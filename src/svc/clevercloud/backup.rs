@@ -0,0 +1,65 @@
+//! # Backup module
+//!
+//! This module provide structures and helpers to interact with the backups'
+//! endpoints of the Clever Cloud's api, which are not exposed by the
+//! `clevercloud-sdk` crate.
+
+use std::fmt::Debug;
+
+use clevercloud_sdk::oauth10a::{ClientError, RestClient};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::svc::clevercloud::client::{retry, Client};
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to list backups of addon '{0}' of organisation '{1}', {2}")]
+    List(String, String, ClientError),
+}
+
+// -----------------------------------------------------------------------------
+// Backup structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Backup {
+    #[serde(rename = "backup_id")]
+    pub id: String,
+    #[serde(rename = "entity_id")]
+    pub entity_id: String,
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "creation_date")]
+    pub creation_date: String,
+    #[serde(rename = "delete_at", default, skip_serializing_if = "Option::is_none")]
+    pub delete_at: Option<String>,
+    #[serde(
+        rename = "download_url",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub download_url: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the backups of the addon, the given identifier is the real
+/// identifier of the addon, e.g. `postgresql_<uuid>`
+pub async fn list(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+) -> Result<Vec<Backup>, Error> {
+    let path = format!("{}/v2/backups/{}/{}", endpoint, organisation, id);
+
+    debug!(endpoint = &path, "execute a request to list backups");
+    retry("backup", "list", || client.get(&path))
+        .await
+        .map_err(|err| Error::List(id.to_owned(), organisation.to_owned(), err))
+}
@@ -0,0 +1,67 @@
+//! # Billing module
+//!
+//! This module provide structures and helpers to interact with the
+//! consumption endpoint of the Clever Cloud's api, which is not exposed by the
+//! `clevercloud-sdk` crate.
+
+use std::fmt::Debug;
+
+use clevercloud_sdk::oauth10a::{ClientError, RestClient};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::svc::clevercloud::client::{retry, Client};
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to get consumption of addon '{0}' of organisation '{1}', {2}")]
+    Get(String, String, ClientError),
+}
+
+// -----------------------------------------------------------------------------
+// Consumption structure
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct Consumption {
+    #[serde(rename = "from", default)]
+    pub from: String,
+    #[serde(rename = "to", default)]
+    pub to: String,
+    /// price of the consumption over the period, in euros
+    #[serde(rename = "price", default)]
+    pub price: f64,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the price of the consumption of the addon between the given
+/// rfc3339 timestamps, the given identifier is the identifier of the addon,
+/// e.g. `addon_<uuid>`
+pub async fn consumption(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+    from: &str,
+    to: &str,
+) -> Result<f64, Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/consumptions?for={}&from={}&to={}",
+        endpoint, organisation, id, from, to
+    );
+
+    debug!(endpoint = &path, "execute a request to get consumption");
+    let consumptions: Vec<Consumption> = retry("billing", "consumption", || client.get(&path))
+        .await
+        .map_err(|err| Error::Get(id.to_owned(), organisation.to_owned(), err))?;
+
+    Ok(consumptions
+        .iter()
+        .map(|consumption| consumption.price)
+        .sum())
+}
@@ -0,0 +1,70 @@
+//! # Biscuit module
+//!
+//! This module provide structures and helpers to interact with the token
+//! endpoint of the pulsar addon provider of the Clever Cloud's api, which is
+//! not exposed by the `clevercloud-sdk` crate. Credentials of pulsar addons are
+//! biscuit tokens, a new token is issued once the current one is rotated.
+
+use std::fmt::{self, Debug, Formatter};
+
+use clevercloud_sdk::oauth10a::{ClientError, RestClient};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::svc::{
+    clevercloud::client::{observe, Client},
+    redact::{Redacted, REDACTED},
+};
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to rotate token of pulsar addon '{0}' of organisation '{1}', {2}")]
+    Rotate(String, String, ClientError),
+}
+
+// -----------------------------------------------------------------------------
+// Token structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
+struct Token {
+    #[serde(rename = "token")]
+    token: String,
+}
+
+impl Debug for Token {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Token").field("token", &REDACTED).finish()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// issue a new biscuit token for the pulsar addon and returns it, the given
+/// identifier is the real identifier of the addon, e.g. `pulsar_<uuid>`. The
+/// new token is then exposed in the environment of the addon
+pub async fn rotate(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+) -> Result<Redacted<String>, Error> {
+    let path = format!(
+        "{}/v4/addon-providers/addon-pulsar/addons/{}/token",
+        endpoint, id
+    );
+
+    debug!(
+        endpoint = &path,
+        "execute a request to rotate a pulsar token"
+    );
+    let token: Token = observe("biscuit", "rotate", client.post(&path, &()))
+        .await
+        .map_err(|err| Error::Rotate(id.to_owned(), organisation.to_owned(), err))?;
+
+    Ok(Redacted(token.token))
+}
@@ -0,0 +1,258 @@
+//! # Cache module
+//!
+//! This module provide an in-memory cache of the plans resolved on the Clever
+//! Cloud's api. Custom resources which do not reference a plan by its
+//! identifier resolve it on each reconciliation, so plans of an addon provider
+//! are shared between reconciliations of custom resources of an organisation
+//! during a configurable time.
+//!
+//! The catalog of an organisation, i.e. the plans of each addon provider
+//! managed by the operator and the regions, is cached alongside plans with the
+//! same time to live, it backs the `/v1/catalog` endpoint of the operator.
+
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use clevercloud_sdk::v4::{
+    addon_provider::{
+        plan::{self, Plan},
+        AddonProviderId,
+    },
+    products::zones::{self, Zone},
+};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_counter_vec, CounterVec};
+use serde::{Deserialize, Serialize};
+use tracing::trace;
+
+use crate::svc::clevercloud::{
+    api::CleverApi,
+    client::{observe, Client},
+};
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static PLAN_CACHE_REQUEST: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "clever_cloud_client_plan_cache",
+            "number of plan resolutions served from the cache or the api",
+        ),
+        &["provider", "result"]
+    )
+    .expect("metrics 'clever_cloud_client_plan_cache' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// addon providers whose plans are listed in the catalog
+pub const CATALOG_PROVIDERS: &[AddonProviderId] = &[
+    AddonProviderId::PostgreSql,
+    AddonProviderId::MySql,
+    AddonProviderId::Redis,
+    AddonProviderId::MongoDb,
+    AddonProviderId::ElasticSearch,
+];
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to list plans of addon provider '{0}', {1}")]
+    Plan(String, plan::Error),
+    #[error("failed to list regions, {0}")]
+    Zone(zones::Error),
+}
+
+// -----------------------------------------------------------------------------
+// Catalog structure
+
+/// plans of an addon provider
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Provider {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "plans")]
+    pub plans: Vec<Plan>,
+}
+
+/// addon providers, plans and regions available to an organisation
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Catalog {
+    #[serde(rename = "organisation")]
+    pub organisation: String,
+    #[serde(rename = "providers")]
+    pub providers: Vec<Provider>,
+    #[serde(rename = "regions")]
+    pub regions: Vec<Zone>,
+}
+
+// -----------------------------------------------------------------------------
+// Entry structure
+
+#[derive(Clone, Debug)]
+struct Entry {
+    plan: Option<Plan>,
+    expires_at: Instant,
+}
+
+// -----------------------------------------------------------------------------
+// PlanCache structure
+
+/// resolved plans, keyed by addon provider, organisation and pattern of the
+/// plan. A time to live of zero disables the cache
+#[derive(Debug)]
+pub struct PlanCache {
+    ttl: Duration,
+    entries: Mutex<BTreeMap<(String, String, String), Entry>>,
+    catalogs: Mutex<BTreeMap<String, (Catalog, Instant)>>,
+}
+
+impl PlanCache {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(BTreeMap::new()),
+            catalogs: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+    /// returns the catalog of the organisation, it is served from the cache if
+    /// it has been listed for less than the time to live
+    pub async fn catalog(&self, client: &Client, organisation: &str) -> Result<Catalog, Error> {
+        let cached = self.catalogs.lock().ok().and_then(|catalogs| {
+            catalogs
+                .get(organisation)
+                .filter(|(_, expires_at)| *expires_at > Instant::now())
+                .map(|(catalog, _)| catalog.to_owned())
+        });
+
+        if let Some(catalog) = cached {
+            trace!(organisation = organisation, "Serve catalog from the cache");
+
+            #[cfg(feature = "metrics")]
+            PLAN_CACHE_REQUEST
+                .with_label_values(&["catalog", "hit"])
+                .inc();
+
+            return Ok(catalog);
+        }
+
+        #[cfg(feature = "metrics")]
+        PLAN_CACHE_REQUEST
+            .with_label_values(&["catalog", "miss"])
+            .inc();
+
+        let mut providers = vec![];
+        for provider in CATALOG_PROVIDERS {
+            let plans = observe("plan", "list", plan::list(client, provider, organisation))
+                .await
+                .map_err(|err| Error::Plan(provider.to_string(), err))?
+                .plans;
+
+            providers.push(Provider {
+                id: provider.to_string(),
+                plans,
+            });
+        }
+
+        let catalog = Catalog {
+            organisation: organisation.to_string(),
+            providers,
+            regions: observe("zone", "list", zones::list(client))
+                .await
+                .map_err(Error::Zone)?,
+        };
+
+        if !self.ttl.is_zero() {
+            if let Ok(mut catalogs) = self.catalogs.lock() {
+                let now = Instant::now();
+
+                catalogs.retain(|_, (_, expires_at)| *expires_at > now);
+                catalogs.insert(
+                    organisation.to_string(),
+                    (catalog.to_owned(), now + self.ttl),
+                );
+            }
+        }
+
+        Ok(catalog)
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(api)))]
+    /// same as [`CleverApi::find_plan`], the result is served from the cache
+    /// if it has been resolved for less than the time to live
+    pub async fn find(
+        &self,
+        api: &dyn CleverApi,
+        provider: &AddonProviderId,
+        organisation: &str,
+        pattern: &str,
+    ) -> Result<Option<Plan>, plan::Error> {
+        let key = (
+            provider.to_string(),
+            organisation.to_string(),
+            pattern.to_string(),
+        );
+
+        if let Some(plan) = self.get(&key) {
+            trace!(
+                provider = &key.0,
+                organisation = organisation,
+                pattern = pattern,
+                "Serve plan from the cache",
+            );
+
+            #[cfg(feature = "metrics")]
+            PLAN_CACHE_REQUEST.with_label_values(&[&key.0, "hit"]).inc();
+
+            return Ok(plan);
+        }
+
+        #[cfg(feature = "metrics")]
+        PLAN_CACHE_REQUEST
+            .with_label_values(&[&key.0, "miss"])
+            .inc();
+
+        let plan = api.find_plan(provider, organisation, pattern).await?;
+        if !self.ttl.is_zero() {
+            if let Ok(mut entries) = self.entries.lock() {
+                let now = Instant::now();
+
+                entries.retain(|_, entry| entry.expires_at > now);
+                entries.insert(
+                    key,
+                    Entry {
+                        plan: plan.to_owned(),
+                        expires_at: now + self.ttl,
+                    },
+                );
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// returns the cached plan, if it has not expired
+    fn get(&self, key: &(String, String, String)) -> Option<Option<Plan>> {
+        let entries = self.entries.lock().ok()?;
+        let entry = entries.get(key)?;
+
+        if entry.expires_at > Instant::now() {
+            Some(entry.plan.to_owned())
+        } else {
+            None
+        }
+    }
+}
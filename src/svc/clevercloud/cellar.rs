@@ -0,0 +1,487 @@
+//! # Cellar module
+//!
+//! This module provide a minimal client of the s3 api exposed by Cellar, the
+//! object storage of Clever Cloud, to manage buckets using the credentials of
+//! a Cellar addon, their cors rules and their static website configuration.
+//! Requests are signed using the AWS signature version 4.
+//!
+//! See <https://docs.aws.amazon.com/AmazonS3/latest/API/sig-v4-authenticating-requests.html>
+
+use std::{collections::BTreeMap, fmt::Debug};
+
+use chrono::Utc;
+use hyper::{Body, Method, Request, StatusCode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::svc::{
+    cfg::{Api, Proxy},
+    clevercloud::client::{self, Connector},
+    crypto,
+    redact::Redacted,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const CELLAR_HOST: &str = "CELLAR_ADDON_HOST";
+pub const CELLAR_KEY_ID: &str = "CELLAR_ADDON_KEY_ID";
+pub const CELLAR_KEY_SECRET: &str = "CELLAR_ADDON_KEY_SECRET";
+pub const CELLAR_BUCKET: &str = "CELLAR_ADDON_BUCKET";
+
+/// region used to sign requests, cellar does not use it to route them
+pub const REGION: &str = "us-east-1";
+pub const SERVICE: &str = "s3";
+
+/// object returned for requests on the root of a static website, if the
+/// configuration does not set one
+pub const INDEX_DOCUMENT: &str = "index.html";
+
+/// hash of an empty payload
+const EMPTY_PAYLOAD_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to find key '{0}' in cellar credentials")]
+    MissingKey(&'static str),
+    #[error("failed to create http client, {0}")]
+    Connector(client::Error),
+    #[error("failed to build request for bucket '{0}', {1}")]
+    Request(String, hyper::http::Error),
+    #[error("failed to execute request for bucket '{0}', {1}")]
+    Execute(String, hyper::Error),
+    #[error("failed to sign request, {0}")]
+    Sign(crypto::Error),
+    #[error("failed to {0} bucket '{1}', cellar answered with status code '{2}'")]
+    StatusCode(&'static str, String, StatusCode),
+    #[error("failed to delete bucket '{0}', it is not empty")]
+    NotEmpty(String),
+}
+
+// -----------------------------------------------------------------------------
+// CorsRule structure
+
+/// cross-origin resource sharing rule of a bucket, see
+/// <https://docs.aws.amazon.com/AmazonS3/latest/userguide/ManageCorsUsing.html>
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct CorsRule {
+    /// origins allowed to send cross-origin requests, e.g. `https://example.com`
+    /// or `*`
+    #[serde(rename = "allowedOrigins")]
+    pub allowed_origins: Vec<String>,
+    /// methods allowed in cross-origin requests, e.g. `GET` or `PUT`
+    #[serde(rename = "allowedMethods")]
+    pub allowed_methods: Vec<String>,
+    /// headers allowed in preflight requests
+    #[serde(
+        rename = "allowedHeaders",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub allowed_headers: Vec<String>,
+    /// headers of responses which browsers expose to applications
+    #[serde(
+        rename = "exposeHeaders",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub expose_headers: Vec<String>,
+    /// duration during which browsers cache the response to a preflight
+    /// request, in seconds
+    #[serde(
+        rename = "maxAgeSeconds",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_age_seconds: Option<u32>,
+}
+
+// -----------------------------------------------------------------------------
+// Website structure
+
+/// static website hosting configuration of a bucket
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Website {
+    /// object returned for requests on the root of the website or of one of
+    /// its folders
+    #[serde(rename = "indexDocument", default = "Website::default_index_document")]
+    pub index_document: String,
+    /// object returned for requests which fail, e.g. on a missing object
+    #[serde(
+        rename = "errorDocument",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub error_document: Option<String>,
+}
+
+impl Website {
+    pub fn default_index_document() -> String {
+        INDEX_DOCUMENT.to_string()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Credentials structure
+
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub host: String,
+    pub key_id: String,
+    pub key_secret: Redacted<String>,
+}
+
+impl TryFrom<&BTreeMap<String, String>> for Credentials {
+    type Error = Error;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(data)))]
+    fn try_from(data: &BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        let get = |key: &'static str| data.get(key).cloned().ok_or(Error::MissingKey(key));
+
+        Ok(Self {
+            host: get(CELLAR_HOST)?,
+            key_id: get(CELLAR_KEY_ID)?,
+            key_secret: Redacted::from(get(CELLAR_KEY_SECRET)?),
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Client structure
+
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: hyper::Client<Connector>,
+    credentials: Credentials,
+}
+
+impl Client {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn try_new(
+        api: &Api,
+        proxy: &Option<Proxy>,
+        credentials: Credentials,
+    ) -> Result<Self, Error> {
+        let connector = client::connector(api, proxy).map_err(Error::Connector)?;
+
+        Ok(Self {
+            inner: hyper::Client::builder().build(connector),
+            credentials,
+        })
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns if the bucket exists and is owned by the credentials
+    pub async fn exists(&self, bucket: &str) -> Result<bool, Error> {
+        match self.execute(Method::HEAD, bucket, None, "").await? {
+            StatusCode::OK => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            status => Err(Error::StatusCode("retrieve", bucket.to_string(), status)),
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// create the bucket, if it does not already exist
+    pub async fn create(&self, bucket: &str) -> Result<(), Error> {
+        if self.exists(bucket).await? {
+            return Ok(());
+        }
+
+        match self.execute(Method::PUT, bucket, None, "").await? {
+            status if status.is_success() => Ok(()),
+            status => Err(Error::StatusCode("create", bucket.to_string(), status)),
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// delete the bucket, if it exists. Cellar refuses to delete a bucket
+    /// which still contains objects
+    pub async fn delete(&self, bucket: &str) -> Result<(), Error> {
+        match self.execute(Method::DELETE, bucket, None, "").await? {
+            status if status.is_success() => Ok(()),
+            StatusCode::NOT_FOUND => Ok(()),
+            StatusCode::CONFLICT => Err(Error::NotEmpty(bucket.to_string())),
+            status => Err(Error::StatusCode("delete", bucket.to_string(), status)),
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// apply the cors rules on the bucket, the ones of the bucket are removed
+    /// if none is given
+    pub async fn cors(&self, bucket: &str, rules: &[CorsRule]) -> Result<(), Error> {
+        if rules.is_empty() {
+            return match self
+                .execute(Method::DELETE, bucket, Some("cors"), "")
+                .await?
+            {
+                status if status.is_success() => Ok(()),
+                StatusCode::NOT_FOUND => Ok(()),
+                status => Err(Error::StatusCode(
+                    "remove cors rules of",
+                    bucket.to_string(),
+                    status,
+                )),
+            };
+        }
+
+        let payload = cors_configuration(rules);
+        match self
+            .execute(Method::PUT, bucket, Some("cors"), &payload)
+            .await?
+        {
+            status if status.is_success() => Ok(()),
+            status => Err(Error::StatusCode(
+                "apply cors rules on",
+                bucket.to_string(),
+                status,
+            )),
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// apply the static website configuration on the bucket, the one of the
+    /// bucket is removed if none is given
+    pub async fn website(&self, bucket: &str, website: Option<&Website>) -> Result<(), Error> {
+        let website = match website {
+            Some(website) => website,
+            None => {
+                return match self
+                    .execute(Method::DELETE, bucket, Some("website"), "")
+                    .await?
+                {
+                    status if status.is_success() => Ok(()),
+                    StatusCode::NOT_FOUND => Ok(()),
+                    status => Err(Error::StatusCode(
+                        "remove website configuration of",
+                        bucket.to_string(),
+                        status,
+                    )),
+                };
+            }
+        };
+
+        let payload = website_configuration(website);
+        match self
+            .execute(Method::PUT, bucket, Some("website"), &payload)
+            .await?
+        {
+            status if status.is_success() => Ok(()),
+            status => Err(Error::StatusCode(
+                "apply website configuration on",
+                bucket.to_string(),
+                status,
+            )),
+        }
+    }
+
+    /// execute a signed request on the bucket, or on the given sub-resource
+    /// of the bucket, e.g. `cors`, and returns the status code of the response
+    async fn execute(
+        &self,
+        method: Method,
+        bucket: &str,
+        subresource: Option<&str>,
+        payload: &str,
+    ) -> Result<StatusCode, Error> {
+        let path = format!("/{}", bucket);
+        let (query, uri) = match subresource {
+            Some(subresource) => (
+                format!("{}=", subresource),
+                format!("https://{}{}?{}", self.credentials.host, path, subresource),
+            ),
+            None => (
+                String::new(),
+                format!("https://{}{}", self.credentials.host, path),
+            ),
+        };
+
+        let payload_hash = if payload.is_empty() {
+            EMPTY_PAYLOAD_HASH.to_string()
+        } else {
+            hex::encode(crypto::sha256(payload.as_bytes()).map_err(Error::Sign)?)
+        };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+
+        let authorization = authorization(
+            &self.credentials,
+            &Canonical {
+                method: method.as_str(),
+                path: &path,
+                query: &query,
+                payload_hash: &payload_hash,
+            },
+            &amz_date,
+            &date,
+        )?;
+
+        let req = Request::builder()
+            .method(method.to_owned())
+            .uri(uri)
+            .header("host", &self.credentials.host)
+            .header("x-amz-content-sha256", &payload_hash)
+            .header("x-amz-date", &amz_date)
+            .header("authorization", authorization)
+            .body(Body::from(payload.to_string()))
+            .map_err(|err| Error::Request(bucket.to_string(), err))?;
+
+        debug!(
+            method = method.as_str(),
+            host = &self.credentials.host,
+            bucket = bucket,
+            subresource = subresource,
+            "execute a request on cellar"
+        );
+
+        let res = self
+            .inner
+            .request(req)
+            .await
+            .map_err(|err| Error::Execute(bucket.to_string(), err))?;
+
+        Ok(res.status())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Canonical structure
+
+/// parts of a request which are signed beside its headers
+struct Canonical<'a> {
+    method: &'a str,
+    path: &'a str,
+    /// canonical query string, e.g. `cors=`
+    query: &'a str,
+    payload_hash: &'a str,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+/// returns the hmac-sha256 of the message using the given key
+fn hmac(key: &[u8], message: &str) -> Result<Vec<u8>, Error> {
+    crypto::hmac_sha256(key, message.as_bytes()).map_err(Error::Sign)
+}
+
+/// returns the given text escaped to be written in a xml document
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// returns the xml document holding the cors rules, as expected by the
+/// `PutBucketCors` operation
+fn cors_configuration(rules: &[CorsRule]) -> String {
+    let mut document = String::from("<CORSConfiguration>");
+    for rule in rules {
+        document.push_str("<CORSRule>");
+        for (element, values) in [
+            ("AllowedOrigin", &rule.allowed_origins),
+            ("AllowedMethod", &rule.allowed_methods),
+            ("AllowedHeader", &rule.allowed_headers),
+            ("ExposeHeader", &rule.expose_headers),
+        ] {
+            for value in values {
+                document.push_str(&format!("<{0}>{1}</{0}>", element, escape(value)));
+            }
+        }
+
+        if let Some(max_age_seconds) = rule.max_age_seconds {
+            document.push_str(&format!(
+                "<MaxAgeSeconds>{}</MaxAgeSeconds>",
+                max_age_seconds
+            ));
+        }
+
+        document.push_str("</CORSRule>");
+    }
+
+    document.push_str("</CORSConfiguration>");
+    document
+}
+
+/// returns the xml document holding the static website configuration, as
+/// expected by the `PutBucketWebsite` operation
+fn website_configuration(website: &Website) -> String {
+    let mut document = format!(
+        "<WebsiteConfiguration><IndexDocument><Suffix>{}</Suffix></IndexDocument>",
+        escape(&website.index_document)
+    );
+
+    if let Some(error_document) = &website.error_document {
+        document.push_str(&format!(
+            "<ErrorDocument><Key>{}</Key></ErrorDocument>",
+            escape(error_document)
+        ));
+    }
+
+    document.push_str("</WebsiteConfiguration>");
+    document
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the url under which the bucket is served as a static website.
+/// Buckets whose name holds dots are not covered by the wildcard certificate
+/// of cellar, they are expected to be named after a domain pointing to cellar
+pub fn website_endpoint(host: &str, bucket: &str) -> String {
+    if bucket.contains('.') {
+        format!("http://{}", bucket)
+    } else {
+        format!("https://{}.{}", bucket, host)
+    }
+}
+
+/// returns the value of the authorization header of a request, signed using
+/// the AWS signature version 4
+fn authorization(
+    credentials: &Credentials,
+    canonical: &Canonical,
+    amz_date: &str,
+    date: &str,
+) -> Result<String, Error> {
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical_request = format!(
+        "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        canonical.method,
+        canonical.path,
+        canonical.query,
+        credentials.host,
+        canonical.payload_hash,
+        amz_date,
+        signed_headers,
+        canonical.payload_hash
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date, REGION, SERVICE);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(crypto::sha256(canonical_request.as_bytes()).map_err(Error::Sign)?)
+    );
+
+    let key = hmac(format!("AWS4{}", *credentials.key_secret).as_bytes(), date)?;
+    let key = hmac(&key, REGION)?;
+    let key = hmac(&key, SERVICE)?;
+    let key = hmac(&key, "aws4_request")?;
+    let signature = hex::encode(hmac(&key, &string_to_sign)?);
+
+    Ok(format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.key_id, scope, signed_headers, signature
+    ))
+}
@@ -2,27 +2,197 @@
 //!
 //! This module provides helpers to create a clever-cloud client
 
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime},
+};
+
 use base64::{engine::general_purpose::STANDARD as BASE64_ENGINE, Engine};
-use clevercloud_sdk::oauth10a::{
-    connector::{GaiResolver, HttpsConnector, HttpsConnectorBuilder, ProxyConnector},
-    proxy::{self, ProxyBuilder, ProxyConnectorBuilder},
-    Credentials,
+use clevercloud_sdk::{
+    oauth10a::{
+        connector::{HttpsConnector, HttpsConnectorBuilder, ProxyConnector},
+        proxy::{self, ProxyBuilder, ProxyConnectorBuilder},
+        ClientError, Credentials,
+    },
+    v2::{addon, myself},
+    v4::{
+        addon_provider::{config_provider::addon::environment, plan},
+        products::zones,
+    },
+};
+use hyper::{
+    client::HttpConnector,
+    header::{self, HeaderValue, InvalidHeaderValue},
+    StatusCode,
 };
-use hyper::client::HttpConnector;
 use k8s_openapi::api::core::v1::Secret;
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{
+    histogram_opts, opts, register_counter_vec, register_histogram_vec, CounterVec, HistogramVec,
+};
+use rand::Rng;
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier},
+    version, Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName,
+    SupportedProtocolVersion,
+};
 use tempfile::NamedTempFile;
 use tokio::{fs::File, io::AsyncWriteExt, task::spawn_blocking as blocking};
+use tracing::{debug, warn};
 
 use crate::svc::{
-    cfg::{self, NamespaceConfiguration, Proxy},
+    cfg::{self, Api, NamespaceConfiguration, Proxy, RateLimit, RequestRetry},
+    clevercloud::{
+        api::CleverApi,
+        connector::{CachingResolver, GuardedConnector, MeteredConnector},
+    },
+    crypto,
     k8s::resource,
 };
 
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static CLIENT_SCOPE_VIOLATION: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "clever_cloud_client_scope_violation",
+            "number of calls rejected as out of the scope of the client",
+        ),
+        &["scope", "kind", "operation"]
+    )
+    .expect("metrics 'clever_cloud_client_scope_violation' to not be already registered")
+});
+
+#[cfg(feature = "metrics")]
+static CLIENT_THROTTLED_REQUEST: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "clever_cloud_client_throttled_request",
+            "number of calls delayed by the rate limiter of the organisation",
+        ),
+        &["organisation"]
+    )
+    .expect("metrics 'clever_cloud_client_throttled_request' to not be already registered")
+});
+
+#[cfg(feature = "metrics")]
+static CLIENT_THROTTLED_DURATION: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "clever_cloud_client_throttled_duration",
+            "duration during which calls are delayed by the rate limiter of the organisation",
+        ),
+        &["organisation", "unit"]
+    )
+    .expect("metrics 'clever_cloud_client_throttled_duration' to not be already registered")
+});
+
+#[cfg(feature = "metrics")]
+static CLIENT_GROUP_REQUEST_SUCCESS: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "clever_cloud_client_group_request_success",
+            "number of successful calls on clever cloud's apis per endpoint group",
+        ),
+        &["group", "operation"]
+    )
+    .expect("metrics 'clever_cloud_client_group_request_success' to not be already registered")
+});
+
+#[cfg(feature = "metrics")]
+static CLIENT_GROUP_REQUEST_FAILURE: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "clever_cloud_client_group_request_failure",
+            "number of failed calls on clever cloud's apis per endpoint group",
+        ),
+        &["group", "operation", "status"]
+    )
+    .expect("metrics 'clever_cloud_client_group_request_failure' to not be already registered")
+});
+
+#[cfg(feature = "metrics")]
+static CLIENT_GROUP_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        histogram_opts!(
+            "clever_cloud_client_group_request_duration_seconds",
+            "latency of calls on clever cloud's apis per endpoint group",
+        ),
+        &["group", "operation"]
+    )
+    .expect(
+        "metrics 'clever_cloud_client_group_request_duration_seconds' to not be already registered",
+    )
+});
+
+#[cfg(feature = "metrics")]
+static CLIENT_RATE_LIMITED: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "clever_cloud_client_rate_limited",
+            "number of calls rejected by the rate limits of clever cloud's apis",
+        ),
+        &["group"]
+    )
+    .expect("metrics 'clever_cloud_client_rate_limited' to not be already registered")
+});
+
+#[cfg(feature = "metrics")]
+static CLIENT_GROUP_REQUEST_RETRY: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "clever_cloud_client_group_request_retry",
+            "number of retried calls on clever cloud's apis per endpoint group",
+        ),
+        &["group", "operation", "status"]
+    )
+    .expect("metrics 'clever_cloud_client_group_request_retry' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// State
+
+/// token buckets limiting the rate of calls on the api, keyed by organisation
+static BUCKETS: Mutex<BTreeMap<String, Bucket>> = Mutex::new(BTreeMap::new());
+
+/// retries of calls on the api, the default configuration is used until it is
+/// configured
+static RETRY: RwLock<Option<RequestRetry>> = RwLock::new(None);
+
 // -----------------------------------------------------------------------------
 // types
 
-pub type Client =
-    clevercloud_sdk::Client<ProxyConnector<HttpsConnector<HttpConnector<GaiResolver>>>>;
+pub type Connector = ProxyConnector<
+    HttpsConnector<MeteredConnector<GuardedConnector<HttpConnector<CachingResolver>>>>,
+>;
+pub type Client = clevercloud_sdk::Client<Connector>;
+
+// -----------------------------------------------------------------------------
+// InsecureVerifier structure
+
+/// certificate verifier accepting any certificate, used when the verification
+/// is explicitly disabled
+struct InsecureVerifier;
+
+impl ServerCertVerifier for InsecureVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
 
 // -----------------------------------------------------------------------------
 // Error enumeration
@@ -31,6 +201,16 @@ pub type Client =
 pub enum Error {
     #[error("failed to create clever cloud client, {0}")]
     CleverClient(proxy::Error),
+    #[error("failed to set authorization of the proxy, {0}")]
+    ProxyAuthorization(InvalidHeaderValue),
+    #[error("failed to read certificate authorities bundle '{0}', {1}")]
+    CaBundle(String, std::io::Error),
+    #[error("failed to find a valid certificate authority in bundle '{0}'")]
+    EmptyCaBundle(String),
+    #[error("failed to parse tls version '{0}', available options are '1.2' or '1.3'")]
+    TlsVersion(String),
+    #[error("failed to configure tls, {0}")]
+    Tls(rustls::Error),
     #[error("failed to retrieve data from secret '{0}/{1}'")]
     SecretData(String, String),
     #[error("failed to find key '{0}' in secret '{1}/{2}")]
@@ -43,6 +223,19 @@ pub enum Error {
     Io(std::io::Error),
     #[error("failed to parse configuration file, {0}")]
     Configuration(cfg::Error),
+    #[error("failed to compute fingerprint of configuration, {0}")]
+    Fingerprint(serde_json::Error),
+}
+
+// -----------------------------------------------------------------------------
+// ScopeError structure
+
+#[derive(thiserror::Error, Debug)]
+#[error("refuse to execute '{operation}' for kind '{kind}', client is scoped to kind '{scope}'")]
+pub struct ScopeError {
+    pub scope: String,
+    pub kind: String,
+    pub operation: String,
 }
 
 impl From<proxy::Error> for Error {
@@ -72,41 +265,573 @@ impl From<cfg::Error> for Error {
     }
 }
 
+// -----------------------------------------------------------------------------
+// StatusCodeExt trait
+
+/// errors of calls on the api which may hold the status code of the response
+pub trait StatusCodeExt {
+    /// returns the status code of the response, if the api has answered
+    fn status_code(&self) -> Option<u16>;
+}
+
+impl StatusCodeExt for ClientError {
+    fn status_code(&self) -> Option<u16> {
+        match self {
+            Self::StatusCode(code, _) => Some(code.as_u16()),
+            _ => None,
+        }
+    }
+}
+
+impl StatusCodeExt for addon::Error {
+    fn status_code(&self) -> Option<u16> {
+        match self {
+            Self::Get(_, _, err)
+            | Self::List(_, err)
+            | Self::Create(_, err)
+            | Self::Delete(_, _, err)
+            | Self::Environment(_, _, err) => err.status_code(),
+        }
+    }
+}
+
+// errors of the following modules of the sdk do not expose the response
+impl StatusCodeExt for plan::Error {
+    fn status_code(&self) -> Option<u16> {
+        None
+    }
+}
+
+impl StatusCodeExt for environment::Error {
+    fn status_code(&self) -> Option<u16> {
+        None
+    }
+}
+
+impl StatusCodeExt for myself::Error {
+    fn status_code(&self) -> Option<u16> {
+        None
+    }
+}
+
+impl StatusCodeExt for zones::Error {
+    fn status_code(&self) -> Option<u16> {
+        None
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Bucket structure
+
+/// token bucket of an organisation, tokens are refilled continuously at the
+/// configured rate up to the burst. A call takes a token even if the bucket is
+/// empty, so the bucket goes negative and concurrent calls wait in turn
+#[derive(Clone, Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// take a token from the bucket and returns the delay to wait for before
+    /// executing the call, if any
+    fn take(&mut self, config: &RateLimit) -> Option<Duration> {
+        let now = Instant::now();
+        let rate = f64::from(config.requests_per_second);
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * rate).min(f64::from(config.burst.max(1)));
+        self.updated_at = now;
+        self.tokens -= 1.0;
+
+        if self.tokens >= 0.0 {
+            None
+        } else {
+            Some(Duration::from_secs_f64(-self.tokens / rate))
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// SharedClient structure
+
+/// global clever cloud client shared by reconcilers, which is replaced at
+/// runtime once the configuration or the credentials are rotated. Callers get
+/// the current client on each reconciliation, in-flight calls keep the client
+/// they started with
+#[derive(Clone, Debug)]
+pub struct SharedClient {
+    inner: Arc<RwLock<Client>>,
+}
+
+impl From<Client> for SharedClient {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(client: Client) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(client)),
+        }
+    }
+}
+
+impl SharedClient {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the current client
+    pub fn get(&self) -> Client {
+        match self.inner.read() {
+            Ok(client) => client.to_owned(),
+            Err(poisoned) => poisoned.into_inner().to_owned(),
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// replace the current client by the given one
+    pub fn set(&self, client: Client) {
+        match self.inner.write() {
+            Ok(mut current) => *current = client,
+            Err(poisoned) => *poisoned.into_inner() = client,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// ScopedClient structure
+
+/// wraps a clever cloud client with the kind of custom resources it is allowed
+/// to act for. Clients created from a namespace override are scoped to the
+/// reconciled kind, so a bug routing them to another kind is rejected before
+/// any request is sent. The global client is unscoped.
+#[derive(Clone, Debug)]
+pub struct ScopedClient {
+    inner: Client,
+    scope: Option<String>,
+    rate_limit: Option<(String, RateLimit)>,
+    backend: Option<Arc<dyn CleverApi>>,
+}
+
+impl ScopedClient {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn unscoped(inner: Client) -> Self {
+        Self {
+            inner,
+            scope: None,
+            rate_limit: None,
+            backend: None,
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn scoped(inner: Client, kind: &str) -> Self {
+        Self {
+            inner,
+            scope: Some(kind.to_string()),
+            rate_limit: None,
+            backend: None,
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// limit the rate of calls authorized by the client using the token bucket
+    /// of the organisation, which is shared by all clients acting on it
+    pub fn with_rate_limit(mut self, organisation: &str, config: &RateLimit) -> Self {
+        if config.enabled && config.requests_per_second > 0 {
+            self.rate_limit = Some((organisation.to_string(), config.to_owned()));
+        }
+
+        self
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// serve calls made through [`ScopedClient::backend`] with the given
+    /// implementation of the api instead of the inner client, if any
+    pub fn with_backend(mut self, backend: Option<Arc<dyn CleverApi>>) -> Self {
+        if backend.is_some() {
+            self.backend = backend;
+        }
+
+        self
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn scope(&self) -> Option<&str> {
+        self.scope.as_deref()
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the inner client, if the operation for the given kind is in the
+    /// scope of the client, once the rate limit of the organisation allows it
+    pub async fn authorize(&self, kind: &str, operation: &str) -> Result<&Client, ScopeError> {
+        match &self.scope {
+            Some(scope) if scope != kind => {
+                warn!(
+                    scope = scope,
+                    kind = kind,
+                    operation = operation,
+                    "Security: reject call on clever-cloud api, it is out of the scope of the client",
+                );
+
+                #[cfg(feature = "metrics")]
+                CLIENT_SCOPE_VIOLATION
+                    .with_label_values(&[scope, kind, operation])
+                    .inc();
+
+                Err(ScopeError {
+                    scope: scope.to_owned(),
+                    kind: kind.to_string(),
+                    operation: operation.to_string(),
+                })
+            }
+            _ => {
+                if let Some((organisation, config)) = &self.rate_limit {
+                    throttle(organisation, config, operation).await;
+                }
+
+                Ok(&self.inner)
+            }
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// same as [`ScopedClient::authorize`], returns the api on which the
+    /// operation is executed, the inner client or the overriding backend
+    pub async fn backend(&self, kind: &str, operation: &str) -> Result<&dyn CleverApi, ScopeError> {
+        let client = self.authorize(kind, operation).await?;
+
+        let api: &dyn CleverApi = match &self.backend {
+            Some(backend) => backend.as_ref(),
+            None => client,
+        };
+
+        Ok(api)
+    }
+}
+
 // -----------------------------------------------------------------------------
 // helpers
 
+/// await the call on the api, recording its outcome and latency under the
+/// given endpoint group and operation. A response with the status code `429`
+/// is counted as a rate limit hit
+pub async fn observe<T, E, F>(group: &str, operation: &str, call: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+    E: StatusCodeExt,
+{
+    #[cfg(feature = "metrics")]
+    let instant = Instant::now();
+
+    let result = call.await;
+
+    #[cfg(feature = "metrics")]
+    CLIENT_GROUP_REQUEST_DURATION
+        .with_label_values(&[group, operation])
+        .observe(instant.elapsed().as_secs_f64());
+
+    match &result {
+        Ok(_) => {
+            #[cfg(feature = "metrics")]
+            CLIENT_GROUP_REQUEST_SUCCESS
+                .with_label_values(&[group, operation])
+                .inc();
+        }
+        Err(err) => {
+            let status = err.status_code();
+            if status == Some(StatusCode::TOO_MANY_REQUESTS.as_u16()) {
+                warn!(
+                    group = group,
+                    operation = operation,
+                    "Call on the clever cloud api has hit its rate limits",
+                );
+
+                #[cfg(feature = "metrics")]
+                CLIENT_RATE_LIMITED.with_label_values(&[group]).inc();
+            }
+
+            #[cfg(feature = "metrics")]
+            CLIENT_GROUP_REQUEST_FAILURE
+                .with_label_values(&[
+                    group,
+                    operation,
+                    &status
+                        .map(|code| code.to_string())
+                        .unwrap_or_else(|| "none".to_string()),
+                ])
+                .inc();
+        }
+    }
+
+    result
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// configure the retries of calls on the api made through [`retry`] and
+/// [`guarded`]
+pub fn configure(config: &RequestRetry) {
+    if let Ok(mut state) = RETRY.write() {
+        *state = Some(config.to_owned());
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if a call answered with the given status code has failed
+/// transiently, the api is then unavailable or is rate limiting the operator
+fn transient(status: Option<u16>) -> bool {
+    status == Some(StatusCode::TOO_MANY_REQUESTS.as_u16())
+        || status == Some(StatusCode::BAD_GATEWAY.as_u16())
+        || status == Some(StatusCode::SERVICE_UNAVAILABLE.as_u16())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the delay before the given retry of a call, the exponential delay
+/// is jittered, so calls failing together are not retried at the same time
+fn delay(config: &RequestRetry, retry: u32) -> Duration {
+    let delay = config
+        .initial_delay
+        .saturating_mul(2u64.saturating_pow(retry))
+        .min(config.max_delay)
+        .max(1);
+
+    Duration::from_millis(rand::thread_rng().gen_range(delay / 2..=delay))
+}
+
+/// execute the call on the api, through [`observe`], and retry it while it
+/// fails transiently up to the configured number of retries. It must only be
+/// used for idempotent calls, e.g. `GET`, `PUT` or `DELETE` requests
+pub async fn retry<T, E, F, Fut>(group: &str, operation: &str, call: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: StatusCodeExt,
+{
+    guarded(group, operation, call, || async { Ok(None) }).await
+}
+
+/// same as [`retry`] for calls which are not idempotent, e.g. `POST`
+/// requests. A call rejected by the rate limits of the api has not been
+/// executed and is retried as is. Otherwise, the api may have executed the
+/// call before failing, so the guard is asked for the result of the call, if
+/// it has been executed, before retrying it
+pub async fn guarded<T, E, F, Fut, G, GFut>(
+    group: &str,
+    operation: &str,
+    mut call: F,
+    mut guard: G,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    G: FnMut() -> GFut,
+    GFut: Future<Output = Result<Option<T>, E>>,
+    E: StatusCodeExt,
+{
+    let config = RETRY
+        .read()
+        .ok()
+        .and_then(|config| config.to_owned())
+        .unwrap_or_default();
+
+    let mut retries = 0;
+    loop {
+        let err = match observe(group, operation, call()).await {
+            Err(err) if retries < config.max_retries && transient(err.status_code()) => err,
+            result => return result,
+        };
+
+        let status = err
+            .status_code()
+            .map(|code| code.to_string())
+            .unwrap_or_else(|| "none".to_string());
+
+        let delay = delay(&config, retries);
+        retries += 1;
+
+        debug!(
+            group = group,
+            operation = operation,
+            status = &status,
+            retry = retries,
+            delay = delay.as_millis() as u64,
+            "Retry call on the clever cloud api, it has failed transiently",
+        );
+
+        #[cfg(feature = "metrics")]
+        CLIENT_GROUP_REQUEST_RETRY
+            .with_label_values(&[group, operation, &status])
+            .inc();
+
+        tokio::time::sleep(delay).await;
+
+        if err.status_code() != Some(StatusCode::TOO_MANY_REQUESTS.as_u16()) {
+            match guard().await {
+                Ok(Some(value)) => return Ok(value),
+                Ok(None) => {}
+                Err(_) => return Err(err),
+            }
+        }
+    }
+}
+
 #[cfg_attr(feature = "trace", tracing::instrument)]
-pub fn try_new(credentials: Credentials, proxy: &Option<Proxy>) -> Result<Client, Error> {
-    let connector = match proxy {
-        Some(proxy) if proxy.https.is_some() || proxy.http.is_some() => {
-            let proxy = ProxyBuilder::try_from(
-                proxy.https.to_owned().unwrap_or_else(|| {
-                    proxy
-                        .http
-                        .to_owned()
-                        .expect("to have one of http or https value in proxy configuration file")
-                }),
+/// returns the tls configuration of connections to the api, trusting the
+/// webpki roots and the given certificate authorities, if any
+pub fn tls(config: &cfg::Tls) -> Result<ClientConfig, Error> {
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            ta.subject,
+            ta.spki,
+            ta.name_constraints,
+        )
+    }));
+
+    if let Some(path) = &config.ca_bundle {
+        let bundle = path.display().to_string();
+        let file =
+            std::fs::File::open(path).map_err(|err| Error::CaBundle(bundle.to_owned(), err))?;
+        let certificates = rustls_pemfile::certs(&mut std::io::BufReader::new(file))
+            .map_err(|err| Error::CaBundle(bundle.to_owned(), err))?;
+
+        let (added, _) = roots.add_parsable_certificates(&certificates);
+        if added == 0 {
+            return Err(Error::EmptyCaBundle(bundle));
+        }
+    }
+
+    let versions: &[&SupportedProtocolVersion] = match config.min_version.as_deref() {
+        None | Some("1.2") => &[&version::TLS13, &version::TLS12],
+        Some("1.3") => &[&version::TLS13],
+        Some(other) => return Err(Error::TlsVersion(other.to_string())),
+    };
+
+    let mut tls = crypto::policy(ClientConfig::builder(), versions)
+        .map_err(Error::Tls)?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    if config.insecure_skip_verify {
+        warn!("Skip the verification of certificates of the clever cloud api, connections are insecure");
+        tls.dangerous()
+            .set_certificate_verifier(Arc::new(InsecureVerifier));
+    }
+
+    Ok(tls)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns a connector which goes through the given proxy, if any, or the one
+/// configured by the environment. The connector is tuned using the connection
+/// settings of the api configuration
+pub fn connector(api: &Api, proxy: &Option<Proxy>) -> Result<Connector, Error> {
+    let mut http = HttpConnector::new_with_resolver(CachingResolver::new(Duration::from_secs(
+        api.dns_cache_ttl,
+    )));
+
+    http.enforce_http(false);
+    http.set_nodelay(true);
+    http.set_keepalive(Some(Duration::from_secs(api.tcp_keepalive)));
+    http.set_connect_timeout(
+        Some(api.http.connect_timeout)
+            .filter(|timeout| *timeout > 0)
+            .map(Duration::from_secs),
+    );
+
+    let http = GuardedConnector::new(http, &api.http);
+    let builder = HttpsConnectorBuilder::new()
+        .with_tls_config(tls(&api.tls)?)
+        .https_or_http()
+        .enable_http1();
+
+    let https = if api.http2 {
+        builder
+            .enable_http2()
+            .wrap_connector(MeteredConnector::new(http))
+    } else {
+        builder.wrap_connector(MeteredConnector::new(http))
+    };
+
+    let connector = match Proxy::resolve(proxy) {
+        Some(proxy) => {
+            let authorization = proxy.authorization();
+            let mut proxy = ProxyBuilder::try_from(
+                proxy
+                    .url()
+                    .expect("to have one of http or https value in proxy configuration"),
                 proxy.no.to_owned(),
             )?;
 
-            ProxyConnectorBuilder::default().with_proxy(proxy).build(
-                HttpsConnectorBuilder::new()
-                    .with_webpki_roots()
-                    .https_or_http()
-                    .enable_http1()
-                    .build(),
-            )?
+            if let Some(authorization) = authorization {
+                proxy.set_header(
+                    header::PROXY_AUTHORIZATION,
+                    HeaderValue::from_str(&authorization).map_err(Error::ProxyAuthorization)?,
+                );
+            }
+
+            ProxyConnectorBuilder::default()
+                .with_proxy(proxy)
+                .build(https)?
         }
-        _ => ProxyConnectorBuilder::try_from_env()?,
+        None => ProxyConnectorBuilder::default().build(https)?,
     };
 
+    Ok(connector)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// wait until the token bucket of the organisation allows a call
+pub async fn throttle(organisation: &str, config: &RateLimit, operation: &str) {
+    let delay = match BUCKETS.lock() {
+        Ok(mut buckets) => buckets
+            .entry(organisation.to_string())
+            .or_insert_with(|| Bucket {
+                tokens: f64::from(config.burst.max(1)),
+                updated_at: Instant::now(),
+            })
+            .take(config),
+        Err(_) => None,
+    };
+
+    if let Some(delay) = delay {
+        debug!(
+            organisation = organisation,
+            operation = operation,
+            delay = delay.as_millis() as u64,
+            "Throttle call on clever-cloud api, rate limit of the organisation is reached",
+        );
+
+        #[cfg(feature = "metrics")]
+        CLIENT_THROTTLED_REQUEST
+            .with_label_values(&[organisation])
+            .inc();
+
+        #[cfg(feature = "metrics")]
+        CLIENT_THROTTLED_DURATION
+            .with_label_values(&[organisation, "us"])
+            .inc_by(delay.as_micros() as f64);
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+pub fn try_new(api: &Api, proxy: &Option<Proxy>) -> Result<Client, Error> {
+    let credentials: Credentials = api.to_owned().into();
+
     Ok(Client::builder()
         .with_credentials(credentials)
-        .build(connector))
+        .build(connector(api, proxy)?))
 }
 
 #[cfg_attr(feature = "trace", tracing::instrument)]
 pub async fn try_from(secret: Secret) -> Result<Client, Error> {
+    let configuration = configuration(secret).await?;
+
+    try_new(&configuration.api, &configuration.proxy)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the configuration of the clever cloud client held by the `config`
+/// key of the secret
+pub async fn configuration(secret: Secret) -> Result<NamespaceConfiguration, Error> {
     let buf = blocking(move || {
         let (namespace, name) = resource::namespaced_name(&secret);
         let data = match &secret.data {
@@ -135,7 +860,129 @@ pub async fn try_from(secret: Secret) -> Result<Client, Error> {
     file.write_all(&buf).await?;
     file.sync_all().await?;
 
-    let configuration = NamespaceConfiguration::try_from(path)?;
+    Ok(NamespaceConfiguration::try_from(path)?)
+}
 
-    try_new(configuration.api.into(), &configuration.proxy)
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the client is still allowed to interact with the given
+/// organisation, this is used to know if credentials are still valid
+pub async fn validate(client: &Client, organisation: &str) -> Result<bool, addon::Error> {
+    match observe("addon", "list", addon::list(client, organisation)).await {
+        Ok(_) => Ok(true),
+        Err(addon::Error::List(_, ClientError::StatusCode(code, _)))
+            if StatusCode::UNAUTHORIZED.as_u16() == code.as_u16()
+                || StatusCode::FORBIDDEN.as_u16() == code.as_u16() =>
+        {
+            Ok(false)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use clevercloud_sdk::oauth10a::RestClient;
+    use hyper::{
+        server::conn::AddrStream,
+        service::{make_service_fn, service_fn},
+        Body, Response, Server,
+    };
+    use serde_json::{json, Value};
+
+    use super::{connector, Client, ScopedClient};
+    use crate::svc::cfg::Api;
+
+    /// returns a client of the api served at the given endpoint
+    fn client(endpoint: &str) -> Client {
+        let api: Api = serde_json::from_value(json!({
+            "endpoint": endpoint,
+            "token": "",
+            "secret": "",
+            "consumerKey": "",
+            "consumerSecret": "",
+        }))
+        .expect("api configuration to be deserialized");
+
+        Client::builder().build(connector(&api, &None).expect("connector"))
+    }
+
+    /// start a local server answering an empty json object and returns its
+    /// address and the number of connections it has accepted
+    fn serve() -> (SocketAddr, Arc<AtomicUsize>) {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.to_owned();
+        let make_service = make_service_fn(move |_: &AddrStream| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async {
+                Ok::<_, Infallible>(service_fn(|_| async {
+                    Ok::<_, Infallible>(Response::new(Body::from("{}")))
+                }))
+            }
+        });
+
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        (addr, connections)
+    }
+
+    #[tokio::test]
+    async fn connections_are_reused() {
+        let (addr, connections) = serve();
+        let client = client(&format!("http://{}", addr));
+        for _ in 0..5 {
+            let _: Value = client
+                .get(&format!("http://{}/v2/self", addr))
+                .await
+                .expect("request to succeed");
+        }
+
+        assert_eq!(1, connections.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn scoped_client_rejects_other_kinds() {
+        let apis = ScopedClient::scoped(client("http://localhost"), "PostgreSql");
+
+        let err = apis
+            .authorize("Redis", "addon::get")
+            .await
+            .expect_err("call to be out of scope");
+
+        assert_eq!("PostgreSql", err.scope);
+        assert_eq!("Redis", err.kind);
+        assert_eq!("addon::get", err.operation);
+        assert_eq!(
+            "refuse to execute 'addon::get' for kind 'Redis', client is scoped to kind 'PostgreSql'",
+            err.to_string()
+        );
+        assert!(apis.backend("Redis", "addon::get").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn scoped_client_authorizes_its_kind() {
+        let apis = ScopedClient::scoped(client("http://localhost"), "PostgreSql");
+
+        assert!(apis.authorize("PostgreSql", "addon::get").await.is_ok());
+        assert!(apis.backend("PostgreSql", "addon::get").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn unscoped_client_authorizes_all_kinds() {
+        let apis = ScopedClient::unscoped(client("http://localhost"));
+
+        assert_eq!(None, apis.scope());
+        assert!(apis.authorize("PostgreSql", "addon::get").await.is_ok());
+        assert!(apis.authorize("Redis", "addon::delete").await.is_ok());
+    }
 }
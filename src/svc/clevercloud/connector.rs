@@ -0,0 +1,404 @@
+//! # Connector module
+//!
+//! This module provide the building blocks of the connector used to reach the
+//! Clever Cloud's api: a resolver caching dns resolution, a connector counting
+//! newly opened connections, so connection reuse can be observed, and a
+//! connector retrying the opening of connections and bounding the duration
+//! and the size of exchanges on them, so a hung api does not stall
+//! reconcilers forever.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::future::poll_fn;
+use hyper::{
+    client::connect::{
+        dns::{GaiResolver, Name},
+        Connected, Connection,
+    },
+    service::Service,
+    Uri,
+};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_counter_vec, CounterVec};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::{sleep, Sleep},
+};
+use tracing::{debug, trace};
+
+use crate::svc::cfg::Http;
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static CLIENT_CONNECTION: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "clever_cloud_client_connection",
+            "number of connections opened to clever cloud's apis",
+        ),
+        &["host"]
+    )
+    .expect("metrics 'clever_cloud_client_connection' to not be already registered")
+});
+
+#[cfg(feature = "metrics")]
+static CLIENT_CONNECTION_RETRY: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "clever_cloud_client_connection_retry",
+            "number of retried openings of connections to clever cloud's apis",
+        ),
+        &["host"]
+    )
+    .expect("metrics 'clever_cloud_client_connection_retry' to not be already registered")
+});
+
+#[cfg(feature = "metrics")]
+static CLIENT_CONNECTION_ABORTED: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "clever_cloud_client_connection_aborted",
+            "number of connections to clever cloud's apis aborted by a timeout or a limit",
+        ),
+        &["reason"]
+    )
+    .expect("metrics 'clever_cloud_client_connection_aborted' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// delay between two attempts to open a connection, multiplied by the number
+/// of the attempt
+pub const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+// -----------------------------------------------------------------------------
+// CachingResolver structure
+
+type Addrs = std::vec::IntoIter<SocketAddr>;
+type Resolutions = HashMap<String, (Instant, Vec<SocketAddr>)>;
+
+/// resolver which keeps the result of dns resolutions for the given duration
+#[derive(Clone, Debug)]
+pub struct CachingResolver {
+    inner: GaiResolver,
+    ttl: Duration,
+    cache: Arc<Mutex<Resolutions>>,
+}
+
+impl CachingResolver {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            inner: GaiResolver::new(),
+            ttl,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl Service<Name> for CachingResolver {
+    type Response = Addrs;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let host = name.as_str().to_string();
+        let cached = self.cache.lock().ok().and_then(|cache| {
+            cache
+                .get(&host)
+                .filter(|(resolved_at, _)| resolved_at.elapsed() < self.ttl)
+                .map(|(_, addrs)| addrs.to_owned())
+        });
+
+        if let Some(addrs) = cached {
+            trace!(host = &host, "Use cached dns resolution");
+            return Box::pin(async move { Ok(addrs.into_iter()) });
+        }
+
+        let cache = self.cache.to_owned();
+        let resolving = self.inner.call(name);
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = resolving.await?.collect();
+            if let Ok(mut cache) = cache.lock() {
+                cache.insert(host, (Instant::now(), addrs.to_owned()));
+            }
+
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// MeteredConnector structure
+
+/// connector which counts the connections it opens, requests sent over a
+/// connection of the pool do not go through it
+#[derive(Clone, Debug)]
+pub struct MeteredConnector<C> {
+    inner: C,
+}
+
+impl<C> MeteredConnector<C> {
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(inner)))]
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+}
+
+impl<C> Service<Uri> for MeteredConnector<C>
+where
+    C: Service<Uri>,
+{
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = C::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        trace!(uri = uri.to_string(), "Open a new connection");
+
+        #[cfg(feature = "metrics")]
+        CLIENT_CONNECTION
+            .with_label_values(&[uri.host().unwrap_or("<none>")])
+            .inc();
+
+        self.inner.call(uri)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// GuardedConnector structure
+
+/// connector which retries the opening of connections and guards the
+/// connections it opens with the timeout and the size limit of the
+/// configuration
+#[derive(Clone, Debug)]
+pub struct GuardedConnector<C> {
+    inner: C,
+    config: Http,
+}
+
+impl<C> GuardedConnector<C> {
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(inner)))]
+    pub fn new(inner: C, config: &Http) -> Self {
+        Self {
+            inner,
+            config: config.to_owned(),
+        }
+    }
+}
+
+impl<C> Service<Uri> for GuardedConnector<C>
+where
+    C: Service<Uri> + Clone + Send + 'static,
+    C::Response: Send,
+    C::Error: std::fmt::Display + Send,
+    C::Future: Send,
+{
+    type Response = GuardedStream<C::Response>;
+    type Error = C::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let mut inner = self.inner.to_owned();
+        let config = self.config.to_owned();
+        let first = self.inner.call(uri.to_owned());
+
+        Box::pin(async move {
+            let mut result = first.await;
+            let mut attempt = 0;
+            while let Err(err) = &result {
+                if attempt >= config.connect_retries {
+                    break;
+                }
+
+                attempt += 1;
+                debug!(
+                    uri = uri.to_string(),
+                    attempt = attempt,
+                    error = err.to_string(),
+                    "Retry to open a connection",
+                );
+
+                #[cfg(feature = "metrics")]
+                CLIENT_CONNECTION_RETRY
+                    .with_label_values(&[uri.host().unwrap_or("<none>")])
+                    .inc();
+
+                sleep(CONNECT_RETRY_DELAY * attempt).await;
+                result = match poll_fn(|cx| inner.poll_ready(cx)).await {
+                    Ok(()) => inner.call(uri.to_owned()).await,
+                    Err(err) => Err(err),
+                };
+            }
+
+            result.map(|stream| GuardedStream::new(stream, &config))
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// GuardedStream structure
+
+/// connection which fails once reading or writing does not progress during
+/// the timeout, or once the response to a request exceeds the size limit. The
+/// size of a response is the number of bytes read since the last write, so
+/// multiplexed responses over http/2 are accounted together
+#[derive(Debug)]
+pub struct GuardedStream<S> {
+    inner: S,
+    timeout: Option<Duration>,
+    max_response_size: Option<u64>,
+    deadline: Option<Pin<Box<Sleep>>>,
+    read: u64,
+}
+
+impl<S> GuardedStream<S> {
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(inner)))]
+    pub fn new(inner: S, config: &Http) -> Self {
+        Self {
+            inner,
+            timeout: Some(config.request_timeout)
+                .filter(|timeout| *timeout > 0)
+                .map(Duration::from_secs),
+            max_response_size: Some(config.max_response_size).filter(|size| *size > 0),
+            deadline: None,
+            read: 0,
+        }
+    }
+
+    /// returns an error once the deadline, armed on the first pending
+    /// operation, is elapsed
+    fn poll_deadline<T>(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<T>> {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return Poll::Pending,
+        };
+
+        let deadline = self
+            .deadline
+            .get_or_insert_with(|| Box::pin(sleep(timeout)));
+
+        match deadline.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                self.deadline = None;
+
+                #[cfg(feature = "metrics")]
+                CLIENT_CONNECTION_ABORTED
+                    .with_label_values(&["timeout"])
+                    .inc();
+
+                Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("connection did not progress during {:?}", timeout),
+                )))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S> AsyncRead for GuardedStream<S>
+where
+    S: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(Ok(())) => {
+                this.deadline = None;
+                this.read += (buf.filled().len() - filled) as u64;
+
+                match this.max_response_size {
+                    Some(max) if this.read > max => {
+                        #[cfg(feature = "metrics")]
+                        CLIENT_CONNECTION_ABORTED
+                            .with_label_values(&["oversized"])
+                            .inc();
+
+                        Poll::Ready(Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("response exceeds the maximum size of {} bytes", max),
+                        )))
+                    }
+                    _ => Poll::Ready(Ok(())),
+                }
+            }
+            Poll::Pending => this.poll_deadline(cx),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+        }
+    }
+}
+
+impl<S> AsyncWrite for GuardedStream<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        match Pin::new(&mut this.inner).poll_write(cx, buf) {
+            Poll::Ready(Ok(written)) => {
+                // a new request is sent, the size of its response is counted
+                // from now on
+                this.deadline = None;
+                this.read = 0;
+                Poll::Ready(Ok(written))
+            }
+            Poll::Pending => this.poll_deadline(cx),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl<S> Connection for GuardedStream<S>
+where
+    S: Connection,
+{
+    fn connected(&self) -> Connected {
+        self.inner.connected()
+    }
+}
@@ -0,0 +1,145 @@
+//! # ElasticSearch module
+//!
+//! This module provide a minimal client of the api exposed by the ElasticSearch
+//! addon of Clever Cloud to manage index templates, using the credentials of
+//! the addon.
+//!
+//! See <https://www.elastic.co/guide/en/elasticsearch/reference/current/index-templates.html>
+
+use std::{collections::BTreeMap, fmt::Debug};
+
+use base64::{engine::general_purpose::STANDARD as BASE64_ENGINE, Engine};
+use hyper::{header, Body, Method, Request, StatusCode};
+use tracing::debug;
+
+use crate::svc::{
+    cfg::{Api, Proxy},
+    clevercloud::client::{self, Connector},
+    redact::Redacted,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const ES_HOST: &str = "ES_ADDON_HOST";
+pub const ES_USER: &str = "ES_ADDON_USER";
+pub const ES_PASSWORD: &str = "ES_ADDON_PASSWORD";
+pub const ES_KIBANA_HOST: &str = "ES_ADDON_KIBANA_HOST";
+pub const ES_APM_HOST: &str = "ES_ADDON_APM_HOST";
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to find key '{0}' in elasticsearch credentials")]
+    MissingKey(&'static str),
+    #[error("failed to create http client, {0}")]
+    Connector(client::Error),
+    #[error("failed to serialize index template '{0}', {1}")]
+    Serialize(String, serde_json::Error),
+    #[error("failed to build request for '{0}', {1}")]
+    Request(String, hyper::http::Error),
+    #[error("failed to execute request for '{0}', {1}")]
+    Execute(String, hyper::Error),
+    #[error("failed to create '{0}', elasticsearch answered with status code '{1}'")]
+    StatusCode(String, StatusCode),
+}
+
+// -----------------------------------------------------------------------------
+// Credentials structure
+
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub host: String,
+    pub user: String,
+    pub password: Redacted<String>,
+}
+
+impl TryFrom<&BTreeMap<String, String>> for Credentials {
+    type Error = Error;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(data)))]
+    fn try_from(data: &BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        let get = |key: &'static str| data.get(key).cloned().ok_or(Error::MissingKey(key));
+
+        Ok(Self {
+            host: get(ES_HOST)?,
+            user: get(ES_USER)?,
+            password: Redacted::from(get(ES_PASSWORD)?),
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Client structure
+
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: hyper::Client<Connector>,
+    credentials: Credentials,
+}
+
+impl Client {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn try_new(
+        api: &Api,
+        proxy: &Option<Proxy>,
+        credentials: Credentials,
+    ) -> Result<Self, Error> {
+        let connector = client::connector(api, proxy).map_err(Error::Connector)?;
+
+        Ok(Self {
+            inner: hyper::Client::builder().build(connector),
+            credentials,
+        })
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(template)))]
+    /// create or replace the composable index template with the given name
+    pub async fn upsert_index_template(
+        &self,
+        name: &str,
+        template: &serde_json::Value,
+    ) -> Result<(), Error> {
+        let resource = format!("_index_template/{}", name);
+        let body =
+            serde_json::to_vec(template).map_err(|err| Error::Serialize(name.to_string(), err))?;
+
+        let authorization = BASE64_ENGINE.encode(format!(
+            "{}:{}",
+            self.credentials.user, *self.credentials.password
+        ));
+
+        let req = Request::builder()
+            .method(Method::PUT)
+            .uri(format!("https://{}/{}", self.credentials.host, resource))
+            .header(header::AUTHORIZATION, format!("Basic {}", authorization))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .map_err(|err| Error::Request(resource.to_owned(), err))?;
+
+        debug!(
+            method = Method::PUT.as_str(),
+            host = &self.credentials.host,
+            resource = &resource,
+            "execute a request on elasticsearch api"
+        );
+
+        let res = self
+            .inner
+            .request(req)
+            .await
+            .map_err(|err| Error::Execute(resource.to_owned(), err))?;
+
+        match res.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(Error::StatusCode(resource, status)),
+        }
+    }
+}
@@ -6,28 +6,107 @@
 use std::{collections::BTreeMap, fmt::Debug};
 
 use async_trait::async_trait;
-use clevercloud_sdk::{
-    oauth10a::ClientError,
-    v2::addon::{self, Addon, CreateOpts, Error},
-};
-use hyper::StatusCode;
+use clevercloud_sdk::v2::addon::{self, Addon, CreateOpts, Error};
+use kube::Resource;
 use tracing::{debug, trace};
 
-use crate::svc::clevercloud::client::Client;
+use crate::{
+    consume,
+    svc::{
+        audit::{self, Actor},
+        cfg::NotificationEvent,
+        clevercloud::client::{ScopeError, ScopedClient},
+        crd::CredentialsSecretReference,
+        k8s::naming,
+        notification,
+        redact::Redacted,
+    },
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// names of the addon creation options which could be set from secrets
+pub const OPTIONS: [&str; 3] = ["version", "encryption", "services"];
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(opts)))]
+/// set the given values on the addon creation options, unknown options are
+/// ignored. The options hold secret values afterwards, so they must never be
+/// given to an instrumented function, see
+/// [`crate::svc::clevercloud::api::CleverApi::create_addon`]
+pub fn merge(opts: &mut addon::Opts, values: &Redacted<BTreeMap<String, String>>) {
+    for (option, value) in values.iter() {
+        match option.as_str() {
+            "version" => opts.version = Some(value.to_owned()),
+            "encryption" => opts.encryption = Some(value.to_owned()),
+            "services" => opts.services = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(password)))]
+/// returns the userinfo of an uri holding the given credentials, characters
+/// which are not unreserved are percent-encoded
+pub fn userinfo(username: &str, password: &str) -> String {
+    consume::userinfo(username, password)
+}
 
 // -----------------------------------------------------------------------------
 // AddonExt trait
 
 #[async_trait]
-pub trait AddonExt: Into<CreateOpts> + Clone + Debug + Sync + Send {
-    type Error: From<Error> + Sync + Send;
+pub trait AddonExt: Resource + Into<CreateOpts> + Clone + Debug + Sync + Send {
+    type Error: From<Error> + From<ScopeError> + Sync + Send;
+
+    /// returns the kind of custom resource, used to check the scope of
+    /// clients
+    fn scope() -> String;
 
     fn id(&self) -> Option<String>;
 
     fn organisation(&self) -> String;
 
+    /// returns the reference to the secret holding the configuration of the
+    /// clever cloud client of the custom resource, if any
+    fn credentials_secret_ref(&self) -> Option<&CredentialsSecretReference>;
+
+    /// returns the name identifying the custom resource, e.g.
+    /// `kubernetes::PostgreSql::<uid>`
     fn name(&self) -> String;
 
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the name of the addon requested by the specification, if any
+    fn display_name(&self) -> Option<String> {
+        None
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the name given to the addon on clever cloud, see
+    /// [`crate::svc::k8s::naming`]
+    fn addon_name(&self) -> String {
+        if let Some(name) = self.display_name().filter(|name| !name.trim().is_empty()) {
+            return name;
+        }
+
+        let meta = self.meta();
+        naming::template()
+            .map(|template| {
+                naming::render(
+                    &template,
+                    &Self::scope(),
+                    meta.namespace.as_deref().unwrap_or_default(),
+                    meta.name.as_deref().unwrap_or_default(),
+                    meta.uid.as_deref().unwrap_or_default(),
+                )
+            })
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| self.name())
+    }
+
     #[cfg_attr(feature = "trace", tracing::instrument)]
     fn prefix() -> String {
         "kubernetes".to_string()
@@ -39,7 +118,21 @@ pub trait AddonExt: Into<CreateOpts> + Clone + Debug + Sync + Send {
     }
 
     #[cfg_attr(feature = "trace", tracing::instrument)]
-    async fn get(&self, client: &Client) -> Result<Option<Addon>, Self::Error> {
+    /// returns the custom resource on behalf of which operations on the addon
+    /// are recorded in the audit log
+    fn actor(&self) -> Actor {
+        let meta = self.meta();
+
+        Actor::new(
+            &Self::scope(),
+            meta.namespace.as_deref().unwrap_or_default(),
+            meta.name.as_deref().unwrap_or_default(),
+        )
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    async fn get(&self, client: &ScopedClient) -> Result<Option<Addon>, Self::Error> {
+        let api = client.backend(&Self::scope(), "get").await?;
         if let Some(id) = &self.id() {
             trace!(
                 id = &id,
@@ -47,31 +140,29 @@ pub trait AddonExt: Into<CreateOpts> + Clone + Debug + Sync + Send {
                 "Retrieve the addon from the identifier",
             );
 
-            match addon::get(client, &self.organisation(), id).await {
-                Ok(addon) => {
-                    return Ok(Some(addon));
-                }
-                Err(Error::Get(_, _, ClientError::StatusCode(code, _)))
-                    if StatusCode::NOT_FOUND.as_u16() == code.as_u16() =>
-                {
-                    // try to retrieve the addon from the name
-                    trace!(
-                        id = &id,
-                        name = self.name(),
-                        "Trying to retrieve the addon by name for the addon",
-                    );
-
-                    return Ok(addon::list(client, &self.organisation())
-                        .await
-                        .map_err(Into::into)?
-                        .iter()
-                        .find(|addon| addon.name == Some(self.name()))
-                        .map(ToOwned::to_owned));
-                }
-                Err(err) => {
-                    return Err(err.into());
-                }
+            if let Some(addon) = api.get_addon(&self.organisation(), id).await? {
+                return Ok(Some(addon));
             }
+
+            // try to retrieve the addon from the name
+            trace!(
+                id = &id,
+                name = self.name(),
+                "Trying to retrieve the addon by name for the addon",
+            );
+
+            let names = [self.name(), self.addon_name()];
+            return Ok(api
+                .list_addons(&self.organisation())
+                .await?
+                .into_iter()
+                .find(|addon| {
+                    addon
+                        .name
+                        .as_ref()
+                        .map(|name| names.contains(name))
+                        .unwrap_or_default()
+                }));
         }
 
         trace!("No such identifier to retrieve addon '{}'", self.name());
@@ -79,7 +170,18 @@ pub trait AddonExt: Into<CreateOpts> + Clone + Debug + Sync + Send {
     }
 
     #[cfg_attr(feature = "trace", tracing::instrument)]
-    async fn upsert(&self, client: &Client) -> Result<Addon, Self::Error> {
+    async fn upsert(&self, client: &ScopedClient) -> Result<Addon, Self::Error> {
+        self.upsert_with_options(client, &Redacted::default()).await
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// same as [`AddonExt::upsert`], the given options are merged into the
+    /// creation options, if the addon has to be created
+    async fn upsert_with_options(
+        &self,
+        client: &ScopedClient,
+        options: &Redacted<BTreeMap<String, String>>,
+    ) -> Result<Addon, Self::Error> {
         debug!(
             id = self.id().unwrap_or_else(|| "<none>".to_string()),
             name = self.name(),
@@ -91,13 +193,59 @@ pub trait AddonExt: Into<CreateOpts> + Clone + Debug + Sync + Send {
         }
 
         debug!(name = self.name(), "Creating a new addon");
-        Ok(addon::create(client, &self.organisation(), &self.to_owned().into()).await?)
+        let api = client.backend(&Self::scope(), "create").await?;
+        let mut opts: CreateOpts = self.to_owned().into();
+        merge(&mut opts.options, options);
+
+        let organisation = self.organisation();
+        let result = api.create_addon(&organisation, &opts).await;
+        audit::record(
+            "addon::create",
+            &self.actor(),
+            Some(&organisation),
+            &self.name(),
+            &result,
+        );
+
+        let addon = result?;
+        let actor = self.actor();
+        notification::notify(
+            NotificationEvent::AddonCreated,
+            &actor.kind,
+            &actor.namespace,
+            &actor.name,
+            Some(&addon.id),
+            &format!("Addon '{}' is created", addon.id),
+        );
+
+        Ok(addon)
     }
 
     #[cfg_attr(feature = "trace", tracing::instrument)]
-    async fn delete(&self, client: &Client) -> Result<(), Self::Error> {
+    async fn delete(&self, client: &ScopedClient) -> Result<(), Self::Error> {
         if let Some(a) = self.get(client).await? {
-            addon::delete(client, &self.organisation(), &a.id).await?;
+            let api = client.backend(&Self::scope(), "delete").await?;
+            let organisation = self.organisation();
+            let result = api.delete_addon(&organisation, &a.id).await;
+            audit::record(
+                "addon::delete",
+                &self.actor(),
+                Some(&organisation),
+                &a.id,
+                &result,
+            );
+
+            result?;
+
+            let actor = self.actor();
+            notification::notify(
+                NotificationEvent::AddonDeleted,
+                &actor.kind,
+                &actor.namespace,
+                &actor.name,
+                Some(&a.id),
+                &format!("Addon '{}' is deleted", a.id),
+            );
         }
 
         Ok(())
@@ -106,12 +254,11 @@ pub trait AddonExt: Into<CreateOpts> + Clone + Debug + Sync + Send {
     #[cfg_attr(feature = "trace", tracing::instrument)]
     async fn secrets(
         &self,
-        client: &Client,
+        client: &ScopedClient,
     ) -> Result<Option<BTreeMap<String, String>>, Self::Error> {
+        let api = client.backend(&Self::scope(), "environment").await?;
         if let Some(id) = &self.id() {
-            return Ok(Some(
-                addon::environment(client, &self.organisation(), id).await?,
-            ));
+            return Ok(Some(api.addon_environment(&self.organisation(), id).await?));
         }
 
         Ok(None)
@@ -0,0 +1,203 @@
+//! # Fake module
+//!
+//! This module provide an in-memory implementation of the [`CleverApi`] trait.
+//! Addons are created, retrieved and deleted from memory, their environment
+//! only exposes their uri and plans have to be registered beforehand. It is
+//! used to exercise reconcilers without reaching the Clever Cloud's api.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use async_trait::async_trait;
+use clevercloud_sdk::{
+    v2::addon::{self, Addon, CreateOpts},
+    v4::addon_provider::{
+        plan::{self, Plan},
+        AddonProviderId,
+    },
+};
+use serde_json::json;
+
+use crate::svc::clevercloud::api::CleverApi;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// name of the environment variable exposed by each addon of the fake api
+pub const ADDON_URI: &str = "ADDON_URI";
+
+// -----------------------------------------------------------------------------
+// FakeApi structure
+
+/// in-memory clever cloud api, addons are keyed by organisation and
+/// identifier, plans by addon provider and organisation
+#[derive(Default, Debug)]
+pub struct FakeApi {
+    counter: AtomicUsize,
+    addons: Mutex<BTreeMap<(String, String), Addon>>,
+    plans: Mutex<BTreeMap<(String, String), Vec<Plan>>>,
+}
+
+impl FakeApi {
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(plan)))]
+    /// register the plan of the addon provider for the organisation
+    pub fn with_plan(self, provider: &AddonProviderId, organisation: &str, plan: Plan) -> Self {
+        self.plans
+            .lock()
+            .expect("lock to not be poisoned")
+            .entry((provider.to_string(), organisation.to_string()))
+            .or_default()
+            .push(plan);
+
+        self
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the addons of all organisations
+    pub fn addons(&self) -> Vec<Addon> {
+        self.addons
+            .lock()
+            .expect("lock to not be poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// returns an addon built from the creation options, as the api would
+    fn addon(&self, opts: &CreateOpts) -> Addon {
+        let id = format!("addon_{}", self.counter.fetch_add(1, Ordering::SeqCst));
+        let opts = serde_json::to_value(opts).expect("creation options to be serializable");
+        let plan = opts["plan"].as_str().unwrap_or_default();
+
+        serde_json::from_value(json!({
+            "id": id,
+            "name": opts["name"],
+            "realId": format!("real_{}", id),
+            "region": opts["region"],
+            "provider": {
+                "id": opts["providerId"],
+                "name": opts["providerId"],
+                "website": "",
+                "supportEmail": "",
+                "googlePlusName": "",
+                "twitterName": "",
+                "analyticsId": "",
+                "shortDesc": "",
+                "longDesc": "",
+                "logoUrl": "",
+                "status": "RELEASE",
+                "openInNewTab": false,
+                "canUpgrade": false,
+                "regions": [opts["region"]],
+            },
+            "plan": {
+                "id": plan,
+                "name": plan,
+                "slug": plan.trim_start_matches("plan_"),
+                "price": 0.0,
+                "features": [],
+                "zones": [opts["region"]],
+            },
+            "creationDate": 0,
+            "configKeys": [ADDON_URI],
+        }))
+        .expect("addon of the fake api to be deserializable")
+    }
+}
+
+#[async_trait]
+impl CleverApi for FakeApi {
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    async fn get_addon(&self, organisation: &str, id: &str) -> Result<Option<Addon>, addon::Error> {
+        Ok(self
+            .addons
+            .lock()
+            .expect("lock to not be poisoned")
+            .get(&(organisation.to_string(), id.to_string()))
+            .cloned())
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    async fn list_addons(&self, organisation: &str) -> Result<Vec<Addon>, addon::Error> {
+        Ok(self
+            .addons
+            .lock()
+            .expect("lock to not be poisoned")
+            .iter()
+            .filter(|((owner, _), _)| owner == organisation)
+            .map(|(_, addon)| addon.to_owned())
+            .collect())
+    }
+
+    #[cfg_attr(
+        feature = "trace",
+        tracing::instrument(skip(self, opts), fields(name = %opts.name))
+    )]
+    async fn create_addon(
+        &self,
+        organisation: &str,
+        opts: &CreateOpts,
+    ) -> Result<Addon, addon::Error> {
+        let addon = self.addon(opts);
+
+        self.addons.lock().expect("lock to not be poisoned").insert(
+            (organisation.to_string(), addon.id.to_owned()),
+            addon.to_owned(),
+        );
+
+        Ok(addon)
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    async fn delete_addon(&self, organisation: &str, id: &str) -> Result<(), addon::Error> {
+        self.addons
+            .lock()
+            .expect("lock to not be poisoned")
+            .remove(&(organisation.to_string(), id.to_string()));
+
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    async fn addon_environment(
+        &self,
+        organisation: &str,
+        id: &str,
+    ) -> Result<BTreeMap<String, String>, addon::Error> {
+        let mut environment = BTreeMap::new();
+        if self.get_addon(organisation, id).await?.is_some() {
+            environment.insert(ADDON_URI.to_string(), format!("fake://{}", id));
+        }
+
+        Ok(environment)
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    async fn find_plan(
+        &self,
+        provider: &AddonProviderId,
+        organisation: &str,
+        pattern: &str,
+    ) -> Result<Option<Plan>, plan::Error> {
+        let pattern = pattern.to_lowercase();
+
+        Ok(self
+            .plans
+            .lock()
+            .expect("lock to not be poisoned")
+            .get(&(provider.to_string(), organisation.to_string()))
+            .and_then(|plans| {
+                plans.iter().find(|plan| {
+                    plan.id == pattern
+                        || plan.slug.to_lowercase() == pattern
+                        || plan.name.to_lowercase() == pattern
+                })
+            })
+            .cloned())
+    }
+}
@@ -0,0 +1,141 @@
+//! # Follower module
+//!
+//! This module provide structures and helpers to interact with the followers'
+//! endpoints of the addon providers of the Clever Cloud's api, which are not
+//! exposed by the `clevercloud-sdk` crate. A follower is a read-only replica of
+//! an addon, continuously replicating its primary, e.g. a postgresql standby.
+
+use std::fmt::Debug;
+
+use clevercloud_sdk::{
+    oauth10a::{ClientError, RestClient},
+    v4::addon_provider::AddonProviderId,
+};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::svc::clevercloud::client::{observe, retry, Client};
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to list followers of addon '{0}', {1}")]
+    List(String, ClientError),
+    #[error("failed to create follower of addon '{0}', {1}")]
+    Create(String, ClientError),
+    #[error("failed to delete follower '{0}' of addon '{1}', {2}")]
+    Delete(String, String, ClientError),
+}
+
+impl Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns if the error is due to an addon provider, or a plan, which does
+    /// not offer followers, retrying on such errors is useless
+    pub fn unavailable(&self) -> bool {
+        matches!(
+            self,
+            Self::List(_, ClientError::StatusCode(code, _))
+                if StatusCode::NOT_FOUND.as_u16() == code.as_u16()
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Follower structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Follower {
+    #[serde(rename = "id")]
+    pub id: String,
+    /// status of the follower as returned by the api, e.g. `TO_DEPLOY` or
+    /// `ACTIVE`
+    #[serde(rename = "status", default)]
+    pub status: String,
+    #[serde(rename = "host", default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(rename = "port", default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+impl Follower {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the host and port of the follower, once it has been deployed
+    pub fn endpoint(&self) -> Option<(&str, u16)> {
+        self.host.as_deref().zip(self.port)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the followers of the addon, the given identifier is the identifier
+/// of the addon, e.g. `addon_<uuid>`
+pub async fn list(
+    client: &Client,
+    endpoint: &str,
+    provider: &AddonProviderId,
+    id: &str,
+) -> Result<Vec<Follower>, Error> {
+    let path = format!(
+        "{}/v4/addon-providers/{}/addons/{}/followers",
+        endpoint, provider, id
+    );
+
+    debug!(
+        endpoint = &path,
+        "execute a request to list followers of an addon"
+    );
+    retry("follower", "list", || client.get(&path))
+        .await
+        .map_err(|err| Error::List(id.to_owned(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// request a new follower of the addon, it is deployed asynchronously, so its
+/// endpoint is only known once it is listed as deployed
+pub async fn create(
+    client: &Client,
+    endpoint: &str,
+    provider: &AddonProviderId,
+    id: &str,
+) -> Result<Follower, Error> {
+    let path = format!(
+        "{}/v4/addon-providers/{}/addons/{}/followers",
+        endpoint, provider, id
+    );
+
+    debug!(
+        endpoint = &path,
+        "execute a request to create a follower of an addon"
+    );
+    observe("follower", "create", client.post(&path, &()))
+        .await
+        .map_err(|err| Error::Create(id.to_owned(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// delete the follower of the addon
+pub async fn delete(
+    client: &Client,
+    endpoint: &str,
+    provider: &AddonProviderId,
+    id: &str,
+    follower_id: &str,
+) -> Result<(), Error> {
+    let path = format!(
+        "{}/v4/addon-providers/{}/addons/{}/followers/{}",
+        endpoint, provider, id, follower_id
+    );
+
+    debug!(
+        endpoint = &path,
+        "execute a request to delete a follower of an addon"
+    );
+    retry("follower", "delete", || client.delete(&path))
+        .await
+        .map_err(|err| Error::Delete(follower_id.to_owned(), id.to_owned(), err))
+}
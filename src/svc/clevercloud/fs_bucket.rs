@@ -0,0 +1,85 @@
+//! # FS bucket module
+//!
+//! This module provide structures and helpers related to the fs bucket addon
+//! provider of Clever Cloud, which is not exposed by the `clevercloud-sdk`
+//! crate. It computes the ftp and webdav uris of a bucket from the environment
+//! of its addon.
+
+use std::collections::BTreeMap;
+
+use crate::svc::{clevercloud::ext, redact::Redacted};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// identifier of the fs bucket addon provider
+pub const PROVIDER_ID: &str = "fs-bucket";
+
+pub const BUCKET_HOST: &str = "BUCKET_HOST";
+pub const BUCKET_FTP_USERNAME: &str = "BUCKET_FTP_USERNAME";
+pub const BUCKET_FTP_PASSWORD: &str = "BUCKET_FTP_PASSWORD";
+pub const BUCKET_FTP_URI: &str = "BUCKET_FTP_URI";
+pub const BUCKET_DAV_URI: &str = "BUCKET_DAV_URI";
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to find key '{0}' in fs bucket credentials")]
+    MissingKey(&'static str),
+}
+
+// -----------------------------------------------------------------------------
+// Credentials structure
+
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub host: String,
+    pub username: String,
+    pub password: Redacted<String>,
+}
+
+impl TryFrom<&BTreeMap<String, String>> for Credentials {
+    type Error = Error;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(data)))]
+    fn try_from(data: &BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        let get = |key: &'static str| data.get(key).cloned().ok_or(Error::MissingKey(key));
+
+        Ok(Self {
+            host: get(BUCKET_HOST)?,
+            username: get(BUCKET_FTP_USERNAME)?,
+            password: Redacted::from(get(BUCKET_FTP_PASSWORD)?),
+        })
+    }
+}
+
+impl Credentials {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the uri to connect the bucket using the given scheme, the
+    /// username and the password are percent-encoded
+    pub fn uri(&self, scheme: &str) -> String {
+        format!(
+            "{}://{}@{}",
+            scheme,
+            ext::userinfo(&self.username, self.password.as_str()),
+            self.host
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(environment)))]
+/// add the ftp and webdav uris of the bucket to the environment of its addon,
+/// the environment is left as is if it does not expose ftp credentials
+pub fn enrich(mut environment: BTreeMap<String, String>) -> BTreeMap<String, String> {
+    if let Ok(credentials) = Credentials::try_from(&environment) {
+        environment.insert(BUCKET_FTP_URI.to_string(), credentials.uri("ftp"));
+        environment.insert(BUCKET_DAV_URI.to_string(), credentials.uri("https"));
+    }
+
+    environment
+}
@@ -0,0 +1,302 @@
+//! # Git module
+//!
+//! This module provide helpers to push code on the git remote of a Clever
+//! Cloud's application. The remote accepts the oauth token and secret of the
+//! api as basic authentication, which are given to git through an extra header
+//! set in its environment, so they never appear in an url or in the arguments
+//! of the process list. Repositories and references come from custom
+//! resources, so they are validated and only reach git as positional arguments
+//! restricted to the https and ssh transports.
+
+use std::{path::Path, process::Stdio};
+
+use base64::{engine::general_purpose::STANDARD as BASE64_ENGINE, Engine};
+use tempfile::TempDir;
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::svc::{cfg::Api, redact::Redacted};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// branch deployed by Clever Cloud on push
+pub const DEPLOYMENT_BRANCH: &str = "master";
+
+/// transports git is allowed to use, as expected by `GIT_ALLOW_PROTOCOL`
+pub const ALLOWED_PROTOCOLS: &str = "https:ssh";
+
+/// url schemes accepted for the repository of a source
+pub const ALLOWED_SCHEMES: [&str; 2] = ["https://", "ssh://"];
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to create temporary directory, {0}")]
+    TempDir(std::io::Error),
+    #[error("failed to execute 'git {0}', {1}")]
+    Spawn(String, std::io::Error),
+    #[error("failed to execute 'git {0}', {1}")]
+    Git(String, String),
+    #[error("repository '{0}' has to be an https:// or ssh:// url")]
+    Repository(String),
+    #[error("reference '{0}' has to be a non-empty branch or tag which does not start with '-'")]
+    Reference(String),
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns an error if the repository or the reference of a source could be
+/// interpreted by git as something else than a remote url and a branch or tag
+pub fn validate(repository: &str, reference: &str) -> Result<(), Error> {
+    let scheme = ALLOWED_SCHEMES
+        .iter()
+        .find(|scheme| repository.starts_with(*scheme));
+
+    match scheme {
+        Some(scheme) if repository.len() > scheme.len() => {}
+        _ => return Err(Error::Repository(repository.to_string())),
+    }
+
+    if repository
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_control())
+    {
+        return Err(Error::Repository(repository.to_string()));
+    }
+
+    if reference.is_empty()
+        || reference.starts_with('-')
+        || reference
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control())
+    {
+        return Err(Error::Reference(reference.to_string()));
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(header)))]
+/// execute git with the given arguments and returns its standard output
+async fn git(
+    dir: Option<&Path>,
+    header: Option<&Redacted<String>>,
+    args: &[&str],
+) -> Result<String, Error> {
+    let subcommand = args.first().copied().unwrap_or_default().to_string();
+    let mut cmd = Command::new("git");
+
+    cmd.env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_ALLOW_PROTOCOL", ALLOWED_PROTOCOLS)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if let Some(dir) = dir {
+        cmd.arg("-C").arg(dir);
+    }
+
+    // the header is given through the configuration environment of git
+    // rather than `-c`, which would expose it in the arguments of the process
+    if let Some(header) = header {
+        cmd.env("GIT_CONFIG_COUNT", "1")
+            .env("GIT_CONFIG_KEY_0", "http.extraHeader")
+            .env("GIT_CONFIG_VALUE_0", header.as_str());
+    }
+
+    let output = cmd
+        .args(args)
+        .output()
+        .await
+        .map_err(|err| Error::Spawn(subcommand.to_owned(), err))?;
+
+    if !output.status.success() {
+        return Err(Error::Git(
+            subcommand,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(api)))]
+/// clone the reference of the given repository and push it on the deployment
+/// branch of the given remote, authenticated with the credentials of the api.
+/// Returns the pushed commit.
+pub async fn push(
+    api: &Api,
+    remote: &str,
+    repository: &str,
+    reference: &str,
+) -> Result<String, Error> {
+    validate(repository, reference)?;
+
+    let dir = TempDir::new().map_err(Error::TempDir)?;
+    let path = dir.path().join("repository.git");
+    let path_str = path.to_string_lossy().to_string();
+
+    debug!(
+        repository = repository,
+        reference = reference,
+        "Clone repository"
+    );
+
+    git(
+        None,
+        None,
+        &[
+            "clone",
+            "--quiet",
+            "--bare",
+            "--single-branch",
+            "--branch",
+            reference,
+            "--",
+            repository,
+            &path_str,
+        ],
+    )
+    .await?;
+
+    let commit = git(Some(&path), None, &["rev-parse", "HEAD"]).await?;
+    let header = Redacted::from(format!(
+        "Authorization: Basic {}",
+        BASE64_ENGINE.encode(format!("{}:{}", api.token, api.secret))
+    ));
+
+    debug!(
+        remote = remote,
+        commit = &commit,
+        "Push commit on the deployment branch of the application"
+    );
+
+    git(
+        Some(&path),
+        Some(&header),
+        &[
+            "push",
+            "--quiet",
+            "--force",
+            "--",
+            remote,
+            &format!("{}:refs/heads/{}", commit, DEPLOYMENT_BRANCH),
+        ],
+    )
+    .await?;
+
+    Ok(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{git, push, validate, Error};
+    use crate::svc::cfg::Api;
+    use crate::svc::redact::Redacted;
+
+    #[tokio::test]
+    async fn header_is_given_through_the_environment() {
+        let header = Redacted::from("Authorization: Basic dG9rZW46c2VjcmV0".to_string());
+        let value = git(
+            None,
+            Some(&header),
+            &["config", "--get", "http.extraHeader"],
+        )
+        .await
+        .expect("git to read its configuration");
+
+        assert_eq!(*header, value);
+    }
+
+    #[test]
+    fn validate_accepts_https_and_ssh_repositories() {
+        assert!(validate("https://github.com/CleverCloud/clever-operator.git", "main").is_ok());
+        assert!(validate(
+            "ssh://git@github.com/CleverCloud/clever-operator.git",
+            "v0.6.0"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_other_repositories() {
+        for repository in [
+            "--upload-pack=touch /tmp/pwned",
+            "-u",
+            "ext::sh -c touch% /tmp/pwned",
+            "file:///etc",
+            "/etc",
+            "git@github.com:CleverCloud/clever-operator.git",
+            "http://github.com/CleverCloud/clever-operator.git",
+            "https://",
+            "https://github.com/a b",
+        ] {
+            assert!(
+                matches!(validate(repository, "main"), Err(Error::Repository(_))),
+                "{}",
+                repository
+            );
+        }
+    }
+
+    #[test]
+    fn validate_rejects_option_like_references() {
+        for reference in ["", "--upload-pack=touch /tmp/pwned", "-b", "main branch"] {
+            assert!(
+                matches!(
+                    validate(
+                        "https://github.com/CleverCloud/clever-operator.git",
+                        reference
+                    ),
+                    Err(Error::Reference(_))
+                ),
+                "{}",
+                reference
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn push_refuses_invalid_source_before_running_git() {
+        let api: Api = serde_json::from_value(serde_json::json!({
+            "endpoint": "https://api.clever-cloud.com/v2",
+            "token": "token",
+            "secret": "secret",
+            "consumerKey": "key",
+            "consumerSecret": "secret",
+        }))
+        .expect("api configuration");
+
+        let result = push(
+            &api,
+            "https://push.clever-cloud.com/app.git",
+            "--upload-pack=touch /tmp/pwned",
+            "main",
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::Repository(_))));
+    }
+
+    #[tokio::test]
+    async fn transports_are_restricted() {
+        let result = git(None, None, &["ls-remote", "--", "file:///"]).await;
+
+        match result {
+            Err(Error::Git(subcommand, stderr)) => {
+                assert_eq!("ls-remote", subcommand);
+                assert!(
+                    stderr.contains("transport 'file' not allowed"),
+                    "{}",
+                    stderr
+                );
+            }
+            other => panic!("expected the file transport to be refused, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! # Gravitee module
+//!
+//! This module provide structures and helpers related to the gravitee partner
+//! addon provider of Clever Cloud, an api gateway and management platform,
+//! which is not exposed by the `clevercloud-sdk` crate. The environment of its
+//! addon holds the urls of the gateway, of the management api and of the
+//! developer portal, alongside the credentials of its administrator.
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// identifier of the gravitee addon provider
+pub const PROVIDER_ID: &str = "gravitee";
+
+pub const GRAVITEE_GATEWAY_URL: &str = "GRAVITEE_GATEWAY_URL";
+pub const GRAVITEE_MANAGEMENT_URL: &str = "GRAVITEE_MANAGEMENT_URL";
+pub const GRAVITEE_PORTAL_URL: &str = "GRAVITEE_PORTAL_URL";
+pub const GRAVITEE_ADMIN_USERNAME: &str = "GRAVITEE_ADMIN_USERNAME";
+pub const GRAVITEE_ADMIN_PASSWORD: &str = "GRAVITEE_ADMIN_PASSWORD";
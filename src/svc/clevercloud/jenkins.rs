@@ -0,0 +1,31 @@
+//! # Jenkins module
+//!
+//! This module provide structures and helpers related to the jenkins addon
+//! provider of Clever Cloud, which is not exposed by the `clevercloud-sdk`
+//! crate. The environment of its addon holds the url of the instance and the
+//! credentials of its administrator.
+
+use std::collections::BTreeMap;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// identifier of the jenkins addon provider
+pub const PROVIDER_ID: &str = "jenkins";
+
+pub const JENKINS_URL: &str = "JENKINS_URL";
+pub const JENKINS_ADMIN_USERNAME: &str = "JENKINS_ADMIN_USERNAME";
+pub const JENKINS_ADMIN_PASSWORD: &str = "JENKINS_ADMIN_PASSWORD";
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(environment)))]
+/// returns the url of the jenkins instance from the environment of its addon,
+/// if any
+pub fn url(environment: &BTreeMap<String, String>) -> Option<String> {
+    environment
+        .get(JENKINS_URL)
+        .map(|url| url.trim_end_matches('/').to_string())
+        .filter(|url| !url.is_empty())
+}
@@ -0,0 +1,100 @@
+//! # Mailpace module
+//!
+//! This module provide structures and helpers related to the mailpace partner
+//! addon provider of Clever Cloud, which sends transactional emails. The
+//! environment of its addon only holds the api token of the sending domain, the
+//! token is also the username and the password of its smtp relay, so smtp
+//! credentials are computed from it.
+//!
+//! See <https://docs.mailpace.com/guide/smtp>
+
+use std::collections::BTreeMap;
+
+use crate::svc::{clevercloud::ext, redact::Redacted};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// identifier of the mailpace addon provider
+pub const PROVIDER_ID: &str = "mailpace";
+
+/// host and port of the smtp relay of mailpace, using starttls
+pub const SMTP_HOST: &str = "smtp.mailpace.com";
+pub const SMTP_PORT: u16 = 587;
+
+pub const MAILPACE_API_TOKEN: &str = "MAILPACE_API_TOKEN";
+pub const MAILPACE_SMTP_HOST: &str = "MAILPACE_SMTP_HOST";
+pub const MAILPACE_SMTP_PORT: &str = "MAILPACE_SMTP_PORT";
+pub const MAILPACE_SMTP_USERNAME: &str = "MAILPACE_SMTP_USERNAME";
+pub const MAILPACE_SMTP_PASSWORD: &str = "MAILPACE_SMTP_PASSWORD";
+pub const MAILPACE_SMTP_URI: &str = "MAILPACE_SMTP_URI";
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to find key '{0}' in mailpace credentials")]
+    MissingKey(&'static str),
+}
+
+// -----------------------------------------------------------------------------
+// Credentials structure
+
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub token: Redacted<String>,
+}
+
+impl TryFrom<&BTreeMap<String, String>> for Credentials {
+    type Error = Error;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(data)))]
+    fn try_from(data: &BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        data.get(MAILPACE_API_TOKEN)
+            .cloned()
+            .map(|token| Self {
+                token: Redacted::from(token),
+            })
+            .ok_or(Error::MissingKey(MAILPACE_API_TOKEN))
+    }
+}
+
+impl Credentials {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the uri of the smtp relay, authenticated using the token
+    pub fn uri(&self) -> String {
+        format!(
+            "smtp://{}@{}:{}",
+            ext::userinfo(self.token.as_str(), self.token.as_str()),
+            SMTP_HOST,
+            SMTP_PORT
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(environment)))]
+/// add the smtp credentials to the environment of the addon, keys which are
+/// already exposed by the addon are kept as is. The environment is left as is
+/// if it does not expose the api token
+pub fn enrich(mut environment: BTreeMap<String, String>) -> BTreeMap<String, String> {
+    if let Ok(credentials) = Credentials::try_from(&environment) {
+        let token = credentials.token.as_str();
+        let entries = [
+            (MAILPACE_SMTP_HOST, SMTP_HOST.to_string()),
+            (MAILPACE_SMTP_PORT, SMTP_PORT.to_string()),
+            (MAILPACE_SMTP_USERNAME, token.to_string()),
+            (MAILPACE_SMTP_PASSWORD, token.to_string()),
+            (MAILPACE_SMTP_URI, credentials.uri()),
+        ];
+
+        for (key, value) in entries {
+            environment.entry(key.to_string()).or_insert(value);
+        }
+    }
+
+    environment
+}
@@ -0,0 +1,126 @@
+//! # Migration module
+//!
+//! This module provide structures and helpers to interact with the migrations'
+//! endpoints of the Clever Cloud's api, which are not exposed by the
+//! `clevercloud-sdk` crate. A migration moves an existing addon to another plan.
+
+use std::fmt::Debug;
+
+use clevercloud_sdk::oauth10a::{ClientError, RestClient};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::svc::clevercloud::client::{observe, retry, Client};
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to migrate addon '{0}' of organisation '{1}' to plan '{2}', {3}")]
+    Create(String, String, String, ClientError),
+    #[error("failed to get migration '{0}' of addon '{1}' of organisation '{2}', {3}")]
+    Get(String, String, String, ClientError),
+}
+
+// -----------------------------------------------------------------------------
+// CreateOpts structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct CreateOpts {
+    #[serde(rename = "planId")]
+    pub plan_id: String,
+    #[serde(rename = "region")]
+    pub region: String,
+    #[serde(rename = "version", default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Migration structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Migration {
+    #[serde(rename = "migrationId", alias = "id")]
+    pub id: String,
+    /// status of the migration as returned by the api, e.g. `RUNNING`, `OK`
+    /// or `FAILED`
+    #[serde(rename = "status")]
+    pub status: String,
+}
+
+impl Migration {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn succeeded(&self) -> bool {
+        matches!(
+            self.status.to_uppercase().as_str(),
+            "OK" | "SUCCESS" | "SUCCEEDED"
+        )
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn failed(&self) -> bool {
+        matches!(
+            self.status.to_uppercase().as_str(),
+            "KO" | "ERROR" | "FAILED"
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// start the migration of the addon to the given plan, the given identifier is
+/// the identifier of the addon, e.g. `addon_<uuid>`
+pub async fn create(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+    opts: &CreateOpts,
+) -> Result<Migration, Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/addons/{}/migrations",
+        endpoint, organisation, id
+    );
+
+    debug!(endpoint = &path, "execute a request to migrate an addon");
+    observe("migration", "create", client.post(&path, opts))
+        .await
+        .map_err(|err| {
+            Error::Create(
+                id.to_owned(),
+                organisation.to_owned(),
+                opts.plan_id.to_owned(),
+                err,
+            )
+        })
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the migration of the addon with the given identifier
+pub async fn get(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+    migration_id: &str,
+) -> Result<Migration, Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/addons/{}/migrations/{}",
+        endpoint, organisation, id, migration_id
+    );
+
+    debug!(endpoint = &path, "execute a request to get a migration");
+    retry("migration", "get", || client.get(&path))
+        .await
+        .map_err(|err| {
+            Error::Get(
+                migration_id.to_owned(),
+                id.to_owned(),
+                organisation.to_owned(),
+                err,
+            )
+        })
+}
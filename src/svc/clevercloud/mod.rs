@@ -4,12 +4,40 @@
 //! and the `clevercloud-sdk` crate.
 
 use clevercloud_sdk::{
+    oauth10a::ClientError,
     v2,
     v4::addon_provider::{config_provider::addon::environment, plan},
 };
+use hyper::StatusCode;
 
+pub mod api;
+pub mod application;
+pub mod backup;
+pub mod billing;
+pub mod biscuit;
+pub mod cache;
+pub mod cellar;
 pub mod client;
+pub mod connector;
+pub mod elasticsearch;
 pub mod ext;
+pub mod fake;
+pub mod follower;
+pub mod fs_bucket;
+pub mod git;
+pub mod gravitee;
+pub mod jenkins;
+pub mod mailpace;
+pub mod migration;
+pub mod mongodb;
+pub mod naming;
+pub mod pool;
+pub mod postgresql;
+pub mod product;
+pub mod provider;
+pub mod pulsar;
+pub mod stats;
+pub mod tags;
 
 // -----------------------------------------------------------------------------
 // Error enumeration
@@ -22,6 +50,74 @@ pub enum Error {
     Plan(plan::Error),
     #[error("{0}")]
     Environment(environment::Error),
+    #[error("{0}")]
+    Application(application::Error),
+    #[error("{0}")]
+    Git(git::Error),
+    #[error("{0}")]
+    Backup(backup::Error),
+    #[error("{0}")]
+    Cellar(cellar::Error),
+    #[error("{0}")]
+    ElasticSearch(elasticsearch::Error),
+    #[error("{0}")]
+    Follower(follower::Error),
+    #[error("{0}")]
+    Migration(migration::Error),
+    #[error("{0}")]
+    MongoDb(mongodb::Error),
+    #[error("{0}")]
+    PostgreSql(postgresql::Error),
+    #[error("{0}")]
+    Product(product::Error),
+    #[error("{0}")]
+    Provider(provider::Error),
+    #[error("{0}")]
+    Pulsar(pulsar::Error),
+    #[error("failed to find plan '{0}' for organisation '{1}'")]
+    UnknownPlan(String, String),
+}
+
+impl Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the reason of the error, if it is permanent. Retrying on such
+    /// errors only hammers the api, until the custom resource is modified
+    pub fn permanent(&self) -> Option<&'static str> {
+        match self {
+            Self::UnknownPlan(_, _) => Some("UnknownPlan"),
+            Self::Addon(v2::addon::Error::List(_, err) | v2::addon::Error::Create(_, err)) => {
+                permanent(err)
+            }
+            Self::Application(
+                application::Error::List(_, err) | application::Error::Create(_, err),
+            ) => permanent(err),
+            Self::Migration(migration::Error::Create(_, _, _, err)) => permanent(err),
+            Self::Follower(follower::Error::Create(_, err)) => permanent(err),
+            Self::Provider(err) if err.unavailable() => Some("UnavailableOptions"),
+            Self::Cellar(cellar::Error::MissingKey(_)) => Some("InvalidCredentials"),
+            Self::Cellar(cellar::Error::StatusCode(_, _, StatusCode::FORBIDDEN)) => {
+                Some("Forbidden")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// returns the reason of the error returned by the api on an organisation, if
+/// it is permanent
+fn permanent(err: &ClientError) -> Option<&'static str> {
+    match err {
+        ClientError::StatusCode(code, _) if StatusCode::NOT_FOUND.as_u16() == code.as_u16() => {
+            Some("OrganisationNotFound")
+        }
+        ClientError::StatusCode(code, _) if StatusCode::BAD_REQUEST.as_u16() == code.as_u16() => {
+            Some("InvalidSpecification")
+        }
+        ClientError::StatusCode(code, _) if StatusCode::FORBIDDEN.as_u16() == code.as_u16() => {
+            Some("Forbidden")
+        }
+        _ => None,
+    }
 }
 
 impl From<v2::addon::Error> for Error {
@@ -44,3 +140,87 @@ impl From<environment::Error> for Error {
         Self::Environment(err)
     }
 }
+
+impl From<application::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: application::Error) -> Self {
+        Self::Application(err)
+    }
+}
+
+impl From<git::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: git::Error) -> Self {
+        Self::Git(err)
+    }
+}
+
+impl From<backup::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: backup::Error) -> Self {
+        Self::Backup(err)
+    }
+}
+
+impl From<cellar::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: cellar::Error) -> Self {
+        Self::Cellar(err)
+    }
+}
+
+impl From<elasticsearch::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: elasticsearch::Error) -> Self {
+        Self::ElasticSearch(err)
+    }
+}
+
+impl From<follower::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: follower::Error) -> Self {
+        Self::Follower(err)
+    }
+}
+
+impl From<migration::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: migration::Error) -> Self {
+        Self::Migration(err)
+    }
+}
+
+impl From<mongodb::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: mongodb::Error) -> Self {
+        Self::MongoDb(err)
+    }
+}
+
+impl From<postgresql::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: postgresql::Error) -> Self {
+        Self::PostgreSql(err)
+    }
+}
+
+impl From<product::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: product::Error) -> Self {
+        Self::Product(err)
+    }
+}
+
+impl From<provider::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: provider::Error) -> Self {
+        Self::Provider(err)
+    }
+}
+
+impl From<pulsar::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: pulsar::Error) -> Self {
+        Self::Pulsar(err)
+    }
+}
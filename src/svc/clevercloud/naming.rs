@@ -0,0 +1,63 @@
+//! # Naming module
+//!
+//! This module provide structures and helpers to interact with the update
+//! endpoint of addons of the Clever Cloud's api, which is not exposed by the
+//! `clevercloud-sdk` crate. It is used to keep the name of an addon in sync
+//! with the naming strategy of its custom resource.
+
+use std::fmt::Debug;
+
+use clevercloud_sdk::oauth10a::{ClientError, RestClient};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::svc::clevercloud::client::{observe, Client};
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to rename addon '{0}' of organisation '{1}' to '{2}', {3}")]
+    Rename(String, String, String, ClientError),
+}
+
+// -----------------------------------------------------------------------------
+// UpdateOpts structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct UpdateOpts {
+    #[serde(rename = "name")]
+    pub name: String,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// rename the addon of the organisation
+pub async fn rename(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+    name: &str,
+) -> Result<(), Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/addons/{}",
+        endpoint, organisation, id
+    );
+
+    let opts = UpdateOpts {
+        name: name.to_string(),
+    };
+
+    debug!(endpoint = &path, "execute a request to rename an addon");
+    let _: serde_json::Value = observe("addon", "rename", client.put(&path, &opts))
+        .await
+        .map_err(|err| {
+            Error::Rename(id.to_owned(), organisation.to_owned(), name.to_owned(), err)
+        })?;
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! # Pool module
+//!
+//! This module provide a pool of Clever Cloud clients built from the secrets
+//! overriding the configuration of namespaces. Clients are keyed by the
+//! fingerprint of the configuration, so reconciliations of custom resources
+//! sharing credentials reuse the same connector and its connections instead of
+//! building a new client each time. A client is dropped from the pool once its
+//! time to live expires or its configuration changes.
+
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use k8s_openapi::api::core::v1::Secret;
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_counter_vec, CounterVec};
+use sha2::{Digest, Sha256};
+use tracing::trace;
+
+use crate::svc::{
+    cfg::NamespaceConfiguration,
+    clevercloud::client::{self, Client, Error},
+};
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static CLIENT_POOL_REQUEST: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "clever_cloud_client_pool",
+            "number of clients served from the pool or built from a secret",
+        ),
+        &["result"]
+    )
+    .expect("metrics 'clever_cloud_client_pool' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(configuration)))]
+/// returns the hex-encoded sha256 of the configuration, so credentials are not
+/// kept in clear as keys of the pool
+pub fn fingerprint(configuration: &NamespaceConfiguration) -> Result<String, Error> {
+    let buf = serde_json::to_vec(configuration).map_err(Error::Fingerprint)?;
+
+    Ok(hex::encode(Sha256::digest(buf)))
+}
+
+// -----------------------------------------------------------------------------
+// ClientPool structure
+
+/// clients built from namespace overrides, keyed by the fingerprint of their
+/// configuration. A time to live of zero disables the pool
+#[derive(Debug)]
+pub struct ClientPool {
+    ttl: Duration,
+    entries: Mutex<BTreeMap<String, (Client, Instant)>>,
+}
+
+impl ClientPool {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(secret)))]
+    /// returns the client of the configuration held by the secret, it is
+    /// served from the pool if it has been built for less than the time to live
+    pub async fn get(&self, secret: Secret) -> Result<Client, Error> {
+        let configuration = client::configuration(secret).await?;
+        let key = fingerprint(&configuration)?;
+
+        let cached = self.entries.lock().ok().and_then(|entries| {
+            entries
+                .get(&key)
+                .filter(|(_, expires_at)| *expires_at > Instant::now())
+                .map(|(client, _)| client.to_owned())
+        });
+
+        if let Some(client) = cached {
+            trace!("Serve clever cloud client from the pool");
+
+            #[cfg(feature = "metrics")]
+            CLIENT_POOL_REQUEST.with_label_values(&["hit"]).inc();
+
+            return Ok(client);
+        }
+
+        #[cfg(feature = "metrics")]
+        CLIENT_POOL_REQUEST.with_label_values(&["miss"]).inc();
+
+        let client = client::try_new(&configuration.api, &configuration.proxy)?;
+        if !self.ttl.is_zero() {
+            if let Ok(mut entries) = self.entries.lock() {
+                let now = Instant::now();
+
+                entries.retain(|_, (_, expires_at)| *expires_at > now);
+                entries.insert(key, (client.to_owned(), now + self.ttl));
+            }
+        }
+
+        Ok(client)
+    }
+}
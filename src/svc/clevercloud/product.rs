@@ -0,0 +1,79 @@
+//! # Product module
+//!
+//! This module provide structures and helpers to interact with the product
+//! endpoint of addon providers of the Clever Cloud's api, which lists the plans
+//! offered by a provider. It is used to resolve the plans of providers which
+//! are not listed in the catalog of the operator, e.g. partner addons, from
+//! their slug or name.
+
+use clevercloud_sdk::oauth10a::{ClientError, RestClient};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::svc::clevercloud::client::{retry, Client};
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to retrieve plans of addon provider '{0}', {1}")]
+    Get(String, ClientError),
+}
+
+// -----------------------------------------------------------------------------
+// Plan structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Plan {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "slug")]
+    pub slug: String,
+}
+
+// -----------------------------------------------------------------------------
+// Product structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Product {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "plans", default)]
+    pub plans: Vec<Plan>,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the product of the addon provider with its plans
+pub async fn get(client: &Client, endpoint: &str, provider: &str) -> Result<Product, Error> {
+    let path = format!("{}/v2/products/addonproviders/{}", endpoint, provider);
+
+    debug!(
+        endpoint = &path,
+        "execute a request to get the plans of an addon provider"
+    );
+    retry("product", "get", || client.get(&path))
+        .await
+        .map_err(|err| Error::Get(provider.to_string(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the plan of the addon provider matching the given slug or name, if
+/// any
+pub async fn find(
+    client: &Client,
+    endpoint: &str,
+    provider: &str,
+    plan: &str,
+) -> Result<Option<Plan>, Error> {
+    Ok(get(client, endpoint, provider)
+        .await?
+        .plans
+        .into_iter()
+        .find(|p| p.slug.eq_ignore_ascii_case(plan) || p.name.eq_ignore_ascii_case(plan)))
+}
@@ -0,0 +1,246 @@
+//! # Provider module
+//!
+//! This module provide structures and helpers to interact with the description
+//! endpoint of addon providers of the Clever Cloud's api, which lists the
+//! versions offered by a provider, the regions of its shared clusters and the
+//! features available for each version. It is used to verify that options of
+//! a custom resource are offered before creating its addon, so the custom
+//! resource reports a precise error rather than the opaque one of the api.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+};
+
+use clevercloud_sdk::{
+    oauth10a::{ClientError, RestClient},
+    v2::addon,
+    v4::addon_provider::AddonProviderId,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::svc::clevercloud::client::{retry, Client};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// name of the feature encrypting the data of an addon at rest
+pub const ENCRYPTION: &str = "encryption";
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to retrieve description of addon provider '{0}', {1}")]
+    Get(String, ClientError),
+    #[error("version {0} is not offered by addon provider '{1}', available versions are {2}")]
+    Version(String, String, String),
+    #[error("version {0} not available in {1}, it is available in {2}")]
+    Region(String, String, String),
+    #[error("feature '{0}' is not available for version {1} of addon provider '{2}'")]
+    Feature(String, String, String),
+}
+
+impl Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns if the error is due to options which are not offered by the
+    /// addon provider, retrying on such errors is useless
+    pub fn unavailable(&self) -> bool {
+        matches!(
+            self,
+            Self::Version(_, _, _) | Self::Region(_, _, _) | Self::Feature(_, _, _)
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Feature structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Feature {
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "enabled", default)]
+    pub enabled: bool,
+}
+
+// -----------------------------------------------------------------------------
+// Cluster structure
+
+/// shared cluster of an addon provider, it serves a single version in a region
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Cluster {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "zone")]
+    pub zone: String,
+    #[serde(rename = "version")]
+    pub version: String,
+    #[serde(rename = "features", default)]
+    pub features: Vec<Feature>,
+}
+
+// -----------------------------------------------------------------------------
+// AddonProvider structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct AddonProvider {
+    #[serde(rename = "providerId")]
+    pub provider_id: String,
+    #[serde(rename = "clusters", default)]
+    pub clusters: Vec<Cluster>,
+    /// features of dedicated addons, keyed by version. Dedicated addons could
+    /// be created in any region
+    #[serde(rename = "dedicated", default)]
+    pub dedicated: BTreeMap<String, Vec<Feature>>,
+}
+
+impl AddonProvider {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// verify that the version is offered in the region with the given
+    /// features enabled
+    pub fn check(&self, version: &str, region: &str, features: &[&str]) -> Result<(), Error> {
+        let versions: BTreeSet<&str> = self
+            .dedicated
+            .keys()
+            .map(String::as_str)
+            .chain(self.clusters.iter().map(|cluster| cluster.version.as_str()))
+            .collect();
+
+        if !versions.contains(version) {
+            return Err(Error::Version(
+                version.to_string(),
+                self.provider_id.to_owned(),
+                join(versions),
+            ));
+        }
+
+        let available = match self.dedicated.get(version) {
+            Some(features) => features,
+            None => {
+                let clusters: Vec<_> = self
+                    .clusters
+                    .iter()
+                    .filter(|cluster| cluster.version == version)
+                    .collect();
+
+                match clusters.iter().find(|cluster| cluster.zone == region) {
+                    Some(cluster) => &cluster.features,
+                    None => {
+                        return Err(Error::Region(
+                            version.to_string(),
+                            region.to_string(),
+                            join(clusters.iter().map(|cluster| cluster.zone.as_str())),
+                        ));
+                    }
+                }
+            }
+        };
+
+        for feature in features {
+            if !available
+                .iter()
+                .any(|available| available.name == *feature && available.enabled)
+            {
+                return Err(Error::Feature(
+                    feature.to_string(),
+                    version.to_string(),
+                    self.provider_id.to_owned(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+/// returns the values joined by commas
+fn join<'a, I>(values: I) -> String
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    values.into_iter().collect::<Vec<_>>().join(", ")
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the description of the addon provider
+pub async fn get(
+    client: &Client,
+    endpoint: &str,
+    provider: &AddonProviderId,
+) -> Result<AddonProvider, Error> {
+    let path = format!("{}/v4/addon-providers/{}", endpoint, provider);
+
+    debug!(
+        endpoint = &path,
+        "execute a request to get the description of an addon provider"
+    );
+    retry("provider", "get", || client.get(&path))
+        .await
+        .map_err(|err| Error::Get(provider.to_string(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the version and the features requested by the creation options,
+/// if a version is requested
+pub fn requirements(opts: &addon::Opts) -> Option<(String, Vec<String>)> {
+    let version = opts.version.to_owned()?;
+    let mut features = vec![];
+
+    if opts.encryption.as_deref() == Some("true") {
+        features.push(ENCRYPTION.to_string());
+    }
+
+    // services are serialized as a list of features, e.g. kibana and apm of
+    // elasticsearch addons
+    if let Some(services) = &opts.services {
+        let services: Vec<Feature> = serde_json::from_str(services).unwrap_or_default();
+        features.extend(
+            services
+                .into_iter()
+                .filter(|service| service.enabled)
+                .map(|service| service.name),
+        );
+    }
+
+    Some((version, features))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// verify that the addon provider offers the version of the creation options
+/// in the region with the requested features enabled. The verification is
+/// skipped if the description of the addon provider could not be retrieved,
+/// the api still rejects options which are not offered
+pub async fn validate(
+    client: &Client,
+    endpoint: &str,
+    provider: &AddonProviderId,
+    opts: &addon::Opts,
+    region: &str,
+) -> Result<(), Error> {
+    let (version, features) = match requirements(opts) {
+        Some(requirements) => requirements,
+        None => return Ok(()),
+    };
+
+    let description = match get(client, endpoint, provider).await {
+        Ok(description) => description,
+        Err(err) => {
+            warn!(
+                provider = provider.to_string(),
+                error = err.to_string(),
+                "Could not verify that options are offered by the addon provider",
+            );
+
+            return Ok(());
+        }
+    };
+
+    let features: Vec<&str> = features.iter().map(String::as_str).collect();
+    description.check(&version, region, &features)
+}
@@ -0,0 +1,169 @@
+//! # Pulsar module
+//!
+//! This module provide a minimal client of the admin api exposed by the Pulsar
+//! addon of Clever Cloud to manage namespaces and topics of the tenant of the
+//! addon, using the credentials of the addon.
+//!
+//! See <https://pulsar.apache.org/admin-rest-api/>
+
+use std::{collections::BTreeMap, fmt::Debug};
+
+use hyper::{header, Body, Method, Request, StatusCode};
+use tracing::debug;
+
+use crate::svc::{
+    cfg::{Api, Proxy},
+    clevercloud::client::{self, Connector},
+    redact::Redacted,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const PULSAR_HTTP_URL: &str = "ADDON_PULSAR_HTTP_URL";
+pub const PULSAR_TENANT: &str = "ADDON_PULSAR_TENANT";
+pub const PULSAR_NAMESPACE: &str = "ADDON_PULSAR_NAMESPACE";
+pub const PULSAR_TOKEN: &str = "ADDON_PULSAR_TOKEN";
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to find key '{0}' in pulsar credentials")]
+    MissingKey(&'static str),
+    #[error("failed to create http client, {0}")]
+    Connector(client::Error),
+    #[error("failed to build request for '{0}', {1}")]
+    Request(String, hyper::http::Error),
+    #[error("failed to execute request for '{0}', {1}")]
+    Execute(String, hyper::Error),
+    #[error("failed to create '{0}', pulsar answered with status code '{1}'")]
+    StatusCode(String, StatusCode),
+}
+
+// -----------------------------------------------------------------------------
+// Credentials structure
+
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub http_url: String,
+    pub tenant: String,
+    pub namespace: String,
+    pub token: Redacted<String>,
+}
+
+impl TryFrom<&BTreeMap<String, String>> for Credentials {
+    type Error = Error;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(data)))]
+    fn try_from(data: &BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        let get = |key: &'static str| data.get(key).cloned().ok_or(Error::MissingKey(key));
+
+        Ok(Self {
+            http_url: get(PULSAR_HTTP_URL)?.trim_end_matches('/').to_string(),
+            tenant: get(PULSAR_TENANT)?,
+            namespace: get(PULSAR_NAMESPACE)?,
+            token: Redacted::from(get(PULSAR_TOKEN)?),
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Client structure
+
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: hyper::Client<Connector>,
+    credentials: Credentials,
+}
+
+impl Client {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn try_new(
+        api: &Api,
+        proxy: &Option<Proxy>,
+        credentials: Credentials,
+    ) -> Result<Self, Error> {
+        let connector = client::connector(api, proxy).map_err(Error::Connector)?;
+
+        Ok(Self {
+            inner: hyper::Client::builder().build(connector),
+            credentials,
+        })
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// create the namespace in the tenant of the addon, if it does not
+    /// already exist
+    pub async fn create_namespace(&self, namespace: &str) -> Result<(), Error> {
+        let resource = format!("{}/{}", self.credentials.tenant, namespace);
+        let path = format!("/admin/v2/namespaces/{}", resource);
+
+        self.create(&resource, &path, Body::empty()).await
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// create the persistent topic in the given namespace of the tenant of the
+    /// addon, if it does not already exist. The topic is partitioned if a
+    /// number of partitions is given
+    pub async fn create_topic(
+        &self,
+        namespace: &str,
+        topic: &str,
+        partitions: Option<u32>,
+    ) -> Result<(), Error> {
+        let resource = format!("{}/{}/{}", self.credentials.tenant, namespace, topic);
+
+        match partitions {
+            Some(partitions) => {
+                let path = format!("/admin/v2/persistent/{}/partitions", resource);
+                self.create(&resource, &path, Body::from(partitions.to_string()))
+                    .await
+            }
+            None => {
+                let path = format!("/admin/v2/persistent/{}", resource);
+                self.create(&resource, &path, Body::empty()).await
+            }
+        }
+    }
+
+    /// execute a creation request on the admin api, a conflict means that the
+    /// resource already exists
+    async fn create(&self, resource: &str, path: &str, body: Body) -> Result<(), Error> {
+        let req = Request::builder()
+            .method(Method::PUT)
+            .uri(format!("{}{}", self.credentials.http_url, path))
+            .header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", *self.credentials.token),
+            )
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .map_err(|err| Error::Request(resource.to_string(), err))?;
+
+        debug!(
+            method = Method::PUT.as_str(),
+            url = &self.credentials.http_url,
+            resource = resource,
+            "execute a request on pulsar admin api"
+        );
+
+        let res = self
+            .inner
+            .request(req)
+            .await
+            .map_err(|err| Error::Execute(resource.to_string(), err))?;
+
+        match res.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::CONFLICT => Ok(()),
+            status => Err(Error::StatusCode(resource.to_string(), status)),
+        }
+    }
+}
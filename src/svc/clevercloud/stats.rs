@@ -0,0 +1,107 @@
+//! # Stats module
+//!
+//! This module provide structures and helpers to interact with the metrics
+//! endpoint of the Clever Cloud's api, backed by Warp10, which is not exposed
+//! by the `clevercloud-sdk` crate.
+
+use std::fmt::Debug;
+
+use clevercloud_sdk::oauth10a::{ClientError, RestClient};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::svc::clevercloud::client::{retry, Client};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// interval between two points of a metric, as an iso 8601 duration
+pub const METRICS_INTERVAL: &str = "PT1M";
+/// period over which points of a metric are requested, as an iso 8601
+/// duration, only the latest one is used
+pub const METRICS_SPAN: &str = "PT5M";
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to get metrics of addon '{0}' of organisation '{1}', {2}")]
+    Get(String, String, ClientError),
+}
+
+// -----------------------------------------------------------------------------
+// Point structure
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct Point {
+    /// timestamp of the point, in milliseconds since the epoch
+    #[serde(rename = "timestamp", default)]
+    pub timestamp: i64,
+    /// value of the point, which is given as a number or as a string
+    #[serde(rename = "value", default)]
+    pub value: serde_json::Value,
+}
+
+impl Point {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn value(&self) -> Option<f64> {
+        self.value
+            .as_f64()
+            .or_else(|| self.value.as_str().and_then(|value| value.parse().ok()))
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Metric structure
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct Metric {
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "unit", default)]
+    pub unit: String,
+    #[serde(rename = "data", default)]
+    pub data: Vec<Point>,
+}
+
+impl Metric {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the value of the latest point of the metric, if any
+    pub fn latest(&self) -> Option<f64> {
+        self.data
+            .iter()
+            .filter_map(|point| point.value().map(|value| (point.timestamp, value)))
+            .max_by_key(|(timestamp, _)| *timestamp)
+            .map(|(_, value)| value)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the given metrics of the addon over the last minutes, the given
+/// identifier is the real identifier of the addon, e.g. `postgresql_<uuid>`
+pub async fn metrics(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+    fields: &[String],
+) -> Result<Vec<Metric>, Error> {
+    let only: String = fields
+        .iter()
+        .map(|field| format!("&only={}", field))
+        .collect();
+
+    let path = format!(
+        "{}/v4/stats/organisations/{}/resources/{}/metrics?interval={}&span={}{}",
+        endpoint, organisation, id, METRICS_INTERVAL, METRICS_SPAN, only
+    );
+
+    debug!(endpoint = &path, "execute a request to get metrics");
+    retry("stats", "metrics", || client.get(&path))
+        .await
+        .map_err(|err| Error::Get(id.to_owned(), organisation.to_owned(), err))
+}
@@ -0,0 +1,118 @@
+//! # Tags module
+//!
+//! This module provide helpers to interact with the tags' endpoints of addons
+//! of the Clever Cloud's api, which are not exposed by the `clevercloud-sdk`
+//! crate. Tags are free-form strings attached to an addon.
+
+use std::fmt::Debug;
+
+use clevercloud_sdk::oauth10a::{ClientError, RestClient};
+use tracing::debug;
+
+use crate::svc::clevercloud::client::{retry, Client};
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to list tags of addon '{0}' of organisation '{1}', {2}")]
+    List(String, String, ClientError),
+    #[error("failed to add tag '{0}' to addon '{1}' of organisation '{2}', {3}")]
+    Add(String, String, String, ClientError),
+    #[error("failed to remove tag '{0}' from addon '{1}' of organisation '{2}', {3}")]
+    Remove(String, String, String, ClientError),
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the tag percent-encoded, so it could be given as a segment of the
+/// path of a request, tags of kubernetes labels may hold `/`
+pub fn encode(tag: &str) -> String {
+    tag.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the tags of the addon of the organisation
+pub async fn list(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+) -> Result<Vec<String>, Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/addons/{}/tags",
+        endpoint, organisation, id
+    );
+
+    debug!(
+        endpoint = &path,
+        "execute a request to list tags of an addon"
+    );
+    retry("tags", "list", || client.get(&path))
+        .await
+        .map_err(|err| Error::List(id.to_owned(), organisation.to_owned(), err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// add the tag to the addon of the organisation
+pub async fn add(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+    tag: &str,
+) -> Result<(), Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/addons/{}/tags/{}",
+        endpoint,
+        organisation,
+        id,
+        encode(tag)
+    );
+
+    debug!(
+        endpoint = &path,
+        "execute a request to add a tag to an addon"
+    );
+    let _: Vec<String> = retry("tags", "add", || client.put(&path, &()))
+        .await
+        .map_err(|err| Error::Add(tag.to_owned(), id.to_owned(), organisation.to_owned(), err))?;
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// remove the tag from the addon of the organisation
+pub async fn remove(
+    client: &Client,
+    endpoint: &str,
+    organisation: &str,
+    id: &str,
+    tag: &str,
+) -> Result<(), Error> {
+    let path = format!(
+        "{}/v2/organisations/{}/addons/{}/tags/{}",
+        endpoint,
+        organisation,
+        id,
+        encode(tag)
+    );
+
+    debug!(
+        endpoint = &path,
+        "execute a request to remove a tag from an addon"
+    );
+    retry("tags", "remove", || client.delete(&path))
+        .await
+        .map_err(|err| Error::Remove(tag.to_owned(), id.to_owned(), organisation.to_owned(), err))
+}
@@ -0,0 +1,467 @@
+//! # Backup resource
+//!
+//! This module provide the backup custom resource and its definition, it tracks
+//! the backups that Clever Cloud takes of a PostgreSql, MySql or MongoDb addon
+//! managed by the operator and exposes them in its status.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use clevercloud_sdk::v2::{self, addon};
+use k8s_openapi::api::core::v1::Secret;
+use kube::{
+    runtime::{controller, watcher, Controller},
+    Client, CustomResource, Resource,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::svc::{
+    cfg::Api as ApiConfiguration,
+    clevercloud::{
+        self, backup,
+        client::{retry, ScopedClient},
+    },
+    crd::{mongodb::MongoDb, mysql::MySql, postgresql::PostgreSql, CredentialsSecretReference},
+    k8s::{
+        self,
+        condition::{self, Condition, Conditions},
+        rbac::{self, Permission},
+        recorder, resource, secret, Context, ControllerBuilder,
+    },
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// kinds of custom resources whose addon could be backed up
+pub const SUPPORTED_KINDS: [&str; 3] = ["PostgreSql", "MySql", "MongoDb"];
+
+/// kubernetes resources touched by the reconciler beside its custom resources
+pub const PERMISSIONS: &[Permission] = &[rbac::SECRETS_READ];
+
+// -----------------------------------------------------------------------------
+// AddonReference structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct AddonReference {
+    /// kind of the custom resource, one of `PostgreSql`, `MySql` or `MongoDb`
+    #[serde(rename = "kind")]
+    pub kind: String,
+    /// name of the custom resource in the namespace of the backup
+    #[serde(rename = "name")]
+    pub name: String,
+}
+
+// -----------------------------------------------------------------------------
+// Spec structure
+
+#[derive(CustomResource, JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[kube(group = "api.clever-cloud.com")]
+#[kube(version = "v1")]
+#[kube(kind = "Backup")]
+#[kube(singular = "backup")]
+#[kube(plural = "backups")]
+#[kube(shortname = "bk")]
+#[kube(status = "Status")]
+#[kube(namespaced)]
+#[kube(derive = "PartialEq")]
+#[kube(
+    printcolumn = r#"{"name":"kind", "type":"string", "description":"Kind", "jsonPath":".spec.addonRef.kind"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"addon", "type":"string", "description":"Addon", "jsonPath":".spec.addonRef.name"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"latest", "type":"string", "description":"Latest backup", "jsonPath":".status.backups[0].createdAt"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"ready", "type":"string", "description":"Ready", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"phase", "type":"string", "description":"Reason of the ready condition", "jsonPath":".status.conditions[?(@.type==\"Ready\")].reason"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"age", "type":"date", "description":"Age", "jsonPath":".metadata.creationTimestamp"}"#
+)]
+pub struct Spec {
+    #[serde(rename = "addonRef")]
+    pub addon_ref: AddonReference,
+    /// secret holding the configuration of the clever cloud client used to
+    /// reconcile the custom resource, the `clever-operator` secret of the
+    /// namespace is used if not set
+    #[serde(
+        rename = "credentialsSecretRef",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub credentials_secret_ref: Option<CredentialsSecretReference>,
+    /// delay in seconds between two refreshes of the backups
+    #[serde(rename = "refreshInterval", default = "Spec::default_refresh_interval")]
+    pub refresh_interval: u64,
+    /// suspend the reconciliation of the custom resource, e.g. during a
+    /// manual maintenance on Clever Cloud, its deletion is still processed
+    #[serde(rename = "suspend", default)]
+    pub suspend: bool,
+}
+
+impl Spec {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn default_refresh_interval() -> u64 {
+        3600
+    }
+}
+
+// -----------------------------------------------------------------------------
+// BackupStatus structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct BackupStatus {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "deleteAt", default, skip_serializing_if = "Option::is_none")]
+    pub delete_at: Option<String>,
+    #[serde(
+        rename = "downloadUrl",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub download_url: Option<String>,
+}
+
+impl From<backup::Backup> for BackupStatus {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(backup: backup::Backup) -> Self {
+        Self {
+            id: backup.id,
+            status: backup.status,
+            created_at: backup.creation_date,
+            delete_at: backup.delete_at,
+            download_url: backup.download_url,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Status structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Status {
+    /// real identifier of the addon, e.g. `postgresql_<uuid>`
+    #[serde(rename = "addon", default, skip_serializing_if = "Option::is_none")]
+    pub addon: Option<String>,
+    /// backups of the addon, from the latest to the oldest
+    #[serde(rename = "backups", default)]
+    pub backups: Vec<BackupStatus>,
+    #[serde(rename = "conditions", default)]
+    pub conditions: Vec<Condition>,
+    /// generation of the specification processed by the last successful
+    /// reconciliation
+    #[serde(
+        rename = "observedGeneration",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub observed_generation: Option<i64>,
+}
+
+// -----------------------------------------------------------------------------
+// Backup implementation
+
+impl Backup {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_backups(&mut self, addon: Option<String>, backups: Vec<BackupStatus>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.addon = addon;
+        status.backups = backups;
+    }
+}
+
+impl Conditions for Backup {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn conditions_mut(&mut self) -> &mut Vec<Condition> {
+        &mut self.status.get_or_insert_with(Status::default).conditions
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Action structure
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub enum Action {
+    UpdateBackups,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::UpdateBackups => write!(f, "UpdateBackups"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// ReconcilerError enum
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReconcilerError {
+    #[error("failed to reconcile resource, {0}")]
+    Reconcile(String),
+    #[error("failed to execute request on clever-cloud api, {0}")]
+    CleverClient(clevercloud::Error),
+    #[error("failed to create clevercloud client, {0}")]
+    CreateCleverClient(clevercloud::client::Error),
+    #[error("failed to retrieve credentials secret, {0}")]
+    Credentials(secret::Error),
+    #[error("failed to execute request on kubernetes api, {0}")]
+    KubeClient(kube::Error),
+    #[error("failed to compute diff between the original and modified object, {0}")]
+    Diff(serde_json::Error),
+    #[error("failed to authorize call on clever-cloud api, {0}")]
+    Scope(clevercloud::client::ScopeError),
+    #[error("failed to back up resources of kind '{0}', supported kinds are {1:?}")]
+    UnsupportedKind(String, [&'static str; 3]),
+    #[error("failed to find addon of custom resource '{0}/{1}', it may not be provisioned yet")]
+    AddonNotFound(String, String),
+}
+
+impl From<kube::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: kube::Error) -> Self {
+        Self::KubeClient(err)
+    }
+}
+
+impl From<clevercloud::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: clevercloud::Error) -> Self {
+        Self::CleverClient(err)
+    }
+}
+
+impl From<v2::addon::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: v2::addon::Error) -> Self {
+        Self::from(clevercloud::Error::from(err))
+    }
+}
+
+impl From<backup::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: backup::Error) -> Self {
+        Self::from(clevercloud::Error::from(err))
+    }
+}
+
+impl From<controller::Error<Self, watcher::Error>> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: controller::Error<ReconcilerError, watcher::Error>) -> Self {
+        Self::Reconcile(err.to_string())
+    }
+}
+
+impl From<clevercloud::client::ScopeError> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: clevercloud::client::ScopeError) -> Self {
+        Self::Scope(err)
+    }
+}
+
+impl From<clevercloud::client::Error> for ReconcilerError {
+    fn from(err: clevercloud::client::Error) -> Self {
+        Self::CreateCleverClient(err)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Reconciler structure
+
+#[derive(Clone, Default, Debug)]
+pub struct Reconciler {}
+
+impl ControllerBuilder<Backup> for Reconciler {
+    fn build(&self, state: Arc<Context>, namespace: Option<&str>) -> Controller<Backup> {
+        Controller::new(
+            k8s::api(state.kube.to_owned(), namespace),
+            k8s::watcher_config(&state.config),
+        )
+    }
+}
+
+impl Reconciler {
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(kube)))]
+    /// returns the organisation and the identifier of the addon of the
+    /// referenced custom resource
+    async fn addon(
+        kube: Client,
+        namespace: &str,
+        reference: &AddonReference,
+    ) -> Result<(String, String), ReconcilerError> {
+        let name = &reference.name;
+        let addon = match reference.kind.as_str() {
+            "PostgreSql" => resource::get::<PostgreSql>(kube, namespace, name)
+                .await?
+                .and_then(|obj| Some((obj.spec.organisation.to_owned(), obj.get_addon_id()?))),
+            "MySql" => resource::get::<MySql>(kube, namespace, name)
+                .await?
+                .and_then(|obj| Some((obj.spec.organisation.to_owned(), obj.get_addon_id()?))),
+            "MongoDb" => resource::get::<MongoDb>(kube, namespace, name)
+                .await?
+                .and_then(|obj| Some((obj.spec.organisation.to_owned(), obj.get_addon_id()?))),
+            kind => {
+                return Err(ReconcilerError::UnsupportedKind(
+                    kind.to_string(),
+                    SUPPORTED_KINDS,
+                ))
+            }
+        };
+
+        addon.ok_or_else(|| ReconcilerError::AddonNotFound(namespace.to_string(), name.to_owned()))
+    }
+}
+
+#[async_trait]
+impl k8s::Reconciler<Backup> for Reconciler {
+    type Error = ReconcilerError;
+
+    fn permanent(err: &ReconcilerError) -> Option<&'static str> {
+        match err {
+            ReconcilerError::CleverClient(err) => err.permanent(),
+            ReconcilerError::UnsupportedKind(_, _) => Some("UnsupportedKind"),
+            _ => None,
+        }
+    }
+
+    fn requeue(obj: &Backup) -> Option<Duration> {
+        Some(Duration::from_secs(obj.spec.refresh_interval))
+    }
+
+    async fn upsert(ctx: Arc<Context>, origin: Arc<Backup>) -> Result<(), ReconcilerError> {
+        let Context {
+            kube,
+            apis,
+            config,
+            secrets,
+            clients,
+            ..
+        } = ctx.as_ref();
+        let ApiConfiguration { endpoint, .. } = &config.api;
+
+        let kind = Backup::kind(&()).to_string();
+        let (namespace, name) = resource::namespaced_name(&*origin);
+
+        // ---------------------------------------------------------------------
+        // Step 0: verify if there is a clever cloud client override
+        let reference = origin.spec.credentials_secret_ref.as_ref();
+        let (secret_namespace, secret_name) = secret::credentials_reference(&namespace, reference);
+
+        debug!(
+            namespace = &secret_namespace,
+            secret = &secret_name,
+            "Try to retrieve the optional secret on namespace",
+        );
+
+        let secret: Option<Secret> = secret::credentials(secrets, &namespace, reference)
+            .await
+            .map_err(ReconcilerError::Credentials)?;
+        let apis = match secret {
+            Some(secret) => {
+                info!(
+                    namespace = &secret_namespace,
+                    secret = &secret_name,
+                    "Use custom Clever Cloud client to connect the api using secret",
+                );
+
+                ScopedClient::scoped(clients.get(secret).await?, &kind)
+            }
+            None => {
+                info!("Use default Clever Cloud client to connect the api");
+                ScopedClient::unscoped(apis.get())
+            }
+        };
+
+        // ---------------------------------------------------------------------
+        // Step 1: resolve the addon of the referenced custom resource
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            addon_kind = &origin.spec.addon_ref.kind,
+            addon_name = &origin.spec.addon_ref.name,
+            "Resolve the addon of the referenced custom resource",
+        );
+
+        let (organisation, id) =
+            Self::addon(kube.to_owned(), &namespace, &origin.spec.addon_ref).await?;
+        let apis = apis.with_rate_limit(&organisation, &config.api.rate_limit);
+
+        let client = apis.authorize(&kind, "get").await?;
+        let addon = retry("addon", "get", || addon::get(client, &organisation, &id)).await?;
+
+        // ---------------------------------------------------------------------
+        // Step 2: list backups of the addon
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            addon = &addon.real_id,
+            "List backups of the addon for custom resource",
+        );
+
+        let client = apis.authorize(&kind, "list").await?;
+        let mut backups = backup::list(client, endpoint, &organisation, &addon.real_id).await?;
+        backups.sort_by(|a, b| b.creation_date.cmp(&a.creation_date));
+
+        let count = backups.len();
+        let mut modified = (*origin).to_owned();
+        modified.set_backups(
+            Some(addon.real_id.to_owned()),
+            backups.into_iter().map(BackupStatus::from).collect(),
+        );
+
+        modified.set_condition(Condition::new(
+            condition::READY,
+            condition::Status::True,
+            "BackupsListed",
+            &format!("Addon '{}' has {} backup(s)", addon.real_id, count),
+        ));
+
+        if *origin == modified {
+            return Ok(());
+        }
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update status of custom resource",
+        );
+
+        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
+        let modified = resource::patch_status(kube.to_owned(), modified, patch).await?;
+
+        let action = &Action::UpdateBackups;
+        let message = &format!("Update backups of addon '{}'", addon.real_id);
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        Ok(())
+    }
+
+    async fn delete(_ctx: Arc<Context>, _origin: Arc<Backup>) -> Result<(), ReconcilerError> {
+        // backups are taken and retained by clever cloud, there is nothing to
+        // clean up on third parts
+        Ok(())
+    }
+}
@@ -0,0 +1,712 @@
+//! # CellarBucket resource
+//!
+//! This module provide the cellar bucket custom resource and its definition, a
+//! bucket is created through the s3 api of Cellar using the credentials of an
+//! existing Cellar addon. Its cors rules and its static website configuration
+//! are managed along.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use futures::TryFutureExt;
+use k8s_openapi::api::core::v1::Secret;
+use kube::{
+    runtime::{controller, watcher, Controller},
+    CustomResource, Resource, ResourceExt,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::svc::{
+    clevercloud::{
+        self,
+        cellar::{
+            self, CorsRule, Credentials, Website, CELLAR_BUCKET, CELLAR_HOST, CELLAR_KEY_ID,
+            CELLAR_KEY_SECRET,
+        },
+    },
+    crd::{
+        Binding, DeletionPolicy, Dependency, Exposure, SecretReference, SecretTemplate,
+        ServiceBinding,
+    },
+    k8s::{
+        self,
+        binding::{self, Mapping},
+        condition::{self, Condition, Conditions},
+        dependency, drift, export, finalizer,
+        rbac::{self, Permission},
+        recorder, resource, secret, Context, ControllerBuilder,
+    },
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const BUCKET_FINALIZER: &str = "api.clever-cloud.com/cellar-bucket";
+
+/// well-known entries of the service binding specification
+pub const BINDING: Mapping = Mapping {
+    kind: "s3",
+    keys: &[
+        ("host", "CELLAR_ADDON_HOST"),
+        ("access-key-id", "CELLAR_ADDON_KEY_ID"),
+        ("secret-access-key", "CELLAR_ADDON_KEY_SECRET"),
+        ("bucket", "CELLAR_ADDON_BUCKET"),
+    ],
+};
+
+/// kubernetes resources touched by the reconciler beside its custom resources
+pub const PERMISSIONS: &[Permission] = &[rbac::SECRETS];
+
+// -----------------------------------------------------------------------------
+// Spec structure
+
+#[derive(CustomResource, JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[kube(group = "api.clever-cloud.com")]
+#[kube(version = "v1")]
+#[kube(kind = "CellarBucket")]
+#[kube(singular = "cellarbucket")]
+#[kube(plural = "cellarbuckets")]
+#[kube(shortname = "cb")]
+#[kube(status = "Status")]
+#[kube(namespaced)]
+#[kube(derive = "PartialEq")]
+#[kube(
+    printcolumn = r#"{"name":"bucket", "type":"string", "description":"Bucket", "jsonPath":".status.bucket"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"host", "type":"string", "description":"Host", "jsonPath":".status.host"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"website", "type":"string", "description":"Website", "jsonPath":".status.websiteEndpoint", "priority":1}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"ready", "type":"string", "description":"Ready", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"phase", "type":"string", "description":"Reason of the ready condition", "jsonPath":".status.conditions[?(@.type==\"Ready\")].reason"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"age", "type":"date", "description":"Age", "jsonPath":".metadata.creationTimestamp"}"#
+)]
+pub struct Spec {
+    /// secret holding the credentials of the Cellar addon, using the keys of
+    /// its environment: `CELLAR_ADDON_HOST`, `CELLAR_ADDON_KEY_ID` and
+    /// `CELLAR_ADDON_KEY_SECRET`
+    #[serde(rename = "credentialsRef")]
+    pub credentials_ref: SecretReference,
+    /// name of the bucket, defaults to the name of the custom resource
+    #[serde(rename = "bucket", default, skip_serializing_if = "Option::is_none")]
+    pub bucket: Option<String>,
+    #[serde(rename = "exposure", default, skip_serializing_if = "Option::is_none")]
+    pub exposure: Option<Exposure>,
+    #[serde(
+        rename = "secretTemplate",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub secret_template: Option<SecretTemplate>,
+    /// namespaces in which the generated secret is replicated, so workloads
+    /// of other namespaces could consume it
+    #[serde(rename = "exportTo", default, skip_serializing_if = "Vec::is_empty")]
+    pub export_to: Vec<String>,
+    /// options of the secret following the service binding specification
+    #[serde(
+        rename = "serviceBinding",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub service_binding: Option<ServiceBinding>,
+    /// custom resources which must be ready before provisioning this one
+    #[serde(rename = "dependsOn", default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<Dependency>,
+    /// whether the bucket and the generated secrets are deleted along the
+    /// custom resource, one of `Delete` (default), `Orphan` or `Retain`
+    #[serde(rename = "deletionPolicy", default)]
+    pub deletion_policy: DeletionPolicy,
+    /// suspend the reconciliation of the custom resource, e.g. during a
+    /// manual maintenance on Clever Cloud, its deletion is still processed
+    #[serde(rename = "suspend", default)]
+    pub suspend: bool,
+    /// cross-origin resource sharing rules of the bucket, the ones of the
+    /// bucket are removed if none is given
+    #[serde(rename = "cors", default, skip_serializing_if = "Vec::is_empty")]
+    pub cors: Vec<CorsRule>,
+    /// serve the objects of the bucket as a static website
+    #[serde(rename = "website", default, skip_serializing_if = "Option::is_none")]
+    pub website: Option<Website>,
+}
+
+// -----------------------------------------------------------------------------
+// Status structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Status {
+    #[serde(rename = "bucket")]
+    pub bucket: Option<String>,
+    #[serde(rename = "host", default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// public url of the static website served from the bucket, if any
+    #[serde(
+        rename = "websiteEndpoint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub website_endpoint: Option<String>,
+    #[serde(rename = "conditions", default)]
+    pub conditions: Vec<Condition>,
+    /// generation of the specification processed by the last successful
+    /// reconciliation
+    #[serde(
+        rename = "observedGeneration",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub observed_generation: Option<i64>,
+    #[serde(
+        rename = "excludedKeys",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub excluded_keys: Vec<String>,
+    /// reference to the secret following the service binding specification
+    #[serde(rename = "binding", default, skip_serializing_if = "Option::is_none")]
+    pub binding: Option<Binding>,
+}
+
+// -----------------------------------------------------------------------------
+// CellarBucket implementation
+
+impl CellarBucket {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the name of the bucket
+    pub fn bucket(&self) -> String {
+        self.spec
+            .bucket
+            .to_owned()
+            .unwrap_or_else(|| self.name_any())
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_bucket(&mut self, bucket: Option<String>, host: Option<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.bucket = bucket;
+        status.host = host;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_website_endpoint(&mut self, endpoint: Option<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.website_endpoint = endpoint;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn get_bucket(&self) -> Option<String> {
+        self.status.to_owned().unwrap_or_default().bucket
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_excluded_keys(&mut self, keys: Vec<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.excluded_keys = keys;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_binding(&mut self, binding: Option<Binding>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.binding = binding;
+    }
+}
+
+impl Conditions for CellarBucket {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn conditions_mut(&mut self) -> &mut Vec<Condition> {
+        &mut self.status.get_or_insert_with(Status::default).conditions
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Action structure
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub enum Action {
+    UpsertFinalizer,
+    UpsertBucket,
+    UpsertCors,
+    UpsertWebsite,
+    UpsertSecret,
+    DeleteFinalizer,
+    DeleteBucket,
+    KeepBucket,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::UpsertFinalizer => write!(f, "UpsertFinalizer"),
+            Self::UpsertBucket => write!(f, "UpsertBucket"),
+            Self::UpsertCors => write!(f, "UpsertCors"),
+            Self::UpsertWebsite => write!(f, "UpsertWebsite"),
+            Self::UpsertSecret => write!(f, "UpsertSecret"),
+            Self::DeleteFinalizer => write!(f, "DeleteFinalizer"),
+            Self::DeleteBucket => write!(f, "DeleteBucket"),
+            Self::KeepBucket => write!(f, "KeepBucket"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// ReconcilerError enum
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReconcilerError {
+    #[error("failed to reconcile resource, {0}")]
+    Reconcile(String),
+    #[error("failed to execute request on cellar, {0}")]
+    Cellar(clevercloud::Error),
+    #[error("failed to execute request on kubernetes api, {0}")]
+    KubeClient(kube::Error),
+    #[error("failed to compute diff between the original and modified object, {0}")]
+    Diff(serde_json::Error),
+    #[error("failed to read credentials of cellar, {0}")]
+    Credentials(secret::Error),
+    #[error("failed to wait for dependencies, {0}")]
+    DependsOn(dependency::Error),
+}
+
+impl From<kube::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: kube::Error) -> Self {
+        Self::KubeClient(err)
+    }
+}
+
+impl From<cellar::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: cellar::Error) -> Self {
+        Self::Cellar(clevercloud::Error::from(err))
+    }
+}
+
+impl From<controller::Error<Self, watcher::Error>> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: controller::Error<ReconcilerError, watcher::Error>) -> Self {
+        Self::Reconcile(err.to_string())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Reconciler structure
+
+#[derive(Clone, Default, Debug)]
+pub struct Reconciler {}
+
+impl ControllerBuilder<CellarBucket> for Reconciler {
+    fn build(&self, state: Arc<Context>, namespace: Option<&str>) -> Controller<CellarBucket> {
+        let client = state.kube.to_owned();
+        let secret = k8s::api::<Secret>(client.to_owned(), namespace);
+
+        Controller::new(
+            k8s::api(client, namespace),
+            k8s::watcher_config(&state.config),
+        )
+        .owns(secret, watcher::Config::default())
+        // reconcile the custom resource once a copy of its secret exported to
+        // another namespace is modified or deleted
+        .watches(
+            k8s::api::<Secret>(state.kube.to_owned(), namespace),
+            watcher::Config::default().labels(export::SOURCE_LABEL),
+            export::source::<CellarBucket>,
+        )
+    }
+}
+
+impl Reconciler {
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(ctx)))]
+    /// returns a cellar client using the credentials referenced by the custom
+    /// resource
+    async fn client(ctx: &Context, obj: &CellarBucket) -> Result<cellar::Client, ReconcilerError> {
+        let (namespace, _) = resource::namespaced_name(obj);
+        let data = secret::data(
+            ctx.kube.to_owned(),
+            &namespace,
+            &obj.spec.credentials_ref.name,
+        )
+        .await
+        .map_err(ReconcilerError::Credentials)?;
+
+        let credentials = Credentials::try_from(&*data)?;
+
+        Ok(cellar::Client::try_new(
+            &ctx.config.api,
+            &ctx.config.proxy,
+            credentials,
+        )?)
+    }
+}
+
+#[async_trait]
+impl k8s::Reconciler<CellarBucket> for Reconciler {
+    type Error = ReconcilerError;
+
+    fn permanent(err: &ReconcilerError) -> Option<&'static str> {
+        match err {
+            ReconcilerError::Cellar(err) => err.permanent(),
+            ReconcilerError::DependsOn(err) => err.permanent(),
+            _ => None,
+        }
+    }
+
+    async fn upsert(ctx: Arc<Context>, origin: Arc<CellarBucket>) -> Result<(), ReconcilerError> {
+        let Context { kube, config, .. } = ctx.as_ref();
+
+        let kind = CellarBucket::kind(&()).to_string();
+        let (namespace, name) = resource::namespaced_name(&*origin);
+
+        // ---------------------------------------------------------------------
+        // Step 1: set finalizer
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Set finalizer on custom resource",
+        );
+
+        let patch = finalizer::add_patch(&*origin, BUCKET_FINALIZER);
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update information of custom resource",
+        );
+
+        let mut modified = resource::patch(kube.to_owned(), &*origin, patch).await?;
+
+        let action = &Action::UpsertFinalizer;
+        let message = &format!("Create finalizer '{}'", BUCKET_FINALIZER);
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        // hold off provisioning until the custom resources this one depends on
+        // are ready
+        if let Err(err) =
+            dependency::all_ready(kube.to_owned(), &namespace, &modified.spec.depends_on).await
+        {
+            let current = modified.to_owned();
+            modified.set_condition(Condition::new(
+                condition::READY,
+                condition::Status::False,
+                condition::REASON_WAITING_FOR_DEPENDENCIES,
+                &err.to_string(),
+            ));
+
+            let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+            resource::patch_status(kube.to_owned(), modified, patch).await?;
+
+            return Err(ReconcilerError::DependsOn(err));
+        }
+
+        // ---------------------------------------------------------------------
+        // Step 2: resolve credentials of the cellar addon
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            secret = &modified.spec.credentials_ref.name,
+            "Retrieve credentials of cellar from secret",
+        );
+
+        let client = match Self::client(ctx.as_ref(), &modified).await {
+            Ok(client) => client,
+            Err(err) => {
+                let current = modified.to_owned();
+                modified.set_condition(Condition::new(
+                    condition::READY,
+                    condition::Status::False,
+                    condition::REASON_WAITING_FOR_DEPENDENCIES,
+                    &err.to_string(),
+                ));
+
+                let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+                resource::patch_status(kube.to_owned(), modified, patch).await?;
+
+                return Err(err);
+            }
+        };
+
+        // ---------------------------------------------------------------------
+        // Step 3: upsert bucket
+
+        let bucket = modified.bucket();
+        let host = client.credentials().host.to_owned();
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            bucket = &bucket,
+            "Upsert bucket for custom resource",
+        );
+
+        client.create(&bucket).await?;
+
+        // cors rules and the static website configuration are applied on each
+        // reconciliation, so changes made outside of kubernetes are reverted
+        client.cors(&bucket, &modified.spec.cors).await?;
+        client
+            .website(&bucket, modified.spec.website.as_ref())
+            .await?;
+
+        let website = modified
+            .spec
+            .website
+            .as_ref()
+            .map(|_| cellar::website_endpoint(&host, &bucket));
+
+        let current = modified.to_owned();
+        modified.set_bucket(Some(bucket.to_owned()), Some(host.to_owned()));
+        modified.set_website_endpoint(website.to_owned());
+        modified.set_condition(Condition::new(
+            condition::READY,
+            condition::Status::True,
+            "BucketProvisioned",
+            &format!("Bucket '{}' is provisioned on cellar '{}'", bucket, host),
+        ));
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update information and status of custom resource",
+        );
+
+        let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+        let modified = resource::patch(kube.to_owned(), &modified, patch.to_owned())
+            .and_then(|modified| resource::patch_status(kube.to_owned(), modified, patch))
+            .await?;
+
+        let action = &Action::UpsertBucket;
+        let message = &format!("Create bucket '{}' on cellar '{}'", bucket, host);
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        if !modified.spec.cors.is_empty() {
+            let action = &Action::UpsertCors;
+            let message = &format!(
+                "Apply {} cors rule(s) on bucket '{}'",
+                modified.spec.cors.len(),
+                bucket
+            );
+            recorder::normal(kube.to_owned(), &modified, action, message).await?;
+        }
+
+        if let Some(website) = &website {
+            let action = &Action::UpsertWebsite;
+            let message = &format!(
+                "Serve bucket '{}' as a static website on '{}'",
+                bucket, website
+            );
+            recorder::normal(kube.to_owned(), &modified, action, message).await?;
+        }
+
+        // ---------------------------------------------------------------------
+        // Step 4: create the secret, cellar only provides credentials for the
+        // whole addon, so they are exposed along the name of the bucket
+
+        let credentials = client.credentials();
+        let mut secrets = BTreeMap::from([
+            (CELLAR_HOST.to_string(), credentials.host.to_owned()),
+            (CELLAR_KEY_ID.to_string(), credentials.key_id.to_owned()),
+            (
+                CELLAR_KEY_SECRET.to_string(),
+                (*credentials.key_secret).to_owned(),
+            ),
+            (CELLAR_BUCKET.to_string(), bucket.to_owned()),
+        ]);
+
+        let (s, excluded) = secret::new(
+            &modified,
+            &mut secrets,
+            modified.spec.exposure.as_ref(),
+            &config.operator.secret.exclude_keys,
+            modified.spec.secret_template.as_ref(),
+        );
+
+        let mut updated = modified.to_owned();
+        updated.set_excluded_keys(excluded);
+        updated.set_binding(Some(binding::reference(&updated)));
+
+        let patch = resource::diff(&modified, &updated).map_err(ReconcilerError::Diff)?;
+        let modified = resource::patch_status(kube.to_owned(), updated, patch).await?;
+
+        let b = binding::new(
+            &modified,
+            &secrets,
+            &BINDING,
+            modified.spec.service_binding.as_ref(),
+        );
+        let s = export::annotate(&modified, s, &modified.spec.export_to);
+        let (s_ns, s_name) = resource::namespaced_name(&s);
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Upsert kubernetes secret resource for custom resource",
+        );
+
+        info!(
+            namespace = &s_ns,
+            name = &s_name,
+            "Upsert kubernetes secret",
+        );
+
+        let current: Option<Secret> = resource::get(kube.to_owned(), &s_ns, &s_name).await?;
+        let secret = resource::upsert(kube.to_owned(), &s, false).await?;
+        if let Some(message) = current
+            .as_ref()
+            .and_then(|current| drift::secret(current, &s))
+        {
+            drift::repaired(kube.to_owned(), &modified, &message).await?;
+        }
+
+        let action = &Action::UpsertSecret;
+        let message = &format!("Create kubernetes secret '{}'", secret.name_any());
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        let exported = export::sync(kube.to_owned(), &modified, &secret, current.as_ref()).await?;
+        if !exported.is_empty() {
+            let message = &format!(
+                "Export kubernetes secret '{}' to namespaces '{}'",
+                secret.name_any(),
+                exported.join("', '")
+            );
+            recorder::normal(kube.to_owned(), &modified, action, message).await?;
+        }
+
+        info!(
+            namespace = &s_ns,
+            name = binding::name(&modified),
+            "Upsert kubernetes service binding secret",
+        );
+
+        let b = resource::upsert(kube.to_owned(), &b, false).await?;
+        let message = &format!(
+            "Create kubernetes service binding secret '{}'",
+            b.name_any()
+        );
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        Ok(())
+    }
+
+    async fn delete(ctx: Arc<Context>, origin: Arc<CellarBucket>) -> Result<(), ReconcilerError> {
+        let Context { kube, config, .. } = ctx.as_ref();
+
+        let mut modified = (*origin).to_owned();
+        let kind = CellarBucket::kind(&()).to_string();
+        let (namespace, name) = resource::namespaced_name(&*origin);
+
+        // ---------------------------------------------------------------------
+        // Step 1: delete the bucket, unless its deletion policy keeps it
+
+        let policy = modified.spec.deletion_policy;
+        if policy.orphans_secrets() || !config.operator.secret.cascade_deletion {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                "Release kubernetes secrets of custom resource",
+            );
+
+            secret::release(kube.to_owned(), &modified).await?;
+        } else {
+            // copies exported to other namespaces are not owned by the custom
+            // resource, so they are not garbage collected along it
+            export::prune(kube.to_owned(), &modified).await?;
+        }
+
+        let bucket = modified.get_bucket();
+        if let (Some(bucket), true) = (&bucket, policy.deletes_addon()) {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                bucket = &bucket,
+                "Delete bucket for custom resource",
+            );
+
+            Self::client(ctx.as_ref(), &modified)
+                .await?
+                .delete(bucket)
+                .await?;
+        }
+
+        modified.set_bucket(None, None);
+        modified.set_website_endpoint(None);
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update information and status of custom resource",
+        );
+
+        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
+        let modified = resource::patch(kube.to_owned(), &modified, patch.to_owned())
+            .and_then(|modified| resource::patch_status(kube.to_owned(), modified, patch))
+            .await?;
+
+        let (action, message) = if policy.deletes_addon() {
+            (&Action::DeleteBucket, "Delete bucket on cellar".to_string())
+        } else {
+            (
+                &Action::KeepBucket,
+                format!(
+                    "Keep bucket '{}' on cellar, as requested by the '{}' deletion policy",
+                    bucket.unwrap_or_default(),
+                    policy
+                ),
+            )
+        };
+
+        recorder::normal(kube.to_owned(), &modified, action, &message).await?;
+
+        // ---------------------------------------------------------------------
+        // Step 2: remove the finalizer
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Remove finalizer on custom resource",
+        );
+
+        let patch = finalizer::remove_patch(&modified, BUCKET_FINALIZER);
+
+        let action = &Action::DeleteFinalizer;
+        let message = "Delete finalizer from custom resource";
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update information of custom resource",
+        );
+
+        resource::patch(kube.to_owned(), &modified, patch).await?;
+
+        Ok(())
+    }
+}
@@ -20,21 +20,33 @@ use clevercloud_sdk::{
     },
 };
 use futures::TryFutureExt;
-use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::api::core::v1::{ConfigMap, Secret};
 use kube::{
-    runtime::{controller, watcher, Controller},
-    Api, CustomResource, Resource, ResourceExt,
+    runtime::{controller, reflector::ObjectRef, watcher, Controller},
+    Client, CustomResource, Resource, ResourceExt,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info};
 
 use crate::svc::{
-    clevercloud::{self, ext::AddonExt},
+    audit,
+    clevercloud::{
+        self,
+        client::{observe, ScopedClient},
+        ext::AddonExt,
+    },
+    crd::{
+        self, Billing, Binding, CredentialsSecretReference, DeletionPolicy, Dependency, Exposure,
+        SecretTemplate, ServiceBinding,
+    },
     k8s::{
-        self, finalizer, recorder, resource,
-        secret::{self, OVERRIDE_CONFIGURATION_NAME},
-        Context, ControllerBuilder,
+        self, billing,
+        binding::{self, Mapping},
+        condition::{self, Condition, Conditions},
+        config_map, dependency, drift, export, finalizer, naming,
+        rbac::{self, Permission},
+        recorder, resource, secret, tags, Context, ControllerBuilder,
     },
 };
 
@@ -43,6 +55,65 @@ use crate::svc::{
 
 pub const ADDON_FINALIZER: &str = "api.clever-cloud.com/config-provider";
 
+/// well-known entries of the service binding specification
+pub const BINDING: Mapping = Mapping {
+    kind: "config",
+    keys: &[],
+};
+
+/// kubernetes resources touched by the reconciler beside its custom resources
+pub const PERMISSIONS: &[Permission] = &[rbac::SECRETS, rbac::CONFIG_MAPS];
+
+// -----------------------------------------------------------------------------
+// LocalReference structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct LocalReference {
+    /// name of the object in the namespace of the custom resource
+    #[serde(rename = "name")]
+    pub name: String,
+    /// do not fail if the object does not exist
+    #[serde(rename = "optional", default)]
+    pub optional: bool,
+}
+
+// -----------------------------------------------------------------------------
+// VariablesFrom structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct VariablesFrom {
+    /// config map whose keys and values are used as variables
+    #[serde(
+        rename = "configMapRef",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub config_map_ref: Option<LocalReference>,
+    /// secret whose keys and values are used as variables
+    #[serde(rename = "secretRef", default, skip_serializing_if = "Option::is_none")]
+    pub secret_ref: Option<LocalReference>,
+    /// prefix prepended to the keys of the source
+    #[serde(rename = "prefix", default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+}
+
+impl VariablesFrom {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns if the source references the object of the given kind and name
+    pub fn references(&self, kind: &str, name: &str) -> bool {
+        let reference = match kind {
+            "ConfigMap" => &self.config_map_ref,
+            "Secret" => &self.secret_ref,
+            _ => return false,
+        };
+
+        reference
+            .as_ref()
+            .map(|r| r.name == name)
+            .unwrap_or_default()
+    }
+}
+
 // -----------------------------------------------------------------------------
 // MySqlSpec structure
 
@@ -62,11 +133,89 @@ pub const ADDON_FINALIZER: &str = "api.clever-cloud.com/config-provider";
 #[kube(
     printcolumn = r#"{"name":"addon", "type":"string", "description":"Addon", "jsonPath":".status.addon"}"#
 )]
+#[kube(
+    printcolumn = r#"{"name":"ready", "type":"string", "description":"Ready", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"phase", "type":"string", "description":"Reason of the ready condition", "jsonPath":".status.conditions[?(@.type==\"Ready\")].reason"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"age", "type":"date", "description":"Age", "jsonPath":".metadata.creationTimestamp"}"#
+)]
 pub struct Spec {
     #[serde(rename = "organisation")]
     pub organisation: String,
-    #[serde(rename = "variables")]
+    /// secret holding the configuration of the clever cloud client used to
+    /// reconcile the custom resource, the `clever-operator` secret of the
+    /// namespace is used if not set
+    #[serde(
+        rename = "credentialsSecretRef",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub credentials_secret_ref: Option<CredentialsSecretReference>,
+    /// variables of the config provider, they take precedence over the ones
+    /// read from `variablesFrom`
+    #[serde(rename = "variables", default)]
     pub variables: BTreeMap<String, String>,
+    /// config maps and secrets whose keys and values are used as variables,
+    /// later sources take precedence over earlier ones
+    #[serde(
+        rename = "variablesFrom",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub variables_from: Vec<VariablesFrom>,
+    #[serde(rename = "exposure", default, skip_serializing_if = "Option::is_none")]
+    pub exposure: Option<Exposure>,
+    /// write the variables matching `nonSensitiveKeys` in a `<name>-config`
+    /// config map instead of the generated secret
+    #[serde(rename = "exposeAsConfigMap", default)]
+    pub expose_as_config_map: bool,
+    /// glob patterns of the keys of variables which are not sensitive, they
+    /// are only used if `exposeAsConfigMap` is set
+    #[serde(
+        rename = "nonSensitiveKeys",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub non_sensitive_keys: Vec<String>,
+    #[serde(
+        rename = "secretTemplate",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub secret_template: Option<SecretTemplate>,
+    /// namespaces in which the generated secret is replicated, so workloads
+    /// of other namespaces could consume it
+    #[serde(rename = "exportTo", default, skip_serializing_if = "Vec::is_empty")]
+    pub export_to: Vec<String>,
+    /// options of the secret following the service binding specification
+    #[serde(
+        rename = "serviceBinding",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub service_binding: Option<ServiceBinding>,
+    /// custom resources which must be ready before provisioning this one
+    #[serde(rename = "dependsOn", default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<Dependency>,
+    /// whether the addon and the generated secrets are deleted along the
+    /// custom resource, one of `Delete` (default), `Orphan` or `Retain`
+    #[serde(rename = "deletionPolicy", default)]
+    pub deletion_policy: DeletionPolicy,
+    /// name of the addon in the Clever Cloud console, the naming template of
+    /// the operator is used if not set
+    #[serde(
+        rename = "displayName",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub display_name: Option<String>,
+    /// suspend the reconciliation of the custom resource, e.g. during a
+    /// manual maintenance on Clever Cloud, its deletion is still processed
+    #[serde(rename = "suspend", default)]
+    pub suspend: bool,
 }
 
 // -----------------------------------------------------------------------------
@@ -76,6 +225,28 @@ pub struct Spec {
 pub struct Status {
     #[serde(rename = "addon")]
     pub addon: Option<String>,
+    #[serde(rename = "conditions", default)]
+    pub conditions: Vec<Condition>,
+    /// generation of the specification processed by the last successful
+    /// reconciliation
+    #[serde(
+        rename = "observedGeneration",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub observed_generation: Option<i64>,
+    #[serde(
+        rename = "excludedKeys",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub excluded_keys: Vec<String>,
+    /// reference to the secret following the service binding specification
+    #[serde(rename = "binding", default, skip_serializing_if = "Option::is_none")]
+    pub binding: Option<Binding>,
+    /// price of the plan and consumption of the addon, if billing is enabled
+    #[serde(rename = "billing", default, skip_serializing_if = "Option::is_none")]
+    pub billing: Option<Billing>,
 }
 
 // -----------------------------------------------------------------------------
@@ -86,7 +257,7 @@ impl Into<CreateOpts> for ConfigProvider {
     #[cfg_attr(feature = "trace", tracing::instrument)]
     fn into(self) -> CreateOpts {
         CreateOpts {
-            name: AddonExt::name(&self),
+            name: AddonExt::addon_name(&self),
             region: "par".to_owned(), // config provider is only available in the "par" datacenter
             provider_id: AddonProviderId::ConfigProvider.to_string(),
             plan: plan::CONFIG_PROVIDER.to_owned(),
@@ -99,12 +270,16 @@ impl AddonExt for ConfigProvider {
     type Error = ReconcilerError;
 
     #[cfg_attr(feature = "trace", tracing::instrument)]
-    fn id(&self) -> Option<String> {
-        if let Some(status) = &self.status {
-            return status.addon.to_owned();
-        }
+    fn scope() -> String {
+        Self::kind(&()).to_string()
+    }
 
-        None
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn id(&self) -> Option<String> {
+        self.status
+            .as_ref()
+            .and_then(|status| status.addon.to_owned())
+            .or_else(|| crd::adopted(self))
     }
 
     #[cfg_attr(feature = "trace", tracing::instrument)]
@@ -112,6 +287,16 @@ impl AddonExt for ConfigProvider {
         self.spec.organisation.to_owned()
     }
 
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn credentials_secret_ref(&self) -> Option<&CredentialsSecretReference> {
+        self.spec.credentials_secret_ref.as_ref()
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn display_name(&self) -> Option<String> {
+        self.spec.display_name.to_owned()
+    }
+
     #[cfg_attr(feature = "trace", tracing::instrument)]
     fn name(&self) -> String {
         let delimiter = Self::delimiter();
@@ -139,6 +324,34 @@ impl ConfigProvider {
     pub fn get_addon_id(&self) -> Option<String> {
         self.status.to_owned().unwrap_or_default().addon
     }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_excluded_keys(&mut self, keys: Vec<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.excluded_keys = keys;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_binding(&mut self, binding: Option<Binding>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.binding = binding;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_billing(&mut self, billing: Option<Billing>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.billing = billing;
+    }
+}
+
+impl Conditions for ConfigProvider {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn conditions_mut(&mut self) -> &mut Vec<Condition> {
+        &mut self.status.get_or_insert_with(Status::default).conditions
+    }
 }
 
 // -----------------------------------------------------------------------------
@@ -149,9 +362,12 @@ pub enum Action {
     UpsertFinalizer,
     UpsertAddon,
     UpsertSecret,
+    UpsertConfigMap,
+    DeleteConfigMap,
     OverridesInstancePlan,
     DeleteFinalizer,
     DeleteAddon,
+    KeepAddon,
 }
 
 impl Display for Action {
@@ -160,9 +376,12 @@ impl Display for Action {
             Self::UpsertFinalizer => write!(f, "UpsertFinalizer"),
             Self::UpsertAddon => write!(f, "UpsertAddon"),
             Self::UpsertSecret => write!(f, "UpsertSecret"),
+            Self::UpsertConfigMap => write!(f, "UpsertConfigMap"),
+            Self::DeleteConfigMap => write!(f, "DeleteConfigMap"),
             Self::OverridesInstancePlan => write!(f, "OverridesInstancePlan"),
             Self::DeleteFinalizer => write!(f, "DeleteFinalizer"),
             Self::DeleteAddon => write!(f, "DeleteAddon"),
+            Self::KeepAddon => write!(f, "KeepAddon"),
         }
     }
 }
@@ -178,10 +397,22 @@ pub enum ReconcilerError {
     CleverClient(clevercloud::Error),
     #[error("failed to create clevercloud client, {0}")]
     CreateCleverClient(clevercloud::client::Error),
+    #[error("failed to retrieve credentials secret, {0}")]
+    Credentials(secret::Error),
     #[error("failed to execute request on kubernetes api, {0}")]
     KubeClient(kube::Error),
     #[error("failed to compute diff between the original and modified object, {0}")]
     Diff(serde_json::Error),
+    #[error("failed to authorize call on clever-cloud api, {0}")]
+    Scope(clevercloud::client::ScopeError),
+    #[error("failed to find config map '{0}/{1}' referenced by 'variablesFrom'")]
+    MissingConfigMap(String, String),
+    #[error("failed to read secret referenced by 'variablesFrom', {0}")]
+    Secret(secret::Error),
+    #[error("failed to reference source of 'variablesFrom', one of 'configMapRef' or 'secretRef' must be set")]
+    InvalidSource,
+    #[error("failed to wait for dependencies, {0}")]
+    DependsOn(dependency::Error),
 }
 
 impl From<kube::Error> for ReconcilerError {
@@ -226,6 +457,13 @@ impl From<controller::Error<Self, watcher::Error>> for ReconcilerError {
     }
 }
 
+impl From<clevercloud::client::ScopeError> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: clevercloud::client::ScopeError) -> Self {
+        Self::Scope(err)
+    }
+}
+
 impl From<clevercloud::client::Error> for ReconcilerError {
     fn from(err: clevercloud::client::Error) -> Self {
         Self::CreateCleverClient(err)
@@ -239,12 +477,112 @@ impl From<clevercloud::client::Error> for ReconcilerError {
 pub struct Reconciler {}
 
 impl ControllerBuilder<ConfigProvider> for Reconciler {
-    fn build(&self, state: Arc<Context>) -> Controller<ConfigProvider> {
+    fn build(&self, state: Arc<Context>, namespace: Option<&str>) -> Controller<ConfigProvider> {
         let client = state.kube.to_owned();
-        let secret = Api::<Secret>::all(client.to_owned());
+        let secret = k8s::api::<Secret>(client.to_owned(), namespace);
+        let config_map = k8s::api::<ConfigMap>(client.to_owned(), namespace);
+
+        let controller = Controller::new(
+            k8s::api(client, namespace),
+            k8s::watcher_config(&state.config),
+        )
+        .owns(secret.to_owned(), watcher::Config::default())
+        .owns(config_map.to_owned(), watcher::Config::default());
+
+        // reconcile config providers reading their variables from the modified
+        // config map or secret, so changes are pushed to clever cloud
+        let store = controller.store();
+        let (config_maps, secrets) = (store.to_owned(), store);
+
+        controller
+            .watches(config_map, watcher::Config::default(), move |obj| {
+                Self::referencing(&config_maps.state(), "ConfigMap", &obj)
+            })
+            .watches(secret, watcher::Config::default(), move |obj| {
+                Self::referencing(&secrets.state(), "Secret", &obj)
+            })
+            // reconcile the custom resource once a copy of its secret exported
+            // to another namespace is modified or deleted
+            .watches(
+                k8s::api::<Secret>(state.kube.to_owned(), namespace),
+                watcher::Config::default().labels(export::SOURCE_LABEL),
+                export::source::<ConfigProvider>,
+            )
+    }
+}
+
+impl Reconciler {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the config providers whose variables are read from the given
+    /// object
+    fn referencing<K>(
+        providers: &[Arc<ConfigProvider>],
+        kind: &str,
+        obj: &K,
+    ) -> Vec<ObjectRef<ConfigProvider>>
+    where
+        K: ResourceExt + std::fmt::Debug,
+    {
+        let (namespace, name) = (obj.namespace(), obj.name_any());
+
+        providers
+            .iter()
+            .filter(|provider| provider.namespace() == namespace)
+            .filter(|provider| {
+                provider
+                    .spec
+                    .variables_from
+                    .iter()
+                    .any(|source| source.references(kind, &name))
+            })
+            .map(|provider| ObjectRef::from_obj(provider.as_ref()))
+            .collect()
+    }
 
-        Controller::new(Api::all(client), watcher::Config::default())
-            .owns(secret, watcher::Config::default())
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(kube)))]
+    /// returns the variables read from the given sources, merged with the ones
+    /// of the specification which take precedence
+    async fn variables(
+        kube: Client,
+        namespace: &str,
+        obj: &ConfigProvider,
+    ) -> Result<BTreeMap<String, String>, ReconcilerError> {
+        let mut variables = BTreeMap::new();
+
+        for source in &obj.spec.variables_from {
+            let prefix = source.prefix.to_owned().unwrap_or_default();
+            let data = match (&source.config_map_ref, &source.secret_ref) {
+                (Some(reference), None) => {
+                    match resource::get::<ConfigMap>(kube.to_owned(), namespace, &reference.name)
+                        .await?
+                    {
+                        Some(config_map) => config_map.data.unwrap_or_default(),
+                        None if reference.optional => BTreeMap::new(),
+                        None => {
+                            return Err(ReconcilerError::MissingConfigMap(
+                                namespace.to_string(),
+                                reference.name.to_owned(),
+                            ))
+                        }
+                    }
+                }
+                (None, Some(reference)) => {
+                    match secret::data(kube.to_owned(), namespace, &reference.name).await {
+                        Ok(data) => data.0,
+                        Err(secret::Error::NotFound(_, _)) if reference.optional => BTreeMap::new(),
+                        Err(err) => return Err(ReconcilerError::Secret(err)),
+                    }
+                }
+                _ => return Err(ReconcilerError::InvalidSource),
+            };
+
+            for (key, value) in data {
+                variables.insert(format!("{}{}", prefix, key), value);
+            }
+        }
+
+        variables.extend(obj.spec.variables.to_owned());
+        Ok(variables)
     }
 }
 
@@ -252,11 +590,24 @@ impl ControllerBuilder<ConfigProvider> for Reconciler {
 impl k8s::Reconciler<ConfigProvider> for Reconciler {
     type Error = ReconcilerError;
 
+    fn permanent(err: &ReconcilerError) -> Option<&'static str> {
+        match err {
+            ReconcilerError::CleverClient(err) => err.permanent(),
+            ReconcilerError::DependsOn(err) => err.permanent(),
+            ReconcilerError::InvalidSource => Some("InvalidSource"),
+            _ => None,
+        }
+    }
+
     async fn upsert(ctx: Arc<Context>, origin: Arc<ConfigProvider>) -> Result<(), ReconcilerError> {
         let Context {
             kube,
             apis,
-            config: _,
+            backend,
+            config,
+            secrets,
+            clients,
+            ..
         } = ctx.as_ref();
 
         let kind = ConfigProvider::kind(&()).to_string();
@@ -264,30 +615,36 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
 
         // ---------------------------------------------------------------------
         // Step 0: verify if there is a clever cloud client override
+        let reference = origin.spec.credentials_secret_ref.as_ref();
+        let (secret_namespace, secret_name) = secret::credentials_reference(&namespace, reference);
+
         debug!(
-            namespace = namespace,
-            secret = OVERRIDE_CONFIGURATION_NAME,
+            namespace = &secret_namespace,
+            secret = &secret_name,
             "Try to retrieve the optional secret on namespace",
         );
 
-        let secret: Option<Secret> =
-            resource::get(kube.to_owned(), &namespace, OVERRIDE_CONFIGURATION_NAME).await?;
+        let secret: Option<Secret> = secret::credentials(secrets, &namespace, reference)
+            .await
+            .map_err(ReconcilerError::Credentials)?;
 
         let apis = match secret {
             Some(secret) => {
                 info!(
-                    namespace = namespace,
-                    secret = OVERRIDE_CONFIGURATION_NAME,
+                    namespace = &secret_namespace,
+                    secret = &secret_name,
                     "Use custom Clever Cloud client to connect the api using secret",
                 );
 
-                clevercloud::client::try_from(secret).await?
+                ScopedClient::scoped(clients.get(secret).await?, &kind)
             }
             None => {
                 info!("Use default Clever Cloud client to connect the api");
-                apis.to_owned()
+                ScopedClient::unscoped(apis.get())
             }
-        };
+        }
+        .with_rate_limit(&origin.spec.organisation, &config.api.rate_limit)
+        .with_backend(backend.to_owned());
 
         // ---------------------------------------------------------------------
         // Step 1: set finalizer
@@ -299,7 +656,7 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
             "Set finalizer on custom resource",
         );
 
-        let modified = finalizer::add((*origin).to_owned(), ADDON_FINALIZER);
+        let patch = finalizer::add_patch(&*origin, ADDON_FINALIZER);
 
         debug!(
             kind = &kind,
@@ -308,13 +665,31 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
             "Update information of custom resource",
         );
 
-        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
-        let mut modified = resource::patch(kube.to_owned(), &modified, patch).await?;
+        let mut modified = resource::patch(kube.to_owned(), &*origin, patch).await?;
 
         let action = &Action::UpsertFinalizer;
         let message = &format!("Create finalizer '{}'", ADDON_FINALIZER);
         recorder::normal(kube.to_owned(), &modified, action, message).await?;
 
+        // hold off provisioning until the custom resources this one depends on
+        // are ready
+        if let Err(err) =
+            dependency::all_ready(kube.to_owned(), &namespace, &modified.spec.depends_on).await
+        {
+            let current = modified.to_owned();
+            modified.set_condition(Condition::new(
+                condition::READY,
+                condition::Status::False,
+                condition::REASON_WAITING_FOR_DEPENDENCIES,
+                &err.to_string(),
+            ));
+
+            let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+            resource::patch_status(kube.to_owned(), modified, patch).await?;
+
+            return Err(ReconcilerError::DependsOn(err));
+        }
+
         // ---------------------------------------------------------------------
         // Step 2: upsert addon
         info!(
@@ -326,7 +701,21 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
 
         let addon = modified.upsert(&apis).await?;
 
+        let current = modified.to_owned();
         modified.set_addon_id(Some(addon.id.to_owned()));
+        naming::sync(&apis, config, &modified, &addon).await;
+        tags::sync(&apis, config, &modified, &addon).await;
+
+        let current_billing = modified.status.as_ref().and_then(|s| s.billing.as_ref());
+        let billing = billing::refresh(&apis, config, &modified, &addon, current_billing).await;
+        modified.set_billing(billing);
+
+        modified.set_condition(Condition::new(
+            condition::READY,
+            condition::Status::True,
+            "AddonProvisioned",
+            &format!("Addon '{}' is provisioned on clever-cloud", addon.id),
+        ));
 
         debug!(
             kind = &kind,
@@ -335,7 +724,7 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
             "Update information and status of custom resource",
         );
 
-        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
+        let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
         let modified = resource::patch(kube.to_owned(), &modified, patch.to_owned())
             .and_then(|modified| resource::patch_status(kube.to_owned(), modified, patch))
             .await?;
@@ -348,7 +737,27 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
         recorder::normal(kube.to_owned(), &modified, action, message).await?;
 
         // ---------------------------------------------------------------------
-        // Step 3: upsert environment variables
+        // Step 3: upsert environment variables, merged with the ones read from
+        // config maps and secrets
+        let expected = match Self::variables(kube.to_owned(), &namespace, &modified).await {
+            Ok(variables) => variables,
+            Err(err) => {
+                let mut modified = modified.to_owned();
+                let current = modified.to_owned();
+                modified.set_condition(Condition::new(
+                    condition::READY,
+                    condition::Status::False,
+                    condition::REASON_WAITING_FOR_DEPENDENCIES,
+                    &err.to_string(),
+                ));
+
+                let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+                resource::patch_status(kube.to_owned(), modified, patch).await?;
+
+                return Err(err);
+            }
+        };
+
         info!(
             kind = &kind,
             namespace = &namespace,
@@ -358,15 +767,22 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
         );
 
         // We could not used the "addon_xxxx" identifier, we have to used the "config_xxxx" identifier
-        let variables = environment::get(&apis, &addon.real_id).await?.iter().fold(
-            BTreeMap::new(),
-            |mut acc, var| {
-                acc.insert(var.name.to_owned(), var.value.to_owned());
-                acc
-            },
-        );
-
-        if modified.spec.variables != variables {
+        let variables = observe(
+            "environment",
+            "get",
+            environment::get(
+                apis.authorize(&kind, "environment::get").await?,
+                &addon.real_id,
+            ),
+        )
+        .await?
+        .iter()
+        .fold(BTreeMap::new(), |mut acc, var| {
+            acc.insert(var.name.to_owned(), var.value.to_owned());
+            acc
+        });
+
+        if expected != variables {
             debug!(
                 kind = &kind,
                 namespace = &namespace,
@@ -375,21 +791,100 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
                 "Update config-provider's environment variables with custom resource ones for addon"
             );
 
-            let variables = modified
-                .spec
-                .variables
-                .iter()
-                .fold(vec![], |mut acc, (k, v)| {
-                    acc.push(Variable::from((k.to_owned(), v.to_owned())));
-                    acc
-                });
+            let variables = expected.iter().fold(vec![], |mut acc, (k, v)| {
+                acc.push(Variable::from((k.to_owned(), v.to_owned())));
+                acc
+            });
+
+            let result = observe(
+                "environment",
+                "put",
+                environment::put(
+                    apis.authorize(&kind, "environment::put").await?,
+                    &addon.real_id,
+                    &variables,
+                ),
+            )
+            .await;
+
+            audit::record(
+                "environment::put",
+                &modified.actor(),
+                Some(&modified.organisation()),
+                &addon.real_id,
+                &result,
+            );
 
-            environment::put(&apis, &addon.real_id, &variables).await?;
+            result?;
         }
 
         // ---------------------------------------------------------------------
         // Step 4: create the secret
-        let s = secret::new(&modified, modified.spec.variables.to_owned());
+        let (mut secrets, mut plain) = if modified.spec.expose_as_config_map {
+            config_map::split(expected, &modified.spec.non_sensitive_keys)
+        } else {
+            (expected, BTreeMap::new())
+        };
+
+        let (s, mut excluded) = secret::new(
+            &modified,
+            &mut secrets,
+            modified.spec.exposure.as_ref(),
+            &config.operator.secret.exclude_keys,
+            modified.spec.secret_template.as_ref(),
+        );
+
+        // excluded keys are not written in the config map either
+        let patterns = secret::patterns(
+            modified.spec.exposure.as_ref(),
+            &config.operator.secret.exclude_keys,
+        );
+        excluded.extend(secret::exclude(&mut plain, &patterns));
+        excluded.sort();
+
+        let mut updated = modified.to_owned();
+        updated.set_excluded_keys(excluded);
+        updated.set_binding(Some(binding::reference(&updated)));
+
+        let patch = resource::diff(&modified, &updated).map_err(ReconcilerError::Diff)?;
+        let modified = resource::patch_status(kube.to_owned(), updated, patch).await?;
+
+        // the binding secret keeps all variables, as bound workloads expect
+        // to find the whole configuration in it
+        let mut variables = secrets;
+        variables.extend(plain.to_owned());
+
+        let b = binding::new(
+            &modified,
+            &variables,
+            &BINDING,
+            modified.spec.service_binding.as_ref(),
+        );
+
+        if modified.spec.expose_as_config_map {
+            let cm = config_map::new(&modified, plain);
+            let (cm_ns, cm_name) = resource::namespaced_name(&cm);
+
+            info!(
+                namespace = &cm_ns,
+                name = &cm_name,
+                "Upsert kubernetes config map",
+            );
+
+            let cm = resource::upsert(kube.to_owned(), &cm, false).await?;
+            let action = &Action::UpsertConfigMap;
+            let message = &format!("Create kubernetes config map '{}'", cm.name_any());
+            recorder::normal(kube.to_owned(), &modified, action, message).await?;
+        } else if config_map::delete(kube.to_owned(), &modified).await? {
+            let action = &Action::DeleteConfigMap;
+            let message = &format!(
+                "Delete kubernetes config map '{}'",
+                config_map::name(&modified)
+            );
+            recorder::normal(kube.to_owned(), &modified, action, message).await?;
+        }
+
+        let s = export::annotate(&modified, s, &modified.spec.export_to);
         let (s_ns, s_name) = resource::namespaced_name(&s);
 
         info!(
@@ -405,19 +900,54 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
             "Upsert kubernetes secret",
         );
 
+        let current: Option<Secret> = resource::get(kube.to_owned(), &s_ns, &s_name).await?;
         let secret = resource::upsert(kube.to_owned(), &s, false).await?;
+        if let Some(message) = current
+            .as_ref()
+            .and_then(|current| drift::secret(current, &s))
+        {
+            drift::repaired(kube.to_owned(), &modified, &message).await?;
+        }
+
         let action = &Action::UpsertSecret;
         let message = &format!("Create kubernetes secret '{}'", secret.name_any());
         recorder::normal(kube.to_owned(), &modified, action, message).await?;
 
+        let exported = export::sync(kube.to_owned(), &modified, &secret, current.as_ref()).await?;
+        if !exported.is_empty() {
+            let message = &format!(
+                "Export kubernetes secret '{}' to namespaces '{}'",
+                secret.name_any(),
+                exported.join("', '")
+            );
+            recorder::normal(kube.to_owned(), &modified, action, message).await?;
+        }
+
+        info!(
+            namespace = &s_ns,
+            name = binding::name(&modified),
+            "Upsert kubernetes service binding secret",
+        );
+
+        let b = resource::upsert(kube.to_owned(), &b, false).await?;
+        let message = &format!(
+            "Create kubernetes service binding secret '{}'",
+            b.name_any()
+        );
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
         Ok(())
     }
 
     async fn delete(ctx: Arc<Context>, origin: Arc<ConfigProvider>) -> Result<(), ReconcilerError> {
         let Context {
             apis,
+            backend,
             kube,
-            config: _,
+            config,
+            secrets,
+            clients,
+            ..
         } = ctx.as_ref();
 
         let mut modified = (*origin).to_owned();
@@ -426,42 +956,90 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
 
         // ---------------------------------------------------------------------
         // Step 0: verify if there is a clever cloud client override
+        let reference = origin.spec.credentials_secret_ref.as_ref();
+        let (secret_namespace, secret_name) = secret::credentials_reference(&namespace, reference);
+
         debug!(
-            namespace = namespace,
-            secret = OVERRIDE_CONFIGURATION_NAME,
+            namespace = &secret_namespace,
+            secret = &secret_name,
             "Try to retrieve the optional secret",
         );
 
-        let secret: Option<Secret> =
-            resource::get(kube.to_owned(), &namespace, OVERRIDE_CONFIGURATION_NAME).await?;
+        let secret: Option<Secret> = secret::credentials(secrets, &namespace, reference)
+            .await
+            .map_err(ReconcilerError::Credentials)?;
 
         let apis = match secret {
             Some(secret) => {
                 info!(
-                    namespace = namespace,
-                    secret = OVERRIDE_CONFIGURATION_NAME,
+                    namespace = &secret_namespace,
+                    secret = &secret_name,
                     "Use custom Clever Cloud client to connect the api using secret",
                 );
 
-                clevercloud::client::try_from(secret).await?
+                ScopedClient::scoped(clients.get(secret).await?, &kind)
             }
             None => {
                 info!("Use default Clever Cloud client to connect the api");
-                apis.to_owned()
+                ScopedClient::unscoped(apis.get())
             }
-        };
+        }
+        .with_rate_limit(&origin.spec.organisation, &config.api.rate_limit)
+        .with_backend(backend.to_owned());
 
         // ---------------------------------------------------------------------
-        // Step 1: delete the addon
+        // Step 1: delete the addon, unless its deletion policy keeps it
 
-        info!(
-            kind = &kind,
-            namespace = &namespace,
-            name = &name,
-            "Delete addon for custom resource",
-        );
+        let policy = modified.spec.deletion_policy;
+        if policy.orphans_secrets() || !config.operator.secret.cascade_deletion {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                "Release kubernetes secrets of custom resource",
+            );
+
+            secret::release(kube.to_owned(), &modified).await?;
+        } else {
+            // copies exported to other namespaces are not owned by the custom
+            // resource, so they are not garbage collected along it
+            export::prune(kube.to_owned(), &modified).await?;
+        }
+
+        let (action, message) = if policy.deletes_addon() {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                "Delete addon for custom resource",
+            );
+
+            modified.delete(&apis).await?;
+            (
+                &Action::DeleteAddon,
+                "Delete configuration provider on clever-cloud".to_string(),
+            )
+        } else {
+            let addon = modified.get_addon_id().unwrap_or_default();
+
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                addon = &addon,
+                policy = policy.to_string(),
+                "Keep addon for custom resource, as requested by its deletion policy",
+            );
+
+            (
+                &Action::KeepAddon,
+                format!(
+                    "Keep addon '{}' on clever-cloud, as requested by the '{}' deletion policy",
+                    addon, policy
+                ),
+            )
+        };
 
-        modified.delete(&apis).await?;
         modified.set_addon_id(None);
 
         debug!(
@@ -476,9 +1054,7 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
             .and_then(|modified| resource::patch_status(kube.to_owned(), modified, patch))
             .await?;
 
-        let action = &Action::DeleteAddon;
-        let message = "Delete configuration provider on clever-cloud";
-        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+        recorder::normal(kube.to_owned(), &modified, action, &message).await?;
 
         // ---------------------------------------------------------------------
         // Step 2: remove the finalizer
@@ -490,7 +1066,7 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
             "Remove finalizer on custom resource",
         );
 
-        let modified = finalizer::remove(modified, ADDON_FINALIZER);
+        let patch = finalizer::remove_patch(&modified, ADDON_FINALIZER);
 
         let action = &Action::DeleteFinalizer;
         let message = "Delete finalizer from custom resource";
@@ -503,9 +1079,25 @@ impl k8s::Reconciler<ConfigProvider> for Reconciler {
             "Update information of custom resource",
         );
 
-        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
-        resource::patch(kube.to_owned(), &modified, patch.to_owned()).await?;
+        resource::patch(kube.to_owned(), &modified, patch).await?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::ConfigProvider;
+
+    /// returns a config provider with the given name in the `default`
+    /// namespace, used as a fixture by tests of generic helpers
+    pub fn provider(name: &str, organisation: &str) -> ConfigProvider {
+        serde_json::from_value(serde_json::json!({
+            "apiVersion": "api.clever-cloud.com/v1",
+            "kind": "ConfigProvider",
+            "metadata": { "name": name, "namespace": "default", "uid": "0000" },
+            "spec": { "organisation": organisation }
+        }))
+        .expect("config provider to deserialize")
+    }
+}
@@ -0,0 +1,917 @@
+//! # Jenkins addon
+//!
+//! This module provide the jenkins custom resource and its definition. The url
+//! of the jenkins instance is written in the status of the custom resource and
+//! the credentials of its administrator in the generated secret
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use clevercloud_sdk::{
+    v2::{
+        self,
+        addon::{self, CreateOpts},
+    },
+    v4,
+};
+use futures::TryFutureExt;
+use k8s_openapi::api::core::v1::Secret;
+use kube::{
+    runtime::{controller, watcher, Controller},
+    CustomResource, Resource, ResourceExt,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+#[cfg(feature = "metrics")]
+use crate::svc::k8s::stats;
+use crate::svc::{
+    clevercloud::{
+        self,
+        client::ScopedClient,
+        ext::{self, AddonExt},
+        jenkins as provider, product,
+    },
+    crd::{
+        self, Billing, Binding, CredentialsSecretReference, DeletionPolicy, Dependency, Exposure,
+        Instance, OptionFrom, SecretTemplate, ServiceBinding,
+    },
+    k8s::{
+        self, billing,
+        binding::{self, Mapping},
+        condition::{self, Condition, Conditions},
+        dependency, drift, export, finalizer, naming,
+        rbac::{self, Permission},
+        recorder, resource, secret, tags, Context, ControllerBuilder,
+    },
+    redact::Redacted,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const ADDON_FINALIZER: &str = "api.clever-cloud.com/jenkins";
+
+/// well-known entries of the service binding specification
+pub const BINDING: Mapping = Mapping {
+    kind: "jenkins",
+    keys: &[
+        ("uri", provider::JENKINS_URL),
+        ("username", provider::JENKINS_ADMIN_USERNAME),
+        ("password", provider::JENKINS_ADMIN_PASSWORD),
+    ],
+};
+
+/// kubernetes resources touched by the reconciler beside its custom resources
+pub const PERMISSIONS: &[Permission] = &[rbac::SECRETS];
+
+// -----------------------------------------------------------------------------
+// Spec structure
+
+#[derive(CustomResource, JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[kube(group = "api.clever-cloud.com")]
+#[kube(version = "v1")]
+#[kube(kind = "Jenkins")]
+#[kube(singular = "jenkins")]
+#[kube(plural = "jenkins")]
+#[kube(shortname = "jk")]
+#[kube(status = "Status")]
+#[kube(namespaced)]
+#[kube(derive = "PartialEq")]
+#[kube(
+    printcolumn = r#"{"name":"organisation", "type":"string", "description":"Organisation", "jsonPath":".spec.organisation"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"addon", "type":"string", "description":"Addon", "jsonPath":".status.addon"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"region", "type":"string", "description":"Region", "jsonPath":".spec.instance.region"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"instance", "type":"string", "description":"Instance", "jsonPath":".spec.instance.plan"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"plan", "type":"string", "description":"Plan", "jsonPath":".status.plan"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"url", "type":"string", "description":"Url", "jsonPath":".status.url", "priority":1}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"ready", "type":"string", "description":"Ready", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"phase", "type":"string", "description":"Reason of the ready condition", "jsonPath":".status.conditions[?(@.type==\"Ready\")].reason"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"age", "type":"date", "description":"Age", "jsonPath":".metadata.creationTimestamp"}"#
+)]
+pub struct Spec {
+    #[serde(rename = "organisation")]
+    pub organisation: String,
+    /// secret holding the configuration of the clever cloud client used to
+    /// reconcile the custom resource, the `clever-operator` secret of the
+    /// namespace is used if not set
+    #[serde(
+        rename = "credentialsSecretRef",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub credentials_secret_ref: Option<CredentialsSecretReference>,
+    /// the plan is either an identifier, e.g. `plan_<uuid>`, or the slug or
+    /// the name of a plan of the jenkins addon provider
+    #[serde(rename = "instance")]
+    pub instance: Instance,
+    /// addon creation options, only `version` and `encryption` are supported
+    /// by the api
+    #[serde(
+        rename = "options",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub options: BTreeMap<String, String>,
+    #[serde(rename = "optionsFrom", default, skip_serializing_if = "Vec::is_empty")]
+    pub options_from: Vec<OptionFrom>,
+    #[serde(rename = "exposure", default, skip_serializing_if = "Option::is_none")]
+    pub exposure: Option<Exposure>,
+    #[serde(
+        rename = "secretTemplate",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub secret_template: Option<SecretTemplate>,
+    /// namespaces in which the generated secret is replicated, so workloads
+    /// of other namespaces could consume it
+    #[serde(rename = "exportTo", default, skip_serializing_if = "Vec::is_empty")]
+    pub export_to: Vec<String>,
+    /// options of the secret following the service binding specification
+    #[serde(
+        rename = "serviceBinding",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub service_binding: Option<ServiceBinding>,
+    /// custom resources which must be ready before provisioning this one
+    #[serde(rename = "dependsOn", default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<Dependency>,
+    /// whether the addon and the generated secrets are deleted along the
+    /// custom resource, one of `Delete` (default), `Orphan` or `Retain`
+    #[serde(rename = "deletionPolicy", default)]
+    pub deletion_policy: DeletionPolicy,
+    /// name of the addon in the Clever Cloud console, the naming template of
+    /// the operator is used if not set
+    #[serde(
+        rename = "displayName",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub display_name: Option<String>,
+    /// suspend the reconciliation of the custom resource, e.g. during a
+    /// manual maintenance on Clever Cloud, its deletion is still processed
+    #[serde(rename = "suspend", default)]
+    pub suspend: bool,
+}
+
+// -----------------------------------------------------------------------------
+// Status structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Status {
+    #[serde(rename = "addon")]
+    pub addon: Option<String>,
+    /// slug of the plan of the addon, e.g. `m`
+    #[serde(rename = "plan", default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<String>,
+    #[serde(rename = "conditions", default)]
+    pub conditions: Vec<Condition>,
+    /// generation of the specification processed by the last successful
+    /// reconciliation
+    #[serde(
+        rename = "observedGeneration",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub observed_generation: Option<i64>,
+    #[serde(
+        rename = "excludedKeys",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub excluded_keys: Vec<String>,
+    /// reference to the secret following the service binding specification
+    #[serde(rename = "binding", default, skip_serializing_if = "Option::is_none")]
+    pub binding: Option<Binding>,
+    /// price of the plan and consumption of the addon, if billing is enabled
+    #[serde(rename = "billing", default, skip_serializing_if = "Option::is_none")]
+    pub billing: Option<Billing>,
+    /// url of the jenkins instance
+    #[serde(rename = "url", default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Jenkins implementation
+
+#[allow(clippy::from_over_into)]
+impl Into<CreateOpts> for Jenkins {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn into(self) -> CreateOpts {
+        let mut options = addon::Opts::default();
+        ext::merge(&mut options, &Redacted(self.spec.options.to_owned()));
+
+        CreateOpts {
+            name: AddonExt::addon_name(&self),
+            region: self.spec.instance.region.to_owned(),
+            provider_id: provider::PROVIDER_ID.to_string(),
+            plan: self.spec.instance.plan.to_owned(),
+            options,
+        }
+    }
+}
+
+impl AddonExt for Jenkins {
+    type Error = ReconcilerError;
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn scope() -> String {
+        Self::kind(&()).to_string()
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn id(&self) -> Option<String> {
+        self.status
+            .as_ref()
+            .and_then(|status| status.addon.to_owned())
+            .or_else(|| crd::adopted(self))
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn organisation(&self) -> String {
+        self.spec.organisation.to_owned()
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn credentials_secret_ref(&self) -> Option<&CredentialsSecretReference> {
+        self.spec.credentials_secret_ref.as_ref()
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn display_name(&self) -> Option<String> {
+        self.spec.display_name.to_owned()
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn name(&self) -> String {
+        let delimiter = Self::delimiter();
+
+        Self::prefix()
+            + &delimiter
+            + &Self::kind(&())
+            + &delimiter
+            + &self
+                .uid()
+                .expect("expect all resources in kubernetes to have an identifier")
+    }
+}
+
+impl Jenkins {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_addon_id(&mut self, id: Option<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.addon = id;
+        self.status = Some(status.to_owned());
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn get_addon_id(&self) -> Option<String> {
+        self.status.to_owned().unwrap_or_default().addon
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_plan(&mut self, plan: Option<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.plan = plan;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_excluded_keys(&mut self, keys: Vec<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.excluded_keys = keys;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_binding(&mut self, binding: Option<Binding>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.binding = binding;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_billing(&mut self, billing: Option<Billing>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.billing = billing;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_url(&mut self, url: Option<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.url = url;
+    }
+}
+
+impl Conditions for Jenkins {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn conditions_mut(&mut self) -> &mut Vec<Condition> {
+        &mut self.status.get_or_insert_with(Status::default).conditions
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Action structure
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub enum Action {
+    UpsertFinalizer,
+    UpsertAddon,
+    UpsertSecret,
+    DeleteFinalizer,
+    DeleteAddon,
+    KeepAddon,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::UpsertFinalizer => write!(f, "UpsertFinalizer"),
+            Self::UpsertAddon => write!(f, "UpsertAddon"),
+            Self::UpsertSecret => write!(f, "UpsertSecret"),
+            Self::DeleteFinalizer => write!(f, "DeleteFinalizer"),
+            Self::DeleteAddon => write!(f, "DeleteAddon"),
+            Self::KeepAddon => write!(f, "KeepAddon"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// ReconcilerError enum
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReconcilerError {
+    #[error("failed to reconcile resource, {0}")]
+    Reconcile(String),
+    #[error("failed to execute request on clever-cloud api, {0}")]
+    CleverClient(clevercloud::Error),
+    #[error("failed to create clevercloud client, {0}")]
+    CreateCleverClient(clevercloud::client::Error),
+    #[error("failed to retrieve credentials secret, {0}")]
+    Credentials(secret::Error),
+    #[error("failed to execute request on kubernetes api, {0}")]
+    KubeClient(kube::Error),
+    #[error("failed to compute diff between the original and modified object, {0}")]
+    Diff(serde_json::Error),
+    #[error("failed to authorize call on clever-cloud api, {0}")]
+    Scope(clevercloud::client::ScopeError),
+    #[error("failed to resolve options from secrets, {0}")]
+    OptionsFrom(secret::Error),
+    #[error("failed to wait for dependencies, {0}")]
+    DependsOn(dependency::Error),
+}
+
+impl From<kube::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: kube::Error) -> Self {
+        Self::KubeClient(err)
+    }
+}
+
+impl From<clevercloud::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: clevercloud::Error) -> Self {
+        Self::CleverClient(err)
+    }
+}
+
+impl From<product::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: product::Error) -> Self {
+        Self::from(clevercloud::Error::from(err))
+    }
+}
+
+impl From<v2::addon::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: v2::addon::Error) -> Self {
+        Self::from(clevercloud::Error::from(err))
+    }
+}
+
+impl From<v4::addon_provider::plan::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: v4::addon_provider::plan::Error) -> Self {
+        Self::from(clevercloud::Error::from(err))
+    }
+}
+
+impl From<controller::Error<Self, watcher::Error>> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: controller::Error<ReconcilerError, watcher::Error>) -> Self {
+        Self::Reconcile(err.to_string())
+    }
+}
+
+impl From<clevercloud::client::ScopeError> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: clevercloud::client::ScopeError) -> Self {
+        Self::Scope(err)
+    }
+}
+
+impl From<clevercloud::client::Error> for ReconcilerError {
+    fn from(err: clevercloud::client::Error) -> Self {
+        Self::CreateCleverClient(err)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Reconciler structure
+
+#[derive(Clone, Default, Debug)]
+pub struct Reconciler {}
+
+impl ControllerBuilder<Jenkins> for Reconciler {
+    fn build(&self, state: Arc<Context>, namespace: Option<&str>) -> Controller<Jenkins> {
+        let client = state.kube.to_owned();
+        let secret = k8s::api::<Secret>(client.to_owned(), namespace);
+
+        Controller::new(
+            k8s::api(client, namespace),
+            k8s::watcher_config(&state.config),
+        )
+        .owns(secret, watcher::Config::default())
+        // reconcile the custom resource once a copy of its secret exported to
+        // another namespace is modified or deleted
+        .watches(
+            k8s::api::<Secret>(state.kube.to_owned(), namespace),
+            watcher::Config::default().labels(export::SOURCE_LABEL),
+            export::source::<Jenkins>,
+        )
+    }
+}
+
+#[async_trait]
+impl k8s::Reconciler<Jenkins> for Reconciler {
+    type Error = ReconcilerError;
+
+    fn permanent(err: &ReconcilerError) -> Option<&'static str> {
+        match err {
+            ReconcilerError::CleverClient(err) => err.permanent(),
+            ReconcilerError::DependsOn(err) => err.permanent(),
+            _ => None,
+        }
+    }
+
+    async fn upsert(ctx: Arc<Context>, origin: Arc<Jenkins>) -> Result<(), ReconcilerError> {
+        let Context {
+            kube,
+            apis,
+            backend,
+            config,
+            secrets,
+            clients,
+            ..
+        } = ctx.as_ref();
+
+        let kind = Jenkins::kind(&()).to_string();
+        let (namespace, name) = resource::namespaced_name(&*origin);
+
+        // ---------------------------------------------------------------------
+        // Step 0: verify if there is a clever cloud client override
+        let reference = origin.spec.credentials_secret_ref.as_ref();
+        let (secret_namespace, secret_name) = secret::credentials_reference(&namespace, reference);
+
+        debug!(
+            namespace = &secret_namespace,
+            secret = &secret_name,
+            "Try to retrieve the optional secret on namespace",
+        );
+
+        let secret: Option<Secret> = secret::credentials(secrets, &namespace, reference)
+            .await
+            .map_err(ReconcilerError::Credentials)?;
+
+        let apis = match secret {
+            Some(secret) => {
+                info!(
+                    namespace = &secret_namespace,
+                    secret = &secret_name,
+                    "Use custom Clever Cloud client to connect the api using secret",
+                );
+
+                ScopedClient::scoped(clients.get(secret).await?, &kind)
+            }
+            None => {
+                info!("Use default Clever Cloud client to connect the api");
+                ScopedClient::unscoped(apis.get())
+            }
+        }
+        .with_rate_limit(&origin.spec.organisation, &config.api.rate_limit)
+        .with_backend(backend.to_owned());
+
+        // ---------------------------------------------------------------------
+        // Step 1: set finalizer
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Set finalizer on custom resource",
+        );
+
+        let patch = finalizer::add_patch(&*origin, ADDON_FINALIZER);
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update information of custom resource",
+        );
+
+        let mut modified = resource::patch(kube.to_owned(), &*origin, patch).await?;
+
+        let action = &Action::UpsertFinalizer;
+        let message = &format!("Create finalizer '{}'", ADDON_FINALIZER);
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        // hold off provisioning until the custom resources this one depends on
+        // are ready
+        if let Err(err) =
+            dependency::all_ready(kube.to_owned(), &namespace, &modified.spec.depends_on).await
+        {
+            let current = modified.to_owned();
+            modified.set_condition(Condition::new(
+                condition::READY,
+                condition::Status::False,
+                condition::REASON_WAITING_FOR_DEPENDENCIES,
+                &err.to_string(),
+            ));
+
+            let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+            resource::patch_status(kube.to_owned(), modified, patch).await?;
+
+            return Err(ReconcilerError::DependsOn(err));
+        }
+
+        // ---------------------------------------------------------------------
+        // Step 2: translate plan, plans of the jenkins addon provider are not
+        // listed in the catalog, so they are resolved using its product
+
+        if !modified.spec.instance.plan.starts_with("plan_") {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                plan = &modified.spec.instance.plan,
+                "Resolve plan for resource'",
+            );
+
+            let plan = product::find(
+                apis.authorize(&kind, "product::find").await?,
+                &config.api.endpoint,
+                provider::PROVIDER_ID,
+                &modified.spec.instance.plan,
+            )
+            .await?;
+
+            match plan {
+                Some(plan) => {
+                    debug!(
+                        kind = &kind,
+                        namespace = &namespace,
+                        name = &name,
+                        plan = &plan.id,
+                        "Use resolved plan for custom resource",
+                    );
+
+                    modified.spec.instance.plan = plan.id;
+                }
+                None => {
+                    return Err(clevercloud::Error::UnknownPlan(
+                        modified.spec.instance.plan.to_owned(),
+                        modified.spec.organisation.to_owned(),
+                    )
+                    .into());
+                }
+            }
+        }
+
+        // ---------------------------------------------------------------------
+        // Step 3: upsert addon
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Upsert addon for custom resource",
+        );
+
+        let options = match secret::resolve(secrets, &namespace, &modified.spec.options_from).await
+        {
+            Ok(options) => options,
+            Err(err) => {
+                let current = modified.to_owned();
+                modified.set_condition(Condition::new(
+                    condition::READY,
+                    condition::Status::False,
+                    condition::REASON_WAITING_FOR_DEPENDENCIES,
+                    &err.to_string(),
+                ));
+
+                let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+                resource::patch_status(kube.to_owned(), modified, patch).await?;
+
+                return Err(ReconcilerError::OptionsFrom(err));
+            }
+        };
+
+        let addon = modified.upsert_with_options(&apis, &options).await?;
+
+        let current = modified.to_owned();
+        modified.set_addon_id(Some(addon.id.to_owned()));
+        naming::sync(&apis, config, &modified, &addon).await;
+        tags::sync(&apis, config, &modified, &addon).await;
+
+        let current_billing = modified.status.as_ref().and_then(|s| s.billing.as_ref());
+        let billing = billing::refresh(&apis, config, &modified, &addon, current_billing).await;
+        modified.set_billing(billing);
+
+        #[cfg(feature = "metrics")]
+        stats::track(&apis, config, &modified, &addon).await;
+
+        modified.set_plan(Some(addon.plan.slug.to_owned()));
+        modified.set_condition(Condition::new(
+            condition::READY,
+            condition::Status::True,
+            "AddonProvisioned",
+            &format!("Addon '{}' is provisioned on clever-cloud", addon.id),
+        ));
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update information and status of custom resource",
+        );
+
+        let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+        let modified = resource::patch(kube.to_owned(), &modified, patch.to_owned())
+            .and_then(|modified| resource::patch_status(kube.to_owned(), modified, patch))
+            .await?;
+
+        let action = &Action::UpsertAddon;
+        let message = &format!(
+            "Create managed jenkins instance on clever-cloud '{}'",
+            addon.id
+        );
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        // ---------------------------------------------------------------------
+        // Step 4: create the secret
+
+        let secrets = modified.secrets(&apis).await?;
+        if let Some(mut secrets) = secrets {
+            // the url is read before excluding keys, so it is known even if
+            // it is not written in the secret
+            let url = provider::url(&secrets);
+            let (s, excluded) = secret::new(
+                &modified,
+                &mut secrets,
+                modified.spec.exposure.as_ref(),
+                &config.operator.secret.exclude_keys,
+                modified.spec.secret_template.as_ref(),
+            );
+
+            let mut updated = modified.to_owned();
+            updated.set_url(url);
+            updated.set_excluded_keys(excluded);
+            updated.set_binding(Some(binding::reference(&updated)));
+
+            let patch = resource::diff(&modified, &updated).map_err(ReconcilerError::Diff)?;
+            let modified = resource::patch_status(kube.to_owned(), updated, patch).await?;
+
+            let b = binding::new(
+                &modified,
+                &secrets,
+                &BINDING,
+                modified.spec.service_binding.as_ref(),
+            );
+            let s = export::annotate(&modified, s, &modified.spec.export_to);
+            let (s_ns, s_name) = resource::namespaced_name(&s);
+
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                "Upsert kubernetes secret resource for custom resource",
+            );
+
+            info!(
+                namespace = &s_ns,
+                name = &s_name,
+                "Upsert kubernetes secret",
+            );
+
+            let current: Option<Secret> = resource::get(kube.to_owned(), &s_ns, &s_name).await?;
+            let secret = resource::upsert(kube.to_owned(), &s, false).await?;
+            if let Some(message) = current
+                .as_ref()
+                .and_then(|current| drift::secret(current, &s))
+            {
+                drift::repaired(kube.to_owned(), &modified, &message).await?;
+            }
+
+            let action = &Action::UpsertSecret;
+            let message = &format!("Create kubernetes secret '{}'", secret.name_any());
+            recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+            let exported =
+                export::sync(kube.to_owned(), &modified, &secret, current.as_ref()).await?;
+            if !exported.is_empty() {
+                let message = &format!(
+                    "Export kubernetes secret '{}' to namespaces '{}'",
+                    secret.name_any(),
+                    exported.join("', '")
+                );
+                recorder::normal(kube.to_owned(), &modified, action, message).await?;
+            }
+
+            info!(
+                namespace = &s_ns,
+                name = binding::name(&modified),
+                "Upsert kubernetes service binding secret",
+            );
+
+            let b = resource::upsert(kube.to_owned(), &b, false).await?;
+            let message = &format!(
+                "Create kubernetes service binding secret '{}'",
+                b.name_any()
+            );
+            recorder::normal(kube.to_owned(), &modified, action, message).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete(ctx: Arc<Context>, origin: Arc<Jenkins>) -> Result<(), ReconcilerError> {
+        let Context {
+            apis,
+            backend,
+            kube,
+            config,
+            secrets,
+            clients,
+            ..
+        } = ctx.as_ref();
+
+        let mut modified = (*origin).to_owned();
+        let kind = Jenkins::kind(&()).to_string();
+        let (namespace, name) = resource::namespaced_name(&*origin);
+
+        // ---------------------------------------------------------------------
+        // Step 0: verify if there is a clever cloud client override
+        let reference = origin.spec.credentials_secret_ref.as_ref();
+        let (secret_namespace, secret_name) = secret::credentials_reference(&namespace, reference);
+
+        debug!(
+            namespace = &secret_namespace,
+            secret = &secret_name,
+            "Try to retrieve the optional secret",
+        );
+
+        let secret: Option<Secret> = secret::credentials(secrets, &namespace, reference)
+            .await
+            .map_err(ReconcilerError::Credentials)?;
+
+        let apis = match secret {
+            Some(secret) => {
+                info!(
+                    namespace = &secret_namespace,
+                    secret = &secret_name,
+                    "Use custom Clever Cloud client to connect the api using secret",
+                );
+
+                ScopedClient::scoped(clients.get(secret).await?, &kind)
+            }
+            None => {
+                info!("Use default Clever Cloud client to connect the api");
+                ScopedClient::unscoped(apis.get())
+            }
+        }
+        .with_rate_limit(&origin.spec.organisation, &config.api.rate_limit)
+        .with_backend(backend.to_owned());
+
+        // ---------------------------------------------------------------------
+        // Step 1: delete the addon, unless its deletion policy keeps it
+
+        let policy = modified.spec.deletion_policy;
+        if policy.orphans_secrets() || !config.operator.secret.cascade_deletion {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                "Release kubernetes secrets of custom resource",
+            );
+
+            secret::release(kube.to_owned(), &modified).await?;
+        } else {
+            // copies exported to other namespaces are not owned by the custom
+            // resource, so they are not garbage collected along it
+            export::prune(kube.to_owned(), &modified).await?;
+        }
+
+        let (action, message) = if policy.deletes_addon() {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                "Delete addon for custom resource",
+            );
+
+            modified.delete(&apis).await?;
+            (
+                &Action::DeleteAddon,
+                "Delete managed jenkins instance on clever-cloud".to_string(),
+            )
+        } else {
+            let addon = modified.get_addon_id().unwrap_or_default();
+
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                addon = &addon,
+                policy = policy.to_string(),
+                "Keep addon for custom resource, as requested by its deletion policy",
+            );
+
+            (
+                &Action::KeepAddon,
+                format!(
+                    "Keep addon '{}' on clever-cloud, as requested by the '{}' deletion policy",
+                    addon, policy
+                ),
+            )
+        };
+
+        modified.set_addon_id(None);
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update information and status of custom resource",
+        );
+
+        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
+        let modified = resource::patch(kube.to_owned(), &modified, patch.to_owned())
+            .and_then(|modified| resource::patch_status(kube.to_owned(), modified, patch))
+            .await?;
+
+        recorder::normal(kube.to_owned(), &modified, action, &message).await?;
+
+        // ---------------------------------------------------------------------
+        // Step 2: remove the finalizer
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Remove finalizer on custom resource",
+        );
+
+        let patch = finalizer::remove_patch(&modified, ADDON_FINALIZER);
+
+        let action = &Action::DeleteFinalizer;
+        let message = "Delete finalizer from custom resource";
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update information of custom resource",
+        );
+
+        resource::patch(kube.to_owned(), &modified, patch).await?;
+
+        Ok(())
+    }
+}
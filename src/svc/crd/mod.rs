@@ -3,16 +3,52 @@
 //! This module provide custom resource definition managed by the operator,
 //! their structures, implementation and reconciliation loop.
 
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+use kube::ResourceExt;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+pub mod addon;
+pub mod backup;
+pub mod cellar_bucket;
 pub mod config_provider;
 pub mod elasticsearch;
+pub mod fs_bucket;
+pub mod gravitee;
+pub mod jenkins;
+pub mod mailpace;
 pub mod mongodb;
 pub mod mysql;
 pub mod postgresql;
 pub mod pulsar;
 pub mod redis;
+pub mod runtime;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// annotation giving the identifier of an existing addon to adopt, it is only
+/// used until the addon identifier is written in the status
+pub const ADDON_ID_ANNOTATION: &str = "api.clever-cloud.com/addon-id";
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the identifier of the existing addon to adopt, if any
+pub fn adopted<T>(obj: &T) -> Option<String>
+where
+    T: ResourceExt + std::fmt::Debug,
+{
+    obj.annotations()
+        .get(ADDON_ID_ANNOTATION)
+        .filter(|id| !id.is_empty())
+        .cloned()
+}
 
 // -----------------------------------------------------------------------------
 // Instance structure
@@ -24,3 +60,293 @@ pub struct Instance {
     #[serde(rename = "plan")]
     pub plan: String,
 }
+
+// -----------------------------------------------------------------------------
+// Exposure structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Exposure {
+    /// glob patterns of environment keys that should never be written in the
+    /// kubernetes secret, e.g. `*_ROOT_PASSWORD`
+    #[serde(rename = "excludeKeys", default)]
+    pub exclude_keys: Vec<String>,
+}
+
+// -----------------------------------------------------------------------------
+// SecretTemplate structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct SecretTemplate {
+    /// glob patterns of environment keys to write in the kubernetes secret,
+    /// all keys are written if empty, e.g. `POSTGRESQL_ADDON_*`
+    #[serde(rename = "select", default, skip_serializing_if = "Vec::is_empty")]
+    pub select: Vec<String>,
+    /// new names of environment keys in the kubernetes secret, e.g.
+    /// `POSTGRESQL_ADDON_URI: DATABASE_URL`
+    #[serde(rename = "rename", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rename: BTreeMap<String, String>,
+    /// labels added on the kubernetes secret
+    #[serde(rename = "labels", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// annotations added on the kubernetes secret
+    #[serde(
+        rename = "annotations",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub annotations: BTreeMap<String, String>,
+}
+
+// -----------------------------------------------------------------------------
+// ServiceBinding structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct ServiceBinding {
+    /// write only the well-known entries of the service binding specification
+    /// (`type`, `provider`, `host`, `port`, `username`, `password`, ...) in
+    /// the binding secret instead of the whole environment of the addon
+    #[serde(rename = "projection", default)]
+    pub projection: bool,
+    /// type of the provisioned service, defaults to the one of the addon, e.g.
+    /// `postgresql`
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// ClusterService structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct ClusterService {
+    /// name of the kubernetes service, defaults to the name of the custom
+    /// resource, the addon is then reachable at `<name>.<namespace>.svc`
+    #[serde(rename = "name", default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// one of `ExternalName` (default) or `Headless`
+    #[serde(rename = "type", default)]
+    pub kind: ClusterServiceType,
+}
+
+// -----------------------------------------------------------------------------
+// ClusterServiceType enumeration
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum ClusterServiceType {
+    /// the cluster dns resolves the name of the service to the host of the
+    /// addon
+    #[default]
+    ExternalName,
+    /// the endpoint slice of the service holds the addresses of the host of
+    /// the addon, resolved by the operator on each reconciliation
+    Headless,
+}
+
+// -----------------------------------------------------------------------------
+// Consumers structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Consumers {
+    /// labels of the pods consuming the addon, in the namespace of the custom
+    /// resource, all pods of the namespace are selected if empty
+    #[serde(rename = "matchLabels", default)]
+    pub match_labels: BTreeMap<String, String>,
+}
+
+// -----------------------------------------------------------------------------
+// Binding structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Binding {
+    /// name of the secret following the service binding specification
+    #[serde(rename = "name")]
+    pub name: String,
+}
+
+// -----------------------------------------------------------------------------
+// DeletionPolicy enumeration
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum DeletionPolicy {
+    /// delete the addon on clever cloud and the generated secrets
+    #[default]
+    Delete,
+    /// keep the addon on clever cloud and the generated secrets, which are
+    /// released from the custom resource
+    Orphan,
+    /// keep the addon on clever cloud, the generated secrets are deleted
+    Retain,
+}
+
+impl Display for DeletionPolicy {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Delete => write!(f, "Delete"),
+            Self::Orphan => write!(f, "Orphan"),
+            Self::Retain => write!(f, "Retain"),
+        }
+    }
+}
+
+impl DeletionPolicy {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns if the addon is deleted on clever cloud along the custom resource
+    pub fn deletes_addon(&self) -> bool {
+        Self::Delete == *self
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns if the generated secrets outlive the custom resource
+    pub fn orphans_secrets(&self) -> bool {
+        Self::Orphan == *self
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Dependency structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Dependency {
+    /// kind of the custom resource managed by the operator, e.g. `PostgreSql`
+    #[serde(rename = "kind")]
+    pub kind: String,
+    /// name of the custom resource in the namespace of the custom resource
+    #[serde(rename = "name")]
+    pub name: String,
+}
+
+// -----------------------------------------------------------------------------
+// SecretReference structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct SecretReference {
+    /// name of the secret in the namespace of the custom resource
+    #[serde(rename = "name")]
+    pub name: String,
+}
+
+// -----------------------------------------------------------------------------
+// CredentialsSecretReference structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct CredentialsSecretReference {
+    /// name of the secret holding the configuration of the clever cloud client
+    #[serde(rename = "name")]
+    pub name: String,
+    /// namespace of the secret, the one of the custom resource if not set. A
+    /// secret of another namespace has to list the one of the custom resource
+    /// in its `api.clever-cloud.com/allowed-namespaces` annotation
+    #[serde(rename = "namespace", default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// SecretKeySelector structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct SecretKeySelector {
+    /// name of the secret in the namespace of the custom resource
+    #[serde(rename = "name")]
+    pub name: String,
+    /// key of the value in the secret
+    #[serde(rename = "key")]
+    pub key: String,
+}
+
+// -----------------------------------------------------------------------------
+// OptionFrom structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct OptionFrom {
+    #[serde(rename = "secretRef")]
+    pub secret_ref: SecretKeySelector,
+    /// name of the addon creation option set with the value of the secret,
+    /// e.g. `version`
+    #[serde(rename = "mapTo")]
+    pub map_to: String,
+}
+
+// -----------------------------------------------------------------------------
+// MigrationStatus structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct MigrationStatus {
+    /// identifier of the migration on clever cloud
+    #[serde(rename = "migrationId")]
+    pub migration_id: String,
+    /// identifier of the plan to which the addon is migrated
+    #[serde(rename = "plan")]
+    pub plan: String,
+    /// version to which the addon is upgraded, if any
+    #[serde(rename = "version", default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// one of `Running`, `Succeeded` or `Failed`
+    #[serde(rename = "phase")]
+    pub phase: String,
+}
+
+impl MigrationStatus {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns a description of the plan and version targeted by the migration
+    pub fn target(&self) -> String {
+        match &self.version {
+            Some(version) => format!("plan '{}' and version '{}'", self.plan, version),
+            None => format!("plan '{}'", self.plan),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Billing structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Billing {
+    /// price of the plan of the addon, in euros, as exposed by the api
+    #[serde(rename = "planPrice", default, skip_serializing_if = "Option::is_none")]
+    pub plan_price: Option<String>,
+    /// price of the consumption of the addon since the beginning of the
+    /// current month, in euros
+    #[serde(
+        rename = "currentMonthConsumption",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub current_month_consumption: Option<String>,
+    /// time of the last refresh of the consumption
+    #[serde(
+        rename = "lastUpdateTimestamp",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_update_timestamp: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Restore structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Restore {
+    /// real identifier of the addon owning the backup, e.g.
+    /// `postgresql_<uuid>`, it must belong to the organisation of the custom
+    /// resource
+    #[serde(rename = "addon")]
+    pub addon: String,
+    /// identifier of the backup to restore, defaults to the latest backup of
+    /// the addon
+    #[serde(rename = "backupId", default, skip_serializing_if = "Option::is_none")]
+    pub backup_id: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// RestoreStatus structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct RestoreStatus {
+    #[serde(rename = "backupId")]
+    pub backup_id: String,
+    /// name of the job restoring the backup
+    #[serde(rename = "job")]
+    pub job: String,
+    /// one of `Running`, `Succeeded` or `Failed`
+    #[serde(rename = "phase")]
+    pub phase: String,
+}
@@ -5,6 +5,7 @@
 use std::{
     fmt::{self, Display, Formatter},
     sync::Arc,
+    time::Duration,
 };
 
 use async_trait::async_trait;
@@ -15,27 +16,46 @@ use clevercloud_sdk::{
     },
     v4::{
         self,
-        addon_provider::{mysql, plan, AddonProviderId},
+        addon_provider::{mysql, AddonProviderId},
     },
 };
 use futures::TryFutureExt;
-use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::api::{batch::v1::Job, core::v1::Secret};
 use kube::{
     runtime::{controller, watcher, Controller},
-    Api, CustomResource, Resource, ResourceExt,
+    CustomResource, Resource, ResourceExt,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
+#[cfg(feature = "metrics")]
+use crate::svc::k8s::stats;
 use crate::svc::{
-    clevercloud::{self, ext::AddonExt},
-    crd::Instance,
+    cfg::NotificationEvent,
+    clevercloud::{
+        self,
+        client::ScopedClient,
+        ext::{self, AddonExt},
+        provider,
+    },
+    crd::{
+        self, Billing, Binding, ClusterService, Consumers, CredentialsSecretReference,
+        DeletionPolicy, Dependency, Exposure, Instance, MigrationStatus, OptionFrom, Restore,
+        RestoreStatus, SecretTemplate, ServiceBinding,
+    },
     k8s::{
-        self, finalizer, recorder, resource,
-        secret::{self, OVERRIDE_CONFIGURATION_NAME},
-        Context, ControllerBuilder,
+        self, billing,
+        binding::{self, Endpoint, Mapping},
+        condition::{self, Condition, Conditions},
+        dependency, drift, export, finalizer, migration, naming, network_policy,
+        probe::{self, Probe},
+        rbac::{self, Permission},
+        recorder, resource,
+        restore::{self, Engine},
+        secret, service, tags, Context, ControllerBuilder,
     },
+    notification,
 };
 
 // -----------------------------------------------------------------------------
@@ -43,6 +63,30 @@ use crate::svc::{
 
 pub const ADDON_FINALIZER: &str = "api.clever-cloud.com/mysql";
 
+/// well-known entries of the service binding specification
+pub const BINDING: Mapping = Mapping {
+    kind: "mysql",
+    keys: &[
+        ("host", "MYSQL_ADDON_HOST"),
+        ("port", "MYSQL_ADDON_PORT"),
+        ("username", "MYSQL_ADDON_USER"),
+        ("password", "MYSQL_ADDON_PASSWORD"),
+        ("database", "MYSQL_ADDON_DB"),
+        ("uri", "MYSQL_ADDON_URI"),
+    ],
+};
+
+/// kubernetes resources touched by the reconciler beside its custom resources
+pub const PERMISSIONS: &[Permission] = &[
+    rbac::SECRETS,
+    rbac::JOBS,
+    rbac::SERVICES,
+    rbac::ENDPOINT_SLICES,
+    rbac::NETWORK_POLICIES,
+    rbac::CILIUM_NETWORK_POLICIES,
+    rbac::CALICO_NETWORK_POLICIES,
+];
+
 // -----------------------------------------------------------------------------
 // Opts structure
 
@@ -96,13 +140,94 @@ impl Into<addon::Opts> for Opts {
 #[kube(
     printcolumn = r#"{"name":"encrypted", "type":"boolean", "description":"Cold encryption", "jsonPath":".spec.options.encryption"}"#
 )]
+#[kube(
+    printcolumn = r#"{"name":"plan", "type":"string", "description":"Plan", "jsonPath":".status.plan"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"host", "type":"string", "description":"Host", "jsonPath":".status.host", "priority":1}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"port", "type":"integer", "description":"Port", "jsonPath":".status.port", "priority":1}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"database", "type":"string", "description":"Database", "jsonPath":".status.database", "priority":1}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"ready", "type":"string", "description":"Ready", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"phase", "type":"string", "description":"Reason of the ready condition", "jsonPath":".status.conditions[?(@.type==\"Ready\")].reason"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"age", "type":"date", "description":"Age", "jsonPath":".metadata.creationTimestamp"}"#
+)]
 pub struct Spec {
     #[serde(rename = "organisation")]
     pub organisation: String,
+    /// secret holding the configuration of the clever cloud client used to
+    /// reconcile the custom resource, the `clever-operator` secret of the
+    /// namespace is used if not set
+    #[serde(
+        rename = "credentialsSecretRef",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub credentials_secret_ref: Option<CredentialsSecretReference>,
     #[serde(rename = "options")]
     pub options: Opts,
     #[serde(rename = "instance")]
     pub instance: Instance,
+    #[serde(rename = "exposure", default, skip_serializing_if = "Option::is_none")]
+    pub exposure: Option<Exposure>,
+    #[serde(
+        rename = "secretTemplate",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub secret_template: Option<SecretTemplate>,
+    /// namespaces in which the generated secret is replicated, so workloads
+    /// of other namespaces could consume it
+    #[serde(rename = "exportTo", default, skip_serializing_if = "Vec::is_empty")]
+    pub export_to: Vec<String>,
+    /// options of the secret following the service binding specification
+    #[serde(
+        rename = "serviceBinding",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub service_binding: Option<ServiceBinding>,
+    /// custom resources which must be ready before provisioning this one
+    #[serde(rename = "dependsOn", default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<Dependency>,
+    /// whether the addon and the generated secrets are deleted along the
+    /// custom resource, one of `Delete` (default), `Orphan` or `Retain`
+    #[serde(rename = "deletionPolicy", default)]
+    pub deletion_policy: DeletionPolicy,
+    #[serde(rename = "optionsFrom", default, skip_serializing_if = "Vec::is_empty")]
+    pub options_from: Vec<OptionFrom>,
+    /// backup to restore once the addon is provisioned
+    #[serde(rename = "restore", default, skip_serializing_if = "Option::is_none")]
+    pub restore: Option<Restore>,
+    /// name of the addon in the Clever Cloud console, the naming template of
+    /// the operator is used if not set
+    #[serde(
+        rename = "displayName",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub display_name: Option<String>,
+    /// suspend the reconciliation of the custom resource, e.g. during a
+    /// manual maintenance on Clever Cloud, its deletion is still processed
+    #[serde(rename = "suspend", default)]
+    pub suspend: bool,
+    /// expose the addon under a stable in-cluster dns name using a kubernetes
+    /// service, e.g. `<name>.<namespace>.svc`
+    #[serde(rename = "service", default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<ClusterService>,
+    /// pods consuming the addon, egress from them to the addon is allowed by
+    /// a network policy once network policies are enabled in the operator
+    #[serde(rename = "consumers", default, skip_serializing_if = "Option::is_none")]
+    pub consumers: Option<Consumers>,
 }
 
 // -----------------------------------------------------------------------------
@@ -112,6 +237,48 @@ pub struct Spec {
 pub struct Status {
     #[serde(rename = "addon")]
     pub addon: Option<String>,
+    /// slug of the plan of the addon, e.g. `xs_sml`
+    #[serde(rename = "plan", default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<String>,
+    #[serde(rename = "conditions", default)]
+    pub conditions: Vec<Condition>,
+    /// generation of the specification processed by the last successful
+    /// reconciliation
+    #[serde(
+        rename = "observedGeneration",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub observed_generation: Option<i64>,
+    #[serde(
+        rename = "excludedKeys",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub excluded_keys: Vec<String>,
+    /// reference to the secret following the service binding specification
+    #[serde(rename = "binding", default, skip_serializing_if = "Option::is_none")]
+    pub binding: Option<Binding>,
+    /// host of the addon, read from its environment
+    #[serde(rename = "host", default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// port of the addon, read from its environment
+    #[serde(rename = "port", default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// database of the addon, read from its environment
+    #[serde(rename = "database", default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    /// price of the plan and consumption of the addon, if billing is enabled
+    #[serde(rename = "billing", default, skip_serializing_if = "Option::is_none")]
+    pub billing: Option<Billing>,
+    /// migration of the addon to the plan of the specification
+    #[serde(rename = "migration", default, skip_serializing_if = "Option::is_none")]
+    pub migration: Option<MigrationStatus>,
+    /// version applied on the addon
+    #[serde(rename = "version", default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(rename = "restore", default, skip_serializing_if = "Option::is_none")]
+    pub restore: Option<RestoreStatus>,
 }
 
 // -----------------------------------------------------------------------------
@@ -122,7 +289,7 @@ impl Into<CreateOpts> for MySql {
     #[cfg_attr(feature = "trace", tracing::instrument)]
     fn into(self) -> CreateOpts {
         CreateOpts {
-            name: AddonExt::name(&self),
+            name: AddonExt::addon_name(&self),
             region: self.spec.instance.region.to_owned(),
             provider_id: AddonProviderId::MySql.to_string(),
             plan: self.spec.instance.plan.to_owned(),
@@ -135,12 +302,16 @@ impl AddonExt for MySql {
     type Error = ReconcilerError;
 
     #[cfg_attr(feature = "trace", tracing::instrument)]
-    fn id(&self) -> Option<String> {
-        if let Some(status) = &self.status {
-            return status.addon.to_owned();
-        }
+    fn scope() -> String {
+        Self::kind(&()).to_string()
+    }
 
-        None
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn id(&self) -> Option<String> {
+        self.status
+            .as_ref()
+            .and_then(|status| status.addon.to_owned())
+            .or_else(|| crd::adopted(self))
     }
 
     #[cfg_attr(feature = "trace", tracing::instrument)]
@@ -148,6 +319,16 @@ impl AddonExt for MySql {
         self.spec.organisation.to_owned()
     }
 
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn credentials_secret_ref(&self) -> Option<&CredentialsSecretReference> {
+        self.spec.credentials_secret_ref.as_ref()
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn display_name(&self) -> Option<String> {
+        self.spec.display_name.to_owned()
+    }
+
     #[cfg_attr(feature = "trace", tracing::instrument)]
     fn name(&self) -> String {
         let delimiter = Self::delimiter();
@@ -175,6 +356,71 @@ impl MySql {
     pub fn get_addon_id(&self) -> Option<String> {
         self.status.to_owned().unwrap_or_default().addon
     }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_plan(&mut self, plan: Option<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.plan = plan;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_excluded_keys(&mut self, keys: Vec<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.excluded_keys = keys;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_binding(&mut self, binding: Option<Binding>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.binding = binding;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_endpoint(&mut self, endpoint: Endpoint) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.host = endpoint.host;
+        status.port = endpoint.port;
+        status.database = endpoint.database;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_billing(&mut self, billing: Option<Billing>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.billing = billing;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_migration(&mut self, migration: Option<MigrationStatus>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.migration = migration;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_version(&mut self, version: Option<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.version = version;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_restore(&mut self, restore: Option<RestoreStatus>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.restore = restore;
+    }
+}
+
+impl Conditions for MySql {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn conditions_mut(&mut self) -> &mut Vec<Condition> {
+        &mut self.status.get_or_insert_with(Status::default).conditions
+    }
 }
 
 // -----------------------------------------------------------------------------
@@ -184,10 +430,15 @@ impl MySql {
 pub enum Action {
     UpsertFinalizer,
     UpsertAddon,
+    MigrateAddon,
     UpsertSecret,
-    OverridesInstancePlan,
+    UpsertService,
+    UpsertNetworkPolicy,
+    ProbeAddon,
+    RestoreBackup,
     DeleteFinalizer,
     DeleteAddon,
+    KeepAddon,
 }
 
 impl Display for Action {
@@ -195,10 +446,15 @@ impl Display for Action {
         match self {
             Self::UpsertFinalizer => write!(f, "UpsertFinalizer"),
             Self::UpsertAddon => write!(f, "UpsertAddon"),
+            Self::MigrateAddon => write!(f, "MigrateAddon"),
             Self::UpsertSecret => write!(f, "UpsertSecret"),
-            Self::OverridesInstancePlan => write!(f, "OverridesInstancePlan"),
+            Self::UpsertService => write!(f, "UpsertService"),
+            Self::UpsertNetworkPolicy => write!(f, "UpsertNetworkPolicy"),
+            Self::ProbeAddon => write!(f, "ProbeAddon"),
+            Self::RestoreBackup => write!(f, "RestoreBackup"),
             Self::DeleteFinalizer => write!(f, "DeleteFinalizer"),
             Self::DeleteAddon => write!(f, "DeleteAddon"),
+            Self::KeepAddon => write!(f, "KeepAddon"),
         }
     }
 }
@@ -214,10 +470,24 @@ pub enum ReconcilerError {
     CleverClient(clevercloud::Error),
     #[error("failed to create clevercloud client, {0}")]
     CreateCleverClient(clevercloud::client::Error),
+    #[error("failed to retrieve credentials secret, {0}")]
+    Credentials(secret::Error),
     #[error("failed to execute request on kubernetes api, {0}")]
     KubeClient(kube::Error),
     #[error("failed to compute diff between the original and modified object, {0}")]
     Diff(serde_json::Error),
+    #[error("failed to authorize call on clever-cloud api, {0}")]
+    Scope(clevercloud::client::ScopeError),
+    #[error("failed to resolve options from secrets, {0}")]
+    OptionsFrom(secret::Error),
+    #[error("failed to restore backup, {0}")]
+    Restore(restore::Error),
+    #[error("failed to wait for dependencies, {0}")]
+    DependsOn(dependency::Error),
+    #[error("failed to expose addon as kubernetes service, {0}")]
+    Service(service::Error),
+    #[error("failed to allow egress from consumers to addon, {0}")]
+    NetworkPolicy(network_policy::Error),
 }
 
 impl From<kube::Error> for ReconcilerError {
@@ -227,6 +497,20 @@ impl From<kube::Error> for ReconcilerError {
     }
 }
 
+impl From<network_policy::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: network_policy::Error) -> Self {
+        Self::NetworkPolicy(err)
+    }
+}
+
+impl From<service::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: service::Error) -> Self {
+        Self::Service(err)
+    }
+}
+
 impl From<clevercloud::Error> for ReconcilerError {
     #[cfg_attr(feature = "trace", tracing::instrument)]
     fn from(err: clevercloud::Error) -> Self {
@@ -234,6 +518,13 @@ impl From<clevercloud::Error> for ReconcilerError {
     }
 }
 
+impl From<restore::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: restore::Error) -> Self {
+        Self::Restore(err)
+    }
+}
+
 impl From<v2::addon::Error> for ReconcilerError {
     #[cfg_attr(feature = "trace", tracing::instrument)]
     fn from(err: v2::addon::Error) -> Self {
@@ -241,6 +532,13 @@ impl From<v2::addon::Error> for ReconcilerError {
     }
 }
 
+impl From<clevercloud::provider::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: clevercloud::provider::Error) -> Self {
+        Self::from(clevercloud::Error::from(err))
+    }
+}
+
 impl From<v4::addon_provider::plan::Error> for ReconcilerError {
     #[cfg_attr(feature = "trace", tracing::instrument)]
     fn from(err: v4::addon_provider::plan::Error) -> Self {
@@ -248,6 +546,13 @@ impl From<v4::addon_provider::plan::Error> for ReconcilerError {
     }
 }
 
+impl From<clevercloud::migration::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: clevercloud::migration::Error) -> Self {
+        Self::from(clevercloud::Error::from(err))
+    }
+}
+
 impl From<controller::Error<Self, watcher::Error>> for ReconcilerError {
     #[cfg_attr(feature = "trace", tracing::instrument)]
     fn from(err: controller::Error<ReconcilerError, watcher::Error>) -> Self {
@@ -255,6 +560,13 @@ impl From<controller::Error<Self, watcher::Error>> for ReconcilerError {
     }
 }
 
+impl From<clevercloud::client::ScopeError> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: clevercloud::client::ScopeError) -> Self {
+        Self::Scope(err)
+    }
+}
+
 impl From<clevercloud::client::Error> for ReconcilerError {
     fn from(err: clevercloud::client::Error) -> Self {
         Self::CreateCleverClient(err)
@@ -268,12 +580,24 @@ impl From<clevercloud::client::Error> for ReconcilerError {
 pub struct Reconciler {}
 
 impl ControllerBuilder<MySql> for Reconciler {
-    fn build(&self, state: Arc<Context>) -> Controller<MySql> {
+    fn build(&self, state: Arc<Context>, namespace: Option<&str>) -> Controller<MySql> {
         let client = state.kube.to_owned();
-        let secret = Api::<Secret>::all(client.to_owned());
-
-        Controller::new(Api::all(client), watcher::Config::default())
-            .owns(secret, watcher::Config::default())
+        let secret = k8s::api::<Secret>(client.to_owned(), namespace);
+        let job = k8s::api::<Job>(client.to_owned(), namespace);
+
+        Controller::new(
+            k8s::api(client, namespace),
+            k8s::watcher_config(&state.config),
+        )
+        .owns(secret, watcher::Config::default())
+        .owns(job, watcher::Config::default())
+        // reconcile the custom resource once a copy of its secret exported to
+        // another namespace is modified or deleted
+        .watches(
+            k8s::api::<Secret>(state.kube.to_owned(), namespace),
+            watcher::Config::default().labels(export::SOURCE_LABEL),
+            export::source::<MySql>,
+        )
     }
 }
 
@@ -281,11 +605,32 @@ impl ControllerBuilder<MySql> for Reconciler {
 impl k8s::Reconciler<MySql> for Reconciler {
     type Error = ReconcilerError;
 
+    fn permanent(err: &ReconcilerError) -> Option<&'static str> {
+        match err {
+            ReconcilerError::CleverClient(err) => err.permanent(),
+            ReconcilerError::DependsOn(err) => err.permanent(),
+            ReconcilerError::Restore(restore::Error::NotFound(_, _)) => Some("BackupNotFound"),
+            _ => None,
+        }
+    }
+
+    fn requeue(obj: &MySql) -> Option<Duration> {
+        migration::requeue(
+            obj.status
+                .as_ref()
+                .and_then(|status| status.migration.as_ref()),
+        )
+    }
+
     async fn upsert(ctx: Arc<Context>, origin: Arc<MySql>) -> Result<(), ReconcilerError> {
         let Context {
             kube,
             apis,
-            config: _,
+            backend,
+            config,
+            plans,
+            secrets,
+            clients,
         } = ctx.as_ref();
 
         let kind = MySql::kind(&()).to_string();
@@ -293,29 +638,35 @@ impl k8s::Reconciler<MySql> for Reconciler {
 
         // ---------------------------------------------------------------------
         // Step 0: verify if there is a clever cloud client override
+        let reference = origin.spec.credentials_secret_ref.as_ref();
+        let (secret_namespace, secret_name) = secret::credentials_reference(&namespace, reference);
+
         debug!(
-            namespace = namespace,
-            secret = OVERRIDE_CONFIGURATION_NAME,
+            namespace = &secret_namespace,
+            secret = &secret_name,
             "Try to retrieve the optional secret on namespace",
         );
 
-        let secret: Option<Secret> =
-            resource::get(kube.to_owned(), &namespace, OVERRIDE_CONFIGURATION_NAME).await?;
+        let secret: Option<Secret> = secret::credentials(secrets, &namespace, reference)
+            .await
+            .map_err(ReconcilerError::Credentials)?;
         let apis = match secret {
             Some(secret) => {
                 info!(
-                    namespace = namespace,
-                    secret = OVERRIDE_CONFIGURATION_NAME,
+                    namespace = &secret_namespace,
+                    secret = &secret_name,
                     "Use custom Clever Cloud client to connect the api using secret",
                 );
 
-                clevercloud::client::try_from(secret).await?
+                ScopedClient::scoped(clients.get(secret).await?, &kind)
             }
             None => {
                 info!("Use default Clever Cloud client to connect the api");
-                apis.to_owned()
+                ScopedClient::unscoped(apis.get())
             }
-        };
+        }
+        .with_rate_limit(&origin.spec.organisation, &config.api.rate_limit)
+        .with_backend(backend.to_owned());
 
         // ---------------------------------------------------------------------
         // Step 1: set finalizer
@@ -327,7 +678,7 @@ impl k8s::Reconciler<MySql> for Reconciler {
             "Set finalizer on custom resource",
         );
 
-        let modified = finalizer::add((*origin).to_owned(), ADDON_FINALIZER);
+        let patch = finalizer::add_patch(&*origin, ADDON_FINALIZER);
 
         debug!(
             kind = &kind,
@@ -336,15 +687,36 @@ impl k8s::Reconciler<MySql> for Reconciler {
             "Update information of custom resource",
         );
 
-        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
-        let mut modified = resource::patch(kube.to_owned(), &modified, patch).await?;
+        let mut modified = resource::patch(kube.to_owned(), &*origin, patch).await?;
 
         let action = &Action::UpsertFinalizer;
         let message = &format!("Create finalizer '{}'", ADDON_FINALIZER);
         recorder::normal(kube.to_owned(), &modified, action, message).await?;
 
+        // hold off provisioning until the custom resources this one depends on
+        // are ready
+        if let Err(err) =
+            dependency::all_ready(kube.to_owned(), &namespace, &modified.spec.depends_on).await
+        {
+            let current = modified.to_owned();
+            modified.set_condition(Condition::new(
+                condition::READY,
+                condition::Status::False,
+                condition::REASON_WAITING_FOR_DEPENDENCIES,
+                &err.to_string(),
+            ));
+
+            let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+            resource::patch_status(kube.to_owned(), modified, patch).await?;
+
+            return Err(ReconcilerError::DependsOn(err));
+        }
+
         // ---------------------------------------------------------------------
-        // Step 2: translate plan
+        // Step 2: translate plan, plans are resolved at admission by the
+        // mutating webhook, when it is enabled. Otherwise, the plan is only
+        // resolved in memory, the specification is never patched by the
+        // reconciler, as it leads to extra reconciliation loops
 
         if !modified.spec.instance.plan.starts_with("plan_") {
             info!(
@@ -355,58 +727,35 @@ impl k8s::Reconciler<MySql> for Reconciler {
                 "Resolve plan for resource'",
             );
 
-            let plan = plan::find(
-                &apis,
-                &AddonProviderId::MySql,
-                &modified.spec.organisation,
-                &modified.spec.instance.plan,
-            )
-            .await?;
-
-            // Update the spec is not a good practise as it lead to
-            // no-deterministic and infinite reconciliation loop. It should be
-            // avoided or done with caution.
-            if let Some(plan) = plan {
-                info!(
-                    kind = &kind,
-                    namespace = &namespace,
-                    name = &name,
-                    plan = &plan.id,
-                    "Override plan for custom resource",
-                );
-
-                let oplan = modified.spec.instance.plan.to_owned();
-                modified.spec.instance.plan = plan.id.to_owned();
-
-                debug!(
-                    kind = &kind,
-                    namespace = &namespace,
-                    name = &name,
-                    "Update information of custom resource",
-                );
-
-                let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
-                let modified =
-                    resource::patch(kube.to_owned(), &modified, patch.to_owned()).await?;
-
-                let action = &Action::OverridesInstancePlan;
-                let message = &format!("Overrides instance plan from '{}' to '{}'", oplan, plan.id);
-
-                info!(
-                    action = action.to_string(),
-                    kind = &kind,
-                    namespace = &namespace,
-                    name = &name,
-                    message = message,
-                    "Create event for custom resource",
-                );
-
-                recorder::normal(kube.to_owned(), &modified, action, message).await?;
+            let plan = plans
+                .find(
+                    apis.backend(&kind, "plan::find").await?,
+                    &AddonProviderId::MySql,
+                    &modified.spec.organisation,
+                    &modified.spec.instance.plan,
+                )
+                .await?;
+
+            match plan {
+                Some(plan) => {
+                    debug!(
+                        kind = &kind,
+                        namespace = &namespace,
+                        name = &name,
+                        plan = &plan.id,
+                        "Use resolved plan for custom resource",
+                    );
+
+                    modified.spec.instance.plan = plan.id;
+                }
+                None => {
+                    return Err(clevercloud::Error::UnknownPlan(
+                        modified.spec.instance.plan.to_owned(),
+                        modified.spec.organisation.to_owned(),
+                    )
+                    .into());
+                }
             }
-
-            // Stop reconciliation here and wait for next iteration, already
-            // triggered by the above patch request
-            return Ok(());
         }
 
         // ---------------------------------------------------------------------
@@ -419,9 +768,83 @@ impl k8s::Reconciler<MySql> for Reconciler {
             "Upsert addon for custom resource",
         );
 
-        let addon = modified.upsert(&apis).await?;
+        let options = match secret::resolve(secrets, &namespace, &modified.spec.options_from).await
+        {
+            Ok(options) => options,
+            Err(err) => {
+                let current = modified.to_owned();
+                modified.set_condition(Condition::new(
+                    condition::READY,
+                    condition::Status::False,
+                    condition::REASON_WAITING_FOR_DEPENDENCIES,
+                    &err.to_string(),
+                ));
+
+                let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+                resource::patch_status(kube.to_owned(), modified, patch).await?;
+
+                return Err(ReconcilerError::OptionsFrom(err));
+            }
+        };
+
+        // verify that the options are offered by the addon provider before
+        // creating the addon, so an unavailable pairing is reported precisely
+        // rather than through the opaque error of the api
+        if AddonExt::id(&modified).is_none() {
+            let mut opts: addon::Opts = modified.spec.options.to_owned().into();
+            ext::merge(&mut opts, &options);
+
+            provider::validate(
+                apis.authorize(&kind, "provider::get").await?,
+                &config.api.endpoint,
+                &AddonProviderId::MySql,
+                &opts,
+                &modified.spec.instance.region,
+            )
+            .await?;
+        }
+
+        let current = modified.to_owned();
+        let mut addon = modified.upsert_with_options(&apis, &options).await?;
+        let mut drift = drift::region(&addon, &modified.spec.instance.region);
+
+        if drift.is_some() && drift::recreate(&modified) {
+            warn!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                addon = &addon.id,
+                "Recreate addon for custom resource, as its region has changed",
+            );
+
+            drift::recreating(kube.to_owned(), &modified, &addon).await?;
+            modified.delete(&apis).await?;
+            modified.set_addon_id(None);
+            modified.set_migration(None);
+
+            addon = modified.upsert_with_options(&apis, &options).await?;
+            drift = None;
+        }
 
         modified.set_addon_id(Some(addon.id.to_owned()));
+        naming::sync(&apis, config, &modified, &addon).await;
+        tags::sync(&apis, config, &modified, &addon).await;
+
+        let current_billing = modified.status.as_ref().and_then(|s| s.billing.as_ref());
+        let billing = billing::refresh(&apis, config, &modified, &addon, current_billing).await;
+        modified.set_billing(billing);
+
+        #[cfg(feature = "metrics")]
+        stats::track(&apis, config, &modified, &addon).await;
+
+        modified.set_plan(Some(addon.plan.slug.to_owned()));
+        modified.set_condition(Condition::new(
+            condition::READY,
+            condition::Status::True,
+            "AddonProvisioned",
+            &format!("Addon '{}' is provisioned on clever-cloud", addon.id),
+        ));
+        let drifted = modified.set_condition(drift::condition(drift.as_deref()));
 
         debug!(
             kind = &kind,
@@ -430,7 +853,7 @@ impl k8s::Reconciler<MySql> for Reconciler {
             "Update information and status of custom resource",
         );
 
-        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
+        let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
         let modified = resource::patch(kube.to_owned(), &modified, patch.to_owned())
             .and_then(|modified| resource::patch_status(kube.to_owned(), modified, patch))
             .await?;
@@ -443,12 +866,161 @@ impl k8s::Reconciler<MySql> for Reconciler {
 
         recorder::normal(kube.to_owned(), &modified, action, message).await?;
 
+        if let Some(message) = drift.as_ref().filter(|_| drifted) {
+            drift::detected(kube.to_owned(), &modified, message).await?;
+        }
+
+        // ---------------------------------------------------------------------
+        // Step 4: migrate the addon to the plan and version of the
+        // specification, the addon is left untouched by the upsert, once it
+        // exists. The version of an addon is not returned by the api, so the
+        // applied one is tracked in the status, from the creation of the addon
+
+        let current = modified
+            .status
+            .as_ref()
+            .and_then(|status| status.migration.to_owned());
+
+        let desired = modified.spec.options.version.to_string();
+        let applied = modified
+            .status
+            .as_ref()
+            .and_then(|status| status.version.to_owned());
+
+        let version = applied
+            .as_deref()
+            .filter(|applied| migration::newer(&desired, applied))
+            .map(|_| desired.as_str());
+
+        let state = migration::reconcile(
+            apis.authorize(&kind, "migration").await?,
+            &config.api.endpoint,
+            &modified.spec.organisation,
+            &addon,
+            &modified.spec.instance.plan,
+            version,
+            current.as_ref(),
+        )
+        .await?;
+
+        let mut updated = modified.to_owned();
+        if applied.is_none() {
+            updated.set_version(Some(desired.to_owned()));
+        }
+
+        let modified = match state {
+            Some(state) if Some(&state) != current.as_ref() => {
+                info!(
+                    kind = &kind,
+                    namespace = &namespace,
+                    name = &name,
+                    plan = &state.plan,
+                    version = &state.version,
+                    phase = &state.phase,
+                    "Update migration of the addon for custom resource",
+                );
+
+                let (status, reason, message) = match state.phase.as_str() {
+                    migration::PHASE_SUCCEEDED => (
+                        condition::Status::True,
+                        "AddonMigrated",
+                        format!("Addon '{}' is migrated to {}", addon.id, state.target()),
+                    ),
+                    migration::PHASE_FAILED => (
+                        condition::Status::False,
+                        "MigrationFailed",
+                        format!(
+                            "Failed to migrate addon '{}' to {}, see migration '{}'",
+                            addon.id,
+                            state.target(),
+                            state.migration_id
+                        ),
+                    ),
+                    _ => (
+                        condition::Status::False,
+                        "MigrationRunning",
+                        format!(
+                            "Addon '{}' is being migrated to {} by migration '{}'",
+                            addon.id,
+                            state.target(),
+                            state.migration_id
+                        ),
+                    ),
+                };
+
+                if state.phase == migration::PHASE_SUCCEEDED && state.version.is_some() {
+                    updated.set_version(state.version.to_owned());
+                }
+
+                updated.set_migration(Some(state.to_owned()));
+                updated.set_condition(Condition::new(
+                    condition::MIGRATED,
+                    status,
+                    reason,
+                    &message,
+                ));
+
+                let patch = resource::diff(&modified, &updated).map_err(ReconcilerError::Diff)?;
+                let modified = resource::patch_status(kube.to_owned(), updated, patch).await?;
+
+                let action = &Action::MigrateAddon;
+                if state.phase == migration::PHASE_FAILED {
+                    recorder::warning(kube.to_owned(), &modified, action, &message).await?;
+                } else {
+                    recorder::normal(kube.to_owned(), &modified, action, &message).await?;
+                }
+
+                // the state of a migration only changes to running once it
+                // has been started to the plan of the specification
+                if state.phase == migration::PHASE_RUNNING {
+                    notification::notify(
+                        NotificationEvent::PlanOverride,
+                        &kind,
+                        &namespace,
+                        &name,
+                        Some(&addon.id),
+                        &message,
+                    );
+                }
+
+                modified
+            }
+            _ if updated != modified => {
+                let patch = resource::diff(&modified, &updated).map_err(ReconcilerError::Diff)?;
+                resource::patch_status(kube.to_owned(), updated, patch).await?
+            }
+            _ => modified,
+        };
+
         // ---------------------------------------------------------------------
-        // Step 4: create the secret
+        // Step 5: create the secret
 
         let secrets = modified.secrets(&apis).await?;
-        if let Some(secrets) = secrets {
-            let s = secret::new(&modified, secrets);
+        if let Some(mut secrets) = secrets {
+            let (s, excluded) = secret::new(
+                &modified,
+                &mut secrets,
+                modified.spec.exposure.as_ref(),
+                &config.operator.secret.exclude_keys,
+                modified.spec.secret_template.as_ref(),
+            );
+
+            let mut updated = modified.to_owned();
+            updated.set_excluded_keys(excluded);
+            updated.set_binding(Some(binding::reference(&updated)));
+            let endpoint = binding::endpoint(&secrets, &BINDING);
+            updated.set_endpoint(endpoint.to_owned());
+
+            let patch = resource::diff(&modified, &updated).map_err(ReconcilerError::Diff)?;
+            let modified = resource::patch_status(kube.to_owned(), updated, patch).await?;
+
+            let b = binding::new(
+                &modified,
+                &secrets,
+                &BINDING,
+                modified.spec.service_binding.as_ref(),
+            );
+            let s = export::annotate(&modified, s, &modified.spec.export_to);
             let (s_ns, s_name) = resource::namespaced_name(&s);
 
             info!(
@@ -464,11 +1036,162 @@ impl k8s::Reconciler<MySql> for Reconciler {
                 "Upsert kubernetes secret",
             );
 
+            let current: Option<Secret> = resource::get(kube.to_owned(), &s_ns, &s_name).await?;
             let secret = resource::upsert(kube.to_owned(), &s, false).await?;
+            if let Some(message) = current
+                .as_ref()
+                .and_then(|current| drift::secret(current, &s))
+            {
+                drift::repaired(kube.to_owned(), &modified, &message).await?;
+            }
 
             let action = &Action::UpsertSecret;
             let message = &format!("Create kubernetes secret '{}'", secret.name_any());
             recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+            let exported =
+                export::sync(kube.to_owned(), &modified, &secret, current.as_ref()).await?;
+            if !exported.is_empty() {
+                let message = &format!(
+                    "Export kubernetes secret '{}' to namespaces '{}'",
+                    secret.name_any(),
+                    exported.join("', '")
+                );
+                recorder::normal(kube.to_owned(), &modified, action, message).await?;
+            }
+
+            info!(
+                namespace = &s_ns,
+                name = binding::name(&modified),
+                "Upsert kubernetes service binding secret",
+            );
+
+            let b = resource::upsert(kube.to_owned(), &b, false).await?;
+            let message = &format!(
+                "Create kubernetes service binding secret '{}'",
+                b.name_any()
+            );
+            recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+            let exposed = service::sync(
+                kube.to_owned(),
+                &modified,
+                modified.spec.service.as_ref(),
+                &endpoint,
+                BINDING.kind,
+            )
+            .await?;
+
+            if let Some(dns) = exposed {
+                let action = &Action::UpsertService;
+                let message = &format!("Expose addon as kubernetes service '{}'", dns);
+                recorder::normal(kube.to_owned(), &modified, action, message).await?;
+            }
+
+            let policy = network_policy::sync(
+                kube.to_owned(),
+                config,
+                &modified,
+                modified.spec.consumers.as_ref(),
+                &endpoint,
+            )
+            .await?;
+
+            if let Some(policy) = policy {
+                let action = &Action::UpsertNetworkPolicy;
+                let message = &format!(
+                    "Allow egress from consumers to addon using network policy '{}'",
+                    policy
+                );
+                recorder::normal(kube.to_owned(), &modified, action, message).await?;
+            }
+
+            let healthy = probe::sync(config, &modified, &Probe::Tcp, &endpoint).await;
+            if let Some(condition) = healthy {
+                let mut updated = modified.to_owned();
+                if updated.set_condition(condition.to_owned()) {
+                    let patch =
+                        resource::diff(&modified, &updated).map_err(ReconcilerError::Diff)?;
+                    let modified = resource::patch_status(kube.to_owned(), updated, patch).await?;
+
+                    let action = &Action::ProbeAddon;
+                    let message = &condition.message;
+                    if condition.is_true() {
+                        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+                    } else {
+                        recorder::warning(kube.to_owned(), &modified, action, message).await?;
+                    }
+                }
+            }
+        }
+
+        // ---------------------------------------------------------------------
+        // Step 6: restore the backup
+
+        if let Some(restore) = modified.spec.restore.to_owned() {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                "Restore backup on the addon for custom resource",
+            );
+
+            let client = apis.authorize(&kind, "backup::list").await?;
+            let organisation = modified.spec.organisation.to_owned();
+            let state = restore::reconcile(
+                ctx.as_ref(),
+                client,
+                &organisation,
+                &modified,
+                &restore,
+                Engine::MySql,
+            )
+            .await?;
+
+            let (status, reason, message) = match state.phase.as_str() {
+                restore::PHASE_SUCCEEDED => (
+                    condition::Status::True,
+                    "BackupRestored",
+                    format!("Backup '{}' is restored", state.backup_id),
+                ),
+                restore::PHASE_FAILED => (
+                    condition::Status::False,
+                    "RestoreFailed",
+                    format!(
+                        "Failed to restore backup '{}', see job '{}'",
+                        state.backup_id, state.job
+                    ),
+                ),
+                _ => (
+                    condition::Status::False,
+                    "RestoreRunning",
+                    format!(
+                        "Backup '{}' is being restored by job '{}'",
+                        state.backup_id, state.job
+                    ),
+                ),
+            };
+
+            let mut updated = modified.to_owned();
+            updated.set_restore(Some(state.to_owned()));
+            updated.set_condition(Condition::new(
+                condition::RESTORED,
+                status.to_owned(),
+                reason,
+                &message,
+            ));
+
+            if updated != modified {
+                let patch = resource::diff(&modified, &updated).map_err(ReconcilerError::Diff)?;
+                let modified = resource::patch_status(kube.to_owned(), updated, patch).await?;
+
+                let action = &Action::RestoreBackup;
+                if state.phase == restore::PHASE_FAILED {
+                    recorder::warning(kube.to_owned(), &modified, action, &message).await?;
+                } else {
+                    recorder::normal(kube.to_owned(), &modified, action, &message).await?;
+                }
+            }
         }
 
         Ok(())
@@ -477,8 +1200,12 @@ impl k8s::Reconciler<MySql> for Reconciler {
     async fn delete(ctx: Arc<Context>, origin: Arc<MySql>) -> Result<(), ReconcilerError> {
         let Context {
             apis,
+            backend,
             kube,
-            config: _,
+            config,
+            secrets,
+            clients,
+            ..
         } = ctx.as_ref();
         let mut modified = (*origin).to_owned();
         let kind = MySql::kind(&()).to_string();
@@ -486,42 +1213,90 @@ impl k8s::Reconciler<MySql> for Reconciler {
 
         // ---------------------------------------------------------------------
         // Step 0: verify if there is a clever cloud client override
+        let reference = origin.spec.credentials_secret_ref.as_ref();
+        let (secret_namespace, secret_name) = secret::credentials_reference(&namespace, reference);
+
         debug!(
-            namespace = namespace,
-            secret = OVERRIDE_CONFIGURATION_NAME,
+            namespace = &secret_namespace,
+            secret = &secret_name,
             "Try to retrieve the optional secret",
         );
 
-        let secret: Option<Secret> =
-            resource::get(kube.to_owned(), &namespace, OVERRIDE_CONFIGURATION_NAME).await?;
+        let secret: Option<Secret> = secret::credentials(secrets, &namespace, reference)
+            .await
+            .map_err(ReconcilerError::Credentials)?;
 
         let apis = match secret {
             Some(secret) => {
                 info!(
-                    namespace = namespace,
-                    secret = OVERRIDE_CONFIGURATION_NAME,
+                    namespace = &secret_namespace,
+                    secret = &secret_name,
                     "Use custom Clever Cloud client to connect the api using secret",
                 );
 
-                clevercloud::client::try_from(secret).await?
+                ScopedClient::scoped(clients.get(secret).await?, &kind)
             }
             None => {
                 info!("Use default Clever Cloud client to connect the api");
-                apis.to_owned()
+                ScopedClient::unscoped(apis.get())
             }
-        };
+        }
+        .with_rate_limit(&origin.spec.organisation, &config.api.rate_limit)
+        .with_backend(backend.to_owned());
 
         // ---------------------------------------------------------------------
-        // Step 1: delete the addon
+        // Step 1: delete the addon, unless its deletion policy keeps it
 
-        info!(
-            kind = &kind,
-            namespace = &namespace,
-            name = &name,
-            "Delete addon for custom resource",
-        );
+        let policy = modified.spec.deletion_policy;
+        if policy.orphans_secrets() || !config.operator.secret.cascade_deletion {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                "Release kubernetes secrets of custom resource",
+            );
+
+            secret::release(kube.to_owned(), &modified).await?;
+        } else {
+            // copies exported to other namespaces are not owned by the custom
+            // resource, so they are not garbage collected along it
+            export::prune(kube.to_owned(), &modified).await?;
+        }
+
+        let (action, message) = if policy.deletes_addon() {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                "Delete addon for custom resource",
+            );
+
+            modified.delete(&apis).await?;
+            (
+                &Action::DeleteAddon,
+                "Delete managed mysql instance on clever-cloud".to_string(),
+            )
+        } else {
+            let addon = modified.get_addon_id().unwrap_or_default();
+
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                addon = &addon,
+                policy = policy.to_string(),
+                "Keep addon for custom resource, as requested by its deletion policy",
+            );
+
+            (
+                &Action::KeepAddon,
+                format!(
+                    "Keep addon '{}' on clever-cloud, as requested by the '{}' deletion policy",
+                    addon, policy
+                ),
+            )
+        };
 
-        modified.delete(&apis).await?;
         modified.set_addon_id(None);
 
         debug!(
@@ -536,9 +1311,7 @@ impl k8s::Reconciler<MySql> for Reconciler {
             .and_then(|modified| resource::patch_status(kube.to_owned(), modified, patch))
             .await?;
 
-        let action = &Action::DeleteAddon;
-        let message = "Delete managed mysql instance on clever-cloud";
-        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+        recorder::normal(kube.to_owned(), &modified, action, &message).await?;
 
         // ---------------------------------------------------------------------
         // Step 2: remove the finalizer
@@ -550,7 +1323,7 @@ impl k8s::Reconciler<MySql> for Reconciler {
             "Remove finalizer on custom resource",
         );
 
-        let modified = finalizer::remove(modified, ADDON_FINALIZER);
+        let patch = finalizer::remove_patch(&modified, ADDON_FINALIZER);
 
         let action = &Action::DeleteFinalizer;
         let message = "Delete finalizer from custom resource";
@@ -563,8 +1336,7 @@ impl k8s::Reconciler<MySql> for Reconciler {
             "Update information of custom resource",
         );
 
-        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
-        resource::patch(kube.to_owned(), &modified, patch.to_owned()).await?;
+        resource::patch(kube.to_owned(), &modified, patch).await?;
 
         Ok(())
     }
@@ -5,9 +5,11 @@
 use std::{
     fmt::{self, Display, Formatter},
     sync::Arc,
+    time::Duration,
 };
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use clevercloud_sdk::{
     v2::{
         self,
@@ -19,18 +21,32 @@ use futures::TryFutureExt;
 use k8s_openapi::api::core::v1::Secret;
 use kube::{
     runtime::{controller, watcher, Controller},
-    Api, CustomResource, Resource, ResourceExt,
+    CustomResource, Resource, ResourceExt,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info};
 
+#[cfg(feature = "metrics")]
+use crate::svc::k8s::stats;
 use crate::svc::{
-    clevercloud::{self, ext::AddonExt},
+    clevercloud::{
+        self, biscuit,
+        client::ScopedClient,
+        ext::AddonExt,
+        pulsar::{self as admin, Credentials},
+    },
+    crd::{
+        self, Billing, Binding, CredentialsSecretReference, DeletionPolicy, Dependency, Exposure,
+        SecretTemplate, ServiceBinding,
+    },
     k8s::{
-        self, finalizer, recorder, resource,
-        secret::{self, OVERRIDE_CONFIGURATION_NAME},
-        Context, ControllerBuilder,
+        self, billing,
+        binding::{self, Mapping},
+        condition::{self, Condition, Conditions},
+        dependency, drift, export, finalizer, naming,
+        rbac::{self, Permission},
+        recorder, resource, rotation, secret, tags, Context, ControllerBuilder,
     },
 };
 
@@ -38,8 +54,21 @@ use crate::svc::{
 // Constants
 
 pub const ADDON_FINALIZER: &str = "api.clever-cloud.com/pulsar";
+
+/// well-known entries of the service binding specification
+pub const BINDING: Mapping = Mapping {
+    kind: "pulsar",
+    keys: &[
+        ("uri", "ADDON_PULSAR_BINARY_URL"),
+        ("http-uri", "ADDON_PULSAR_HTTP_URL"),
+        ("token", "ADDON_PULSAR_TOKEN"),
+    ],
+};
 pub const ADDON_BETA_PLAN: &str = "plan_3ad3c5be-5c1e-4dae-bf9a-87120b88fc13";
 
+/// kubernetes resources touched by the reconciler beside its custom resources
+pub const PERMISSIONS: &[Permission] = &[rbac::SECRETS];
+
 // -----------------------------------------------------------------------------
 // Instance structure
 
@@ -49,12 +78,43 @@ pub struct Instance {
     pub region: String,
 }
 
+// -----------------------------------------------------------------------------
+// Topic structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Topic {
+    #[serde(rename = "name")]
+    pub name: String,
+    /// namespace of the topic in the tenant of the addon, defaults to the
+    /// namespace of the addon
+    #[serde(rename = "namespace", default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// number of partitions of the topic, the topic is not partitioned if
+    /// omitted
+    #[serde(
+        rename = "partitions",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub partitions: Option<u32>,
+}
+
+// -----------------------------------------------------------------------------
+// TokenRotation structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct TokenRotation {
+    /// delay in seconds between two rotations of the token of the addon
+    #[serde(rename = "interval")]
+    pub interval: u64,
+}
+
 // -----------------------------------------------------------------------------
 // Spec structure
 
 #[derive(CustomResource, JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
 #[kube(group = "api.clever-cloud.com")]
-#[kube(version = "v1beta1")]
+#[kube(version = "v1")]
 #[kube(kind = "Pulsar")]
 #[kube(singular = "pulsar")]
 #[kube(plural = "pulsars")]
@@ -72,11 +132,84 @@ pub struct Instance {
 #[kube(
     printcolumn = r#"{"name":"region", "type":"string", "description":"Region", "jsonPath":".spec.instance.region"}"#
 )]
+#[kube(
+    printcolumn = r#"{"name":"plan", "type":"string", "description":"Plan", "jsonPath":".status.plan"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"ready", "type":"string", "description":"Ready", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"phase", "type":"string", "description":"Reason of the ready condition", "jsonPath":".status.conditions[?(@.type==\"Ready\")].reason"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"age", "type":"date", "description":"Age", "jsonPath":".metadata.creationTimestamp"}"#
+)]
 pub struct Spec {
     #[serde(rename = "organisation")]
     pub organisation: String,
+    /// secret holding the configuration of the clever cloud client used to
+    /// reconcile the custom resource, the `clever-operator` secret of the
+    /// namespace is used if not set
+    #[serde(
+        rename = "credentialsSecretRef",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub credentials_secret_ref: Option<CredentialsSecretReference>,
     #[serde(rename = "instance")]
     pub instance: Instance,
+    #[serde(rename = "exposure", default, skip_serializing_if = "Option::is_none")]
+    pub exposure: Option<Exposure>,
+    #[serde(
+        rename = "secretTemplate",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub secret_template: Option<SecretTemplate>,
+    /// namespaces in which the generated secret is replicated, so workloads
+    /// of other namespaces could consume it
+    #[serde(rename = "exportTo", default, skip_serializing_if = "Vec::is_empty")]
+    pub export_to: Vec<String>,
+    /// options of the secret following the service binding specification
+    #[serde(
+        rename = "serviceBinding",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub service_binding: Option<ServiceBinding>,
+    /// custom resources which must be ready before provisioning this one
+    #[serde(rename = "dependsOn", default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<Dependency>,
+    /// whether the addon and the generated secrets are deleted along the
+    /// custom resource, one of `Delete` (default), `Orphan` or `Retain`
+    #[serde(rename = "deletionPolicy", default)]
+    pub deletion_policy: DeletionPolicy,
+    /// namespaces to create in the tenant of the addon
+    #[serde(rename = "namespaces", default, skip_serializing_if = "Vec::is_empty")]
+    pub namespaces: Vec<String>,
+    /// persistent topics to create in the tenant of the addon
+    #[serde(rename = "topics", default, skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<Topic>,
+    /// periodic rotation of the token of the addon, the token is never
+    /// rotated if omitted
+    #[serde(
+        rename = "tokenRotation",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub token_rotation: Option<TokenRotation>,
+    /// name of the addon in the Clever Cloud console, the naming template of
+    /// the operator is used if not set
+    #[serde(
+        rename = "displayName",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub display_name: Option<String>,
+    /// suspend the reconciliation of the custom resource, e.g. during a
+    /// manual maintenance on Clever Cloud, its deletion is still processed
+    #[serde(rename = "suspend", default)]
+    pub suspend: bool,
 }
 
 // -----------------------------------------------------------------------------
@@ -86,6 +219,42 @@ pub struct Spec {
 pub struct Status {
     #[serde(rename = "addon")]
     pub addon: Option<String>,
+    /// slug of the plan of the addon, e.g. `xs_sml`
+    #[serde(rename = "plan", default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<String>,
+    #[serde(rename = "conditions", default)]
+    pub conditions: Vec<Condition>,
+    /// generation of the specification processed by the last successful
+    /// reconciliation
+    #[serde(
+        rename = "observedGeneration",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub observed_generation: Option<i64>,
+    #[serde(
+        rename = "excludedKeys",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub excluded_keys: Vec<String>,
+    /// reference to the secret following the service binding specification
+    #[serde(rename = "binding", default, skip_serializing_if = "Option::is_none")]
+    pub binding: Option<Binding>,
+    /// price of the plan and consumption of the addon, if billing is enabled
+    #[serde(rename = "billing", default, skip_serializing_if = "Option::is_none")]
+    pub billing: Option<Billing>,
+    #[serde(rename = "namespaces", default, skip_serializing_if = "Vec::is_empty")]
+    pub namespaces: Vec<String>,
+    #[serde(rename = "topics", default, skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<String>,
+    /// time of the last rotation of the token of the addon, if any
+    #[serde(
+        rename = "lastTokenRotationTimestamp",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_token_rotation_timestamp: Option<String>,
 }
 
 // -----------------------------------------------------------------------------
@@ -96,7 +265,7 @@ impl Into<CreateOpts> for Pulsar {
     #[cfg_attr(feature = "trace", tracing::instrument)]
     fn into(self) -> CreateOpts {
         CreateOpts {
-            name: AddonExt::name(&self),
+            name: AddonExt::addon_name(&self),
             region: self.spec.instance.region.to_owned(),
             provider_id: AddonProviderId::Pulsar.to_string(),
             plan: ADDON_BETA_PLAN.to_string(),
@@ -109,12 +278,16 @@ impl AddonExt for Pulsar {
     type Error = ReconcilerError;
 
     #[cfg_attr(feature = "trace", tracing::instrument)]
-    fn id(&self) -> Option<String> {
-        if let Some(status) = &self.status {
-            return status.addon.to_owned();
-        }
+    fn scope() -> String {
+        Self::kind(&()).to_string()
+    }
 
-        None
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn id(&self) -> Option<String> {
+        self.status
+            .as_ref()
+            .and_then(|status| status.addon.to_owned())
+            .or_else(|| crd::adopted(self))
     }
 
     #[cfg_attr(feature = "trace", tracing::instrument)]
@@ -122,6 +295,16 @@ impl AddonExt for Pulsar {
         self.spec.organisation.to_owned()
     }
 
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn credentials_secret_ref(&self) -> Option<&CredentialsSecretReference> {
+        self.spec.credentials_secret_ref.as_ref()
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn display_name(&self) -> Option<String> {
+        self.spec.display_name.to_owned()
+    }
+
     #[cfg_attr(feature = "trace", tracing::instrument)]
     fn name(&self) -> String {
         let delimiter = Self::delimiter();
@@ -149,6 +332,86 @@ impl Pulsar {
     pub fn get_addon_id(&self) -> Option<String> {
         self.status.to_owned().unwrap_or_default().addon
     }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_plan(&mut self, plan: Option<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.plan = plan;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_excluded_keys(&mut self, keys: Vec<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.excluded_keys = keys;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_binding(&mut self, binding: Option<Binding>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.binding = binding;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_billing(&mut self, billing: Option<Billing>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.billing = billing;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_namespaces(&mut self, namespaces: Vec<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.namespaces = namespaces;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_topics(&mut self, topics: Vec<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.topics = topics;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_last_token_rotation_timestamp(&mut self, timestamp: Option<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.last_token_rotation_timestamp = timestamp;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the delay before the next rotation of the token, which is zero
+    /// once it is due, or none if the token is not periodically rotated. The
+    /// token of an addon which has never been rotated is as old as the custom
+    /// resource
+    pub fn next_token_rotation(&self) -> Option<Duration> {
+        let interval = self.spec.token_rotation.as_ref()?.interval;
+        let last_rotation = self
+            .status
+            .as_ref()
+            .and_then(|status| status.last_token_rotation_timestamp.as_deref())
+            .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+            .map(|timestamp| timestamp.with_timezone(&Utc))
+            .or_else(|| self.metadata.creation_timestamp.as_ref().map(|time| time.0))
+            .unwrap_or_else(Utc::now);
+
+        let elapsed = Utc::now()
+            .signed_duration_since(last_rotation)
+            .num_seconds()
+            .max(0) as u64;
+
+        Some(Duration::from_secs(interval.saturating_sub(elapsed)))
+    }
+}
+
+impl Conditions for Pulsar {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn conditions_mut(&mut self) -> &mut Vec<Condition> {
+        &mut self.status.get_or_insert_with(Status::default).conditions
+    }
 }
 
 // -----------------------------------------------------------------------------
@@ -159,8 +422,11 @@ pub enum Action {
     UpsertFinalizer,
     UpsertAddon,
     UpsertSecret,
+    UpsertTopics,
+    RotateToken,
     DeleteFinalizer,
     DeleteAddon,
+    KeepAddon,
 }
 
 impl Display for Action {
@@ -169,8 +435,11 @@ impl Display for Action {
             Self::UpsertFinalizer => write!(f, "UpsertFinalizer"),
             Self::UpsertAddon => write!(f, "UpsertAddon"),
             Self::UpsertSecret => write!(f, "UpsertSecret"),
+            Self::UpsertTopics => write!(f, "UpsertTopics"),
+            Self::RotateToken => write!(f, "RotateToken"),
             Self::DeleteFinalizer => write!(f, "DeleteFinalizer"),
             Self::DeleteAddon => write!(f, "DeleteAddon"),
+            Self::KeepAddon => write!(f, "KeepAddon"),
         }
     }
 }
@@ -186,10 +455,18 @@ pub enum ReconcilerError {
     CleverClient(clevercloud::Error),
     #[error("failed to create clevercloud client, {0}")]
     CreateCleverClient(clevercloud::client::Error),
+    #[error("failed to retrieve credentials secret, {0}")]
+    Credentials(secret::Error),
     #[error("failed to execute request on kubernetes api, {0}")]
     KubeClient(kube::Error),
     #[error("failed to compute diff between the original and modified object, {0}")]
     Diff(serde_json::Error),
+    #[error("failed to authorize call on clever-cloud api, {0}")]
+    Scope(clevercloud::client::ScopeError),
+    #[error("failed to wait for dependencies, {0}")]
+    DependsOn(dependency::Error),
+    #[error("failed to rotate token of addon, {0}")]
+    RotateToken(biscuit::Error),
 }
 
 impl From<kube::Error> for ReconcilerError {
@@ -206,6 +483,13 @@ impl From<clevercloud::Error> for ReconcilerError {
     }
 }
 
+impl From<admin::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: admin::Error) -> Self {
+        Self::from(clevercloud::Error::from(err))
+    }
+}
+
 impl From<v2::addon::Error> for ReconcilerError {
     #[cfg_attr(feature = "trace", tracing::instrument)]
     fn from(err: v2::addon::Error) -> Self {
@@ -227,6 +511,13 @@ impl From<controller::Error<Self, watcher::Error>> for ReconcilerError {
     }
 }
 
+impl From<clevercloud::client::ScopeError> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: clevercloud::client::ScopeError) -> Self {
+        Self::Scope(err)
+    }
+}
+
 impl From<clevercloud::client::Error> for ReconcilerError {
     fn from(err: clevercloud::client::Error) -> Self {
         Self::CreateCleverClient(err)
@@ -240,12 +531,22 @@ impl From<clevercloud::client::Error> for ReconcilerError {
 pub struct Reconciler {}
 
 impl ControllerBuilder<Pulsar> for Reconciler {
-    fn build(&self, state: Arc<Context>) -> Controller<Pulsar> {
+    fn build(&self, state: Arc<Context>, namespace: Option<&str>) -> Controller<Pulsar> {
         let client = state.kube.to_owned();
-        let secret = Api::<Secret>::all(client.to_owned());
-
-        Controller::new(Api::all(client), watcher::Config::default())
-            .owns(secret, watcher::Config::default())
+        let secret = k8s::api::<Secret>(client.to_owned(), namespace);
+
+        Controller::new(
+            k8s::api(client, namespace),
+            k8s::watcher_config(&state.config),
+        )
+        .owns(secret, watcher::Config::default())
+        // reconcile the custom resource once a copy of its secret exported to
+        // another namespace is modified or deleted
+        .watches(
+            k8s::api::<Secret>(state.kube.to_owned(), namespace),
+            watcher::Config::default().labels(export::SOURCE_LABEL),
+            export::source::<Pulsar>,
+        )
     }
 }
 
@@ -253,11 +554,28 @@ impl ControllerBuilder<Pulsar> for Reconciler {
 impl k8s::Reconciler<Pulsar> for Reconciler {
     type Error = ReconcilerError;
 
+    fn permanent(err: &ReconcilerError) -> Option<&'static str> {
+        match err {
+            ReconcilerError::CleverClient(err) => err.permanent(),
+            ReconcilerError::DependsOn(err) => err.permanent(),
+            _ => None,
+        }
+    }
+
+    fn requeue(obj: &Pulsar) -> Option<Duration> {
+        obj.next_token_rotation()
+            .map(|delay| delay.max(Duration::from_secs(1)))
+    }
+
     async fn upsert(ctx: Arc<Context>, origin: Arc<Pulsar>) -> Result<(), ReconcilerError> {
         let Context {
             kube,
             apis,
-            config: _,
+            backend,
+            config,
+            secrets,
+            clients,
+            ..
         } = ctx.as_ref();
 
         let kind = Pulsar::kind(&()).to_string();
@@ -265,29 +583,35 @@ impl k8s::Reconciler<Pulsar> for Reconciler {
 
         // ---------------------------------------------------------------------
         // Step 0: verify if there is a clever cloud client override
+        let reference = origin.spec.credentials_secret_ref.as_ref();
+        let (secret_namespace, secret_name) = secret::credentials_reference(&namespace, reference);
+
         debug!(
-            namespace = namespace,
-            secret = OVERRIDE_CONFIGURATION_NAME,
+            namespace = &secret_namespace,
+            secret = &secret_name,
             "Try to retrieve the optional secret on namespace",
         );
 
-        let secret: Option<Secret> =
-            resource::get(kube.to_owned(), &namespace, OVERRIDE_CONFIGURATION_NAME).await?;
+        let secret: Option<Secret> = secret::credentials(secrets, &namespace, reference)
+            .await
+            .map_err(ReconcilerError::Credentials)?;
         let apis = match secret {
             Some(secret) => {
                 info!(
-                    namespace = namespace,
-                    secret = OVERRIDE_CONFIGURATION_NAME,
+                    namespace = &secret_namespace,
+                    secret = &secret_name,
                     "Use custom Clever Cloud client to connect the api using secret",
                 );
 
-                clevercloud::client::try_from(secret).await?
+                ScopedClient::scoped(clients.get(secret).await?, &kind)
             }
             None => {
                 info!("Use default Clever Cloud client to connect the api");
-                apis.to_owned()
+                ScopedClient::unscoped(apis.get())
             }
-        };
+        }
+        .with_rate_limit(&origin.spec.organisation, &config.api.rate_limit)
+        .with_backend(backend.to_owned());
 
         // ---------------------------------------------------------------------
         // Step 1: set finalizer
@@ -299,7 +623,7 @@ impl k8s::Reconciler<Pulsar> for Reconciler {
             "Set finalizer on custom resource",
         );
 
-        let modified = finalizer::add((*origin).to_owned(), ADDON_FINALIZER);
+        let patch = finalizer::add_patch(&*origin, ADDON_FINALIZER);
 
         debug!(
             kind = &kind,
@@ -308,13 +632,31 @@ impl k8s::Reconciler<Pulsar> for Reconciler {
             "Update information of custom resource",
         );
 
-        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
-        let mut modified = resource::patch(kube.to_owned(), &modified, patch).await?;
+        let mut modified = resource::patch(kube.to_owned(), &*origin, patch).await?;
 
         let action = &Action::UpsertFinalizer;
         let message = &format!("Create finalizer '{}'", ADDON_FINALIZER);
         recorder::normal(kube.to_owned(), &modified, action, message).await?;
 
+        // hold off provisioning until the custom resources this one depends on
+        // are ready
+        if let Err(err) =
+            dependency::all_ready(kube.to_owned(), &namespace, &modified.spec.depends_on).await
+        {
+            let current = modified.to_owned();
+            modified.set_condition(Condition::new(
+                condition::READY,
+                condition::Status::False,
+                condition::REASON_WAITING_FOR_DEPENDENCIES,
+                &err.to_string(),
+            ));
+
+            let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+            resource::patch_status(kube.to_owned(), modified, patch).await?;
+
+            return Err(ReconcilerError::DependsOn(err));
+        }
+
         // ---------------------------------------------------------------------
         // Step 2:
 
@@ -332,7 +674,25 @@ impl k8s::Reconciler<Pulsar> for Reconciler {
 
         let addon = modified.upsert(&apis).await?;
 
+        let current = modified.to_owned();
         modified.set_addon_id(Some(addon.id.to_owned()));
+        naming::sync(&apis, config, &modified, &addon).await;
+        tags::sync(&apis, config, &modified, &addon).await;
+
+        let current_billing = modified.status.as_ref().and_then(|s| s.billing.as_ref());
+        let billing = billing::refresh(&apis, config, &modified, &addon, current_billing).await;
+        modified.set_billing(billing);
+
+        #[cfg(feature = "metrics")]
+        stats::track(&apis, config, &modified, &addon).await;
+
+        modified.set_plan(Some(addon.plan.slug.to_owned()));
+        modified.set_condition(Condition::new(
+            condition::READY,
+            condition::Status::True,
+            "AddonProvisioned",
+            &format!("Addon '{}' is provisioned on clever-cloud", addon.id),
+        ));
 
         debug!(
             kind = &kind,
@@ -341,7 +701,7 @@ impl k8s::Reconciler<Pulsar> for Reconciler {
             "Update information and status of custom resource",
         );
 
-        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
+        let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
         let modified = resource::patch(kube.to_owned(), &modified, patch.to_owned())
             .and_then(|modified| resource::patch_status(kube.to_owned(), modified, patch))
             .await?;
@@ -354,11 +714,79 @@ impl k8s::Reconciler<Pulsar> for Reconciler {
         recorder::normal(kube.to_owned(), &modified, action, message).await?;
 
         // ---------------------------------------------------------------------
-        // Step 4: create the secret
+        // Step 4: rotate the token, once it is due or once requested
+
+        let due = modified.next_token_rotation() == Some(Duration::ZERO);
+        let (modified, token) = if due || rotation::requested(&modified) {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                addon = &addon.real_id,
+                "Rotate token of the addon for custom resource",
+            );
+
+            let client = apis.authorize(&kind, "biscuit::rotate").await?;
+            let token = biscuit::rotate(
+                client,
+                &config.api.endpoint,
+                &modified.spec.organisation,
+                &addon.real_id,
+            )
+            .await
+            .map_err(ReconcilerError::RotateToken)?;
+
+            let mut updated = modified.to_owned();
+            updated.set_last_token_rotation_timestamp(Some(condition::now()));
+
+            let patch = resource::diff(&modified, &updated).map_err(ReconcilerError::Diff)?;
+            let updated = resource::patch_status(kube.to_owned(), updated, patch).await?;
+
+            let action = &Action::RotateToken;
+            let message = "Rotate token of the managed pulsar instance";
+            recorder::normal(kube.to_owned(), &updated, action, message).await?;
+
+            (updated, Some(token))
+        } else {
+            (modified, None)
+        };
+
+        // ---------------------------------------------------------------------
+        // Step 5: create the secret
+
+        let environment = modified.secrets(&apis).await?.map(|mut environment| {
+            // the rotated token is written right away, without waiting for
+            // the environment of the addon to expose it
+            if let Some(token) = &token {
+                environment.insert(admin::PULSAR_TOKEN.to_string(), (**token).to_owned());
+            }
+
+            environment
+        });
+
+        if let Some(mut secrets) = environment.to_owned() {
+            let (s, excluded) = secret::new(
+                &modified,
+                &mut secrets,
+                modified.spec.exposure.as_ref(),
+                &config.operator.secret.exclude_keys,
+                modified.spec.secret_template.as_ref(),
+            );
+
+            let mut updated = modified.to_owned();
+            updated.set_excluded_keys(excluded);
+            updated.set_binding(Some(binding::reference(&updated)));
 
-        let secrets = modified.secrets(&apis).await?;
-        if let Some(secrets) = secrets {
-            let s = secret::new(&modified, secrets);
+            let patch = resource::diff(&modified, &updated).map_err(ReconcilerError::Diff)?;
+            let modified = resource::patch_status(kube.to_owned(), updated, patch).await?;
+
+            let b = binding::new(
+                &modified,
+                &secrets,
+                &BINDING,
+                modified.spec.service_binding.as_ref(),
+            );
+            let s = export::annotate(&modified, s, &modified.spec.export_to);
             let (s_ns, s_name) = resource::namespaced_name(&s);
 
             info!(
@@ -374,11 +802,114 @@ impl k8s::Reconciler<Pulsar> for Reconciler {
                 "Upsert kubernetes secret",
             );
 
+            let current: Option<Secret> = resource::get(kube.to_owned(), &s_ns, &s_name).await?;
             let secret = resource::upsert(kube.to_owned(), &s, false).await?;
+            if let Some(message) = current
+                .as_ref()
+                .and_then(|current| drift::secret(current, &s))
+            {
+                drift::repaired(kube.to_owned(), &modified, &message).await?;
+            }
 
             let action = &Action::UpsertSecret;
             let message = &format!("Create kubernetes secret '{}'", secret.name_any());
             recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+            let exported =
+                export::sync(kube.to_owned(), &modified, &secret, current.as_ref()).await?;
+            if !exported.is_empty() {
+                let message = &format!(
+                    "Export kubernetes secret '{}' to namespaces '{}'",
+                    secret.name_any(),
+                    exported.join("', '")
+                );
+                recorder::normal(kube.to_owned(), &modified, action, message).await?;
+            }
+
+            info!(
+                namespace = &s_ns,
+                name = binding::name(&modified),
+                "Upsert kubernetes service binding secret",
+            );
+
+            let b = resource::upsert(kube.to_owned(), &b, false).await?;
+            let message = &format!(
+                "Create kubernetes service binding secret '{}'",
+                b.name_any()
+            );
+            recorder::normal(kube.to_owned(), &modified, action, message).await?;
+        }
+
+        // deployments are rolled out once reconciliation succeeds, if the
+        // rotation has been requested using the annotation
+        if due && !rotation::requested(&modified) {
+            let deployments = rotation::rollout(kube.to_owned(), &modified).await?;
+            if !deployments.is_empty() {
+                let action = &Action::RotateToken;
+                let message = &format!(
+                    "Roll out deployments '{}' consuming the rotated token",
+                    deployments.join("', '")
+                );
+                recorder::normal(kube.to_owned(), &modified, action, message).await?;
+            }
+        }
+
+        // ---------------------------------------------------------------------
+        // Step 6: create namespaces and topics
+
+        let environment = match environment {
+            Some(environment)
+                if !modified.spec.namespaces.is_empty() || !modified.spec.topics.is_empty() =>
+            {
+                environment
+            }
+            _ => return Ok(()),
+        };
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Upsert namespaces and topics of the addon for custom resource",
+        );
+
+        let client = admin::Client::try_new(
+            &config.api,
+            &config.proxy,
+            Credentials::try_from(&environment)?,
+        )?;
+
+        let tenant = client.credentials().tenant.to_owned();
+        let mut namespaces = vec![];
+        for ns in &modified.spec.namespaces {
+            client.create_namespace(ns).await?;
+            namespaces.push(format!("{}/{}", tenant, ns));
+        }
+
+        let mut topics = vec![];
+        for topic in &modified.spec.topics {
+            let ns = topic
+                .namespace
+                .to_owned()
+                .unwrap_or_else(|| client.credentials().namespace.to_owned());
+
+            client
+                .create_topic(&ns, &topic.name, topic.partitions)
+                .await?;
+            topics.push(format!("persistent://{}/{}/{}", tenant, ns, topic.name));
+        }
+
+        let mut updated = modified.to_owned();
+        updated.set_namespaces(namespaces);
+        updated.set_topics(topics);
+
+        if updated != modified {
+            let patch = resource::diff(&modified, &updated).map_err(ReconcilerError::Diff)?;
+            let modified = resource::patch_status(kube.to_owned(), updated, patch).await?;
+
+            let action = &Action::UpsertTopics;
+            let message = "Create namespaces and topics of the managed pulsar instance";
+            recorder::normal(kube.to_owned(), &modified, action, message).await?;
         }
 
         Ok(())
@@ -387,8 +918,12 @@ impl k8s::Reconciler<Pulsar> for Reconciler {
     async fn delete(ctx: Arc<Context>, origin: Arc<Pulsar>) -> Result<(), ReconcilerError> {
         let Context {
             apis,
+            backend,
             kube,
-            config: _,
+            config,
+            secrets,
+            clients,
+            ..
         } = ctx.as_ref();
 
         let mut modified = (*origin).to_owned();
@@ -397,42 +932,90 @@ impl k8s::Reconciler<Pulsar> for Reconciler {
 
         // ---------------------------------------------------------------------
         // Step 0: verify if there is a clever cloud client override
+        let reference = origin.spec.credentials_secret_ref.as_ref();
+        let (secret_namespace, secret_name) = secret::credentials_reference(&namespace, reference);
+
         debug!(
-            namespace = namespace,
-            secret = OVERRIDE_CONFIGURATION_NAME,
+            namespace = &secret_namespace,
+            secret = &secret_name,
             "Try to retrieve the optional secret",
         );
 
-        let secret: Option<Secret> =
-            resource::get(kube.to_owned(), &namespace, OVERRIDE_CONFIGURATION_NAME).await?;
+        let secret: Option<Secret> = secret::credentials(secrets, &namespace, reference)
+            .await
+            .map_err(ReconcilerError::Credentials)?;
 
         let apis = match secret {
             Some(secret) => {
                 info!(
-                    namespace = namespace,
-                    secret = OVERRIDE_CONFIGURATION_NAME,
+                    namespace = &secret_namespace,
+                    secret = &secret_name,
                     "Use custom Clever Cloud client to connect the api using secret",
                 );
 
-                clevercloud::client::try_from(secret).await?
+                ScopedClient::scoped(clients.get(secret).await?, &kind)
             }
             None => {
                 info!("Use default Clever Cloud client to connect the api");
-                apis.to_owned()
+                ScopedClient::unscoped(apis.get())
             }
-        };
+        }
+        .with_rate_limit(&origin.spec.organisation, &config.api.rate_limit)
+        .with_backend(backend.to_owned());
 
         // ---------------------------------------------------------------------
-        // Step 1: delete the addon
+        // Step 1: delete the addon, unless its deletion policy keeps it
 
-        info!(
-            kind = &kind,
-            namespace = &namespace,
-            name = &name,
-            "Delete addon for custom resource",
-        );
+        let policy = modified.spec.deletion_policy;
+        if policy.orphans_secrets() || !config.operator.secret.cascade_deletion {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                "Release kubernetes secrets of custom resource",
+            );
+
+            secret::release(kube.to_owned(), &modified).await?;
+        } else {
+            // copies exported to other namespaces are not owned by the custom
+            // resource, so they are not garbage collected along it
+            export::prune(kube.to_owned(), &modified).await?;
+        }
+
+        let (action, message) = if policy.deletes_addon() {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                "Delete addon for custom resource",
+            );
+
+            modified.delete(&apis).await?;
+            (
+                &Action::DeleteAddon,
+                "Delete managed pulsar instance on clever-cloud".to_string(),
+            )
+        } else {
+            let addon = modified.get_addon_id().unwrap_or_default();
+
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                addon = &addon,
+                policy = policy.to_string(),
+                "Keep addon for custom resource, as requested by its deletion policy",
+            );
+
+            (
+                &Action::KeepAddon,
+                format!(
+                    "Keep addon '{}' on clever-cloud, as requested by the '{}' deletion policy",
+                    addon, policy
+                ),
+            )
+        };
 
-        modified.delete(&apis).await?;
         modified.set_addon_id(None);
 
         debug!(
@@ -447,9 +1030,7 @@ impl k8s::Reconciler<Pulsar> for Reconciler {
             .and_then(|modified| resource::patch_status(kube.to_owned(), modified, patch))
             .await?;
 
-        let action = &Action::DeleteAddon;
-        let message = "Delete managed pulsar instance on clever-cloud";
-        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+        recorder::normal(kube.to_owned(), &modified, action, &message).await?;
 
         // ---------------------------------------------------------------------
         // Step 2: remove the finalizer
@@ -461,7 +1042,7 @@ impl k8s::Reconciler<Pulsar> for Reconciler {
             "Remove finalizer on custom resource",
         );
 
-        let modified = finalizer::remove(modified, ADDON_FINALIZER);
+        let patch = finalizer::remove_patch(&modified, ADDON_FINALIZER);
 
         let action = &Action::DeleteFinalizer;
         let message = "Delete finalizer from custom resource";
@@ -474,8 +1055,7 @@ impl k8s::Reconciler<Pulsar> for Reconciler {
             "Update information of custom resource",
         );
 
-        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
-        resource::patch(kube.to_owned(), &modified, patch.to_owned()).await?;
+        resource::patch(kube.to_owned(), &modified, patch).await?;
 
         Ok(())
     }
@@ -0,0 +1,857 @@
+//! # Runtime application
+//!
+//! This module provide the runtime custom resource and its definition, a
+//! runtime is an application hosted on Clever Cloud which is deployed by
+//! pushing code on its git remote.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use clevercloud_sdk::oauth10a::ClientError;
+use futures::TryFutureExt;
+use hyper::StatusCode;
+use k8s_openapi::api::core::v1::Secret;
+use kube::{
+    runtime::{controller, watcher, Controller},
+    CustomResource, Resource, ResourceExt,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, trace};
+
+use crate::svc::{
+    cfg::Api as ApiConfiguration,
+    clevercloud::{
+        self,
+        application::{self, Application, WannaBeApplication},
+        client::{self, Client, ScopedClient},
+        git,
+    },
+    crd::CredentialsSecretReference,
+    k8s::{
+        self,
+        condition::{self, Condition, Conditions},
+        finalizer,
+        rbac::{self, Permission},
+        recorder, resource, secret, Context, ControllerBuilder,
+    },
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const APPLICATION_FINALIZER: &str = "api.clever-cloud.com/runtime";
+
+/// kubernetes resources touched by the reconciler beside its custom resources
+pub const PERMISSIONS: &[Permission] = &[rbac::SECRETS_READ];
+
+// -----------------------------------------------------------------------------
+// Instance structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Instance {
+    /// type of the runtime, e.g. `node`, `java` or `docker`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// version of the runtime, the latest one is used if omitted
+    #[serde(rename = "version", default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(rename = "region")]
+    pub region: String,
+}
+
+// -----------------------------------------------------------------------------
+// Scalability structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Scalability {
+    #[serde(rename = "minInstances", default = "Scalability::default_instances")]
+    pub min_instances: u32,
+    #[serde(rename = "maxInstances", default = "Scalability::default_instances")]
+    pub max_instances: u32,
+    #[serde(rename = "minFlavor", default = "Scalability::default_flavor")]
+    pub min_flavor: String,
+    #[serde(rename = "maxFlavor", default = "Scalability::default_flavor")]
+    pub max_flavor: String,
+}
+
+impl Default for Scalability {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn default() -> Self {
+        Self {
+            min_instances: Self::default_instances(),
+            max_instances: Self::default_instances(),
+            min_flavor: Self::default_flavor(),
+            max_flavor: Self::default_flavor(),
+        }
+    }
+}
+
+impl Scalability {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn default_instances() -> u32 {
+        1
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn default_flavor() -> String {
+        "nano".to_string()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Source structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Source {
+    /// url of a git repository reachable by the operator, over `https://` or
+    /// `ssh://`
+    #[serde(rename = "repository")]
+    pub repository: String,
+    /// branch or tag of the repository pushed on the application
+    #[serde(rename = "ref", default = "Source::default_reference")]
+    pub reference: String,
+}
+
+impl Source {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn default_reference() -> String {
+        "main".to_string()
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}#{}", self.repository, self.reference)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Spec structure
+
+#[derive(CustomResource, JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[kube(group = "api.clever-cloud.com")]
+#[kube(version = "v1")]
+#[kube(kind = "Runtime")]
+#[kube(singular = "runtime")]
+#[kube(plural = "runtimes")]
+#[kube(shortname = "rt")]
+#[kube(status = "Status")]
+#[kube(namespaced)]
+#[kube(derive = "PartialEq")]
+#[kube(
+    printcolumn = r#"{"name":"organisation", "type":"string", "description":"Organisation", "jsonPath":".spec.organisation"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"application", "type":"string", "description":"Application", "jsonPath":".status.application"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"type", "type":"string", "description":"Type", "jsonPath":".spec.instance.type"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"region", "type":"string", "description":"Region", "jsonPath":".spec.instance.region"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"ready", "type":"string", "description":"Ready", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"phase", "type":"string", "description":"Reason of the ready condition", "jsonPath":".status.conditions[?(@.type==\"Ready\")].reason"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"age", "type":"date", "description":"Age", "jsonPath":".metadata.creationTimestamp"}"#
+)]
+pub struct Spec {
+    #[serde(rename = "organisation")]
+    pub organisation: String,
+    /// secret holding the configuration of the clever cloud client used to
+    /// reconcile the custom resource, the `clever-operator` secret of the
+    /// namespace is used if not set
+    #[serde(
+        rename = "credentialsSecretRef",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub credentials_secret_ref: Option<CredentialsSecretReference>,
+    #[serde(rename = "instance")]
+    pub instance: Instance,
+    #[serde(rename = "scalability", default)]
+    pub scalability: Scalability,
+    #[serde(rename = "environment", default)]
+    pub environment: BTreeMap<String, String>,
+    #[serde(rename = "domains", default)]
+    pub domains: Vec<String>,
+    /// code pushed on the application once created, and each time the source
+    /// changes
+    #[serde(rename = "source", default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Source>,
+    /// suspend the reconciliation of the custom resource, e.g. during a
+    /// manual maintenance on Clever Cloud, its deletion is still processed
+    #[serde(rename = "suspend", default)]
+    pub suspend: bool,
+}
+
+// -----------------------------------------------------------------------------
+// Status structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct Status {
+    #[serde(rename = "application")]
+    pub application: Option<String>,
+    /// domains added on the application by the operator
+    #[serde(rename = "domains", default, skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<String>,
+    /// source pushed on the application, as `<repository>#<ref>`
+    #[serde(rename = "source", default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(rename = "commit", default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(rename = "conditions", default)]
+    pub conditions: Vec<Condition>,
+    /// generation of the specification processed by the last successful
+    /// reconciliation
+    #[serde(
+        rename = "observedGeneration",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub observed_generation: Option<i64>,
+}
+
+// -----------------------------------------------------------------------------
+// Runtime implementation
+
+impl Runtime {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the name of the application on Clever Cloud
+    pub fn name(&self) -> String {
+        let delimiter = "::";
+
+        "kubernetes".to_string()
+            + delimiter
+            + &Self::kind(&())
+            + delimiter
+            + &self
+                .uid()
+                .expect("expect all resources in kubernetes to have an identifier")
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn organisation(&self) -> String {
+        self.spec.organisation.to_owned()
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_application_id(&mut self, id: Option<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.application = id;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn get_application_id(&self) -> Option<String> {
+        self.status.to_owned().unwrap_or_default().application
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_domains(&mut self, domains: Vec<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.domains = domains;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn get_domains(&self) -> Vec<String> {
+        self.status.to_owned().unwrap_or_default().domains
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn set_source(&mut self, source: Option<String>, commit: Option<String>) {
+        let status = self.status.get_or_insert_with(Status::default);
+
+        status.source = source;
+        status.commit = commit;
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn get_source(&self) -> Option<String> {
+        self.status.to_owned().unwrap_or_default().source
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+    /// returns the application, looked up by identifier and then by name, if
+    /// the identifier is unknown
+    pub async fn get(
+        &self,
+        client: &Client,
+        endpoint: &str,
+    ) -> Result<Option<Application>, application::Error> {
+        if let Some(id) = &self.get_application_id() {
+            trace!(
+                id = &id,
+                name = self.name(),
+                "Retrieve the application from the identifier",
+            );
+
+            match application::get(client, endpoint, &self.organisation(), id).await {
+                Ok(app) => return Ok(Some(app)),
+                Err(application::Error::Get(_, _, ClientError::StatusCode(code, _)))
+                    if StatusCode::NOT_FOUND.as_u16() == code.as_u16() => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        trace!(
+            name = self.name(),
+            "Trying to retrieve the application by name",
+        );
+
+        Ok(application::list(client, endpoint, &self.organisation())
+            .await?
+            .into_iter()
+            .find(|app| app.name == self.name()))
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+    /// returns the payload to create or update the application
+    pub async fn wanna_be(
+        &self,
+        client: &Client,
+        endpoint: &str,
+    ) -> Result<WannaBeApplication, application::Error> {
+        let instance = application::instance(
+            client,
+            endpoint,
+            &self.spec.instance.kind,
+            self.spec.instance.version.as_deref(),
+        )
+        .await?;
+
+        let Scalability {
+            min_instances,
+            max_instances,
+            min_flavor,
+            max_flavor,
+        } = self.spec.scalability.to_owned();
+
+        Ok(WannaBeApplication {
+            name: self.name(),
+            description: format!(
+                "Managed by the clever-operator from '{}/{}'",
+                self.namespace().unwrap_or_default(),
+                self.name_any()
+            ),
+            zone: self.spec.instance.region.to_owned(),
+            deploy: "git".to_string(),
+            instance_type: instance.kind,
+            instance_version: instance.version,
+            instance_variant: instance.variant.id,
+            min_instances,
+            max_instances,
+            min_flavor,
+            max_flavor,
+        })
+    }
+}
+
+impl Conditions for Runtime {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn conditions_mut(&mut self) -> &mut Vec<Condition> {
+        &mut self.status.get_or_insert_with(Status::default).conditions
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Action structure
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub enum Action {
+    UpsertFinalizer,
+    UpsertApplication,
+    UpsertEnvironment,
+    UpsertDomains,
+    PushSource,
+    DeleteFinalizer,
+    DeleteApplication,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::UpsertFinalizer => write!(f, "UpsertFinalizer"),
+            Self::UpsertApplication => write!(f, "UpsertApplication"),
+            Self::UpsertEnvironment => write!(f, "UpsertEnvironment"),
+            Self::UpsertDomains => write!(f, "UpsertDomains"),
+            Self::PushSource => write!(f, "PushSource"),
+            Self::DeleteFinalizer => write!(f, "DeleteFinalizer"),
+            Self::DeleteApplication => write!(f, "DeleteApplication"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// ReconcilerError enum
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReconcilerError {
+    #[error("failed to reconcile resource, {0}")]
+    Reconcile(String),
+    #[error("failed to execute request on clever-cloud api, {0}")]
+    CleverClient(clevercloud::Error),
+    #[error("failed to create clevercloud client, {0}")]
+    CreateCleverClient(clevercloud::client::Error),
+    #[error("failed to retrieve credentials secret, {0}")]
+    Credentials(secret::Error),
+    #[error("failed to execute request on kubernetes api, {0}")]
+    KubeClient(kube::Error),
+    #[error("failed to compute diff between the original and modified object, {0}")]
+    Diff(serde_json::Error),
+    #[error("failed to authorize call on clever-cloud api, {0}")]
+    Scope(clevercloud::client::ScopeError),
+}
+
+impl From<kube::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: kube::Error) -> Self {
+        Self::KubeClient(err)
+    }
+}
+
+impl From<clevercloud::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: clevercloud::Error) -> Self {
+        Self::CleverClient(err)
+    }
+}
+
+impl From<application::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: application::Error) -> Self {
+        Self::from(clevercloud::Error::from(err))
+    }
+}
+
+impl From<git::Error> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: git::Error) -> Self {
+        Self::from(clevercloud::Error::from(err))
+    }
+}
+
+impl From<controller::Error<Self, watcher::Error>> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: controller::Error<ReconcilerError, watcher::Error>) -> Self {
+        Self::Reconcile(err.to_string())
+    }
+}
+
+impl From<clevercloud::client::ScopeError> for ReconcilerError {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: clevercloud::client::ScopeError) -> Self {
+        Self::Scope(err)
+    }
+}
+
+impl From<clevercloud::client::Error> for ReconcilerError {
+    fn from(err: clevercloud::client::Error) -> Self {
+        Self::CreateCleverClient(err)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Reconciler structure
+
+#[derive(Clone, Default, Debug)]
+pub struct Reconciler {}
+
+impl ControllerBuilder<Runtime> for Reconciler {
+    fn build(&self, state: Arc<Context>, namespace: Option<&str>) -> Controller<Runtime> {
+        Controller::new(
+            k8s::api(state.kube.to_owned(), namespace),
+            k8s::watcher_config(&state.config),
+        )
+    }
+}
+
+#[async_trait]
+impl k8s::Reconciler<Runtime> for Reconciler {
+    type Error = ReconcilerError;
+
+    fn permanent(err: &ReconcilerError) -> Option<&'static str> {
+        match err {
+            ReconcilerError::CleverClient(err) => err.permanent(),
+            _ => None,
+        }
+    }
+
+    async fn upsert(ctx: Arc<Context>, origin: Arc<Runtime>) -> Result<(), ReconcilerError> {
+        let Context {
+            kube,
+            apis,
+            config,
+            secrets,
+            clients,
+            ..
+        } = ctx.as_ref();
+        let ApiConfiguration { endpoint, .. } = &config.api;
+
+        let kind = Runtime::kind(&()).to_string();
+        let (namespace, name) = resource::namespaced_name(&*origin);
+
+        // ---------------------------------------------------------------------
+        // Step 0: verify if there is a clever cloud client override
+        let reference = origin.spec.credentials_secret_ref.as_ref();
+        let (secret_namespace, secret_name) = secret::credentials_reference(&namespace, reference);
+
+        debug!(
+            namespace = &secret_namespace,
+            secret = &secret_name,
+            "Try to retrieve the optional secret on namespace",
+        );
+
+        let secret: Option<Secret> = secret::credentials(secrets, &namespace, reference)
+            .await
+            .map_err(ReconcilerError::Credentials)?;
+
+        // the git remote is authenticated with the credentials of the client
+        // used to reconcile the custom resource
+        let (apis, api) = match secret {
+            Some(secret) => {
+                info!(
+                    namespace = &secret_namespace,
+                    secret = &secret_name,
+                    "Use custom Clever Cloud client to connect the api using secret",
+                );
+
+                let api = client::configuration(secret.to_owned()).await?.api;
+                (ScopedClient::scoped(clients.get(secret).await?, &kind), api)
+            }
+            None => {
+                info!("Use default Clever Cloud client to connect the api");
+                (ScopedClient::unscoped(apis.get()), config.api.to_owned())
+            }
+        };
+
+        let apis = apis.with_rate_limit(&origin.spec.organisation, &config.api.rate_limit);
+
+        // ---------------------------------------------------------------------
+        // Step 1: set finalizer
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Set finalizer on custom resource",
+        );
+
+        let patch = finalizer::add_patch(&*origin, APPLICATION_FINALIZER);
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update information of custom resource",
+        );
+
+        let mut modified = resource::patch(kube.to_owned(), &*origin, patch).await?;
+
+        let action = &Action::UpsertFinalizer;
+        let message = &format!("Create finalizer '{}'", APPLICATION_FINALIZER);
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        // ---------------------------------------------------------------------
+        // Step 2: upsert application
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Upsert application for custom resource",
+        );
+
+        let client = apis.authorize(&kind, "application::get").await?;
+        let app = match modified.get(client, endpoint).await? {
+            Some(app) => {
+                debug!(
+                    application = &app.id,
+                    "Update scalability of the application"
+                );
+
+                let client = apis.authorize(&kind, "application::update").await?;
+                let wanna_be = modified.wanna_be(client, endpoint).await?;
+                application::update(
+                    client,
+                    endpoint,
+                    &modified.organisation(),
+                    &app.id,
+                    &wanna_be,
+                )
+                .await?
+            }
+            None => {
+                debug!(name = modified.name(), "Creating a new application");
+
+                let client = apis.authorize(&kind, "application::create").await?;
+                let wanna_be = modified.wanna_be(client, endpoint).await?;
+                application::create(client, endpoint, &modified.organisation(), &wanna_be).await?
+            }
+        };
+
+        let current = modified.to_owned();
+        modified.set_application_id(Some(app.id.to_owned()));
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update information and status of custom resource",
+        );
+
+        let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+        let mut modified = resource::patch(kube.to_owned(), &modified, patch.to_owned())
+            .and_then(|modified| resource::patch_status(kube.to_owned(), modified, patch))
+            .await?;
+
+        let action = &Action::UpsertApplication;
+        let message = &format!("Create application on clever-cloud '{}'", app.id);
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        // ---------------------------------------------------------------------
+        // Step 3: upsert environment variables
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            application = &app.id,
+            "Upsert environment variables of application",
+        );
+
+        application::environment(
+            apis.authorize(&kind, "application::environment").await?,
+            endpoint,
+            &modified.organisation(),
+            &app.id,
+            &modified.spec.environment,
+        )
+        .await?;
+
+        let action = &Action::UpsertEnvironment;
+        let message = &format!("Update environment of application '{}'", app.id);
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        // ---------------------------------------------------------------------
+        // Step 4: upsert domains, only domains added by the operator are
+        // removed, so the default domain of the application is kept
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            application = &app.id,
+            "Upsert domains of application",
+        );
+
+        let client = apis.authorize(&kind, "application::domains").await?;
+        let domains: Vec<String> =
+            application::domains(client, endpoint, &modified.organisation(), &app.id)
+                .await?
+                .into_iter()
+                .map(|domain| domain.fqdn)
+                .collect();
+
+        for fqdn in &modified.spec.domains {
+            if !domains.contains(fqdn) {
+                debug!(application = &app.id, domain = fqdn, "Add domain");
+                application::add_domain(client, endpoint, &modified.organisation(), &app.id, fqdn)
+                    .await?;
+            }
+        }
+
+        for fqdn in modified.get_domains() {
+            if domains.contains(&fqdn) && !modified.spec.domains.contains(&fqdn) {
+                debug!(application = &app.id, domain = &fqdn, "Remove domain");
+                application::remove_domain(
+                    client,
+                    endpoint,
+                    &modified.organisation(),
+                    &app.id,
+                    &fqdn,
+                )
+                .await?;
+            }
+        }
+
+        if modified.get_domains() != modified.spec.domains {
+            let current = modified.to_owned();
+            modified.set_domains(modified.spec.domains.to_owned());
+
+            let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+            modified = resource::patch_status(kube.to_owned(), modified, patch).await?;
+
+            let action = &Action::UpsertDomains;
+            let message = &format!("Update domains of application '{}'", app.id);
+            recorder::normal(kube.to_owned(), &modified, action, message).await?;
+        }
+
+        // ---------------------------------------------------------------------
+        // Step 5: push the source on the git remote of the application
+
+        let current = modified.to_owned();
+        match (
+            modified.spec.source.to_owned(),
+            app.deployment.http_url.to_owned(),
+        ) {
+            (Some(source), Some(remote)) if Some(source.to_string()) != modified.get_source() => {
+                info!(
+                    kind = &kind,
+                    namespace = &namespace,
+                    name = &name,
+                    application = &app.id,
+                    source = source.to_string(),
+                    "Push source on the git remote of application",
+                );
+
+                let commit =
+                    git::push(&api, &remote, &source.repository, &source.reference).await?;
+
+                modified.set_source(Some(source.to_string()), Some(commit.to_owned()));
+
+                let action = &Action::PushSource;
+                let message = &format!("Push commit '{}' on application '{}'", commit, app.id);
+                recorder::normal(kube.to_owned(), &modified, action, message).await?;
+            }
+            (Some(_), None) => {
+                debug!(
+                    application = &app.id,
+                    "Application does not expose a git remote, skip push of source"
+                );
+            }
+            _ => {}
+        }
+
+        modified.set_condition(Condition::new(
+            condition::READY,
+            condition::Status::True,
+            "ApplicationProvisioned",
+            &format!("Application '{}' is provisioned on clever-cloud", app.id),
+        ));
+
+        let patch = resource::diff(&current, &modified).map_err(ReconcilerError::Diff)?;
+        resource::patch_status(kube.to_owned(), modified, patch).await?;
+
+        Ok(())
+    }
+
+    async fn delete(ctx: Arc<Context>, origin: Arc<Runtime>) -> Result<(), ReconcilerError> {
+        let Context {
+            apis,
+            kube,
+            config,
+            secrets,
+            clients,
+            ..
+        } = ctx.as_ref();
+        let ApiConfiguration { endpoint, .. } = &config.api;
+
+        let mut modified = (*origin).to_owned();
+        let kind = Runtime::kind(&()).to_string();
+        let (namespace, name) = resource::namespaced_name(&*origin);
+
+        // ---------------------------------------------------------------------
+        // Step 0: verify if there is a clever cloud client override
+        let reference = origin.spec.credentials_secret_ref.as_ref();
+        let (secret_namespace, secret_name) = secret::credentials_reference(&namespace, reference);
+
+        debug!(
+            namespace = &secret_namespace,
+            secret = &secret_name,
+            "Try to retrieve the optional secret",
+        );
+
+        let secret: Option<Secret> = secret::credentials(secrets, &namespace, reference)
+            .await
+            .map_err(ReconcilerError::Credentials)?;
+
+        let apis = match secret {
+            Some(secret) => {
+                info!(
+                    namespace = &secret_namespace,
+                    secret = &secret_name,
+                    "Use custom Clever Cloud client to connect the api using secret",
+                );
+
+                ScopedClient::scoped(clients.get(secret).await?, &kind)
+            }
+            None => {
+                info!("Use default Clever Cloud client to connect the api");
+                ScopedClient::unscoped(apis.get())
+            }
+        }
+        .with_rate_limit(&origin.spec.organisation, &config.api.rate_limit);
+
+        // ---------------------------------------------------------------------
+        // Step 1: delete the application
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Delete application for custom resource",
+        );
+
+        let client = apis.authorize(&kind, "application::get").await?;
+        if let Some(app) = modified.get(client, endpoint).await? {
+            let client = apis.authorize(&kind, "application::delete").await?;
+            application::delete(client, endpoint, &modified.organisation(), &app.id).await?;
+        }
+
+        modified.set_application_id(None);
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update information and status of custom resource",
+        );
+
+        let patch = resource::diff(&*origin, &modified).map_err(ReconcilerError::Diff)?;
+        let modified = resource::patch(kube.to_owned(), &modified, patch.to_owned())
+            .and_then(|modified| resource::patch_status(kube.to_owned(), modified, patch))
+            .await?;
+
+        let action = &Action::DeleteApplication;
+        let message = "Delete application on clever-cloud";
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        // ---------------------------------------------------------------------
+        // Step 2: remove the finalizer
+
+        info!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Remove finalizer on custom resource",
+        );
+
+        let patch = finalizer::remove_patch(&modified, APPLICATION_FINALIZER);
+
+        let action = &Action::DeleteFinalizer;
+        let message = "Delete finalizer from custom resource";
+        recorder::normal(kube.to_owned(), &modified, action, message).await?;
+
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Update information of custom resource",
+        );
+
+        resource::patch(kube.to_owned(), &modified, patch).await?;
+
+        Ok(())
+    }
+}
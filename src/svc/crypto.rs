@@ -0,0 +1,232 @@
+//! # Crypto module
+//!
+//! This module provide the cryptographic primitives used by the operator and
+//! the policy of its tls sessions. The backend is selected at build time using
+//! the `crypto-openssl` or the `crypto-aws-lc` features, the former takes
+//! precedence, or is ring otherwise. The `fips` feature restricts the selected
+//! backend to its FIPS validated module and tls sessions to FIPS approved
+//! cipher suites and key exchange groups.
+//!
+//! The backend signs requests sent to Cellar and negotiates the tls sessions
+//! of the kubernetes client when it is built with `crypto-openssl`. Sessions
+//! negotiated by rustls, i.e. the ones of the Clever Cloud client, the webhook
+//! server and the postgresql connections, follow the policy of this module,
+//! while rustls still relies on ring for its own primitives.
+
+#[cfg(all(feature = "crypto-openssl", feature = "fips"))]
+use std::sync::Mutex;
+
+use rustls::{
+    ConfigBuilder, ConfigSide, SupportedCipherSuite, SupportedKxGroup, SupportedProtocolVersion,
+    WantsCipherSuites, WantsVerifier,
+};
+use tracing::info;
+
+// -----------------------------------------------------------------------------
+// Backend selection
+
+#[cfg(all(
+    feature = "fips",
+    not(any(feature = "crypto-openssl", feature = "crypto-aws-lc"))
+))]
+compile_error!("the 'fips' feature requires the 'crypto-openssl' or 'crypto-aws-lc' feature");
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// name of the crypto backend selected at build time
+#[cfg(not(any(feature = "crypto-openssl", feature = "crypto-aws-lc")))]
+pub const BACKEND: &str = "ring";
+#[cfg(feature = "crypto-openssl")]
+pub const BACKEND: &str = "openssl";
+#[cfg(all(feature = "crypto-aws-lc", not(feature = "crypto-openssl")))]
+pub const BACKEND: &str = "aws-lc";
+
+// -----------------------------------------------------------------------------
+// State
+
+/// providers loaded in the default library context of openssl, they are
+/// unloaded once dropped
+#[cfg(all(feature = "crypto-openssl", feature = "fips"))]
+static PROVIDERS: Mutex<Vec<openssl::provider::Provider>> = Mutex::new(Vec::new());
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[cfg(not(any(feature = "crypto-openssl", feature = "crypto-aws-lc")))]
+    #[error("failed to compute message authentication code, {0}")]
+    InvalidLength(hmac::digest::InvalidLength),
+    #[cfg(feature = "crypto-openssl")]
+    #[error("failed to execute openssl operation, {0}")]
+    OpenSsl(openssl::error::ErrorStack),
+    #[error("failed to enable fips mode of the '{0}' backend, {1}")]
+    Fips(&'static str, String),
+}
+
+#[cfg(feature = "crypto-openssl")]
+impl From<openssl::error::ErrorStack> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: openssl::error::ErrorStack) -> Self {
+        Self::OpenSsl(err)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// initialize the crypto backend, it has to be called before any tls session
+/// is negotiated. It fails if the `fips` feature is enabled and the FIPS
+/// validated module of the backend could not be used
+pub fn configure() -> Result<(), Error> {
+    #[cfg(feature = "crypto-openssl")]
+    {
+        openssl::init();
+
+        // loading the fips provider prevents the default one from being
+        // loaded, so only algorithms of the validated module are available
+        #[cfg(feature = "fips")]
+        {
+            let fips = openssl::provider::Provider::load(None, "fips")
+                .map_err(|err| Error::Fips(BACKEND, err.to_string()))?;
+            let base = openssl::provider::Provider::load(None, "base")
+                .map_err(|err| Error::Fips(BACKEND, err.to_string()))?;
+
+            if let Ok(mut providers) = PROVIDERS.lock() {
+                providers.extend([fips, base]);
+            }
+        }
+    }
+
+    #[cfg(all(
+        feature = "crypto-aws-lc",
+        feature = "fips",
+        not(feature = "crypto-openssl")
+    ))]
+    aws_lc_rs::try_fips_mode().map_err(|err| Error::Fips(BACKEND, err.to_string()))?;
+
+    info!(
+        backend = BACKEND,
+        fips = cfg!(feature = "fips"),
+        "Configure crypto backend"
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the cipher suites of tls sessions negotiated by rustls
+#[cfg(not(feature = "fips"))]
+pub fn cipher_suites() -> Vec<SupportedCipherSuite> {
+    rustls::DEFAULT_CIPHER_SUITES.to_vec()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the cipher suites of tls sessions negotiated by rustls, restricted
+/// to the FIPS approved ones
+#[cfg(feature = "fips")]
+pub fn cipher_suites() -> Vec<SupportedCipherSuite> {
+    vec![
+        rustls::cipher_suite::TLS13_AES_256_GCM_SHA384,
+        rustls::cipher_suite::TLS13_AES_128_GCM_SHA256,
+        rustls::cipher_suite::TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+        rustls::cipher_suite::TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+        rustls::cipher_suite::TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+        rustls::cipher_suite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+    ]
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the key exchange groups of tls sessions negotiated by rustls
+#[cfg(not(feature = "fips"))]
+pub fn kx_groups() -> Vec<&'static SupportedKxGroup> {
+    rustls::ALL_KX_GROUPS.to_vec()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the key exchange groups of tls sessions negotiated by rustls,
+/// restricted to the FIPS approved ones
+#[cfg(feature = "fips")]
+pub fn kx_groups() -> Vec<&'static SupportedKxGroup> {
+    vec![&rustls::kx_group::SECP384R1, &rustls::kx_group::SECP256R1]
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(builder)))]
+/// returns the builder of a tls configuration restricted to the cipher suites
+/// and the key exchange groups of the crypto policy, and to the given versions
+pub fn policy<S>(
+    builder: ConfigBuilder<S, WantsCipherSuites>,
+    versions: &[&'static SupportedProtocolVersion],
+) -> Result<ConfigBuilder<S, WantsVerifier>, rustls::Error>
+where
+    S: ConfigSide,
+{
+    builder
+        .with_cipher_suites(&cipher_suites())
+        .with_kx_groups(&kx_groups())
+        .with_protocol_versions(versions)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(data)))]
+/// returns the sha-256 digest of the data
+#[cfg(not(any(feature = "crypto-openssl", feature = "crypto-aws-lc")))]
+pub fn sha256(data: &[u8]) -> Result<Vec<u8>, Error> {
+    use sha2::{Digest, Sha256};
+
+    Ok(Sha256::digest(data).to_vec())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(data)))]
+/// returns the sha-256 digest of the data
+#[cfg(feature = "crypto-openssl")]
+pub fn sha256(data: &[u8]) -> Result<Vec<u8>, Error> {
+    use openssl::hash::{hash, MessageDigest};
+
+    Ok(hash(MessageDigest::sha256(), data)?.to_vec())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(data)))]
+/// returns the sha-256 digest of the data
+#[cfg(all(feature = "crypto-aws-lc", not(feature = "crypto-openssl")))]
+pub fn sha256(data: &[u8]) -> Result<Vec<u8>, Error> {
+    use aws_lc_rs::digest::{digest, SHA256};
+
+    Ok(digest(&SHA256, data).as_ref().to_vec())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(key, message)))]
+/// returns the hmac-sha256 of the message using the given key
+#[cfg(not(any(feature = "crypto-openssl", feature = "crypto-aws-lc")))]
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Result<Vec<u8>, Error> {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).map_err(Error::InvalidLength)?;
+    mac.update(message);
+
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(key, message)))]
+/// returns the hmac-sha256 of the message using the given key
+#[cfg(feature = "crypto-openssl")]
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Result<Vec<u8>, Error> {
+    use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+
+    let key = PKey::hmac(key)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(message)?;
+
+    Ok(signer.sign_to_vec()?)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(key, message)))]
+/// returns the hmac-sha256 of the message using the given key
+#[cfg(all(feature = "crypto-aws-lc", not(feature = "crypto-openssl")))]
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Result<Vec<u8>, Error> {
+    use aws_lc_rs::hmac::{sign, Key, HMAC_SHA256};
+
+    Ok(sign(&Key::new(HMAC_SHA256, key), message).as_ref().to_vec())
+}
@@ -0,0 +1,258 @@
+//! # Health module
+//!
+//! This module provide the health registry of the operator which backs the
+//! `/livez` and `/readyz` endpoints. Each controller registers itself and
+//! reports when its watchers have synced and, periodically, a heartbeat. The
+//! reachability of the Clever Cloud api is checked in the background.
+//!
+//! The operator is ready once it is not shutting down, every registered
+//! controller has synced and the Clever Cloud api is reachable. It is alive as
+//! long as no controller has panicked or stopped unexpectedly, without being
+//! restarted by the supervisor, and no watcher has stalled.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use clevercloud_sdk::v2::myself;
+use serde::{Deserialize, Serialize};
+use tokio::time::interval;
+use tracing::{debug, warn};
+
+use crate::svc::{
+    clevercloud::client::{observe, SharedClient},
+    shutdown,
+};
+
+// -----------------------------------------------------------------------------
+// State
+
+static CONTROLLERS: Mutex<BTreeMap<String, Controller>> = Mutex::new(BTreeMap::new());
+static API_REACHABLE: AtomicBool = AtomicBool::new(false);
+
+// -----------------------------------------------------------------------------
+// Controller structure
+
+#[derive(Clone, Debug)]
+struct Controller {
+    synced: bool,
+    stopped: Option<String>,
+    heartbeat: Instant,
+}
+
+// -----------------------------------------------------------------------------
+// Report structure
+
+/// health of the operator, returned by the `/livez` and `/readyz` endpoints
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Report {
+    #[serde(rename = "ok")]
+    pub ok: bool,
+    #[serde(rename = "checks")]
+    pub checks: BTreeMap<String, String>,
+}
+
+impl Report {
+    fn new(checks: BTreeMap<String, Result<(), String>>) -> Self {
+        Self {
+            ok: checks.values().all(Result::is_ok),
+            checks: checks
+                .into_iter()
+                .map(|(name, check)| (name, check.err().unwrap_or_else(|| "ok".to_string())))
+                .collect(),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Guard structure
+
+/// registration of a controller which marks it as stopped when dropped, e.g.
+/// when the task running the controller panics
+#[derive(Debug)]
+pub struct Guard {
+    kind: String,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let reason = if std::thread::panicking() {
+            "panicked"
+        } else {
+            "stopped"
+        };
+
+        stop(&self.kind, reason);
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// register the controller of the given kind, it is not synced until
+/// [`synced`] is called
+pub fn register(kind: &str) -> Guard {
+    if let Ok(mut controllers) = CONTROLLERS.lock() {
+        controllers.insert(
+            kind.to_string(),
+            Controller {
+                synced: false,
+                stopped: None,
+                heartbeat: Instant::now(),
+            },
+        );
+    }
+
+    Guard {
+        kind: kind.to_string(),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// mark the watchers of the controller of the given kind as synced
+pub fn synced(kind: &str) {
+    if let Ok(mut controllers) = CONTROLLERS.lock() {
+        if let Some(controller) = controllers.get_mut(kind) {
+            if !controller.synced {
+                debug!(kind = kind, "Watchers of custom resource have synced");
+            }
+
+            controller.synced = true;
+            controller.heartbeat = Instant::now();
+        }
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// record that the watch loop of the controller of the given kind is alive
+pub fn heartbeat(kind: &str) {
+    if let Ok(mut controllers) = CONTROLLERS.lock() {
+        if let Some(controller) = controllers.get_mut(kind) {
+            controller.heartbeat = Instant::now();
+        }
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// mark the controller of the given kind as stopped for the given reason
+pub fn stop(kind: &str, reason: &str) {
+    if let Ok(mut controllers) = CONTROLLERS.lock() {
+        if let Some(controller) = controllers.get_mut(kind) {
+            controller.stopped.get_or_insert_with(|| reason.to_string());
+        }
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// mark the controller of the given kind as being restarted by the supervisor,
+/// it is alive but not synced until it is registered again
+pub fn restarting(kind: &str) {
+    if let Ok(mut controllers) = CONTROLLERS.lock() {
+        if let Some(controller) = controllers.get_mut(kind) {
+            controller.synced = false;
+            controller.stopped = None;
+            controller.heartbeat = Instant::now();
+        }
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the liveness report, a controller which has stopped while the
+/// operator is not shutting down or whose last heartbeat is older than the
+/// stall timeout fails the check
+pub fn liveness(stall_timeout: Duration) -> Report {
+    let shutting_down = !shutdown::ready();
+    let controllers = CONTROLLERS
+        .lock()
+        .map(|controllers| controllers.to_owned())
+        .unwrap_or_default();
+
+    let checks = controllers
+        .into_iter()
+        .map(|(kind, controller)| {
+            let check = match &controller.stopped {
+                Some(reason) if reason != "stopped" || !shutting_down => {
+                    Err(format!("controller has {}", reason))
+                }
+                Some(_) => Ok(()),
+                None if controller.heartbeat.elapsed() > stall_timeout => Err(format!(
+                    "watch stream has stalled for {}s",
+                    controller.heartbeat.elapsed().as_secs()
+                )),
+                None => Ok(()),
+            };
+
+            (format!("controller/{}", kind), check)
+        })
+        .collect();
+
+    Report::new(checks)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the readiness report
+pub fn readiness() -> Report {
+    let mut checks = BTreeMap::new();
+
+    checks.insert(
+        "shutdown".to_string(),
+        if shutdown::ready() {
+            Ok(())
+        } else {
+            Err("operator is not ready or is shutting down".to_string())
+        },
+    );
+
+    checks.insert(
+        "clever-cloud-api".to_string(),
+        if API_REACHABLE.load(Ordering::SeqCst) {
+            Ok(())
+        } else {
+            Err("api is not reachable".to_string())
+        },
+    );
+
+    if let Ok(controllers) = CONTROLLERS.lock() {
+        for (kind, controller) in controllers.iter() {
+            let check = if controller.synced {
+                Ok(())
+            } else {
+                Err("watchers have not synced".to_string())
+            };
+
+            checks.insert(format!("controller/{}", kind), check);
+        }
+    }
+
+    Report::new(checks)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// check the reachability of the clever cloud api at the given interval,
+/// until the future is dropped
+pub async fn probe(client: SharedClient, period: Duration) {
+    let mut ticker = interval(period);
+
+    loop {
+        ticker.tick().await;
+
+        let reachable = match observe("self", "get", myself::get(&client.get())).await {
+            Ok(_) => true,
+            Err(err) => {
+                warn!(
+                    error = err.to_string(),
+                    "Could not reach the clever cloud api"
+                );
+                false
+            }
+        };
+
+        API_REACHABLE.store(reachable, Ordering::SeqCst);
+    }
+}
@@ -3,3 +3,4 @@
 //! This module provide utilities to interact using HTTP protocol
 
 pub mod server;
+pub mod webhook;
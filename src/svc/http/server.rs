@@ -1,9 +1,10 @@
 //! # Server module
 //!
-//! This module provide a HTTP server to handle health request
+//! This module provide a HTTP server to handle health, metrics, catalog and
+//! management requests
 
 use std::net::SocketAddr;
-use std::{net::AddrParseError, sync::Arc};
+use std::{future::Future, net::AddrParseError, sync::Arc};
 
 use hyper::{
     service::{make_service_fn, service_fn},
@@ -11,7 +12,7 @@ use hyper::{
 };
 use tracing::{info, Instrument};
 
-use crate::svc::{cfg::Configuration, telemetry::router};
+use crate::svc::{k8s::Context, telemetry::router};
 
 // -----------------------------------------------------------------------------
 
@@ -25,8 +26,13 @@ pub enum Error {
     Serve(hyper::Error),
 }
 
-#[tracing::instrument(skip(config))]
-pub async fn serve(config: Arc<Configuration>) -> Result<(), Error> {
+#[tracing::instrument(skip(ctx, signal))]
+/// serve http requests until the given signal is resolved
+pub async fn serve<F>(ctx: Arc<Context>, signal: F) -> Result<(), Error>
+where
+    F: Future<Output = ()>,
+{
+    let config = &ctx.config;
     let addr: SocketAddr = config
         .operator
         .listen
@@ -39,9 +45,11 @@ pub async fn serve(config: Arc<Configuration>) -> Result<(), Error> {
     );
     Server::try_bind(&addr)
         .map_err(Error::Bind)?
-        .serve(make_service_fn(|_| async {
-            Ok::<_, Error>(service_fn(router))
+        .serve(make_service_fn(|_| {
+            let ctx = ctx.to_owned();
+            async move { Ok::<_, Error>(service_fn(move |req| router(ctx.to_owned(), req))) }
         }))
+        .with_graceful_shutdown(signal)
         .instrument(tracing::info_span!("Server::serve"))
         .await
         .map_err(Error::Serve)?;
@@ -0,0 +1,507 @@
+//! # Webhook module
+//!
+//! This module provide a mutating admission webhook served over https. It
+//! resolves human-readable plans (e.g. `xs`) of custom resources into plan
+//! identifiers and fills the default region and organisation at admission
+//! time, so reconcilers never have to patch the specification. Sources of
+//! runtimes which could not be given safely to git are denied. It also serves
+//! the conversion of custom resources between the versions of their custom
+//! resource definition.
+//!
+//! See <https://kubernetes.io/docs/reference/access-authn-authz/extensible-admission-controllers/>
+
+use std::{
+    fs::File,
+    future::Future,
+    io::{self, BufReader},
+    net::{AddrParseError, SocketAddr},
+    path::Path,
+    sync::Arc,
+};
+
+use clevercloud_sdk::v4::addon_provider::{plan, AddonProviderId};
+use hyper::{
+    header::{self, HeaderValue},
+    server::conn::Http,
+    service::service_fn,
+    Body, Method, Request, Response, StatusCode,
+};
+use k8s_openapi::api::core::v1::Secret;
+use kube::core::{
+    admission::{AdmissionRequest, AdmissionResponse, AdmissionReview, Operation},
+    DynamicObject,
+};
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tokio_rustls::{
+    rustls::{self, Certificate, PrivateKey, ServerConfig},
+    TlsAcceptor,
+};
+use tracing::{debug, info, warn, Instrument};
+
+use crate::svc::{
+    clevercloud::{self, client::ScopedClient, git},
+    crd::CredentialsSecretReference,
+    crypto,
+    k8s::{
+        conversion::{self, ConversionReview, CONVERT_PATH},
+        secret, Context,
+    },
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const MUTATE_PATH: &str = "/mutate";
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to parse listen address '{0}', {1}")]
+    Listen(String, AddrParseError),
+    #[error("failed to bind server, {0}")]
+    Bind(io::Error),
+    #[error("failed to read '{0}', {1}")]
+    Read(String, io::Error),
+    #[error("failed to find a private key in '{0}'")]
+    MissingKey(String),
+    #[error("failed to configure tls, {0}")]
+    Tls(rustls::Error),
+    #[error("failed to read request body, {0}")]
+    Body(hyper::Error),
+    #[error("failed to deserialize review, {0}")]
+    Deserialize(serde_json::Error),
+    #[error("failed to serialize payload, {0}")]
+    Serialize(serde_json::Error),
+    #[error("failed to serialize patch, {0}")]
+    Patch(kube::core::admission::SerializePatchError),
+    #[error("failed to retrieve credentials secret, {0}")]
+    Secret(secret::Error),
+    #[error("failed to create clevercloud client, {0}")]
+    CleverClient(clevercloud::client::Error),
+    #[error("{0}")]
+    Scope(clevercloud::client::ScopeError),
+    #[error("failed to resolve plan '{0}' of organisation '{1}', {2}")]
+    ResolvePlan(String, String, plan::Error),
+    #[error("failed to find plan '{0}' of organisation '{1}'")]
+    UnknownPlan(String, String),
+    #[error("invalid source, {0}")]
+    Source(git::Error),
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(ctx, signal)))]
+/// serve admission requests over https until the given signal is resolved. If
+/// the webhook is disabled, only wait for the signal
+pub async fn serve<F>(ctx: Arc<Context>, signal: F) -> Result<(), Error>
+where
+    F: Future<Output = ()>,
+{
+    let config = &ctx.config.operator.webhook;
+    if !config.enabled {
+        debug!("Mutating admission webhook is disabled");
+        signal.await;
+        return Ok(());
+    }
+
+    let addr: SocketAddr = config
+        .listen
+        .parse()
+        .map_err(|err| Error::Listen(config.listen.to_owned(), err))?;
+
+    let acceptor = acceptor(&config.certificate, &config.key)?;
+    let listener = TcpListener::bind(&addr).await.map_err(Error::Bind)?;
+    let http = Http::new();
+
+    info!(
+        address = addr.to_string(),
+        "Start to listen for admission request"
+    );
+
+    tokio::pin!(signal);
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = &mut signal => break,
+            r = listener.accept() => match r {
+                Ok(conn) => conn,
+                Err(err) => {
+                    warn!(error = err.to_string(), "Could not accept connection");
+                    continue;
+                }
+            },
+        };
+
+        let acceptor = acceptor.to_owned();
+        let http = http.to_owned();
+        let ctx = ctx.to_owned();
+
+        tokio::spawn(
+            async move {
+                let stream = match acceptor.accept(stream).await {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        warn!(
+                            peer = peer.to_string(),
+                            error = err.to_string(),
+                            "Could not negotiate tls with peer"
+                        );
+                        return;
+                    }
+                };
+
+                let service = service_fn(move |req| router(ctx.to_owned(), req));
+                if let Err(err) = http.serve_connection(stream, service).await {
+                    warn!(
+                        peer = peer.to_string(),
+                        error = err.to_string(),
+                        "Could not serve connection"
+                    );
+                }
+            }
+            .instrument(tracing::info_span!("Webhook::serve")),
+        );
+    }
+
+    Ok(())
+}
+
+/// returns the tls acceptor using the pem encoded certificate chain and private
+/// key located at the given paths
+fn acceptor(certificate: &Path, key: &Path) -> Result<TlsAcceptor, Error> {
+    let open = |path: &Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|err| Error::Read(path.display().to_string(), err))
+    };
+
+    let certificates = rustls_pemfile::certs(&mut open(certificate)?)
+        .map_err(|err| Error::Read(certificate.display().to_string(), err))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+
+    let key = rustls_pemfile::read_all(&mut open(key)?)
+        .map_err(|err| Error::Read(key.display().to_string(), err))?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| Error::MissingKey(key.display().to_string()))?;
+
+    let config = crypto::policy(ServerConfig::builder(), rustls::DEFAULT_VERSIONS)
+        .map_err(Error::Tls)?
+        .with_no_client_auth()
+        .with_single_cert(certificates, key)
+        .map_err(Error::Tls)?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(ctx)))]
+async fn router(ctx: Arc<Context>, req: Request<Body>) -> Result<Response<Body>, Error> {
+    let mut res = Response::default();
+
+    let path = match (req.method(), req.uri().path()) {
+        (&Method::POST, path @ (MUTATE_PATH | CONVERT_PATH)) => path.to_string(),
+        _ => {
+            *res.status_mut() = StatusCode::NOT_FOUND;
+            return Ok(res);
+        }
+    };
+
+    let body = hyper::body::to_bytes(req.into_body())
+        .await
+        .map_err(Error::Body)?;
+
+    let payload = if path == CONVERT_PATH {
+        let review: ConversionReview = serde_json::from_slice(&body).map_err(Error::Deserialize)?;
+
+        serde_json::to_vec(&conversion::review(review)).map_err(Error::Serialize)?
+    } else {
+        let review: AdmissionReview<DynamicObject> =
+            serde_json::from_slice(&body).map_err(Error::Deserialize)?;
+
+        let req: Result<AdmissionRequest<DynamicObject>, _> = review.try_into();
+        let review = match req {
+            Ok(req) => admit(&ctx, &req).await?.into_review(),
+            Err(err) => AdmissionResponse::invalid(err.to_string()).into_review(),
+        };
+
+        serde_json::to_vec(&review).map_err(Error::Serialize)?
+    };
+
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    *res.status_mut() = StatusCode::OK;
+    *res.body_mut() = Body::from(payload);
+
+    Ok(res)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(ctx)))]
+/// returns the admission response of the request. Unknown plans and invalid
+/// sources are denied,
+/// if the plan could not be resolved for another reason, the resource is
+/// admitted as is and the reconciler resolves it later
+async fn admit(
+    ctx: &Context,
+    req: &AdmissionRequest<DynamicObject>,
+) -> Result<AdmissionResponse, Error> {
+    let res = AdmissionResponse::from(req);
+    let obj = match (&req.operation, &req.object) {
+        (Operation::Create | Operation::Update, Some(obj)) => obj,
+        _ => return Ok(res),
+    };
+
+    let kind = req.kind.kind.to_owned();
+    let namespace = req.namespace.to_owned().unwrap_or_default();
+    let origin = serde_json::to_value(obj).map_err(Error::Serialize)?;
+
+    let modified = match mutate(ctx, &kind, &namespace, origin.to_owned()).await {
+        Ok(modified) => modified,
+        Err(err @ (Error::UnknownPlan(_, _) | Error::Source(_))) => {
+            info!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &req.name,
+                error = err.to_string(),
+                "Deny admission of custom resource",
+            );
+
+            return Ok(res.deny(err.to_string()));
+        }
+        Err(err) => {
+            warn!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &req.name,
+                error = err.to_string(),
+                "Could not mutate custom resource, admit it as is",
+            );
+
+            return Ok(res);
+        }
+    };
+
+    let patch = json_patch::diff(&origin, &modified);
+    if patch.0.is_empty() {
+        return Ok(res);
+    }
+
+    debug!(
+        kind = &kind,
+        namespace = &namespace,
+        name = &req.name,
+        patch = serde_json::to_string(&patch).map_err(Error::Serialize)?,
+        "Mutate custom resource",
+    );
+
+    res.with_patch(patch).map_err(Error::Patch)
+}
+
+/// returns the object with default values filled and plan resolved
+async fn mutate(
+    ctx: &Context,
+    kind: &str,
+    namespace: &str,
+    mut obj: Value,
+) -> Result<Value, Error> {
+    let config = &ctx.config.operator.webhook;
+    let spec = match obj.get_mut("spec").and_then(Value::as_object_mut) {
+        Some(spec) => spec,
+        None => return Ok(obj),
+    };
+
+    // -------------------------------------------------------------------------
+    // Step 0: validate the source given to git
+    validate_source(kind, spec).map_err(Error::Source)?;
+
+    // -------------------------------------------------------------------------
+    // Step 1: fill default organisation
+    if let Some(organisation) = &config.default_organisation {
+        if has_organisation(kind) && is_empty(spec.get("organisation")) {
+            spec.insert("organisation".into(), Value::from(organisation.to_owned()));
+        }
+    }
+
+    // -------------------------------------------------------------------------
+    // Step 2: fill default region
+    if has_region(kind) {
+        if let Some(instance) = spec.get_mut("instance").and_then(Value::as_object_mut) {
+            if is_empty(instance.get("region")) {
+                instance.insert(
+                    "region".into(),
+                    Value::from(config.default_region.to_owned()),
+                );
+            }
+        }
+    }
+
+    // -------------------------------------------------------------------------
+    // Step 3: resolve plan
+    let provider = match provider(kind) {
+        Some(provider) => provider,
+        None => return Ok(obj),
+    };
+
+    let organisation = spec
+        .get("organisation")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    let reference: Option<CredentialsSecretReference> = spec
+        .get("credentialsSecretRef")
+        .cloned()
+        .and_then(|reference| serde_json::from_value(reference).ok());
+
+    let instance = match spec.get_mut("instance").and_then(Value::as_object_mut) {
+        Some(instance) => instance,
+        None => return Ok(obj),
+    };
+
+    let pattern = match instance.get("plan").and_then(Value::as_str) {
+        Some(pattern) if !pattern.is_empty() && !pattern.starts_with("plan_") => {
+            pattern.to_string()
+        }
+        _ => return Ok(obj),
+    };
+
+    let secret: Option<Secret> = secret::credentials(&ctx.secrets, namespace, reference.as_ref())
+        .await
+        .map_err(Error::Secret)?;
+
+    let apis = match secret {
+        Some(secret) => ScopedClient::scoped(
+            ctx.clients.get(secret).await.map_err(Error::CleverClient)?,
+            kind,
+        ),
+        None => ScopedClient::unscoped(ctx.apis.get()),
+    }
+    .with_rate_limit(&organisation, &ctx.config.api.rate_limit)
+    .with_backend(ctx.backend.to_owned());
+
+    let plan = ctx
+        .plans
+        .find(
+            apis.backend(kind, "plan::find")
+                .await
+                .map_err(Error::Scope)?,
+            &provider,
+            &organisation,
+            &pattern,
+        )
+        .await
+        .map_err(|err| Error::ResolvePlan(pattern.to_owned(), organisation.to_owned(), err))?
+        .ok_or_else(|| Error::UnknownPlan(pattern.to_owned(), organisation.to_owned()))?;
+
+    instance.insert("plan".into(), Value::from(plan.id));
+
+    Ok(obj)
+}
+
+/// returns an error if the source of a runtime could not be given safely to git
+fn validate_source(kind: &str, spec: &serde_json::Map<String, Value>) -> Result<(), git::Error> {
+    let source = match spec.get("source") {
+        Some(source) if kind == "Runtime" => source,
+        _ => return Ok(()),
+    };
+
+    let repository = source
+        .get("repository")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let reference = source.get("ref").and_then(Value::as_str).unwrap_or("main");
+
+    git::validate(repository, reference)
+}
+
+/// returns if the value is missing or an empty string
+fn is_empty(value: Option<&Value>) -> bool {
+    value
+        .and_then(Value::as_str)
+        .map(str::is_empty)
+        .unwrap_or(true)
+}
+
+/// returns the addon provider of the kind, if its plan has to be resolved
+fn provider(kind: &str) -> Option<AddonProviderId> {
+    match kind {
+        "PostgreSql" => Some(AddonProviderId::PostgreSql),
+        "MySql" => Some(AddonProviderId::MySql),
+        "Redis" => Some(AddonProviderId::Redis),
+        "MongoDb" => Some(AddonProviderId::MongoDb),
+        "ElasticSearch" => Some(AddonProviderId::ElasticSearch),
+        _ => None,
+    }
+}
+
+/// returns if the kind has a region at '.spec.instance.region'
+fn has_region(kind: &str) -> bool {
+    provider(kind).is_some()
+        || matches!(
+            kind,
+            "Pulsar" | "Runtime" | "Addon" | "FSBucket" | "Mailpace" | "Jenkins" | "Gravitee"
+        )
+}
+
+/// returns if the kind has an organisation at '.spec.organisation'
+fn has_organisation(kind: &str) -> bool {
+    has_region(kind) || kind == "ConfigProvider"
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::validate_source;
+    use crate::svc::clevercloud::git::Error;
+
+    fn spec(source: Value) -> serde_json::Map<String, Value> {
+        match json!({ "organisation": "orga_test", "source": source }) {
+            Value::Object(spec) => spec,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn validate_source_denies_option_like_repository() {
+        let spec = spec(json!({ "repository": "--upload-pack=touch /tmp/pwned", "ref": "main" }));
+
+        assert!(matches!(
+            validate_source("Runtime", &spec),
+            Err(Error::Repository(_))
+        ));
+    }
+
+    #[test]
+    fn validate_source_denies_option_like_reference() {
+        let spec = spec(
+            json!({ "repository": "https://github.com/CleverCloud/clever-operator.git", "ref": "-b" }),
+        );
+
+        assert!(matches!(
+            validate_source("Runtime", &spec),
+            Err(Error::Reference(_))
+        ));
+    }
+
+    #[test]
+    fn validate_source_admits_https_repository_and_default_reference() {
+        let spec =
+            spec(json!({ "repository": "https://github.com/CleverCloud/clever-operator.git" }));
+
+        assert!(validate_source("Runtime", &spec).is_ok());
+        assert!(validate_source("PostgreSql", &spec).is_ok());
+    }
+}
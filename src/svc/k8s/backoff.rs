@@ -0,0 +1,68 @@
+//! # Backoff module
+//!
+//! This module computes the delay before retrying a failed reconciliation. The
+//! delay grows exponentially with the number of consecutive failures of each
+//! object and is jittered, so objects failing together do not hammer the api
+//! at the same time.
+
+use std::{collections::BTreeMap, sync::Mutex, time::Duration};
+
+use rand::Rng;
+
+use crate::svc::cfg::Retry;
+
+// -----------------------------------------------------------------------------
+// State
+
+/// number of consecutive failures, keyed by object
+static FAILURES: Mutex<BTreeMap<String, u32>> = Mutex::new(BTreeMap::new());
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the key of the object
+pub fn key(kind: &str, namespace: &str, name: &str) -> String {
+    format!("{}/{}/{}", kind, namespace, name)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// record a failure of the object and returns the delay before retrying. The
+/// delay is drawn between the half and the whole of the exponential delay
+pub fn next(key: &str, config: &Retry) -> Duration {
+    let failures = match FAILURES.lock() {
+        Ok(mut failures) => {
+            let count = failures.entry(key.to_string()).or_default();
+            *count = count.saturating_add(1);
+            *count
+        }
+        Err(_) => 1,
+    };
+
+    let delay = config
+        .initial_delay
+        .saturating_mul(2u64.saturating_pow(failures - 1))
+        .min(config.max_delay)
+        .max(1);
+
+    Duration::from_millis(rand::thread_rng().gen_range(delay / 2..=delay))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the number of consecutive failures of the object
+pub fn failures(key: &str) -> u32 {
+    FAILURES
+        .lock()
+        .ok()
+        .and_then(|failures| failures.get(key).copied())
+        .unwrap_or_default()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// forget failures of the object, once it has been reconciled or once it
+/// stops being retried
+pub fn reset(key: &str) {
+    if let Ok(mut failures) = FAILURES.lock() {
+        failures.remove(key);
+    }
+}
@@ -0,0 +1,162 @@
+//! # Billing module
+//!
+//! This module provide helpers to surface the price of the plan of an addon
+//! and its consumption over the current month in the status of its custom
+//! resource and as gauges, so platform teams could track the spend caused by
+//! addons provisioned from kubernetes. The consumption is requested at most
+//! once per refresh interval, failures are logged and the last known values
+//! are kept.
+
+use std::fmt::Debug;
+
+use chrono::{DateTime, Datelike, Utc};
+use clevercloud_sdk::v2::addon::Addon;
+use k8s_openapi::NamespaceResourceScope;
+use kube::{Resource, ResourceExt};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_gauge_vec, GaugeVec};
+use tracing::warn;
+
+use crate::svc::{
+    cfg::Configuration,
+    clevercloud::{billing, client::ScopedClient, ext::AddonExt},
+    crd::Billing,
+    k8s::{condition, resource},
+};
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static PLAN_PRICE: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        opts!(
+            "kubernetes_operator_addon_plan_price",
+            "price of the plan of the addon of custom resources, in euros",
+        ),
+        &["kind", "namespace", "name"]
+    )
+    .expect("metrics 'kubernetes_operator_addon_plan_price' to not be already registered")
+});
+
+#[cfg(feature = "metrics")]
+static CURRENT_MONTH_CONSUMPTION: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        opts!(
+            "kubernetes_operator_addon_current_month_consumption",
+            "price of the consumption of the addon of custom resources since the beginning of the current month, in euros",
+        ),
+        &["kind", "namespace", "name"]
+    )
+    .expect("metrics 'kubernetes_operator_addon_current_month_consumption' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(apis, config)))]
+/// returns the billing information of the addon of the custom resource, the
+/// consumption is only requested if the current one is older than the
+/// refresh interval. It returns none, if billing is disabled
+pub async fn refresh<T>(
+    apis: &ScopedClient,
+    config: &Configuration,
+    obj: &T,
+    addon: &Addon,
+    current: Option<&Billing>,
+) -> Option<Billing>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + AddonExt + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    if !config.operator.billing.enabled {
+        return None;
+    }
+
+    let kind = T::kind(&Default::default()).to_string();
+    let (namespace, name) = resource::namespaced_name(obj);
+    let mut billing = current.cloned().unwrap_or_default();
+
+    billing.plan_price = Some(format!("{:.2}", addon.plan.price));
+    #[cfg(feature = "metrics")]
+    PLAN_PRICE
+        .with_label_values(&[&kind, &namespace, &name])
+        .set(f64::from(addon.plan.price));
+
+    if stale(&billing, config.operator.billing.refresh_interval) {
+        let (now, organisation) = (Utc::now(), obj.organisation());
+        let from = format!("{:04}-{:02}-01T00:00:00Z", now.year(), now.month());
+        let to = condition::now();
+
+        let consumption = match apis.authorize(&kind, "billing::consumption").await {
+            Ok(client) => {
+                let endpoint = &config.api.endpoint;
+                billing::consumption(client, endpoint, &organisation, &addon.id, &from, &to)
+                    .await
+                    .map_err(|err| err.to_string())
+            }
+            Err(err) => Err(err.to_string()),
+        };
+
+        match consumption {
+            Ok(price) => {
+                billing.current_month_consumption = Some(format!("{:.2}", price));
+                billing.last_update_timestamp = Some(to);
+            }
+            Err(err) => warn!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                addon = &addon.id,
+                error = err,
+                "Could not refresh the consumption of the addon, keep the last known one",
+            ),
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    if let Some(price) = billing
+        .current_month_consumption
+        .as_ref()
+        .and_then(|price| price.parse().ok())
+    {
+        CURRENT_MONTH_CONSUMPTION
+            .with_label_values(&[&kind, &namespace, &name])
+            .set(price);
+    }
+
+    Some(billing)
+}
+
+#[cfg(feature = "metrics")]
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// remove the gauges of the given custom resource, once it has been deleted
+pub fn forget(kind: &str, namespace: &str, name: &str) {
+    for gauge in [&*PLAN_PRICE, &*CURRENT_MONTH_CONSUMPTION] {
+        // the gauges do not exist, if billing is disabled
+        let _ = gauge.remove_label_values(&[kind, namespace, name]);
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the consumption has to be requested again, it is also the case
+/// once a new month has begun
+fn stale(billing: &Billing, refresh_interval: u64) -> bool {
+    let last_update = match billing
+        .last_update_timestamp
+        .as_deref()
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+    {
+        Some(last_update) => last_update.with_timezone(&Utc),
+        None => return true,
+    };
+
+    let now = Utc::now();
+    let elapsed = now.signed_duration_since(last_update).num_seconds();
+
+    elapsed < 0
+        || elapsed as u64 >= refresh_interval
+        || (now.year(), now.month()) != (last_update.year(), last_update.month())
+}
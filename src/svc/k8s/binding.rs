@@ -0,0 +1,163 @@
+//! # Binding module
+//!
+//! This module provide helpers to generate secrets following the
+//! [Service Binding](https://servicebinding.io) specification. The secret of a
+//! provisioned service holds a `type` and a `provider` entries alongside the
+//! environment of the addon, or only the well-known entries (`host`, `port`,
+//! `username`, `password`, ...) when a projection is requested, so workloads
+//! could be bound automatically by Spring Boot or Quarkus.
+
+use std::{collections::BTreeMap, fmt::Debug};
+
+use hyper::Uri;
+use k8s_openapi::{api::core::v1::Secret, NamespaceResourceScope};
+use kube::{CustomResourceExt, Resource, ResourceExt};
+
+use crate::svc::{
+    crd::{Binding, ServiceBinding},
+    k8s::secret,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const PROVIDER: &str = "clever-cloud";
+pub const TYPE_KEY: &str = "type";
+pub const PROVIDER_KEY: &str = "provider";
+
+// -----------------------------------------------------------------------------
+// Mapping structure
+
+/// well-known entries of the service binding specification of an addon
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Mapping {
+    /// type of the provisioned service, e.g. `postgresql`
+    pub kind: &'static str,
+    /// pairs of well-known entries and environment keys of the addon
+    pub keys: &'static [(&'static str, &'static str)],
+}
+
+// -----------------------------------------------------------------------------
+// Endpoint structure
+
+/// connection endpoint of an addon, as written in the status of its custom
+/// resource
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Endpoint {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub database: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+pub fn name<T>(obj: &T) -> String
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+{
+    format!("{}-binding", obj.name_any())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the reference to the binding secret to write in the status of the
+/// custom resource
+pub fn reference<T>(obj: &T) -> Binding
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+{
+    Binding { name: name(obj) }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secrets)))]
+/// returns the entries of the binding secret. The environment of the addon is
+/// kept, unless a projection is requested in which case only the well-known
+/// entries are written
+pub fn project(
+    secrets: &BTreeMap<String, String>,
+    mapping: &Mapping,
+    binding: Option<&ServiceBinding>,
+) -> BTreeMap<String, String> {
+    let projection = binding.map(|b| b.projection).unwrap_or_default();
+    let kind = binding
+        .and_then(|b| b.kind.to_owned())
+        .unwrap_or_else(|| mapping.kind.to_string());
+
+    let mut entries = if projection {
+        BTreeMap::new()
+    } else {
+        secrets.to_owned()
+    };
+
+    for (entry, key) in mapping.keys {
+        if let Some(value) = secrets.get(*key) {
+            entries.insert(entry.to_string(), value.to_owned());
+        }
+    }
+
+    entries.insert(TYPE_KEY.to_string(), kind);
+    entries.insert(PROVIDER_KEY.to_string(), PROVIDER.to_string());
+    entries
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secrets)))]
+/// returns the binding secret of the custom resource, its type is
+/// `servicebinding.io/<type>` as recommended by the specification
+pub fn new<T>(
+    obj: &T,
+    secrets: &BTreeMap<String, String>,
+    mapping: &Mapping,
+    binding: Option<&ServiceBinding>,
+) -> Secret
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    let entries = project(secrets, mapping, binding);
+    let kind = entries.get(TYPE_KEY).cloned().unwrap_or_default();
+
+    let mut s = secret::named(obj, &name(obj), entries);
+    s.type_ = Some(format!("servicebinding.io/{}", kind));
+    s
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secrets)))]
+/// returns the connection endpoint of the addon, read from the well-known
+/// `host`, `port` and `database` entries of its environment. Missing entries
+/// are parsed from the `uri` entry, if any, credentials are never returned
+pub fn endpoint(secrets: &BTreeMap<String, String>, mapping: &Mapping) -> Endpoint {
+    let get = |entry: &str| {
+        mapping
+            .keys
+            .iter()
+            .find(|(e, _)| *e == entry)
+            .and_then(|(_, key)| secrets.get(*key))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let uri = get("uri").and_then(|uri| uri.parse::<Uri>().ok());
+    let authority = uri.as_ref().and_then(Uri::authority);
+
+    let host = get("host").or_else(|| authority.map(|authority| authority.host().to_string()));
+    let port = get("port")
+        .and_then(|port| port.parse().ok())
+        .or_else(|| authority.and_then(|authority| authority.port_u16()))
+        .or_else(|| match uri.as_ref().and_then(Uri::scheme_str) {
+            Some("https") => Some(443),
+            Some("http") => Some(80),
+            _ => None,
+        });
+
+    let database = get("database").or_else(|| {
+        uri.as_ref()
+            .map(|uri| uri.path().trim_matches('/').to_string())
+            .filter(|database| !database.is_empty())
+    });
+
+    Endpoint {
+        host,
+        port,
+        database,
+    }
+}
@@ -0,0 +1,207 @@
+//! # Cache module
+//!
+//! This module provide an in-memory cache of kubernetes resources shared by
+//! all reconcilers, e.g. the secrets overriding the configuration of the
+//! Clever Cloud client. The cache is fed by a reflector per watched namespace,
+//! so reconcilers read resources from the local store and only request the
+//! api server while the store is not synchronised yet.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Formatter},
+    hash::Hash,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use futures::{future::join_all, StreamExt};
+use k8s_openapi::NamespaceResourceScope;
+use kube::{
+    runtime::{
+        reflector::{self, store::Writer, ObjectRef, Store},
+        watcher, WatchStreamExt,
+    },
+    Resource,
+};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_counter_vec, CounterVec};
+use serde::de::DeserializeOwned;
+use tracing::{debug, info, warn};
+
+use crate::svc::k8s::{api, resource};
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static RESOURCE_CACHE_REQUEST: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "kubernetes_client_resource_cache",
+            "number of resources read from the cache or the api server",
+        ),
+        &["kind", "result"]
+    )
+    .expect("metrics 'kubernetes_client_resource_cache' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// Shard structure
+
+/// store of the resources of a namespace, or of all namespaces, with the
+/// writer of the reflector feeding it until the cache is run
+struct Shard<K>
+where
+    K: Resource + 'static,
+    <K as Resource>::DynamicType: Eq + Hash,
+{
+    store: Store<K>,
+    writer: Mutex<Option<Writer<K>>>,
+    synced: Arc<AtomicBool>,
+}
+
+// -----------------------------------------------------------------------------
+// ResourceCache structure
+
+/// resources of a kind, keyed by watched namespace. The empty key holds the
+/// resources of all namespaces
+pub struct ResourceCache<K>
+where
+    K: Resource + 'static,
+    <K as Resource>::DynamicType: Eq + Hash,
+{
+    client: kube::Client,
+    shards: BTreeMap<String, Shard<K>>,
+}
+
+impl<K> Debug for ResourceCache<K>
+where
+    K: Resource + 'static,
+    <K as Resource>::DynamicType: Eq + Hash,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResourceCache")
+            .field("namespaces", &self.shards.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<K> ResourceCache<K>
+where
+    K: Resource<Scope = NamespaceResourceScope>
+        + DeserializeOwned
+        + Clone
+        + Debug
+        + Send
+        + Sync
+        + 'static,
+    <K as Resource>::DynamicType: Eq + Hash + Clone + Default + Send + Sync,
+{
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+    /// returns a cache of the resources of the given namespaces, or of all
+    /// namespaces, if none is given. The cache is empty until it is run
+    pub fn new(client: kube::Client, namespaces: &[String]) -> Self {
+        let namespaces = if namespaces.is_empty() {
+            vec![String::new()]
+        } else {
+            namespaces.to_owned()
+        };
+
+        let shards = namespaces
+            .into_iter()
+            .map(|namespace| {
+                let (store, writer) = reflector::store();
+                let shard = Shard {
+                    store,
+                    writer: Mutex::new(Some(writer)),
+                    synced: Arc::new(AtomicBool::new(false)),
+                };
+
+                (namespace, shard)
+            })
+            .collect();
+
+        Self { client, shards }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    /// returns the resource with the given namespace and name, from the store
+    /// once it is synchronised or else from the api server
+    pub async fn get(&self, namespace: &str, name: &str) -> Result<Option<K>, kube::Error> {
+        let kind = K::kind(&Default::default()).to_string();
+        let shard = self
+            .shards
+            .get(namespace)
+            .or_else(|| self.shards.get(""))
+            .filter(|shard| shard.synced.load(Ordering::Acquire));
+
+        if let Some(shard) = shard {
+            #[cfg(feature = "metrics")]
+            RESOURCE_CACHE_REQUEST
+                .with_label_values(&[&kind, "hit"])
+                .inc();
+
+            let reference = ObjectRef::new(name).within(namespace);
+            return Ok(shard.store.get(&reference).map(|obj| (*obj).to_owned()));
+        }
+
+        debug!(
+            kind = &kind,
+            namespace = namespace,
+            name = name,
+            "Cache of resources is not synchronised, request the api server",
+        );
+
+        #[cfg(feature = "metrics")]
+        RESOURCE_CACHE_REQUEST
+            .with_label_values(&[&kind, "miss"])
+            .inc();
+
+        resource::get(self.client.to_owned(), namespace, name).await
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    /// feed the stores of the cache with events of the api server, it only
+    /// returns if the cache has already been run
+    pub async fn run(&self) {
+        let kind = K::kind(&Default::default()).to_string();
+        let reflectors = self.shards.iter().filter_map(|(namespace, shard)| {
+            let writer = shard.writer.lock().ok()?.take()?;
+            let namespace = Some(namespace.as_str()).filter(|ns| !ns.is_empty());
+            let synced = shard.synced.to_owned();
+            let kind = kind.to_owned();
+
+            info!(
+                kind = &kind,
+                namespace = namespace.unwrap_or("<all>"),
+                "Start to cache resources",
+            );
+
+            let stream = watcher(
+                api::<K>(self.client.to_owned(), namespace),
+                watcher::Config::default(),
+            )
+            .default_backoff();
+
+            Some(reflector::reflector(writer, stream).for_each(move |event| {
+                match event {
+                    Ok(watcher::Event::Restarted(_)) => synced.store(true, Ordering::Release),
+                    Ok(_) => {}
+                    Err(err) => warn!(
+                        kind = &kind,
+                        error = err.to_string(),
+                        "Failed to watch resources to cache",
+                    ),
+                }
+
+                futures::future::ready(())
+            }))
+        });
+
+        join_all(reflectors).await;
+    }
+}
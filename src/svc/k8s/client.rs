@@ -1,38 +1,109 @@
 //! # Client module
 //!
-//! This module provide an helper to create a kubernetes client
+//! This module provide an helper to create a kubernetes client, which goes
+//! through the configured proxy, if any. Tls sessions are negotiated using
+//! openssl if the operator is built with the `crypto-openssl` feature or
+//! rustls otherwise
 
 use std::{convert::TryFrom, path::PathBuf};
 
+use clevercloud_sdk::oauth10a::proxy::{self, ProxyBuilder};
+use hyper::{
+    client::HttpConnector,
+    header::{self, HeaderValue, InvalidHeaderValue},
+};
+use hyper_proxy::ProxyConnector;
+#[cfg(not(feature = "crypto-openssl"))]
+use hyper_rustls::HttpsConnectorBuilder;
 use kube::{
-    config::{KubeConfigOptions, Kubeconfig, KubeconfigError},
+    client::ConfigExt,
+    config::{InferConfigError, KubeConfigOptions, Kubeconfig, KubeconfigError},
     Config,
 };
+use tower::ServiceBuilder;
+use tracing::info;
+
+use crate::svc::cfg::Proxy;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("failed to read kubernetes configuration file, {0}")]
     Kubeconfig(KubeconfigError),
+    #[error("failed to infer kubernetes configuration, {0}")]
+    Infer(InferConfigError),
     #[error("failed to create kubernetes client, {0}")]
     CreateClient(kube::Error),
+    #[error("failed to configure proxy of kubernetes client, {0}")]
+    Proxy(proxy::Error),
+    #[error("failed to set authorization of the proxy, {0}")]
+    ProxyAuthorization(InvalidHeaderValue),
 }
 
 #[cfg_attr(feature = "trace", tracing::instrument)]
 /// returns a new kubernetes client from the given path if defined
-/// or retrieve it from environment or defaults paths
-pub async fn try_new(path: Option<PathBuf>) -> Result<kube::Client, Error> {
-    match path {
-        None => kube::Client::try_default()
-            .await
-            .map_err(Error::CreateClient),
+/// or retrieve it from environment or defaults paths. The client goes through
+/// the given proxy or the one configured by the environment, if any
+pub async fn try_new(path: Option<PathBuf>, proxy: &Option<Proxy>) -> Result<kube::Client, Error> {
+    let config = match path {
+        None => Config::infer().await.map_err(Error::Infer)?,
         Some(path) => {
             let kubeconfig = Kubeconfig::read_from(path).map_err(Error::Kubeconfig)?;
             let opts = KubeConfigOptions::default();
-            let config = Config::from_custom_kubeconfig(kubeconfig, &opts)
+            Config::from_custom_kubeconfig(kubeconfig, &opts)
                 .await
-                .map_err(Error::Kubeconfig)?;
-
-            kube::Client::try_from(config).map_err(Error::CreateClient)
+                .map_err(Error::Kubeconfig)?
         }
+    };
+
+    let proxy = match Proxy::resolve(proxy) {
+        Some(proxy) => proxy,
+        None => return kube::Client::try_from(config).map_err(Error::CreateClient),
+    };
+
+    info!(
+        cluster = config.cluster_url.to_string(),
+        "Connect kubernetes api server through proxy",
+    );
+
+    let authorization = proxy.authorization();
+    let mut upstream = ProxyBuilder::try_from(
+        proxy
+            .url()
+            .expect("to have one of http or https value in proxy configuration"),
+        proxy.no.to_owned(),
+    )
+    .map_err(Error::Proxy)?;
+
+    if let Some(authorization) = authorization {
+        upstream.set_header(
+            header::PROXY_AUTHORIZATION,
+            HeaderValue::from_str(&authorization).map_err(Error::ProxyAuthorization)?,
+        );
     }
+
+    // the proxy only opens the tunnel, the tls session is negotiated with the
+    // api server using the certificate authority of the kubernetes config
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+
+    #[cfg(not(feature = "crypto-openssl"))]
+    let https = HttpsConnectorBuilder::new()
+        .with_tls_config(config.rustls_client_config().map_err(Error::CreateClient)?)
+        .https_or_http()
+        .enable_http1()
+        .wrap_connector(ProxyConnector::from_proxy_unsecured(http, upstream));
+
+    #[cfg(feature = "crypto-openssl")]
+    let https = config
+        .openssl_https_connector_with_connector(ProxyConnector::from_proxy_unsecured(
+            http, upstream,
+        ))
+        .map_err(Error::CreateClient)?;
+
+    let service = ServiceBuilder::new()
+        .layer(config.base_uri_layer())
+        .option_layer(config.auth_layer().map_err(Error::CreateClient)?)
+        .service(hyper::Client::builder().build(https));
+
+    Ok(kube::Client::new(service, config.default_namespace))
 }
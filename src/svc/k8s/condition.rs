@@ -0,0 +1,414 @@
+//! # Condition module
+//!
+//! This module provide helpers to manipulate conditions of custom resources'
+//! status following the kubernetes api conventions.
+//!
+//! See following links for more details:
+//! - <https://github.com/kubernetes/community/blob/master/contributors/devel/sig-architecture/api-conventions.md#typical-status-properties>
+//! - <https://github.com/kubernetes/apimachinery/blob/master/pkg/api/meta/conditions.go>
+
+use std::fmt::{self, Debug, Display, Formatter};
+
+use chrono::{SecondsFormat, Utc};
+use k8s_openapi::NamespaceResourceScope;
+use kube::{Resource, ResourceExt};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::svc::k8s::resource;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const READY: &str = "Ready";
+pub const ERROR: &str = "Error";
+pub const RESTORED: &str = "Restored";
+pub const MIGRATED: &str = "Migrated";
+pub const HEALTHY: &str = "Healthy";
+pub const FOLLOWERS: &str = "Followers";
+
+pub const REASON_WAITING_FOR_DEPENDENCIES: &str = "WaitingForDependencies";
+pub const REASON_RECONCILIATION_FAILED: &str = "ReconciliationFailed";
+
+pub const REASON_MAX_LENGTH: usize = 1024;
+pub const MESSAGE_MAX_LENGTH: usize = 32768;
+
+// -----------------------------------------------------------------------------
+// Status enumeration
+
+#[derive(
+    JsonSchema, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Default,
+)]
+pub enum Status {
+    True,
+    False,
+    #[default]
+    Unknown,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::True => write!(f, "True"),
+            Self::False => write!(f, "False"),
+            Self::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+impl From<bool> for Status {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(b: bool) -> Self {
+        if b {
+            Self::True
+        } else {
+            Self::False
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Condition structure
+
+#[derive(JsonSchema, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Condition {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(rename = "status")]
+    pub status: Status,
+    #[serde(rename = "observedGeneration", skip_serializing_if = "Option::is_none")]
+    pub observed_generation: Option<i64>,
+    #[serde(rename = "lastTransitionTime")]
+    pub last_transition_time: String,
+    #[serde(rename = "reason")]
+    pub reason: String,
+    #[serde(rename = "message")]
+    pub message: String,
+}
+
+impl Condition {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn new(kind: &str, status: Status, reason: &str, message: &str) -> Self {
+        Self {
+            kind: kind.to_string(),
+            status,
+            observed_generation: None,
+            last_transition_time: now(),
+            reason: truncate(reason, REASON_MAX_LENGTH),
+            message: truncate(message, MESSAGE_MAX_LENGTH),
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn with_observed_generation(mut self, generation: Option<i64>) -> Self {
+        self.observed_generation = generation;
+        self
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn is_true(&self) -> bool {
+        Status::True == self.status
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Conditions trait
+
+/// provides a setter of the conditions of a custom resource, observed at its
+/// current generation. Implementors only expose the conditions of their status
+pub trait Conditions: Resource {
+    /// returns the conditions of the status, which is created if needed
+    fn conditions_mut(&mut self) -> &mut Vec<Condition>;
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(self)))]
+    /// set the given condition, see [`set`]. Returns if the conditions have
+    /// been modified
+    fn set_condition(&mut self, condition: Condition) -> bool {
+        let generation = self.meta().generation;
+
+        set(
+            self.conditions_mut(),
+            condition.with_observed_generation(generation),
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the current time formatted as expected by kubernetes
+pub fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the given string truncated to the given number of bytes, on a
+/// character boundary
+pub fn truncate(s: &str, max: usize) -> String {
+    if s.len() <= max {
+        return s.to_string();
+    }
+
+    let mut idx = max;
+    while !s.is_char_boundary(idx) {
+        idx -= 1;
+    }
+
+    s[..idx].to_string()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the condition of the given type, if any
+pub fn get<'a>(conditions: &'a [Condition], kind: &str) -> Option<&'a Condition> {
+    conditions.iter().find(|condition| condition.kind == kind)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the condition of the given type is present and true
+pub fn is_true(conditions: &[Condition], kind: &str) -> bool {
+    get(conditions, kind)
+        .map(Condition::is_true)
+        .unwrap_or(false)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// set the given condition in the list, the last transition time is only
+/// updated when the status of the condition flips. Returns if the list has been
+/// modified.
+pub fn set(conditions: &mut Vec<Condition>, condition: Condition) -> bool {
+    let Condition {
+        kind,
+        status,
+        observed_generation,
+        last_transition_time,
+        reason,
+        message,
+    } = condition;
+
+    let reason = truncate(&reason, REASON_MAX_LENGTH);
+    let message = truncate(&message, MESSAGE_MAX_LENGTH);
+
+    let modified = match conditions.iter_mut().find(|c| c.kind == kind) {
+        Some(existing) => {
+            let mut modified = false;
+
+            if existing.status != status {
+                existing.status = status;
+                existing.last_transition_time = last_transition_time;
+                modified = true;
+            }
+
+            if existing.reason != reason {
+                existing.reason = reason;
+                modified = true;
+            }
+
+            if existing.message != message {
+                existing.message = message;
+                modified = true;
+            }
+
+            if observed_generation.is_some() && existing.observed_generation != observed_generation
+            {
+                existing.observed_generation = observed_generation;
+                modified = true;
+            }
+
+            modified
+        }
+        None => {
+            conditions.push(Condition {
+                kind,
+                status,
+                observed_generation,
+                last_transition_time,
+                reason,
+                message,
+            });
+
+            true
+        }
+    };
+
+    normalize(conditions);
+    modified
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// remove the condition of the given type. Returns if the list has been
+/// modified.
+pub fn remove(conditions: &mut Vec<Condition>, kind: &str) -> bool {
+    let len = conditions.len();
+
+    conditions.retain(|condition| condition.kind != kind);
+    len != conditions.len()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// normalize the list of conditions, so equivalent lists are equal once
+/// serialized. Conditions are sorted by type, only the first condition of a
+/// given type is kept and reason and message are truncated. Conditions set by
+/// third parts are preserved.
+pub fn normalize(conditions: &mut Vec<Condition>) {
+    let mut kinds = vec![];
+
+    conditions.retain(|condition| {
+        if kinds.contains(&condition.kind) {
+            return false;
+        }
+
+        kinds.push(condition.kind.to_owned());
+        true
+    });
+
+    for condition in conditions.iter_mut() {
+        condition.reason = truncate(&condition.reason, REASON_MAX_LENGTH);
+        condition.message = truncate(&condition.message, MESSAGE_MAX_LENGTH);
+    }
+
+    conditions.sort_by(|a, b| a.kind.cmp(&b.kind));
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client, f)))]
+/// apply the given function on the conditions of the object and patch its
+/// status, if they have been modified. This works on any custom resource
+/// exposing conditions in `status.conditions`
+pub async fn update<T, F>(client: kube::Client, obj: &T, f: F) -> Result<(), kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope>
+        + ResourceExt
+        + DeserializeOwned
+        + Serialize
+        + Clone
+        + Debug,
+    <T as Resource>::DynamicType: Default,
+    F: FnOnce(&mut Vec<Condition>) -> bool,
+{
+    let origin = serde_json::to_value(obj).map_err(kube::Error::SerdeError)?;
+    let mut conditions: Vec<Condition> = origin
+        .pointer("/status/conditions")
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(kube::Error::SerdeError)?
+        .unwrap_or_default();
+
+    if !f(&mut conditions) {
+        return Ok(());
+    }
+
+    let mut modified = origin.to_owned();
+    if let Value::Object(root) = &mut modified {
+        let status = root
+            .entry("status")
+            .or_insert_with(|| Value::Object(Default::default()));
+
+        if let Value::Object(status) = status {
+            status.insert(
+                "conditions".to_string(),
+                serde_json::to_value(conditions).map_err(kube::Error::SerdeError)?,
+            );
+        }
+    }
+
+    let patch = json_patch::diff(&origin, &modified);
+    resource::patch_status(client, obj.to_owned(), patch).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::svc::crd::config_provider::tests::provider;
+
+    use super::{get, set, Condition, Conditions, Status, READY};
+
+    const THEN: &str = "2023-01-01T00:00:00Z";
+    const NOW: &str = "2023-01-02T00:00:00Z";
+
+    fn condition(kind: &str, status: Status, reason: &str, message: &str, at: &str) -> Condition {
+        Condition {
+            last_transition_time: at.to_string(),
+            ..Condition::new(kind, status, reason, message)
+        }
+    }
+
+    #[test]
+    fn set_updates_transition_time_when_status_flips() {
+        let mut conditions = vec![condition(READY, Status::False, "Pending", "", THEN)];
+
+        assert!(set(
+            &mut conditions,
+            condition(READY, Status::True, "AddonProvisioned", "", NOW)
+        ));
+
+        let ready = get(&conditions, READY).expect("ready condition");
+        assert_eq!(Status::True, ready.status);
+        assert_eq!("AddonProvisioned", ready.reason);
+        assert_eq!(NOW, ready.last_transition_time);
+    }
+
+    #[test]
+    fn set_is_a_no_op_when_nothing_changes() {
+        let mut conditions = vec![condition(
+            READY,
+            Status::True,
+            "AddonProvisioned",
+            "ok",
+            THEN,
+        )];
+        let expected = conditions.to_owned();
+
+        assert!(!set(
+            &mut conditions,
+            condition(READY, Status::True, "AddonProvisioned", "ok", NOW)
+        ));
+        assert_eq!(expected, conditions);
+    }
+
+    #[test]
+    fn set_keeps_transition_time_when_only_the_message_changes() {
+        let mut conditions = vec![condition(READY, Status::False, "Failed", "first", THEN)];
+
+        assert!(set(
+            &mut conditions,
+            condition(READY, Status::False, "Failed", "second", NOW)
+        ));
+
+        let ready = get(&conditions, READY).expect("ready condition");
+        assert_eq!("second", ready.message);
+        assert_eq!(THEN, ready.last_transition_time);
+    }
+
+    #[test]
+    fn set_preserves_unknown_conditions() {
+        let foreign = condition("Synced", Status::True, "ThirdParty", "", THEN);
+        let mut conditions = vec![foreign.to_owned()];
+
+        assert!(set(
+            &mut conditions,
+            condition(READY, Status::True, "AddonProvisioned", "", NOW)
+        ));
+
+        assert_eq!(2, conditions.len());
+        assert_eq!(Some(&foreign), get(&conditions, "Synced"));
+    }
+
+    #[test]
+    fn set_condition_observes_the_generation() {
+        let mut obj = provider("app", "orga_test");
+        obj.metadata.generation = Some(3);
+
+        assert!(obj.set_condition(Condition::new(READY, Status::True, "Provisioned", "")));
+        assert!(!obj.set_condition(Condition::new(READY, Status::True, "Provisioned", "")));
+
+        let conditions = obj
+            .status
+            .map(|status| status.conditions)
+            .unwrap_or_default();
+        let ready = get(&conditions, READY).expect("ready condition");
+        assert_eq!(Some(3), ready.observed_generation);
+    }
+}
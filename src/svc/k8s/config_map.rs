@@ -0,0 +1,96 @@
+//! # Config map module
+//!
+//! This module provide helpers to generate config maps from a custom resource,
+//! they hold the variables classified as non-sensitive, which do not need to
+//! be kept in a secret
+
+use std::{collections::BTreeMap, fmt::Debug};
+
+use k8s_openapi::{api::core::v1::ConfigMap, NamespaceResourceScope};
+use kube::{
+    api::{DeleteParams, ObjectMeta},
+    Api, CustomResourceExt, Resource, ResourceExt,
+};
+use tracing::info;
+
+use crate::svc::k8s::{resource, secret};
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+pub fn name<T>(obj: &T) -> String
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+{
+    format!("{}-config", obj.name_any())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(variables)))]
+/// returns the variables split between the sensitive ones and the ones
+/// matching one of the given glob patterns, which are non-sensitive
+pub fn split(
+    variables: BTreeMap<String, String>,
+    patterns: &[String],
+) -> (BTreeMap<String, String>, BTreeMap<String, String>) {
+    variables
+        .into_iter()
+        .partition(|(key, _)| !patterns.iter().any(|pattern| secret::matches(pattern, key)))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(data)))]
+/// returns the config map of the custom resource holding the given data
+pub fn new<T>(obj: &T, data: BTreeMap<String, String>) -> ConfigMap
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    let owner = resource::owner_reference(obj);
+    let metadata = ObjectMeta {
+        name: Some(name(obj)),
+        namespace: obj.namespace(),
+        owner_references: Some(vec![owner]),
+        ..Default::default()
+    };
+
+    ConfigMap {
+        metadata,
+        data: Some(data),
+        ..Default::default()
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// delete the config map of the custom resource, if it exists and is owned by
+/// it, and returns if it has been deleted
+pub async fn delete<T>(client: kube::Client, obj: &T) -> Result<bool, kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+{
+    let (namespace, _) = resource::namespaced_name(obj);
+    let (uid, name) = (obj.uid().unwrap_or_default(), name(obj));
+
+    let current: Option<ConfigMap> = resource::get(client.to_owned(), &namespace, &name).await?;
+    if !current.map_or(false, |current| {
+        current
+            .owner_references()
+            .iter()
+            .any(|owner| owner.uid == uid)
+    }) {
+        return Ok(false);
+    }
+
+    info!(
+        namespace = &namespace,
+        name = &name,
+        "Delete kubernetes config map",
+    );
+
+    match Api::<ConfigMap>::namespaced(client, &namespace)
+        .delete(&name, &DeleteParams::default())
+        .await
+    {
+        Ok(_) => Ok(true),
+        Err(kube::Error::Api(err)) if err.code == 404 => Ok(false),
+        Err(err) => Err(err),
+    }
+}
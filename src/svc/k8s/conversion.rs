@@ -0,0 +1,309 @@
+//! # Conversion module
+//!
+//! This module provide the conversion of custom resources between the versions
+//! served by their custom resource definition. Previous versions are still
+//! served, marked as deprecated, and the api server converts objects through
+//! the conversion webhook of the operator, so evolutions of the specification
+//! (e.g. renamed fields, structured options) do not break stored objects.
+//!
+//! See <https://kubernetes.io/docs/tasks/extend-kubernetes/custom-resources/custom-resource-definition-versioning/>
+
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+    CustomResourceConversion, CustomResourceDefinition, CustomResourceDefinitionVersion,
+    ServiceReference, WebhookClientConfig, WebhookConversion,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::svc::cfg::Webhook;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const CONVERT_PATH: &str = "/convert";
+pub const CONVERSION_REVIEW_API_VERSION: &str = "apiextensions.k8s.io/v1";
+pub const CONVERSION_REVIEW_KIND: &str = "ConversionReview";
+pub const CONVERSION_STRATEGY_WEBHOOK: &str = "Webhook";
+pub const RESULT_SUCCESS: &str = "Success";
+pub const RESULT_FAILURE: &str = "Failure";
+
+/// annotation asking cert-manager to inject the certificate authority of the
+/// webhook in the conversion configuration of the custom resource definition
+pub const INJECT_CA_FROM_ANNOTATION: &str = "cert-manager.io/inject-ca-from";
+
+/// previous versions, which are still served, of each kind
+pub const DEPRECATED_VERSIONS: &[(&str, &[&str])] = &[
+    ("Pulsar", &["v1beta1"]),
+    ("Runtime", &["v1beta1"]),
+    ("CellarBucket", &["v1beta1"]),
+    ("Backup", &["v1beta1"]),
+    ("Addon", &["v1beta1"]),
+];
+
+/// conversions of objects between two versions of a kind. Versions without
+/// conversion share the same schema, only the api version is rewritten
+pub const CONVERSIONS: &[Conversion] = &[];
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to parse api version '{0}', expected 'group/version'")]
+    ApiVersion(String),
+    #[error("failed to convert '{0}' to another group '{1}'")]
+    Group(String, String),
+    #[error("failed to convert kind '{0}' from '{1}' to '{2}', {3}")]
+    Convert(String, String, String, String),
+}
+
+// -----------------------------------------------------------------------------
+// Conversion structure
+
+pub struct Conversion {
+    /// kind of the custom resource, e.g. `Pulsar`
+    pub kind: &'static str,
+    /// version of the given object, e.g. `v1beta1`
+    pub from: &'static str,
+    /// version of the returned object, e.g. `v1`
+    pub to: &'static str,
+    /// rewrite the object in place, e.g. rename or move fields
+    pub convert: fn(&mut Value) -> Result<(), String>,
+}
+
+// -----------------------------------------------------------------------------
+// ConversionReview structure
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConversionReview {
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "request", default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<ConversionRequest>,
+    #[serde(rename = "response", default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<ConversionResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConversionRequest {
+    #[serde(rename = "uid")]
+    pub uid: String,
+    #[serde(rename = "desiredAPIVersion")]
+    pub desired_api_version: String,
+    #[serde(rename = "objects", default)]
+    pub objects: Vec<Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConversionResponse {
+    #[serde(rename = "uid")]
+    pub uid: String,
+    #[serde(rename = "convertedObjects")]
+    pub converted_objects: Vec<Value>,
+    #[serde(rename = "result")]
+    pub result: ConversionResult,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConversionResult {
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "message", default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the response of the conversion review, objects are all converted
+/// or the review fails
+pub fn review(review: ConversionReview) -> ConversionReview {
+    let (uid, result) = match review.request {
+        Some(request) => (
+            request.uid,
+            request
+                .objects
+                .into_iter()
+                .map(|obj| convert(obj, &request.desired_api_version))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| err.to_string()),
+        ),
+        None => (
+            String::new(),
+            Err("failed to find the request of the conversion review".to_string()),
+        ),
+    };
+
+    let response = match result {
+        Ok(converted_objects) => ConversionResponse {
+            uid,
+            converted_objects,
+            result: ConversionResult {
+                status: RESULT_SUCCESS.to_string(),
+                message: None,
+            },
+        },
+        Err(message) => {
+            warn!(error = &message, "Could not convert custom resources");
+
+            ConversionResponse {
+                uid,
+                converted_objects: vec![],
+                result: ConversionResult {
+                    status: RESULT_FAILURE.to_string(),
+                    message: Some(message),
+                },
+            }
+        }
+    };
+
+    ConversionReview {
+        api_version: CONVERSION_REVIEW_API_VERSION.to_string(),
+        kind: CONVERSION_REVIEW_KIND.to_string(),
+        request: None,
+        response: Some(response),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the object converted to the desired api version, e.g.
+/// `api.clever-cloud.com/v1`
+pub fn convert(mut obj: Value, desired: &str) -> Result<Value, Error> {
+    let kind = obj
+        .get("kind")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    let current = obj
+        .get("apiVersion")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    let (group, from) = current
+        .split_once('/')
+        .ok_or_else(|| Error::ApiVersion(current.to_owned()))?;
+
+    let (desired_group, to) = desired
+        .split_once('/')
+        .ok_or_else(|| Error::ApiVersion(desired.to_string()))?;
+
+    if group != desired_group {
+        return Err(Error::Group(current.to_owned(), desired_group.to_string()));
+    }
+
+    if from == to {
+        return Ok(obj);
+    }
+
+    if let Some(conversion) = CONVERSIONS.iter().find(|conversion| {
+        conversion.kind == kind && conversion.from == from && conversion.to == to
+    }) {
+        (conversion.convert)(&mut obj).map_err(|err| {
+            Error::Convert(kind.to_owned(), from.to_string(), to.to_string(), err)
+        })?;
+    }
+
+    if let Some(obj) = obj.as_object_mut() {
+        obj.insert("apiVersion".into(), Value::from(desired));
+    }
+
+    debug!(
+        kind = &kind,
+        from = from,
+        to = to,
+        "Convert custom resource"
+    );
+    Ok(obj)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the custom resource definition serving the deprecated versions of
+/// its kind, with the schema of the storage version. If the webhook is
+/// enabled, objects are converted by the operator, otherwise the api server
+/// only rewrites their api version
+pub fn versions(mut crd: CustomResourceDefinition, webhook: &Webhook) -> CustomResourceDefinition {
+    let deprecated = DEPRECATED_VERSIONS
+        .iter()
+        .find(|(kind, _)| *kind == crd.spec.names.kind)
+        .map(|(_, versions)| *versions)
+        .unwrap_or_default();
+
+    let storage = match crd.spec.versions.iter().find(|version| version.storage) {
+        Some(storage) => storage.to_owned(),
+        None => return crd,
+    };
+
+    let mut versions: Vec<CustomResourceDefinitionVersion> = deprecated
+        .iter()
+        .filter(|name| {
+            !crd.spec
+                .versions
+                .iter()
+                .any(|version| version.name == **name)
+        })
+        .map(|name| CustomResourceDefinitionVersion {
+            name: name.to_string(),
+            storage: false,
+            deprecated: Some(true),
+            deprecation_warning: Some(format!(
+                "{}/{} {} is deprecated, use {}/{}",
+                crd.spec.group, name, crd.spec.names.kind, crd.spec.group, storage.name
+            )),
+            ..storage.to_owned()
+        })
+        .collect();
+
+    if versions.is_empty() {
+        return crd;
+    }
+
+    versions.append(&mut crd.spec.versions);
+    crd.spec.versions = versions;
+
+    if !webhook.enabled {
+        return crd;
+    }
+
+    let (namespace, name) = match webhook.service.split_once('/') {
+        Some(reference) => reference,
+        None => {
+            warn!(
+                service = &webhook.service,
+                "Could not parse service of the webhook, expected 'namespace/name'"
+            );
+            return crd;
+        }
+    };
+
+    crd.spec.conversion = Some(CustomResourceConversion {
+        strategy: CONVERSION_STRATEGY_WEBHOOK.to_string(),
+        webhook: Some(WebhookConversion {
+            client_config: Some(WebhookClientConfig {
+                service: Some(ServiceReference {
+                    namespace: namespace.to_string(),
+                    name: name.to_string(),
+                    path: Some(CONVERT_PATH.to_string()),
+                    port: None,
+                }),
+                ..Default::default()
+            }),
+            conversion_review_versions: vec!["v1".to_string()],
+        }),
+    });
+
+    crd.metadata
+        .annotations
+        .get_or_insert_with(Default::default)
+        .insert(
+            INJECT_CA_FROM_ANNOTATION.to_string(),
+            webhook.service.to_owned(),
+        );
+
+    crd
+}
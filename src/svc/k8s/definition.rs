@@ -0,0 +1,286 @@
+//! # Definition module
+//!
+//! This module provide helpers to install and upgrade the custom resource
+//! definitions of the operator at startup, see [`apply`]. Definitions are
+//! applied using server-side apply without forcing the ownership of fields, so
+//! a field changed by another field manager, e.g. `kubectl edit`, is reported
+//! as a conflict instead of being silently overwritten.
+//!
+//! It also provide a preflight check, see [`preflight`], which compares the
+//! schemas of the custom resource definitions stored in the cluster with the
+//! ones of the operator, as the api server drops fields that an older schema
+//! does not know.
+
+use std::{collections::BTreeSet, time::Duration};
+
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+    CustomResourceDefinition, CustomResourceDefinitionVersion, JSONSchemaProps,
+    JSONSchemaPropsOrArray,
+};
+use kube::{
+    api::{Patch, PatchParams},
+    runtime::wait::{self, await_condition, conditions},
+    Api, Client,
+};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_int_gauge_vec, IntGaugeVec};
+use tracing::{debug, info, warn};
+
+use crate::svc::{
+    cfg::Configuration,
+    k8s::{conversion, rbac},
+};
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static CUSTOM_RESOURCE_DEFINITION_SKEW: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        opts!(
+            "kubernetes_operator_custom_resource_definition_skew",
+            "custom resource definitions stored in the cluster which are older than the operator",
+        ),
+        &["name"]
+    )
+    .expect("metrics 'kubernetes_operator_custom_resource_definition_skew' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to apply custom resource definition '{0}', some fields are owned by another field manager, {1}")]
+    Conflict(String, String),
+    #[error("failed to apply custom resource definition '{0}', {1}")]
+    Apply(String, kube::Error),
+    #[error("failed to wait for custom resource definition '{0}' to be established, {1}")]
+    Wait(String, wait::Error),
+    #[error("custom resource definition '{0}' is not established after {1}s")]
+    Timeout(String, u64),
+    #[error("custom resource definitions are older than the operator, {0}")]
+    Skew(String),
+}
+
+// -----------------------------------------------------------------------------
+// Skew enumeration
+
+/// difference between a custom resource definition stored in the cluster and
+/// the one of the operator
+#[derive(thiserror::Error, PartialEq, Eq, Clone, Debug)]
+pub enum Skew {
+    #[error("not installed")]
+    NotInstalled,
+    #[error("version '{0}' is not served")]
+    NotServed(String),
+    #[error("version '{0}' misses field(s) '{1}'")]
+    MissingFields(String, String),
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the custom resource definitions of the operator, with their served
+/// versions
+pub fn expected(config: &Configuration) -> Vec<CustomResourceDefinition> {
+    rbac::reconcilers()
+        .into_iter()
+        .map(|(crd, _)| conversion::versions(crd, &config.operator.webhook))
+        .collect()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the schema of the version of a custom resource definition, if any
+fn schema(version: &CustomResourceDefinitionVersion) -> Option<&JSONSchemaProps> {
+    version
+        .schema
+        .as_ref()
+        .and_then(|schema| schema.open_api_v3_schema.as_ref())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// collect the paths of the properties of the schema, and the ones whose
+/// unknown fields are preserved, items of arrays are suffixed by `[]`
+fn collect(
+    prefix: &str,
+    schema: &JSONSchemaProps,
+    fields: &mut BTreeSet<String>,
+    preserved: &mut BTreeSet<String>,
+) {
+    if schema.x_kubernetes_preserve_unknown_fields == Some(true) {
+        preserved.insert(prefix.to_string());
+    }
+
+    for (name, property) in schema.properties.iter().flatten() {
+        let path = if prefix.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+
+        collect(&path, property, fields, preserved);
+        fields.insert(path);
+    }
+
+    if let Some(JSONSchemaPropsOrArray::Schema(items)) = &schema.items {
+        collect(&format!("{}[]", prefix), items, fields, preserved);
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the paths of the properties of the schema, and the ones whose
+/// unknown fields are preserved
+fn fields(schema: Option<&JSONSchemaProps>) -> (BTreeSet<String>, BTreeSet<String>) {
+    let mut fields = BTreeSet::new();
+    let mut preserved = BTreeSet::new();
+    if let Some(schema) = schema {
+        collect("", schema, &mut fields, &mut preserved);
+    }
+
+    (fields, preserved)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the versions served by the installed custom resource definition, if
+/// it serves the versions and the fields known by the operator
+pub fn skew(
+    expected: &CustomResourceDefinition,
+    installed: &CustomResourceDefinition,
+) -> Result<Vec<String>, Skew> {
+    let mut served = vec![];
+    for version in &expected.spec.versions {
+        let current = installed
+            .spec
+            .versions
+            .iter()
+            .find(|current| current.name == version.name && current.served)
+            .ok_or_else(|| Skew::NotServed(version.name.to_owned()))?;
+
+        let (expected, _) = fields(schema(version));
+        let (current, preserved) = fields(schema(current));
+
+        // fields below a property preserving unknown fields are kept by the
+        // api server, even if the schema does not know them
+        let missing: Vec<_> = expected
+            .difference(&current)
+            .filter(|field| {
+                !preserved
+                    .iter()
+                    .any(|prefix| prefix.is_empty() || field.starts_with(&format!("{}.", prefix)))
+            })
+            .cloned()
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(Skew::MissingFields(
+                version.name.to_owned(),
+                missing.join("', '"),
+            ));
+        }
+
+        served.push(version.name.to_owned());
+    }
+
+    Ok(served)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client, config)))]
+/// compare the custom resource definitions stored in the cluster with the ones
+/// of the operator and log the ones which are older. It fails if any is older
+/// and the operator is configured to refuse to start in such case
+pub async fn preflight(client: Client, config: &Configuration) -> Result<(), Error> {
+    let api: Api<CustomResourceDefinition> = Api::all(client);
+
+    let mut skewed = vec![];
+    for expected in expected(config) {
+        let name = expected.metadata.name.to_owned().unwrap_or_default();
+        let result = match api.get_opt(&name).await {
+            Ok(Some(installed)) => skew(&expected, &installed),
+            Ok(None) => Err(Skew::NotInstalled),
+            Err(err) => {
+                warn!(
+                    name = &name,
+                    error = err.to_string(),
+                    "Could not retrieve custom resource definition to compare it with the one of the operator",
+                );
+                continue;
+            }
+        };
+
+        #[cfg(feature = "metrics")]
+        CUSTOM_RESOURCE_DEFINITION_SKEW
+            .with_label_values(&[&name])
+            .set(i64::from(result.is_err()));
+
+        match result {
+            Ok(served) => debug!(
+                name = &name,
+                versions = served.join(", "),
+                "Custom resource definition is up to date"
+            ),
+            Err(err) => {
+                warn!(
+                    name = &name,
+                    error = err.to_string(),
+                    "Custom resource definition is older than the operator, the api server drops fields it does not know",
+                );
+                skewed.push(format!("'{}' {}", name, err));
+            }
+        }
+    }
+
+    if !skewed.is_empty() && config.operator.custom_resource_definitions.strict {
+        return Err(Error::Skew(skewed.join(", ")));
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client, config)))]
+/// apply the custom resource definitions of the operator and wait for them to
+/// be established, so controllers could watch their custom resources
+pub async fn apply(client: Client, config: &Configuration) -> Result<(), Error> {
+    let api: Api<CustomResourceDefinition> = Api::all(client);
+    let params = PatchParams::apply(&config.operator.server_side_apply.field_manager);
+    let timeout = config.operator.custom_resource_definitions.timeout;
+
+    let mut names = vec![];
+    for crd in expected(config) {
+        let name = crd.metadata.name.to_owned().unwrap_or_default();
+
+        info!(name = &name, "Apply custom resource definition");
+        api.patch(&name, &params, &Patch::Apply(&crd))
+            .await
+            .map_err(|err| match err {
+                kube::Error::Api(err) if err.code == 409 => {
+                    Error::Conflict(name.to_owned(), err.message)
+                }
+                err => Error::Apply(name.to_owned(), err),
+            })?;
+
+        names.push(name);
+    }
+
+    for name in names {
+        debug!(
+            name = &name,
+            timeout = timeout,
+            "Wait for custom resource definition to be established"
+        );
+
+        tokio::time::timeout(
+            Duration::from_secs(timeout),
+            await_condition(api.to_owned(), &name, conditions::is_crd_established()),
+        )
+        .await
+        .map_err(|_| Error::Timeout(name.to_owned(), timeout))?
+        .map_err(|err| Error::Wait(name.to_owned(), err))?;
+    }
+
+    info!("Custom resource definitions are established");
+    Ok(())
+}
@@ -0,0 +1,145 @@
+//! # Dependency module
+//!
+//! This module provide helpers to hold off the provisioning of a custom
+//! resource until the custom resources it depends on, declared in its
+//! `dependsOn` field, are ready. It enables a deterministic bring-up of stacks,
+//! e.g. an application waiting for its database.
+
+use kube::{
+    api::{ApiResource, DynamicObject},
+    Api, Resource,
+};
+use tracing::debug;
+
+use crate::svc::{
+    crd::{
+        addon::Addon, cellar_bucket::CellarBucket, config_provider::ConfigProvider,
+        elasticsearch::ElasticSearch, fs_bucket::FSBucket, gravitee::Gravitee, jenkins::Jenkins,
+        mailpace::Mailpace, mongodb::MongoDb, mysql::MySql, postgresql::PostgreSql, pulsar::Pulsar,
+        redis::Redis, Dependency,
+    },
+    k8s::condition::{self, Condition},
+};
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to depend on custom resource of unknown kind '{0}'")]
+    UnknownKind(String),
+    #[error("failed to find {0} '{1}/{2}'")]
+    NotFound(String, String, String),
+    #[error("{0} '{1}/{2}' is not ready")]
+    NotReady(String, String, String),
+    #[error("failed to retrieve {0} '{1}/{2}', {3}")]
+    Get(String, String, String, kube::Error),
+    #[error("failed to parse conditions of {0} '{1}/{2}', {3}")]
+    Conditions(String, String, String, serde_json::Error),
+}
+
+impl Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns if the error will not be solved by waiting
+    pub fn permanent(&self) -> Option<&'static str> {
+        match self {
+            Self::UnknownKind(_) => Some("UnknownDependencyKind"),
+            _ => None,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the api resource of the custom resources of the given kind which
+/// could be depended on
+pub fn api_resource(kind: &str) -> Option<ApiResource> {
+    let kinds = [
+        (PostgreSql::kind(&()), ApiResource::erase::<PostgreSql>(&())),
+        (MySql::kind(&()), ApiResource::erase::<MySql>(&())),
+        (Redis::kind(&()), ApiResource::erase::<Redis>(&())),
+        (MongoDb::kind(&()), ApiResource::erase::<MongoDb>(&())),
+        (Pulsar::kind(&()), ApiResource::erase::<Pulsar>(&())),
+        (
+            ConfigProvider::kind(&()),
+            ApiResource::erase::<ConfigProvider>(&()),
+        ),
+        (
+            ElasticSearch::kind(&()),
+            ApiResource::erase::<ElasticSearch>(&()),
+        ),
+        (
+            CellarBucket::kind(&()),
+            ApiResource::erase::<CellarBucket>(&()),
+        ),
+        (Addon::kind(&()), ApiResource::erase::<Addon>(&())),
+        (FSBucket::kind(&()), ApiResource::erase::<FSBucket>(&())),
+        (Mailpace::kind(&()), ApiResource::erase::<Mailpace>(&())),
+        (Jenkins::kind(&()), ApiResource::erase::<Jenkins>(&())),
+        (Gravitee::kind(&()), ApiResource::erase::<Gravitee>(&())),
+    ];
+
+    kinds
+        .into_iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(kind))
+        .map(|(_, resource)| resource)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns an error if the given dependency does not exist in the namespace or
+/// its `Ready` condition is not true
+pub async fn ready(
+    client: kube::Client,
+    namespace: &str,
+    dependency: &Dependency,
+) -> Result<(), Error> {
+    let (kind, name) = (&dependency.kind, &dependency.name);
+    let resource = api_resource(kind).ok_or_else(|| Error::UnknownKind(kind.to_owned()))?;
+    let api: Api<DynamicObject> = Api::namespaced_with(client, namespace, &resource);
+
+    let obj = api
+        .get_opt(name)
+        .await
+        .map_err(|err| Error::Get(kind.to_owned(), namespace.to_string(), name.to_owned(), err))?
+        .ok_or_else(|| Error::NotFound(kind.to_owned(), namespace.to_string(), name.to_owned()))?;
+
+    let conditions: Vec<Condition> = match obj.data.pointer("/status/conditions") {
+        Some(conditions) => serde_json::from_value(conditions.to_owned()).map_err(|err| {
+            Error::Conditions(kind.to_owned(), namespace.to_string(), name.to_owned(), err)
+        })?,
+        None => vec![],
+    };
+
+    if !condition::is_true(&conditions, condition::READY) {
+        return Err(Error::NotReady(
+            kind.to_owned(),
+            namespace.to_string(),
+            name.to_owned(),
+        ));
+    }
+
+    debug!(
+        kind = kind,
+        namespace = namespace,
+        name = name,
+        "Dependency of custom resource is ready",
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns an error on the first dependency which is not ready
+pub async fn all_ready(
+    client: kube::Client,
+    namespace: &str,
+    dependencies: &[Dependency],
+) -> Result<(), Error> {
+    for dependency in dependencies {
+        ready(client.to_owned(), namespace, dependency).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! # Drift module
+//!
+//! This module provide helpers to detect changes of the specification of a
+//! custom resource which could not be applied on its existing addon, like its
+//! region. Such a drift is reported by the [`SPEC_DRIFT`] condition and a
+//! warning event. The addon is only deleted and created again in the new
+//! region, if the custom resource carries the [`RECREATE_ANNOTATION`]
+//! annotation, as the data of the addon is lost. Secrets generated from the
+//! credentials of the addon, which are edited afterwards, are also reported
+//! once they are repaired.
+
+use std::fmt::{self, Debug, Display, Formatter};
+
+use clevercloud_sdk::v2::addon::Addon;
+use k8s_openapi::{api::core::v1::Secret, NamespaceResourceScope};
+use kube::{Client, CustomResourceExt, Resource, ResourceExt};
+
+use crate::svc::k8s::{
+    condition::{self, Condition},
+    recorder,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// annotation allowing the operator to delete and create again the addon when
+/// an immutable field of the specification has changed
+pub const RECREATE_ANNOTATION: &str = "api.clever-cloud.com/recreate";
+
+pub const SPEC_DRIFT: &str = "SpecDrift";
+
+// -----------------------------------------------------------------------------
+// Action enumeration
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub enum Action {
+    DetectSpecDrift,
+    RecreateAddon,
+    RepairSecret,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::DetectSpecDrift => write!(f, "DetectSpecDrift"),
+            Self::RecreateAddon => write!(f, "RecreateAddon"),
+            Self::RepairSecret => write!(f, "RepairSecret"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns a message describing the drift between the region of the addon and
+/// the given one, if they differ
+pub fn region(addon: &Addon, region: &str) -> Option<String> {
+    if addon.region == region {
+        return None;
+    }
+
+    Some(format!(
+        "Field 'spec.instance.region' is immutable, addon '{}' stays in region '{}' instead of '{}', set the '{}' annotation to 'true' to recreate it",
+        addon.id, addon.region, region, RECREATE_ANNOTATION
+    ))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the addon of the object could be deleted and created again
+pub fn recreate<T>(obj: &T) -> bool
+where
+    T: ResourceExt + Debug,
+{
+    obj.annotations()
+        .get(RECREATE_ANNOTATION)
+        .map(|value| value == "true")
+        .unwrap_or_default()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the condition reporting the given drift, if any
+pub fn condition(drift: Option<&str>) -> Condition {
+    match drift {
+        Some(message) => Condition::new(
+            SPEC_DRIFT,
+            condition::Status::True,
+            "ImmutableFieldChanged",
+            message,
+        ),
+        None => Condition::new(
+            SPEC_DRIFT,
+            condition::Status::False,
+            "InSync",
+            "Specification is applied on the addon",
+        ),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// record that the given drift is detected on the object
+pub async fn detected<T>(client: Client, obj: &T, message: &str) -> Result<(), kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    recorder::warning(client, obj, &Action::DetectSpecDrift, message).await?;
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// record that the addon of the object is deleted to be created again
+pub async fn recreating<T>(client: Client, obj: &T, addon: &Addon) -> Result<(), kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    let message = format!(
+        "Delete addon '{}' in region '{}' to create it again, as the '{}' annotation is set",
+        addon.id, addon.region, RECREATE_ANNOTATION
+    );
+
+    recorder::warning(client, obj, &Action::RecreateAddon, &message).await?;
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(current, desired)))]
+/// returns a message describing the keys of the current secret which differ
+/// from the desired one, if any. Keys added on the current secret are ignored
+pub fn secret(current: &Secret, desired: &Secret) -> Option<String> {
+    let data = current.data.to_owned().unwrap_or_default();
+    let keys: Vec<_> = desired
+        .string_data
+        .iter()
+        .flatten()
+        .filter(|(key, value)| {
+            data.get(*key).map(|bytes| bytes.0.as_slice()) != Some(value.as_bytes())
+        })
+        .map(|(key, _)| key.to_owned())
+        .collect();
+
+    if keys.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "Secret '{}' has been modified, restore key(s) '{}'",
+        current.name_any(),
+        keys.join("', '")
+    ))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// record that the given drift of a secret owned by the object is repaired
+pub async fn repaired<T>(client: Client, obj: &T, message: &str) -> Result<(), kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    recorder::warning(client, obj, &Action::RepairSecret, message).await?;
+    Ok(())
+}
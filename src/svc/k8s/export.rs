@@ -0,0 +1,246 @@
+//! # Export module
+//!
+//! This module provide helpers to replicate the secret generated for a custom
+//! resource into other namespaces, listed in its `spec.exportTo` field, so a
+//! single addon could be consumed by workloads of several namespaces. Owner
+//! references could not cross namespaces, so copies are labelled with the
+//! identifier of the custom resource and annotated with its reference, which
+//! are used to reconcile the custom resource once a copy is modified. The
+//! namespaces to which the secret is exported are recorded on the secret
+//! itself, so stale copies are pruned without listing secrets of the cluster.
+
+use std::{collections::BTreeSet, fmt::Debug};
+
+use k8s_openapi::{api::core::v1::Secret, NamespaceResourceScope};
+use kube::{
+    api::{DeleteParams, ObjectMeta},
+    runtime::reflector::ObjectRef,
+    Api, Resource, ResourceExt,
+};
+use tracing::{info, warn};
+
+use crate::svc::k8s::{resource, secret};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// label holding the identifier of the custom resource which exported the
+/// secret
+pub const SOURCE_LABEL: &str = "api.clever-cloud.com/export-source";
+/// annotation holding the reference of the custom resource which exported the
+/// secret, formatted as `kind/namespace/name`
+pub const SOURCE_ANNOTATION: &str = "api.clever-cloud.com/export-source";
+/// annotation of the exported secret holding the comma separated namespaces
+/// to which it is exported
+pub const EXPORTED_TO_ANNOTATION: &str = "api.clever-cloud.com/exported-to";
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secret)))]
+/// record on the secret of the custom resource the namespaces to which it is
+/// exported, the namespace of the custom resource is ignored
+pub fn annotate<T>(obj: &T, mut secret: Secret, export_to: &[String]) -> Secret
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+{
+    let (source_namespace, _) = resource::namespaced_name(obj);
+    let namespaces: BTreeSet<&str> = export_to
+        .iter()
+        .map(|namespace| namespace.trim())
+        .filter(|namespace| !namespace.is_empty() && *namespace != source_namespace)
+        .collect();
+
+    if !namespaces.is_empty() {
+        secret.annotations_mut().insert(
+            EXPORTED_TO_ANNOTATION.to_string(),
+            namespaces.into_iter().collect::<Vec<_>>().join(","),
+        );
+    }
+
+    secret
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secret)))]
+/// returns the namespaces to which the secret is exported
+pub fn namespaces(secret: &Secret) -> BTreeSet<String> {
+    secret
+        .annotations()
+        .get(EXPORTED_TO_ANNOTATION)
+        .map(|namespaces| {
+            namespaces
+                .split(',')
+                .filter(|namespace| !namespace.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secret)))]
+/// returns the copy of the secret of the custom resource in the given
+/// namespace
+pub fn copy<T>(obj: &T, secret: &Secret, namespace: &str) -> Secret
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let (source_namespace, name) = resource::namespaced_name(obj);
+    let kind = T::kind(&Default::default()).to_string();
+
+    let mut labels = secret.labels().to_owned();
+    labels.insert(SOURCE_LABEL.to_string(), obj.uid().unwrap_or_default());
+
+    let mut annotations = secret.annotations().to_owned();
+    annotations.remove(EXPORTED_TO_ANNOTATION);
+    annotations.insert(
+        SOURCE_ANNOTATION.to_string(),
+        format!("{}/{}/{}", kind, source_namespace, name),
+    );
+
+    Secret {
+        metadata: ObjectMeta {
+            name: Some(secret.name_any()),
+            namespace: Some(namespace.to_string()),
+            labels: Some(labels),
+            annotations: Some(annotations),
+            ..Default::default()
+        },
+        type_: secret.type_.to_owned(),
+        data: secret.data.to_owned(),
+        string_data: secret.string_data.to_owned(),
+        ..Default::default()
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client, secret, previous)))]
+/// upsert the copies of the secret of the custom resource in the namespaces
+/// recorded on it and delete the copies in namespaces to which the previous
+/// version of the secret was exported, if any. It returns the namespaces to
+/// which the secret is exported
+pub async fn sync<T>(
+    client: kube::Client,
+    obj: &T,
+    secret: &Secret,
+    previous: Option<&Secret>,
+) -> Result<Vec<String>, kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let uid = obj.uid().unwrap_or_default();
+    let name = secret.name_any();
+    let namespaces = namespaces(secret);
+
+    let mut exported = vec![];
+    for namespace in &namespaces {
+        let current: Option<Secret> = resource::get(client.to_owned(), namespace, &name).await?;
+        if let Some(current) = current.filter(|current| !exported_by(current, &uid)) {
+            warn!(
+                namespace = namespace,
+                name = current.name_any(),
+                "Refuse to export kubernetes secret, a secret with the same name already exists",
+            );
+
+            continue;
+        }
+
+        info!(
+            namespace = namespace,
+            name = &name,
+            "Upsert exported kubernetes secret",
+        );
+
+        resource::upsert(client.to_owned(), &copy(obj, secret, namespace), false).await?;
+        exported.push(namespace.to_owned());
+    }
+
+    let stale = previous.map(self::namespaces).unwrap_or_default();
+    for namespace in stale.difference(&namespaces) {
+        remove(client.to_owned(), namespace, &name, &uid).await?;
+    }
+
+    Ok(exported)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// delete the copies of the secret of the custom resource
+pub async fn prune<T>(client: kube::Client, obj: &T) -> Result<(), kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+{
+    let (namespace, _) = resource::namespaced_name(obj);
+    let (uid, name) = (obj.uid().unwrap_or_default(), secret::name(obj));
+
+    let current: Option<Secret> = resource::get(client.to_owned(), &namespace, &name).await?;
+    if let Some(current) = current {
+        for namespace in namespaces(&current) {
+            remove(client.to_owned(), &namespace, &name, &uid).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(
+    feature = "trace",
+    tracing::instrument(
+        skip(secret),
+        fields(namespace = ?secret.metadata.namespace, name = ?secret.metadata.name)
+    )
+)]
+/// returns the reference of the custom resource which exported the secret,
+/// if it is of the given kind. It maps events on copies to the custom
+/// resource to reconcile
+pub fn source<T>(secret: Secret) -> Option<ObjectRef<T>>
+where
+    T: Resource<Scope = NamespaceResourceScope>,
+    <T as Resource>::DynamicType: Default,
+{
+    let reference = secret.annotations().get(SOURCE_ANNOTATION)?;
+    let mut parts = reference.splitn(3, '/');
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(kind), Some(namespace), Some(name)) if kind == T::kind(&Default::default()) => {
+            Some(ObjectRef::new(name).within(namespace))
+        }
+        _ => None,
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secret)))]
+/// returns if the secret is a copy exported by the custom resource with the
+/// given identifier
+fn exported_by(secret: &Secret, uid: &str) -> bool {
+    secret.labels().get(SOURCE_LABEL).map(String::as_str) == Some(uid)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// delete the copy of the secret in the given namespace, if it has been
+/// exported by the custom resource with the given identifier
+async fn remove(
+    client: kube::Client,
+    namespace: &str,
+    name: &str,
+    uid: &str,
+) -> Result<(), kube::Error> {
+    let current: Option<Secret> = resource::get(client.to_owned(), namespace, name).await?;
+    if !current.map_or(false, |current| exported_by(&current, uid)) {
+        return Ok(());
+    }
+
+    info!(
+        namespace = namespace,
+        name = name,
+        "Delete exported kubernetes secret",
+    );
+
+    match Api::<Secret>::namespaced(client, namespace)
+        .delete(name, &DeleteParams::default())
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(err)) if err.code == 404 => Ok(()),
+        Err(err) => Err(err),
+    }
+}
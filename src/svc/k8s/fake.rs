@@ -0,0 +1,294 @@
+//! # Fake module
+//!
+//! This module provide an in-memory kubernetes api server, which creates,
+//! retrieves, lists, patches and deletes objects held in memory. It serves a
+//! [`kube::Client`] to exercise reconcilers without a cluster. Watches, label
+//! selectors, validation and garbage collection are not implemented, lists
+//! are only served in a namespace.
+
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use hyper::{body, header::CONTENT_TYPE, Body, Method, Request, Response, StatusCode};
+use serde_json::{json, Value};
+use tower::service_fn;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const DEFAULT_NAMESPACE: &str = "default";
+pub const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+pub const STATUS_SUBRESOURCE: &str = "/status";
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to read body of the request, {0}")]
+    Body(hyper::Error),
+    #[error("failed to deserialize body of the request, {0}")]
+    Deserialize(serde_json::Error),
+    #[error("failed to apply json patch on '{0}', {1}")]
+    Patch(String, json_patch::PatchError),
+    #[error("failed to find object '{0}'")]
+    NotFound(String),
+    #[error("failed to create object '{0}', it already exists")]
+    AlreadyExists(String),
+    #[error("failed to find the name of the object to create in '{0}'")]
+    Name(String),
+    #[error("failed to serve method '{0}' on '{1}'")]
+    Method(Method, String),
+}
+
+impl Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the status code and the reason of the error, as the api server
+    /// would
+    pub fn status(&self) -> (StatusCode, &'static str) {
+        match self {
+            Self::NotFound(_) => (StatusCode::NOT_FOUND, "NotFound"),
+            Self::AlreadyExists(_) => (StatusCode::CONFLICT, "AlreadyExists"),
+            Self::Patch(_, _) => (StatusCode::UNPROCESSABLE_ENTITY, "Invalid"),
+            Self::Method(_, _) => (StatusCode::METHOD_NOT_ALLOWED, "MethodNotAllowed"),
+            Self::Body(_) | Self::Deserialize(_) | Self::Name(_) => {
+                (StatusCode::BAD_REQUEST, "BadRequest")
+            }
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// FakeApiServer structure
+
+/// in-memory kubernetes api server, objects are keyed by their path, e.g.
+/// `/api/v1/namespaces/default/secrets/name`
+#[derive(Clone, Default, Debug)]
+pub struct FakeApiServer {
+    counter: Arc<AtomicUsize>,
+    objects: Arc<Mutex<BTreeMap<String, Value>>>,
+}
+
+impl FakeApiServer {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns a kubernetes client sending its requests to the server
+    pub fn client(&self) -> kube::Client {
+        let server = self.to_owned();
+        let service = service_fn(move |req: Request<Body>| {
+            let server = server.to_owned();
+            async move { Ok::<_, Infallible>(server.serve(req).await) }
+        });
+
+        kube::Client::new(service, DEFAULT_NAMESPACE)
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the number of objects held by the server
+    pub fn count(&self) -> usize {
+        self.objects.lock().expect("lock to not be poisoned").len()
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(req)))]
+    /// returns the response of the server to the given request
+    pub async fn serve(&self, req: Request<Body>) -> Response<Body> {
+        let method = req.method().to_owned();
+        let path = req
+            .uri()
+            .path()
+            .trim_end_matches(STATUS_SUBRESOURCE)
+            .to_string();
+
+        let content_type = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+
+        let result = match body::to_bytes(req.into_body()).await {
+            Ok(buf) => self.dispatch(&method, &path, &content_type, &buf),
+            Err(err) => Err(Error::Body(err)),
+        };
+
+        match result {
+            Ok((status, obj)) => response(status, &obj),
+            Err(err) => {
+                let (status, reason) = err.status();
+                response(
+                    status,
+                    &json!({
+                        "apiVersion": "v1",
+                        "kind": "Status",
+                        "metadata": {},
+                        "status": "Failure",
+                        "message": err.to_string(),
+                        "reason": reason,
+                        "code": status.as_u16(),
+                    }),
+                )
+            }
+        }
+    }
+
+    /// execute the request on the objects held by the server
+    fn dispatch(
+        &self,
+        method: &Method,
+        path: &str,
+        content_type: &str,
+        buf: &[u8],
+    ) -> Result<(StatusCode, Value), Error> {
+        let mut objects = self.objects.lock().expect("lock to not be poisoned");
+
+        match (method, collection(path)) {
+            (&Method::GET, true) => {
+                let prefix = format!("{}/", path);
+                let items: Vec<Value> = objects
+                    .iter()
+                    .filter(|(key, _)| {
+                        key.strip_prefix(&prefix)
+                            .map(|name| !name.contains('/'))
+                            .unwrap_or(false)
+                    })
+                    .map(|(_, obj)| obj.to_owned())
+                    .collect();
+
+                Ok((
+                    StatusCode::OK,
+                    json!({
+                        "apiVersion": "v1",
+                        "kind": "List",
+                        "metadata": { "resourceVersion": self.version() },
+                        "items": items,
+                    }),
+                ))
+            }
+            (&Method::GET, false) => objects
+                .get(path)
+                .map(|obj| (StatusCode::OK, obj.to_owned()))
+                .ok_or_else(|| Error::NotFound(path.to_string())),
+            (&Method::POST, true) => {
+                let mut obj: Value = serde_json::from_slice(buf).map_err(Error::Deserialize)?;
+                let name = match (
+                    obj.pointer("/metadata/name").and_then(Value::as_str),
+                    obj.pointer("/metadata/generateName")
+                        .and_then(Value::as_str),
+                ) {
+                    (Some(name), _) => name.to_string(),
+                    (None, Some(prefix)) => format!("{}{}", prefix, self.version()),
+                    (None, None) => return Err(Error::Name(path.to_string())),
+                };
+
+                let key = format!("{}/{}", path, name);
+                if objects.contains_key(&key) {
+                    return Err(Error::AlreadyExists(key));
+                }
+
+                self.stamp(&mut obj, Some(&name));
+                objects.insert(key, obj.to_owned());
+                Ok((StatusCode::CREATED, obj))
+            }
+            (&Method::PATCH, false) => {
+                let current = objects.get(path).cloned();
+                let mut obj = if content_type == JSON_PATCH_CONTENT_TYPE {
+                    let mut obj = current.ok_or_else(|| Error::NotFound(path.to_string()))?;
+                    let patch: json_patch::Patch =
+                        serde_json::from_slice(buf).map_err(Error::Deserialize)?;
+
+                    json_patch::patch(&mut obj, &patch)
+                        .map_err(|err| Error::Patch(path.to_string(), err))?;
+                    obj
+                } else {
+                    // merge, strategic merge and server-side apply patches are
+                    // all served as json merge patches, only server-side apply
+                    // creates missing objects
+                    let apply = content_type.starts_with("application/apply-patch");
+                    let mut obj = match current {
+                        Some(obj) => obj,
+                        None if apply => json!({}),
+                        None => return Err(Error::NotFound(path.to_string())),
+                    };
+
+                    let patch: Value = serde_json::from_slice(buf).map_err(Error::Deserialize)?;
+                    json_patch::merge(&mut obj, &patch);
+                    obj
+                };
+
+                self.stamp(&mut obj, None);
+                objects.insert(path.to_string(), obj.to_owned());
+                Ok((StatusCode::OK, obj))
+            }
+            (&Method::PUT, false) => {
+                if !objects.contains_key(path) {
+                    return Err(Error::NotFound(path.to_string()));
+                }
+
+                let mut obj: Value = serde_json::from_slice(buf).map_err(Error::Deserialize)?;
+                self.stamp(&mut obj, None);
+                objects.insert(path.to_string(), obj.to_owned());
+                Ok((StatusCode::OK, obj))
+            }
+            (&Method::DELETE, false) => objects
+                .remove(path)
+                .map(|obj| (StatusCode::OK, obj))
+                .ok_or_else(|| Error::NotFound(path.to_string())),
+            _ => Err(Error::Method(method.to_owned(), path.to_string())),
+        }
+    }
+
+    /// returns a new resource version
+    fn version(&self) -> String {
+        self.counter.fetch_add(1, Ordering::SeqCst).to_string()
+    }
+
+    /// set the name, if any, a new resource version and an identifier, if it
+    /// has none, on the given object
+    fn stamp(&self, obj: &mut Value, name: Option<&str>) {
+        let version = self.version();
+        if let Some(metadata) = obj
+            .as_object_mut()
+            .map(|obj| obj.entry("metadata").or_insert_with(|| json!({})))
+            .and_then(Value::as_object_mut)
+        {
+            if let Some(name) = name {
+                metadata.insert("name".into(), Value::from(name));
+            }
+
+            metadata
+                .entry("uid")
+                .or_insert_with(|| Value::from(format!("uid-{}", version)));
+            metadata.insert("resourceVersion".into(), Value::from(version));
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+/// returns if the path targets a collection of objects rather than an object,
+/// e.g. `/apis/{group}/{version}/namespaces/{namespace}/{plural}`
+fn collection(path: &str) -> bool {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let rest = match segments.first() {
+        Some(&"api") => segments.get(2..),
+        _ => segments.get(3..),
+    }
+    .unwrap_or_default();
+
+    matches!(rest, ["namespaces", _, _] | [_])
+}
+
+/// returns a json response with the given status code
+fn response(status: StatusCode, obj: &Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(obj.to_string()))
+        .expect("response to be valid")
+}
@@ -5,8 +5,10 @@
 
 use std::fmt::Debug;
 
+use json_patch::{AddOperation, Patch, PatchOperation, RemoveOperation, TestOperation};
 use k8s_openapi::NamespaceResourceScope;
 use kube::Resource;
+use serde_json::Value;
 
 #[cfg_attr(feature = "trace", tracing::instrument)]
 /// returns if there is the given finalizer on the resource
@@ -59,3 +61,168 @@ where
 
     obj
 }
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns a json patch adding the finalizer to the resource, if it is not
+/// already present. The patch only appends the given finalizer and never
+/// replaces the whole list, so finalizers added concurrently by other
+/// controllers are kept
+pub fn add_patch<T>(obj: &T, finalizer: &str) -> Patch
+where
+    T: Resource<Scope = NamespaceResourceScope> + Debug,
+{
+    if contains(obj, finalizer) {
+        return Patch(vec![]);
+    }
+
+    match &obj.meta().finalizers {
+        Some(_) => Patch(vec![PatchOperation::Add(AddOperation {
+            path: "/metadata/finalizers/-".to_string(),
+            value: Value::String(finalizer.to_string()),
+        })]),
+        None => {
+            let mut operations = vec![];
+
+            // there is no way to test the absence of a field using json
+            // patch, so we rely on the resource version to reject the request,
+            // if the resource was modified since we have read it
+            if let Some(version) = &obj.meta().resource_version {
+                operations.push(PatchOperation::Test(TestOperation {
+                    path: "/metadata/resourceVersion".to_string(),
+                    value: Value::String(version.to_string()),
+                }));
+            }
+
+            operations.push(PatchOperation::Add(AddOperation {
+                path: "/metadata/finalizers".to_string(),
+                value: Value::Array(vec![Value::String(finalizer.to_string())]),
+            }));
+
+            Patch(operations)
+        }
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns a json patch removing the finalizer from the resource, if it is
+/// present. The patch first tests that the finalizer is still at the expected
+/// index, so the request is rejected by kubernetes instead of removing a
+/// finalizer added concurrently by other controllers
+pub fn remove_patch<T>(obj: &T, finalizer: &str) -> Patch
+where
+    T: Resource<Scope = NamespaceResourceScope> + Debug,
+{
+    let idx = obj
+        .meta()
+        .finalizers
+        .as_ref()
+        .and_then(|finalizers| finalizers.iter().position(|f| finalizer == f));
+
+    match idx {
+        None => Patch(vec![]),
+        Some(idx) => Patch(vec![
+            PatchOperation::Test(TestOperation {
+                path: format!("/metadata/finalizers/{}", idx),
+                value: Value::String(finalizer.to_string()),
+            }),
+            PatchOperation::Remove(RemoveOperation {
+                path: format!("/metadata/finalizers/{}", idx),
+            }),
+        ]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{add_patch, remove_patch};
+    use crate::svc::crd::config_provider::{tests::provider, ConfigProvider, ADDON_FINALIZER};
+
+    const FOREIGN_FINALIZER: &str = "example.com/foreign";
+
+    /// returns the json of a config provider held by the given finalizers
+    fn held(finalizers: Option<Vec<&str>>) -> Value {
+        let mut obj = provider("app", "orga_test");
+        obj.metadata.resource_version = Some("1".to_string());
+        obj.metadata.finalizers =
+            finalizers.map(|finalizers| finalizers.into_iter().map(String::from).collect());
+
+        serde_json::to_value(obj).expect("config provider to serialize")
+    }
+
+    fn finalizers(value: &Value) -> Value {
+        value["metadata"]["finalizers"].to_owned()
+    }
+
+    /// apply the json patch computed on the object read by the operator on the
+    /// given state of the api server
+    fn apply(
+        read: &Value,
+        server: &Value,
+        f: fn(&ConfigProvider, &str) -> json_patch::Patch,
+    ) -> Result<Value, json_patch::PatchError> {
+        let obj: ConfigProvider = serde_json::from_value(read.to_owned()).expect("object");
+        let mut server = server.to_owned();
+
+        json_patch::patch(&mut server, &f(&obj, ADDON_FINALIZER))?;
+        Ok(server)
+    }
+
+    #[test]
+    fn upsert_keeps_foreign_finalizer() {
+        let obj = held(Some(vec![FOREIGN_FINALIZER]));
+        let patched = apply(&obj, &obj, add_patch).expect("patch to apply");
+
+        assert_eq!(
+            json!([FOREIGN_FINALIZER, ADDON_FINALIZER]),
+            finalizers(&patched)
+        );
+    }
+
+    #[test]
+    fn upsert_keeps_foreign_finalizer_added_concurrently() {
+        let read = held(Some(vec![]));
+        let server = held(Some(vec![FOREIGN_FINALIZER]));
+        let patched = apply(&read, &server, add_patch).expect("patch to apply");
+
+        assert_eq!(
+            json!([FOREIGN_FINALIZER, ADDON_FINALIZER]),
+            finalizers(&patched)
+        );
+    }
+
+    #[test]
+    fn upsert_without_finalizers_is_rejected_once_modified() {
+        let read = held(None);
+        let mut server = held(Some(vec![FOREIGN_FINALIZER]));
+        server["metadata"]["resourceVersion"] = json!("2");
+
+        assert!(apply(&read, &server, add_patch).is_err());
+    }
+
+    #[test]
+    fn delete_keeps_foreign_finalizer() {
+        let obj = held(Some(vec![FOREIGN_FINALIZER, ADDON_FINALIZER]));
+        let patched = apply(&obj, &obj, remove_patch).expect("patch to apply");
+
+        assert_eq!(json!([FOREIGN_FINALIZER]), finalizers(&patched));
+    }
+
+    #[test]
+    fn delete_keeps_foreign_finalizer_added_concurrently() {
+        let read = held(Some(vec![ADDON_FINALIZER]));
+        let server = held(Some(vec![ADDON_FINALIZER, FOREIGN_FINALIZER]));
+        let patched = apply(&read, &server, remove_patch).expect("patch to apply");
+
+        assert_eq!(json!([FOREIGN_FINALIZER]), finalizers(&patched));
+    }
+
+    #[test]
+    fn delete_is_rejected_once_finalizers_moved() {
+        let read = held(Some(vec![ADDON_FINALIZER]));
+        let server = held(Some(vec![FOREIGN_FINALIZER, ADDON_FINALIZER]));
+
+        assert!(apply(&read, &server, remove_patch).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+//! # Generation module
+//!
+//! This module records in `status.observedGeneration` the generation of the
+//! specification processed by the last successful reconciliation, so external
+//! tooling could tell whether the operator has caught up with the latest
+//! specification of a custom resource. It also keeps in memory the last
+//! version of each custom resource seen by reconcilers, to skip
+//! reconciliations triggered by a modification of the status only, e.g. the
+//! ones written by the operator itself. Reconciliations of an already seen
+//! version, which are triggered by a requeue or by a modification of an owned
+//! resource, are never skipped.
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    sync::Mutex,
+};
+
+use k8s_openapi::NamespaceResourceScope;
+use kube::{Resource, ResourceExt};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::svc::k8s::resource;
+
+// -----------------------------------------------------------------------------
+// State
+
+/// last seen version of custom resources, keyed by kind, namespace and name
+static SEEN: Mutex<BTreeMap<(String, String, String), Seen>> = Mutex::new(BTreeMap::new());
+
+// -----------------------------------------------------------------------------
+// Seen structure
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+struct Seen {
+    resource_version: Option<String>,
+    /// hash of the generation, labels and annotations of the custom resource
+    fingerprint: u64,
+    /// whether the last reconciliation of the custom resource has succeeded
+    synced: bool,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the generation recorded in `status.observedGeneration`, if any
+pub fn observed<T>(obj: &T) -> Option<i64>
+where
+    T: Serialize + Debug,
+{
+    serde_json::to_value(obj)
+        .ok()?
+        .pointer("/status/observedGeneration")
+        .and_then(Value::as_i64)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the custom resource has only been modified on its status since
+/// the last successful reconciliation, which has observed its current
+/// generation. The given version is then recorded as seen
+pub fn unchanged<T>(obj: &T) -> bool
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Serialize + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    if obj.meta().generation.is_none() || observed(obj) != obj.meta().generation {
+        return false;
+    }
+
+    let fingerprint = fingerprint(obj);
+    let resource_version = obj.resource_version();
+
+    let mut seen = match SEEN.lock() {
+        Ok(seen) => seen,
+        Err(_) => return false,
+    };
+
+    match seen.get_mut(&key(obj)) {
+        Some(seen)
+            if seen.synced
+                && seen.fingerprint == fingerprint
+                && seen.resource_version != resource_version =>
+        {
+            seen.resource_version = resource_version;
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// record the version of the custom resource processed by a reconciliation
+/// and whether it has succeeded
+pub fn record<T>(obj: &T, synced: bool)
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    if let Ok(mut seen) = SEEN.lock() {
+        seen.insert(
+            key(obj),
+            Seen {
+                resource_version: obj.resource_version(),
+                fingerprint: fingerprint(obj),
+                synced,
+            },
+        );
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// forget the last seen version of the given custom resource, so its next
+/// reconciliation is never skipped
+pub fn forget(kind: &str, namespace: &str, name: &str) {
+    if let Ok(mut seen) = SEEN.lock() {
+        seen.remove(&(kind.to_string(), namespace.to_string(), name.to_string()));
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// write the generation of the custom resource in `status.observedGeneration`,
+/// if it is not already recorded. The latest version of the custom resource is
+/// retrieved, so the status written during the reconciliation is preserved
+pub async fn update<T>(client: kube::Client, obj: &T) -> Result<(), kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope>
+        + ResourceExt
+        + DeserializeOwned
+        + Serialize
+        + Clone
+        + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let generation = match obj.meta().generation {
+        Some(generation) if observed(obj) != Some(generation) => generation,
+        _ => return Ok(()),
+    };
+
+    let (namespace, name) = resource::namespaced_name(obj);
+    let current: T = match resource::get::<T>(client.to_owned(), &namespace, &name).await? {
+        // a newer specification will be processed by another reconciliation
+        Some(current) if current.meta().generation == Some(generation) => current,
+        _ => return Ok(()),
+    };
+
+    let origin = serde_json::to_value(&current).map_err(kube::Error::SerdeError)?;
+    let mut modified = origin.to_owned();
+    if let Value::Object(root) = &mut modified {
+        let status = root
+            .entry("status")
+            .or_insert_with(|| Value::Object(Default::default()));
+
+        if let Value::Object(status) = status {
+            status.insert("observedGeneration".to_string(), Value::from(generation));
+        }
+    }
+
+    let patch = json_patch::diff(&origin, &modified);
+    let modified: T = serde_json::from_value(modified).map_err(kube::Error::SerdeError)?;
+    resource::patch_status(client, modified, patch).await?;
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the key of the custom resource in the seen versions
+fn key<T>(obj: &T) -> (String, String, String)
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let (namespace, name) = resource::namespaced_name(obj);
+
+    (T::kind(&Default::default()).to_string(), namespace, name)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the hash of the generation, labels and annotations of the custom
+/// resource, which are the fields driving its reconciliation
+fn fingerprint<T>(obj: &T) -> u64
+where
+    T: Resource + ResourceExt + Debug,
+{
+    let mut hasher = DefaultHasher::new();
+
+    obj.meta().generation.hash(&mut hasher);
+    obj.labels().hash(&mut hasher);
+    obj.annotations().hash(&mut hasher);
+    hasher.finish()
+}
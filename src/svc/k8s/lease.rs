@@ -0,0 +1,326 @@
+//! # Lease module
+//!
+//! This module provide a leader election based on a kubernetes [`Lease`], so
+//! only one replica of the operator runs controllers at a time. The leader
+//! periodically renews the lease, other replicas take it over once it has not
+//! been renewed for the lease duration.
+
+use std::{fmt::Debug, time::Duration};
+
+use chrono::Utc;
+use k8s_openapi::{
+    api::coordination::v1::{Lease, LeaseSpec},
+    apimachinery::pkg::apis::meta::v1::MicroTime,
+};
+use kube::{
+    api::{ObjectMeta, PostParams},
+    Api, Client,
+};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_int_gauge, IntGauge};
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info, warn};
+
+use crate::svc::cfg::LeaderElection;
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static LEADER: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(opts!(
+        "kubernetes_operator_leader",
+        "whether this replica of the operator holds the leader lease",
+    ))
+    .expect("metrics 'kubernetes_operator_leader' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to retrieve hostname to identify the replica, {0}")]
+    Identity(std::io::Error),
+    #[error("failed to retrieve lease '{0}/{1}', {2}")]
+    Get(String, String, kube::Error),
+    #[error("failed to write lease '{0}/{1}', {2}")]
+    Write(String, String, kube::Error),
+    #[error("lost leader lease '{0}/{1}', it is held by '{2}'")]
+    Lost(String, String, String),
+    #[error("lost leader lease '{0}/{1}', it could not be renewed within {2}s")]
+    RenewDeadline(String, String, u64),
+}
+
+// -----------------------------------------------------------------------------
+// Elector structure
+
+/// acquires, renews and releases the leader lease on behalf of this replica
+#[derive(Clone)]
+pub struct Elector {
+    api: Api<Lease>,
+    namespace: String,
+    name: String,
+    identity: String,
+    lease_duration: Duration,
+    renew_deadline: Duration,
+    retry_period: Duration,
+}
+
+impl Debug for Elector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Elector")
+            .field("namespace", &self.namespace)
+            .field("name", &self.name)
+            .field("identity", &self.identity)
+            .finish()
+    }
+}
+
+impl Elector {
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+    /// returns an elector identified by the hostname of the replica, which is
+    /// the name of the pod in kubernetes
+    pub fn try_new(client: Client, config: &LeaderElection) -> Result<Self, Error> {
+        let identity = hostname::get()
+            .map_err(Error::Identity)?
+            .to_string_lossy()
+            .to_string();
+
+        let namespace = config
+            .namespace
+            .to_owned()
+            .unwrap_or_else(|| client.default_namespace().to_string());
+
+        Ok(Self {
+            api: Api::namespaced(client, &namespace),
+            namespace,
+            name: config.lease_name.to_owned(),
+            identity,
+            lease_duration: Duration::from_secs(config.lease_duration),
+            renew_deadline: Duration::from_secs(config.renew_deadline),
+            retry_period: Duration::from_secs(config.retry_period),
+        })
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// wait until the lease is acquired by this replica
+    pub async fn acquire(&self) {
+        info!(
+            namespace = &self.namespace,
+            name = &self.name,
+            identity = &self.identity,
+            "Wait to acquire the leader lease"
+        );
+
+        loop {
+            match self.try_acquire_or_renew().await {
+                Ok(true) => break,
+                Ok(false) => {}
+                Err(err) => {
+                    warn!(
+                        error = err.to_string(),
+                        "Could not acquire the leader lease"
+                    );
+                }
+            }
+
+            sleep(self.retry_period).await;
+        }
+
+        info!(
+            namespace = &self.namespace,
+            name = &self.name,
+            identity = &self.identity,
+            "Acquired the leader lease"
+        );
+
+        #[cfg(feature = "metrics")]
+        LEADER.set(1);
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// renew the lease until the leadership is lost, which happens if another
+    /// replica holds the lease or if it could not be renewed within the renew
+    /// deadline
+    pub async fn renew(&self) -> Result<(), Error> {
+        let mut renewed = Instant::now();
+
+        let err = loop {
+            sleep(self.retry_period).await;
+
+            match self.try_acquire_or_renew().await {
+                Ok(true) => {
+                    debug!(name = &self.name, "Renewed the leader lease");
+                    renewed = Instant::now();
+                }
+                Ok(false) => {
+                    break Error::Lost(
+                        self.namespace.to_owned(),
+                        self.name.to_owned(),
+                        self.holder().await.unwrap_or_default(),
+                    );
+                }
+                Err(err) if Instant::now().duration_since(renewed) < self.renew_deadline => {
+                    warn!(error = err.to_string(), "Could not renew the leader lease");
+                }
+                Err(_) => {
+                    break Error::RenewDeadline(
+                        self.namespace.to_owned(),
+                        self.name.to_owned(),
+                        self.renew_deadline.as_secs(),
+                    );
+                }
+            }
+        };
+
+        #[cfg(feature = "metrics")]
+        LEADER.set(0);
+
+        Err(err)
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// release the lease, if it is held by this replica, so another replica
+    /// could take it over without waiting for the lease duration
+    pub async fn release(&self) -> Result<(), Error> {
+        let mut lease = match self.get().await? {
+            Some(lease) => lease,
+            None => return Ok(()),
+        };
+
+        let spec = lease.spec.get_or_insert_with(LeaseSpec::default);
+        if spec.holder_identity.as_deref() != Some(self.identity.as_str()) {
+            return Ok(());
+        }
+
+        spec.holder_identity = None;
+        spec.lease_duration_seconds = Some(1);
+        spec.renew_time = Some(MicroTime(Utc::now()));
+
+        self.api
+            .replace(&self.name, &PostParams::default(), &lease)
+            .await
+            .map_err(|err| Error::Write(self.namespace.to_owned(), self.name.to_owned(), err))?;
+
+        #[cfg(feature = "metrics")]
+        LEADER.set(0);
+
+        info!(
+            namespace = &self.namespace,
+            name = &self.name,
+            identity = &self.identity,
+            "Released the leader lease"
+        );
+
+        Ok(())
+    }
+
+    /// returns the lease, if any
+    async fn get(&self) -> Result<Option<Lease>, Error> {
+        self.api
+            .get_opt(&self.name)
+            .await
+            .map_err(|err| Error::Get(self.namespace.to_owned(), self.name.to_owned(), err))
+    }
+
+    /// returns the current holder of the lease, if any
+    async fn holder(&self) -> Option<String> {
+        self.get()
+            .await
+            .ok()
+            .flatten()
+            .and_then(|lease| lease.spec)
+            .and_then(|spec| spec.holder_identity)
+    }
+
+    /// create, renew or take over the lease and returns if this replica holds
+    /// it. Writes rely on the resource version of the lease, so concurrent
+    /// replicas could not both succeed
+    async fn try_acquire_or_renew(&self) -> Result<bool, Error> {
+        let now = MicroTime(Utc::now());
+        let lease_duration_seconds = Some(self.lease_duration.as_secs() as i32);
+
+        let mut lease = match self.get().await? {
+            Some(lease) => lease,
+            None => {
+                let lease = Lease {
+                    metadata: ObjectMeta {
+                        name: Some(self.name.to_owned()),
+                        namespace: Some(self.namespace.to_owned()),
+                        ..Default::default()
+                    },
+                    spec: Some(LeaseSpec {
+                        holder_identity: Some(self.identity.to_owned()),
+                        lease_duration_seconds,
+                        acquire_time: Some(now.to_owned()),
+                        renew_time: Some(now),
+                        lease_transitions: Some(0),
+                    }),
+                };
+
+                return match self.api.create(&PostParams::default(), &lease).await {
+                    Ok(_) => Ok(true),
+                    Err(kube::Error::Api(err)) if err.code == 409 => Ok(false),
+                    Err(err) => Err(Error::Write(
+                        self.namespace.to_owned(),
+                        self.name.to_owned(),
+                        err,
+                    )),
+                };
+            }
+        };
+
+        let spec = lease.spec.get_or_insert_with(LeaseSpec::default);
+        if spec.holder_identity.as_deref() != Some(self.identity.as_str()) {
+            let expired = match (&spec.holder_identity, &spec.renew_time) {
+                (Some(_), Some(MicroTime(renew_time))) => {
+                    let duration = chrono::Duration::seconds(
+                        spec.lease_duration_seconds.unwrap_or_default().into(),
+                    );
+
+                    *renew_time + duration < now.0
+                }
+                _ => true,
+            };
+
+            if !expired {
+                return Ok(false);
+            }
+
+            debug!(
+                name = &self.name,
+                holder = spec.holder_identity.as_deref().unwrap_or_default(),
+                "Take over the expired leader lease"
+            );
+
+            spec.holder_identity = Some(self.identity.to_owned());
+            spec.acquire_time = Some(now.to_owned());
+            spec.lease_transitions = Some(spec.lease_transitions.unwrap_or_default() + 1);
+        }
+
+        spec.lease_duration_seconds = lease_duration_seconds;
+        spec.renew_time = Some(now);
+
+        match self
+            .api
+            .replace(&self.name, &PostParams::default(), &lease)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(kube::Error::Api(err)) if err.code == 409 => Ok(false),
+            Err(err) => Err(Error::Write(
+                self.namespace.to_owned(),
+                self.name.to_owned(),
+                err,
+            )),
+        }
+    }
+}
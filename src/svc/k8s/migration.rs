@@ -0,0 +1,127 @@
+//! # Migration module
+//!
+//! This module provide helpers to migrate the addon of a custom resource to
+//! the plan of its specification. The addon is only created by the reconciler,
+//! so changing the plan of the specification afterwards has to go through a
+//! migration on Clever Cloud, which is polled on each reconciliation.
+
+use std::time::Duration;
+
+use clevercloud_sdk::v2::addon::Addon;
+use tracing::info;
+
+use crate::svc::{
+    clevercloud::{
+        client::Client,
+        migration::{self, CreateOpts, Error},
+    },
+    crd::MigrationStatus,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const PHASE_RUNNING: &str = "Running";
+pub const PHASE_SUCCEEDED: &str = "Succeeded";
+pub const PHASE_FAILED: &str = "Failed";
+
+/// delay between two polls of a running migration
+pub const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the phase of the given migration
+pub fn phase(migration: &migration::Migration) -> &'static str {
+    if migration.succeeded() {
+        PHASE_SUCCEEDED
+    } else if migration.failed() {
+        PHASE_FAILED
+    } else {
+        PHASE_RUNNING
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the delay after which a custom resource with the given migration
+/// has to be reconciled again, to poll the migration while it is running
+pub fn requeue(state: Option<&MigrationStatus>) -> Option<Duration> {
+    state
+        .filter(|state| state.phase == PHASE_RUNNING)
+        .map(|_| POLL_INTERVAL)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the desired version is newer than the applied one, versions are
+/// compared component by component, e.g. `14` is newer than `13` and `8.0`
+/// is newer than `5.7`
+pub fn newer(desired: &str, applied: &str) -> bool {
+    let components = |version: &str| -> Vec<u64> {
+        version
+            .split('.')
+            .map(|component| component.parse().unwrap_or_default())
+            .collect()
+    };
+
+    components(desired) > components(applied)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(apis)))]
+/// poll the running migration of the addon, if any, or start a migration to
+/// the given plan, if the addon uses another one, or to the given version, if
+/// any, and returns the state of the migration. A migration to a plan and
+/// version which has already failed is not retried, until the specification
+/// is changed again
+pub async fn reconcile(
+    apis: &Client,
+    endpoint: &str,
+    organisation: &str,
+    addon: &Addon,
+    plan: &str,
+    version: Option<&str>,
+    current: Option<&MigrationStatus>,
+) -> Result<Option<MigrationStatus>, Error> {
+    if let Some(state) = current.filter(|state| state.phase == PHASE_RUNNING) {
+        let migration =
+            migration::get(apis, endpoint, organisation, &addon.id, &state.migration_id).await?;
+
+        return Ok(Some(MigrationStatus {
+            phase: phase(&migration).to_string(),
+            ..state.to_owned()
+        }));
+    }
+
+    if addon.plan.id == plan && version.is_none() {
+        return Ok(current.cloned());
+    }
+
+    if let Some(state) = current.filter(|state| {
+        state.phase == PHASE_FAILED && state.plan == plan && state.version.as_deref() == version
+    }) {
+        return Ok(Some(state.to_owned()));
+    }
+
+    info!(
+        addon = &addon.id,
+        from = &addon.plan.id,
+        to = plan,
+        version = version,
+        "Migrate addon to the plan and version of the custom resource",
+    );
+
+    let opts = CreateOpts {
+        plan_id: plan.to_string(),
+        region: addon.region.to_owned(),
+        version: version.map(ToString::to_string),
+    };
+
+    let migration = migration::create(apis, endpoint, organisation, &addon.id, &opts).await?;
+
+    Ok(Some(MigrationStatus {
+        migration_id: migration.id,
+        plan: plan.to_string(),
+        version: opts.version,
+        phase: PHASE_RUNNING.to_string(),
+    }))
+}
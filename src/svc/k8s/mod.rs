@@ -3,35 +3,74 @@
 //! This module provide kubernetes custom resources, helpers and custom resource definition
 //! generator
 
-use std::{error::Error, fmt::Debug, hash::Hash, sync::Arc, time::Duration};
+use std::{error::Error, fmt::Debug, future::Future, hash::Hash, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
-use futures::{StreamExt, TryStreamExt};
-use k8s_openapi::NamespaceResourceScope;
+use futures::{future::join_all, stream::select_all, FutureExt, StreamExt, TryStreamExt};
+use k8s_openapi::{api::core::v1::Secret, NamespaceResourceScope};
 use kube::{
+    api::ListParams,
     runtime::{
         controller::{self, Action},
         watcher, Controller,
     },
-    CustomResourceExt, Resource, ResourceExt,
+    Api, CustomResourceExt, Resource, ResourceExt,
 };
 #[cfg(feature = "metrics")]
 use once_cell::sync::Lazy;
 #[cfg(feature = "metrics")]
 use prometheus::{opts, register_counter_vec, CounterVec};
-use serde::de::DeserializeOwned;
-use tokio::time::{sleep_until, Instant};
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "metrics")]
+use tokio::time::Instant;
+use tokio::{sync::Semaphore, time::interval};
 #[cfg(feature = "trace")]
 use tracing::Instrument;
-use tracing::{debug, error, info, trace};
-
-use crate::svc::{cfg::Configuration, clevercloud};
+use tracing::{debug, error, info, trace, warn};
+
+use crate::svc::{
+    cfg::{Configuration, NotificationEvent},
+    clevercloud::{self, api::CleverApi, cache::PlanCache, pool::ClientPool},
+    health,
+    k8s::{cache::ResourceCache, condition::Condition},
+    notification, tracker,
+};
 
+pub mod backoff;
+pub mod billing;
+pub mod binding;
+pub mod cache;
 pub mod client;
+pub mod condition;
+pub mod config_map;
+pub mod conversion;
+pub mod definition;
+pub mod dependency;
+pub mod drift;
+pub mod export;
+pub mod fake;
 pub mod finalizer;
+pub mod generation;
+pub mod lease;
+pub mod migration;
+pub mod naming;
+pub mod network_policy;
+pub mod pause;
+pub mod probe;
+pub mod protection;
+pub mod rbac;
 pub mod recorder;
+pub mod registry;
 pub mod resource;
+pub mod restore;
+pub mod rotation;
 pub mod secret;
+pub mod service;
+pub mod skip;
+#[cfg(feature = "metrics")]
+pub mod stats;
+pub mod sweeper;
+pub mod tags;
 
 // -----------------------------------------------------------------------------
 // constants
@@ -97,8 +136,18 @@ static RECONCILIATION_DURATION: Lazy<CounterVec> = Lazy::new(|| {
 #[derive(Clone)]
 pub struct Context {
     pub kube: kube::Client,
-    pub apis: clevercloud::client::Client,
+    /// global clever cloud client, replaced once credentials are rotated
+    pub apis: clevercloud::client::SharedClient,
     pub config: Arc<Configuration>,
+    /// plans resolved on the clever-cloud api, shared by all reconcilers
+    pub plans: Arc<PlanCache>,
+    /// secrets of the watched namespaces, shared by all reconcilers
+    pub secrets: Arc<ResourceCache<Secret>>,
+    /// clients built from namespace overrides, shared by all reconcilers
+    pub clients: Arc<ClientPool>,
+    /// implementation of the api serving addons and plans instead of the
+    /// clever cloud clients, e.g. the in-memory fake
+    pub backend: Option<Arc<dyn CleverApi>>,
 }
 
 impl
@@ -115,7 +164,28 @@ impl
             Arc<Configuration>,
         ),
     ) -> Self {
-        Self { kube, apis, config }
+        let plans = Arc::new(PlanCache::new(Duration::from_secs(
+            config.api.plan_cache_ttl,
+        )));
+
+        let secrets = Arc::new(ResourceCache::new(
+            kube.to_owned(),
+            &config.watch.namespaces,
+        ));
+
+        let clients = Arc::new(ClientPool::new(Duration::from_secs(
+            config.api.client_pool_ttl,
+        )));
+
+        Self {
+            kube,
+            apis: apis.into(),
+            config,
+            plans,
+            secrets,
+            clients,
+            backend: None,
+        }
     }
 }
 
@@ -123,6 +193,12 @@ impl Context {
     pub fn new(k: kube::Client, a: clevercloud::client::Client, c: Arc<Configuration>) -> Self {
         Self::from((k, a, c))
     }
+
+    /// serve addons and plans from the given implementation of the api
+    pub fn with_backend(mut self, backend: Arc<dyn CleverApi>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
 }
 
 // -----------------------------------------------------------------------------
@@ -135,8 +211,32 @@ where
     T: Resource<Scope = NamespaceResourceScope> + Clone + Debug,
     <T as Resource>::DynamicType: Eq + Hash,
 {
-    /// returns a new created kubernetes controller
-    fn build(&self, context: Arc<Context>) -> Controller<T>;
+    /// returns a new created kubernetes controller, which listens for events
+    /// in the given namespace or in all namespaces
+    fn build(&self, context: Arc<Context>, namespace: Option<&str>) -> Controller<T>;
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the api of the resource in the given namespace or in all namespaces
+pub fn api<K>(client: kube::Client, namespace: Option<&str>) -> Api<K>
+where
+    K: Resource<Scope = NamespaceResourceScope>,
+    <K as Resource>::DynamicType: Default,
+{
+    match namespace {
+        Some(namespace) => Api::namespaced(client, namespace),
+        None => Api::all(client),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the configuration of watchers of custom resources, restricted to
+/// the configured label selector, if any
+pub fn watcher_config(config: &Configuration) -> watcher::Config {
+    match &config.watch.label_selector {
+        Some(selector) => watcher::Config::default().labels(selector),
+        None => watcher::Config::default(),
+    }
 }
 
 // -----------------------------------------------------------------------------
@@ -150,26 +250,80 @@ where
     T: Resource<Scope = NamespaceResourceScope>
         + ResourceExt
         + CustomResourceExt
+        + DeserializeOwned
+        + Serialize
         + Debug
         + Clone
         + Send
         + Sync
         + 'static,
+    <T as Resource>::DynamicType: Default,
 {
     type Error: Error + Send + Sync;
 
+    /// returns the reason and a message explaining why the reconciliation of
+    /// the object should be skipped, if it should be. This is only consulted
+    /// on upsertion, deletion is always processed.
+    fn skip(_obj: &T, _ctx: &Context) -> Option<(skip::Reason, String)> {
+        None
+    }
+
     /// create or update the object, this is part of the the reconcile function
     async fn upsert(ctx: Arc<Context>, obj: Arc<T>) -> Result<(), Self::Error>;
 
     /// delete the object from kubernetes and third parts
     async fn delete(ctx: Arc<Context>, obj: Arc<T>) -> Result<(), Self::Error>;
 
+    /// returns the reason of the error, if it is permanent. Reconciliation
+    /// failing with a permanent error is not retried until the object is
+    /// modified, an error condition is written on the object instead.
+    fn permanent(_err: &Self::Error) -> Option<&'static str> {
+        None
+    }
+
+    /// returns the delay after which the object should be reconciled again,
+    /// even if it has not been modified. This is useful for objects tracking
+    /// the state of third parts which does not notify changes.
+    fn requeue(_obj: &T) -> Option<Duration> {
+        None
+    }
+
     /// returns a [`Action`] to perform following the given error
-    fn retry(_obj: Arc<T>, err: &Self::Error, _ctx: Arc<Context>) -> Action {
-        // Implements a basic reconciliation which always re-schedule the event
-        // 500 ms later
-        trace!("Requeue failed reconciliation for 500ms, {}", err);
-        Action::requeue(Duration::from_millis(500))
+    fn retry(obj: Arc<T>, err: &Self::Error, ctx: Arc<Context>) -> Action {
+        // Re-schedule the event after an exponential and jittered delay keyed
+        // by object, so a failing object does not hammer the apis
+        let (namespace, name) = resource::namespaced_name(&*obj);
+        let key = backoff::key(&T::api_resource().kind, &namespace, &name);
+        let delay = backoff::next(&key, &ctx.config.operator.retry);
+
+        // notify once per streak of failures, the streak is reset once the
+        // object is reconciled
+        let failures = backoff::failures(&key);
+        if failures == ctx.config.notification.failure_threshold {
+            let addon = serde_json::to_value(&*obj).ok().and_then(|value| {
+                value
+                    .pointer("/status/addon")
+                    .and_then(serde_json::Value::as_str)
+                    .map(String::from)
+            });
+
+            notification::notify(
+                NotificationEvent::RepeatedFailures,
+                &T::api_resource().kind,
+                &namespace,
+                &name,
+                addon.as_deref(),
+                &format!("Reconciliation failed {} times in a row, {}", failures, err),
+            );
+        }
+
+        trace!(
+            "Requeue failed reconciliation for {}ms, {}",
+            delay.as_millis(),
+            err
+        );
+
+        Action::requeue(delay)
     }
 
     /// process the object and perform actions on kubernetes and/or
@@ -179,7 +333,35 @@ where
         let (namespace, name) = resource::namespaced_name(&*obj);
         let api_resource = T::api_resource();
 
-        if resource::deleted(obj.as_ref()) {
+        if resource::deleted(obj.as_ref()) && protection::protected(obj.as_ref()) {
+            // the finalizer is kept, so the object is reconciled again once
+            // the annotation is removed
+            warn!(
+                kind = &api_resource.kind,
+                namespace = &namespace,
+                name = &name,
+                annotation = protection::PROTECTED_ANNOTATION,
+                "Refuse to delete protected custom resource",
+            );
+
+            registry::record(
+                &api_resource.kind,
+                &namespace,
+                &name,
+                registry::Status::Protected,
+                None,
+            );
+
+            if let Err(err) = protection::refuse(ctx.kube.to_owned(), &*obj).await {
+                warn!(
+                    kind = &api_resource.kind,
+                    namespace = &namespace,
+                    name = &name,
+                    error = err.to_string(),
+                    "Could not record refused deletion of custom resource"
+                );
+            }
+        } else if resource::deleted(obj.as_ref()) {
             info!(
                 kind = &api_resource.kind,
                 namespace = &namespace,
@@ -193,24 +375,81 @@ where
                 .inc();
 
             #[cfg(not(feature = "trace"))]
-            let result = Self::delete(ctx, obj.to_owned()).await;
+            let result = Self::delete(ctx.to_owned(), obj.to_owned()).await;
 
             #[cfg(feature = "trace")]
-            let result = Self::delete(ctx, obj.to_owned())
-                .instrument(tracing::info_span!("Reconciler::delete"))
+            let result = Self::delete(ctx.to_owned(), obj.to_owned())
+                .instrument(tracing::info_span!(
+                    "Reconciler::delete",
+                    kind = %api_resource.kind,
+                    namespace = %namespace,
+                    name = %name,
+                ))
                 .await;
 
             if let Err(err) = result {
-                error!(
+                tracker::resource(&api_resource.kind, &namespace, &name, || {
+                    error!(
+                        kind = &api_resource.kind,
+                        namespace = &namespace,
+                        name = &name,
+                        error = err.to_string(),
+                        "Failed to delete custom resource"
+                    )
+                });
+
+                registry::record(
+                    &api_resource.kind,
+                    &namespace,
+                    &name,
+                    registry::Status::Failed,
+                    Some(&err.to_string()),
+                );
+
+                return Err(err);
+            }
+
+            registry::forget(&api_resource.kind, &namespace, &name);
+            generation::forget(&api_resource.kind, &namespace, &name);
+            #[cfg(feature = "metrics")]
+            billing::forget(&api_resource.kind, &namespace, &name);
+            #[cfg(feature = "metrics")]
+            stats::forget(&api_resource.kind, &namespace, &name);
+            #[cfg(feature = "metrics")]
+            probe::forget(&api_resource.kind, &namespace, &name);
+        } else if let Some((reason, message)) =
+            pause::paused(&*obj).or_else(|| Self::skip(&obj, &ctx))
+        {
+            // the next reconciliation, once the custom resource is not
+            // skipped anymore, should process it fully
+            generation::forget(&api_resource.kind, &namespace, &name);
+
+            registry::record(
+                &api_resource.kind,
+                &namespace,
+                &name,
+                registry::Status::Skipped,
+                None,
+            );
+
+            // writing the condition is best-effort, the decision to skip is
+            // already recorded in metrics and debug state
+            if let Err(err) = skip::skip(ctx.kube.to_owned(), &*obj, reason, &message).await {
+                warn!(
                     kind = &api_resource.kind,
                     namespace = &namespace,
                     name = &name,
                     error = err.to_string(),
-                    "Failed to delete custom resource"
+                    "Could not write skipped condition on custom resource"
                 );
-
-                return Err(err);
             }
+        } else if generation::unchanged(&*obj) {
+            debug!(
+                kind = &api_resource.kind,
+                namespace = &namespace,
+                name = &name,
+                "Skip reconciliation of custom resource, only its status has been modified",
+            );
         } else {
             info!(
                 kind = &api_resource.kind,
@@ -219,33 +458,172 @@ where
                 "Received upsertion event for custom resource",
             );
 
+            if let Err(err) = skip::clear(ctx.kube.to_owned(), &*obj).await {
+                warn!(
+                    kind = &api_resource.kind,
+                    namespace = &namespace,
+                    name = &name,
+                    error = err.to_string(),
+                    "Could not remove skipped condition from custom resource"
+                );
+            }
+
             #[cfg(feature = "metrics")]
             RECONCILIATION_EVENT
                 .with_label_values(&[&api_resource.kind, &namespace, RECONCILIATION_UPSERT_EVENT])
                 .inc();
 
             #[cfg(not(feature = "trace"))]
-            let result = Self::upsert(ctx, obj.to_owned()).await;
+            let result = Self::upsert(ctx.to_owned(), obj.to_owned()).await;
 
             #[cfg(feature = "trace")]
-            let result = Self::upsert(ctx, obj.to_owned())
-                .instrument(tracing::info_span!("Reconciler::upsert"))
+            let result = Self::upsert(ctx.to_owned(), obj.to_owned())
+                .instrument(tracing::info_span!(
+                    "Reconciler::upsert",
+                    kind = %api_resource.kind,
+                    namespace = %namespace,
+                    name = %name,
+                ))
                 .await;
 
             if let Err(err) = result {
-                error!(
+                tracker::resource(&api_resource.kind, &namespace, &name, || {
+                    error!(
+                        kind = &api_resource.kind,
+                        namespace = &namespace,
+                        name = &name,
+                        error = err.to_string(),
+                        "Failed to upsert custom resource"
+                    )
+                });
+
+                generation::record(&*obj, false);
+
+                registry::record(
+                    &api_resource.kind,
+                    &namespace,
+                    &name,
+                    registry::Status::Failed,
+                    Some(&err.to_string()),
+                );
+
+                // the object is not ready anymore, whether the error is
+                // retried or not
+                let ready = Condition::new(
+                    condition::READY,
+                    condition::Status::False,
+                    condition::REASON_RECONCILIATION_FAILED,
+                    &err.to_string(),
+                )
+                .with_observed_generation(obj.meta().generation);
+
+                if let Err(err) = condition::update(ctx.kube.to_owned(), &*obj, |conditions| {
+                    condition::set(conditions, ready)
+                })
+                .await
+                {
+                    warn!(
+                        kind = &api_resource.kind,
+                        namespace = &namespace,
+                        name = &name,
+                        error = err.to_string(),
+                        "Could not write ready condition on custom resource"
+                    );
+                }
+
+                let reason = match Self::permanent(&err) {
+                    Some(reason) => reason,
+                    None => return Err(err),
+                };
+
+                warn!(
                     kind = &api_resource.kind,
                     namespace = &namespace,
                     name = &name,
-                    error = err.to_string(),
-                    "Failed to upsert custom resource"
+                    reason = reason,
+                    "Stop to retry reconciliation of custom resource until it is modified, the error is permanent"
                 );
 
-                return Err(err);
+                let condition = Condition::new(
+                    condition::ERROR,
+                    condition::Status::True,
+                    reason,
+                    &err.to_string(),
+                )
+                .with_observed_generation(obj.meta().generation);
+
+                if let Err(err) = condition::update(ctx.kube.to_owned(), &*obj, |conditions| {
+                    condition::set(conditions, condition)
+                })
+                .await
+                {
+                    warn!(
+                        kind = &api_resource.kind,
+                        namespace = &namespace,
+                        name = &name,
+                        error = err.to_string(),
+                        "Could not write error condition on custom resource"
+                    );
+                }
+            } else {
+                registry::record(
+                    &api_resource.kind,
+                    &namespace,
+                    &name,
+                    registry::Status::Synced,
+                    None,
+                );
+
+                generation::record(&*obj, true);
+                if let Err(err) = generation::update(ctx.kube.to_owned(), &*obj).await {
+                    warn!(
+                        kind = &api_resource.kind,
+                        namespace = &namespace,
+                        name = &name,
+                        error = err.to_string(),
+                        "Could not write observed generation on custom resource"
+                    );
+                }
+
+                if let Err(err) = condition::update(ctx.kube.to_owned(), &*obj, |conditions| {
+                    condition::remove(conditions, condition::ERROR)
+                })
+                .await
+                {
+                    warn!(
+                        kind = &api_resource.kind,
+                        namespace = &namespace,
+                        name = &name,
+                        error = err.to_string(),
+                        "Could not remove error condition from custom resource"
+                    );
+                }
+
+                // the rotation request is kept on failure, so propagation is
+                // tried again on the next reconciliation of the object
+                if rotation::requested(&*obj) {
+                    if let Err(err) = rotation::propagate(ctx.kube.to_owned(), &*obj).await {
+                        warn!(
+                            kind = &api_resource.kind,
+                            namespace = &namespace,
+                            name = &name,
+                            error = err.to_string(),
+                            "Could not propagate rotated credentials of custom resource"
+                        );
+                    }
+                }
             }
         }
 
-        Ok(Action::await_change())
+        backoff::reset(&backoff::key(&api_resource.kind, &namespace, &name));
+        // custom resources whose addon is probed are reconciled again
+        // periodically, so their health stays accurate
+        let requeue = match (Self::requeue(&obj), probe::requeue(&ctx.config, &*obj)) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+
+        Ok(requeue.map_or_else(Action::await_change, Action::requeue))
     }
 }
 
@@ -281,34 +659,116 @@ where
         + ResourceExt
         + CustomResourceExt
         + DeserializeOwned
+        + Serialize
         + Clone
         + Debug
         + Send
         + Sync
         + 'static,
-    <T as Resource>::DynamicType: Unpin + Eq + Hash + Clone + Debug + Send + Sync,
+    <T as Resource>::DynamicType: Unpin + Eq + Hash + Clone + Debug + Default + Send + Sync,
     Self: Send + Sync + 'static,
     <Self as Reconciler<T>>::Error: WatcherError + Send + Sync,
 {
     type Error: WatcherError + Send + Sync;
 
-    /// listen for events of the custom resource as generic parameter
-    async fn watch(&self, context: Arc<Context>) -> Result<(), <Self as Watcher<T>>::Error> {
+    /// returns if custom resources could be listed in each given namespace,
+    /// the watchers are considered as synced once it is the case
+    async fn listable(client: kube::Client, namespaces: &[Option<&str>]) -> bool {
+        let lists = namespaces.iter().map(|namespace| {
+            let client = client.to_owned();
+            async move {
+                api::<T>(client, *namespace)
+                    .list_metadata(&ListParams::default().limit(1))
+                    .await
+            }
+        });
+
+        join_all(lists).await.iter().all(Result::is_ok)
+    }
+
+    /// listen for events of the custom resource as generic parameter, until
+    /// the given signal is resolved and in-flight reconciliation are done
+    async fn watch<F>(
+        &self,
+        context: Arc<Context>,
+        signal: F,
+    ) -> Result<(), <Self as Watcher<T>>::Error>
+    where
+        F: Future<Output = ()> + Send + Sync + 'static,
+    {
         let api_resource = T::api_resource();
-        let mut stream = self
-            .build(context.to_owned())
-            .run(Self::reconcile, Self::retry, context)
-            .boxed();
+        let signal = signal.shared();
+
+        // listen for events in each watched namespace, or in all namespaces
+        let namespaces = &context.config.watch.namespaces;
+        let namespaces: Vec<Option<&str>> = if namespaces.is_empty() {
+            vec![None]
+        } else {
+            namespaces.iter().map(|ns| Some(ns.as_str())).collect()
+        };
+
+        // reconciliations of the kind are processed in parallel, up to the
+        // configured concurrency shared by controllers of each namespace
+        let permits = Arc::new(Semaphore::new(
+            match context.config.reconciliation.concurrency {
+                0 => Semaphore::MAX_PERMITS,
+                concurrency => concurrency,
+            },
+        ));
+
+        let watched = namespaces.to_owned();
+        let mut stream = select_all(namespaces.into_iter().map(|namespace| {
+            let permits = permits.to_owned();
+
+            self.build(context.to_owned(), namespace)
+                .graceful_shutdown_on(signal.to_owned())
+                .run(
+                    move |obj, ctx| {
+                        let permits = permits.to_owned();
+                        async move {
+                            let _permit = permits.acquire_owned().await;
+                            Self::reconcile(obj, ctx).await
+                        }
+                    },
+                    Self::retry,
+                    context.to_owned(),
+                )
+                .boxed()
+        }));
+
+        // the registration marks the controller as stopped once dropped, which
+        // also happens when the task panics
+        let _registration = health::register(&api_resource.kind);
+        let mut heartbeat = interval(Duration::from_secs(
+            context.config.operator.health.probe_interval,
+        ));
 
         loop {
+            #[cfg(feature = "metrics")]
             let instant = Instant::now();
 
-            match stream.try_next().await {
+            let result = tokio::select! {
+                result = stream.try_next() => result,
+                _ = heartbeat.tick() => {
+                    health::heartbeat(&api_resource.kind);
+                    if Self::listable(context.kube.to_owned(), &watched).await {
+                        health::synced(&api_resource.kind);
+                    }
+
+                    continue;
+                }
+            };
+
+            match result {
                 Ok(None) => {
-                    debug!("We have reached the end of the infinite watch stream");
+                    info!(
+                        kind = &api_resource.kind,
+                        "Stop to listen for events of custom resource"
+                    );
                     return Ok(());
                 }
                 Ok(Some((obj, _action))) => {
+                    health::synced(&api_resource.kind);
                     info!(
                         kind = &api_resource.kind,
                         namespace = &obj.namespace.unwrap_or_else(|| "<none>".to_string()),
@@ -348,21 +808,10 @@ where
                 }
             }
 
-            trace!(
-                kind = &api_resource.kind,
-                duration = Instant::now()
-                    .checked_duration_since(instant + Duration::from_millis(100))
-                    .map(|d| d.as_millis())
-                    .unwrap_or_else(|| 0),
-                "Put watch event loop for resource to sleep",
-            );
-
             #[cfg(feature = "metrics")]
             RECONCILIATION_DURATION
                 .with_label_values(&[&api_resource.kind, "us"])
                 .inc_by(Instant::now().duration_since(instant).as_micros() as f64);
-
-            sleep_until(instant + Duration::from_millis(100)).await;
         }
     }
 }
@@ -374,12 +823,13 @@ where
         + ResourceExt
         + CustomResourceExt
         + DeserializeOwned
+        + Serialize
         + Clone
         + Debug
         + Send
         + Sync
         + 'static,
-    <T as Resource>::DynamicType: Unpin + Eq + Hash + Clone + Debug + Send + Sync,
+    <T as Resource>::DynamicType: Unpin + Eq + Hash + Clone + Debug + Default + Send + Sync,
     U: Reconciler<T> + ControllerBuilder<T>,
     U::Error: WatcherError + Send + Sync,
     Self: Send + Sync + 'static,
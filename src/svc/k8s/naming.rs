@@ -0,0 +1,127 @@
+//! # Naming module
+//!
+//! This module provide helpers to name addons after their custom resource, so
+//! they are recognisable in the Clever Cloud console. The name of an addon is
+//! the display name of the specification of its custom resource, if any, or
+//! else the naming template of the operator rendered for the custom resource,
+//! see [`configure`], or else the name identifying the custom resource, e.g.
+//! `kubernetes::PostgreSql::<uid>`. The name is applied at creation and kept
+//! in sync on each reconciliation.
+
+use std::{fmt::Debug, sync::RwLock};
+
+use clevercloud_sdk::v2::addon::Addon;
+use k8s_openapi::NamespaceResourceScope;
+use kube::{Resource, ResourceExt};
+use tracing::{info, warn};
+
+use crate::svc::{
+    audit,
+    cfg::{self, Configuration},
+    clevercloud::{client::ScopedClient, ext::AddonExt, naming},
+    k8s::resource,
+};
+
+// -----------------------------------------------------------------------------
+// State
+
+/// template of the name of addons, the name identifying the custom resource
+/// is used if it is not set
+static TEMPLATE: RwLock<Option<String>> = RwLock::new(None);
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// configure the template of the name of addons
+pub fn configure(config: &cfg::Naming) {
+    if let Ok(mut state) = TEMPLATE.write() {
+        *state = config
+            .template
+            .to_owned()
+            .filter(|template| !template.trim().is_empty());
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the configured template of the name of addons, if any
+pub fn template() -> Option<String> {
+    TEMPLATE
+        .read()
+        .ok()
+        .and_then(|template| template.to_owned())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the template with the `{kind}`, `{namespace}`, `{name}` and `{uid}`
+/// placeholders replaced by the ones of the custom resource
+pub fn render(template: &str, kind: &str, namespace: &str, name: &str, uid: &str) -> String {
+    template
+        .replace("{kind}", kind)
+        .replace("{namespace}", namespace)
+        .replace("{name}", name)
+        .replace("{uid}", uid)
+        .trim()
+        .to_string()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(apis, config)))]
+/// rename the addon of the custom resource, if its name does not follow the
+/// naming strategy anymore, e.g. once the display name has been changed.
+/// Failures are logged, as the name of an addon is only informative
+pub async fn sync<T>(apis: &ScopedClient, config: &Configuration, obj: &T, addon: &Addon)
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + AddonExt + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let expected = obj.addon_name();
+    if addon.name.as_deref() == Some(expected.as_str()) {
+        return;
+    }
+
+    let kind = T::kind(&Default::default()).to_string();
+    let (namespace, name) = resource::namespaced_name(obj);
+
+    info!(
+        kind = &kind,
+        namespace = &namespace,
+        name = &name,
+        addon = &addon.id,
+        from = addon.name.as_deref().unwrap_or_default(),
+        to = &expected,
+        "Rename addon of custom resource",
+    );
+
+    let organisation = obj.organisation();
+    let result = match apis.authorize(&kind, "addon::rename").await {
+        Ok(client) => naming::rename(
+            client,
+            &config.api.endpoint,
+            &organisation,
+            &addon.id,
+            &expected,
+        )
+        .await
+        .map_err(|err| err.to_string()),
+        Err(err) => Err(err.to_string()),
+    };
+
+    audit::record(
+        "addon::rename",
+        &obj.actor(),
+        Some(&organisation),
+        &addon.id,
+        &result,
+    );
+
+    if let Err(err) = result {
+        warn!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            addon = &addon.id,
+            error = err,
+            "Could not rename addon of custom resource",
+        );
+    }
+}
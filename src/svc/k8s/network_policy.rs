@@ -0,0 +1,220 @@
+//! # Network policy module
+//!
+//! This module provide helpers to generate the network policy allowing egress
+//! from the pods consuming the addon of a custom resource, selected by its
+//! `spec.consumers` field, to the addresses and the port of the addon, so
+//! namespaces denying egress by default keep working without editing policies
+//! by hand. The policy is a kubernetes, a cilium or a calico one, depending on
+//! the configured provider, and is applied using server-side apply as cilium
+//! and calico policies are only known through the unstructured api. Addresses
+//! are resolved by the operator on each reconciliation.
+
+use std::{collections::BTreeMap, fmt::Debug};
+
+use k8s_openapi::{api::networking::v1::NetworkPolicy, NamespaceResourceScope};
+use kube::{
+    api::{ApiResource, DeleteParams, DynamicObject, GroupVersionKind, Patch, PatchParams},
+    Api, CustomResourceExt, Resource, ResourceExt,
+};
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::svc::{
+    cfg::{Configuration, NetworkPolicyProvider},
+    crd::Consumers,
+    k8s::{binding::Endpoint, resource, service},
+};
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to resolve addresses of addon, {0}")]
+    Resolve(service::Error),
+    #[error("failed to apply network policy '{0}', {1}")]
+    Apply(String, kube::Error),
+    #[error("failed to delete network policy '{0}', {1}")]
+    Delete(String, kube::Error),
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the name of the network policy of the custom resource
+pub fn name<T>(obj: &T) -> String
+where
+    T: ResourceExt + Debug,
+{
+    format!("{}-egress", obj.name_any())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the api resource of the network policies of the provider
+pub fn api_resource(provider: NetworkPolicyProvider) -> ApiResource {
+    match provider {
+        NetworkPolicyProvider::Kubernetes => ApiResource::erase::<NetworkPolicy>(&()),
+        NetworkPolicyProvider::Cilium => ApiResource::from_gvk_with_plural(
+            &GroupVersionKind::gvk("cilium.io", "v2", "CiliumNetworkPolicy"),
+            "ciliumnetworkpolicies",
+        ),
+        NetworkPolicyProvider::Calico => ApiResource::from_gvk_with_plural(
+            &GroupVersionKind::gvk("projectcalico.org", "v3", "NetworkPolicy"),
+            "networkpolicies",
+        ),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the calico selector matching the given labels
+pub fn selector(labels: &BTreeMap<String, String>) -> String {
+    if labels.is_empty() {
+        return "all()".to_string();
+    }
+
+    labels
+        .iter()
+        .map(|(key, value)| format!("{} == '{}'", key, value))
+        .collect::<Vec<_>>()
+        .join(" && ")
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the specification of the network policy of the provider allowing
+/// egress from the consumers to the given addresses and port
+pub fn spec(
+    provider: NetworkPolicyProvider,
+    consumers: &Consumers,
+    addresses: &[String],
+    port: u16,
+) -> Value {
+    let cidrs: Vec<String> = addresses
+        .iter()
+        .map(|address| format!("{}/32", address))
+        .collect();
+
+    match provider {
+        NetworkPolicyProvider::Kubernetes => json!({
+            "podSelector": { "matchLabels": consumers.match_labels },
+            "policyTypes": ["Egress"],
+            "egress": [{
+                "to": cidrs
+                    .iter()
+                    .map(|cidr| json!({ "ipBlock": { "cidr": cidr } }))
+                    .collect::<Vec<_>>(),
+                "ports": [{ "protocol": "TCP", "port": port }],
+            }],
+        }),
+        NetworkPolicyProvider::Cilium => json!({
+            "endpointSelector": { "matchLabels": consumers.match_labels },
+            "egress": [{
+                "toCIDR": cidrs,
+                "toPorts": [{ "ports": [{ "port": port.to_string(), "protocol": "TCP" }] }],
+            }],
+        }),
+        NetworkPolicyProvider::Calico => json!({
+            "selector": selector(&consumers.match_labels),
+            "types": ["Egress"],
+            "egress": [{
+                "action": "Allow",
+                "protocol": "TCP",
+                "destination": { "nets": cidrs, "ports": [port] },
+            }],
+        }),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the network policy of the custom resource, owned by it
+pub fn new<T>(
+    obj: &T,
+    provider: NetworkPolicyProvider,
+    consumers: &Consumers,
+    addresses: &[String],
+    port: u16,
+) -> Value
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    let api_resource = api_resource(provider);
+
+    json!({
+        "apiVersion": api_resource.api_version,
+        "kind": api_resource.kind,
+        "metadata": {
+            "name": name(obj),
+            "namespace": obj.namespace(),
+            "ownerReferences": [resource::owner_reference(obj)],
+        },
+        "spec": spec(provider, consumers, addresses, port),
+    })
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client, config)))]
+/// apply the network policy allowing egress from the consumers of the custom
+/// resource to its addon and returns its name, or delete it once consumers
+/// are not given anymore. Nothing is done if network policies are disabled
+pub async fn sync<T>(
+    client: kube::Client,
+    config: &Configuration,
+    obj: &T,
+    consumers: Option<&Consumers>,
+    endpoint: &Endpoint,
+) -> Result<Option<String>, Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    let settings = &config.operator.network_policy;
+    if !settings.enabled {
+        return Ok(None);
+    }
+
+    let (namespace, _) = resource::namespaced_name(obj);
+    let name = name(obj);
+    let api: Api<DynamicObject> =
+        Api::namespaced_with(client, &namespace, &api_resource(settings.provider));
+
+    let consumers = match consumers {
+        Some(consumers) => consumers,
+        None => {
+            return match api.delete(&name, &DeleteParams::default()).await {
+                Ok(_) => {
+                    info!(
+                        namespace = &namespace,
+                        name = &name,
+                        "Delete network policy of custom resource, as it has no consumers anymore",
+                    );
+                    Ok(None)
+                }
+                Err(kube::Error::Api(err)) if err.code == 404 => Ok(None),
+                Err(err) => Err(Error::Delete(name, err)),
+            };
+        }
+    };
+
+    // keep the current policy while the endpoint of the addon is unknown
+    let (host, port) = match (&endpoint.host, endpoint.port) {
+        (Some(host), Some(port)) => (host, port),
+        _ => return Ok(None),
+    };
+
+    let addresses = service::resolve(host, port).await.map_err(Error::Resolve)?;
+
+    let policy = new(obj, settings.provider, consumers, &addresses, port);
+    let params = PatchParams::apply(&config.operator.server_side_apply.field_manager).force();
+
+    info!(
+        namespace = &namespace,
+        name = &name,
+        addresses = addresses.join(", "),
+        port = port,
+        "Apply network policy allowing egress from consumers to addon of custom resource",
+    );
+
+    api.patch(&name, &params, &Patch::Apply(&policy))
+        .await
+        .map_err(|err| Error::Apply(name.to_owned(), err))?;
+
+    Ok(Some(name))
+}
@@ -0,0 +1,70 @@
+//! # Pause module
+//!
+//! This module provide helpers to suspend the reconciliation of a custom
+//! resource without deleting it, e.g. during a manual maintenance on Clever
+//! Cloud. A custom resource is paused if its specification has the `suspend`
+//! field set to `true` or if it carries the [`PAUSED_ANNOTATION`] annotation.
+//! The decision is recorded as a skip, so a `Skipped` condition with the
+//! `Paused` reason is written on the custom resource until it is resumed.
+
+use std::fmt::Debug;
+
+use kube::ResourceExt;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::svc::k8s::skip;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// annotation suspending the reconciliation of the custom resource
+pub const PAUSED_ANNOTATION: &str = "api.clever-cloud.com/paused";
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the specification of the object has the `suspend` field set
+pub fn suspended<T>(obj: &T) -> bool
+where
+    T: Serialize + Debug,
+{
+    serde_json::to_value(obj)
+        .ok()
+        .and_then(|value| value.pointer("/spec/suspend").and_then(Value::as_bool))
+        .unwrap_or_default()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the reason and a message explaining why the reconciliation of the
+/// object is suspended, if it is
+pub fn paused<T>(obj: &T) -> Option<(skip::Reason, String)>
+where
+    T: ResourceExt + Serialize + Debug,
+{
+    if suspended(obj) {
+        return Some((
+            skip::Reason::Paused,
+            "Reconciliation is suspended, set 'spec.suspend' to false to resume it".to_string(),
+        ));
+    }
+
+    let annotated = obj
+        .annotations()
+        .get(PAUSED_ANNOTATION)
+        .map(|value| value == "true")
+        .unwrap_or_default();
+
+    if annotated {
+        return Some((
+            skip::Reason::Paused,
+            format!(
+                "Reconciliation is suspended, remove the '{}' annotation to resume it",
+                PAUSED_ANNOTATION
+            ),
+        ));
+    }
+
+    None
+}
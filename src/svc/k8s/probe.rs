@@ -0,0 +1,291 @@
+//! # Probe module
+//!
+//! This module provide lightweight health probes of the connection to the
+//! addon of a custom resource, as a provisioned addon is not necessarily
+//! reachable from the cluster. Probes open a tcp connection to the endpoint of
+//! the addon and, when the protocol is known, execute a trivial command on it,
+//! i.e. `SELECT 1` on postgresql, `ping` on mongodb and `PING` on redis. The
+//! result is written in the `Healthy` condition of the custom resource and
+//! exported as a gauge. Custom resources holding this condition are reconciled
+//! again on each interval of the configuration, so it stays accurate.
+
+use std::{collections::BTreeMap, fmt::Debug, time::Duration};
+
+use k8s_openapi::NamespaceResourceScope;
+use kube::{Resource, ResourceExt};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_int_gauge_vec, IntGaugeVec};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+use tracing::{debug, warn};
+
+use crate::svc::{
+    cfg::Configuration,
+    clevercloud::{mongodb, postgresql},
+    k8s::{
+        binding::Endpoint,
+        condition::{self, Condition},
+        resource,
+    },
+    redact::Redacted,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const REASON_REACHABLE: &str = "AddonReachable";
+pub const REASON_UNREACHABLE: &str = "AddonUnreachable";
+
+pub const REDIS_PASSWORD: &str = "REDIS_PASSWORD";
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static ADDON_HEALTHY: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        opts!(
+            "kubernetes_operator_addon_healthy",
+            "whether the addon of custom resources is reachable from the operator",
+        ),
+        &["kind", "namespace", "name"]
+    )
+    .expect("metrics 'kubernetes_operator_addon_healthy' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to connect '{0}:{1}', {2}")]
+    Connect(String, u16, std::io::Error),
+    #[error("failed to probe '{0}:{1}' within {2}s")]
+    Timeout(String, u16, u64),
+    #[error("failed to ping redis addon, {0}")]
+    Redis(String),
+    #[error("failed to probe postgresql addon, {0}")]
+    Postgresql(postgresql::Error),
+    #[error("failed to probe mongodb addon, {0}")]
+    MongoDb(mongodb::Error),
+}
+
+// -----------------------------------------------------------------------------
+// Probe enumeration
+
+/// health probe of the addon, the tcp one is used when the protocol of the
+/// addon is not known or its credentials could not be read
+#[derive(Clone, Debug)]
+pub enum Probe {
+    Tcp,
+    Redis(Option<Redacted<String>>),
+    Postgresql(Box<postgresql::Credentials>),
+    MongoDb(Box<mongodb::Credentials>),
+}
+
+impl Probe {
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(secrets)))]
+    /// returns the probe sending `PING` using the password of the environment
+    /// of the addon, if any
+    pub fn redis(secrets: &BTreeMap<String, String>) -> Self {
+        Self::Redis(secrets.get(REDIS_PASSWORD).cloned().map(Redacted::from))
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(secrets)))]
+    /// returns the probe executing `SELECT 1` using the credentials of the
+    /// environment of the addon
+    pub fn postgresql(secrets: &BTreeMap<String, String>) -> Self {
+        postgresql::Credentials::try_from(secrets)
+            .map(|credentials| Self::Postgresql(Box::new(credentials)))
+            .unwrap_or(Self::Tcp)
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument(skip(secrets)))]
+    /// returns the probe executing `ping` using the credentials of the
+    /// environment of the addon
+    pub fn mongodb(secrets: &BTreeMap<String, String>) -> Self {
+        mongodb::Credentials::try_from(secrets)
+            .map(|credentials| Self::MongoDb(Box::new(credentials)))
+            .unwrap_or(Self::Tcp)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns a tcp connection to the given host and port
+async fn connect(host: &str, port: u16) -> Result<TcpStream, Error> {
+    TcpStream::connect((host, port))
+        .await
+        .map_err(|err| Error::Connect(host.to_string(), port, err))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(stream)))]
+/// send the command to redis using its serialization protocol and returns
+/// the first line of the reply, an error reply fails
+async fn command(stream: &mut BufReader<TcpStream>, args: &[&str]) -> Result<String, Error> {
+    let mut request = format!("*{}\r\n", args.len());
+    for arg in args {
+        request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+
+    stream
+        .get_mut()
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|err| Error::Redis(err.to_string()))?;
+
+    let mut reply = String::new();
+    stream
+        .read_line(&mut reply)
+        .await
+        .map_err(|err| Error::Redis(err.to_string()))?;
+
+    let reply = reply.trim_end().to_string();
+    match reply.strip_prefix('-') {
+        Some(err) => Err(Error::Redis(err.to_string())),
+        None if reply.is_empty() => Err(Error::Redis("connection closed".to_string())),
+        None => Ok(reply),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// execute the probe on the given host and port
+pub async fn check(probe: &Probe, host: &str, port: u16, timeout: u64) -> Result<(), Error> {
+    let probe = async {
+        match probe {
+            Probe::Tcp => connect(host, port).await.map(|_| ()),
+            Probe::Redis(password) => {
+                let mut stream = BufReader::new(connect(host, port).await?);
+                if let Some(password) = password {
+                    command(&mut stream, &["AUTH", password.as_str()]).await?;
+                }
+
+                match command(&mut stream, &["PING"]).await?.as_str() {
+                    "+PONG" => Ok(()),
+                    reply => Err(Error::Redis(format!("unexpected reply '{}'", reply))),
+                }
+            }
+            Probe::Postgresql(credentials) => postgresql::Client::from((**credentials).to_owned())
+                .ping()
+                .await
+                .map_err(Error::Postgresql),
+            Probe::MongoDb(credentials) => mongodb::Client::from((**credentials).to_owned())
+                .ping()
+                .await
+                .map_err(Error::MongoDb),
+        }
+    };
+
+    tokio::time::timeout(Duration::from_secs(timeout), probe)
+        .await
+        .map_err(|_| Error::Timeout(host.to_string(), port, timeout))?
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the `Healthy` condition reflecting the result of a probe
+pub fn condition(result: &Result<(), Error>, host: &str, port: u16) -> Condition {
+    match result {
+        Ok(_) => Condition::new(
+            condition::HEALTHY,
+            condition::Status::True,
+            REASON_REACHABLE,
+            &format!("Addon is reachable on '{}:{}'", host, port),
+        ),
+        Err(err) => Condition::new(
+            condition::HEALTHY,
+            condition::Status::False,
+            REASON_UNREACHABLE,
+            &err.to_string(),
+        ),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+/// probe the addon of the custom resource and returns its `Healthy`
+/// condition. It returns none, if probes are disabled or the endpoint of the
+/// addon is unknown
+pub async fn sync<T>(
+    config: &Configuration,
+    obj: &T,
+    probe: &Probe,
+    endpoint: &Endpoint,
+) -> Option<Condition>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let settings = &config.operator.addon_health;
+    if !settings.enabled {
+        return None;
+    }
+
+    let (host, port) = match (&endpoint.host, endpoint.port) {
+        (Some(host), Some(port)) => (host, port),
+        _ => return None,
+    };
+
+    let kind = T::kind(&Default::default()).to_string();
+    let (namespace, name) = resource::namespaced_name(obj);
+    let result = check(probe, host, port, settings.timeout).await;
+
+    match &result {
+        Ok(_) => debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            "Addon of custom resource is reachable",
+        ),
+        Err(err) => warn!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            error = err.to_string(),
+            "Addon of custom resource is not reachable",
+        ),
+    }
+
+    #[cfg(feature = "metrics")]
+    ADDON_HEALTHY
+        .with_label_values(&[&kind, &namespace, &name])
+        .set(i64::from(result.is_ok()));
+
+    Some(condition(&result, host, port))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+/// returns the delay after which the custom resource should be reconciled
+/// again to probe its addon, if probes are enabled and it holds a `Healthy`
+/// condition
+pub fn requeue<T>(config: &Configuration, obj: &T) -> Option<Duration>
+where
+    T: Serialize + Debug,
+{
+    let settings = &config.operator.addon_health;
+    if !settings.enabled {
+        return None;
+    }
+
+    serde_json::to_value(obj)
+        .ok()?
+        .pointer("/status/conditions")
+        .and_then(Value::as_array)?
+        .iter()
+        .any(|condition| condition.get("type").and_then(Value::as_str) == Some(condition::HEALTHY))
+        .then_some(Duration::from_secs(settings.interval.max(1)))
+}
+
+#[cfg(feature = "metrics")]
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// remove the gauge of the given custom resource, once it has been deleted
+pub fn forget(kind: &str, namespace: &str, name: &str) {
+    // the gauge does not exist, if probes are disabled
+    let _ = ADDON_HEALTHY.remove_label_values(&[kind, namespace, name]);
+}
@@ -0,0 +1,66 @@
+//! # Protection module
+//!
+//! This module provide helpers to protect custom resources against an
+//! accidental deletion, e.g. through `kubectl delete ns`. The deletion of a
+//! custom resource carrying the [`PROTECTED_ANNOTATION`] annotation is refused:
+//! the addon is kept on Clever Cloud and the finalizer is kept on the custom
+//! resource until the annotation is removed.
+
+use std::fmt::{self, Debug, Display, Formatter};
+
+use k8s_openapi::NamespaceResourceScope;
+use kube::{Client, CustomResourceExt, Resource, ResourceExt};
+
+use crate::svc::k8s::recorder;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// annotation protecting the custom resource against deletion
+pub const PROTECTED_ANNOTATION: &str = "api.clever-cloud.com/protected";
+
+// -----------------------------------------------------------------------------
+// Action enumeration
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub enum Action {
+    RefuseDeletion,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::RefuseDeletion => write!(f, "RefuseDeletion"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the object is protected against deletion
+pub fn protected<T>(obj: &T) -> bool
+where
+    T: ResourceExt + Debug,
+{
+    obj.annotations()
+        .get(PROTECTED_ANNOTATION)
+        .map(|value| value == "true")
+        .unwrap_or_default()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// record that the deletion of the object is refused
+pub async fn refuse<T>(client: Client, obj: &T) -> Result<(), kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    let message = format!(
+        "Refuse to delete custom resource, remove the '{}' annotation to proceed",
+        PROTECTED_ANNOTATION
+    );
+
+    recorder::warning(client, obj, &Action::RefuseDeletion, &message).await?;
+    Ok(())
+}
@@ -0,0 +1,244 @@
+//! # Role-based access control module
+//!
+//! This module provide the permissions required by the reconcilers of the
+//! operator. Each custom resource module declares the kubernetes resources its
+//! reconciler touches beside its own custom resources, which allows to
+//! generate the minimal roles of the operator from the code.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use k8s_openapi::{
+    api::rbac::v1::PolicyRule,
+    apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
+};
+use kube::CustomResourceExt;
+
+use crate::svc::crd::{
+    addon::{self, Addon},
+    backup::{self, Backup},
+    cellar_bucket::{self, CellarBucket},
+    config_provider::{self, ConfigProvider},
+    elasticsearch::{self, ElasticSearch},
+    fs_bucket::{self, FSBucket},
+    gravitee::{self, Gravitee},
+    jenkins::{self, Jenkins},
+    mailpace::{self, Mailpace},
+    mongodb::{self, MongoDb},
+    mysql::{self, MySql},
+    postgresql::{self, PostgreSql},
+    pulsar::{self, Pulsar},
+    redis::{self, Redis},
+    runtime::{self, Runtime},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const READ: &[&str] = &["get", "list", "watch"];
+pub const WRITE: &[&str] = &[
+    "get", "list", "watch", "create", "update", "patch", "delete",
+];
+
+/// secrets written by the reconciler, e.g. the credentials of an addon
+pub const SECRETS: Permission = Permission {
+    group: "",
+    resources: &["secrets"],
+    verbs: WRITE,
+};
+
+/// secrets only read by the reconciler, e.g. the override of the configuration
+pub const SECRETS_READ: Permission = Permission {
+    group: "",
+    resources: &["secrets"],
+    verbs: READ,
+};
+
+/// config maps referenced by the custom resources or written by the
+/// reconciler, e.g. the non-sensitive variables of a config provider
+pub const CONFIG_MAPS: Permission = Permission {
+    group: "",
+    resources: &["configmaps"],
+    verbs: WRITE,
+};
+
+/// services, and their endpoint slices, exposing the addon of a database
+/// under an in-cluster dns name
+pub const SERVICES: Permission = Permission {
+    group: "",
+    resources: &["services"],
+    verbs: WRITE,
+};
+
+pub const ENDPOINT_SLICES: Permission = Permission {
+    group: "discovery.k8s.io",
+    resources: &["endpointslices"],
+    verbs: WRITE,
+};
+
+/// network policies allowing egress from the consumers of an addon, the ones
+/// of cilium and calico are only used if they are the configured provider
+pub const NETWORK_POLICIES: Permission = Permission {
+    group: "networking.k8s.io",
+    resources: &["networkpolicies"],
+    verbs: WRITE,
+};
+
+pub const CILIUM_NETWORK_POLICIES: Permission = Permission {
+    group: "cilium.io",
+    resources: &["ciliumnetworkpolicies"],
+    verbs: WRITE,
+};
+
+pub const CALICO_NETWORK_POLICIES: Permission = Permission {
+    group: "projectcalico.org",
+    resources: &["networkpolicies"],
+    verbs: WRITE,
+};
+
+/// jobs restoring backups in a database
+pub const JOBS: Permission = Permission {
+    group: "batch",
+    resources: &["jobs"],
+    verbs: &["get", "list", "watch", "create", "update", "patch"],
+};
+
+/// permissions required by every reconciler, to record events and to roll
+/// out deployments on the rotation of credentials
+pub const RECONCILER: &[Permission] = &[
+    Permission {
+        group: "",
+        resources: &["events"],
+        verbs: &["get", "list", "watch", "create", "update", "patch"],
+    },
+    Permission {
+        group: "apps",
+        resources: &["deployments"],
+        verbs: &["get", "list", "watch", "patch"],
+    },
+];
+
+/// permissions required by the leader election, in the namespace of the lease
+pub const LEADER_ELECTION: &[Permission] = &[Permission {
+    group: "coordination.k8s.io",
+    resources: &["leases"],
+    verbs: &["get", "create", "update"],
+}];
+
+/// permissions required to apply the custom resource definitions of the
+/// operator at startup, they are cluster-scoped
+pub const CUSTOM_RESOURCE_DEFINITIONS: &[Permission] = &[Permission {
+    group: "apiextensions.k8s.io",
+    resources: &["customresourcedefinitions"],
+    verbs: &["get", "list", "watch", "create", "patch"],
+}];
+
+// -----------------------------------------------------------------------------
+// Permission structure
+
+/// verbs allowed on resources of an api group
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Permission {
+    pub group: &'static str,
+    pub resources: &'static [&'static str],
+    pub verbs: &'static [&'static str],
+}
+
+// -----------------------------------------------------------------------------
+// helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the custom resource definitions watched by the operator alongside
+/// the permissions of their reconciler
+pub fn reconcilers() -> Vec<(CustomResourceDefinition, &'static [Permission])> {
+    vec![
+        (PostgreSql::crd(), postgresql::PERMISSIONS),
+        (Redis::crd(), redis::PERMISSIONS),
+        (MySql::crd(), mysql::PERMISSIONS),
+        (MongoDb::crd(), mongodb::PERMISSIONS),
+        (Pulsar::crd(), pulsar::PERMISSIONS),
+        (ConfigProvider::crd(), config_provider::PERMISSIONS),
+        (ElasticSearch::crd(), elasticsearch::PERMISSIONS),
+        (Runtime::crd(), runtime::PERMISSIONS),
+        (CellarBucket::crd(), cellar_bucket::PERMISSIONS),
+        (Backup::crd(), backup::PERMISSIONS),
+        (Addon::crd(), addon::PERMISSIONS),
+        (FSBucket::crd(), fs_bucket::PERMISSIONS),
+        (Mailpace::crd(), mailpace::PERMISSIONS),
+        (Jenkins::crd(), jenkins::PERMISSIONS),
+        (Gravitee::crd(), gravitee::PERMISSIONS),
+    ]
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the minimal rules allowing the reconcilers to work, resources
+/// sharing the same api group and verbs are merged in a single rule and the
+/// verbs of a resource are the union of the ones required by each reconciler
+pub fn rules(reconcilers: &[(CustomResourceDefinition, &'static [Permission])]) -> Vec<PolicyRule> {
+    let mut resources: BTreeMap<(String, String), BTreeSet<&str>> = BTreeMap::new();
+    let mut insert = |group: &str, resource: &str, verbs: &[&'static str]| {
+        resources
+            .entry((group.to_string(), resource.to_string()))
+            .or_default()
+            .extend(verbs.iter().copied());
+    };
+
+    for (crd, permissions) in reconcilers {
+        let plural = &crd.spec.names.plural;
+        insert(&crd.spec.group, plural, WRITE);
+        insert(&crd.spec.group, &format!("{}/status", plural), WRITE);
+
+        for permission in permissions.iter().chain(RECONCILER) {
+            for resource in permission.resources {
+                insert(permission.group, resource, permission.verbs);
+            }
+        }
+    }
+
+    // keep the order of verbs of the kubernetes documentation
+    let order = |verb: &str| WRITE.iter().position(|v| *v == verb).unwrap_or(WRITE.len());
+
+    let mut rules: BTreeMap<(String, Vec<String>), Vec<String>> = BTreeMap::new();
+    for ((group, resource), verbs) in resources {
+        let mut verbs: Vec<_> = verbs.into_iter().collect();
+        verbs.sort_by_key(|verb| order(verb));
+
+        rules
+            .entry((group, verbs.into_iter().map(String::from).collect()))
+            .or_default()
+            .push(resource);
+    }
+
+    rules
+        .into_iter()
+        .map(|((group, verbs), resources)| PolicyRule {
+            api_groups: Some(vec![group]),
+            resources: Some(resources),
+            verbs,
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the rules of the given permissions
+pub fn to_rules(permissions: &[Permission]) -> Vec<PolicyRule> {
+    permissions
+        .iter()
+        .map(|permission| PolicyRule {
+            api_groups: Some(vec![permission.group.to_string()]),
+            resources: Some(
+                permission
+                    .resources
+                    .iter()
+                    .map(|resource| resource.to_string())
+                    .collect(),
+            ),
+            verbs: permission
+                .verbs
+                .iter()
+                .map(|verb| verb.to_string())
+                .collect(),
+            ..Default::default()
+        })
+        .collect()
+}
@@ -1,17 +1,20 @@
 //! # Event module
 //!
 //! This module provide helpers to interact with the kubernetes core/v1/event
-//! api
+//! api. Events are named after the object, the level, the action and the
+//! message they report, so repeated identical events are aggregated into a
+//! single one by incrementing its count and series instead of flooding etcd.
 
 use std::fmt::Debug;
 
 use chrono::Utc;
 use k8s_openapi::{
-    api::core::v1::{Event, EventSource},
+    api::core::v1::{Event, EventSeries, EventSource, ObjectReference},
     apimachinery::pkg::apis::meta::v1::{MicroTime, Time},
     NamespaceResourceScope,
 };
 use kube::{api::ObjectMeta, CustomResourceExt, Resource, ResourceExt};
+use sha2::{Digest, Sha256};
 
 use crate::svc::k8s::{recorder::Level, resource};
 
@@ -20,9 +23,35 @@ use crate::svc::k8s::{recorder::Level, resource};
 
 pub const EVENT_FOR: &str = "for";
 
+/// name of the controller reporting events
+pub const REPORTING_CONTROLLER: &str = "clever-operator";
+
 // -----------------------------------------------------------------------------
 // Helper functions
 
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the name of the event reporting the given action and message on
+/// the object, identical events share the same name
+pub fn name<T, U>(obj: &T, kind: &Level, action: &U, message: &str) -> String
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+    U: ToString + Debug,
+{
+    let mut hasher = Sha256::new();
+    for part in [
+        obj.uid().unwrap_or_default(),
+        kind.to_string(),
+        action.to_string(),
+        message.to_string(),
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+
+    let digest = hex::encode(hasher.finalize());
+    format!("{}.{}", obj.name_any(), &digest[..16])
+}
+
 #[cfg_attr(feature = "trace", tracing::instrument)]
 /// create a new event from the given parameters
 pub fn new<T, U>(obj: &T, kind: &Level, action: &U, message: &str) -> Event
@@ -35,12 +64,7 @@ where
     Event {
         metadata: ObjectMeta {
             namespace: obj.namespace(),
-            name: Some(format!(
-                "{}-{}-{}",
-                obj.name_any(),
-                action.to_string().to_lowercase(),
-                now.timestamp()
-            )),
+            name: Some(name(obj, kind, action, message)),
             ..Default::default()
         },
         type_: Some(kind.to_string()),
@@ -52,18 +76,90 @@ where
         last_timestamp: Some(Time(now)),
         message: Some(message.to_string()),
         reason: Some(action.to_string()),
-        reporting_component: Some("clever-operator".to_string()),
-        reporting_instance: Some(format!(
-            "{}/{}",
-            env!("CARGO_PKG_NAME"),
-            env!("CARGO_PKG_VERSION")
-        )),
+        reporting_component: Some(REPORTING_CONTROLLER.to_string()),
+        reporting_instance: Some(instance()),
+        series: None,
+        source: Some(source()),
+        ..Default::default()
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// create a new event from the given parameters, involving the namespace
+/// itself. It is used when the event could not be recorded on the object
+pub fn for_namespace<U>(namespace: &str, kind: &Level, action: &U, message: &str) -> Event
+where
+    U: ToString + Debug,
+{
+    let now = Utc::now();
+
+    let mut hasher = Sha256::new();
+    for part in [kind.to_string(), action.to_string(), message.to_string()] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+
+    let digest = hex::encode(hasher.finalize());
+
+    Event {
+        metadata: ObjectMeta {
+            namespace: Some(namespace.to_string()),
+            name: Some(format!("{}.{}", namespace, &digest[..16])),
+            ..Default::default()
+        },
+        type_: Some(kind.to_string()),
+        action: Some(action.to_string()),
+        count: Some(1),
+        event_time: Some(MicroTime(now)),
+        first_timestamp: Some(Time(now)),
+        involved_object: ObjectReference {
+            api_version: Some("v1".to_string()),
+            kind: Some("Namespace".to_string()),
+            name: Some(namespace.to_string()),
+            ..Default::default()
+        },
+        last_timestamp: Some(Time(now)),
+        message: Some(message.to_string()),
+        reason: Some(action.to_string()),
+        reporting_component: Some(REPORTING_CONTROLLER.to_string()),
+        reporting_instance: Some(instance()),
         series: None,
         source: Some(source()),
         ..Default::default()
     }
 }
 
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the given event, which has been observed once more
+pub fn aggregate(event: &Event) -> Event {
+    let now = Utc::now();
+    let count = event.count.unwrap_or(1) + 1;
+
+    Event {
+        count: Some(count),
+        last_timestamp: Some(Time(now)),
+        reporting_component: Some(REPORTING_CONTROLLER.to_string()),
+        reporting_instance: Some(instance()),
+        series: Some(EventSeries {
+            count: Some(count),
+            last_observed_time: Some(MicroTime(now)),
+        }),
+        ..event.to_owned()
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the instance of the controller reporting events, i.e. the
+/// hostname, which is the name of the pod of the operator, and its version
+pub fn instance() -> String {
+    let host = hostname::get()
+        .ok()
+        .map(|host| host.to_string_lossy().to_string())
+        .unwrap_or_else(|| env!("CARGO_PKG_NAME").to_string());
+
+    format!("{}/{}", host, env!("CARGO_PKG_VERSION"))
+}
+
 #[cfg_attr(feature = "trace", tracing::instrument)]
 /// returns the source of this operator
 pub fn source() -> EventSource {
@@ -72,7 +168,7 @@ pub fn source() -> EventSource {
         .map(|host| host.to_string_lossy().to_string());
 
     EventSource {
-        component: Some("clever-operator".to_string()),
+        component: Some(REPORTING_CONTROLLER.to_string()),
         host,
     }
 }
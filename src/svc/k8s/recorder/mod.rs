@@ -15,9 +15,9 @@ use std::{
 
 use k8s_openapi::{api::core::v1::Event, NamespaceResourceScope};
 use kube::{Client, CustomResourceExt, Resource, ResourceExt};
-use tracing::debug;
 #[cfg(feature = "trace")]
 use tracing::Instrument;
+use tracing::{debug, trace};
 
 use crate::svc::k8s::resource;
 
@@ -120,7 +120,8 @@ where
         .await
 }
 
-/// record an event for the given object
+/// record an event for the given object, or increment the count of an
+/// identical event, if any
 async fn irecord<T, U>(
     client: Client,
     obj: &T,
@@ -140,7 +141,24 @@ where
         "Create an event for resource",
     );
 
-    resource::upsert(client, &event::new(obj, kind, action, message), false).await
+    let event = event::new(obj, kind, action, message);
+    let (namespace, name) = resource::namespaced_name(&event);
+
+    match resource::get::<Event>(client.to_owned(), &namespace, &name).await? {
+        Some(existing) => {
+            trace!(
+                namespace = &namespace,
+                name = &name,
+                count = existing.count.unwrap_or(1),
+                "Aggregate event with an identical one",
+            );
+
+            let modified = event::aggregate(&existing);
+            let patch = resource::diff(&existing, &modified).map_err(kube::Error::SerdeError)?;
+            resource::patch(client, &modified, patch).await
+        }
+        None => resource::create(client, &event).await,
+    }
 }
 
 #[cfg(not(feature = "trace"))]
@@ -234,3 +252,30 @@ where
 {
     record(client, obj, &Level::Warning, action, message).await
 }
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// record an event on the given namespace, e.g. when the object it reports on
+/// could not hold it anymore
+pub async fn namespace<U>(
+    client: Client,
+    namespace: &str,
+    kind: &Level,
+    action: &U,
+    message: &str,
+) -> Result<Event, kube::Error>
+where
+    U: ToString + Debug,
+{
+    debug!(
+        action = action.to_string(),
+        namespace = namespace,
+        message = message,
+        "Create an event for namespace",
+    );
+
+    resource::create(
+        client,
+        &event::for_namespace(namespace, kind, action, message),
+    )
+    .await
+}
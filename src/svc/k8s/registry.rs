@@ -0,0 +1,129 @@
+//! # Registry module
+//!
+//! This module keeps track of custom resources reconciled by the operator,
+//! along with the outcome of their last reconciliation. The registry is held
+//! in memory and updated by reconcilers, it is exposed by the management api
+//! of the http server for dashboards and support tooling.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::svc::k8s::condition;
+
+// -----------------------------------------------------------------------------
+// State
+
+/// tracked custom resources, keyed by kind, namespace and name
+static REGISTRY: Mutex<BTreeMap<(String, String, String), Entry>> = Mutex::new(BTreeMap::new());
+
+// -----------------------------------------------------------------------------
+// Status enumeration
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub enum Status {
+    /// the last reconciliation has succeeded
+    #[serde(rename = "synced")]
+    Synced,
+    /// the last reconciliation has failed, it will be retried
+    #[serde(rename = "failed")]
+    Failed,
+    /// the last reconciliation has been skipped
+    #[serde(rename = "skipped")]
+    Skipped,
+    /// the deletion of the custom resource has been refused
+    #[serde(rename = "protected")]
+    Protected,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Synced => write!(f, "synced"),
+            Self::Failed => write!(f, "failed"),
+            Self::Skipped => write!(f, "skipped"),
+            Self::Protected => write!(f, "protected"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Entry structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Entry {
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "namespace")]
+    pub namespace: String,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "status")]
+    pub status: Status,
+    /// error of the last failed reconciliation, it is cleared once the custom
+    /// resource is synced again
+    #[serde(rename = "lastError", skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(rename = "lastReconcileTimestamp")]
+    pub last_reconcile_timestamp: String,
+    /// time of the last successful reconciliation, if any
+    #[serde(rename = "lastSyncTimestamp", skip_serializing_if = "Option::is_none")]
+    pub last_sync_timestamp: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// record the outcome of the reconciliation of the given custom resource, the
+/// error is only kept if the reconciliation has failed
+pub fn record(kind: &str, namespace: &str, name: &str, status: Status, error: Option<&str>) {
+    let now = condition::now();
+
+    if let Ok(mut registry) = REGISTRY.lock() {
+        let entry = registry
+            .entry((kind.to_string(), namespace.to_string(), name.to_string()))
+            .or_insert_with(|| Entry {
+                kind: kind.to_string(),
+                namespace: namespace.to_string(),
+                name: name.to_string(),
+                status,
+                last_error: None,
+                last_reconcile_timestamp: now.to_owned(),
+                last_sync_timestamp: None,
+            });
+
+        entry.status = status;
+        entry.last_reconcile_timestamp = now.to_owned();
+
+        match status {
+            Status::Synced => {
+                entry.last_error = None;
+                entry.last_sync_timestamp = Some(now);
+            }
+            Status::Failed => entry.last_error = error.map(String::from),
+            Status::Skipped | Status::Protected => {}
+        }
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// stop to track the given custom resource, once it has been deleted
+pub fn forget(kind: &str, namespace: &str, name: &str) {
+    if let Ok(mut registry) = REGISTRY.lock() {
+        registry.remove(&(kind.to_string(), namespace.to_string(), name.to_string()));
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the tracked custom resources, ordered by kind, namespace and name
+pub fn list() -> Vec<Entry> {
+    REGISTRY
+        .lock()
+        .map(|registry| registry.values().cloned().collect())
+        .unwrap_or_default()
+}
@@ -1,10 +1,14 @@
 //! # Resource module
 //!
 //! This module provide helpers on kubernetes [`Resource`]
+//!
+//! Resources are upserted using json patches computed from the difference
+//! with the object known by kubernetes, or using server-side apply, if it is
+//! enabled in the configuration, see [`configure`].
 
-use std::fmt::Debug;
 #[cfg(feature = "metrics")]
 use std::time::Instant;
+use std::{fmt::Debug, sync::RwLock};
 
 use k8s_openapi::{
     api::core::v1::ObjectReference, apimachinery::pkg::apis::meta::v1::OwnerReference,
@@ -21,7 +25,19 @@ use prometheus::{opts, register_counter_vec, CounterVec};
 use serde::{de::DeserializeOwned, Serialize};
 #[cfg(feature = "trace")]
 use tracing::Instrument;
-use tracing::{debug, level_enabled, trace, Level};
+use tracing::{debug, info, level_enabled, trace, Level};
+
+use crate::svc::{
+    audit::{self, Actor},
+    cfg,
+};
+
+// -----------------------------------------------------------------------------
+// State
+
+/// name of the field manager used to apply resources, server-side apply is
+/// disabled if it is not set
+static FIELD_MANAGER: RwLock<Option<String>> = RwLock::new(None);
 
 // -----------------------------------------------------------------------------
 // Telemetry
@@ -62,6 +78,34 @@ static CLIENT_REQUEST_DURATION: Lazy<CounterVec> = Lazy::new(|| {
     .expect("metrics 'kubernetes_client_request_duration' to not be already registered")
 });
 
+// -----------------------------------------------------------------------------
+// Server-side apply
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// enable or disable server-side apply of resources using the given
+/// configuration
+pub fn configure(config: &cfg::ServerSideApply) {
+    if config.enabled {
+        info!(
+            field_manager = &config.field_manager,
+            "Use server-side apply to manage kubernetes resources"
+        );
+    }
+
+    if let Ok(mut state) = FIELD_MANAGER.write() {
+        *state = Some(config.field_manager.to_owned()).filter(|_| config.enabled);
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the field manager to use, if server-side apply is enabled
+fn field_manager() -> Option<String> {
+    FIELD_MANAGER
+        .read()
+        .ok()
+        .and_then(|manager| manager.to_owned())
+}
+
 // -----------------------------------------------------------------------------
 // Helpers functions
 
@@ -240,6 +284,10 @@ where
         );
     }
 
+    if let Some(manager) = field_manager() {
+        return apply(client, &obj, &manager, true).await;
+    }
+
     #[cfg(feature = "metrics")]
     let instant = Instant::now();
     let result = Api::namespaced(client, &namespace)
@@ -508,9 +556,36 @@ where
         .await
 }
 
+/// upsert the given kubernetes object and record it in the audit log on
+/// behalf of its controller, if any
+async fn iupsert<T>(client: Client, obj: &T, status: bool) -> Result<T, kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope>
+        + ResourceExt
+        + Serialize
+        + DeserializeOwned
+        + Clone
+        + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let kind = T::kind(&Default::default()).to_lowercase();
+    let (namespace, name) = namespaced_name(obj);
+    let actor = obj
+        .owner_references()
+        .iter()
+        .find(|owner| owner.controller == Some(true))
+        .map(|owner| Actor::new(&owner.kind, &namespace, &owner.name))
+        .unwrap_or_else(|| Actor::new(&T::kind(&Default::default()), &namespace, &name));
+
+    let result = write(client, obj, status).await;
+    audit::record(&format!("{}::upsert", kind), &actor, None, &name, &result);
+
+    result
+}
+
 /// upsert the given kubernetes object, get it and create it, if it does not
 /// exist or else patch it
-async fn iupsert<T>(client: Client, obj: &T, status: bool) -> Result<T, kube::Error>
+async fn write<T>(client: Client, obj: &T, status: bool) -> Result<T, kube::Error>
 where
     T: Resource<Scope = NamespaceResourceScope>
         + ResourceExt
@@ -520,6 +595,17 @@ where
         + Debug,
     <T as Resource>::DynamicType: Default,
 {
+    if let Some(manager) = field_manager() {
+        let applied = apply(client.to_owned(), obj, &manager, false).await?;
+
+        // todo: change this boolean to a polymorphic implementation instead
+        if status {
+            return apply(client, obj, &manager, true).await;
+        }
+
+        return Ok(applied);
+    }
+
     let (ns, name) = namespaced_name(obj);
     if let Some(o) = get(client.to_owned(), &ns, &name).await? {
         let p = diff(&o, obj).map_err(kube::Error::SerdeError)?;
@@ -536,8 +622,87 @@ where
     create(client, obj).await
 }
 
+/// apply the given kubernetes object, or only its status, using server-side
+/// apply with the given field manager. Conflicts are forced, as the operator
+/// is the owner of the fields it applies
+async fn apply<T>(client: Client, obj: &T, manager: &str, status: bool) -> Result<T, kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + DeserializeOwned + Serialize + Clone + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let (namespace, name) = namespaced_name(obj);
+    let dt = <T as Resource>::DynamicType::default();
+
+    let mut payload = serde_json::to_value(obj).map_err(kube::Error::SerdeError)?;
+    if status {
+        payload = serde_json::json!({
+            "metadata": {
+                "name": name,
+                "namespace": namespace,
+            },
+            "status": payload.get("status").cloned().unwrap_or_default(),
+        });
+    } else if let Some(metadata) = payload
+        .get_mut("metadata")
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        // server-side apply rejects objects with managed fields and the
+        // resource version would prevent to apply a stale object
+        metadata.remove("managedFields");
+        metadata.remove("resourceVersion");
+    }
+
+    if let Some(object) = payload.as_object_mut() {
+        object.insert("apiVersion".into(), T::api_version(&dt).into());
+        object.insert("kind".into(), T::kind(&dt).into());
+    }
+
+    if level_enabled!(Level::TRACE) {
+        trace!(
+            namespace = &namespace,
+            name = &name,
+            field_manager = manager,
+            status = status,
+            payload = payload.to_string(),
+            "execute apply request on resource",
+        );
+    }
+
+    let api: Api<T> = Api::namespaced(client, &namespace);
+    let params = PatchParams::apply(manager).force();
+
+    #[cfg(feature = "metrics")]
+    let instant = Instant::now();
+    let result = if status {
+        api.patch_status(&name, &params, &Patch::Apply(payload))
+            .await
+    } else {
+        api.patch(&name, &params, &Patch::Apply(payload)).await
+    };
+
+    #[cfg(feature = "metrics")]
+    if result.is_ok() {
+        CLIENT_REQUEST_SUCCESS
+            .with_label_values(&["APPLY", &namespace])
+            .inc();
+    } else {
+        CLIENT_REQUEST_FAILURE
+            .with_label_values(&["APPLY", &namespace])
+            .inc();
+    }
+
+    #[cfg(feature = "metrics")]
+    CLIENT_REQUEST_DURATION
+        .with_label_values(&["APPLY", &namespace, "us"])
+        .inc_by(Instant::now().duration_since(instant).as_micros() as f64);
+
+    result
+}
+
 #[cfg_attr(feature = "trace", tracing::instrument)]
-/// returns a owner reference object pointing to the given resource
+/// returns a owner reference object pointing to the given resource, as the
+/// controller of the owned object, so changes of the owned object trigger a
+/// reconciliation of the given resource
 pub fn owner_reference<T>(obj: &T) -> OwnerReference
 where
     T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
@@ -547,7 +712,7 @@ where
     OwnerReference {
         api_version: api_resource.api_version,
         block_owner_deletion: Some(true),
-        controller: None,
+        controller: Some(true),
         kind: api_resource.kind,
         name: obj.name_any(),
         uid: obj
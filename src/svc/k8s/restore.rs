@@ -0,0 +1,326 @@
+//! # Restore module
+//!
+//! This module provide helpers to restore a backup of a database addon on a
+//! freshly provisioned one. Clever Cloud does not provide a way to provision an
+//! addon from a backup, so the backup is downloaded and restored by a kubernetes
+//! [`Job`] using the secret generated for the custom resource. The download url
+//! of the backup is signed, it is given to the job through a dedicated secret.
+
+use std::{collections::BTreeMap, fmt::Debug};
+
+use k8s_openapi::{
+    api::{
+        batch::v1::{Job, JobSpec},
+        core::v1::{
+            Container, EmptyDirVolumeSource, EnvFromSource, EnvVar, EnvVarSource, PodSpec,
+            PodTemplateSpec, Secret, SecretEnvSource, SecretKeySelector, Volume, VolumeMount,
+        },
+    },
+    NamespaceResourceScope,
+};
+use kube::{api::ObjectMeta, CustomResourceExt, Resource, ResourceExt};
+use tracing::info;
+
+use crate::svc::{
+    cfg,
+    clevercloud::{backup, client::Client},
+    crd::{Restore, RestoreStatus},
+    k8s::{resource, secret, Context},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const BACKUP_ANNOTATION: &str = "api.clever-cloud.com/backup";
+pub const BACKUP_VOLUME: &str = "backup";
+pub const BACKUP_PATH: &str = "/backup/dump";
+pub const BACKUP_URL: &str = "BACKUP_URL";
+
+pub const PHASE_RUNNING: &str = "Running";
+pub const PHASE_SUCCEEDED: &str = "Succeeded";
+pub const PHASE_FAILED: &str = "Failed";
+
+/// number of retries of the job before considering the restoration failed
+pub const BACKOFF_LIMIT: i32 = 3;
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to list backups, {0}")]
+    Backup(backup::Error),
+    #[error("failed to find backup '{0}' of addon '{1}'")]
+    NotFound(String, String),
+    #[error("failed to find the download url of backup '{0}' of addon '{1}'")]
+    DownloadUrl(String, String),
+    #[error("failed to retrieve or create job '{0}/{1}', {2}")]
+    Job(String, String, kube::Error),
+    #[error("failed to create secret '{0}/{1}' holding the download url of the backup, {2}")]
+    Secret(String, String, kube::Error),
+}
+
+impl From<backup::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: backup::Error) -> Self {
+        Self::Backup(err)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Engine enumeration
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Engine {
+    PostgreSql,
+    MySql,
+    MongoDb,
+}
+
+impl Engine {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the image of the container restoring the backup
+    pub fn image(&self, config: &cfg::Restore) -> String {
+        match self {
+            Self::PostgreSql => config.postgresql_image.to_owned(),
+            Self::MySql => config.mysql_image.to_owned(),
+            Self::MongoDb => config.mongodb_image.to_owned(),
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the shell command restoring the downloaded backup using the
+    /// environment of the addon
+    pub fn command(&self) -> String {
+        match self {
+            Self::PostgreSql => format!(
+                "pg_restore --no-owner --no-privileges --dbname \"$POSTGRESQL_ADDON_URI\" {}",
+                BACKUP_PATH
+            ),
+            Self::MySql => format!(
+                "gzip -dcf {} | mysql --host=\"$MYSQL_ADDON_HOST\" --port=\"$MYSQL_ADDON_PORT\" --user=\"$MYSQL_ADDON_USER\" --password=\"$MYSQL_ADDON_PASSWORD\" \"$MYSQL_ADDON_DB\"",
+                BACKUP_PATH
+            ),
+            Self::MongoDb => format!(
+                "mongorestore --uri \"$MONGODB_ADDON_URI\" --gzip --archive={}",
+                BACKUP_PATH
+            ),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+pub fn name<T>(obj: &T) -> String
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+{
+    format!("{}-restore", obj.name_any())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(url)))]
+/// returns the secret holding the download url of the backup, it is named
+/// after the job and owned by the custom resource
+pub fn secret<T>(obj: &T, url: &str) -> Secret
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    secret::named(
+        obj,
+        &name(obj),
+        BTreeMap::from([(BACKUP_URL.to_string(), url.to_string())]),
+    )
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the job downloading the backup from the url of the restore secret
+/// and restoring it on the addon of the custom resource
+pub fn new<T>(obj: &T, engine: Engine, backup_id: &str, config: &cfg::Restore) -> Job
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    let owner = resource::owner_reference(obj);
+    let volume_mount = VolumeMount {
+        name: BACKUP_VOLUME.to_string(),
+        mount_path: "/backup".to_string(),
+        ..Default::default()
+    };
+
+    let download = Container {
+        name: "download".to_string(),
+        image: Some(config.download_image.to_owned()),
+        args: Some(vec![
+            "--fail".to_string(),
+            "--silent".to_string(),
+            "--show-error".to_string(),
+            "--location".to_string(),
+            "--output".to_string(),
+            BACKUP_PATH.to_string(),
+            format!("$({})", BACKUP_URL),
+        ]),
+        env: Some(vec![EnvVar {
+            name: BACKUP_URL.to_string(),
+            value_from: Some(EnvVarSource {
+                secret_key_ref: Some(SecretKeySelector {
+                    name: Some(name(obj)),
+                    key: BACKUP_URL.to_string(),
+                    optional: Some(false),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }]),
+        volume_mounts: Some(vec![volume_mount.to_owned()]),
+        ..Default::default()
+    };
+
+    let restore = Container {
+        name: "restore".to_string(),
+        image: Some(engine.image(config)),
+        command: Some(vec!["sh".to_string(), "-c".to_string(), engine.command()]),
+        env_from: Some(vec![EnvFromSource {
+            secret_ref: Some(SecretEnvSource {
+                name: Some(secret::name(obj)),
+                optional: Some(false),
+            }),
+            ..Default::default()
+        }]),
+        volume_mounts: Some(vec![volume_mount]),
+        ..Default::default()
+    };
+
+    Job {
+        metadata: ObjectMeta {
+            name: Some(name(obj)),
+            namespace: obj.namespace(),
+            owner_references: Some(vec![owner]),
+            annotations: Some(BTreeMap::from([(
+                BACKUP_ANNOTATION.to_string(),
+                backup_id.to_string(),
+            )])),
+            ..Default::default()
+        },
+        spec: Some(JobSpec {
+            backoff_limit: Some(BACKOFF_LIMIT),
+            template: PodTemplateSpec {
+                metadata: None,
+                spec: Some(PodSpec {
+                    restart_policy: Some("Never".to_string()),
+                    init_containers: Some(vec![download]),
+                    containers: vec![restore],
+                    volumes: Some(vec![Volume {
+                        name: BACKUP_VOLUME.to_string(),
+                        empty_dir: Some(EmptyDirVolumeSource::default()),
+                        ..Default::default()
+                    }]),
+                    ..Default::default()
+                }),
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the phase of the restoration from the status of the job
+pub fn phase(job: &Job) -> &'static str {
+    let status = match &job.status {
+        Some(status) => status,
+        None => return PHASE_RUNNING,
+    };
+
+    if status.succeeded.unwrap_or_default() > 0 {
+        return PHASE_SUCCEEDED;
+    }
+
+    let failed = status
+        .conditions
+        .iter()
+        .flatten()
+        .any(|condition| condition.type_ == PHASE_FAILED && condition.status == "True");
+
+    if failed {
+        PHASE_FAILED
+    } else {
+        PHASE_RUNNING
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(ctx, apis)))]
+/// create the job restoring the backup on the addon of the custom resource,
+/// if it does not exist yet, and returns the state of the restoration. The
+/// job is created once, so the backup is never restored twice on the addon
+pub async fn reconcile<T>(
+    ctx: &Context,
+    apis: &Client,
+    organisation: &str,
+    obj: &T,
+    restore: &Restore,
+    engine: Engine,
+) -> Result<RestoreStatus, Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    let (namespace, job_name) = (obj.namespace().unwrap_or_default(), name(obj));
+    let job: Option<Job> = resource::get(ctx.kube.to_owned(), &namespace, &job_name)
+        .await
+        .map_err(|err| Error::Job(namespace.to_owned(), job_name.to_owned(), err))?;
+
+    let job = match job {
+        Some(job) => job,
+        None => {
+            let backups =
+                backup::list(apis, &ctx.config.api.endpoint, organisation, &restore.addon).await?;
+
+            let backup = match &restore.backup_id {
+                Some(id) => backups.into_iter().find(|backup| &backup.id == id),
+                None => backups
+                    .into_iter()
+                    .max_by(|a, b| a.creation_date.cmp(&b.creation_date)),
+            }
+            .ok_or_else(|| {
+                Error::NotFound(
+                    restore
+                        .backup_id
+                        .to_owned()
+                        .unwrap_or_else(|| "latest".to_string()),
+                    restore.addon.to_owned(),
+                )
+            })?;
+
+            let url = backup.download_url.to_owned().ok_or_else(|| {
+                Error::DownloadUrl(backup.id.to_owned(), restore.addon.to_owned())
+            })?;
+
+            info!(
+                namespace = &namespace,
+                name = &job_name,
+                backup = &backup.id,
+                addon = &restore.addon,
+                "Create job to restore backup",
+            );
+
+            resource::upsert(ctx.kube.to_owned(), &secret(obj, &url), false)
+                .await
+                .map_err(|err| Error::Secret(namespace.to_owned(), job_name.to_owned(), err))?;
+
+            let job = new(obj, engine, &backup.id, &ctx.config.operator.restore);
+            resource::upsert(ctx.kube.to_owned(), &job, false)
+                .await
+                .map_err(|err| Error::Job(namespace.to_owned(), job_name.to_owned(), err))?
+        }
+    };
+
+    Ok(RestoreStatus {
+        backup_id: job
+            .annotations()
+            .get(BACKUP_ANNOTATION)
+            .cloned()
+            .unwrap_or_default(),
+        job: job_name,
+        phase: phase(&job).to_string(),
+    })
+}
@@ -0,0 +1,221 @@
+//! # Rotation module
+//!
+//! This module provide helpers to propagate rotated credentials of a custom
+//! resource, which is requested using the [`ROTATE_ANNOTATION`] annotation.
+//! Deployments of the namespace consuming one of the secrets owned by the
+//! custom resource are rolled out by bumping an annotation of their pod
+//! template, so their pods read the new credentials.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Display, Formatter},
+};
+
+use chrono::{SecondsFormat, Utc};
+use k8s_openapi::{
+    api::{
+        apps::v1::Deployment,
+        core::v1::{PodSpec, Secret},
+    },
+    NamespaceResourceScope,
+};
+use kube::{api::ObjectMeta, Client, CustomResourceExt, Resource, ResourceExt};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::info;
+
+use crate::svc::k8s::{recorder, resource};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// annotation requesting the rotation of the credentials of a custom resource,
+/// it is removed once credentials are propagated
+pub const ROTATE_ANNOTATION: &str = "api.clever-cloud.com/rotate";
+/// annotation bumped on the pod template of deployments consuming rotated
+/// credentials
+pub const ROTATED_AT_ANNOTATION: &str = "api.clever-cloud.com/rotated-at";
+
+// -----------------------------------------------------------------------------
+// Action enumeration
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub enum Action {
+    RotateCredentials,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::RotateCredentials => write!(f, "RotateCredentials"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the rotation of the credentials of the object is requested
+pub fn requested<T>(obj: &T) -> bool
+where
+    T: ResourceExt + Debug,
+{
+    obj.annotations()
+        .get(ROTATE_ANNOTATION)
+        .map(|value| value == "true")
+        .unwrap_or_default()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the pod specification consumes one of the given secrets through
+/// environment variables or volumes
+pub fn consumes(spec: &PodSpec, secrets: &[String]) -> bool {
+    let containers = spec
+        .containers
+        .iter()
+        .chain(spec.init_containers.iter().flatten());
+
+    for container in containers {
+        let env_from = container
+            .env_from
+            .iter()
+            .flatten()
+            .filter_map(|source| source.secret_ref.as_ref())
+            .filter_map(|secret| secret.name.as_ref());
+
+        let env = container
+            .env
+            .iter()
+            .flatten()
+            .filter_map(|var| var.value_from.as_ref())
+            .filter_map(|source| source.secret_key_ref.as_ref())
+            .filter_map(|selector| selector.name.as_ref());
+
+        if env_from.chain(env).any(|name| secrets.contains(name)) {
+            return true;
+        }
+    }
+
+    spec.volumes
+        .iter()
+        .flatten()
+        .filter_map(|volume| volume.secret.as_ref())
+        .filter_map(|secret| secret.secret_name.as_ref())
+        .any(|name| secrets.contains(name))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// roll out deployments of the namespace of the object consuming one of the
+/// secrets it owns and returns their names
+pub async fn rollout<T>(client: Client, obj: &T) -> Result<Vec<String>, kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+{
+    let (namespace, _) = resource::namespaced_name(obj);
+    let uid = obj.uid().unwrap_or_default();
+
+    let secrets: Vec<String> =
+        resource::find_by_labels::<Secret>(client.to_owned(), &namespace, "")
+            .await?
+            .iter()
+            .filter(|secret| {
+                secret
+                    .owner_references()
+                    .iter()
+                    .any(|owner| owner.uid == uid)
+            })
+            .map(ResourceExt::name_any)
+            .collect();
+
+    if secrets.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let mut rolled = vec![];
+    for deployment in
+        resource::find_by_labels::<Deployment>(client.to_owned(), &namespace, "").await?
+    {
+        let consumes = deployment
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.template.spec.as_ref())
+            .map(|spec| consumes(spec, &secrets))
+            .unwrap_or_default();
+
+        if !consumes {
+            continue;
+        }
+
+        let mut modified = deployment.to_owned();
+        if let Some(spec) = modified.spec.as_mut() {
+            spec.template
+                .metadata
+                .get_or_insert_with(ObjectMeta::default)
+                .annotations
+                .get_or_insert_with(BTreeMap::new)
+                .insert(ROTATED_AT_ANNOTATION.to_string(), now.to_owned());
+        }
+
+        info!(
+            namespace = &namespace,
+            name = deployment.name_any(),
+            "Roll out deployment consuming rotated credentials",
+        );
+
+        let patch = resource::diff(&deployment, &modified).map_err(kube::Error::SerdeError)?;
+        resource::patch(client.to_owned(), &deployment, patch).await?;
+        rolled.push(deployment.name_any());
+    }
+
+    Ok(rolled)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// remove the rotation request from the object
+pub async fn clear<T>(client: Client, obj: &T) -> Result<T, kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope>
+        + ResourceExt
+        + Serialize
+        + DeserializeOwned
+        + Clone
+        + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let mut modified = obj.to_owned();
+    modified.annotations_mut().remove(ROTATE_ANNOTATION);
+
+    let patch = resource::diff(obj, &modified).map_err(kube::Error::SerdeError)?;
+    resource::patch(client, obj, patch).await
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// roll out deployments consuming the secrets of the object, record it and
+/// remove the rotation request from the object
+pub async fn propagate<T>(client: Client, obj: &T) -> Result<(), kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope>
+        + ResourceExt
+        + CustomResourceExt
+        + Serialize
+        + DeserializeOwned
+        + Clone
+        + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let deployments = rollout(client.to_owned(), obj).await?;
+    let message = if deployments.is_empty() {
+        "Propagate rotated credentials, no deployment consumes them".to_string()
+    } else {
+        format!(
+            "Propagate rotated credentials, roll out deployments '{}'",
+            deployments.join("', '")
+        )
+    };
+
+    recorder::normal(client.to_owned(), obj, &Action::RotateCredentials, &message).await?;
+    clear(client, obj).await?;
+
+    Ok(())
+}
@@ -2,18 +2,52 @@
 //!
 //! This module provide helpers to generate secrets from a custom resource
 
-use std::{collections::BTreeMap, fmt::Debug};
+use std::{collections::BTreeMap, fmt::Debug, string::FromUtf8Error};
 
 use k8s_openapi::{api::core::v1::Secret, NamespaceResourceScope};
-use kube::{api::ObjectMeta, CustomResourceExt, Resource, ResourceExt};
+use kube::{
+    api::{Api, DeleteParams, ObjectMeta},
+    CustomResourceExt, Resource, ResourceExt,
+};
+use tracing::info;
 
-use crate::svc::k8s::resource;
+use crate::svc::{
+    clevercloud::ext,
+    crd::{CredentialsSecretReference, Exposure, OptionFrom, SecretTemplate},
+    k8s::{cache::ResourceCache, resource},
+    redact::Redacted,
+};
 
 // -----------------------------------------------------------------------------
 // Constants
 
 pub const OVERRIDE_CONFIGURATION_NAME: &str = "clever-operator";
 
+/// annotation of a secret listing, comma separated, the namespaces of custom
+/// resources allowed to reference it as credentials from another namespace
+pub const ALLOWED_NAMESPACES_ANNOTATION: &str = "api.clever-cloud.com/allowed-namespaces";
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to find secret '{0}/{1}'")]
+    NotFound(String, String),
+    #[error("not allowed to read secret '{0}/{1}'")]
+    Forbidden(String, String),
+    #[error("failed to find key '{2}' in secret '{0}/{1}'")]
+    MissingKey(String, String, String),
+    #[error("failed to decode key '{2}' of secret '{0}/{1}' as utf-8, {3}")]
+    Decode(String, String, String, FromUtf8Error),
+    #[error("failed to map value of key '{2}' of secret '{0}/{1}' to unknown option '{3}'")]
+    UnknownOption(String, String, String, String),
+    #[error("failed to retrieve secret '{0}/{1}', {2}")]
+    Get(String, String, kube::Error),
+    #[error("secret '{1}/{2}' does not allow custom resources of namespace '{0}' to reference it, list the namespace in its '{ALLOWED_NAMESPACES_ANNOTATION}' annotation")]
+    CrossNamespace(String, String, String),
+}
+
 // -----------------------------------------------------------------------------
 // Helpers
 
@@ -25,14 +59,49 @@ where
     format!("{}-secrets", obj.name_any())
 }
 
-#[cfg_attr(feature = "trace", tracing::instrument)]
-pub fn new<T>(obj: &T, secrets: BTreeMap<String, String>) -> Secret
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secrets)))]
+/// returns the secret of the custom resource and the list of excluded keys.
+/// Keys matching the exclusion patterns of the operator configuration or of the
+/// exposure of the custom resource are removed from the given secrets, so they
+/// are neither written in the secret nor in its binding. The remaining secrets
+/// are rendered using the given template, if any
+pub fn new<T>(
+    obj: &T,
+    secrets: &mut BTreeMap<String, String>,
+    exposure: Option<&Exposure>,
+    defaults: &[String],
+    template: Option<&SecretTemplate>,
+) -> (Secret, Vec<String>)
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    let excluded = exclude(secrets, &patterns(exposure, defaults));
+    let template = match template {
+        Some(template) => template,
+        None => return (named(obj, &name(obj), secrets.to_owned()), excluded),
+    };
+
+    let mut secret = named(obj, &name(obj), render(secrets.to_owned(), template));
+    if !template.labels.is_empty() {
+        secret.metadata.labels = Some(template.labels.to_owned());
+    }
+
+    if !template.annotations.is_empty() {
+        secret.metadata.annotations = Some(template.annotations.to_owned());
+    }
+
+    (secret, excluded)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secrets)))]
+/// returns a secret with the given name owned by the custom resource
+pub fn named<T>(obj: &T, name: &str, secrets: BTreeMap<String, String>) -> Secret
 where
     T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
 {
     let owner = resource::owner_reference(obj);
     let metadata = ObjectMeta {
-        name: Some(name(obj)),
+        name: Some(name.to_string()),
         namespace: obj.namespace(),
         owner_references: Some(vec![owner]),
         ..Default::default()
@@ -44,3 +113,485 @@ where
         ..Default::default()
     }
 }
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// remove the owner reference to the object from the secrets it owns, so they
+/// are not garbage collected along the object, and returns their names
+pub async fn release<T>(client: kube::Client, obj: &T) -> Result<Vec<String>, kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+{
+    let (namespace, _) = resource::namespaced_name(obj);
+    let uid = obj.uid().unwrap_or_default();
+
+    let mut released = vec![];
+    for secret in resource::find_by_labels::<Secret>(client.to_owned(), &namespace, "").await? {
+        if !secret
+            .owner_references()
+            .iter()
+            .any(|owner| owner.uid == uid)
+        {
+            continue;
+        }
+
+        let mut modified = secret.to_owned();
+        modified
+            .owner_references_mut()
+            .retain(|owner| owner.uid != uid);
+
+        info!(
+            namespace = &namespace,
+            name = secret.name_any(),
+            "Release kubernetes secret from its owner",
+        );
+
+        let patch = resource::diff(&secret, &modified).map_err(kube::Error::SerdeError)?;
+        resource::patch(client.to_owned(), &secret, patch).await?;
+        released.push(secret.name_any());
+    }
+
+    Ok(released)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// delete the secret with the given name, if it exists
+pub async fn remove(client: kube::Client, namespace: &str, name: &str) -> Result<(), kube::Error> {
+    info!(
+        namespace = namespace,
+        name = name,
+        "Delete kubernetes secret",
+    );
+
+    match Api::<Secret>::namespaced(client, namespace)
+        .delete(name, &DeleteParams::default())
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(err)) if err.code == 404 => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the key matches the given glob pattern. The pattern is anchored
+/// at both ends, case sensitive and supports the '*' (any sequence of
+/// characters) and '?' (any character) wildcards
+pub fn matches(pattern: &str, key: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let key: Vec<char> = key.chars().collect();
+    let (mut p, mut k) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while k < key.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == key[k]) {
+            p += 1;
+            k += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, k));
+            p += 1;
+        } else if let Some((bp, bk)) = backtrack {
+            // the last wildcard consumes one more character of the key
+            backtrack = Some((bp, bk + 1));
+            p = bp + 1;
+            k = bk + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| '*' == *c)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secrets)))]
+/// returns the secrets selected by the template, under their new names. Keys
+/// are selected and renamed using their name in the environment of the addon
+pub fn render(
+    secrets: BTreeMap<String, String>,
+    template: &SecretTemplate,
+) -> BTreeMap<String, String> {
+    secrets
+        .into_iter()
+        .filter(|(key, _)| {
+            template.select.is_empty()
+                || template.select.iter().any(|pattern| matches(pattern, key))
+        })
+        .map(|(key, value)| match template.rename.get(&key) {
+            Some(renamed) => (renamed.to_owned(), value),
+            None => (key, value),
+        })
+        .collect()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the exclusion patterns of the custom resource, the ones of the
+/// operator configuration followed by the ones of its exposure, if any
+pub fn patterns(exposure: Option<&Exposure>, defaults: &[String]) -> Vec<String> {
+    let mut patterns = defaults.to_vec();
+    if let Some(exposure) = exposure {
+        patterns.extend(exposure.exclude_keys.to_owned());
+    }
+
+    patterns
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secrets)))]
+/// remove keys matching one of the given glob patterns from the secrets and
+/// returns the list of excluded keys
+pub fn exclude(secrets: &mut BTreeMap<String, String>, patterns: &[String]) -> Vec<String> {
+    let mut excluded = vec![];
+
+    secrets.retain(|key, _| {
+        if patterns.iter().any(|pattern| matches(pattern, key)) {
+            excluded.push(key.to_owned());
+            return false;
+        }
+
+        true
+    });
+
+    excluded
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the decoded data of the given secret. Values are wrapped, so they
+/// never appear in logs or traces. The secret is requested on the api server,
+/// as it could have been written by the operator during the reconciliation
+pub async fn data(
+    client: kube::Client,
+    namespace: &str,
+    name: &str,
+) -> Result<Redacted<BTreeMap<String, String>>, Error> {
+    let secret = match resource::get::<Secret>(client, namespace, name).await {
+        Ok(Some(secret)) => secret,
+        Ok(None) => return Err(Error::NotFound(namespace.to_string(), name.to_string())),
+        Err(kube::Error::Api(err)) if 403 == err.code => {
+            return Err(Error::Forbidden(namespace.to_string(), name.to_string()));
+        }
+        Err(err) => return Err(Error::Get(namespace.to_string(), name.to_string(), err)),
+    };
+
+    let mut data = BTreeMap::new();
+    for (key, value) in secret.data.unwrap_or_default() {
+        let value = String::from_utf8(value.0).map_err(|err| {
+            Error::Decode(namespace.to_string(), name.to_string(), key.to_owned(), err)
+        })?;
+
+        data.insert(key, value);
+    }
+
+    Ok(Redacted(data))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(cache)))]
+/// returns the addon creation options read from the referenced secrets of the
+/// given namespace. Values are wrapped, so they never appear in logs or traces
+pub async fn resolve(
+    cache: &ResourceCache<Secret>,
+    namespace: &str,
+    refs: &[OptionFrom],
+) -> Result<Redacted<BTreeMap<String, String>>, Error> {
+    let mut options = BTreeMap::new();
+    let mut secrets: BTreeMap<String, Secret> = BTreeMap::new();
+
+    for OptionFrom { secret_ref, map_to } in refs {
+        let (name, key) = (secret_ref.name.to_owned(), secret_ref.key.to_owned());
+        if !ext::OPTIONS.contains(&map_to.as_str()) {
+            return Err(Error::UnknownOption(
+                namespace.to_string(),
+                name,
+                key,
+                map_to.to_owned(),
+            ));
+        }
+
+        if !secrets.contains_key(&name) {
+            let secret = match cache.get(namespace, &name).await {
+                Ok(Some(secret)) => secret,
+                Ok(None) => return Err(Error::NotFound(namespace.to_string(), name)),
+                Err(kube::Error::Api(err)) if 403 == err.code => {
+                    return Err(Error::Forbidden(namespace.to_string(), name));
+                }
+                Err(err) => return Err(Error::Get(namespace.to_string(), name, err)),
+            };
+
+            secrets.insert(name.to_owned(), secret);
+        }
+
+        let value = secrets
+            .get(&name)
+            .and_then(|secret| secret.data.as_ref())
+            .and_then(|data| data.get(&key))
+            .ok_or_else(|| {
+                Error::MissingKey(namespace.to_string(), name.to_owned(), key.to_owned())
+            })?;
+
+        let value = String::from_utf8(value.0.to_owned())
+            .map_err(|err| Error::Decode(namespace.to_string(), name, key, err))?;
+
+        options.insert(map_to.to_owned(), value);
+    }
+
+    Ok(Redacted(options))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the namespace and name of the secret overriding the configuration
+/// of the clever cloud client of a custom resource in the given namespace, the
+/// one referenced by its specification or else the `clever-operator` one
+pub fn credentials_reference(
+    namespace: &str,
+    reference: Option<&CredentialsSecretReference>,
+) -> (String, String) {
+    match reference {
+        Some(reference) => (
+            reference
+                .namespace
+                .to_owned()
+                .unwrap_or_else(|| namespace.to_string()),
+            reference.name.to_owned(),
+        ),
+        None => (
+            namespace.to_string(),
+            OVERRIDE_CONFIGURATION_NAME.to_string(),
+        ),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secret)))]
+/// returns if the secret allows custom resources of the given namespace to
+/// reference it as credentials, secrets are only shared within their own
+/// namespace unless they list others in the [`ALLOWED_NAMESPACES_ANNOTATION`]
+pub fn allows(secret: &Secret, namespace: &str) -> bool {
+    if secret.metadata.namespace.as_deref() == Some(namespace) {
+        return true;
+    }
+
+    secret
+        .annotations()
+        .get(ALLOWED_NAMESPACES_ANNOTATION)
+        .map(|namespaces| {
+            namespaces
+                .split(',')
+                .any(|allowed| allowed.trim() == namespace)
+        })
+        .unwrap_or_default()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(cache)))]
+/// returns the secret overriding the configuration of the clever cloud client
+/// of a custom resource in the given namespace. The secret referenced by its
+/// specification is required, while the `clever-operator` one is optional. A
+/// secret of another namespace has to allow the one of the custom resource
+pub async fn credentials(
+    cache: &ResourceCache<Secret>,
+    namespace: &str,
+    reference: Option<&CredentialsSecretReference>,
+) -> Result<Option<Secret>, Error> {
+    let (secret_namespace, name) = credentials_reference(namespace, reference);
+    match cache.get(&secret_namespace, &name).await {
+        Ok(None) if reference.is_some() => Err(Error::NotFound(secret_namespace, name)),
+        Ok(Some(secret)) if secret_namespace != namespace && !allows(&secret, namespace) => Err(
+            Error::CrossNamespace(namespace.to_string(), secret_namespace, name),
+        ),
+        Ok(secret) => Ok(secret),
+        Err(kube::Error::Api(err)) if 403 == err.code => {
+            Err(Error::Forbidden(secret_namespace, name))
+        }
+        Err(err) => Err(Error::Get(secret_namespace, name, err)),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::BTreeMap;
+
+    use k8s_openapi::api::core::v1::Secret;
+    use serde_json::{json, Value};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::svc::{
+        crd::{
+            config_provider::tests::provider, CredentialsSecretReference, Exposure, SecretTemplate,
+        },
+        k8s::cache::ResourceCache,
+    };
+
+    use super::{credentials, matches, new, Error, ALLOWED_NAMESPACES_ANNOTATION};
+
+    #[test]
+    fn matches_is_anchored() {
+        assert!(matches("*_ROOT_PASSWORD", "MYSQL_ROOT_PASSWORD"));
+        assert!(!matches("*_ROOT_PASSWORD", "MYSQL_ROOT_PASSWORD_OLD"));
+        assert!(!matches("ADDON", "ADDON_HOST"));
+        assert!(!matches("HOST", "ADDON_HOST"));
+        assert!(matches("ADDON_*", "ADDON_"));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn matches_is_case_sensitive() {
+        assert!(!matches("*_password", "MYSQL_PASSWORD"));
+        assert!(!matches("MYSQL_*", "mysql_host"));
+        assert!(matches("MYSQL_*", "MYSQL_HOST"));
+    }
+
+    #[test]
+    fn matches_single_character() {
+        assert!(matches("REDIS_?OST", "REDIS_HOST"));
+        assert!(!matches("REDIS_?OST", "REDIS_OST"));
+        assert!(!matches("REDIS_?OST", "REDIS_GHOST"));
+        assert!(matches("*_?_*", "A_B_C"));
+    }
+
+    #[test]
+    fn new_removes_excluded_keys() {
+        let obj = provider("app", "orga_test");
+        let mut secrets = BTreeMap::from([
+            ("MYSQL_ADDON_HOST".to_string(), "host".to_string()),
+            ("MYSQL_ADDON_PASSWORD".to_string(), "password".to_string()),
+            ("MYSQL_ROOT_PASSWORD".to_string(), "root".to_string()),
+        ]);
+
+        let exposure = Exposure {
+            exclude_keys: vec!["*_ADDON_PASSWORD".to_string()],
+        };
+
+        let template = SecretTemplate {
+            rename: BTreeMap::from([("MYSQL_ADDON_HOST".to_string(), "HOST".to_string())]),
+            ..Default::default()
+        };
+
+        let (secret, excluded) = new(
+            &obj,
+            &mut secrets,
+            Some(&exposure),
+            &["*_ROOT_PASSWORD".to_string()],
+            Some(&template),
+        );
+
+        assert_eq!(
+            vec![
+                "MYSQL_ADDON_PASSWORD".to_string(),
+                "MYSQL_ROOT_PASSWORD".to_string()
+            ],
+            excluded
+        );
+        assert_eq!(
+            BTreeMap::from([("MYSQL_ADDON_HOST".to_string(), "host".to_string())]),
+            secrets
+        );
+        assert_eq!(
+            Some(BTreeMap::from([("HOST".to_string(), "host".to_string())])),
+            secret.string_data
+        );
+        assert_eq!(Some("app-secrets".to_string()), secret.metadata.name);
+    }
+
+    #[test]
+    fn new_keeps_secrets_without_patterns() {
+        let obj = provider("app", "orga_test");
+        let mut secrets = BTreeMap::from([("KEY".to_string(), "value".to_string())]);
+
+        let (secret, excluded) = new(&obj, &mut secrets, None, &[], None);
+
+        assert!(excluded.is_empty());
+        assert_eq!(Some(secrets), secret.string_data);
+    }
+
+    /// returns the data of the secret written by the operator for the given
+    /// environment, used to check that it could be consumed by services
+    pub fn data(mut environment: BTreeMap<String, String>) -> BTreeMap<String, String> {
+        let obj = provider("app", "orga_test");
+        let (secret, _) = new(&obj, &mut environment, None, &[], None);
+
+        secret.string_data.unwrap_or_default()
+    }
+
+    /// returns a cache of secrets, not yet synchronised, reading the given
+    /// secret of the `team-b` namespace from a mocked api server
+    async fn cache(server: &MockServer, annotations: Value) -> ResourceCache<Secret> {
+        Mock::given(method("GET"))
+            .and(path("/api/v1/namespaces/team-b/secrets/shared"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "apiVersion": "v1",
+                "kind": "Secret",
+                "metadata": {
+                    "name": "shared",
+                    "namespace": "team-b",
+                    "annotations": annotations,
+                },
+                "data": { "config": "" },
+            })))
+            .mount(server)
+            .await;
+
+        let config = kube::Config::new(server.uri().parse().expect("uri"));
+        let kube = kube::Client::try_from(config).expect("kubernetes client");
+
+        ResourceCache::new(kube, &[])
+    }
+
+    fn reference() -> CredentialsSecretReference {
+        CredentialsSecretReference {
+            name: "shared".to_string(),
+            namespace: Some("team-b".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn credentials_refuses_cross_namespace_reference() {
+        let server = MockServer::start().await;
+        let cache = cache(&server, json!({})).await;
+
+        match credentials(&cache, "team-a", Some(&reference())).await {
+            Err(Error::CrossNamespace(namespace, secret_namespace, name)) => {
+                assert_eq!("team-a", namespace);
+                assert_eq!("team-b", secret_namespace);
+                assert_eq!("shared", name);
+            }
+            other => panic!("expected a cross namespace refusal, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn credentials_refuses_namespace_not_listed() {
+        let server = MockServer::start().await;
+        let cache = cache(&server, json!({ ALLOWED_NAMESPACES_ANNOTATION: "team-c" })).await;
+
+        assert!(matches!(
+            credentials(&cache, "team-a", Some(&reference())).await,
+            Err(Error::CrossNamespace(_, _, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn credentials_accepts_allowed_namespace() {
+        let server = MockServer::start().await;
+        let cache = cache(
+            &server,
+            json!({ ALLOWED_NAMESPACES_ANNOTATION: "team-c, team-a" }),
+        )
+        .await;
+
+        let secret = credentials(&cache, "team-a", Some(&reference()))
+            .await
+            .expect("secret to be shared with the namespace")
+            .expect("secret to exist");
+
+        assert_eq!(Some("shared"), secret.metadata.name.as_deref());
+    }
+
+    #[tokio::test]
+    async fn credentials_accepts_same_namespace() {
+        let server = MockServer::start().await;
+        let cache = cache(&server, json!({})).await;
+
+        assert!(credentials(&cache, "team-b", Some(&reference()))
+            .await
+            .expect("secret of the namespace to be allowed")
+            .is_some());
+    }
+}
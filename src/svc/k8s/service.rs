@@ -0,0 +1,291 @@
+//! # Service module
+//!
+//! This module provide helpers to expose the addon of a custom resource under
+//! a stable in-cluster dns name, e.g. `my-redis.ns.svc`, as requested by its
+//! `spec.service` field, so workloads do not have to read the host of the
+//! addon from its secret. The kubernetes service is either an `ExternalName`
+//! one, that the cluster dns resolves to the host of the addon, or a headless
+//! one, whose endpoint slice holds the addresses of the host resolved by the
+//! operator on each reconciliation. Services are owned by the custom resource
+//! and endpoint slices by their service, so they are garbage collected along.
+
+use std::{collections::BTreeSet, fmt::Debug, net::IpAddr};
+
+use k8s_openapi::{
+    api::{
+        core::v1::{Service, ServicePort, ServiceSpec},
+        discovery::v1::{Endpoint as Address, EndpointPort, EndpointSlice},
+    },
+    apimachinery::pkg::apis::meta::v1::OwnerReference,
+    NamespaceResourceScope,
+};
+use kube::{
+    api::{DeleteParams, ObjectMeta},
+    Api, CustomResourceExt, Resource, ResourceExt,
+};
+use tracing::info;
+
+use crate::svc::{
+    crd::{ClusterService, ClusterServiceType},
+    k8s::{binding::Endpoint, resource},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// label holding the identifier of the custom resource which exposes its addon
+/// through the service
+pub const SOURCE_LABEL: &str = "api.clever-cloud.com/service-source";
+/// label linking an endpoint slice to its service
+pub const SERVICE_NAME_LABEL: &str = "kubernetes.io/service-name";
+/// label giving the controller of an endpoint slice, the endpoint slice
+/// controller of kubernetes leaves the ones managed by others as is
+pub const MANAGED_BY_LABEL: &str = "endpointslice.kubernetes.io/managed-by";
+pub const MANAGED_BY: &str = "clever-operator";
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to resolve addresses of host '{0}', {1}")]
+    Resolve(String, std::io::Error),
+    #[error("failed to execute request on kubernetes api, {0}")]
+    KubeClient(kube::Error),
+}
+
+impl From<kube::Error> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: kube::Error) -> Self {
+        Self::KubeClient(err)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the name of the service, the one of the custom resource if it is
+/// not set
+pub fn name<T>(obj: &T, spec: &ClusterService) -> String
+where
+    T: ResourceExt + Debug,
+{
+    spec.name
+        .to_owned()
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| obj.name_any())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the in-cluster dns name of the service
+pub fn dns<T>(obj: &T, spec: &ClusterService) -> String
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+{
+    let (namespace, _) = resource::namespaced_name(obj);
+    format!("{}.{}.svc", name(obj, spec), namespace)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the service exposing the endpoint of the addon, its port is named
+/// after the kind of the addon, e.g. `redis`
+pub fn new<T>(obj: &T, spec: &ClusterService, host: &str, port: u16, kind: &str) -> Service
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    let ports = Some(vec![ServicePort {
+        name: Some(kind.to_string()),
+        port: i32::from(port),
+        protocol: Some("TCP".to_string()),
+        ..Default::default()
+    }]);
+
+    let service = match spec.kind {
+        ClusterServiceType::ExternalName => ServiceSpec {
+            type_: Some("ExternalName".to_string()),
+            external_name: Some(host.to_string()),
+            ports,
+            ..Default::default()
+        },
+        ClusterServiceType::Headless => ServiceSpec {
+            type_: Some("ClusterIP".to_string()),
+            cluster_ip: Some("None".to_string()),
+            ports,
+            ..Default::default()
+        },
+    };
+
+    Service {
+        metadata: ObjectMeta {
+            name: Some(name(obj, spec)),
+            namespace: obj.namespace(),
+            labels: Some([(SOURCE_LABEL.to_string(), obj.uid().unwrap_or_default())].into()),
+            owner_references: Some(vec![resource::owner_reference(obj)]),
+            ..Default::default()
+        },
+        spec: Some(service),
+        ..Default::default()
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the endpoint slice of the headless service holding the given
+/// addresses, it is owned by the service
+pub fn slice(service: &Service, addresses: Vec<String>, port: u16, kind: &str) -> EndpointSlice {
+    let name = service.name_any();
+
+    EndpointSlice {
+        metadata: ObjectMeta {
+            name: Some(name.to_owned()),
+            namespace: service.namespace(),
+            labels: Some(
+                [
+                    (SERVICE_NAME_LABEL.to_string(), name.to_owned()),
+                    (MANAGED_BY_LABEL.to_string(), MANAGED_BY.to_string()),
+                ]
+                .into(),
+            ),
+            owner_references: Some(vec![OwnerReference {
+                api_version: "v1".to_string(),
+                kind: "Service".to_string(),
+                name,
+                uid: service.uid().unwrap_or_default(),
+                controller: Some(true),
+                block_owner_deletion: Some(true),
+            }]),
+            ..Default::default()
+        },
+        address_type: "IPv4".to_string(),
+        endpoints: addresses
+            .into_iter()
+            .map(|address| Address {
+                addresses: vec![address],
+                ..Default::default()
+            })
+            .collect(),
+        ports: Some(vec![EndpointPort {
+            name: Some(kind.to_string()),
+            port: Some(i32::from(port)),
+            protocol: Some("TCP".to_string()),
+            ..Default::default()
+        }]),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the ipv4 addresses of the host, sorted so the endpoint slice is
+/// only updated once they change
+pub async fn resolve(host: &str, port: u16) -> Result<Vec<String>, Error> {
+    let addresses: BTreeSet<String> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|err| Error::Resolve(host.to_string(), err))?
+        .map(|addr| addr.ip())
+        .filter(IpAddr::is_ipv4)
+        .map(|ip| ip.to_string())
+        .collect();
+
+    Ok(addresses.into_iter().collect())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// delete the services exposing the addon of the custom resource, except the
+/// given one, e.g. once the service has been renamed or is not requested
+/// anymore
+pub async fn prune<T>(client: kube::Client, obj: &T, keep: Option<&str>) -> Result<(), Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+{
+    let (namespace, _) = resource::namespaced_name(obj);
+    let query = format!("{}={}", SOURCE_LABEL, obj.uid().unwrap_or_default());
+    let api: Api<Service> = Api::namespaced(client.to_owned(), &namespace);
+
+    for service in resource::find_by_labels::<Service>(client, &namespace, &query).await? {
+        let name = service.name_any();
+        if Some(name.as_str()) == keep {
+            continue;
+        }
+
+        info!(
+            namespace = &namespace,
+            name = &name,
+            "Delete kubernetes service exposing addon of custom resource",
+        );
+
+        match api.delete(&name, &DeleteParams::default()).await {
+            Ok(_) => {}
+            Err(kube::Error::Api(err)) if err.code == 404 => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// upsert the service exposing the endpoint of the addon of the custom
+/// resource, if requested, and returns its in-cluster dns name. Services which
+/// are not requested anymore are deleted
+pub async fn sync<T>(
+    client: kube::Client,
+    obj: &T,
+    spec: Option<&ClusterService>,
+    endpoint: &Endpoint,
+    kind: &str,
+) -> Result<Option<String>, Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + CustomResourceExt + Debug,
+{
+    let (spec, host, port) = match (spec, &endpoint.host, endpoint.port) {
+        (Some(spec), Some(host), Some(port)) => (spec, host, port),
+        (spec, _, _) => {
+            // keep the service while the endpoint of the addon is unknown
+            let keep = spec.map(|spec| name(obj, spec));
+            prune(client, obj, keep.as_deref()).await?;
+            return Ok(None);
+        }
+    };
+
+    let service = new(obj, spec, host, port, kind);
+    let (namespace, name) = resource::namespaced_name(&service);
+    prune(client.to_owned(), obj, Some(&name)).await?;
+
+    // the type of a service could not be changed from `ExternalName` to a
+    // headless one, and conversely, the service is then created again
+    let current: Option<Service> = resource::get(client.to_owned(), &namespace, &name).await?;
+    let kind_of = |service: &Service| service.spec.as_ref().and_then(|s| s.type_.to_owned());
+    if let Some(current) = current {
+        if kind_of(&current) != kind_of(&service) {
+            info!(
+                namespace = &namespace,
+                name = &name,
+                "Delete kubernetes service to change its type",
+            );
+
+            match Api::<Service>::namespaced(client.to_owned(), &namespace)
+                .delete(&name, &DeleteParams::default())
+                .await
+            {
+                Ok(_) => {}
+                Err(kube::Error::Api(err)) if err.code == 404 => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    info!(
+        namespace = &namespace,
+        name = &name,
+        "Upsert kubernetes service exposing addon of custom resource",
+    );
+
+    let service = resource::upsert(client.to_owned(), &service, false).await?;
+    if ClusterServiceType::Headless == spec.kind {
+        let addresses = resolve(host, port).await?;
+        let slice = slice(&service, addresses, port, kind);
+
+        resource::upsert(client, &slice, false).await?;
+    }
+
+    Ok(Some(dns(obj, spec)))
+}
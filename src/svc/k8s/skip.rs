@@ -0,0 +1,359 @@
+//! # Skip module
+//!
+//! This module gathers decisions of the operator to not reconcile a custom
+//! resource. Each decision is counted, kept in a bounded list of recent skips
+//! exposed for debugging purposes and, where possible, written as a condition
+//! on the custom resource itself.
+
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug, Display, Formatter},
+    sync::Mutex,
+};
+
+use k8s_openapi::NamespaceResourceScope;
+use kube::{Resource, ResourceExt};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_counter_vec, CounterVec};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::debug;
+
+use crate::svc::k8s::{
+    condition::{self, Condition},
+    resource,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const SKIPPED: &str = "Skipped";
+pub const RECENT_SKIPS_CAPACITY: usize = 128;
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static SKIPPED_RECONCILES: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "kubernetes_operator_skipped_reconciles_total",
+            "number of reconciliation skipped by the operator",
+        ),
+        &["kind", "reason"]
+    )
+    .expect("metrics 'kubernetes_operator_skipped_reconciles_total' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// State
+
+// `VecDeque::new` is only usable in a const context since rust 1.68, the
+// queue is lazily created on the first record to keep the supported version
+static RECENT_SKIPS: Mutex<Option<VecDeque<Skip>>> = Mutex::new(None);
+
+// -----------------------------------------------------------------------------
+// Reason enumeration
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub enum Reason {
+    #[serde(rename = "filtered-by-instance")]
+    FilteredByInstance,
+    #[serde(rename = "kind-disabled")]
+    KindDisabled,
+    #[serde(rename = "namespace-not-watched")]
+    NamespaceNotWatched,
+    #[serde(rename = "paused")]
+    Paused,
+    #[serde(rename = "policy-denied")]
+    PolicyDenied,
+    #[serde(rename = "dry-run")]
+    DryRun,
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::FilteredByInstance => write!(f, "filtered-by-instance"),
+            Self::KindDisabled => write!(f, "kind-disabled"),
+            Self::NamespaceNotWatched => write!(f, "namespace-not-watched"),
+            Self::Paused => write!(f, "paused"),
+            Self::PolicyDenied => write!(f, "policy-denied"),
+            Self::DryRun => write!(f, "dry-run"),
+        }
+    }
+}
+
+impl Reason {
+    /// returns the reason in the format expected for conditions
+    pub fn to_condition_reason(&self) -> &'static str {
+        match self {
+            Self::FilteredByInstance => "FilteredByInstance",
+            Self::KindDisabled => "KindDisabled",
+            Self::NamespaceNotWatched => "NamespaceNotWatched",
+            Self::Paused => "Paused",
+            Self::PolicyDenied => "PolicyDenied",
+            Self::DryRun => "DryRun",
+        }
+    }
+
+    /// returns if a condition could be written on the resource, resources
+    /// filtered at the watch level are not ours to update
+    pub fn is_visible(&self) -> bool {
+        !matches!(
+            self,
+            Self::FilteredByInstance | Self::KindDisabled | Self::NamespaceNotWatched
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Skip structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Skip {
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "namespace")]
+    pub namespace: String,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "reason")]
+    pub reason: Reason,
+    #[serde(rename = "message")]
+    pub message: String,
+    #[serde(rename = "timestamp")]
+    pub timestamp: String,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// record the decision to skip the reconciliation of the given object
+pub fn record(kind: &str, namespace: &str, name: &str, reason: Reason, message: &str) {
+    debug!(
+        kind = kind,
+        namespace = namespace,
+        name = name,
+        reason = reason.to_string(),
+        message = message,
+        "Skip reconciliation of custom resource",
+    );
+
+    #[cfg(feature = "metrics")]
+    SKIPPED_RECONCILES
+        .with_label_values(&[kind, &reason.to_string()])
+        .inc();
+
+    if let Ok(mut skips) = RECENT_SKIPS.lock() {
+        let skips = skips.get_or_insert_with(VecDeque::new);
+        if skips.len() >= RECENT_SKIPS_CAPACITY {
+            skips.pop_front();
+        }
+
+        skips.push_back(Skip {
+            kind: kind.to_string(),
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            reason,
+            message: message.to_string(),
+            timestamp: condition::now(),
+        });
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the recent skips, from the oldest to the newest
+pub fn recent() -> Vec<Skip> {
+    RECENT_SKIPS
+        .lock()
+        .map(|skips| skips.iter().flatten().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// record the decision to skip the reconciliation of the given object and
+/// write it as a condition on the object, if the reason allows it
+pub async fn skip<T>(
+    client: kube::Client,
+    obj: &T,
+    reason: Reason,
+    message: &str,
+) -> Result<(), kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope>
+        + ResourceExt
+        + DeserializeOwned
+        + Serialize
+        + Clone
+        + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let (namespace, name) = resource::namespaced_name(obj);
+    let kind = T::kind(&Default::default()).to_string();
+
+    record(&kind, &namespace, &name, reason, message);
+    if !reason.is_visible() {
+        return Ok(());
+    }
+
+    let condition = Condition::new(
+        SKIPPED,
+        condition::Status::True,
+        reason.to_condition_reason(),
+        message,
+    )
+    .with_observed_generation(obj.meta().generation);
+
+    condition::update(client, obj, |conditions| {
+        condition::set(conditions, condition)
+    })
+    .await
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// remove the condition written by a previous skip, if any
+pub async fn clear<T>(client: kube::Client, obj: &T) -> Result<(), kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope>
+        + ResourceExt
+        + DeserializeOwned
+        + Serialize
+        + Clone
+        + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    condition::update(client, obj, |conditions| {
+        condition::remove(conditions, SKIPPED)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use wiremock::{
+        http::Method,
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::{recent, skip, Reason, SKIPPED};
+    use crate::svc::crd::config_provider::tests::provider;
+
+    /// skip the reconciliation of a custom resource named after the test and
+    /// check the reason emitted in logs, metrics, recent skips and, if any,
+    /// in the condition patched on the custom resource
+    async fn assert_skip(name: &str, reason: Reason, emitted: &str, condition: Option<&str>) {
+        let server = MockServer::start().await;
+        Mock::given(method("PATCH"))
+            .and(path(format!(
+                "/apis/api.clever-cloud.com/v1/namespaces/default/configproviders/{}/status",
+                name
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_json(provider(name, "orga_test")))
+            .expect(u64::from(condition.is_some()))
+            .mount(&server)
+            .await;
+
+        let config = kube::Config::new(server.uri().parse().expect("uri"));
+        let kube = kube::Client::try_from(config).expect("kubernetes client");
+        let obj = provider(name, "orga_test");
+
+        skip(kube, &obj, reason, "skipped by test")
+            .await
+            .expect("skip to be recorded");
+
+        assert_eq!(emitted, reason.to_string());
+        assert_eq!(
+            json!(emitted),
+            serde_json::to_value(reason).expect("reason")
+        );
+        assert_eq!(condition.is_some(), reason.is_visible());
+
+        let skips: Vec<_> = recent().into_iter().filter(|s| s.name == name).collect();
+        assert_eq!(1, skips.len());
+        assert_eq!("ConfigProvider", skips[0].kind);
+        assert_eq!(
+            json!(emitted),
+            serde_json::to_value(&skips[0]).expect("skip")["reason"]
+        );
+
+        let requests = server.received_requests().await.expect("requests");
+        let patches: Vec<_> = requests
+            .iter()
+            .filter(|request| request.method == Method::Patch)
+            .collect();
+
+        match condition {
+            None => assert!(patches.is_empty()),
+            Some(condition) => {
+                assert_eq!(condition, reason.to_condition_reason());
+                assert_eq!(1, patches.len());
+
+                let patch: Value = serde_json::from_slice(&patches[0].body).expect("json patch");
+                let body = patch.to_string();
+                assert!(
+                    body.contains(&format!("\"type\":\"{}\"", SKIPPED)),
+                    "{}",
+                    body
+                );
+                assert!(
+                    body.contains(&format!("\"reason\":\"{}\"", condition)),
+                    "{}",
+                    body
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn skip_filtered_by_instance() {
+        assert_skip(
+            "filtered-by-instance",
+            Reason::FilteredByInstance,
+            "filtered-by-instance",
+            None,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn skip_kind_disabled() {
+        assert_skip("kind-disabled", Reason::KindDisabled, "kind-disabled", None).await;
+    }
+
+    #[tokio::test]
+    async fn skip_namespace_not_watched() {
+        assert_skip(
+            "namespace-not-watched",
+            Reason::NamespaceNotWatched,
+            "namespace-not-watched",
+            None,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn skip_paused() {
+        assert_skip("paused", Reason::Paused, "paused", Some("Paused")).await;
+    }
+
+    #[tokio::test]
+    async fn skip_policy_denied() {
+        assert_skip(
+            "policy-denied",
+            Reason::PolicyDenied,
+            "policy-denied",
+            Some("PolicyDenied"),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn skip_dry_run() {
+        assert_skip("dry-run", Reason::DryRun, "dry-run", Some("DryRun")).await;
+    }
+}
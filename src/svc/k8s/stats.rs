@@ -0,0 +1,211 @@
+//! # Stats module
+//!
+//! This module export the metrics of addons reconciled by the operator, e.g.
+//! their cpu, memory or connections, as gauges labelled by the custom
+//! resource, so a single scrape of the operator covers both its health and
+//! the one of the addons it manages. Addons are tracked by reconcilers and
+//! their metrics are pulled periodically from the Clever Cloud api by a
+//! background task.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use clevercloud_sdk::v2::addon::Addon;
+use k8s_openapi::NamespaceResourceScope;
+use kube::{Resource, ResourceExt};
+use once_cell::sync::Lazy;
+use prometheus::{opts, register_gauge_vec, GaugeVec};
+use tracing::{debug, warn};
+
+use crate::svc::{
+    cfg::Configuration,
+    clevercloud::{
+        client::{Client, ScopedClient},
+        ext::AddonExt,
+        stats,
+    },
+    k8s::resource,
+};
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+static ADDON_METRIC: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        opts!(
+            "clever_cloud_addon_metric",
+            "latest value of a metric of the addon of custom resources pulled from clever cloud's api",
+        ),
+        &["kind", "namespace", "name", "metric"]
+    )
+    .expect("metrics 'clever_cloud_addon_metric' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// State
+
+/// tracked addons, keyed by kind, namespace and name of their custom resource
+static TRACKED: Mutex<BTreeMap<(String, String, String), Tracked>> = Mutex::new(BTreeMap::new());
+
+// -----------------------------------------------------------------------------
+// Tracked structure
+
+#[derive(Clone, Debug)]
+struct Tracked {
+    /// client authorized to request the organisation of the addon, it could
+    /// be the one of a namespace override
+    client: Client,
+    endpoint: String,
+    organisation: String,
+    /// real identifier of the addon, e.g. `postgresql_<uuid>`
+    id: String,
+    /// metrics exported for the addon, they are removed once it is forgotten
+    metrics: BTreeSet<String>,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(apis, config)))]
+/// track the addon of the custom resource, so its metrics are pulled by the
+/// background task. It does nothing, if addon metrics are disabled
+pub async fn track<T>(apis: &ScopedClient, config: &Configuration, obj: &T, addon: &Addon)
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + AddonExt + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    if !config.operator.addon_metrics.enabled {
+        return;
+    }
+
+    let kind = T::kind(&Default::default()).to_string();
+    let (namespace, name) = resource::namespaced_name(obj);
+
+    let client = match apis.authorize(&kind, "stats::metrics").await {
+        Ok(client) => client.to_owned(),
+        Err(err) => {
+            warn!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                error = err.to_string(),
+                "Could not track the metrics of the addon",
+            );
+            return;
+        }
+    };
+
+    if let Ok(mut tracked) = TRACKED.lock() {
+        let metrics = tracked
+            .remove(&(kind.to_owned(), namespace.to_owned(), name.to_owned()))
+            .map(|tracked| tracked.metrics)
+            .unwrap_or_default();
+
+        tracked.insert(
+            (kind, namespace, name),
+            Tracked {
+                client,
+                endpoint: config.api.endpoint.to_owned(),
+                organisation: obj.organisation(),
+                id: addon.real_id.to_owned(),
+                metrics,
+            },
+        );
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// stop tracking the addon of the given custom resource and remove its
+/// gauges, once it has been deleted
+pub fn forget(kind: &str, namespace: &str, name: &str) {
+    let removed = TRACKED.lock().ok().and_then(|mut tracked| {
+        tracked.remove(&(kind.to_string(), namespace.to_string(), name.to_string()))
+    });
+
+    if let Some(removed) = removed {
+        for metric in &removed.metrics {
+            let _ = ADDON_METRIC.remove_label_values(&[kind, namespace, name, metric]);
+        }
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// periodically pull the metrics of tracked addons and set the gauges to
+/// their latest values. It never returns
+pub async fn watch(config: Arc<Configuration>) {
+    let addon_metrics = &config.operator.addon_metrics;
+    if !addon_metrics.enabled {
+        debug!("Addon metrics are disabled");
+        return futures::future::pending().await;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(addon_metrics.interval.max(1)));
+    loop {
+        interval.tick().await;
+
+        let tracked = match TRACKED.lock() {
+            Ok(tracked) => tracked.to_owned(),
+            Err(_) => continue,
+        };
+
+        for ((kind, namespace, name), addon) in tracked {
+            let metrics = match stats::metrics(
+                &addon.client,
+                &addon.endpoint,
+                &addon.organisation,
+                &addon.id,
+                &addon_metrics.fields,
+            )
+            .await
+            {
+                Ok(metrics) => metrics,
+                Err(err) => {
+                    warn!(
+                        kind = &kind,
+                        namespace = &namespace,
+                        name = &name,
+                        addon = &addon.id,
+                        error = err.to_string(),
+                        "Could not pull the metrics of the addon",
+                    );
+                    continue;
+                }
+            };
+
+            let mut exported = BTreeSet::new();
+            for metric in metrics {
+                if let Some(value) = metric.latest() {
+                    ADDON_METRIC
+                        .with_label_values(&[&kind, &namespace, &name, &metric.name])
+                        .set(value);
+
+                    exported.insert(metric.name);
+                }
+            }
+
+            // the custom resource could have been forgotten in the meantime,
+            // its gauges are then removed right away
+            let key = (kind, namespace, name);
+            let forgotten = match TRACKED.lock() {
+                Ok(mut tracked) => match tracked.get_mut(&key) {
+                    Some(current) => {
+                        current.metrics.extend(exported.iter().cloned());
+                        false
+                    }
+                    None => true,
+                },
+                Err(_) => false,
+            };
+
+            if forgotten {
+                for metric in &exported {
+                    let _ = ADDON_METRIC.remove_label_values(&[&key.0, &key.1, &key.2, metric]);
+                }
+            }
+        }
+    }
+}
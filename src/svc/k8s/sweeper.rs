@@ -0,0 +1,293 @@
+//! # Sweeper module
+//!
+//! This module provide a background task which looks for addons created by
+//! the operator whose custom resource does not exist anymore, e.g. because
+//! the operator has crashed while deleting it. Addons are named after the kind
+//! and the identifier of their custom resource, so an addon is orphaned if no
+//! custom resource of this kind has this identifier. Orphaned addons are
+//! reported and, if it is enabled, deleted once they have been orphaned for a
+//! grace period.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use clevercloud_sdk::v2::addon;
+use kube::{
+    api::{ApiResource, DynamicObject, ListParams},
+    Api, ResourceExt,
+};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_int_gauge_vec, IntGaugeVec};
+use tracing::{debug, info, warn};
+
+use crate::svc::{
+    audit::{self, Actor},
+    cfg::{GarbageCollection, NotificationEvent},
+    clevercloud::{
+        client::{ScopeError, ScopedClient},
+        ext::AddonExt,
+    },
+    crd::postgresql::PostgreSql,
+    k8s::{dependency, Context},
+    notification,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// kind used to authorize calls of the sweeper on the clever cloud api
+pub const SCOPE: &str = "GarbageCollection";
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static ORPHANED_ADDONS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        opts!(
+            "kubernetes_operator_orphaned_addons",
+            "number of addons orphaned for longer than the grace period",
+        ),
+        &["organisation"]
+    )
+    .expect("metrics 'kubernetes_operator_orphaned_addons' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// State
+
+/// instant at which addons have been found orphaned, keyed by identifier
+static ORPHANS: Mutex<BTreeMap<String, Instant>> = Mutex::new(BTreeMap::new());
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to list addons of organisation '{0}', {1}")]
+    Addons(String, addon::Error),
+    #[error("failed to list custom resources of kind '{0}', {1}")]
+    CustomResources(String, kube::Error),
+    #[error("{0}")]
+    Scope(ScopeError),
+}
+
+impl From<ScopeError> for Error {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from(err: ScopeError) -> Self {
+        Self::Scope(err)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the kind and the identifier of the custom resource of an addon
+/// created by the operator, e.g. `kubernetes::PostgreSql::<uid>`
+pub fn parse(name: &str) -> Option<(String, String)> {
+    let delimiter = PostgreSql::delimiter();
+    let (kind, uid) = name
+        .strip_prefix(&format!("{}{}", PostgreSql::prefix(), delimiter))?
+        .split_once(&delimiter)?;
+
+    if kind.is_empty() || uid.is_empty() {
+        return None;
+    }
+
+    Some((kind.to_string(), uid.to_string()))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// returns the identifiers of custom resources of the given kind in the
+/// watched namespaces or in all namespaces
+pub async fn uids(
+    client: kube::Client,
+    namespaces: &[String],
+    resource: &ApiResource,
+) -> Result<BTreeSet<String>, kube::Error> {
+    let apis: Vec<Api<DynamicObject>> = if namespaces.is_empty() {
+        vec![Api::all_with(client, resource)]
+    } else {
+        namespaces
+            .iter()
+            .map(|namespace| Api::namespaced_with(client.to_owned(), namespace, resource))
+            .collect()
+    };
+
+    let mut uids = BTreeSet::new();
+    for api in apis {
+        for obj in api.list(&ListParams::default()).await? {
+            if let Some(uid) = obj.uid() {
+                uids.insert(uid);
+            }
+        }
+    }
+
+    Ok(uids)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(ctx)))]
+/// look for orphaned addons in the configured organisations, report the ones
+/// orphaned for longer than the grace period and delete them, if enabled. No
+/// addon is deleted if custom resources could not be listed
+pub async fn sweep(ctx: &Context, config: &GarbageCollection) -> Result<(), Error> {
+    let apis = ScopedClient::unscoped(ctx.apis.get()).with_backend(ctx.backend.to_owned());
+    let grace_period = Duration::from_secs(config.grace_period);
+
+    let mut uids_by_kind: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut orphans = BTreeSet::new();
+
+    for organisation in &config.organisations {
+        let api = apis.backend(SCOPE, "list").await?;
+        let addons = api
+            .list_addons(organisation)
+            .await
+            .map_err(|err| Error::Addons(organisation.to_owned(), err))?;
+
+        let mut orphaned: i64 = 0;
+        for addon in addons {
+            let (kind, uid) = match addon.name.as_deref().and_then(parse) {
+                Some(parsed) => parsed,
+                None => continue,
+            };
+
+            let resource = match dependency::api_resource(&kind) {
+                Some(resource) => resource,
+                None => continue,
+            };
+
+            // custom resources are listed after addons, so the custom
+            // resource of an addon listed above could not be missed
+            if !uids_by_kind.contains_key(&kind) {
+                let uids = uids(ctx.kube.to_owned(), &ctx.config.watch.namespaces, &resource)
+                    .await
+                    .map_err(|err| Error::CustomResources(kind.to_owned(), err))?;
+
+                uids_by_kind.insert(kind.to_owned(), uids);
+            }
+
+            if uids_by_kind
+                .get(&kind)
+                .map(|uids| uids.contains(&uid))
+                .unwrap_or_default()
+            {
+                continue;
+            }
+
+            orphans.insert(addon.id.to_owned());
+            let since = ORPHANS
+                .lock()
+                .map(|mut orphans| {
+                    *orphans
+                        .entry(addon.id.to_owned())
+                        .or_insert_with(Instant::now)
+                })
+                .unwrap_or_else(|_| Instant::now());
+
+            if since.elapsed() < grace_period {
+                debug!(
+                    organisation = organisation,
+                    addon = &addon.id,
+                    kind = &kind,
+                    uid = &uid,
+                    "Addon is orphaned, wait for the grace period to elapse",
+                );
+                continue;
+            }
+
+            orphaned += 1;
+            if !config.delete {
+                warn!(
+                    organisation = organisation,
+                    addon = &addon.id,
+                    kind = &kind,
+                    uid = &uid,
+                    "Found orphaned addon, its custom resource does not exist anymore",
+                );
+                continue;
+            }
+
+            info!(
+                organisation = organisation,
+                addon = &addon.id,
+                kind = &kind,
+                uid = &uid,
+                "Delete orphaned addon, its custom resource does not exist anymore",
+            );
+
+            let api = apis.backend(SCOPE, "delete").await?;
+            let result = api.delete_addon(organisation, &addon.id).await;
+            audit::record(
+                "addon::delete",
+                &Actor::new(&kind, "", &uid),
+                Some(organisation),
+                &addon.id,
+                &result,
+            );
+
+            match result {
+                Ok(()) => {
+                    orphaned -= 1;
+                    orphans.remove(&addon.id);
+                    notification::notify(
+                        NotificationEvent::AddonDeleted,
+                        &kind,
+                        "",
+                        &uid,
+                        Some(&addon.id),
+                        &format!("Orphaned addon '{}' is deleted", addon.id),
+                    );
+                }
+                Err(err) => {
+                    warn!(
+                        organisation = organisation,
+                        addon = &addon.id,
+                        error = err.to_string(),
+                        "Could not delete orphaned addon",
+                    );
+                }
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        ORPHANED_ADDONS
+            .with_label_values(&[organisation])
+            .set(orphaned);
+
+        #[cfg(not(feature = "metrics"))]
+        let _ = orphaned;
+    }
+
+    // addons which are not orphaned anymore, or deleted, are forgotten
+    if let Ok(mut state) = ORPHANS.lock() {
+        state.retain(|id, _| orphans.contains(id));
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(ctx)))]
+/// periodically sweep orphaned addons, this function never returns and does
+/// nothing if the garbage collection is disabled
+pub async fn watch(ctx: Arc<Context>) {
+    let config = &ctx.config.operator.garbage_collection;
+    if !config.enabled {
+        debug!("Garbage collection of orphaned addons is disabled");
+        return futures::future::pending().await;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval.max(1)));
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = sweep(&ctx, config).await {
+            warn!(error = err.to_string(), "Could not sweep orphaned addons");
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! # Tags module
+//!
+//! This module provide helpers to tag addons with the kubernetes metadata of
+//! their custom resource, i.e. the name of the cluster, the kind, the
+//! namespace, the name and the labels, so they could be traced back from the
+//! Clever Cloud console. Tags managed by the operator start with the configured
+//! prefix, they are pushed once the addon is created and kept in sync on each
+//! reconciliation, while other tags are left as is.
+
+use std::{collections::BTreeSet, fmt::Debug};
+
+use clevercloud_sdk::v2::addon::Addon;
+use k8s_openapi::NamespaceResourceScope;
+use kube::{Resource, ResourceExt};
+use tracing::{debug, warn};
+
+use crate::svc::{
+    audit,
+    cfg::{self, Configuration},
+    clevercloud::{client::ScopedClient, ext::AddonExt, tags},
+    k8s::resource,
+};
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the tags that the addon of the custom resource should have
+pub fn expected<T>(config: &cfg::Tags, obj: &T) -> BTreeSet<String>
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let prefix = &config.prefix;
+    let (namespace, name) = resource::namespaced_name(obj);
+
+    let mut tags = BTreeSet::new();
+    if let Some(cluster) = &config.cluster {
+        tags.insert(format!("{}cluster={}", prefix, cluster));
+    }
+
+    tags.insert(format!("{}kind={}", prefix, T::kind(&Default::default())));
+    tags.insert(format!("{}namespace={}", prefix, namespace));
+    tags.insert(format!("{}name={}", prefix, name));
+
+    if config.labels {
+        for (key, value) in obj.labels() {
+            tags.insert(format!("{}label.{}={}", prefix, key, value));
+        }
+    }
+
+    tags
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(apis, config)))]
+/// add the missing tags to the addon of the custom resource and remove the
+/// ones managed by the operator which are not expected anymore, e.g. once a
+/// label has been removed. Failures are logged, as tags are only informative
+pub async fn sync<T>(apis: &ScopedClient, config: &Configuration, obj: &T, addon: &Addon)
+where
+    T: Resource<Scope = NamespaceResourceScope> + ResourceExt + AddonExt + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let settings = &config.operator.tags;
+    if !settings.enabled {
+        return;
+    }
+
+    let kind = T::kind(&Default::default()).to_string();
+    let (namespace, name) = resource::namespaced_name(obj);
+    let organisation = obj.organisation();
+
+    let client = match apis.authorize(&kind, "addon::tag").await {
+        Ok(client) => client,
+        Err(err) => {
+            warn!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                addon = &addon.id,
+                error = err.to_string(),
+                "Could not authorize to tag addon of custom resource",
+            );
+            return;
+        }
+    };
+
+    let current: BTreeSet<String> =
+        match tags::list(client, &config.api.endpoint, &organisation, &addon.id).await {
+            Ok(current) => current.into_iter().collect(),
+            Err(err) => {
+                warn!(
+                    kind = &kind,
+                    namespace = &namespace,
+                    name = &name,
+                    addon = &addon.id,
+                    error = err.to_string(),
+                    "Could not list tags of addon of custom resource",
+                );
+                return;
+            }
+        };
+
+    let expected = expected(settings, obj);
+    let missing = expected.difference(&current);
+    let stale = current
+        .iter()
+        .filter(|tag| tag.starts_with(&settings.prefix) && !expected.contains(*tag));
+
+    for tag in missing {
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            addon = &addon.id,
+            tag = tag,
+            "Add tag to addon of custom resource",
+        );
+
+        let result = tags::add(client, &config.api.endpoint, &organisation, &addon.id, tag).await;
+        audit::record(
+            "addon::tag",
+            &obj.actor(),
+            Some(&organisation),
+            &addon.id,
+            &result,
+        );
+
+        if let Err(err) = result {
+            warn!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                addon = &addon.id,
+                tag = tag,
+                error = err.to_string(),
+                "Could not add tag to addon of custom resource",
+            );
+        }
+    }
+
+    for tag in stale {
+        debug!(
+            kind = &kind,
+            namespace = &namespace,
+            name = &name,
+            addon = &addon.id,
+            tag = tag,
+            "Remove tag from addon of custom resource",
+        );
+
+        let result =
+            tags::remove(client, &config.api.endpoint, &organisation, &addon.id, tag).await;
+        audit::record(
+            "addon::untag",
+            &obj.actor(),
+            Some(&organisation),
+            &addon.id,
+            &result,
+        );
+
+        if let Err(err) = result {
+            warn!(
+                kind = &kind,
+                namespace = &namespace,
+                name = &name,
+                addon = &addon.id,
+                tag = tag,
+                error = err.to_string(),
+                "Could not remove tag from addon of custom resource",
+            );
+        }
+    }
+}
@@ -3,9 +3,20 @@
 //! This module provide services to interact with kubernetes, clever-cloud and
 //! helpers to do so.
 
+pub mod audit;
+pub mod bench;
 pub mod cfg;
 pub mod clevercloud;
 pub mod crd;
+pub mod crypto;
+pub mod health;
 pub mod http;
 pub mod k8s;
+pub mod notification;
+pub mod redact;
+pub mod reload;
+pub mod shutdown;
+pub mod supervisor;
 pub mod telemetry;
+pub mod tracker;
+pub mod update;
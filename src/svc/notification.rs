@@ -0,0 +1,226 @@
+//! # Notification module
+//!
+//! This module sends notifications to slack, mattermost or generic webhook
+//! targets on outcomes of reconciliations, e.g. the creation or deletion of an
+//! addon, repeated failures of a custom resource or a migration to the plan
+//! overriding the one of an addon. Notifications are sent in the background
+//! and failures to send them are only logged.
+
+use std::{
+    fmt::Debug,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use hyper::{header, Body, Method, Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::svc::{
+    cfg::{
+        Configuration, Notification as NotificationConfig, NotificationEvent, NotificationKind,
+        NotificationTarget,
+    },
+    clevercloud::client::{self, Connector},
+};
+
+// -----------------------------------------------------------------------------
+// State
+
+static NOTIFIER: RwLock<Option<Arc<Notifier>>> = RwLock::new(None);
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to create http client, {0}")]
+    Connector(client::Error),
+    #[error("failed to serialize notification, {0}")]
+    Serialize(serde_json::Error),
+    #[error("failed to build request, {0}")]
+    Request(hyper::http::Error),
+    #[error("failed to execute request, {0}")]
+    Execute(hyper::Error),
+    #[error("failed to execute request, timed out after {0}s")]
+    Timeout(u64),
+    #[error("failed to send notification, target answered with status code '{0}'")]
+    StatusCode(StatusCode),
+}
+
+// -----------------------------------------------------------------------------
+// Notifier structure
+
+#[derive(Clone, Debug)]
+struct Notifier {
+    client: hyper::Client<Connector>,
+    config: NotificationConfig,
+}
+
+// -----------------------------------------------------------------------------
+// Notification structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Notification {
+    #[serde(rename = "event")]
+    pub event: NotificationEvent,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "namespace")]
+    pub namespace: String,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "addon", default, skip_serializing_if = "Option::is_none")]
+    pub addon: Option<String>,
+    #[serde(rename = "message")]
+    pub message: String,
+}
+
+impl Notification {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns the message of the notification rendered with the given
+    /// template
+    pub fn render(&self, template: &str) -> String {
+        template
+            .replace("{event}", &self.event.to_string())
+            .replace("{kind}", &self.kind)
+            .replace("{namespace}", &self.namespace)
+            .replace("{name}", &self.name)
+            .replace("{addon}", self.addon.as_deref().unwrap_or("<none>"))
+            .replace("{message}", &self.message)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Payload structure
+
+/// body posted to generic webhook targets, the rendered message is given in
+/// the `text` field alongside the notification
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+struct Payload {
+    #[serde(flatten)]
+    notification: Notification,
+    #[serde(rename = "text")]
+    text: String,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+/// configure targets to which notifications are sent, notifications are
+/// dropped if there is none
+pub fn configure(config: &Configuration) -> Result<(), Error> {
+    let notifier = if config.notification.targets.is_empty() {
+        None
+    } else {
+        let connector = client::connector(&config.api, &config.proxy).map_err(Error::Connector)?;
+
+        Some(Arc::new(Notifier {
+            client: hyper::Client::builder().build(connector),
+            config: config.notification.to_owned(),
+        }))
+    };
+
+    if let Ok(mut state) = NOTIFIER.write() {
+        *state = notifier;
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// send the event to targets which are interested in it, in the background
+pub fn notify(
+    event: NotificationEvent,
+    kind: &str,
+    namespace: &str,
+    name: &str,
+    addon: Option<&str>,
+    message: &str,
+) {
+    let notifier = match NOTIFIER.read().ok().and_then(|state| state.to_owned()) {
+        Some(notifier) => notifier,
+        None => return,
+    };
+
+    let notification = Notification {
+        event,
+        kind: kind.to_string(),
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        addon: addon.map(String::from),
+        message: message.to_string(),
+    };
+
+    for target in &notifier.config.targets {
+        if !target.events.is_empty() && !target.events.contains(&event) {
+            continue;
+        }
+
+        let client = notifier.client.to_owned();
+        let timeout = notifier.config.timeout;
+        let target = target.to_owned();
+        let notification = notification.to_owned();
+
+        tokio::spawn(async move {
+            if let Err(err) = send(&client, &target, &notification, timeout).await {
+                warn!(
+                    event = notification.event.to_string(),
+                    kind = &notification.kind,
+                    namespace = &notification.namespace,
+                    name = &notification.name,
+                    error = err.to_string(),
+                    "Could not send notification",
+                );
+            }
+        });
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client, target)))]
+/// post the notification to the target, using the payload expected by its
+/// kind
+pub async fn send(
+    client: &hyper::Client<Connector>,
+    target: &NotificationTarget,
+    notification: &Notification,
+    timeout: u64,
+) -> Result<(), Error> {
+    let text = notification.render(&target.template);
+    let body = match target.kind {
+        NotificationKind::Slack | NotificationKind::Mattermost => {
+            serde_json::to_vec(&serde_json::json!({ "text": text }))
+        }
+        NotificationKind::Webhook => serde_json::to_vec(&Payload {
+            notification: notification.to_owned(),
+            text,
+        }),
+    }
+    .map_err(Error::Serialize)?;
+
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(&target.url)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .map_err(Error::Request)?;
+
+    debug!(
+        event = notification.event.to_string(),
+        kind = &notification.kind,
+        namespace = &notification.namespace,
+        name = &notification.name,
+        "execute a request to send a notification"
+    );
+
+    let res = tokio::time::timeout(Duration::from_secs(timeout), client.request(req))
+        .await
+        .map_err(|_| Error::Timeout(timeout))?
+        .map_err(Error::Execute)?;
+
+    match res.status() {
+        status if status.is_success() => Ok(()),
+        status => Err(Error::StatusCode(status)),
+    }
+}
@@ -0,0 +1,54 @@
+//! # Redact module
+//!
+//! This module provide a wrapper around sensitive values, so they are never
+//! written in logs, traces or events through their [`Debug`] or [`Display`]
+//! implementations.
+
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    ops::Deref,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const REDACTED: &str = "<redacted>";
+
+// -----------------------------------------------------------------------------
+// Redacted structure
+
+#[derive(PartialEq, Eq, Clone, Default)]
+pub struct Redacted<T>(pub T);
+
+impl<T> Debug for Redacted<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl<T> Display for Redacted<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl<T> Deref for Redacted<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Redacted<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> Redacted<T> {
+    /// returns the wrapped sensitive value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
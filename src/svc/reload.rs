@@ -0,0 +1,292 @@
+//! # Reload module
+//!
+//! This module provide a background task which rebuilds the Clever Cloud
+//! client shared by reconcilers, once the api connection, credentials or proxy
+//! have changed. The configuration is periodically loaded again, e.g. after
+//! the secret mounted as configuration file is rotated, and the secret holding
+//! the credentials, if any, is watched. Other settings are only applied when
+//! the operator restarts.
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use futures::{future::pending, stream, StreamExt};
+use k8s_openapi::api::core::v1::Secret;
+use kube::runtime::{watcher, WatchStreamExt};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_counter_vec, CounterVec};
+use tracing::{debug, info, warn};
+
+use crate::svc::{
+    cfg::{self, Api, Configuration},
+    clevercloud,
+    k8s::{self, resource, Context},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const KEY_ENDPOINT: &str = "endpoint";
+pub const KEY_TOKEN: &str = "token";
+pub const KEY_SECRET: &str = "secret";
+pub const KEY_CONSUMER_KEY: &str = "consumerKey";
+pub const KEY_CONSUMER_SECRET: &str = "consumerSecret";
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static CONFIGURATION_RELOAD: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "kubernetes_operator_configuration_reload",
+            "number of loads of the configuration which changed the clever cloud client",
+        ),
+        &["result"]
+    )
+    .expect("metrics 'kubernetes_operator_configuration_reload' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to load configuration, {0}")]
+    Configuration(cfg::Error),
+    #[error("failed to create clever cloud client, {0}")]
+    CleverClient(clevercloud::client::Error),
+    #[error("failed to parse credentials secret '{0}', expected 'namespace/name'")]
+    Reference(String),
+    #[error("failed to retrieve credentials secret '{0}/{1}', {2}")]
+    Get(String, String, kube::Error),
+    #[error("failed to find credentials secret '{0}/{1}'")]
+    NotFound(String, String),
+    #[error("failed to find key '{0}' in credentials secret '{1}/{2}'")]
+    SecretKey(&'static str, String, String),
+    #[error("failed to decode key '{0}' in credentials secret '{1}/{2}', {3}")]
+    Utf8(&'static str, String, String, std::string::FromUtf8Error),
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the configuration at the given path, or the one from environment
+/// and defaults locations, if none is given
+pub fn load(path: &Option<PathBuf>) -> Result<Configuration, Error> {
+    match path {
+        Some(path) => Configuration::try_from(path.to_owned()),
+        None => Configuration::try_default(),
+    }
+    .map_err(Error::Configuration)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the namespace and name of the given secret reference, formatted
+/// as `namespace/name`
+pub fn reference(value: &str) -> Result<(String, String), Error> {
+    match value.split_once('/') {
+        Some((namespace, name))
+            if !namespace.is_empty() && !name.is_empty() && !name.contains('/') =>
+        {
+            Ok((namespace.to_string(), name.to_string()))
+        }
+        _ => Err(Error::Reference(value.to_string())),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(secret)))]
+/// returns the given api configuration with the credentials of the secret,
+/// the endpoint is only overridden if the secret holds one
+pub fn overlay(api: &Api, secret: &Secret) -> Result<Api, Error> {
+    let (namespace, name) = resource::namespaced_name(secret);
+    let value = |key: &'static str| -> Result<Option<String>, Error> {
+        secret
+            .data
+            .as_ref()
+            .and_then(|data| data.get(key))
+            .map(|bytes| {
+                String::from_utf8(bytes.0.to_owned())
+                    .map_err(|err| Error::Utf8(key, namespace.to_owned(), name.to_owned(), err))
+            })
+            .transpose()
+    };
+
+    let required = |key: &'static str| -> Result<String, Error> {
+        value(key)?.ok_or_else(|| Error::SecretKey(key, namespace.to_owned(), name.to_owned()))
+    };
+
+    Ok(Api {
+        endpoint: value(KEY_ENDPOINT)?.unwrap_or_else(|| api.endpoint.to_owned()),
+        token: required(KEY_TOKEN)?,
+        secret: required(KEY_SECRET)?,
+        consumer_key: required(KEY_CONSUMER_KEY)?,
+        consumer_secret: required(KEY_CONSUMER_SECRET)?,
+        ..api.to_owned()
+    })
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(kube)))]
+/// returns the given api configuration with the credentials of the secret
+/// referenced by the configuration, if any
+pub async fn credentials(
+    kube: kube::Client,
+    reference: &Option<String>,
+    api: &Api,
+) -> Result<Api, Error> {
+    let (namespace, name) = match reference {
+        Some(value) => self::reference(value)?,
+        None => return Ok(api.to_owned()),
+    };
+
+    let secret: Secret = resource::get(kube, &namespace, &name)
+        .await
+        .map_err(|err| Error::Get(namespace.to_owned(), name.to_owned(), err))?
+        .ok_or_else(|| Error::NotFound(namespace.to_owned(), name.to_owned()))?;
+
+    overlay(api, &secret)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(ctx)))]
+/// rebuild the clever cloud client of the context from the configuration
+/// loaded again at a regular interval, if the reload is enabled, and from the
+/// watched credentials secret, if any. The client is only swapped once the
+/// api or proxy configuration has changed. This function never returns
+pub async fn watch(ctx: Arc<Context>, path: Option<PathBuf>) {
+    let reload = &ctx.config.operator.reload;
+    let reference = match ctx
+        .config
+        .operator
+        .credentials_secret
+        .as_deref()
+        .map(reference)
+    {
+        Some(Ok(reference)) => Some(reference),
+        Some(Err(err)) => {
+            warn!(
+                error = err.to_string(),
+                "Could not watch credentials secret"
+            );
+            None
+        }
+        None => None,
+    };
+
+    if !reload.enabled && reference.is_none() {
+        debug!("Reload of the configuration is disabled");
+        return pending().await;
+    }
+
+    let mut secrets = match &reference {
+        Some((namespace, name)) => {
+            info!(
+                namespace = namespace,
+                name = name,
+                "Start to watch credentials secret"
+            );
+
+            watcher(
+                k8s::api::<Secret>(ctx.kube.to_owned(), Some(namespace)),
+                watcher::Config::default().fields(&format!("metadata.name={}", name)),
+            )
+            .default_backoff()
+            .boxed()
+        }
+        None => stream::pending().boxed(),
+    };
+
+    // the configuration loaded at startup has been applied, with the
+    // credentials of the secret, if any
+    let mut config = (*ctx.config).to_owned();
+    let mut secret: Option<Secret> = match &reference {
+        Some((namespace, name)) => resource::get(ctx.kube.to_owned(), namespace, name)
+            .await
+            .unwrap_or_default(),
+        None => None,
+    };
+    let mut applied = (ctx.config.api.to_owned(), ctx.config.proxy.to_owned());
+
+    let mut interval = tokio::time::interval(Duration::from_secs(reload.interval.max(1)));
+
+    // the first tick completes immediately, the configuration is already loaded
+    interval.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = interval.tick(), if reload.enabled => {
+                match load(&path) {
+                    Ok(loaded) => config = loaded,
+                    Err(err) => {
+                        warn!(
+                            error = err.to_string(),
+                            "Could not reload configuration, keep the current clever cloud client",
+                        );
+
+                        #[cfg(feature = "metrics")]
+                        CONFIGURATION_RELOAD.with_label_values(&["failure"]).inc();
+                        continue;
+                    }
+                }
+            }
+            Some(event) = secrets.next() => {
+                match event {
+                    Ok(watcher::Event::Applied(obj)) => secret = Some(obj),
+                    Ok(watcher::Event::Restarted(objs)) => match objs.into_iter().last() {
+                        Some(obj) => secret = Some(obj),
+                        None => continue,
+                    },
+                    Ok(watcher::Event::Deleted(_)) => {
+                        warn!("Credentials secret has been deleted, keep the current clever cloud client");
+                        continue;
+                    }
+                    Err(err) => {
+                        warn!(error = err.to_string(), "Failed to watch credentials secret");
+                        continue;
+                    }
+                }
+            }
+        }
+
+        let result = match &secret {
+            Some(secret) => overlay(&config.api, secret),
+            None => Ok(config.api.to_owned()),
+        }
+        .and_then(|api| {
+            if api == applied.0 && config.proxy == applied.1 {
+                return Ok(None);
+            }
+
+            let client =
+                clevercloud::client::try_new(&api, &config.proxy).map_err(Error::CleverClient)?;
+
+            Ok(Some((client, api)))
+        });
+
+        match result {
+            Ok(None) => {}
+            Ok(Some((client, api))) => {
+                info!(
+                    endpoint = &api.endpoint,
+                    "Configuration of the clever cloud client changed, use the new one",
+                );
+
+                ctx.apis.set(client);
+                applied = (api, config.proxy.to_owned());
+
+                #[cfg(feature = "metrics")]
+                CONFIGURATION_RELOAD.with_label_values(&["success"]).inc();
+            }
+            Err(err) => {
+                warn!(
+                    error = err.to_string(),
+                    "Could not rebuild the clever cloud client, keep the current one",
+                );
+
+                #[cfg(feature = "metrics")]
+                CONFIGURATION_RELOAD.with_label_values(&["failure"]).inc();
+            }
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! # Shutdown module
+//!
+//! This module provide the readiness state of the operator and the ordered
+//! phases executed when the operator receives a termination signal, so the
+//! http server stops advertising readiness before controllers stop and is
+//! itself stopped last.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    future::Future,
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use futures::future::join_all;
+use tokio::{
+    sync::watch,
+    task::JoinHandle,
+    time::{timeout, Instant},
+};
+use tracing::{info, warn};
+
+use crate::svc::k8s::lease::Elector;
+
+// -----------------------------------------------------------------------------
+// State
+
+static READY: AtomicBool = AtomicBool::new(false);
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the operator is ready to handle work
+pub fn ready() -> bool {
+    READY.load(Ordering::SeqCst)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// set the readiness of the operator
+pub fn set_ready(ready: bool) {
+    READY.store(ready, Ordering::SeqCst);
+}
+
+// -----------------------------------------------------------------------------
+// Phase enumeration
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub enum Phase {
+    Unready,
+    Drain,
+    ReleaseLease,
+    StopServer,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Unready => write!(f, "Unready"),
+            Self::Drain => write!(f, "Drain"),
+            Self::ReleaseLease => write!(f, "ReleaseLease"),
+            Self::StopServer => write!(f, "StopServer"),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Shutdown structure
+
+/// holds triggers given to controllers and the http server and executes the
+/// shutdown phases in order
+#[derive(Debug)]
+pub struct Shutdown {
+    grace_period: Duration,
+    controllers: watch::Sender<bool>,
+    server: watch::Sender<bool>,
+}
+
+impl Shutdown {
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    pub fn new(grace_period: Duration) -> Self {
+        let (controllers, _) = watch::channel(false);
+        let (server, _) = watch::channel(false);
+
+        Self {
+            grace_period,
+            controllers,
+            server,
+        }
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns a future resolved once controllers have to stop accepting new
+    /// reconciliation
+    pub fn controllers(&self) -> impl Future<Output = ()> + Send + Sync + 'static {
+        triggered(self.controllers.subscribe())
+    }
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    /// returns a future resolved once the http server has to stop
+    pub fn server(&self) -> impl Future<Output = ()> + Send + Sync + 'static {
+        triggered(self.server.subscribe())
+    }
+
+    #[cfg_attr(
+        feature = "trace",
+        tracing::instrument(skip(controllers, server, elector))
+    )]
+    /// execute shutdown phases in order:
+    /// 1. flip readiness, so the http server answers 503 on '/readyz'
+    /// 2. stop controllers and wait for in-flight reconciliation up to the
+    ///    grace period
+    /// 3. release the leader lease, if any
+    /// 4. stop the http server
+    pub async fn run<C, S>(
+        &self,
+        mut controllers: Vec<JoinHandle<C>>,
+        server: JoinHandle<S>,
+        elector: Option<&Elector>,
+    ) {
+        let begin = Instant::now();
+
+        phase(Phase::Unready, async {
+            set_ready(false);
+        })
+        .await;
+
+        phase(Phase::Drain, async {
+            self.controllers.send_replace(true);
+            if timeout(self.grace_period, join_all(controllers.iter_mut()))
+                .await
+                .is_err()
+            {
+                warn!(
+                    grace_period = self.grace_period.as_secs(),
+                    "Controllers did not stop within the grace period, abort in-flight reconciliation"
+                );
+
+                for controller in &controllers {
+                    controller.abort();
+                }
+            }
+        })
+        .await;
+
+        phase(Phase::ReleaseLease, async {
+            match elector {
+                Some(elector) => {
+                    if let Err(err) = elector.release().await {
+                        warn!(
+                            error = err.to_string(),
+                            "Could not release the leader lease"
+                        );
+                    }
+                }
+                None => info!("There is no leader lease to release"),
+            }
+        })
+        .await;
+
+        phase(Phase::StopServer, async {
+            self.server.send_replace(true);
+            if let Err(err) = server.await {
+                warn!(error = err.to_string(), "Could not stop the http server");
+            }
+        })
+        .await;
+
+        info!(
+            duration = Instant::now().duration_since(begin).as_millis(),
+            "Shutdown sequence completed"
+        );
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+/// returns a future resolved once the given channel is set to true or closed
+async fn triggered(mut rx: watch::Receiver<bool>) {
+    let _ = rx.wait_for(|triggered| *triggered).await;
+}
+
+/// execute the given phase and log its duration
+async fn phase<F>(phase: Phase, fut: F)
+where
+    F: Future<Output = ()>,
+{
+    let begin = Instant::now();
+
+    info!(phase = phase.to_string(), "Enter shutdown phase");
+    fut.await;
+    info!(
+        phase = phase.to_string(),
+        duration = Instant::now().duration_since(begin).as_millis(),
+        "Leave shutdown phase"
+    );
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns once the process received an interrupt or a termination signal
+pub async fn signal() -> Result<(), io::Error> {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+
+        tokio::select! {
+            r = tokio::signal::ctrl_c() => r,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use tokio::{
+        sync::oneshot,
+        time::{timeout, Instant},
+    };
+
+    use super::{ready, set_ready, Shutdown};
+
+    #[tokio::test(start_paused = true)]
+    async fn abort_controllers_after_the_grace_period() {
+        let shutdown = Shutdown::new(Duration::from_secs(30));
+
+        // the controller ignores the trigger, its reconciliation never ends
+        let (tx, rx) = oneshot::channel::<()>();
+        let controller = tokio::spawn(async move {
+            let _tx = tx;
+            std::future::pending::<()>().await;
+        });
+
+        let stopped = shutdown.server();
+        let server = tokio::spawn(stopped);
+
+        let begin = Instant::now();
+        shutdown.run(vec![controller], server, None).await;
+
+        assert!(Instant::now().duration_since(begin) >= Duration::from_secs(30));
+        assert!(
+            matches!(timeout(Duration::from_secs(1), rx).await, Ok(Err(_))),
+            "the controller has been aborted"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_for_controllers_within_the_grace_period() {
+        let shutdown = Shutdown::new(Duration::from_secs(30));
+
+        let triggered = shutdown.controllers();
+        let controller = tokio::spawn(async move {
+            triggered.await;
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            "drained"
+        });
+
+        let stopped = shutdown.server();
+        let server = tokio::spawn(stopped);
+
+        let begin = Instant::now();
+        shutdown.run(vec![controller], server, None).await;
+
+        let elapsed = Instant::now().duration_since(begin);
+        assert!(elapsed >= Duration::from_secs(5));
+        assert!(elapsed < Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn flip_readiness_before_controllers_are_triggered() {
+        set_ready(true);
+        let shutdown = Shutdown::new(Duration::from_secs(5));
+
+        let ready_when_triggered = Arc::new(AtomicBool::new(true));
+        let observed = ready_when_triggered.to_owned();
+        let triggered = shutdown.controllers();
+        let controller = tokio::spawn(async move {
+            triggered.await;
+            observed.store(ready(), Ordering::SeqCst);
+        });
+
+        let server_ready_when_stopped = Arc::new(AtomicBool::new(true));
+        let observed = server_ready_when_stopped.to_owned();
+        let stopped = shutdown.server();
+        let server = tokio::spawn(async move {
+            stopped.await;
+            observed.store(ready(), Ordering::SeqCst);
+        });
+
+        shutdown.run(vec![controller], server, None).await;
+
+        assert!(!ready_when_triggered.load(Ordering::SeqCst));
+        assert!(!server_ready_when_stopped.load(Ordering::SeqCst));
+        assert!(!ready());
+    }
+}
@@ -0,0 +1,141 @@
+//! # Supervisor module
+//!
+//! This module provide a supervision layer for controllers. A controller which
+//! fails or panics is restarted after an exponential backoff, instead of
+//! stopping the whole operator. The operator only stops once a controller has
+//! been restarted too many times within the supervision window.
+
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::FutureExt;
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_counter_vec, CounterVec};
+use tokio::{task::JoinError, time::sleep};
+use tracing::{error, info, warn};
+
+use crate::svc::{cfg::Configuration, health, k8s::backoff, tracker};
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static CONTROLLER_RESTARTS: Lazy<CounterVec> = Lazy::new(|| {
+    register_counter_vec!(
+        opts!(
+            "kubernetes_operator_controller_restarts_total",
+            "number of restarts of failed controllers",
+        ),
+        &["kind", "reason"]
+    )
+    .expect("metrics 'kubernetes_operator_controller_restarts_total' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("controller of {0} failed {1} times within {2}s, last failure: {3}")]
+    Exhausted(String, usize, u64, String),
+    #[error("failed to join controller of {0}, {1}")]
+    Join(String, JoinError),
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(config, signal, factory)))]
+/// run the controller created by the factory until the given signal is
+/// resolved, the controller is restarted with a backoff each time it fails
+/// or panics
+pub async fn supervise<S, F, Fut, E>(
+    kind: &str,
+    config: Arc<Configuration>,
+    signal: S,
+    factory: F,
+) -> Result<(), Error>
+where
+    S: Future<Output = ()> + Clone + Send + 'static,
+    F: Fn(S) -> Fut,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Display + Send + 'static,
+{
+    let key = format!("controller/{}", kind);
+    let supervision = &config.operator.supervision;
+    let window = Duration::from_secs(supervision.window);
+    let mut failures: VecDeque<Instant> = VecDeque::new();
+
+    loop {
+        let (reason, failure) = match tokio::spawn(factory(signal.to_owned())).await {
+            Ok(Ok(())) => {
+                backoff::reset(&key);
+                return Ok(());
+            }
+            Ok(Err(err)) => ("error", err.to_string()),
+            Err(err) if err.is_panic() => ("panic", "controller has panicked".to_string()),
+            Err(err) => return Err(Error::Join(kind.to_string(), err)),
+        };
+
+        // the controller may fail while the operator is shutting down
+        if signal.to_owned().now_or_never().is_some() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        failures.push_back(now);
+        while failures
+            .front()
+            .map(|instant| now.duration_since(*instant) > window)
+            .unwrap_or_default()
+        {
+            failures.pop_front();
+        }
+
+        #[cfg(feature = "metrics")]
+        CONTROLLER_RESTARTS.with_label_values(&[kind, reason]).inc();
+
+        if failures.len() > supervision.max_restarts {
+            error!(
+                kind = kind,
+                failures = failures.len(),
+                error = &failure,
+                "Controller failed too many times, stop the operator",
+            );
+
+            return Err(Error::Exhausted(
+                kind.to_string(),
+                failures.len(),
+                supervision.window,
+                failure,
+            ));
+        }
+
+        health::restarting(kind);
+
+        let delay = backoff::next(&key, &config.operator.retry);
+        warn!(
+            kind = kind,
+            reason = reason,
+            error = &failure,
+            delay = delay.as_millis(),
+            "Controller failed, restart it after a delay",
+        );
+
+        tracker::restart(kind, &failure);
+
+        tokio::select! {
+            _ = sleep(delay) => {}
+            _ = signal.to_owned() => return Ok(()),
+        }
+
+        info!(kind = kind, "Restart controller");
+    }
+}
@@ -3,7 +3,11 @@
 //! This module expose telemetry measurements mainly metrics and tracing through
 //! structures, enums and helpers
 
-use std::{collections::BTreeMap, time::Instant};
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use hyper::{
     header::{self, HeaderValue},
@@ -15,8 +19,27 @@ use once_cell::sync::Lazy;
 use prometheus::{opts, register_counter_vec, CounterVec};
 use tracing::info;
 
+use serde::{Deserialize, Serialize};
+
+use crate::svc::{
+    cfg::Configuration,
+    clevercloud::{
+        cache,
+        client::{ScopeError, ScopedClient},
+    },
+    health,
+    k8s::{
+        registry,
+        skip::{self, Skip},
+        Context,
+    },
+    update,
+};
+
 #[cfg(feature = "metrics")]
 pub mod metrics;
+#[cfg(feature = "debug")]
+pub mod profiling;
 
 // -----------------------------------------------------------------------------
 // Telemetry
@@ -67,21 +90,59 @@ pub enum Error {
     Metrics(metrics::Error),
     #[error("failed to serialize payload, {0}")]
     Serialize(serde_json::Error),
+    #[error("{0}")]
+    Scope(ScopeError),
+    #[error("failed to retrieve catalog, {0}")]
+    Catalog(cache::Error),
+    #[cfg(feature = "debug")]
+    #[error("{0}")]
+    Profiling(profiling::Error),
+}
+
+// -----------------------------------------------------------------------------
+// State structure
+
+/// debug state of the operator, exposed to help understanding its decisions
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct State {
+    #[serde(rename = "ready")]
+    pub ready: bool,
+    #[serde(rename = "version")]
+    pub version: update::Status,
+    #[serde(rename = "skipped")]
+    pub skipped: Vec<Skip>,
 }
 
 // -----------------------------------------------------------------------------
 // Helper methods
 
-#[cfg_attr(feature = "trace", tracing::instrument)]
-pub async fn router(req: Request<Body>) -> Result<Response<Body>, Error> {
+#[cfg_attr(feature = "trace", tracing::instrument(skip(ctx, req)))]
+pub async fn router(ctx: Arc<Context>, req: Request<Body>) -> Result<Response<Body>, Error> {
     let begin = Instant::now();
 
     // -------------------------------------------------------------------------
     // Basic routing
     let result = match (req.method(), req.uri().path()) {
-        (&Method::GET, "/healthz") => healthz(&req).await,
+        (&Method::GET, "/livez") => livez(&ctx.config, &req).await,
+        (&Method::GET, "/readyz") => readyz(&req).await,
+        (&Method::GET, "/version") => version(&req).await,
+        (&Method::GET, "/debug/state") => state(&req).await,
+        (&Method::GET, "/v1/catalog") => catalog(&ctx, &req).await,
+        (&Method::GET, "/v1/resources") if ctx.config.operator.management.enabled => {
+            resources(&ctx.config, &req).await
+        }
         #[cfg(feature = "metrics")]
         (&Method::GET, "/metrics") => metrics::handler(&req).await.map_err(Error::Metrics),
+        #[cfg(feature = "debug")]
+        (&Method::GET, "/debug/pprof/profile") if ctx.config.operator.pprof.enabled => {
+            profiling::profile(&ctx.config.operator.pprof, &req)
+                .await
+                .map_err(Error::Profiling)
+        }
+        #[cfg(feature = "debug")]
+        (&Method::GET, "/debug/pprof/heap") if ctx.config.operator.pprof.enabled => {
+            profiling::heap(&req).await.map_err(Error::Profiling)
+        }
         _ => not_found(&req).await,
     };
 
@@ -186,15 +247,224 @@ pub async fn router(req: Request<Body>) -> Result<Response<Body>, Error> {
     }
 }
 
+#[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+/// returns 200 while no controller has panicked, stopped unexpectedly or
+/// stalled, 503 otherwise
+pub async fn livez(config: &Configuration, _req: &Request<Body>) -> Result<Response<Body>, Error> {
+    let stall_timeout = Duration::from_secs(config.operator.health.stall_timeout);
+
+    report(health::liveness(stall_timeout))
+}
+
 #[cfg_attr(feature = "trace", tracing::instrument)]
-pub async fn healthz(_req: &Request<Body>) -> Result<Response<Body>, Error> {
+/// returns 200 once every watcher has synced and the clever cloud api is
+/// reachable, 503 otherwise
+pub async fn readyz(_req: &Request<Body>) -> Result<Response<Body>, Error> {
+    report(health::readiness())
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+pub fn report(report: health::Report) -> Result<Response<Body>, Error> {
+    let mut res = Response::default();
+
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    *res.status_mut() = if report.ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    *res.body_mut() = Body::from(serde_json::to_string_pretty(&report).map_err(Error::Serialize)?);
+
+    Ok(res)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+pub async fn version(_req: &Request<Body>) -> Result<Response<Body>, Error> {
+    let mut res = Response::default();
+
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    *res.status_mut() = StatusCode::OK;
+    *res.body_mut() =
+        Body::from(serde_json::to_string_pretty(&update::status()).map_err(Error::Serialize)?);
+
+    Ok(res)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+pub async fn state(_req: &Request<Body>) -> Result<Response<Body>, Error> {
+    let state = State {
+        ready: health::readiness().ok,
+        version: update::status(),
+        skipped: skip::recent(),
+    };
+
+    let mut res = Response::default();
+
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    *res.status_mut() = StatusCode::OK;
+    *res.body_mut() = Body::from(serde_json::to_string_pretty(&state).map_err(Error::Serialize)?);
+
+    Ok(res)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(ctx, req)))]
+/// returns the addon providers, plans and regions available to the
+/// organisation given by the `organisation` query parameter, or to the default
+/// organisation of the admission webhook
+pub async fn catalog(ctx: &Context, req: &Request<Body>) -> Result<Response<Body>, Error> {
+    let organisation = req
+        .uri()
+        .query()
+        .and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("organisation="))
+                .map(String::from)
+        })
+        .or_else(|| ctx.config.operator.webhook.default_organisation.to_owned());
+
     let mut res = Response::default();
 
-    *res.status_mut() = StatusCode::NO_CONTENT;
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    let organisation = match organisation {
+        Some(organisation) if !organisation.is_empty() => organisation,
+        _ => {
+            let map = BTreeMap::from([(
+                "error".to_string(),
+                "missing 'organisation' query parameter".to_string(),
+            )]);
+
+            *res.status_mut() = StatusCode::BAD_REQUEST;
+            *res.body_mut() =
+                Body::from(serde_json::to_string_pretty(&map).map_err(Error::Serialize)?);
+
+            return Ok(res);
+        }
+    };
+
+    let apis = ScopedClient::unscoped(ctx.apis.get())
+        .with_rate_limit(&organisation, &ctx.config.api.rate_limit);
+
+    let catalog = ctx
+        .plans
+        .catalog(
+            apis.authorize("Catalog", "plan::list")
+                .await
+                .map_err(Error::Scope)?,
+            &organisation,
+        )
+        .await
+        .map_err(Error::Catalog)?;
+
+    *res.status_mut() = StatusCode::OK;
+    *res.body_mut() = Body::from(serde_json::to_string_pretty(&catalog).map_err(Error::Serialize)?);
 
     Ok(res)
 }
 
+#[cfg_attr(feature = "trace", tracing::instrument(skip(config, req)))]
+/// returns the custom resources tracked by the operator and the outcome of
+/// their last reconciliation, filtered by the `kind`, `namespace` and
+/// `status` query parameters, if any. Requests have to provide the token of
+/// the management api as a bearer token
+pub async fn resources(
+    config: &Configuration,
+    req: &Request<Body>,
+) -> Result<Response<Body>, Error> {
+    let mut res = Response::default();
+
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    let authorized = match (
+        config.operator.management.token.as_deref(),
+        req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer ")),
+    ) {
+        (Some(expected), Some(token)) if !expected.is_empty() => {
+            constant_time_eq(expected.as_bytes(), token.trim().as_bytes())
+        }
+        _ => false,
+    };
+
+    if !authorized {
+        let map = BTreeMap::from([(
+            "error".to_string(),
+            "missing or invalid bearer token".to_string(),
+        )]);
+
+        res.headers_mut()
+            .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        *res.status_mut() = StatusCode::UNAUTHORIZED;
+        *res.body_mut() = Body::from(serde_json::to_string_pretty(&map).map_err(Error::Serialize)?);
+
+        return Ok(res);
+    }
+
+    let kind = query(req, "kind");
+    let namespace = query(req, "namespace");
+    let status = query(req, "status");
+
+    let entries: Vec<registry::Entry> = registry::list()
+        .into_iter()
+        .filter(|entry| kind.as_deref().map_or(true, |kind| kind == entry.kind))
+        .filter(|entry| {
+            namespace
+                .as_deref()
+                .map_or(true, |namespace| namespace == entry.namespace)
+        })
+        .filter(|entry| {
+            status
+                .as_deref()
+                .map_or(true, |status| status == entry.status.to_string())
+        })
+        .collect();
+
+    *res.status_mut() = StatusCode::OK;
+    *res.body_mut() = Body::from(serde_json::to_string_pretty(&entries).map_err(Error::Serialize)?);
+
+    Ok(res)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the value of the given query parameter, if any
+fn query(req: &Request<Body>, key: &str) -> Option<String> {
+    req.uri().query().and_then(|query| {
+        query.split('&').find_map(|pair| {
+            pair.split_once('=')
+                .filter(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        })
+    })
+}
+
+/// returns if both slices are equal, in a time which does not depend on the
+/// position of the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 #[cfg_attr(feature = "trace", tracing::instrument)]
 pub async fn not_found(_req: &Request<Body>) -> Result<Response<Body>, Error> {
     let mut res = Response::default();
@@ -0,0 +1,151 @@
+//! # Profiling module
+//!
+//! This module expose pprof-style profiling endpoints, which sample the cpu
+//! of the running operator or dump the profile of its heap. The heap is
+//! sampled by jemalloc, which is the global allocator once the operator is
+//! built with the `debug` feature. Sampling is only active once the endpoints
+//! are enabled in the configuration.
+
+use std::{ffi::CString, os::unix::ffi::OsStrExt, time::Duration};
+
+use hyper::{
+    header::{self, HeaderValue},
+    Body, Request, Response, StatusCode,
+};
+use pprof::{protos::Message, ProfilerGuardBuilder};
+use tempfile::NamedTempFile;
+use tikv_jemalloc_ctl::raw;
+use tokio::task::spawn_blocking as blocking;
+use tracing::{info, warn};
+
+use crate::svc::cfg::Pprof;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// duration of a cpu profile, if the `seconds` query parameter is not given
+pub const PROFILE_DURATION: u64 = 30;
+/// number of samples of the cpu per second
+pub const PROFILE_FREQUENCY: i32 = 99;
+/// libraries whose frames are not sampled, they could deadlock the profiler
+pub const PROFILE_BLOCKLIST: [&str; 4] = ["libc", "libgcc", "pthread", "vdso"];
+
+// -----------------------------------------------------------------------------
+// Error enum
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to profile cpu, {0}")]
+    Profiler(pprof::Error),
+    #[error("failed to encode cpu profile, {0}")]
+    Encode(String),
+    #[error("failed to spawn blocking task, {0}")]
+    Join(tokio::task::JoinError),
+    #[error("failed to dump heap profile, heap profiling is not enabled")]
+    HeapDisabled,
+    #[error("failed to dump heap profile, {0}")]
+    Dump(tikv_jemalloc_ctl::Error),
+    #[error("failed to read heap profile, {0}")]
+    Io(std::io::Error),
+}
+
+// -----------------------------------------------------------------------------
+// Helper methods
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// activate the sampling of the heap, if the profiling endpoints are enabled
+pub fn configure(config: &Pprof) {
+    if !config.enabled {
+        return;
+    }
+
+    // SAFETY: `prof.active` is a boolean option of jemalloc
+    match unsafe { raw::write(b"prof.active\0", true) } {
+        Ok(()) => info!("Serve profiling endpoints and sample the heap"),
+        Err(err) => warn!(
+            error = err.to_string(),
+            "Could not activate the sampling of the heap, heap profiles will be empty"
+        ),
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(req)))]
+/// returns the cpu profile of the operator, in the pprof format, sampled
+/// during the number of seconds given by the `seconds` query parameter, up to
+/// the configured maximum duration
+pub async fn profile(config: &Pprof, req: &Request<Body>) -> Result<Response<Body>, Error> {
+    let seconds = req
+        .uri()
+        .query()
+        .and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("seconds="))
+                .and_then(|seconds| seconds.parse().ok())
+        })
+        .unwrap_or(PROFILE_DURATION)
+        .clamp(1, config.max_duration.max(1));
+
+    let profile = blocking(move || {
+        let guard = ProfilerGuardBuilder::default()
+            .frequency(PROFILE_FREQUENCY)
+            .blocklist(&PROFILE_BLOCKLIST)
+            .build()
+            .map_err(Error::Profiler)?;
+
+        std::thread::sleep(Duration::from_secs(seconds));
+
+        guard
+            .report()
+            .build()
+            .map_err(Error::Profiler)?
+            .pprof()
+            .map_err(Error::Profiler)
+    })
+    .await
+    .map_err(Error::Join)??;
+
+    let buf = profile
+        .write_to_bytes()
+        .map_err(|err| Error::Encode(err.to_string()))?;
+
+    Ok(octet_stream(buf))
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(_req)))]
+/// returns the heap profile of the operator dumped by jemalloc, it could be
+/// read using `jeprof`
+pub async fn heap(_req: &Request<Body>) -> Result<Response<Body>, Error> {
+    // SAFETY: `opt.prof` is a boolean option of jemalloc
+    if !unsafe { raw::read::<bool>(b"opt.prof\0") }.map_err(Error::Dump)? {
+        return Err(Error::HeapDisabled);
+    }
+
+    // File will be automatically delete when it will be drop
+    let file = NamedTempFile::new().map_err(Error::Io)?;
+    let path = CString::new(file.path().as_os_str().as_bytes())
+        .expect("path of temporary file to not contain a nul byte");
+
+    // SAFETY: `prof.dump` expects a nul terminated path, which outlives the
+    // call
+    unsafe { raw::write(b"prof.dump\0", path.as_ptr()) }.map_err(Error::Dump)?;
+
+    let buf = tokio::fs::read(file.path()).await.map_err(Error::Io)?;
+
+    Ok(octet_stream(buf))
+}
+
+/// returns a response holding the given profile
+fn octet_stream(buf: Vec<u8>) -> Response<Body> {
+    let mut res = Response::default();
+
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/octet-stream"),
+    );
+
+    *res.status_mut() = StatusCode::OK;
+    *res.body_mut() = Body::from(buf);
+
+    res
+}
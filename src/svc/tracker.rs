@@ -0,0 +1,60 @@
+//! # Tracker module
+//!
+//! This module provide helpers to report errors of reconcilers and restarts of
+//! controllers to Sentry, tagged with the custom resource they relate to.
+//! Errors logged through `tracing` are turned into Sentry events by the
+//! `sentry-tracing` layer, helpers only enrich them. They do nothing, if the
+//! operator is built without the `tracker` feature.
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg(feature = "tracker")]
+#[cfg_attr(feature = "trace", tracing::instrument(skip(f)))]
+/// run the given function in a scope tagged with the kind, namespace and name
+/// of the custom resource, events reported by the function carry those tags
+pub fn resource<F, R>(kind: &str, namespace: &str, name: &str, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    sentry::with_scope(
+        |scope| {
+            scope.set_tag("kind", kind);
+            scope.set_tag("namespace", namespace);
+            scope.set_tag("name", name);
+        },
+        f,
+    )
+}
+
+#[cfg(not(feature = "tracker"))]
+#[cfg_attr(feature = "trace", tracing::instrument(skip(f)))]
+/// run the given function, the operator is built without the `tracker`
+/// feature
+pub fn resource<F, R>(_kind: &str, _namespace: &str, _name: &str, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    f()
+}
+
+#[cfg(feature = "tracker")]
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// report the restart of the controller of the given kind
+pub fn restart(kind: &str, failure: &str) {
+    sentry::with_scope(
+        |scope| scope.set_tag("kind", kind),
+        || {
+            sentry::capture_message(
+                &format!("Controller of kind '{}' restarted, {}", kind, failure),
+                sentry::Level::Warning,
+            )
+        },
+    );
+}
+
+#[cfg(not(feature = "tracker"))]
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// report the restart of the controller of the given kind, the operator is
+/// built without the `tracker` feature
+pub fn restart(_kind: &str, _failure: &str) {}
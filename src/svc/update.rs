@@ -0,0 +1,336 @@
+//! # Update module
+//!
+//! This module provide a background task which periodically compares the
+//! running version of the operator against the latest release
+
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+    num::ParseIntError,
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use clevercloud_sdk::oauth10a::{ClientError, RestClient};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use prometheus::{opts, register_int_gauge, IntGauge};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::svc::{
+    cfg::{Configuration, UpdateCheck},
+    clevercloud,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// -----------------------------------------------------------------------------
+// Telemetry
+
+#[cfg(feature = "metrics")]
+static UPDATE_AVAILABLE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(opts!(
+        "kubernetes_operator_update_available",
+        "whether a newer release of the operator is available",
+    ))
+    .expect("metrics 'kubernetes_operator_update_available' to not be already registered")
+});
+
+// -----------------------------------------------------------------------------
+// State
+
+static LATEST: RwLock<Option<String>> = RwLock::new(None);
+
+// -----------------------------------------------------------------------------
+// Error enumeration
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to retrieve version manifest from '{0}', {1}")]
+    Request(String, ClientError),
+    #[error("failed to retrieve version manifest from '{0}', timed out after {1}s")]
+    Timeout(String, u64),
+    #[error("failed to parse version '{0}', {1}")]
+    Version(String, String),
+}
+
+// -----------------------------------------------------------------------------
+// Manifest structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Manifest {
+    #[serde(rename = "version", alias = "tag_name")]
+    pub version: String,
+}
+
+// -----------------------------------------------------------------------------
+// Version structure
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    #[cfg_attr(feature = "trace", tracing::instrument)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |part: Option<&str>| -> Result<u64, Error> {
+            part.ok_or_else(|| Error::Version(s.to_string(), "missing component".into()))?
+                .parse()
+                .map_err(|err: ParseIntError| Error::Version(s.to_string(), err.to_string()))
+        };
+
+        // pre-release and build metadata are ignored
+        let core = s
+            .trim()
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default();
+
+        let mut parts = core.split('.');
+        let version = Self {
+            major: parse(parts.next())?,
+            minor: parse(parts.next())?,
+            patch: parse(parts.next())?,
+        };
+
+        if parts.next().is_some() {
+            return Err(Error::Version(s.to_string(), "too many components".into()));
+        }
+
+        Ok(version)
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Status structure
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Status {
+    #[serde(rename = "version")]
+    pub version: String,
+    #[serde(rename = "latest", skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+    #[serde(rename = "updateAvailable", skip_serializing_if = "Option::is_none")]
+    pub update_available: Option<bool>,
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns the running version and, if it has been checked, the latest release
+pub fn status() -> Status {
+    let latest = LATEST.read().ok().and_then(|latest| latest.to_owned());
+    let update_available = latest
+        .as_ref()
+        .and_then(|latest| is_newer(latest, VERSION).ok());
+
+    Status {
+        version: VERSION.to_string(),
+        latest,
+        update_available,
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// returns if the latest version is strictly greater than the current one
+pub fn is_newer(latest: &str, current: &str) -> Result<bool, Error> {
+    Ok(Version::from_str(latest)?.cmp(&Version::from_str(current)?) == Ordering::Greater)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// retrieve the version manifest and returns the latest version
+pub async fn fetch(
+    client: &clevercloud::client::Client,
+    config: &UpdateCheck,
+) -> Result<String, Error> {
+    let manifest: Manifest =
+        tokio::time::timeout(Duration::from_secs(config.timeout), client.get(&config.url))
+            .await
+            .map_err(|_| Error::Timeout(config.url.to_owned(), config.timeout))?
+            .map_err(|err| Error::Request(config.url.to_owned(), err))?;
+
+    // ensure that the manifest holds a well-formed version
+    Version::from_str(&manifest.version)?;
+
+    Ok(manifest.version)
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(client)))]
+/// retrieve the latest version and keep it, so it is reported by [`status`]
+pub async fn check(client: &clevercloud::client::Client, config: &UpdateCheck) {
+    match fetch(client, config).await {
+        Ok(latest) => {
+            if let Ok(mut state) = LATEST.write() {
+                *state = Some(latest);
+            }
+        }
+        Err(err) => {
+            warn!(
+                url = &config.url,
+                error = err.to_string(),
+                "Could not check for a newer release of the operator"
+            );
+        }
+    }
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument)]
+/// log the running version and, if it has been checked, the latest release
+pub fn heartbeat() -> Status {
+    let status = status();
+    let update_available = status.update_available.unwrap_or(false);
+
+    #[cfg(feature = "metrics")]
+    UPDATE_AVAILABLE.set(update_available as i64);
+
+    info!(
+        version = &status.version,
+        latest = status.latest.as_deref().unwrap_or("<unknown>"),
+        update_available = update_available,
+        "Heartbeat"
+    );
+
+    status
+}
+
+#[cfg_attr(feature = "trace", tracing::instrument(skip(config)))]
+/// periodically log a heartbeat holding the running version, this function
+/// never returns. If the update check is enabled, the latest release is
+/// retrieved before each heartbeat, otherwise no request is made
+pub async fn watch(config: Arc<Configuration>) {
+    let update_check = &config.operator.update_check;
+    let client = if update_check.enabled {
+        match clevercloud::client::connector(&config.api, &config.proxy) {
+            Ok(connector) => Some(clevercloud::client::Client::builder().build(connector)),
+            Err(err) => {
+                warn!(
+                    error = err.to_string(),
+                    "Could not create http client to check for updates"
+                );
+                None
+            }
+        }
+    } else {
+        debug!("Update check is disabled");
+        None
+    };
+
+    let mut interval = tokio::time::interval(Duration::from_secs(update_check.interval.max(1)));
+    loop {
+        interval.tick().await;
+
+        if let Some(client) = &client {
+            check(client, update_check).await;
+        }
+
+        heartbeat();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::{fetch, is_newer, Error, VERSION};
+    use crate::svc::{
+        cfg::{Api, UpdateCheck},
+        clevercloud::client::{connector, Client},
+    };
+
+    /// returns the client and the configuration to retrieve the manifest served
+    /// by the given server with the given body
+    async fn serve(server: &MockServer, body: ResponseTemplate) -> (Client, UpdateCheck) {
+        Mock::given(method("GET"))
+            .and(path("/releases/latest"))
+            .respond_with(body)
+            .mount(server)
+            .await;
+
+        let api: Api = serde_json::from_value(json!({
+            "endpoint": server.uri(),
+            "token": "",
+            "secret": "",
+            "consumerKey": "",
+            "consumerSecret": "",
+        }))
+        .expect("api configuration to be deserialized");
+
+        let client = Client::builder().build(connector(&api, &None).expect("connector"));
+        let config = UpdateCheck {
+            enabled: true,
+            url: format!("{}/releases/latest", server.uri()),
+            ..Default::default()
+        };
+
+        (client, config)
+    }
+
+    #[tokio::test]
+    async fn newer_release_is_available() {
+        let server = MockServer::start().await;
+        let body = ResponseTemplate::new(200).set_body_json(json!({ "tag_name": "v99.0.0" }));
+        let (client, config) = serve(&server, body).await;
+
+        let latest = fetch(&client, &config).await.expect("manifest");
+
+        assert_eq!("v99.0.0", latest);
+        assert!(is_newer(&latest, VERSION).expect("versions"));
+    }
+
+    #[tokio::test]
+    async fn same_release_is_not_newer() {
+        let server = MockServer::start().await;
+        let body = ResponseTemplate::new(200).set_body_json(json!({ "version": VERSION }));
+        let (client, config) = serve(&server, body).await;
+
+        let latest = fetch(&client, &config).await.expect("manifest");
+
+        assert_eq!(VERSION, latest);
+        assert!(!is_newer(&latest, VERSION).expect("versions"));
+    }
+
+    #[tokio::test]
+    async fn malformed_version_is_rejected() {
+        let server = MockServer::start().await;
+        let body = ResponseTemplate::new(200).set_body_json(json!({ "version": "latest" }));
+        let (client, config) = serve(&server, body).await;
+
+        let result = fetch(&client, &config).await;
+
+        assert!(matches!(result, Err(Error::Version(version, _)) if version == "latest"));
+    }
+
+    #[tokio::test]
+    async fn malformed_manifest_is_rejected() {
+        let server = MockServer::start().await;
+        let body = ResponseTemplate::new(200).set_body_string("<html></html>");
+        let (client, config) = serve(&server, body).await;
+
+        let result = fetch(&client, &config).await;
+
+        assert!(matches!(result, Err(Error::Request(..))));
+    }
+}
@@ -0,0 +1,96 @@
+//! # Addon module
+//!
+//! This module provide end-to-end tests of the reconciliation of the `Addon`
+//! custom resource.
+
+use k8s_openapi::api::core::v1::Secret;
+use kube::{
+    api::{ApiResource, DeleteParams, DynamicObject, GroupVersionKind, PostParams},
+    Api,
+};
+use serde_json::json;
+
+use crate::{
+    api::FakeApi,
+    cluster::{self, Namespace, Operator},
+};
+
+#[tokio::test]
+async fn addon_is_created_exposed_and_deleted() {
+    let api = FakeApi::start().await;
+    let client = cluster::client().await;
+    let namespace = Namespace::create(client.to_owned(), "addon").await;
+    let _operator = Operator::spawn(&api.endpoint(), &namespace.name);
+
+    let resource = ApiResource::from_gvk_with_plural(
+        &GroupVersionKind::gvk("api.clever-cloud.com", "v1", "Addon"),
+        "addons",
+    );
+
+    let addons: Api<DynamicObject> =
+        Api::namespaced_with(client.to_owned(), &namespace.name, &resource);
+    let secrets: Api<Secret> = Api::namespaced(client.to_owned(), &namespace.name);
+
+    let obj: DynamicObject = serde_json::from_value(json!({
+        "apiVersion": "api.clever-cloud.com/v1",
+        "kind": "Addon",
+        "metadata": { "name": "matomo" },
+        "spec": {
+            "organisation": "orga_e2e",
+            "providerId": "addon-matomo",
+            "instance": { "region": "par", "plan": "plan_e2e" },
+        },
+    }))
+    .expect("custom resource to be valid");
+
+    addons
+        .create(&PostParams::default(), &obj)
+        .await
+        .expect("custom resource to be created");
+
+    // -------------------------------------------------------------------------
+    // the addon is created on the api and its environment is exposed
+    let secret = cluster::eventually("secret to be generated", || async {
+        secrets.get_opt("matomo-secrets").await.ok().flatten()
+    })
+    .await;
+
+    let addons_on_api = api.addons.list();
+    assert_eq!(1, addons_on_api.len(), "one addon to be created on the api");
+
+    let id = addons_on_api[0]["id"].as_str().unwrap_or_default();
+    let uri = secret
+        .data
+        .unwrap_or_default()
+        .get("ADDON_URI")
+        .map(|value| String::from_utf8_lossy(&value.0).to_string());
+
+    assert_eq!(Some(format!("fake://{}", id)), uri);
+
+    let status = cluster::eventually("status to be written", || async {
+        addons
+            .get("matomo")
+            .await
+            .ok()
+            .and_then(|obj| obj.data.get("status").cloned())
+            .filter(|status| status["addon"].is_string())
+    })
+    .await;
+
+    assert_eq!(json!(id), status["addon"]);
+    assert_eq!(json!("e2e"), status["plan"]);
+
+    // -------------------------------------------------------------------------
+    // the addon is deleted from the api along the custom resource
+    addons
+        .delete("matomo", &DeleteParams::default())
+        .await
+        .expect("custom resource to be deleted");
+
+    cluster::eventually("addon to be deleted on the api", || async {
+        api.addons.list().is_empty().then_some(())
+    })
+    .await;
+
+    namespace.delete().await;
+}
@@ -0,0 +1,227 @@
+//! # Api module
+//!
+//! This module provide a fake Clever Cloud api, which creates, retrieves,
+//! lists and deletes addons held in memory, and returns their environment.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path_regex},
+    Mock, MockServer, Request, Respond, ResponseTemplate,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+pub const ADDONS_PATH: &str = r"^/v2/organisations/[^/]+/addons$";
+pub const ADDON_PATH: &str = r"^/v2/organisations/[^/]+/addons/[^/]+$";
+pub const ENVIRONMENT_PATH: &str = r"^/v2/organisations/[^/]+/addons/[^/]+/env$";
+
+// -----------------------------------------------------------------------------
+// Addons structure
+
+#[derive(Clone, Default, Debug)]
+pub struct Addons {
+    counter: Arc<AtomicUsize>,
+    inner: Arc<Mutex<BTreeMap<String, Value>>>,
+}
+
+impl Addons {
+    /// returns the addons held by the fake api
+    pub fn list(&self) -> Vec<Value> {
+        self.inner
+            .lock()
+            .expect("lock to not be poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    fn get(&self, id: &str) -> Option<Value> {
+        self.inner
+            .lock()
+            .expect("lock to not be poisoned")
+            .get(id)
+            .cloned()
+    }
+
+    fn insert(&self, opts: &Value) -> Value {
+        let id = format!("addon_{}", self.counter.fetch_add(1, Ordering::SeqCst));
+        let plan = opts["plan"].as_str().unwrap_or_default();
+        let addon = json!({
+            "id": id,
+            "name": opts["name"],
+            "realId": format!("real_{}", id),
+            "region": opts["region"],
+            "provider": {
+                "id": opts["providerId"],
+                "name": opts["providerId"],
+                "website": "",
+                "supportEmail": "",
+                "googlePlusName": "",
+                "twitterName": "",
+                "analyticsId": "",
+                "shortDesc": "",
+                "longDesc": "",
+                "logoUrl": "",
+                "status": "RELEASE",
+                "openInNewTab": false,
+                "canUpgrade": false,
+                "regions": [opts["region"]],
+            },
+            "plan": {
+                "id": plan,
+                "name": plan,
+                "slug": plan.trim_start_matches("plan_"),
+                "price": 0.0,
+                "features": [],
+                "zones": [opts["region"]],
+            },
+            "creationDate": 0,
+            "configKeys": ["ADDON_URI"],
+        });
+
+        self.inner
+            .lock()
+            .expect("lock to not be poisoned")
+            .insert(id, addon.to_owned());
+
+        addon
+    }
+
+    fn remove(&self, id: &str) -> Option<Value> {
+        self.inner
+            .lock()
+            .expect("lock to not be poisoned")
+            .remove(id)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Responders
+
+/// returns the identifier of the addon at the given position of the path
+/// segments, starting from the end
+fn segment(req: &Request, position: usize) -> String {
+    req.url
+        .path()
+        .rsplit('/')
+        .nth(position)
+        .unwrap_or_default()
+        .to_string()
+}
+
+struct Create(Addons);
+
+impl Respond for Create {
+    fn respond(&self, req: &Request) -> ResponseTemplate {
+        match serde_json::from_slice::<Value>(&req.body) {
+            Ok(opts) => ResponseTemplate::new(200).set_body_json(self.0.insert(&opts)),
+            Err(err) => ResponseTemplate::new(400).set_body_string(err.to_string()),
+        }
+    }
+}
+
+struct List(Addons);
+
+impl Respond for List {
+    fn respond(&self, _req: &Request) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(self.0.list())
+    }
+}
+
+struct Get(Addons);
+
+impl Respond for Get {
+    fn respond(&self, req: &Request) -> ResponseTemplate {
+        match self.0.get(&segment(req, 0)) {
+            Some(addon) => ResponseTemplate::new(200).set_body_json(addon),
+            None => ResponseTemplate::new(404),
+        }
+    }
+}
+
+struct Delete(Addons);
+
+impl Respond for Delete {
+    fn respond(&self, req: &Request) -> ResponseTemplate {
+        match self.0.remove(&segment(req, 0)) {
+            Some(_) => ResponseTemplate::new(200).set_body_json(json!({})),
+            None => ResponseTemplate::new(404),
+        }
+    }
+}
+
+struct Environment(Addons);
+
+impl Respond for Environment {
+    fn respond(&self, req: &Request) -> ResponseTemplate {
+        let id = segment(req, 1);
+        match self.0.get(&id) {
+            Some(_) => ResponseTemplate::new(200).set_body_json(json!([
+                { "name": "ADDON_URI", "value": format!("fake://{}", id) },
+            ])),
+            None => ResponseTemplate::new(404),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// FakeApi structure
+
+pub struct FakeApi {
+    pub server: MockServer,
+    pub addons: Addons,
+}
+
+impl FakeApi {
+    /// start the fake api on a random port of the loopback interface
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+        let addons = Addons::default();
+
+        Mock::given(method("POST"))
+            .and(path_regex(ADDONS_PATH))
+            .respond_with(Create(addons.to_owned()))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path_regex(ADDONS_PATH))
+            .respond_with(List(addons.to_owned()))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path_regex(ADDON_PATH))
+            .respond_with(Get(addons.to_owned()))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("DELETE"))
+            .and(path_regex(ADDON_PATH))
+            .respond_with(Delete(addons.to_owned()))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path_regex(ENVIRONMENT_PATH))
+            .respond_with(Environment(addons.to_owned()))
+            .mount(&server)
+            .await;
+
+        Self { server, addons }
+    }
+
+    /// returns the endpoint of the fake api
+    pub fn endpoint(&self) -> String {
+        self.server.uri()
+    }
+}
@@ -0,0 +1,154 @@
+//! # Cluster module
+//!
+//! This module provide helpers to create a namespace per test in the cluster
+//! of the current kubeconfig, to run the operator binary watching it and to
+//! wait for the cluster to converge.
+
+use std::{
+    future::Future,
+    io::Write,
+    net::TcpListener,
+    process::Stdio,
+    time::{Duration, Instant},
+};
+
+use k8s_openapi::api::core::v1::Namespace as KubeNamespace;
+use kube::{
+    api::{DeleteParams, ObjectMeta, PostParams},
+    Api, Client,
+};
+use tempfile::NamedTempFile;
+use tokio::process::{Child, Command};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// delay after which a condition which is not met fails the test
+pub const TIMEOUT: Duration = Duration::from_secs(120);
+
+/// delay between two checks of a condition
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+/// returns a kubernetes client from the current kubeconfig
+pub async fn client() -> Client {
+    Client::try_default()
+        .await
+        .expect("a kubernetes cluster to be reachable through the current kubeconfig")
+}
+
+/// returns the value of the given function, once it returns one, and panics
+/// with the given message if it does not before the timeout
+pub async fn eventually<F, Fut, T>(message: &str, f: F) -> T
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Option<T>>,
+{
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        if let Some(value) = f().await {
+            return value;
+        }
+
+        if Instant::now() > deadline {
+            panic!("{} within {:?}", message, TIMEOUT);
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Namespace structure
+
+pub struct Namespace {
+    pub name: String,
+    client: Client,
+}
+
+impl Namespace {
+    /// create a namespace with a unique name for the given test
+    pub async fn create(client: Client, test: &str) -> Self {
+        let name = format!("e2e-{}-{}", test, rand::random::<u16>());
+        let namespace = KubeNamespace {
+            metadata: ObjectMeta {
+                name: Some(name.to_owned()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        Api::<KubeNamespace>::all(client.to_owned())
+            .create(&PostParams::default(), &namespace)
+            .await
+            .expect("namespace to be created");
+
+        Self { name, client }
+    }
+
+    /// delete the namespace and the resources it holds
+    pub async fn delete(self) {
+        Api::<KubeNamespace>::all(self.client)
+            .delete(&self.name, &DeleteParams::default())
+            .await
+            .expect("namespace to be deleted");
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Operator structure
+
+pub struct Operator {
+    _child: Child,
+    _config: NamedTempFile,
+}
+
+impl Operator {
+    /// spawn the operator binary, which reaches the given clever cloud api
+    /// and only watches the given namespace. It is killed once dropped
+    pub fn spawn(endpoint: &str, namespace: &str) -> Self {
+        // the http server of the operator listens on a free port, so tests do
+        // not conflict with another operator running on the host
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("a free port on the loopback interface")
+            .port();
+
+        let mut config = NamedTempFile::new().expect("configuration file to be created");
+        write!(
+            config,
+            r#"
+[api]
+endpoint = "{endpoint}"
+token = "token"
+secret = "secret"
+consumerKey = "consumer-key"
+consumerSecret = "consumer-secret"
+
+[operator]
+listen = "127.0.0.1:{port}"
+
+[watch]
+namespaces = ["{namespace}"]
+"#
+        )
+        .expect("configuration to be written");
+
+        let child = Command::new(env!("CARGO_BIN_EXE_clever-operator"))
+            .arg("--config")
+            .arg(config.path())
+            .arg("-vvvv")
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("operator to be spawned");
+
+        Self {
+            _child: child,
+            _config: config,
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! # End-to-end tests
+//!
+//! This test harness runs the operator binary against the kubernetes cluster
+//! of the current kubeconfig (e.g. a kind cluster), with a fake Clever Cloud
+//! api serving addons from memory. Custom resource definitions have to be
+//! installed beforehand, see `make e2e`. Tests only run with the `it` feature.
+#![cfg(feature = "it")]
+
+mod addon;
+mod api;
+mod cluster;